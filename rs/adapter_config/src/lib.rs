@@ -0,0 +1,326 @@
+//! Loading of the configs of adapter processes.
+//!
+//! Adapters are configured with a JSON file that is generated when the node
+//! boots. To catch config drift and invalid deployments when the adapter
+//! starts, rather than when a misconfigured code path is first hit, all
+//! adapters load their config the same way:
+//!
+//! 1. the JSON file is read, if one is given, otherwise the config starts out
+//!    empty, i.e., with the defaults of all fields;
+//! 2. top-level fields are overridden by environment variables, e.g.
+//!    `IC_BTC_ADAPTER_MAX_CONNECTIONS=8` sets `max_connections` of the
+//!    bitcoin adapter. The value is parsed according to the type of the
+//!    field: as JSON if the field takes it, e.g. numbers, booleans or
+//!    objects, and as a plain string otherwise. A variable that matches no
+//!    field, or whose value the field does not take, is an error;
+//! 3. the result is deserialized and validated with
+//!    [`AdapterConfig::validate`].
+//!
+//! The defaults of a config, with all of its fields, can be printed with the
+//! [`AdapterSubcommand::DumpDefaults`] subcommand of the adapter.
+use clap::Clap;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use thiserror::Error;
+
+/// The config of an adapter process.
+///
+/// The `Default` of the config must hold the defaults of all fields, which
+/// must also be used when a field is missing from the JSON file.
+pub trait AdapterConfig: Default + Serialize + DeserializeOwned {
+    /// The prefix of the environment variables that override the fields of
+    /// the config, e.g. `IC_BTC_ADAPTER_`.
+    const ENV_PREFIX: &'static str;
+
+    /// The error returned when the config is invalid.
+    type ValidationError: std::error::Error;
+
+    /// Checks that the fields of the config are consistent.
+    fn validate(&self) -> Result<(), Self::ValidationError>;
+}
+
+/// The possible errors when loading an [`AdapterConfig`].
+#[derive(Debug, Error)]
+pub enum LoadConfigError<E> {
+    #[error("failed to read the config file {path:?}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("the config file {path:?} is not valid JSON: {reason}")]
+    InvalidJson { path: PathBuf, reason: String },
+    #[error("the config must be a JSON object")]
+    NotAnObject,
+    #[error("environment variable {var} overrides no config field, the fields are: {fields}")]
+    UnknownEnvOverride { var: String, fields: String },
+    #[error("environment variable {var} is not a valid value of its config field: {reason}")]
+    InvalidEnvOverride { var: String, reason: String },
+    #[error("{0}")]
+    Deserialize(String),
+    #[error("{0}")]
+    Invalid(E),
+}
+
+/// The subcommands that all adapters support next to their regular mode of
+/// operation.
+#[derive(Clap, Clone, Debug, PartialEq, Eq)]
+pub enum AdapterSubcommand {
+    /// Prints the default config, with all of its fields, as JSON and exits.
+    DumpDefaults,
+}
+
+/// Loads the config from the JSON file at `path`, or from the defaults if no
+/// path is given, and applies the overrides of the environment of the process.
+pub fn load_config<C: AdapterConfig>(
+    path: Option<&Path>,
+) -> Result<C, LoadConfigError<C::ValidationError>> {
+    load_config_with_env(path, std::env::vars())
+}
+
+/// Like [`load_config`], taking the environment variables from `env`.
+pub fn load_config_with_env<C: AdapterConfig>(
+    path: Option<&Path>,
+    env: impl IntoIterator<Item = (String, String)>,
+) -> Result<C, LoadConfigError<C::ValidationError>> {
+    let mut value = match path {
+        Some(path) => {
+            let contents = fs::read_to_string(path).map_err(|source| LoadConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            serde_json::from_str(&contents).map_err(|err| LoadConfigError::InvalidJson {
+                path: path.to_path_buf(),
+                reason: err.to_string(),
+            })?
+        }
+        None => Value::Object(Map::new()),
+    };
+    let fields = match &mut value {
+        Value::Object(fields) => fields,
+        _ => return Err(LoadConfigError::NotAnObject),
+    };
+    apply_env_overrides::<C, _>(fields, env)?;
+
+    let config: C = serde_json::from_value(value)
+        .map_err(|err| LoadConfigError::Deserialize(err.to_string()))?;
+    config.validate().map_err(LoadConfigError::Invalid)?;
+    Ok(config)
+}
+
+/// Returns the defaults of the config `C` as pretty-printed JSON.
+pub fn dump_defaults<C: AdapterConfig>() -> String {
+    serde_json::to_string_pretty(&C::default()).expect("Failed to serialize the default config")
+}
+
+fn apply_env_overrides<C: AdapterConfig, E>(
+    fields: &mut Map<String, Value>,
+    env: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), LoadConfigError<E>> {
+    let defaults = match serde_json::to_value(C::default()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => Map::new(),
+    };
+    let known_fields: BTreeSet<String> = defaults.keys().cloned().collect();
+    for (var, raw_value) in env {
+        let field = match var.strip_prefix(C::ENV_PREFIX) {
+            Some(field) => field.to_lowercase(),
+            None => continue,
+        };
+        if !known_fields.contains(&field) {
+            return Err(LoadConfigError::UnknownEnvOverride {
+                var,
+                fields: known_fields.into_iter().collect::<Vec<_>>().join(", "),
+            });
+        }
+        let value = parse_env_value::<C, _>(&defaults, &field, &var, raw_value)?;
+        fields.insert(field, value);
+    }
+    Ok(())
+}
+
+/// Parses the value of the environment variable `var` overriding `field` as
+/// the type of the field. The value is tried as JSON first and as a plain
+/// string second, and the first one that the field takes, with all other
+/// fields at their `defaults`, is returned.
+fn parse_env_value<C: AdapterConfig, E>(
+    defaults: &Map<String, Value>,
+    field: &str,
+    var: &str,
+    raw_value: String,
+) -> Result<Value, LoadConfigError<E>> {
+    let takes = |value: &Value| {
+        let mut fields = defaults.clone();
+        fields.insert(field.to_string(), value.clone());
+        serde_json::from_value::<C>(Value::Object(fields))
+    };
+    if let Ok(value) = serde_json::from_str::<Value>(&raw_value) {
+        if takes(&value).is_ok() {
+            return Ok(value);
+        }
+    }
+    let value = Value::String(raw_value);
+    match takes(&value) {
+        Ok(_) => Ok(value),
+        Err(err) => Err(LoadConfigError::InvalidEnvOverride {
+            var: var.to_string(),
+            reason: err.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::io::Write;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    #[error("min ({min}) must not exceed max ({max})")]
+    struct MinExceedsMax {
+        min: u64,
+        max: u64,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+    struct TestConfig {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        min: u64,
+        #[serde(default = "default_max")]
+        max: u64,
+    }
+
+    fn default_max() -> u64 {
+        10
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                name: String::new(),
+                min: 0,
+                max: default_max(),
+            }
+        }
+    }
+
+    impl AdapterConfig for TestConfig {
+        const ENV_PREFIX: &'static str = "TEST_ADAPTER_";
+        type ValidationError = MinExceedsMax;
+
+        fn validate(&self) -> Result<(), MinExceedsMax> {
+            if self.min > self.max {
+                return Err(MinExceedsMax {
+                    min: self.min,
+                    max: self.max,
+                });
+            }
+            Ok(())
+        }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(var, value)| (var.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn config_file(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn loads_defaults_without_file() {
+        let config: TestConfig = load_config_with_env(None, env(&[])).unwrap();
+        assert_eq!(config, TestConfig::default());
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let file = config_file(r#"{ "name": "file", "min": 2 }"#);
+        let config: TestConfig = load_config_with_env(
+            Some(file.path()),
+            env(&[
+                ("TEST_ADAPTER_NAME", "env"),
+                ("TEST_ADAPTER_MAX", "20"),
+                ("OTHER_MAX", "30"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            TestConfig {
+                name: "env".to_string(),
+                min: 2,
+                max: 20,
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_env_override_is_rejected() {
+        let err = load_config_with_env::<TestConfig>(None, env(&[("TEST_ADAPTER_MAXIMUM", "20")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "environment variable TEST_ADAPTER_MAXIMUM overrides no config field, \
+             the fields are: max, min, name"
+        );
+    }
+
+    #[test]
+    fn env_overrides_are_parsed_as_field_type() {
+        let config: TestConfig = load_config_with_env(
+            None,
+            env(&[("TEST_ADAPTER_NAME", "42"), ("TEST_ADAPTER_MIN", "3")]),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            TestConfig {
+                name: "42".to_string(),
+                min: 3,
+                max: 10,
+            }
+        );
+
+        let config: TestConfig =
+            load_config_with_env(None, env(&[("TEST_ADAPTER_NAME", "\"quoted\"")])).unwrap();
+        assert_eq!(config.name, "quoted");
+
+        let err = load_config_with_env::<TestConfig>(None, env(&[("TEST_ADAPTER_MAX", "ten")]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LoadConfigError::InvalidEnvOverride { var, .. } if var == "TEST_ADAPTER_MAX"
+        ));
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let file = config_file(r#"{ "min": 11 }"#);
+        let err = load_config_with_env::<TestConfig>(Some(file.path()), env(&[])).unwrap_err();
+        assert!(matches!(
+            err,
+            LoadConfigError::Invalid(MinExceedsMax { min: 11, max: 10 })
+        ));
+
+        let file = config_file(r#"{ "min": "one" }"#);
+        let err = load_config_with_env::<TestConfig>(Some(file.path()), env(&[])).unwrap_err();
+        assert!(matches!(err, LoadConfigError::Deserialize(_)));
+
+        let file = config_file("[]");
+        let err = load_config_with_env::<TestConfig>(Some(file.path()), env(&[])).unwrap_err();
+        assert!(matches!(err, LoadConfigError::NotAnObject));
+    }
+
+    #[test]
+    fn dumped_defaults_load_as_defaults() {
+        let file = config_file(&dump_defaults::<TestConfig>());
+        let config: TestConfig = load_config_with_env(Some(file.path()), env(&[])).unwrap();
+        assert_eq!(config, TestConfig::default());
+    }
+}
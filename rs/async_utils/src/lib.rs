@@ -4,7 +4,10 @@ mod observable_counting_semaphore;
 mod unix;
 
 pub use observable_counting_semaphore::*;
-pub use unix::{ensure_single_named_systemd_socket, incoming_from_first_systemd_socket};
+pub use unix::{
+    ensure_single_named_systemd_socket, incoming_from_first_systemd_socket, incoming_from_path,
+    SystemdSockets, SystemdSocketsError,
+};
 
 /// Returns a `Future` that completes when the service should gracefully
 /// shutdown. Completion happens if either of `SIGINT` or `SIGTERM` are
@@ -6,20 +6,35 @@
 use async_stream::AsyncStream;
 use futures::TryFutureExt;
 use std::{
-    os::unix::io::FromRawFd,
+    collections::BTreeMap,
+    os::unix::{
+        fs::FileTypeExt,
+        io::{FromRawFd, RawFd},
+    },
+    path::Path,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
+use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tonic::transport::server::Connected;
 
+// See https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
+const SD_LISTEN_FDS_START: RawFd = 3;
+// These env. variables are set by the systemd service manager and can be used to check what file
+// descriptors are passed.
+const SYSTEMD_SOCKET_PID: &str = "LISTEN_PID";
+const SYSTEMD_SOCKET_COUNT: &str = "LISTEN_FDS";
+const SYSTEMD_SOCKET_NAMES: &str = "LISTEN_FDNAMES";
+
 /// listener_from_first_systemd_socket() takes the first FD(3) passed by systemd. It does not check if
 /// more FDs are passed to the process. Make sure to call ensure_single_named_systemd_socket() before!
 /// To ensure that only one listener on the socket exists this function should only be called once!
 fn listener_from_first_systemd_socket() -> tokio::net::UnixListener {
-    const SD_LISTEN_FDS_START: i32 = 3; // see https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
-
     let std_unix_listener = unsafe {
         // SAFETY: Primitives returned by `FromRawFd::from_raw_fd` have the contract
         // that they are the sole owner of the file descriptor they are wrapping.
@@ -27,7 +42,12 @@ fn listener_from_first_systemd_socket() -> tokio::net::UnixListener {
         // the first file descriptor provided by systemd, we consider this call safe.
         std::os::unix::net::UnixListener::from_raw_fd(SD_LISTEN_FDS_START)
     };
+    into_tokio_listener(std_unix_listener)
+}
 
+fn into_tokio_listener(
+    std_unix_listener: std::os::unix::net::UnixListener,
+) -> tokio::net::UnixListener {
     // Set non-blocking mode as required by `tokio::net::UnixListener::from_std`.
     std_unix_listener
         .set_nonblocking(true)
@@ -40,11 +60,7 @@ fn listener_from_first_systemd_socket() -> tokio::net::UnixListener {
 /// ensure_single_named_systemd_socket() ensures that the correct file descriptor is passed by
 /// checking the name. Additionally it makes sure that only one FD is received.
 pub fn ensure_single_named_systemd_socket(socket_name: &str) {
-    // This env. variable is set by the systemd service manager and can be used to check what file
-    // descriptors are passed.
     // Setting the env. variable is done by ic-os/guestos/rootfs/etc/systemd/system/*.socket.
-    // For more info see https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
-    const SYSTEMD_SOCKET_NAMES: &str = "LISTEN_FDNAMES";
     let systemd_socket_names =
         std::env::var(SYSTEMD_SOCKET_NAMES).expect("failed to read systemd socket names");
     if systemd_socket_names != socket_name {
@@ -58,8 +74,30 @@ pub fn ensure_single_named_systemd_socket(socket_name: &str) {
 /// Creates an incoming async stream using the first systemd socket.
 pub fn incoming_from_first_systemd_socket(
 ) -> AsyncStream<Result<UnixStream, std::io::Error>, impl futures::Future<Output = ()>> {
-    let uds = listener_from_first_systemd_socket();
+    incoming_from_listener(listener_from_first_systemd_socket())
+}
+
+/// Binds a unix domain socket at `path` and creates an incoming async stream
+/// on it, for processes that are not started by systemd. A socket that a
+/// previous run left at `path` is removed first; any other file is kept and
+/// fails the binding.
+pub fn incoming_from_path<P: AsRef<Path>>(
+    path: P,
+) -> std::io::Result<
+    AsyncStream<Result<UnixStream, std::io::Error>, impl futures::Future<Output = ()>>,
+> {
+    let path = path.as_ref();
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    tokio::net::UnixListener::bind(path).map(incoming_from_listener)
+}
 
+fn incoming_from_listener(
+    uds: tokio::net::UnixListener,
+) -> AsyncStream<Result<UnixStream, std::io::Error>, impl futures::Future<Output = ()>> {
     async_stream::stream! {
         loop {
             let item = uds.accept().map_ok(|(st, _)| UnixStream(st)).await;
@@ -69,6 +107,156 @@ pub fn incoming_from_first_systemd_socket(
     }
 }
 
+/// The errors returned when looking up the sockets passed by systemd.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum SystemdSocketsError {
+    #[error("the systemd sockets of this process were already taken")]
+    AlreadyTaken,
+    #[error("failed to read the env. variable {name}: {reason}")]
+    InvalidEnv { name: &'static str, reason: String },
+    #[error("the systemd sockets were passed to process {listen_pid}, not to this process {pid}")]
+    PidMismatch { listen_pid: u32, pid: u32 },
+    #[error("systemd passed {count} sockets but {names_count} socket names")]
+    NameCountMismatch { count: usize, names_count: usize },
+    #[error("systemd passed several sockets named '{0}'")]
+    DuplicateName(String),
+    #[error("no systemd socket named '{name}', the remaining sockets are {available:?}")]
+    NotFound {
+        name: String,
+        available: Vec<String>,
+    },
+}
+
+static SYSTEMD_SOCKETS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The sockets passed to the process by systemd, looked up by the names set
+/// with `FileDescriptorName=` in their socket units.
+///
+/// Unlike `ensure_single_named_systemd_socket()`, this lets a process receive
+/// several sockets and hand each to a different service, e.g. the main gRPC
+/// service and a debug service:
+///
+/// ```no_run
+/// # use ic_async_utils::SystemdSockets;
+/// let mut sockets = SystemdSockets::take().unwrap();
+/// let main_incoming = sockets.take_incoming("ic-example.socket").unwrap();
+/// let debug_incoming = sockets.take_incoming("ic-example-debug.socket").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SystemdSockets {
+    fds: BTreeMap<String, RawFd>,
+}
+
+impl SystemdSockets {
+    /// Takes the sockets passed to the process by systemd. To ensure that only
+    /// one listener on each socket exists, this fails if they were already
+    /// taken. Must not be combined with `incoming_from_first_systemd_socket()`.
+    pub fn take() -> Result<Self, SystemdSocketsError> {
+        let env_var = |name: &'static str| {
+            std::env::var(name).map_err(|e| SystemdSocketsError::InvalidEnv {
+                name,
+                reason: e.to_string(),
+            })
+        };
+        let sockets = Self::from_env(
+            &env_var(SYSTEMD_SOCKET_PID)?,
+            std::process::id(),
+            &env_var(SYSTEMD_SOCKET_COUNT)?,
+            &env_var(SYSTEMD_SOCKET_NAMES)?,
+        )?;
+        if SYSTEMD_SOCKETS_TAKEN.swap(true, Ordering::SeqCst) {
+            return Err(SystemdSocketsError::AlreadyTaken);
+        }
+        Ok(sockets)
+    }
+
+    /// Maps the names in `LISTEN_FDNAMES` to the file descriptors passed in
+    /// order, starting at `SD_LISTEN_FDS_START`. Fails if `LISTEN_PID` is not
+    /// `pid`, i.e., if the sockets were passed to another process, e.g. to
+    /// the parent process that inherited its environment to this process.
+    fn from_env(
+        listen_pid: &str,
+        pid: u32,
+        count: &str,
+        names: &str,
+    ) -> Result<Self, SystemdSocketsError> {
+        let listen_pid =
+            listen_pid
+                .parse::<u32>()
+                .map_err(|e| SystemdSocketsError::InvalidEnv {
+                    name: SYSTEMD_SOCKET_PID,
+                    reason: e.to_string(),
+                })?;
+        if listen_pid != pid {
+            return Err(SystemdSocketsError::PidMismatch { listen_pid, pid });
+        }
+        let count = count
+            .parse::<usize>()
+            .map_err(|e| SystemdSocketsError::InvalidEnv {
+                name: SYSTEMD_SOCKET_COUNT,
+                reason: e.to_string(),
+            })?;
+        let names: Vec<&str> = if names.is_empty() {
+            vec![]
+        } else {
+            names.split(':').collect()
+        };
+        if names.len() != count {
+            return Err(SystemdSocketsError::NameCountMismatch {
+                count,
+                names_count: names.len(),
+            });
+        }
+        let mut fds = BTreeMap::new();
+        for (fd, name) in (SD_LISTEN_FDS_START..).zip(names) {
+            if fds.insert(name.to_string(), fd).is_some() {
+                return Err(SystemdSocketsError::DuplicateName(name.to_string()));
+            }
+        }
+        Ok(Self { fds })
+    }
+
+    /// Returns the names of the sockets that were not taken yet.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.fds.keys().map(String::as_str)
+    }
+
+    /// Takes the listener on the socket named `name`.
+    pub fn take_listener(
+        &mut self,
+        name: &str,
+    ) -> Result<tokio::net::UnixListener, SystemdSocketsError> {
+        let fd = match self.fds.remove(name) {
+            Some(fd) => fd,
+            None => {
+                return Err(SystemdSocketsError::NotFound {
+                    name: name.to_string(),
+                    available: self.names().map(str::to_string).collect(),
+                })
+            }
+        };
+        let std_unix_listener = unsafe {
+            // SAFETY: The sockets are taken at most once per process, and each file descriptor
+            // is removed from `fds` before it is wrapped. So the listener is the sole owner of
+            // the file descriptor.
+            std::os::unix::net::UnixListener::from_raw_fd(fd)
+        };
+        Ok(into_tokio_listener(std_unix_listener))
+    }
+
+    /// Takes the socket named `name` and creates an incoming async stream on
+    /// it, e.g. to serve a gRPC service with `serve_with_incoming`.
+    pub fn take_incoming(
+        &mut self,
+        name: &str,
+    ) -> Result<
+        AsyncStream<Result<UnixStream, std::io::Error>, impl futures::Future<Output = ()>>,
+        SystemdSocketsError,
+    > {
+        self.take_listener(name).map(incoming_from_listener)
+    }
+}
+
 #[derive(Debug)]
 pub struct UnixStream(pub tokio::net::UnixStream);
 
@@ -116,3 +304,96 @@ impl AsyncWrite for UnixStream {
         Pin::new(&mut self.0).poll_shutdown(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_assign_file_descriptors_in_order_of_names() {
+        let sockets = SystemdSockets::from_env("42", 42, "3", "main.socket:metrics:debug").unwrap();
+        assert_eq!(
+            sockets.fds,
+            vec![
+                ("debug".to_string(), 5),
+                ("main.socket".to_string(), 3),
+                ("metrics".to_string(), 4),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn should_reject_inconsistent_env() {
+        assert_eq!(
+            SystemdSockets::from_env("42", 42, "2", "main.socket").unwrap_err(),
+            SystemdSocketsError::NameCountMismatch {
+                count: 2,
+                names_count: 1
+            }
+        );
+        assert_eq!(
+            SystemdSockets::from_env("42", 42, "2", "main.socket:main.socket").unwrap_err(),
+            SystemdSocketsError::DuplicateName("main.socket".to_string())
+        );
+        assert!(matches!(
+            SystemdSockets::from_env("42", 42, "two", "a:b"),
+            Err(SystemdSocketsError::InvalidEnv { .. })
+        ));
+        assert_eq!(
+            SystemdSockets::from_env("42", 42, "0", "")
+                .unwrap()
+                .names()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn should_reject_sockets_of_other_process() {
+        assert_eq!(
+            SystemdSockets::from_env("41", 42, "1", "main.socket").unwrap_err(),
+            SystemdSocketsError::PidMismatch {
+                listen_pid: 41,
+                pid: 42
+            }
+        );
+        assert!(matches!(
+            SystemdSockets::from_env("", 42, "1", "main.socket"),
+            Err(SystemdSocketsError::InvalidEnv {
+                name: SYSTEMD_SOCKET_PID,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_report_remaining_sockets_if_not_found() {
+        let mut sockets = SystemdSockets::from_env("42", 42, "2", "main.socket:metrics").unwrap();
+        assert_eq!(
+            sockets.take_listener("debug").unwrap_err(),
+            SystemdSocketsError::NotFound {
+                name: "debug".to_string(),
+                available: vec!["main.socket".to_string(), "metrics".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_replace_stale_socket_but_keep_other_files() {
+        let dir = std::env::temp_dir().join(format!("ic-async-utils-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let socket_path = dir.join("stale.sock");
+        drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+        assert!(incoming_from_path(&socket_path).is_ok());
+
+        let file_path = dir.join("file");
+        std::fs::write(&file_path, b"not a socket").unwrap();
+        assert!(incoming_from_path(&file_path).is_err());
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"not a socket");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
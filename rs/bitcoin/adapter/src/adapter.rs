@@ -1,15 +1,28 @@
 use crate::{
+    bandwidth::BandwidthAccounting,
     blockchainmanager::{BlockchainManager, GetSuccessorsRequest, GetSuccessorsResponse},
     connectionmanager::ConnectionManager,
     stream::handle_stream,
     transaction_manager::TransactionManager,
     Config, ProcessEvent, ProcessEventError,
 };
+use ic_metrics::MetricsRegistry;
 use slog::Logger;
-use std::{net::SocketAddr, time::Instant};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
+/// The adapter only connects to the Bitcoin network while the replica demands blocks, i.e.,
+/// while it issues `get_successors` requests. A transaction to send also wakes up an idle
+/// adapter. On subnets where the Bitcoin feature is disabled, no such requests are issued
+/// and the adapter stays idle.
+#[derive(Debug)]
 enum AdapterState {
+    /// The adapter has no connections to the Bitcoin network.
     Idle,
+    /// The adapter is connected to the Bitcoin network. The field contains the time at which
+    /// the last `get_successors` request was received, or the adapter was woken up.
     ActiveSince(Instant),
 }
 
@@ -23,25 +36,34 @@ pub struct Adapter {
     connection_manager: ConnectionManager,
     /// This field is used to relay transactions from the replica state to the BTC network.
     transaction_manager: TransactionManager,
-    /// This field contains the timestamp when the last RPC call was received from the adapter.
+    /// This field accounts the bytes exchanged with the BTC network and determines whether the
+    /// daily egress cap is reached.
+    bandwidth: BandwidthAccounting,
+    /// This field contains the timestamp when the last `get_successors` request was received
+    /// from the replica.
     update_state: AdapterState,
     /// This field contains the how long the adapter should wait to enter the [AdapterState::Idle](AdapterState::Idle) state.
     idle_seconds: u64,
+    /// This field contains a logger for the adapter's use.
+    logger: Logger,
 }
 
 impl Adapter {
-    /// Constructs a new adapter.
-    pub fn new(config: &Config, logger: Logger) -> Self {
-        let connection_manager = ConnectionManager::new(config, logger.clone());
-        let blockchain_manager = BlockchainManager::new(config, logger.clone());
+    /// Constructs a new adapter whose metrics are registered in `metrics_registry`.
+    pub fn new(config: &Config, logger: Logger, metrics_registry: &MetricsRegistry) -> Self {
+        let bandwidth = BandwidthAccounting::new(config.max_daily_egress_bytes, metrics_registry);
+        let connection_manager = ConnectionManager::new(config, logger.clone(), bandwidth.clone());
+        let blockchain_manager = BlockchainManager::new(config, logger.clone(), metrics_registry);
         let transaction_manager = TransactionManager::new(logger.clone());
 
         Self {
             blockchain_manager,
             connection_manager,
             transaction_manager,
+            bandwidth,
             update_state: AdapterState::Idle,
             idle_seconds: config.idle_seconds,
+            logger,
         }
     }
 
@@ -52,7 +74,12 @@ impl Adapter {
     /// Function to called periodically for updating the adapter's state.
     pub fn tick(&mut self) {
         if let AdapterState::ActiveSince(last_received_at) = self.update_state {
-            if last_received_at.elapsed().as_secs() > self.idle_seconds {
+            if last_received_at.elapsed() > Duration::from_secs(self.idle_seconds) {
+                slog::info!(
+                    self.logger,
+                    "No get_successors request received for {} seconds, entering the idle state",
+                    self.idle_seconds
+                );
                 self.make_idle();
             }
         }
@@ -85,24 +112,54 @@ impl Adapter {
         // outgoing messages.
         self.connection_manager
             .tick(&self.blockchain_manager, handle_stream);
+        // Once the daily egress cap is reached, blocks are not requested anymore until the day
+        // is over, so that only headers are synced.
+        self.blockchain_manager
+            .set_header_only(self.bandwidth.egress_cap_reached());
         self.blockchain_manager.tick(&mut self.connection_manager);
         self.transaction_manager.tick(&mut self.connection_manager);
     }
 
     /// Gets successors from the configured  bitcoin network.
     pub fn get_successors(&mut self, request: GetSuccessorsRequest) -> GetSuccessorsResponse {
-        self.received_rpc_call();
+        self.received_get_successors_request();
         self.blockchain_manager.get_successors(request)
     }
 
     /// Sends transaction to the configured bitcoin network.
+    ///
+    /// An idle adapter is woken up so that the transaction is broadcast. Unlike a
+    /// `get_successors` request, sending a transaction does not keep an active adapter from
+    /// becoming idle.
     pub fn send_transaction(&mut self, raw_tx: Vec<u8>) {
-        self.received_rpc_call();
+        if let AdapterState::Idle = self.update_state {
+            slog::info!(
+                self.logger,
+                "Received a send_transaction request, leaving the idle state"
+            );
+            self.update_state = AdapterState::ActiveSince(Instant::now());
+        }
         self.transaction_manager.send_transaction(&raw_tx)
     }
 
+    /// Gets the cached transactions that pay to `address`, see
+    /// [BlockchainManager::get_transactions_by_address].
+    #[cfg(feature = "debug_rpc")]
+    pub fn get_transactions_by_address(
+        &self,
+        address: &bitcoin::Address,
+    ) -> Option<Vec<(bitcoin::BlockHash, &bitcoin::Transaction)>> {
+        self.blockchain_manager.get_transactions_by_address(address)
+    }
+
     /// Set the state to `Active` with the current timestamp.
-    fn received_rpc_call(&mut self) {
+    fn received_get_successors_request(&mut self) {
+        if let AdapterState::Idle = self.update_state {
+            slog::info!(
+                self.logger,
+                "Received a get_successors request, leaving the idle state"
+            );
+        }
         self.update_state = AdapterState::ActiveSince(Instant::now());
     }
 
@@ -118,3 +175,55 @@ impl Adapter {
         self.transaction_manager.make_idle();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::test_common::make_logger, config::test::ConfigBuilder};
+    use bitcoin::{BlockHash, Network};
+
+    fn get_successors_request() -> GetSuccessorsRequest {
+        GetSuccessorsRequest {
+            anchor: BlockHash::default(),
+            processed_block_hashes: vec![],
+        }
+    }
+
+    /// Tests that the adapter is only active while it receives `get_successors` requests.
+    #[test]
+    fn test_idle_state_follows_get_successors_requests() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut adapter = Adapter::new(&config, make_logger(), &MetricsRegistry::new());
+        assert!(matches!(adapter.update_state, AdapterState::Idle));
+
+        adapter.get_successors(get_successors_request());
+        assert!(matches!(adapter.update_state, AdapterState::ActiveSince(_)));
+
+        adapter.update_state = AdapterState::ActiveSince(
+            Instant::now() - Duration::from_secs(config.idle_seconds + 1),
+        );
+        adapter.tick();
+        assert!(matches!(adapter.update_state, AdapterState::Idle));
+    }
+
+    /// Tests that sending a transaction wakes up an idle adapter, but does not keep an active
+    /// adapter from becoming idle.
+    #[test]
+    fn test_send_transaction_wakes_up_idle_adapter() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut adapter = Adapter::new(&config, make_logger());
+
+        adapter.send_transaction(vec![]);
+        assert!(matches!(adapter.update_state, AdapterState::ActiveSince(_)));
+
+        let last_received_at = Instant::now() - Duration::from_secs(config.idle_seconds + 1);
+        adapter.update_state = AdapterState::ActiveSince(last_received_at);
+        adapter.send_transaction(vec![]);
+        assert!(matches!(
+            adapter.update_state,
+            AdapterState::ActiveSince(at) if at == last_received_at
+        ));
+        adapter.tick();
+        assert!(matches!(adapter.update_state, AdapterState::Idle));
+    }
+}
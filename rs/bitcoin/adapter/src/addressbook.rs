@@ -9,8 +9,8 @@ use rand::{
 };
 use slog::Logger;
 use std::{
-    collections::{HashSet, VecDeque},
-    net::{SocketAddr, ToSocketAddrs},
+    collections::{HashMap, HashSet, VecDeque},
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
 };
 use thiserror::Error;
 
@@ -31,6 +31,10 @@ pub enum AddressBookError {
     /// to address the possibility.
     #[error("Could not find an available address.")]
     AddressesDepleted,
+    /// This variant is used when all available addresses belong to network groups
+    /// that already have the maximum number of active addresses.
+    #[error("Could not find an available address in a network group with capacity.")]
+    NetworkGroupsExhausted,
     /// This variant is used when there are no available seed addresses to be
     /// found in the seed queue. This occurs due to improper configuration.
     #[error("Could not find any available seed address.")]
@@ -61,6 +65,41 @@ pub enum AddressEntry {
     Discovered(SocketAddr),
 }
 
+/// This enum represents the network group of an address. Addresses in the same network
+/// group are likely to be operated by the same entity, so connections are spread over
+/// multiple network groups.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum NetworkGroup {
+    /// The /16 subnet of an IPv4 address.
+    V4([u8; 2]),
+    /// The /32 subnet of an IPv6 address.
+    V6([u16; 2]),
+}
+
+impl NetworkGroup {
+    /// This function returns the network group of the given address. IPv4-mapped IPv6
+    /// addresses belong to the group of the IPv4 address.
+    pub fn of(addr: &SocketAddr) -> Self {
+        let ip = match addr.ip() {
+            IpAddr::V6(ip) => match ip.to_ipv4() {
+                Some(ipv4) if ip.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] => IpAddr::V4(ipv4),
+                _ => IpAddr::V6(ip),
+            },
+            ip => ip,
+        };
+        match ip {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                NetworkGroup::V4([octets[0], octets[1]])
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                NetworkGroup::V6([segments[0], segments[1]])
+            }
+        }
+    }
+}
+
 impl AddressEntry {
     /// This function is used to access the stored address in the enum.
     pub fn addr(&self) -> &SocketAddr {
@@ -92,6 +131,8 @@ pub struct AddressBook {
     min_addresses: usize,
     /// The maximum number of addresses that can be stored in the address book.
     max_addresses: usize,
+    /// The maximum number of active addresses per network group, if limited.
+    max_active_per_network_group: Option<usize>,
     /// This field contains the addresses that will be used for the address discovery
     /// on initial startup and when the adapter is running low on addresses.
     seed_queue: VecDeque<SocketAddr>,
@@ -114,6 +155,7 @@ impl AddressBook {
             logger,
             min_addresses,
             max_addresses,
+            max_active_per_network_group: config.max_connections_per_network_group,
             seed_queue: VecDeque::new(),
         }
     }
@@ -243,17 +285,47 @@ impl AddressBook {
     /// This function grabs an address randomly from the available addresses pool.
     /// If the available addresses is empty, then an
     /// [AddressBookError::AddressesDepleted](AddressBookError::AddressesDepleted)
+    /// error is returned. If the number of active addresses per network group is limited,
+    /// only addresses of network groups below the limit are considered. If there are none, an
+    /// [AddressBookError::NetworkGroupsExhausted](AddressBookError::NetworkGroupsExhausted)
     /// error is returned.
     pub fn pop(&mut self) -> AddressBookResult<AddressEntry> {
+        if self.known_addresses.is_empty() {
+            return Err(AddressBookError::AddressesDepleted);
+        }
+
+        let mut active_per_network_group: HashMap<NetworkGroup, usize> = HashMap::new();
+        for addr in self.active_addresses.iter() {
+            *active_per_network_group
+                .entry(NetworkGroup::of(addr))
+                .or_insert(0) += 1;
+        }
+        let max_active_per_network_group = self.max_active_per_network_group;
+        let has_capacity = |addr: &SocketAddr| match max_active_per_network_group {
+            Some(max) => {
+                active_per_network_group
+                    .get(&NetworkGroup::of(addr))
+                    .copied()
+                    .unwrap_or(0)
+                    < max
+            }
+            None => true,
+        };
+
         let mut rng = StdRng::from_entropy();
-        let maybe_address = self.known_addresses.iter().choose(&mut rng).cloned();
+        let maybe_address = self
+            .known_addresses
+            .iter()
+            .filter(|addr| has_capacity(addr))
+            .choose(&mut rng)
+            .cloned();
         if let Some(addr) = maybe_address {
             self.mark_as_active(&addr);
         }
 
         maybe_address
             .map(AddressEntry::Discovered)
-            .ok_or(AddressBookError::AddressesDepleted)
+            .ok_or(AddressBookError::NetworkGroupsExhausted)
     }
 
     /// This function retrieves the next seed address from the seed queue.
@@ -346,6 +418,49 @@ mod test {
 
     use super::*;
 
+    /// This function tests that the address book does not return more active addresses per
+    /// network group than configured.
+    #[test]
+    fn test_pop_with_network_group_limit() {
+        let config = ConfigBuilder::new()
+            .with_nodes(vec![
+                SocketAddr::from_str("10.0.1.1:8333").expect("invalid address"),
+                SocketAddr::from_str("10.0.2.2:8333").expect("invalid address"),
+                SocketAddr::from_str("[2001:db8:1::1]:8333").expect("invalid address"),
+            ])
+            .with_max_connections_per_network_group(1)
+            .build();
+        let mut book = AddressBook::new(&config, make_logger());
+
+        let first = book.pop().expect("there should be an address");
+        let second = book.pop().expect("there should be an address");
+        assert_ne!(
+            NetworkGroup::of(first.addr()),
+            NetworkGroup::of(second.addr())
+        );
+        assert!(matches!(
+            book.pop(),
+            Err(AddressBookError::NetworkGroupsExhausted)
+        ));
+
+        // Once an address is no longer active, its network group has capacity again.
+        book.remove_from_active(&first);
+        assert!(book.pop().is_ok());
+    }
+
+    #[test]
+    fn test_network_group() {
+        let group = |addr: &str| NetworkGroup::of(&SocketAddr::from_str(addr).unwrap());
+        assert_eq!(group("1.2.3.4:8333"), group("1.2.200.1:8333"));
+        assert_ne!(group("1.2.3.4:8333"), group("1.3.3.4:8333"));
+        assert_eq!(group("[::ffff:1.2.3.4]:8333"), group("1.2.9.9:8333"));
+        assert_eq!(
+            group("[2001:db8::1]:8333"),
+            group("[2001:db8:ffff::1]:8333")
+        );
+        assert_ne!(group("[2001:db8::1]:8333"), group("[2001:db9::1]:8333"));
+    }
+
     /// This function tests the address manager basic interactions `mark_as_active`
     /// and `remove_from_active`.
     #[test]
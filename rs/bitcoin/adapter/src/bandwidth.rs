@@ -0,0 +1,266 @@
+use ic_metrics::MetricsRegistry;
+use prometheus::{IntCounterVec, IntGauge};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The length of the window the daily egress cap applies to.
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The label value of the bytes received from Bitcoin nodes.
+const RECEIVED: &str = "received";
+/// The label value of the bytes sent to Bitcoin nodes.
+const SENT: &str = "sent";
+/// The label value of the bytes exchanged with Bitcoin nodes reached over IPv4.
+const IPV4: &str = "ipv4";
+/// The label value of the bytes exchanged with Bitcoin nodes reached over IPv6.
+const IPV6: &str = "ipv6";
+
+/// Returns the label value of the address family of `address`. The peers are aggregated by
+/// address family, as the addresses of the peers change too often to be used as label values.
+fn address_family(address: &SocketAddr) -> &'static str {
+    match address {
+        SocketAddr::V4(_) => IPV4,
+        SocketAddr::V6(_) => IPV6,
+    }
+}
+
+/// This struct contains the number of bytes exchanged with a single Bitcoin node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerBandwidth {
+    /// The number of bytes received from the node.
+    pub received_bytes: u64,
+    /// The number of bytes sent to the node.
+    pub sent_bytes: u64,
+}
+
+#[derive(Clone)]
+struct BandwidthMetrics {
+    bytes: IntCounterVec,
+    daily_egress_bytes: IntGauge,
+    header_only_sync: IntGauge,
+}
+
+impl BandwidthMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            bytes: metrics_registry.int_counter_vec(
+                "btc_adapter_network_bytes_total",
+                "Total number of bytes exchanged with Bitcoin nodes, by direction and address family of the nodes.",
+                &["direction", "address_family"],
+            ),
+            daily_egress_bytes: metrics_registry.int_gauge(
+                "btc_adapter_daily_egress_bytes",
+                "Number of bytes sent to Bitcoin nodes in the current day.",
+            ),
+            header_only_sync: metrics_registry.int_gauge(
+                "btc_adapter_header_only_sync",
+                "Whether the adapter only syncs headers because the daily egress cap is reached (1) or not (0).",
+            ),
+        }
+    }
+}
+
+struct BandwidthState {
+    /// The time at which the current day started.
+    day_started_at: Instant,
+    /// The number of bytes sent in the current day.
+    daily_egress_bytes: u64,
+    /// The number of bytes exchanged with the connected Bitcoin nodes.
+    peers: HashMap<SocketAddr, PeerBandwidth>,
+}
+
+impl BandwidthState {
+    /// Starts a new day if the current one is over.
+    fn roll_over(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.day_started_at) >= DAY {
+            self.day_started_at = now;
+            self.daily_egress_bytes = 0;
+        }
+    }
+}
+
+/// This struct accounts the bytes the streams exchange with Bitcoin nodes, per direction and
+/// per node, and enforces the optional daily egress cap. Once the bytes sent in the current day
+/// reach the cap, the adapter degrades to syncing headers only until the day is over.
+///
+/// Clones share their counts, so every stream records into the same accounting.
+#[derive(Clone)]
+pub struct BandwidthAccounting {
+    max_daily_egress_bytes: Option<u64>,
+    state: Arc<Mutex<BandwidthState>>,
+    metrics: BandwidthMetrics,
+}
+
+impl fmt::Debug for BandwidthAccounting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BandwidthAccounting")
+            .field("max_daily_egress_bytes", &self.max_daily_egress_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BandwidthAccounting {
+    /// Creates a new accounting whose days start now.
+    pub fn new(max_daily_egress_bytes: Option<u64>, metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            max_daily_egress_bytes,
+            state: Arc::new(Mutex::new(BandwidthState {
+                day_started_at: Instant::now(),
+                daily_egress_bytes: 0,
+                peers: HashMap::new(),
+            })),
+            metrics: BandwidthMetrics::new(metrics_registry),
+        }
+    }
+
+    /// Records that `count` bytes were received from the node at `address`.
+    pub fn record_received(&self, address: SocketAddr, count: usize) {
+        let mut state = self.state.lock().unwrap();
+        let peer = state.peers.entry(address).or_default();
+        peer.received_bytes = peer.received_bytes.saturating_add(count as u64);
+        self.metrics
+            .bytes
+            .with_label_values(&[RECEIVED, address_family(&address)])
+            .inc_by(count as u64);
+    }
+
+    /// Records that `count` bytes were sent to the node at `address`.
+    pub fn record_sent(&self, address: SocketAddr, count: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.roll_over(Instant::now());
+        let peer = state.peers.entry(address).or_default();
+        peer.sent_bytes = peer.sent_bytes.saturating_add(count as u64);
+        state.daily_egress_bytes = state.daily_egress_bytes.saturating_add(count as u64);
+        self.metrics
+            .bytes
+            .with_label_values(&[SENT, address_family(&address)])
+            .inc_by(count as u64);
+        self.metrics
+            .daily_egress_bytes
+            .set(state.daily_egress_bytes as i64);
+    }
+
+    /// Stops accounting the bytes of the node at `address`, e.g., because its stream was
+    /// disconnected, and returns the number of bytes exchanged with it.
+    pub fn remove_peer(&self, address: &SocketAddr) -> Option<PeerBandwidth> {
+        self.state.lock().unwrap().peers.remove(address)
+    }
+
+    /// Returns whether the bytes sent in the current day reached the daily egress cap.
+    pub fn egress_cap_reached(&self) -> bool {
+        self.egress_cap_reached_at(Instant::now())
+    }
+
+    fn egress_cap_reached_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.roll_over(now);
+        let reached = match self.max_daily_egress_bytes {
+            Some(max) => state.daily_egress_bytes >= max,
+            None => false,
+        };
+        self.metrics
+            .daily_egress_bytes
+            .set(state.daily_egress_bytes as i64);
+        self.metrics.header_only_sync.set(reached as i64);
+        reached
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn address(s: &str) -> SocketAddr {
+        SocketAddr::from_str(s).expect("invalid address")
+    }
+
+    fn peer(bandwidth: &BandwidthAccounting, address: &SocketAddr) -> Option<PeerBandwidth> {
+        bandwidth.state.lock().unwrap().peers.get(address).copied()
+    }
+
+    fn bytes_metric(bandwidth: &BandwidthAccounting, direction: &str, family: &str) -> u64 {
+        bandwidth
+            .metrics
+            .bytes
+            .with_label_values(&[direction, family])
+            .get()
+    }
+
+    /// Tests that the bytes are accounted per direction and per node.
+    #[test]
+    fn test_accounts_bytes_per_peer() {
+        let bandwidth = BandwidthAccounting::new(None, &MetricsRegistry::new());
+        let peer_1 = address("127.0.0.1:8333");
+        let peer_2 = address("192.168.1.1:8333");
+        let peer_3 = address("[::1]:8333");
+
+        bandwidth.record_received(peer_1, 100);
+        bandwidth.record_sent(peer_1, 10);
+        bandwidth.clone().record_sent(peer_1, 5);
+        bandwidth.record_received(peer_2, 7);
+        bandwidth.record_received(peer_3, 3);
+        bandwidth.record_sent(peer_3, 1);
+
+        assert_eq!(
+            peer(&bandwidth, &peer_1),
+            Some(PeerBandwidth {
+                received_bytes: 100,
+                sent_bytes: 15,
+            })
+        );
+        assert_eq!(
+            bandwidth.remove_peer(&peer_2),
+            Some(PeerBandwidth {
+                received_bytes: 7,
+                sent_bytes: 0,
+            })
+        );
+        assert_eq!(peer(&bandwidth, &peer_2), None);
+        // The metrics aggregate the nodes by address family.
+        assert_eq!(bytes_metric(&bandwidth, RECEIVED, IPV4), 107);
+        assert_eq!(bytes_metric(&bandwidth, SENT, IPV4), 15);
+        assert_eq!(bytes_metric(&bandwidth, RECEIVED, IPV6), 3);
+        assert_eq!(bytes_metric(&bandwidth, SENT, IPV6), 1);
+    }
+
+    /// Tests that the cap is reached once the bytes sent in a day reach it, and that it is
+    /// lifted once the day is over.
+    #[test]
+    fn test_egress_cap_resets_daily() {
+        let bandwidth = BandwidthAccounting::new(Some(100), &MetricsRegistry::new());
+        let peer = address("127.0.0.1:8333");
+        let now = Instant::now();
+
+        bandwidth.record_received(peer, 1_000);
+        assert!(!bandwidth.egress_cap_reached_at(now));
+
+        bandwidth.record_sent(peer, 60);
+        assert!(!bandwidth.egress_cap_reached_at(now));
+        bandwidth.record_sent(peer, 40);
+        assert!(bandwidth.egress_cap_reached_at(now));
+        assert_eq!(bandwidth.metrics.header_only_sync.get(), 1);
+
+        assert!(!bandwidth.egress_cap_reached_at(now + DAY));
+        assert_eq!(bandwidth.metrics.daily_egress_bytes.get(), 0);
+        assert_eq!(bandwidth.metrics.header_only_sync.get(), 0);
+        // The bytes exchanged with the node are still accounted.
+        assert_eq!(
+            peer(&bandwidth, &peer).map(|peer| peer.sent_bytes),
+            Some(100)
+        );
+    }
+
+    /// Tests that the egress is never capped if no cap is configured.
+    #[test]
+    fn test_no_egress_cap() {
+        let bandwidth = BandwidthAccounting::new(None, &MetricsRegistry::new());
+        bandwidth.record_sent(address("127.0.0.1:8333"), usize::MAX);
+        assert!(!bandwidth.egress_cap_reached());
+    }
+}
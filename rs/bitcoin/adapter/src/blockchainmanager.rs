@@ -1,7 +1,12 @@
 use crate::{
     blockchainstate::{AddHeaderError, BlockchainState},
     common::{BlockHeight, MINIMUM_VERSION_NUMBER},
-    config::Config,
+    compact_block::{
+        BlockTransactions, BlockTransactionsRequest, CompactBlockMessage, CompactBlockMetrics,
+        HeaderAndShortIds, PartialBlock, SendCompact, COMPACT_BLOCKS_VERSION, FAILED,
+        RECONSTRUCTED, RECONSTRUCTED_AFTER_GETBLOCKTXN,
+    },
+    config::{Config, EvictionPolicy},
     stream::{StreamEvent, StreamEventKind},
     Channel, Command, HasHeight, ProcessEventError,
 };
@@ -12,6 +17,7 @@ use bitcoin::{
     },
     Block, BlockHash, BlockHeader, Network,
 };
+use ic_metrics::MetricsRegistry;
 use slog::Logger;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
@@ -94,6 +100,18 @@ enum ReceivedInvMessageError {
     TooMuchInventory,
 }
 
+/// The possible errors the `BlockchainManager::received_compact_block_message(...)` and
+/// `BlockchainManager::received_block_transactions_message(...)` may produce.
+#[derive(Debug, Error)]
+enum ReceivedCompactBlockMessageError {
+    /// This variant represents when a message from a no longer known peer.
+    #[error("Unknown peer")]
+    UnknownPeer,
+    /// The header of the compact block is invalid.
+    #[error("Received an invalid header")]
+    ReceivedInvalidHeader,
+}
+
 /// The possible errors the `BlockchainManager::received_block_message(...)` may produce.
 #[derive(Debug, Error)]
 pub enum ReceivedBlockMessageError {
@@ -139,6 +157,8 @@ pub struct PeerInfo {
     sent_at: Option<SystemTime>,
     /// What to do if this request times out.
     on_timeout: OnTimeout,
+    /// The number of consecutive `getdata` requests to the peer that timed out.
+    timed_out_requests: u32,
 }
 
 /// This struct stores the information related to a "getdata" request sent by the BlockChainManager
@@ -152,6 +172,16 @@ pub struct GetDataRequestInfo {
     _on_timeout: OnTimeout,
 }
 
+/// This struct stores the information related to a compact block whose missing transactions
+/// were requested with a "getblocktxn" request.
+#[derive(Debug)]
+struct CompactBlockRequestInfo {
+    /// This field stores the socket address of the Bitcoin node that sent the compact block.
+    socket: SocketAddr,
+    /// This field contains the block that is being reconstructed.
+    partial_block: PartialBlock,
+}
+
 /// The BlockChainManager struct handles interactions that involve the headers.
 #[derive(Debug)]
 pub struct BlockchainManager {
@@ -185,13 +215,32 @@ pub struct BlockchainManager {
     logger: Logger,
     /// Contains the network type the adapter is connecting to.
     network: Network,
+
+    /// This field determines whether or not peers are asked to announce new blocks with
+    /// compact blocks.
+    compact_blocks: bool,
+    /// This HashMap stores the compact blocks that wait for a "blocktxn" response. Every entry
+    /// has a corresponding entry in `getdata_request_info`, which tracks when the request
+    /// expires.
+    compact_block_requests: HashMap<BlockHash, CompactBlockRequestInfo>,
+    /// This field counts the outcomes of the compact block reconstructions.
+    compact_block_metrics: CompactBlockMetrics,
+
+    /// This field determines what to do with peers whose `getdata` requests time out.
+    eviction_policy: EvictionPolicy,
+
+    /// This field determines whether or not the manager only syncs headers, e.g., because the
+    /// daily egress cap is reached. No `getdata` requests are sent for the queued blocks while
+    /// it is set.
+    header_only: bool,
 }
 
 impl BlockchainManager {
     /// This function instantiates a BlockChainManager struct. A node is provided
     /// in order to get its client so the manager can send messages to the
-    /// BTC network.
-    pub fn new(config: &Config, logger: Logger) -> Self {
+    /// BTC network. The metrics of the manager are registered in
+    /// `metrics_registry`.
+    pub fn new(config: &Config, logger: Logger, metrics_registry: &MetricsRegistry) -> Self {
         let blockchain = BlockchainState::new(config);
         let peer_info = HashMap::new();
         let getdata_request_info = HashMap::new();
@@ -204,7 +253,45 @@ impl BlockchainManager {
             outgoing_command_queue,
             logger,
             network: config.network,
+            compact_blocks: config.compact_blocks,
+            compact_block_requests: HashMap::new(),
+            compact_block_metrics: CompactBlockMetrics::new(metrics_registry),
+            eviction_policy: config.eviction_policy,
+            header_only: false,
+        }
+    }
+
+    /// This method is used to make the manager only sync headers, or to sync blocks again.
+    pub fn set_header_only(&mut self, header_only: bool) {
+        if self.header_only != header_only {
+            if header_only {
+                slog::warn!(
+                    self.logger,
+                    "The daily egress cap is reached, only syncing headers"
+                );
+            } else {
+                slog::info!(self.logger, "Syncing blocks again");
+            }
+        }
+        self.header_only = header_only;
+    }
+
+    /// Returns the transactions of the cached blocks that pay to `address`, along
+    /// with the hashes of their blocks, in the order of the heights of the
+    /// blocks. Returns `None` if `address` is not an address of the network of
+    /// the adapter.
+    #[cfg(feature = "debug_rpc")]
+    pub fn get_transactions_by_address(
+        &self,
+        address: &bitcoin::Address,
+    ) -> Option<Vec<(BlockHash, &bitcoin::Transaction)>> {
+        if address.network != self.network {
+            return None;
         }
+        Some(
+            self.blockchain
+                .get_transactions_paying_to(&address.script_pubkey()),
+        )
     }
 
     /// This method is used when the adapter is no longer receiving RPC calls from the replica.
@@ -214,6 +301,7 @@ impl BlockchainManager {
         self.outgoing_command_queue.clear();
         self.block_sync_queue.clear();
         self.getdata_request_info.clear();
+        self.compact_block_requests.clear();
         self.peer_info.clear();
         self.blockchain.clear_blocks();
     }
@@ -431,6 +519,10 @@ impl BlockchainManager {
 
         //Remove the corresponding `getdata` request from peer_info and getdata_request_info.
         self.getdata_request_info.remove(&block_hash);
+        self.compact_block_requests.remove(&block_hash);
+        if let Some(peer) = self.peer_info.get_mut(addr) {
+            peer.timed_out_requests = 0;
+        }
 
         match self.blockchain.add_block(block.clone()) {
             Ok(block_height) => {
@@ -452,6 +544,229 @@ impl BlockchainManager {
         }
     }
 
+    /// This function processes "cmpctblock" messages received from Bitcoin nodes.
+    /// The block is reconstructed from the prefilled transactions if possible. Otherwise,
+    /// the missing transactions are requested with a "getblocktxn" message.
+    fn received_compact_block_message(
+        &mut self,
+        addr: &SocketAddr,
+        compact_block: HeaderAndShortIds,
+    ) -> Result<(), ReceivedCompactBlockMessageError> {
+        if !self.peer_info.contains_key(addr) {
+            return Err(ReceivedCompactBlockMessageError::UnknownPeer);
+        }
+
+        let block_hash = compact_block.header.block_hash();
+        slog::info!(
+            self.logger,
+            "Received cmpctblock message from {} : Block {:?}",
+            addr,
+            block_hash
+        );
+        self.compact_block_metrics.received.inc();
+
+        // The block is either known or already requested by a `getdata` or `getblocktxn` request.
+        if self.blockchain.get_block(&block_hash).is_some()
+            || self.getdata_request_info.contains_key(&block_hash)
+        {
+            return Ok(());
+        }
+
+        match self.blockchain.add_headers(&[compact_block.header]).1 {
+            Some(AddHeaderError::InvalidHeader(_, _)) => {
+                return Err(ReceivedCompactBlockMessageError::ReceivedInvalidHeader)
+            }
+            // The header does not connect to the known headers yet. The block is synced
+            // through `getheaders` and `getdata` requests once the headers are caught up.
+            Some(AddHeaderError::PrevHeaderNotCached(_)) => return Ok(()),
+            None => {}
+        }
+
+        if let Some(cached) = self.blockchain.get_cached_header(&block_hash) {
+            let height = cached.height;
+            if let Some(peer) = self.peer_info.get_mut(addr) {
+                if height > peer.height {
+                    peer.tip = block_hash;
+                    peer.height = height;
+                }
+            }
+        }
+
+        if self.blockchain.get_block_cache_size() >= BLOCK_CACHE_THRESHOLD_BYTES {
+            return Ok(());
+        }
+
+        let partial_block = match PartialBlock::new(compact_block) {
+            Ok(partial_block) => partial_block,
+            Err(err) => {
+                slog::warn!(
+                    self.logger,
+                    "Unable to reconstruct block {} from {}. Error: {}",
+                    block_hash,
+                    addr,
+                    err
+                );
+                self.compact_block_reconstruction_failed(addr, block_hash);
+                return Ok(());
+            }
+        };
+
+        let missing_indexes = partial_block.missing_indexes();
+        if missing_indexes.is_empty() {
+            match partial_block.reconstruct(vec![]) {
+                Ok(block) => {
+                    self.compact_block_metrics
+                        .observe_reconstructions(RECONSTRUCTED, 1);
+                    self.add_reconstructed_block(block);
+                }
+                Err(err) => {
+                    slog::warn!(
+                        self.logger,
+                        "Unable to reconstruct block {} from {}. Error: {}",
+                        block_hash,
+                        addr,
+                        err
+                    );
+                    self.compact_block_reconstruction_failed(addr, block_hash);
+                }
+            }
+            return Ok(());
+        }
+
+        slog::info!(
+            self.logger,
+            "Sending getblocktxn to {} : Block {:?}, Transactions {}",
+            addr,
+            block_hash,
+            missing_indexes.len()
+        );
+        self.outgoing_command_queue.push(Command {
+            address: Some(*addr),
+            message: CompactBlockMessage::GetBlockTransactions(BlockTransactionsRequest {
+                block_hash,
+                indexes: missing_indexes,
+            })
+            .into_network_message(),
+        });
+        self.getdata_request_info.insert(
+            block_hash,
+            GetDataRequestInfo {
+                socket: *addr,
+                sent_at: SystemTime::now(),
+                _on_timeout: OnTimeout::Ignore,
+            },
+        );
+        self.compact_block_requests.insert(
+            block_hash,
+            CompactBlockRequestInfo {
+                socket: *addr,
+                partial_block,
+            },
+        );
+        Ok(())
+    }
+
+    /// This function processes "blocktxn" messages received from Bitcoin nodes
+    /// and completes the reconstruction of the corresponding compact block.
+    fn received_block_transactions_message(
+        &mut self,
+        addr: &SocketAddr,
+        block_transactions: BlockTransactions,
+    ) -> Result<(), ReceivedCompactBlockMessageError> {
+        if !self.peer_info.contains_key(addr) {
+            return Err(ReceivedCompactBlockMessageError::UnknownPeer);
+        }
+
+        let BlockTransactions {
+            block_hash,
+            transactions,
+        } = block_transactions;
+        slog::info!(
+            self.logger,
+            "Received blocktxn message from {} : Block {:?}, Transactions {}",
+            addr,
+            block_hash,
+            transactions.len()
+        );
+
+        let request = match self.compact_block_requests.get(&block_hash) {
+            Some(request) if request.socket == *addr => {
+                self.compact_block_requests.remove(&block_hash)
+            }
+            // Ignore unsolicited responses.
+            _ => None,
+        };
+        let request = match request {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        self.getdata_request_info.remove(&block_hash);
+
+        match request.partial_block.reconstruct(transactions) {
+            Ok(block) => {
+                self.compact_block_metrics
+                    .observe_reconstructions(RECONSTRUCTED_AFTER_GETBLOCKTXN, 1);
+                self.add_reconstructed_block(block);
+            }
+            Err(err) => {
+                slog::warn!(
+                    self.logger,
+                    "Unable to reconstruct block {} from {}. Error: {}",
+                    block_hash,
+                    addr,
+                    err
+                );
+                self.compact_block_reconstruction_failed(addr, block_hash);
+            }
+        }
+        Ok(())
+    }
+
+    /// This function records a failed reconstruction of a compact block and falls back to
+    /// requesting the full block from the peer.
+    fn compact_block_reconstruction_failed(&mut self, addr: &SocketAddr, block_hash: BlockHash) {
+        self.compact_block_metrics
+            .observe_reconstructions(FAILED, 1);
+
+        slog::info!(
+            self.logger,
+            "Sending getdata to {} : Inventory {:?}",
+            addr,
+            [block_hash]
+        );
+        self.outgoing_command_queue.push(Command {
+            address: Some(*addr),
+            message: NetworkMessage::GetData(vec![Inventory::Block(block_hash)]),
+        });
+        self.getdata_request_info.insert(
+            block_hash,
+            GetDataRequestInfo {
+                socket: *addr,
+                sent_at: SystemTime::now(),
+                _on_timeout: OnTimeout::Ignore,
+            },
+        );
+    }
+
+    fn add_reconstructed_block(&mut self, block: Block) {
+        match self.blockchain.add_block(block) {
+            Ok(block_height) => {
+                slog::info!(
+                    self.logger,
+                    "Reconstructed block added to the cache successfully at height = {}",
+                    block_height
+                );
+            }
+            Err(err) => {
+                slog::warn!(
+                    self.logger,
+                    "Unable to add the reconstructed block in blockchain. Error: {:?}",
+                    err
+                );
+            }
+        }
+    }
+
     /// This function adds a new peer to `peer_info`
     /// and initiates sync with the peer by sending `getheaders` message.
     fn add_peer(&mut self, addr: &SocketAddr) {
@@ -469,10 +784,23 @@ impl BlockchainManager {
                 last_asked: None,
                 sent_at: None,
                 on_timeout: OnTimeout::Ignore,
+                timed_out_requests: 0,
             },
         );
         let locators = (vec![initial_hash], BlockHash::default());
         self.send_getheaders(addr, locators, OnTimeout::Disconnect);
+
+        if self.compact_blocks {
+            // Ask the peer to announce new blocks with compact blocks (high-bandwidth mode).
+            self.outgoing_command_queue.push(Command {
+                address: Some(*addr),
+                message: CompactBlockMessage::SendCompact(SendCompact {
+                    announce: true,
+                    version: COMPACT_BLOCKS_VERSION,
+                })
+                .into_network_message(),
+            });
+        }
     }
 
     /// This function adds a new peer to `peer_info`
@@ -482,17 +810,38 @@ impl BlockchainManager {
         self.peer_info.remove(addr);
         // Removing all the `getdata` requests that have been sent to the peer before.
         self.getdata_request_info.retain(|_, v| v.socket != *addr);
+        self.compact_block_requests.retain(|_, v| v.socket != *addr);
     }
 
     fn filter_expired_getdata_requests(&mut self) {
         let now = SystemTime::now();
         let timeout_period = Duration::new(GETDATA_REQUEST_TIMEOUT_SECS, 0);
-        self.getdata_request_info
-            .retain(|_, request| request.sent_at + timeout_period > now);
+        let peer_info = &mut self.peer_info;
+        self.getdata_request_info.retain(|_, request| {
+            let expired = request.sent_at + timeout_period <= now;
+            if expired {
+                if let Some(peer) = peer_info.get_mut(&request.socket) {
+                    peer.timed_out_requests = peer.timed_out_requests.saturating_add(1);
+                }
+            }
+            !expired
+        });
+
+        // Expired `getblocktxn` requests count as failed reconstructions. The blocks are
+        // requested again through the block sync queue.
+        let getdata_request_info = &self.getdata_request_info;
+        let pending = self.compact_block_requests.len();
+        self.compact_block_requests
+            .retain(|block_hash, _| getdata_request_info.contains_key(block_hash));
+        let expired = pending - self.compact_block_requests.len();
+        if expired > 0 {
+            self.compact_block_metrics
+                .observe_reconstructions(FAILED, expired as u64);
+        }
     }
 
     fn sync_blocks(&mut self) {
-        if self.block_sync_queue.is_empty() {
+        if self.header_only || self.block_sync_queue.is_empty() {
             return;
         }
 
@@ -611,6 +960,26 @@ impl BlockchainManager {
                         return Err(ProcessEventError::InvalidMessage);
                     }
                 }
+                NetworkMessage::Unknown { .. } if self.compact_blocks => {
+                    let result = match CompactBlockMessage::from_network_message(message) {
+                        Some(Ok(CompactBlockMessage::CompactBlock(compact_block))) => {
+                            self.received_compact_block_message(&event.address, compact_block)
+                        }
+                        Some(Ok(CompactBlockMessage::BlockTransactions(block_transactions))) => {
+                            self.received_block_transactions_message(
+                                &event.address,
+                                block_transactions,
+                            )
+                        }
+                        Some(Err(_)) => return Err(ProcessEventError::InvalidMessage),
+                        // The adapter does not serve blocks, so `sendcmpct` and `getblocktxn`
+                        // messages are ignored.
+                        _ => Ok(()),
+                    };
+                    if result.is_err() {
+                        return Err(ProcessEventError::InvalidMessage);
+                    }
+                }
                 _ => {}
             };
         }
@@ -643,6 +1012,32 @@ impl BlockchainManager {
         for command in self.outgoing_command_queue.drain(..) {
             channel.send(command).ok();
         }
+
+        for addr in self.stalling_peers() {
+            slog::warn!(
+                self.logger,
+                "Evicting peer {} as its getdata requests time out",
+                addr
+            );
+            self.remove_peer(&addr);
+            channel.evict(addr);
+        }
+    }
+
+    /// Returns the peers that are to be evicted according to the eviction policy as their
+    /// `getdata` requests time out.
+    fn stalling_peers(&self) -> Vec<SocketAddr> {
+        match self.eviction_policy {
+            EvictionPolicy::Never => vec![],
+            EvictionPolicy::AfterTimeouts {
+                max_timed_out_requests,
+            } => self
+                .peer_info
+                .values()
+                .filter(|peer| peer.timed_out_requests >= max_timed_out_requests)
+                .map(|peer| peer.socket)
+                .collect(),
+        }
     }
 
     /// Performs a breadth-first search to retrieve blocks from the block cache.
@@ -806,9 +1201,10 @@ fn is_single_block_only_mode_enabled(network: Network, anchor_height: BlockHeigh
 pub mod test {
     use super::*;
     use crate::common::test_common::{
-        generate_headers, generate_large_block_blockchain, make_logger, TestState, BLOCK_1_ENCODED,
-        BLOCK_2_ENCODED,
+        generate_block, generate_headers, generate_large_block_blockchain, make_logger, TestState,
+        BLOCK_1_ENCODED, BLOCK_2_ENCODED,
     };
+    use crate::compact_block::PrefilledTransaction;
     use crate::config::test::ConfigBuilder;
     use crate::config::Config;
     use bitcoin::consensus::deserialize;
@@ -825,7 +1221,8 @@ pub mod test {
     #[test]
     fn test_manager_can_send_getheaders_messages() {
         let config = ConfigBuilder::new().build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
         blockchain_manager.add_peer(&addr);
         assert_eq!(blockchain_manager.outgoing_command_queue.len(), 1);
@@ -872,7 +1269,8 @@ pub mod test {
     #[test]
     fn test_init_sync() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
 
         // Create an arbitrary chain and adding to the BlockchainState.
         let chain = generate_headers(
@@ -940,7 +1338,8 @@ pub mod test {
     /// The test then sends an inv message for a fork chain, and verifies if the BlockChainManager responds correctly.
     fn test_received_inv() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
 
         // Create an arbitrary chain and adding to the BlockchainState.
         let chain = generate_headers(
@@ -1041,7 +1440,8 @@ pub mod test {
         let block_2: Block = deserialize(&encoded_block_2).expect("failed to decoded block 2");
 
         let config = Config::default();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         let headers = vec![block_1.header, block_2.header];
         // Initialize the blockchain manager state
         let (added_headers, maybe_err) = blockchain_manager.blockchain.add_headers(&headers);
@@ -1117,7 +1517,8 @@ pub mod test {
         let config = ConfigBuilder::new().build();
         let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
         let headers = vec![test_state.block_1.header, test_state.block_2.header];
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         blockchain_manager.add_peer(&addr);
         let (added_headers, _) = blockchain_manager.blockchain.add_headers(&headers);
         assert_eq!(added_headers.len(), 2);
@@ -1154,12 +1555,45 @@ pub mod test {
         assert!(blockchain_manager.block_sync_queue.is_empty());
     }
 
+    /// This test ensures that no `getdata` requests are sent while the manager only syncs
+    /// headers, and that the queued blocks are requested once it syncs blocks again.
+    #[test]
+    fn test_sync_blocks_header_only() {
+        let test_state = TestState::setup();
+        let config = ConfigBuilder::new().build();
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
+        blockchain_manager.add_peer(&addr);
+        let block_1_hash = test_state.block_1.block_hash();
+        blockchain_manager.block_sync_queue.push_back(block_1_hash);
+
+        blockchain_manager.set_header_only(true);
+        blockchain_manager.sync_blocks();
+        assert!(blockchain_manager.outgoing_command_queue.is_empty());
+        assert!(blockchain_manager.getdata_request_info.is_empty());
+        assert_eq!(blockchain_manager.block_sync_queue.len(), 1);
+
+        blockchain_manager.set_header_only(false);
+        blockchain_manager.sync_blocks();
+        assert!(blockchain_manager.block_sync_queue.is_empty());
+        assert!(blockchain_manager
+            .getdata_request_info
+            .contains_key(&block_1_hash));
+        assert!(matches!(
+            blockchain_manager.outgoing_command_queue.first(),
+            Some(Command { message: NetworkMessage::GetData(inventory), .. })
+                if inventory == &vec![Inventory::Block(block_1_hash)]
+        ));
+    }
+
     /// This tests ensures that `BlockchainManager::handle_client_request(...)` returns multiple
     /// blocks from the main chain and a fork. Order should be preserved.
     #[test]
     fn test_get_successors_multiple_blocks() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         // Set up the following chain:
         // |-> 1'
         // 0 -> 1 -> 2
@@ -1226,7 +1660,8 @@ pub mod test {
     #[test]
     fn test_get_successors_multiple_blocks_out_of_order() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         // Set up the following chain:
         // |-> 1'
         // 0 -> 1 -> 2
@@ -1284,7 +1719,8 @@ pub mod test {
     fn test_get_successors_large_block() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
         let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
-        let blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         let genesis = blockchain_manager.blockchain.genesis();
 
         let large_blocks =
@@ -1299,7 +1735,8 @@ pub mod test {
             txdata: vec![],
         };
 
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         blockchain_manager.add_peer(&addr);
         let (added_headers, _) = blockchain_manager.blockchain.add_headers(&headers);
         assert_eq!(added_headers.len(), 1);
@@ -1335,7 +1772,8 @@ pub mod test {
     fn test_sync_blocks_size_limit() {
         let test_state = TestState::setup();
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
 
         // Make 5 large blocks that are around 2MiB each.
@@ -1376,7 +1814,8 @@ pub mod test {
         let block_2_hash = block_2.block_hash();
 
         let config = Config::default();
-        let mut blockchain_manager = BlockchainManager::new(&config, make_logger());
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
         let headers = vec![block_1.header, block_2.header];
         // Initialize the blockchain manager state
         let (added_headers, maybe_err) = blockchain_manager.blockchain.add_headers(&headers);
@@ -1406,4 +1845,294 @@ pub mod test {
             .is_none());
         assert_eq!(blockchain_manager.peer_info.len(), 0);
     }
+
+    /// A channel that records the evicted connections.
+    struct TestChannel {
+        connections: Vec<SocketAddr>,
+        evicted: Vec<SocketAddr>,
+    }
+
+    impl Channel for TestChannel {
+        fn send(&mut self, _: Command) -> Result<(), crate::ChannelError> {
+            Ok(())
+        }
+
+        fn evict(&mut self, address: SocketAddr) {
+            self.evicted.push(address);
+        }
+
+        fn available_connections(&self) -> Vec<SocketAddr> {
+            self.connections.clone()
+        }
+    }
+
+    /// Lets a `getdata` request to a peer time out and returns the peers evicted afterwards.
+    fn evicted_after_getdata_timeout(eviction_policy: EvictionPolicy) -> Vec<SocketAddr> {
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_eviction_policy(eviction_policy)
+            .build();
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
+        let mut channel = TestChannel {
+            connections: vec![addr],
+            evicted: vec![],
+        };
+        blockchain_manager.tick(&mut channel);
+
+        let headers = generate_headers(
+            blockchain_manager.blockchain.genesis().header.block_hash(),
+            blockchain_manager.blockchain.genesis().header.time,
+            2,
+        );
+        blockchain_manager.getdata_request_info.insert(
+            headers[0].block_hash(),
+            GetDataRequestInfo {
+                socket: addr,
+                sent_at: SystemTime::now() - Duration::from_secs(GETDATA_REQUEST_TIMEOUT_SECS + 1),
+                _on_timeout: OnTimeout::Ignore,
+            },
+        );
+        // Expired requests are only filtered when there are blocks to sync.
+        blockchain_manager
+            .block_sync_queue
+            .push_back(headers[1].block_hash());
+        blockchain_manager.tick(&mut channel);
+
+        if !channel.evicted.is_empty() {
+            assert!(!blockchain_manager.peer_info.contains_key(&addr));
+        }
+        channel.evicted
+    }
+
+    /// This unit test verifies that peers whose `getdata` requests time out are only evicted if
+    /// the eviction policy says so.
+    #[test]
+    fn test_stalling_peer_eviction() {
+        assert!(evicted_after_getdata_timeout(EvictionPolicy::Never).is_empty());
+        assert!(
+            evicted_after_getdata_timeout(EvictionPolicy::AfterTimeouts {
+                max_timed_out_requests: 2
+            })
+            .is_empty()
+        );
+        assert_eq!(
+            evicted_after_getdata_timeout(EvictionPolicy::AfterTimeouts {
+                max_timed_out_requests: 1
+            }),
+            vec![SocketAddr::from_str("127.0.0.1:8333").unwrap()]
+        );
+    }
+
+    /// Creates a compact block of `block` in which only the coinbase transaction is prefilled.
+    fn compact_block_with_coinbase(block: &Block) -> HeaderAndShortIds {
+        HeaderAndShortIds {
+            header: block.header,
+            nonce: 0,
+            short_ids: vec![[0; 6]; block.txdata.len() - 1],
+            prefilled_txs: vec![PrefilledTransaction {
+                differential_index: 0,
+                tx: block.txdata[0].clone(),
+            }],
+        }
+    }
+
+    /// This unit test verifies that the BlockchainManager asks peers for compact blocks and
+    /// reconstructs the blocks from them, falling back to requesting the full block if the
+    /// reconstruction fails.
+    #[test]
+    fn test_compact_block_reconstruction() {
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_compact_blocks(true)
+            .build();
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
+        blockchain_manager.add_peer(&addr);
+        let commands: Vec<Command> = blockchain_manager
+            .outgoing_command_queue
+            .drain(..)
+            .collect();
+        assert_eq!(commands.len(), 2);
+        assert!(matches!(
+            CompactBlockMessage::from_network_message(&commands[1].message),
+            Some(Ok(CompactBlockMessage::SendCompact(SendCompact {
+                announce: true,
+                version: COMPACT_BLOCKS_VERSION
+            })))
+        ));
+
+        let coinbase = TestState::setup().block_1.txdata[0].clone();
+        let transactions: Vec<_> = (0..3)
+            .map(|lock_time| {
+                let mut tx = coinbase.clone();
+                tx.lock_time = lock_time;
+                tx
+            })
+            .collect();
+        let genesis = blockchain_manager.blockchain.genesis().header;
+        let block_1 = generate_block(
+            genesis.block_hash(),
+            genesis.time,
+            transactions[..1].to_vec(),
+        );
+        let block_2 = generate_block(
+            block_1.block_hash(),
+            block_1.header.time,
+            transactions.clone(),
+        );
+        let block_3 = generate_block(
+            block_2.block_hash(),
+            block_2.header.time,
+            transactions.clone(),
+        );
+
+        // A block that only contains the prefilled coinbase transaction is reconstructed right away.
+        blockchain_manager
+            .received_compact_block_message(&addr, compact_block_with_coinbase(&block_1))
+            .expect("should accept the compact block");
+        assert_eq!(
+            blockchain_manager
+                .blockchain
+                .get_block(&block_1.block_hash()),
+            Some(&block_1)
+        );
+        assert!(blockchain_manager.outgoing_command_queue.is_empty());
+
+        // The missing transactions of a block are requested from the peer.
+        blockchain_manager
+            .received_compact_block_message(&addr, compact_block_with_coinbase(&block_2))
+            .expect("should accept the compact block");
+        let command = blockchain_manager
+            .outgoing_command_queue
+            .pop()
+            .expect("getblocktxn should have been sent");
+        assert_eq!(command.address, Some(addr));
+        assert!(matches!(
+            CompactBlockMessage::from_network_message(&command.message),
+            Some(Ok(CompactBlockMessage::GetBlockTransactions(BlockTransactionsRequest { block_hash, indexes })))
+                if block_hash == block_2.block_hash() && indexes == vec![1, 2]
+        ));
+        assert!(blockchain_manager
+            .getdata_request_info
+            .contains_key(&block_2.block_hash()));
+        blockchain_manager
+            .received_block_transactions_message(
+                &addr,
+                BlockTransactions {
+                    block_hash: block_2.block_hash(),
+                    transactions: transactions[1..].to_vec(),
+                },
+            )
+            .expect("should accept the block transactions");
+        assert_eq!(
+            blockchain_manager
+                .blockchain
+                .get_block(&block_2.block_hash()),
+            Some(&block_2)
+        );
+        assert!(blockchain_manager.getdata_request_info.is_empty());
+
+        // If the reconstruction fails, the full block is requested instead.
+        blockchain_manager
+            .received_compact_block_message(&addr, compact_block_with_coinbase(&block_3))
+            .expect("should accept the compact block");
+        blockchain_manager.outgoing_command_queue.clear();
+        blockchain_manager
+            .received_block_transactions_message(
+                &addr,
+                BlockTransactions {
+                    block_hash: block_3.block_hash(),
+                    transactions: vec![transactions[2].clone(), transactions[1].clone()],
+                },
+            )
+            .expect("should accept the block transactions");
+        assert!(blockchain_manager
+            .blockchain
+            .get_block(&block_3.block_hash())
+            .is_none());
+        let command = blockchain_manager
+            .outgoing_command_queue
+            .pop()
+            .expect("getdata should have been sent");
+        assert!(
+            matches!(&command.message, NetworkMessage::GetData(inventory) if inventory == &vec![Inventory::Block(block_3.block_hash())])
+        );
+        assert!(blockchain_manager
+            .getdata_request_info
+            .contains_key(&block_3.block_hash()));
+
+        let metrics = &blockchain_manager.compact_block_metrics;
+        assert_eq!(metrics.received.get(), 3);
+        for outcome in &[RECONSTRUCTED, RECONSTRUCTED_AFTER_GETBLOCKTXN, FAILED] {
+            assert_eq!(
+                metrics.reconstructions.with_label_values(&[outcome]).get(),
+                1
+            );
+        }
+    }
+
+    /// This unit test verifies that compact block messages are ignored if compact blocks are
+    /// disabled.
+    #[test]
+    fn test_compact_blocks_disabled() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
+        blockchain_manager.add_peer(&addr);
+        assert_eq!(blockchain_manager.outgoing_command_queue.len(), 1);
+
+        let genesis = blockchain_manager.blockchain.genesis().header;
+        let coinbase = TestState::setup().block_1.txdata[0].clone();
+        let block = generate_block(genesis.block_hash(), genesis.time, vec![coinbase]);
+        let event = StreamEvent {
+            address: addr,
+            kind: StreamEventKind::Message(
+                CompactBlockMessage::CompactBlock(compact_block_with_coinbase(&block))
+                    .into_network_message(),
+            ),
+        };
+        assert!(blockchain_manager.process_event(&event).is_ok());
+        assert!(blockchain_manager
+            .blockchain
+            .get_block(&block.block_hash())
+            .is_none());
+        assert_eq!(blockchain_manager.compact_block_metrics.received.get(), 0);
+    }
+
+    /// Tests that `BlockchainManager::get_transactions_by_address(...)` returns the cached
+    /// transactions paying to an address of the network of the adapter, and `None` for an
+    /// address of another network.
+    #[cfg(feature = "debug_rpc")]
+    #[test]
+    fn test_get_transactions_by_address() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut blockchain_manager =
+            BlockchainManager::new(&config, make_logger(), &MetricsRegistry::new());
+        let genesis = blockchain_manager.blockchain.genesis().header;
+        let address = bitcoin::Address::p2wsh(&bitcoin::Script::new(), Network::Regtest);
+        let mut transaction = TestState::setup().block_1.txdata[0].clone();
+        transaction.output[0].script_pubkey = address.script_pubkey();
+        let block = generate_block(
+            genesis.block_hash(),
+            genesis.time,
+            vec![transaction.clone()],
+        );
+        blockchain_manager
+            .blockchain
+            .add_block(block.clone())
+            .expect("invalid block");
+
+        assert_eq!(
+            blockchain_manager.get_transactions_by_address(&address),
+            Some(vec![(block.block_hash(), &transaction)])
+        );
+        let mainnet_address = bitcoin::Address::p2wsh(&bitcoin::Script::new(), Network::Bitcoin);
+        assert!(blockchain_manager
+            .get_transactions_by_address(&mainnet_address)
+            .is_none());
+    }
 }
@@ -1,8 +1,15 @@
 use crate::{common::BlockHeight, config::Config};
 use bitcoin::{blockdata::constants::genesis_block, Block, BlockHash, BlockHeader, Network};
-use ic_btc_validation::{validate_header, HeaderStore, ValidateHeaderError};
+#[cfg(any(test, feature = "debug_rpc"))]
+use bitcoin::{Script, Transaction};
+use ic_btc_validation::{
+    validate_header, validate_header_pow, validate_header_with_pow_result, HeaderStore,
+    ValidateHeaderError,
+};
 use std::collections::HashMap;
+use std::sync::mpsc;
 use thiserror::Error;
+use threadpool::ThreadPool;
 
 /// This field contains the datatype used to store "work" of a Bitcoin blockchain
 pub type Work = bitcoin::util::uint::Uint256;
@@ -85,6 +92,17 @@ pub struct BlockchainState {
 
     ///
     network: Network,
+
+    /// The built-in and configured checkpoints of the network, by height.
+    checkpoints: HashMap<BlockHeight, BlockHash>,
+
+    /// The height of the highest configured checkpoint. Until the active chain reaches this
+    /// height, the proof of work of the received headers is checked in parallel.
+    highest_checkpoint_height: BlockHeight,
+
+    /// The threads that check the proof of work of headers in parallel, if more than one is
+    /// configured.
+    header_validation_pool: Option<ThreadPool>,
 }
 
 impl BlockchainState {
@@ -118,6 +136,18 @@ impl BlockchainState {
             cached_genesis,
             tips,
             network: config.network,
+            checkpoints: config.all_checkpoints(),
+            highest_checkpoint_height: config
+                .checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.height)
+                .max()
+                .unwrap_or_default(),
+            header_validation_pool: if config.header_validation_threads > 1 {
+                Some(ThreadPool::new(config.header_validation_threads))
+            } else {
+                None
+            },
         }
     }
 
@@ -147,8 +177,18 @@ impl BlockchainState {
     ) -> (Vec<CachedHeader>, Option<AddHeaderError>) {
         let mut added_headers = vec![];
 
+        // During the initial sync, the proof of work of the headers is checked up front, in
+        // parallel. The headers are validated against their ancestors as they are added.
+        let mut pow_results = match &self.header_validation_pool {
+            Some(pool) if self.get_height() < self.highest_checkpoint_height => {
+                validate_pow_in_parallel(pool, self.network, headers)
+            }
+            _ => vec![],
+        }
+        .into_iter();
+
         for header in headers {
-            match self.add_header(*header) {
+            match self.add_header(*header, pow_results.next()) {
                 Ok(AddHeaderResult::HeaderAdded(cached_header)) => {
                     added_headers.push(cached_header);
                 }
@@ -163,9 +203,14 @@ impl BlockchainState {
         (added_headers, None)
     }
 
-    /// This method adds the input header to the `header_cache`.
+    /// This method adds the input header to the `header_cache`. If the proof of work of the
+    /// header has already been checked, the result is passed in `pow_result`.
     #[allow(clippy::indexing_slicing)]
-    fn add_header(&mut self, header: BlockHeader) -> Result<AddHeaderResult, AddHeaderError> {
+    fn add_header(
+        &mut self,
+        header: BlockHeader,
+        pow_result: Option<Result<(), ValidateHeaderError>>,
+    ) -> Result<AddHeaderResult, AddHeaderError> {
         let block_hash = header.block_hash();
 
         // If the header already exists in the cache,
@@ -174,7 +219,14 @@ impl BlockchainState {
             return Ok(AddHeaderResult::HeaderAlreadyExists(cached_header.clone()));
         }
 
-        if let Err(err) = validate_header(&self.network, self, &header) {
+        let validation = match pow_result {
+            Some(pow_result) => {
+                validate_header_with_pow_result(&self.network, self, &header, pow_result)
+            }
+            None => validate_header(&self.network, self, &header),
+        }
+        .and_then(|()| self.validate_configured_checkpoints(&header));
+        if let Err(err) = validation {
             return Err(AddHeaderError::InvalidHeader(block_hash, err));
         }
 
@@ -228,6 +280,33 @@ impl BlockchainState {
         Ok(AddHeaderResult::HeaderAdded(cached_header))
     }
 
+    /// Validates a header, whose previous header is cached, against the configured checkpoints
+    /// in addition to the built-in ones: the header must match the checkpoint at its height and
+    /// must not fork off the chain below a checkpoint that the active chain already passed.
+    fn validate_configured_checkpoints(
+        &self,
+        header: &BlockHeader,
+    ) -> Result<(), ValidateHeaderError> {
+        let prev_height = match self.header_cache.get(&header.prev_blockhash) {
+            Some(prev_header) => prev_header.height,
+            None => return Err(ValidateHeaderError::PrevHeaderNotFound),
+        };
+        #[allow(clippy::integer_arithmetic)]
+        let height = prev_height + 1;
+        if let Some(hash) = self.checkpoints.get(&height) {
+            if *hash != header.block_hash() {
+                return Err(ValidateHeaderError::DoesNotMatchCheckpoint);
+            }
+        }
+        let chain_height = self.get_height();
+        if self.checkpoints.keys().any(|checkpoint_height| {
+            height <= *checkpoint_height && *checkpoint_height <= chain_height
+        }) {
+            return Err(ValidateHeaderError::DoesNotMatchCheckpoint);
+        }
+        Ok(())
+    }
+
     /// This method verifies if the input block is valid.
     /// TODO: ER-1546: Validate incoming blocks
     fn is_block_valid(&self, block: &Block) -> bool {
@@ -239,7 +318,7 @@ impl BlockchainState {
         // If the block's header is not added before, then add the header into the `header_cache` first.
         let block_hash = block.block_hash();
         let result = self
-            .add_header(block.header)
+            .add_header(block.header, None)
             .map_err(AddBlockError::Header)?;
         if !self.is_block_valid(&block) {
             return Err(AddBlockError::InvalidBlock(block_hash));
@@ -251,6 +330,32 @@ impl BlockchainState {
         })
     }
 
+    /// Returns the transactions of the cached blocks with an output locked by
+    /// `script_pubkey`, along with the hashes of their blocks, in the order of
+    /// the heights of the blocks.
+    #[cfg(any(test, feature = "debug_rpc"))]
+    pub fn get_transactions_paying_to(
+        &self,
+        script_pubkey: &Script,
+    ) -> Vec<(BlockHash, &Transaction)> {
+        let mut blocks: Vec<_> = self.block_cache.iter().collect();
+        blocks.sort_by_key(|(hash, _)| self.get_cached_header(hash).map(|cached| cached.height));
+        blocks
+            .into_iter()
+            .flat_map(|(hash, block)| {
+                block
+                    .txdata
+                    .iter()
+                    .filter(|tx| {
+                        tx.output
+                            .iter()
+                            .any(|output| output.script_pubkey == *script_pubkey)
+                    })
+                    .map(move |tx| (*hash, tx))
+            })
+            .collect()
+    }
+
     /// This method returns the tip header with the highest cumulative work.
     #[allow(clippy::indexing_slicing)]
     pub fn get_active_chain_tip(&self) -> &Tip {
@@ -330,6 +435,42 @@ impl BlockchainState {
     }
 }
 
+/// Checks the proof of work of each of `headers`, splitting the headers among the threads of
+/// `pool`. The results are returned in the order of the headers. If a thread fails, the results
+/// of the headers after the ones it checked are missing, and these headers are checked when
+/// they are added.
+fn validate_pow_in_parallel(
+    pool: &ThreadPool,
+    network: Network,
+    headers: &[BlockHeader],
+) -> Vec<Result<(), ValidateHeaderError>> {
+    let threads = pool.max_count();
+    #[allow(clippy::integer_arithmetic)]
+    let chunk_size = ((headers.len() + threads - 1) / threads).max(1);
+    let (sender, receiver) = mpsc::channel();
+    for (index, chunk) in headers.chunks(chunk_size).enumerate() {
+        let chunk = chunk.to_vec();
+        let sender = sender.clone();
+        pool.execute(move || {
+            let results: Vec<_> = chunk
+                .iter()
+                .map(|header| validate_header_pow(&network, header))
+                .collect();
+            // The receiver waits until all chunks are checked.
+            let _ = sender.send((index, results));
+        });
+    }
+    drop(sender);
+    let mut chunks: Vec<_> = receiver.iter().collect();
+    chunks.sort_unstable_by_key(|(index, _)| *index);
+    chunks
+        .into_iter()
+        .enumerate()
+        .take_while(|(expected_index, (index, _))| expected_index == index)
+        .flat_map(|(_, (_, results))| results)
+        .collect()
+}
+
 impl HeaderStore for BlockchainState {
     fn get_header(&self, hash: &BlockHash) -> Option<(&BlockHeader, BlockHeight)> {
         self.get_cached_header(hash)
@@ -349,8 +490,8 @@ impl HeaderStore for BlockchainState {
 mod test {
     use super::*;
     use crate::{
-        common::test_common::{block_1, block_2, generate_headers, TestState},
-        config::test::ConfigBuilder,
+        common::test_common::{block_1, block_2, generate_block, generate_headers, TestState},
+        config::{test::ConfigBuilder, Checkpoint},
     };
     use std::collections::HashSet;
 
@@ -521,6 +662,93 @@ mod test {
         assert_eq!(tip.height, 10);
     }
 
+    /// Tests that headers up to the highest configured checkpoint, which are validated in
+    /// parallel, and the headers after it are added to the cache.
+    #[test]
+    fn test_adding_headers_across_checkpoints() {
+        let initial_header = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16);
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_checkpoints(vec![
+                Checkpoint {
+                    height: 8,
+                    hash: chain[7].block_hash(),
+                },
+                Checkpoint {
+                    height: 12,
+                    hash: chain[11].block_hash(),
+                },
+            ])
+            .with_header_validation_threads(4)
+            .build();
+        let mut state = BlockchainState::new(&config);
+
+        let (added_headers, maybe_err) = state.add_headers(&chain);
+        assert!(maybe_err.is_none(), "unexpected error: {:?}", maybe_err);
+        assert_eq!(added_headers.len(), 16);
+        let tip = state.get_active_chain_tip();
+        assert_eq!(tip.height, 16);
+        assert_eq!(tip.header.block_hash(), chain[15].block_hash());
+    }
+
+    /// Tests that a header that does not match the configured checkpoint at its height is
+    /// rejected.
+    #[test]
+    fn test_adding_headers_that_do_not_match_a_checkpoint() {
+        let initial_header = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16);
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_checkpoints(vec![Checkpoint {
+                height: 8,
+                hash: BlockHash::default(),
+            }])
+            .build();
+        let mut state = BlockchainState::new(&config);
+
+        let (added_headers, maybe_err) = state.add_headers(&chain);
+        assert_eq!(added_headers.len(), 7);
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidHeader(
+                block_hash,
+                ValidateHeaderError::DoesNotMatchCheckpoint
+            )) if block_hash == chain[7].block_hash()
+        ));
+        assert_eq!(state.get_active_chain_tip().height, 7);
+    }
+
+    /// Tests that a fork off the chain below a checkpoint that the chain already passed is
+    /// rejected.
+    #[test]
+    fn test_adding_a_fork_below_a_passed_checkpoint() {
+        let initial_header = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16);
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_checkpoints(vec![Checkpoint {
+                height: 12,
+                hash: chain[11].block_hash(),
+            }])
+            .build();
+        let mut state = BlockchainState::new(&config);
+        let (_, maybe_err) = state.add_headers(&chain);
+        assert!(maybe_err.is_none(), "unexpected error: {:?}", maybe_err);
+
+        let fork_chain = generate_headers(chain[5].block_hash(), chain[5].time, 16);
+        let (added_headers, maybe_err) = state.add_headers(&fork_chain);
+        assert!(added_headers.is_empty());
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidHeader(
+                _,
+                ValidateHeaderError::DoesNotMatchCheckpoint
+            ))
+        ));
+        assert_eq!(state.get_active_chain_tip().height, 16);
+    }
+
     /// Tests the functionality of `BlockchainState::add_block(...)` to push it through the add_header
     /// validation and adding the block to the cache.
     #[test]
@@ -575,4 +803,44 @@ mod test {
 
         assert_eq!(expected_cache_size, block_cache_size);
     }
+
+    /// Tests that `BlockchainState::get_transactions_paying_to(...)` returns the
+    /// transactions of the cached blocks with an output to the given script, in
+    /// the order of the heights of the blocks.
+    #[test]
+    fn test_get_transactions_paying_to() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut state = BlockchainState::new(&config);
+        let genesis = state.genesis().header;
+        let coinbase = TestState::setup().block_1.txdata[0].clone();
+        let script_pubkey = coinbase.output[0].script_pubkey.clone();
+        let mut other_coinbase = coinbase.clone();
+        other_coinbase.output[0].script_pubkey = Script::new();
+
+        let block_1 = generate_block(genesis.block_hash(), genesis.time, vec![coinbase.clone()]);
+        let block_2 = generate_block(
+            block_1.block_hash(),
+            block_1.header.time,
+            vec![other_coinbase],
+        );
+        let block_3 = generate_block(
+            block_2.block_hash(),
+            block_2.header.time,
+            vec![coinbase.clone()],
+        );
+        state.add_block(block_1.clone()).unwrap();
+        state.add_block(block_2).unwrap();
+        state.add_block(block_3.clone()).unwrap();
+
+        assert_eq!(
+            state.get_transactions_paying_to(&script_pubkey),
+            vec![
+                (block_1.block_hash(), &coinbase),
+                (block_3.block_hash(), &coinbase)
+            ]
+        );
+        assert!(state
+            .get_transactions_paying_to(&Script::from(vec![0x51]))
+            .is_empty());
+    }
 }
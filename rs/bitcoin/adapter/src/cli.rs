@@ -1,8 +1,9 @@
 //! A parser for the command line flags and configuration file.
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
 use clap::{AppSettings, Clap};
+use ic_adapter_config::{load_config, AdapterSubcommand, LoadConfigError};
 use slog::Level;
-use std::{fs::File, io, path::PathBuf};
+use std::{io, path::PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,14 +12,28 @@ pub enum CliError {
     Io(io::Error),
     #[error("An error occurred while deserialized the provided configuration: {0}")]
     Deserialize(String),
+    #[error("The provided configuration is invalid: {0}")]
+    InvalidConfig(ConfigError),
+}
+
+impl From<LoadConfigError<ConfigError>> for CliError {
+    fn from(err: LoadConfigError<ConfigError>) -> Self {
+        match err {
+            LoadConfigError::Io { source, .. } => CliError::Io(source),
+            LoadConfigError::Invalid(err) => CliError::InvalidConfig(err),
+            err => CliError::Deserialize(err.to_string()),
+        }
+    }
 }
 /// This struct is use to provide a command line interface to the adapter.
 #[derive(Clap)]
 #[clap(version = "0.0.0", author = "DFINITY team <team@dfinity.org>")]
 #[clap(setting = AppSettings::ColoredHelp)]
 pub struct Cli {
-    /// This field contains the path to the config file.
-    pub config: PathBuf,
+    /// This field contains the path to the config file. If it is not given,
+    /// the config consists of the defaults and the overrides of the
+    /// `IC_BTC_ADAPTER_*` environment variables.
+    pub config: Option<PathBuf>,
 
     /// This field represents if the adapter should ignore connecting to IPv4 addresses only.
     #[clap(short, long)]
@@ -27,6 +42,10 @@ pub struct Cli {
     #[clap(short, long)]
     /// This field represents if the adapter should run in verbose.
     pub verbose: bool,
+
+    #[clap(subcommand)]
+    /// This field contains the subcommand to run instead of the adapter, if any.
+    pub command: Option<AdapterSubcommand>,
 }
 
 impl Cli {
@@ -39,11 +58,10 @@ impl Cli {
         }
     }
 
-    /// Loads the config from the provided `config` argument.
+    /// Loads the config from the provided `config` argument, applies the
+    /// overrides of the environment and validates the result.
     pub fn get_config(&self) -> Result<Config, CliError> {
-        // The expected JSON config.
-        let file = File::open(&self.config).map_err(CliError::Io)?;
-        serde_json::from_reader(file).map_err(|err| CliError::Deserialize(err.to_string()))
+        Ok(load_config(self.config.as_deref())?)
     }
 }
 
@@ -57,17 +75,19 @@ pub mod test {
     #[test]
     fn test_cli_get_logging_level() {
         let cli = Cli {
-            config: PathBuf::new(),
+            config: None,
             ipv6_only: false,
             verbose: false,
+            command: None,
         };
 
         assert_eq!(cli.get_logging_level(), Level::Info);
 
         let cli = Cli {
-            config: PathBuf::new(),
+            config: None,
             ipv6_only: false,
             verbose: true,
+            command: None,
         };
 
         assert_eq!(cli.get_logging_level(), Level::Debug);
@@ -76,9 +96,12 @@ pub mod test {
     #[test]
     fn test_cli_get_config_error_opening_file() {
         let cli = Cli {
-            config: PathBuf::from_str("/tmp/btc-adapter-test.json").expect("Bad file path string"),
+            config: Some(
+                PathBuf::from_str("/tmp/btc-adapter-test.json").expect("Bad file path string"),
+            ),
             ipv6_only: false,
             verbose: true,
+            command: None,
         };
         let result = cli.get_config();
         assert!(result.is_err());
@@ -89,18 +112,19 @@ pub mod test {
     #[test]
     fn test_cli_get_config_error_invalid_json() {
         let cli = Cli {
-            config: PathBuf::from_str("./src/json_configs/empty.config.json")
-                .expect("Bad file path string"),
+            config: Some(
+                PathBuf::from_str("./src/json_configs/empty.config.json")
+                    .expect("Bad file path string"),
+            ),
             ipv6_only: false,
             verbose: true,
+            command: None,
         };
         let result = cli.get_config();
         assert!(result.is_err());
         let error = result.unwrap_err();
         let matches = match error {
-            CliError::Deserialize(message) => {
-                message == "missing field `network` at line 1 column 2"
-            }
+            CliError::Deserialize(message) => message == "missing field `network`",
             _ => false,
         };
         assert!(matches);
@@ -109,10 +133,13 @@ pub mod test {
     #[test]
     fn test_cli_get_config_good_mainnet_json() {
         let cli = Cli {
-            config: PathBuf::from_str("./src/json_configs/mainnet.config.json")
-                .expect("Bad file path string"),
+            config: Some(
+                PathBuf::from_str("./src/json_configs/mainnet.config.json")
+                    .expect("Bad file path string"),
+            ),
             ipv6_only: false,
             verbose: true,
+            command: None,
         };
         let result = cli.get_config();
         let config = result.unwrap();
@@ -124,10 +151,13 @@ pub mod test {
     #[test]
     fn test_cli_get_config_good_testnet_json() {
         let cli = Cli {
-            config: PathBuf::from_str("./src/json_configs/testnet.config.json")
-                .expect("Bad file path string"),
+            config: Some(
+                PathBuf::from_str("./src/json_configs/testnet.config.json")
+                    .expect("Bad file path string"),
+            ),
             ipv6_only: false,
             verbose: true,
+            command: None,
         };
         let result = cli.get_config();
         let config = result.unwrap();
@@ -31,6 +31,8 @@ pub mod test_common {
     use slog::Logger;
 
     use super::BlockHeight;
+    use crate::bandwidth::BandwidthAccounting;
+    use ic_metrics::MetricsRegistry;
 
     /// This is a hex dump of the first block on the BTC network: 00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048
     pub const BLOCK_1_ENCODED: &str = "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e362990101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000";
@@ -110,6 +112,12 @@ pub mod test_common {
         Logger::root(slog::Discard, slog::o!())
     }
 
+    /// This function creates a [BandwidthAccounting](BandwidthAccounting) without an egress
+    /// cap whose metrics are not exported.
+    pub fn make_bandwidth_accounting() -> BandwidthAccounting {
+        BandwidthAccounting::new(None, &MetricsRegistry::new())
+    }
+
     /// Generates a blockchain containing large blocks (blocks over 2MiB) starting at a given hash and time.
     pub fn generate_large_block_blockchain(
         initial_blockhash: BlockHash,
@@ -173,6 +181,29 @@ pub mod test_common {
         header
     }
 
+    /// This helper generates a block with the given transactions and previous blockhash.
+    pub fn generate_block(
+        prev_blockhash: BlockHash,
+        prev_time: u32,
+        txdata: Vec<Transaction>,
+    ) -> Block {
+        let mut block = Block {
+            header: BlockHeader {
+                version: 1,
+                prev_blockhash,
+                merkle_root: TxMerkleNode::default(),
+                time: prev_time + gen_time_delta(),
+                bits: BlockHeader::compact_target_from_u256(&TARGET),
+                nonce: 0,
+            },
+            txdata,
+        };
+
+        block.header.merkle_root = block.merkle_root();
+        solve_proof_of_work(&mut block.header);
+        block
+    }
+
     /// Generates a number of seconds used for adding to the previous time (1 to 10 minutes).
     fn gen_time_delta() -> u32 {
         let mut rng = StdRng::from_entropy();
@@ -0,0 +1,615 @@
+use bitcoin::{
+    consensus::{deserialize, encode, serialize, Decodable, Encodable, ReadExt},
+    network::message::{CommandString, NetworkMessage},
+    Block, BlockHash, BlockHeader, Transaction, VarInt,
+};
+use ic_metrics::MetricsRegistry;
+use prometheus::{IntCounter, IntCounterVec};
+use std::io;
+use thiserror::Error;
+
+/// The command of the message used to negotiate compact block relay.
+pub const SENDCMPCT_COMMAND: &str = "sendcmpct";
+
+/// The command of the message used to relay a compact block.
+pub const CMPCTBLOCK_COMMAND: &str = "cmpctblock";
+
+/// The command of the message used to request the transactions of a compact
+/// block that could not be reconstructed.
+pub const GETBLOCKTXN_COMMAND: &str = "getblocktxn";
+
+/// The command of the message used to respond to a `getblocktxn` message.
+pub const BLOCKTXN_COMMAND: &str = "blocktxn";
+
+/// The compact block version requested by the adapter. Bitcoin Core only relays
+/// compact blocks of version 2 (short IDs computed from wtxids) to peers if it
+/// supports segregated witness itself.
+pub const COMPACT_BLOCKS_VERSION: u64 = 2;
+
+/// The protocol version that needs to be announced in the `version` message so
+/// that peers announce new blocks with `cmpctblock` messages.
+///
+/// 70014 introduced compact blocks, 70015 stopped banning peers relaying
+/// compact blocks that turn out to be invalid. Bitcoin Core only announces
+/// compact blocks to peers with a version of at least 70015.
+pub const COMPACT_BLOCKS_PROTOCOL_VERSION: u32 = 70_015;
+
+/// The maximum number of transactions a block may contain: the maximum block
+/// weight (4_000_000) divided by the weight of the smallest transaction (40).
+const MAX_TRANSACTIONS_PER_BLOCK: u64 = 100_000;
+
+/// The size of a short transaction ID in bytes.
+const SHORT_ID_SIZE: usize = 6;
+
+/// The possible errors when handling a compact block.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CompactBlockError {
+    /// The compact block contains more transactions than a block may contain.
+    #[error("Compact block contains too many transactions ({0})")]
+    TooManyTransactions(u64),
+    /// A prefilled transaction has an index outside of the block.
+    #[error("Prefilled transaction index {0} is out of bounds")]
+    PrefilledIndexOutOfBounds(u64),
+    /// The `blocktxn` message does not contain the requested number of transactions.
+    #[error("Expected {expected} missing transactions, got {received}")]
+    UnexpectedTransactionCount {
+        /// The number of transactions that were requested.
+        expected: usize,
+        /// The number of transactions that were received.
+        received: usize,
+    },
+    /// The reconstructed block does not match the merkle root of its header.
+    #[error("Reconstructed block does not match the merkle root of the header")]
+    MerkleRootMismatch,
+}
+
+/// The `sendcmpct` message, see
+/// [BIP152](https://github.com/bitcoin/bips/blob/master/bip-0152.mediawiki#sendcmpct).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendCompact {
+    /// If set, the peer is asked to announce new blocks with a `cmpctblock`
+    /// message without waiting for a request (high-bandwidth mode).
+    pub announce: bool,
+    /// The compact block version.
+    pub version: u64,
+}
+
+/// A transaction that is sent as part of a compact block, together with its
+/// differentially encoded index in the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrefilledTransaction {
+    /// The index of the transaction in the block, relative to the previous
+    /// prefilled transaction.
+    pub differential_index: u64,
+    /// The transaction.
+    pub tx: Transaction,
+}
+
+/// The `cmpctblock` message, see
+/// [BIP152](https://github.com/bitcoin/bips/blob/master/bip-0152.mediawiki#cmpctblock).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderAndShortIds {
+    /// The header of the block.
+    pub header: BlockHeader,
+    /// The nonce used to compute the short transaction IDs.
+    pub nonce: u64,
+    /// The short IDs of the transactions that are not prefilled.
+    pub short_ids: Vec<[u8; SHORT_ID_SIZE]>,
+    /// The transactions that the sender expects the receiver to miss, at least
+    /// the coinbase transaction.
+    pub prefilled_txs: Vec<PrefilledTransaction>,
+}
+
+/// The `getblocktxn` message, see
+/// [BIP152](https://github.com/bitcoin/bips/blob/master/bip-0152.mediawiki#getblocktxn).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockTransactionsRequest {
+    /// The hash of the block.
+    pub block_hash: BlockHash,
+    /// The absolute indices of the requested transactions, in ascending order.
+    pub indexes: Vec<u64>,
+}
+
+/// The `blocktxn` message, see
+/// [BIP152](https://github.com/bitcoin/bips/blob/master/bip-0152.mediawiki#blocktxn).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockTransactions {
+    /// The hash of the block.
+    pub block_hash: BlockHash,
+    /// The requested transactions, in the order they were requested.
+    pub transactions: Vec<Transaction>,
+}
+
+/// The compact block relay messages the adapter handles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompactBlockMessage {
+    /// A `sendcmpct` message.
+    SendCompact(SendCompact),
+    /// A `cmpctblock` message.
+    CompactBlock(HeaderAndShortIds),
+    /// A `getblocktxn` message.
+    GetBlockTransactions(BlockTransactionsRequest),
+    /// A `blocktxn` message.
+    BlockTransactions(BlockTransactions),
+}
+
+impl CompactBlockMessage {
+    /// Returns the compact block relay message contained in `message`, if any.
+    /// rust-bitcoin does not know these messages, so they are received as
+    /// unknown messages. Returns an error if the payload cannot be decoded.
+    pub fn from_network_message(message: &NetworkMessage) -> Option<Result<Self, encode::Error>> {
+        let (command, payload) = match message {
+            NetworkMessage::Unknown { command, payload } => (command.to_string(), payload),
+            _ => return None,
+        };
+        let message = match command.as_str() {
+            SENDCMPCT_COMMAND => deserialize(payload).map(Self::SendCompact),
+            CMPCTBLOCK_COMMAND => deserialize(payload).map(Self::CompactBlock),
+            GETBLOCKTXN_COMMAND => deserialize(payload).map(Self::GetBlockTransactions),
+            BLOCKTXN_COMMAND => deserialize(payload).map(Self::BlockTransactions),
+            _ => return None,
+        };
+        Some(message)
+    }
+
+    /// Wraps the message into a network message that can be sent to a peer.
+    pub fn into_network_message(self) -> NetworkMessage {
+        let (command, payload) = match &self {
+            Self::SendCompact(message) => (SENDCMPCT_COMMAND, serialize(message)),
+            Self::CompactBlock(message) => (CMPCTBLOCK_COMMAND, serialize(message)),
+            Self::GetBlockTransactions(message) => (GETBLOCKTXN_COMMAND, serialize(message)),
+            Self::BlockTransactions(message) => (BLOCKTXN_COMMAND, serialize(message)),
+        };
+        NetworkMessage::Unknown {
+            command: CommandString::try_from(command)
+                .expect("compact block commands are valid command strings"),
+            payload,
+        }
+    }
+}
+
+/// Returns true if `command` is the command of a compact block relay message.
+pub fn is_compact_block_command(command: &CommandString) -> bool {
+    matches!(
+        command.to_string().as_str(),
+        SENDCMPCT_COMMAND | CMPCTBLOCK_COMMAND | GETBLOCKTXN_COMMAND | BLOCKTXN_COMMAND
+    )
+}
+
+/// Reads a length prefix and ensures it does not exceed the number of
+/// transactions a block may contain, so that no oversized allocations are made.
+fn read_transaction_count<D: io::Read>(d: &mut D) -> Result<u64, encode::Error> {
+    let count = VarInt::consensus_decode(&mut *d)?.0;
+    if count > MAX_TRANSACTIONS_PER_BLOCK {
+        return Err(encode::Error::ParseFailed(
+            "length exceeds the maximum number of transactions in a block",
+        ));
+    }
+    Ok(count)
+}
+
+impl Encodable for SendCompact {
+    fn consensus_encode<S: io::Write>(&self, mut s: S) -> Result<usize, io::Error> {
+        let mut len = self.announce.consensus_encode(&mut s)?;
+        len += self.version.consensus_encode(&mut s)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for SendCompact {
+    fn consensus_decode<D: io::Read>(mut d: D) -> Result<Self, encode::Error> {
+        Ok(Self {
+            announce: Decodable::consensus_decode(&mut d)?,
+            version: Decodable::consensus_decode(&mut d)?,
+        })
+    }
+}
+
+impl Encodable for HeaderAndShortIds {
+    fn consensus_encode<S: io::Write>(&self, mut s: S) -> Result<usize, io::Error> {
+        let mut len = self.header.consensus_encode(&mut s)?;
+        len += self.nonce.consensus_encode(&mut s)?;
+        len += VarInt(self.short_ids.len() as u64).consensus_encode(&mut s)?;
+        for short_id in self.short_ids.iter() {
+            s.write_all(short_id)?;
+            len += SHORT_ID_SIZE;
+        }
+        len += VarInt(self.prefilled_txs.len() as u64).consensus_encode(&mut s)?;
+        for prefilled in self.prefilled_txs.iter() {
+            len += VarInt(prefilled.differential_index).consensus_encode(&mut s)?;
+            len += prefilled.tx.consensus_encode(&mut s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for HeaderAndShortIds {
+    fn consensus_decode<D: io::Read>(mut d: D) -> Result<Self, encode::Error> {
+        let header = Decodable::consensus_decode(&mut d)?;
+        let nonce = Decodable::consensus_decode(&mut d)?;
+        let short_ids_count = read_transaction_count(&mut d)?;
+        let mut short_ids = Vec::with_capacity(short_ids_count as usize);
+        for _ in 0..short_ids_count {
+            let mut short_id = [0; SHORT_ID_SIZE];
+            d.read_slice(&mut short_id)?;
+            short_ids.push(short_id);
+        }
+        let prefilled_count = read_transaction_count(&mut d)?;
+        let mut prefilled_txs = Vec::with_capacity(prefilled_count as usize);
+        for _ in 0..prefilled_count {
+            prefilled_txs.push(PrefilledTransaction {
+                differential_index: VarInt::consensus_decode(&mut d)?.0,
+                tx: Decodable::consensus_decode(&mut d)?,
+            });
+        }
+        Ok(Self {
+            header,
+            nonce,
+            short_ids,
+            prefilled_txs,
+        })
+    }
+}
+
+impl Encodable for BlockTransactionsRequest {
+    fn consensus_encode<S: io::Write>(&self, mut s: S) -> Result<usize, io::Error> {
+        let mut len = self.block_hash.consensus_encode(&mut s)?;
+        len += VarInt(self.indexes.len() as u64).consensus_encode(&mut s)?;
+        // Indices are differentially encoded: each index is stored as the
+        // difference to the previous index minus one.
+        let mut next_index = 0;
+        for index in self.indexes.iter() {
+            len += VarInt(index.saturating_sub(next_index)).consensus_encode(&mut s)?;
+            next_index = index.saturating_add(1);
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for BlockTransactionsRequest {
+    fn consensus_decode<D: io::Read>(mut d: D) -> Result<Self, encode::Error> {
+        let block_hash = Decodable::consensus_decode(&mut d)?;
+        let count = read_transaction_count(&mut d)?;
+        let mut indexes = Vec::with_capacity(count as usize);
+        let mut next_index: u64 = 0;
+        for _ in 0..count {
+            let index = next_index
+                .checked_add(VarInt::consensus_decode(&mut d)?.0)
+                .filter(|index| *index < MAX_TRANSACTIONS_PER_BLOCK)
+                .ok_or(encode::Error::ParseFailed(
+                    "transaction index out of bounds",
+                ))?;
+            indexes.push(index);
+            next_index = index + 1;
+        }
+        Ok(Self {
+            block_hash,
+            indexes,
+        })
+    }
+}
+
+impl Encodable for BlockTransactions {
+    fn consensus_encode<S: io::Write>(&self, mut s: S) -> Result<usize, io::Error> {
+        let mut len = self.block_hash.consensus_encode(&mut s)?;
+        len += self.transactions.consensus_encode(&mut s)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for BlockTransactions {
+    fn consensus_decode<D: io::Read>(mut d: D) -> Result<Self, encode::Error> {
+        Ok(Self {
+            block_hash: Decodable::consensus_decode(&mut d)?,
+            transactions: Decodable::consensus_decode(&mut d)?,
+        })
+    }
+}
+
+/// The outcome of a block reconstructed from the prefilled transactions only.
+pub const RECONSTRUCTED: &str = "reconstructed";
+/// The outcome of a block reconstructed after a `getblocktxn` round trip.
+pub const RECONSTRUCTED_AFTER_GETBLOCKTXN: &str = "reconstructed_after_getblocktxn";
+/// The outcome of a reconstruction that failed or timed out, after which the
+/// full block was requested instead.
+pub const FAILED: &str = "failed";
+
+/// Counts the compact blocks received and the outcomes of their
+/// reconstructions. The success rate is the share of the reconstructions
+/// with any other outcome than [`FAILED`].
+#[derive(Clone)]
+pub struct CompactBlockMetrics {
+    /// The number of `cmpctblock` messages received.
+    pub received: IntCounter,
+    /// The number of completed reconstructions, by outcome.
+    pub reconstructions: IntCounterVec,
+}
+
+impl CompactBlockMetrics {
+    pub fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            received: metrics_registry.int_counter(
+                "btc_adapter_compact_blocks_received_total",
+                "Total number of cmpctblock messages received from Bitcoin nodes.",
+            ),
+            reconstructions: metrics_registry.int_counter_vec(
+                "btc_adapter_compact_block_reconstructions_total",
+                "Total number of completed compact block reconstructions, by outcome.",
+                &["outcome"],
+            ),
+        }
+    }
+
+    /// Counts `count` reconstructions with the given `outcome`.
+    pub fn observe_reconstructions(&self, outcome: &str, count: u64) {
+        self.reconstructions
+            .with_label_values(&[outcome])
+            .inc_by(count);
+    }
+}
+
+/// A block that is being reconstructed from a compact block.
+///
+/// The adapter does not keep a mempool, so every transaction of the block
+/// that is not prefilled is requested from the peer with a `getblocktxn`
+/// message. The gain over a `getdata` request for the full block is latency:
+/// in high-bandwidth mode, new blocks are pushed without waiting for the
+/// `inv`/`headers` and `getdata` round trips.
+#[derive(Clone, Debug)]
+pub struct PartialBlock {
+    header: BlockHeader,
+    transactions: Vec<Option<Transaction>>,
+}
+
+impl PartialBlock {
+    /// Places the prefilled transactions of `compact_block` in an otherwise
+    /// empty block.
+    pub fn new(compact_block: HeaderAndShortIds) -> Result<Self, CompactBlockError> {
+        let transaction_count =
+            compact_block.short_ids.len() as u64 + compact_block.prefilled_txs.len() as u64;
+        if transaction_count > MAX_TRANSACTIONS_PER_BLOCK {
+            return Err(CompactBlockError::TooManyTransactions(transaction_count));
+        }
+
+        let mut transactions = vec![None; transaction_count as usize];
+        let mut next_index: u64 = 0;
+        for prefilled in compact_block.prefilled_txs {
+            let index = next_index.saturating_add(prefilled.differential_index);
+            match transactions.get_mut(index as usize) {
+                Some(slot) => *slot = Some(prefilled.tx),
+                None => return Err(CompactBlockError::PrefilledIndexOutOfBounds(index)),
+            }
+            next_index = index + 1;
+        }
+
+        Ok(Self {
+            header: compact_block.header,
+            transactions,
+        })
+    }
+
+    /// Returns the hash of the block.
+    pub fn block_hash(&self) -> BlockHash {
+        self.header.block_hash()
+    }
+
+    /// Returns the indices of the transactions that are still missing.
+    pub fn missing_indexes(&self) -> Vec<u64> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_none())
+            .map(|(index, _)| index as u64)
+            .collect()
+    }
+
+    /// Fills the missing transactions, in order, with `missing_transactions`
+    /// and returns the reconstructed block.
+    ///
+    /// The witness data is removed from the transactions, such that the block
+    /// is identical to the one returned for a `getdata` request of the block.
+    pub fn reconstruct(
+        self,
+        missing_transactions: Vec<Transaction>,
+    ) -> Result<Block, CompactBlockError> {
+        let expected = self.transactions.iter().filter(|tx| tx.is_none()).count();
+        if expected != missing_transactions.len() {
+            return Err(CompactBlockError::UnexpectedTransactionCount {
+                expected,
+                received: missing_transactions.len(),
+            });
+        }
+
+        let mut missing_transactions = missing_transactions.into_iter();
+        let txdata = self
+            .transactions
+            .into_iter()
+            .map(|tx| {
+                let mut tx = tx
+                    .or_else(|| missing_transactions.next())
+                    .expect("the number of missing transactions was checked");
+                for input in tx.input.iter_mut() {
+                    input.witness.clear();
+                }
+                tx
+            })
+            .collect();
+
+        let block = Block {
+            header: self.header,
+            txdata,
+        };
+        if !block.check_merkle_root() {
+            return Err(CompactBlockError::MerkleRootMismatch);
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::test_common::{TestState, BLOCK_1_ENCODED};
+    use hex::FromHex;
+
+    fn compact_block_with(
+        block: &Block,
+        prefilled: &[u64],
+    ) -> (HeaderAndShortIds, Vec<Transaction>) {
+        let mut prefilled_txs = vec![];
+        let mut missing = vec![];
+        let mut next_index = 0;
+        for (index, tx) in block.txdata.iter().enumerate() {
+            let index = index as u64;
+            if prefilled.contains(&index) {
+                prefilled_txs.push(PrefilledTransaction {
+                    differential_index: index - next_index,
+                    tx: tx.clone(),
+                });
+                next_index = index + 1;
+            } else {
+                missing.push(tx.clone());
+            }
+        }
+        let compact_block = HeaderAndShortIds {
+            header: block.header,
+            nonce: 42,
+            short_ids: vec![[0; SHORT_ID_SIZE]; missing.len()],
+            prefilled_txs,
+        };
+        (compact_block, missing)
+    }
+
+    /// Creates a block with three transactions, all copies of the coinbase
+    /// transaction of block 1 with different lock times.
+    fn block_with_three_transactions() -> Block {
+        let mut block = TestState::setup().block_1;
+        let coinbase = block.txdata[0].clone();
+        for lock_time in 1..3 {
+            let mut tx = coinbase.clone();
+            tx.lock_time = lock_time;
+            block.txdata.push(tx);
+        }
+        block.header.merkle_root = block.merkle_root();
+        block
+    }
+
+    #[test]
+    fn test_messages_roundtrip_through_network_messages() {
+        let block = block_with_three_transactions();
+        let (compact_block, missing) = compact_block_with(&block, &[0, 2]);
+        let messages = vec![
+            CompactBlockMessage::SendCompact(SendCompact {
+                announce: true,
+                version: COMPACT_BLOCKS_VERSION,
+            }),
+            CompactBlockMessage::CompactBlock(compact_block),
+            CompactBlockMessage::GetBlockTransactions(BlockTransactionsRequest {
+                block_hash: block.block_hash(),
+                indexes: vec![1, 5, 6, 100],
+            }),
+            CompactBlockMessage::BlockTransactions(BlockTransactions {
+                block_hash: block.block_hash(),
+                transactions: missing,
+            }),
+        ];
+
+        for message in messages {
+            let network_message = message.clone().into_network_message();
+            assert!(matches!(
+                &network_message,
+                NetworkMessage::Unknown { command, .. } if is_compact_block_command(command)
+            ));
+            let decoded = CompactBlockMessage::from_network_message(&network_message)
+                .expect("should be a compact block message")
+                .expect("should decode");
+            assert_eq!(decoded, message);
+        }
+    }
+
+    #[test]
+    fn test_other_messages_are_not_compact_block_messages() {
+        assert!(CompactBlockMessage::from_network_message(&NetworkMessage::Verack).is_none());
+        let unknown = NetworkMessage::Unknown {
+            command: CommandString::try_from("foo").unwrap(),
+            payload: vec![],
+        };
+        assert!(CompactBlockMessage::from_network_message(&unknown).is_none());
+    }
+
+    #[test]
+    fn test_sendcmpct_encoding() {
+        let message = SendCompact {
+            announce: true,
+            version: 2,
+        };
+        assert_eq!(
+            serialize(&message),
+            Vec::from_hex("010200000000000000").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_oversized_compact_block_is_rejected() {
+        let header = TestState::setup().block_1.header;
+        let mut payload = serialize(&header);
+        payload.extend(serialize(&42u64));
+        payload.extend(serialize(&VarInt(MAX_TRANSACTIONS_PER_BLOCK + 1)));
+        assert!(deserialize::<HeaderAndShortIds>(&payload).is_err());
+    }
+
+    #[test]
+    fn test_reconstruct_block_from_prefilled_transactions() {
+        let block: Block = deserialize(&Vec::from_hex(BLOCK_1_ENCODED).unwrap()).unwrap();
+        let (compact_block, missing) = compact_block_with(&block, &[0]);
+        assert!(missing.is_empty());
+
+        let partial_block = PartialBlock::new(compact_block).unwrap();
+
+        assert!(partial_block.missing_indexes().is_empty());
+        assert_eq!(partial_block.reconstruct(vec![]), Ok(block));
+    }
+
+    #[test]
+    fn test_reconstruct_block_with_missing_transactions() {
+        let block = block_with_three_transactions();
+        let (compact_block, missing) = compact_block_with(&block, &[0, 2]);
+
+        let partial_block = PartialBlock::new(compact_block).unwrap();
+
+        assert_eq!(partial_block.missing_indexes(), vec![1]);
+        assert_eq!(partial_block.reconstruct(missing), Ok(block));
+    }
+
+    #[test]
+    fn test_reconstruct_fails_on_wrong_transactions() {
+        let block = block_with_three_transactions();
+        let (compact_block, missing) = compact_block_with(&block, &[0]);
+
+        let partial_block = PartialBlock::new(compact_block).unwrap();
+        assert_eq!(
+            partial_block.clone().reconstruct(missing[..1].to_vec()),
+            Err(CompactBlockError::UnexpectedTransactionCount {
+                expected: 2,
+                received: 1
+            })
+        );
+        let swapped = vec![missing[1].clone(), missing[0].clone()];
+        assert_eq!(
+            partial_block.reconstruct(swapped),
+            Err(CompactBlockError::MerkleRootMismatch)
+        );
+    }
+
+    #[test]
+    fn test_prefilled_index_out_of_bounds_is_rejected() {
+        let block = block_with_three_transactions();
+        let (mut compact_block, _) = compact_block_with(&block, &[0]);
+        compact_block.prefilled_txs[0].differential_index = 3;
+
+        assert_eq!(
+            PartialBlock::new(compact_block).unwrap_err(),
+            CompactBlockError::PrefilledIndexOutOfBounds(3)
+        );
+    }
+}
@@ -1,12 +1,79 @@
-use std::net::SocketAddr;
+use std::{collections::HashMap, net::SocketAddr};
 
-use bitcoin::Network;
+use crate::common::BlockHeight;
+
+use bitcoin::{BlockHash, Network};
+use ic_adapter_config::AdapterConfig;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The possible errors found when validating a [Config](Config).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The maximum number of connections must be at least 1.
+    #[error("max_connections must be at least 1")]
+    NoConnections,
+    /// The minimum number of connections must not exceed the maximum number of connections.
+    #[error("min_connections ({min}) must not exceed max_connections ({max})")]
+    MinConnectionsExceedMax {
+        /// The configured minimum number of connections.
+        min: usize,
+        /// The configured maximum number of connections.
+        max: usize,
+    },
+    /// The maximum number of connections per network group must be at least 1.
+    #[error("max_connections_per_network_group must be at least 1")]
+    NoConnectionsPerNetworkGroup,
+    /// A peer can only be evicted after at least one timed out request.
+    #[error("max_timed_out_requests of the eviction policy must be at least 1")]
+    EvictionWithoutTimeouts,
+    /// A daily egress cap of zero bytes would prevent the adapter from syncing anything.
+    #[error("max_daily_egress_bytes must be at least 1")]
+    NoDailyEgress,
+    /// Headers can not be validated without threads.
+    #[error("header_validation_threads must be at least 1")]
+    NoHeaderValidationThreads,
+    /// A checkpoint disagrees with another checkpoint at the same height.
+    #[error("the checkpoints at height {0} disagree on the block hash")]
+    ConflictingCheckpoints(BlockHeight),
+}
+
+/// A block of the chain that the adapter syncs, identified by its height and hash.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The height of the block.
+    pub height: BlockHeight,
+    /// The hash of the block.
+    pub hash: BlockHash,
+}
+
+/// This enum determines what the adapter does with peers that stall the synchronization
+/// of blocks, i.e., that do not respond to `getdata` requests in time.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Stalling peers are kept. Blocks requested from them are requested again, possibly from
+    /// other peers.
+    Never,
+    /// A peer is disconnected, and discarded from the address book, once the given number of
+    /// consecutive requests to it have timed out.
+    AfterTimeouts {
+        /// The number of consecutive timed out requests after which a peer is evicted.
+        max_timed_out_requests: u32,
+    },
+}
+
+impl Default for EvictionPolicy {
+    fn default() -> Self {
+        EvictionPolicy::Never
+    }
+}
 
 /// This struct contains configuration options for the BTC Adapter.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Config {
     /// The type of Bitcoin network we plan to communicate to (e.g. Mainnet, Testnet, etc.).
+    #[serde(default = "default_network")]
     pub network: Network,
     /// A list of DNS seeds for address discovery.
     #[serde(default)]
@@ -26,12 +93,64 @@ pub struct Config {
     /// that support IPv6.
     #[serde(default)]
     pub ipv6_only: bool,
+    /// When this field is set to `true`, the adapter asks its peers to announce new blocks
+    /// with compact blocks (BIP152) and reconstructs the blocks from them.
+    #[serde(default)]
+    pub compact_blocks: bool,
+    /// The number of connections to Bitcoin nodes the adapter needs in order to broadcast
+    /// transactions. Only used if DNS seeds are provided. Otherwise, the adapter connects to
+    /// all of the provided nodes, up to `max_connections`.
+    #[serde(default = "default_min_connections")]
+    pub min_connections: usize,
+    /// The maximum number of outbound connections to Bitcoin nodes.
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+    /// The maximum number of connections to nodes of the same network group (the same /16
+    /// subnet for IPv4, the same /32 subnet for IPv6). If not set, the number of connections
+    /// per network group is not limited.
+    #[serde(default)]
+    pub max_connections_per_network_group: Option<usize>,
+    /// This field determines what the adapter does with peers that stall the synchronization
+    /// of blocks.
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
+    /// The maximum number of bytes the adapter sends to Bitcoin nodes per day. Once the cap
+    /// is reached, the adapter only syncs headers until the day is over. If not set, the
+    /// egress is not limited.
+    #[serde(default)]
+    pub max_daily_egress_bytes: Option<u64>,
+    /// Checkpoints of the chain of `network`, in addition to the ones built into the adapter.
+    /// Headers must match the checkpoints. Until the adapter has synced up to the highest of
+    /// these checkpoints, the proof of work of the received headers is checked in parallel,
+    /// which speeds up the initial sync of the headers.
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    /// The number of threads that check the proof of work of the headers up to the highest
+    /// of the `checkpoints`.
+    #[serde(default = "default_header_validation_threads")]
+    pub header_validation_threads: usize,
+}
+
+fn default_network() -> Network {
+    Network::Bitcoin
 }
 
 fn default_idle_seconds() -> u64 {
     5
 }
 
+fn default_min_connections() -> usize {
+    2
+}
+
+fn default_max_connections() -> usize {
+    5
+}
+
+fn default_header_validation_threads() -> usize {
+    4
+}
+
 impl Config {
     /// This function returns the port to use based on the Bitcoin network provided.
     pub fn port(&self) -> u16 {
@@ -41,17 +160,82 @@ impl Config {
             _ => 8333,
         }
     }
+
+    /// This function checks that the connection settings are consistent.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_connections == 0 {
+            return Err(ConfigError::NoConnections);
+        }
+        if self.min_connections > self.max_connections {
+            return Err(ConfigError::MinConnectionsExceedMax {
+                min: self.min_connections,
+                max: self.max_connections,
+            });
+        }
+        if self.max_connections_per_network_group == Some(0) {
+            return Err(ConfigError::NoConnectionsPerNetworkGroup);
+        }
+        if let EvictionPolicy::AfterTimeouts {
+            max_timed_out_requests: 0,
+        } = self.eviction_policy
+        {
+            return Err(ConfigError::EvictionWithoutTimeouts);
+        }
+        if self.max_daily_egress_bytes == Some(0) {
+            return Err(ConfigError::NoDailyEgress);
+        }
+        if self.header_validation_threads == 0 {
+            return Err(ConfigError::NoHeaderValidationThreads);
+        }
+        let mut checkpoints = ic_btc_validation::checkpoints(&self.network);
+        for checkpoint in &self.checkpoints {
+            if let Some(hash) = checkpoints.insert(checkpoint.height, checkpoint.hash) {
+                if hash != checkpoint.hash {
+                    return Err(ConfigError::ConflictingCheckpoints(checkpoint.height));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the built-in checkpoints of `network` together with the configured ones.
+    pub fn all_checkpoints(&self) -> HashMap<BlockHeight, BlockHash> {
+        let mut checkpoints = ic_btc_validation::checkpoints(&self.network);
+        checkpoints.extend(
+            self.checkpoints
+                .iter()
+                .map(|checkpoint| (checkpoint.height, checkpoint.hash)),
+        );
+        checkpoints
+    }
+}
+
+impl AdapterConfig for Config {
+    const ENV_PREFIX: &'static str = "IC_BTC_ADAPTER_";
+    type ValidationError = ConfigError;
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Config::validate(self)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             dns_seeds: Default::default(),
-            network: Network::Bitcoin,
+            network: default_network(),
             socks_proxy: Default::default(),
             nodes: vec![],
-            idle_seconds: 5,
+            idle_seconds: default_idle_seconds(),
             ipv6_only: false,
+            compact_blocks: false,
+            min_connections: default_min_connections(),
+            max_connections: default_max_connections(),
+            max_connections_per_network_group: None,
+            eviction_policy: EvictionPolicy::default(),
+            max_daily_egress_bytes: None,
+            checkpoints: vec![],
+            header_validation_threads: default_header_validation_threads(),
         }
     }
 }
@@ -60,6 +244,7 @@ impl Default for Config {
 pub mod test {
 
     use super::*;
+    use ic_adapter_config::{load_config_with_env, LoadConfigError};
 
     pub struct ConfigBuilder {
         config: Config,
@@ -92,8 +277,192 @@ pub mod test {
             self
         }
 
+        pub fn with_compact_blocks(mut self, compact_blocks: bool) -> Self {
+            self.config.compact_blocks = compact_blocks;
+            self
+        }
+
+        pub fn with_connection_limits(mut self, min: usize, max: usize) -> Self {
+            self.config.min_connections = min;
+            self.config.max_connections = max;
+            self
+        }
+
+        pub fn with_max_connections_per_network_group(mut self, max: usize) -> Self {
+            self.config.max_connections_per_network_group = Some(max);
+            self
+        }
+
+        pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+            self.config.eviction_policy = eviction_policy;
+            self
+        }
+
+        pub fn with_max_daily_egress_bytes(mut self, max: u64) -> Self {
+            self.config.max_daily_egress_bytes = Some(max);
+            self
+        }
+
+        pub fn with_checkpoints(mut self, checkpoints: Vec<Checkpoint>) -> Self {
+            self.config.checkpoints = checkpoints;
+            self
+        }
+
+        pub fn with_header_validation_threads(mut self, threads: usize) -> Self {
+            self.config.header_validation_threads = threads;
+            self
+        }
+
         pub fn build(self) -> Config {
             self.config
         }
     }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(ConfigBuilder::new().build().validate(), Ok(()));
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_connection_limits(0, 0)
+                .build()
+                .validate(),
+            Err(ConfigError::NoConnections)
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_connection_limits(3, 2)
+                .build()
+                .validate(),
+            Err(ConfigError::MinConnectionsExceedMax { min: 3, max: 2 })
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_max_connections_per_network_group(0)
+                .build()
+                .validate(),
+            Err(ConfigError::NoConnectionsPerNetworkGroup)
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_eviction_policy(EvictionPolicy::AfterTimeouts {
+                    max_timed_out_requests: 0
+                })
+                .build()
+                .validate(),
+            Err(ConfigError::EvictionWithoutTimeouts)
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_max_daily_egress_bytes(0)
+                .build()
+                .validate(),
+            Err(ConfigError::NoDailyEgress)
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_header_validation_threads(0)
+                .build()
+                .validate(),
+            Err(ConfigError::NoHeaderValidationThreads)
+        );
+        assert_eq!(
+            ConfigBuilder::new()
+                .with_network(Network::Bitcoin)
+                .with_checkpoints(vec![Checkpoint {
+                    height: 11_111,
+                    hash: BlockHash::default(),
+                }])
+                .build()
+                .validate(),
+            Err(ConfigError::ConflictingCheckpoints(11_111))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_checkpoints() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "network": "regtest",
+                "checkpoints": [
+                    {
+                        "height": 10,
+                        "hash": "0000000000000000000000000000000000000000000000000000000000000000"
+                    }
+                ],
+                "header_validation_threads": 8
+            }"#,
+        )
+        .expect("should deserialize");
+        assert_eq!(
+            config.checkpoints,
+            vec![Checkpoint {
+                height: 10,
+                hash: BlockHash::default(),
+            }]
+        );
+        assert_eq!(config.header_validation_threads, 8);
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_deserialize_connection_settings() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "network": "bitcoin",
+                "min_connections": 4,
+                "max_connections": 8,
+                "max_connections_per_network_group": 1,
+                "eviction_policy": { "after_timeouts": { "max_timed_out_requests": 3 } },
+                "max_daily_egress_bytes": 1000000
+            }"#,
+        )
+        .expect("should deserialize");
+        assert_eq!(config.min_connections, 4);
+        assert_eq!(config.max_connections, 8);
+        assert_eq!(config.max_connections_per_network_group, Some(1));
+        assert_eq!(
+            config.eviction_policy,
+            EvictionPolicy::AfterTimeouts {
+                max_timed_out_requests: 3
+            }
+        );
+        assert_eq!(config.max_daily_egress_bytes, Some(1_000_000));
+
+        let config: Config =
+            serde_json::from_str(r#"{ "network": "bitcoin" }"#).expect("should deserialize");
+        assert_eq!(config.min_connections, 2);
+        assert_eq!(config.max_connections, 5);
+        assert_eq!(config.max_connections_per_network_group, None);
+        assert_eq!(config.eviction_policy, EvictionPolicy::Never);
+        assert_eq!(config.max_daily_egress_bytes, None);
+
+        let config: Config = serde_json::from_str("{}").expect("should deserialize");
+        assert_eq!(config.network, Network::Bitcoin);
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let env = vec![
+            ("IC_BTC_ADAPTER_NETWORK".to_string(), "testnet".to_string()),
+            (
+                "IC_BTC_ADAPTER_MAX_CONNECTIONS".to_string(),
+                "8".to_string(),
+            ),
+        ];
+        let config: Config = load_config_with_env(None, env).expect("should load");
+        assert_eq!(config.network, Network::Testnet);
+        assert_eq!(config.max_connections, 8);
+        assert_eq!(config.min_connections, default_min_connections());
+
+        let env = vec![(
+            "IC_BTC_ADAPTER_MIN_CONNECTIONS".to_string(),
+            "9".to_string(),
+        )];
+        assert!(matches!(
+            load_config_with_env::<Config>(None, env),
+            Err(LoadConfigError::Invalid(
+                ConfigError::MinConnectionsExceedMax { min: 9, max: 5 }
+            ))
+        ));
+    }
 }
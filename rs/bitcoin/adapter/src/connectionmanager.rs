@@ -24,8 +24,10 @@ use crate::{
     addressbook::{
         validate_services, AddressBook, AddressBookError, AddressEntry, AddressTimestamp,
     },
+    bandwidth::BandwidthAccounting,
     common::DEFAULT_CHANNEL_BUFFER_SIZE,
     common::*,
+    compact_block::{is_compact_block_command, COMPACT_BLOCKS_PROTOCOL_VERSION},
     connection::{Connection, ConnectionConfig, ConnectionState, PingState},
     stream::{StreamConfig, StreamEvent, StreamEventKind},
     Channel, ChannelError, Command, Config, HasHeight, ProcessEvent, ProcessEventError,
@@ -100,16 +102,21 @@ pub struct ConnectionManager {
     stream_event_sender: Sender<StreamEvent>,
     /// This field is used for the version nonce generation.
     rng: StdRng,
+    /// This field determines whether or not the adapter announces support for compact blocks
+    /// in its `version` message.
+    compact_blocks: bool,
+    /// This field is used to account the bytes the streams exchange with BTC nodes.
+    bandwidth: BandwidthAccounting,
 }
 
 impl ConnectionManager {
     /// This function is used to create a new connection manager with a provided config.
-    pub fn new(config: &Config, logger: Logger) -> Self {
+    pub fn new(config: &Config, logger: Logger, bandwidth: BandwidthAccounting) -> Self {
         let address_book = AddressBook::new(config, logger.clone());
         let (stream_event_sender, stream_event_receiver) =
             channel::<StreamEvent>(DEFAULT_CHANNEL_BUFFER_SIZE);
 
-        let (min_connections, max_connections) = connection_limits(&address_book);
+        let (min_connections, max_connections) = connection_limits(&address_book, config);
 
         Self {
             initial_address_discovery: !address_book.has_enough_addresses(),
@@ -124,6 +131,8 @@ impl ConnectionManager {
             socks_proxy: config.socks_proxy,
             stream_event_sender,
             stream_event_receiver,
+            compact_blocks: config.compact_blocks,
+            bandwidth,
         }
     }
 
@@ -149,8 +158,14 @@ impl ConnectionManager {
     {
         self.current_height = has_height_impl.get_height();
 
-        if let Err(ConnectionManagerError::AddressBook(err)) = self.manage_connections(handle) {
-            slog::error!(self.logger, "{}", err);
+        match self.manage_connections(handle) {
+            // All network groups are at capacity, which is expected if the number of
+            // connections per network group is limited.
+            Err(ConnectionManagerError::AddressBook(
+                err @ AddressBookError::NetworkGroupsExhausted,
+            )) => slog::debug!(self.logger, "{}", err),
+            Err(ConnectionManagerError::AddressBook(err)) => slog::error!(self.logger, "{}", err),
+            _ => {}
         }
     }
 
@@ -162,6 +177,9 @@ impl ConnectionManager {
         }
         self.reap_disconnected();
         self.address_book.clear();
+        // The addresses discovered from the seeds are cleared, so they have to be discovered
+        // again when the adapter becomes active.
+        self.initial_address_discovery = !self.address_book.has_enough_addresses();
     }
 
     /// This function will remove disconnects and establish new connections.
@@ -305,6 +323,7 @@ impl ConnectionManager {
             network_message_receiver,
             socks_proxy,
             stream_event_sender,
+            bandwidth: self.bandwidth.clone(),
         };
         let handle = task::spawn(async move {
             handle(stream_config).await;
@@ -343,7 +362,7 @@ impl ConnectionManager {
         let receiver = Address::new(addr, ServiceFlags::NETWORK | ServiceFlags::NETWORK_LIMITED);
         let nonce: u64 = self.rng.gen();
         let user_agent = String::from(USER_AGENT);
-        let message = NetworkMessage::Version(VersionMessage::new(
+        let mut version_message = VersionMessage::new(
             services,
             timestamp as i64,
             receiver,
@@ -352,7 +371,12 @@ impl ConnectionManager {
             user_agent,
             // The height the adapter believes is the active tip.
             self.current_height as i32,
-        ));
+        );
+        if self.compact_blocks {
+            // Peers only announce compact blocks to nodes with a recent enough version.
+            version_message.version = COMPACT_BLOCKS_PROTOCOL_VERSION;
+        }
+        let message = NetworkMessage::Version(version_message);
 
         slog::debug!(self.logger, "Sending version to {}", addr);
         self.send_to(addr, message)
@@ -537,6 +561,12 @@ impl ConnectionManager {
         command: &CommandString,
         payload: &[u8],
     ) -> Result<(), ProcessEventError> {
+        // Compact block messages are unknown to rust-bitcoin, but they are handled by the
+        // blockchain manager.
+        if is_compact_block_command(command) {
+            return Ok(());
+        }
+
         // If we receive an unknown message from a BTC node, the adapter should log
         // the message for further analysis.
         slog::warn!(
@@ -574,6 +604,11 @@ impl Channel for ConnectionManager {
         Ok(())
     }
 
+    fn evict(&mut self, address: SocketAddr) {
+        slog::warn!(self.logger, "Evicting connection {}", address);
+        self.discard(address);
+    }
+
     /// This function provides an iterator to the currently available connections.
     fn available_connections(&self) -> Vec<SocketAddr> {
         self.connections
@@ -641,13 +676,14 @@ impl ProcessEvent for ConnectionManager {
     }
 }
 
-fn connection_limits(address_book: &AddressBook) -> (usize, usize) {
+fn connection_limits(address_book: &AddressBook, config: &Config) -> (usize, usize) {
     if address_book.has_seeds() {
         // Seeds are available.
-        (2, 5)
+        (config.min_connections, config.max_connections)
     } else {
         // No seeds are available. Can only connect to nodes explicitly provided.
-        (address_book.size(), address_book.size())
+        let connections = address_book.size().min(config.max_connections);
+        (connections, connections)
     }
 }
 
@@ -658,7 +694,10 @@ mod test {
 
     use bitcoin::{network::constants::ServiceFlags, Network};
 
-    use crate::{common::test_common::make_logger, config::test::ConfigBuilder};
+    use crate::{
+        common::test_common::{make_bandwidth_accounting, make_logger},
+        config::test::ConfigBuilder,
+    };
 
     use super::*;
 
@@ -691,7 +730,7 @@ mod test {
         );
         version_message.version = MINIMUM_VERSION_NUMBER - 1;
 
-        let manager = ConnectionManager::new(&config, make_logger());
+        let manager = ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         assert!(!manager.validate_received_version(&version_message));
     }
 
@@ -715,7 +754,8 @@ mod test {
         let config = ConfigBuilder::new()
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let mut manager = ConnectionManager::new(&config, make_logger());
+        let mut manager =
+            ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         manager.current_height = 100_000;
 
         assert!(!manager.validate_received_version(&version_message));
@@ -741,7 +781,7 @@ mod test {
         let config = ConfigBuilder::new()
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let manager = ConnectionManager::new(&config, make_logger());
+        let manager = ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
 
         assert!(!manager.validate_received_version(&version_message));
     }
@@ -832,7 +872,8 @@ mod test {
             .with_network(Network::Signet)
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let mut manager = ConnectionManager::new(&config, make_logger());
+        let mut manager =
+            ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         let has_height_impl = HasHeightImpl {};
         let addr = SocketAddr::from_str("127.0.0.1:8333").expect("invalid address");
         assert!(manager.initial_address_discovery);
@@ -888,7 +929,8 @@ mod test {
         let config = ConfigBuilder::new()
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let mut manager = ConnectionManager::new(&config, make_logger());
+        let mut manager =
+            ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         let timestamp = SystemTime::now() - Duration::from_secs(60);
         let (writer, _) = unbounded_channel();
         runtime.block_on(async {
@@ -925,7 +967,8 @@ mod test {
         let config = ConfigBuilder::new()
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let mut manager = ConnectionManager::new(&config, make_logger());
+        let mut manager =
+            ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         let timestamp1 = SystemTime::now() - Duration::from_secs(SEED_ADDR_RETRIEVED_TIMEOUT_SECS);
         let timestamp2 = SystemTime::now() + Duration::from_secs(SEED_ADDR_RETRIEVED_TIMEOUT_SECS);
         let (writer, _) = unbounded_channel();
@@ -986,7 +1029,8 @@ mod test {
         let config = ConfigBuilder::new()
             .with_dns_seeds(vec![String::from("127.0.0.1")])
             .build();
-        let mut manager = ConnectionManager::new(&config, make_logger());
+        let mut manager =
+            ConnectionManager::new(&config, make_logger(), make_bandwidth_accounting());
         let timestamp1 = SystemTime::now() - Duration::from_secs(SEED_ADDR_RETRIEVED_TIMEOUT_SECS);
         let timestamp2 = SystemTime::now() + Duration::from_secs(SEED_ADDR_RETRIEVED_TIMEOUT_SECS);
         let (writer, _) = unbounded_channel();
@@ -1016,6 +1060,7 @@ mod test {
 
             manager.make_idle();
             assert!(manager.connections.is_empty());
+            assert!(manager.initial_address_discovery);
         });
     }
 }
@@ -14,6 +14,9 @@ mod adapter;
 /// that will be used to create new connections. It also tracks addresses that
 /// are in current use to encourage use from non-utilized addresses.
 mod addressbook;
+/// This module contains the accounting of the bytes exchanged with Bitcoin nodes and the
+/// daily egress cap.
+mod bandwidth;
 /// This module contains method for managing the local Bitcoin ledger,
 /// sending "getheaders", "getdata" messages to Bitcoin peers,
 /// processing the "inv", "headers", "block" messages received from Bitcoin peers, and
@@ -23,6 +26,9 @@ mod blockchainmanager;
 mod blockchainstate;
 /// This module contains constants and types that are shared by many modules.
 mod common;
+/// This module contains the compact block (BIP152) messages and the
+/// reconstruction of blocks from them.
+mod compact_block;
 /// This module contains the basic configuration struct used to start up an
 /// adapter instance.
 mod config;
@@ -49,8 +55,18 @@ mod proto {
 pub use adapter::Adapter;
 pub use cli::Cli;
 use common::BlockHeight;
-pub use config::Config;
+pub use config::{Config, ConfigError, EvictionPolicy};
 pub use proto::btc_adapter_client::BtcAdapterClient;
+pub use proto::btc_adapter_server::{BtcAdapter, BtcAdapterServer};
+#[cfg(feature = "debug_rpc")]
+pub use proto::{
+    btc_adapter_debug_client::BtcAdapterDebugClient, AddressTransaction,
+    GetTransactionsByAddressRequest, GetTransactionsByAddressResponse,
+};
+pub use proto::{
+    get_successors_stream_request, get_successors_stream_response, BlockChunk,
+    GetSuccessorsStreamEnd, GetSuccessorsStreamRequest, GetSuccessorsStreamResponse,
+};
 pub use rpc_server::spawn_grpc_server;
 use stream::StreamEvent;
 
@@ -87,6 +103,10 @@ pub trait Channel {
     /// or to all connections based on the [Command](Command)'s fields.
     fn send(&mut self, command: Command) -> Result<(), ChannelError>;
 
+    /// This method is used to disconnect from a connection that misbehaves
+    /// and to discard its address.
+    fn evict(&mut self, address: SocketAddr);
+
     /// This method is used to retrieve a list of available connections
     /// that have completed the version handshake.
     fn available_connections(&self) -> Vec<SocketAddr>;
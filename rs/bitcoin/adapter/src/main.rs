@@ -1,5 +1,7 @@
 use clap::Clap;
-use ic_btc_adapter::{spawn_grpc_server, Adapter, Cli};
+use ic_adapter_config::{dump_defaults, AdapterSubcommand};
+use ic_btc_adapter::{spawn_grpc_server, Adapter, Cli, Config};
+use ic_metrics::MetricsRegistry;
 use serde_json::to_string_pretty;
 use slog::{error, info, slog_o, Drain, Logger};
 use std::io::stdout;
@@ -12,6 +14,10 @@ use tokio::{
 #[tokio::main]
 pub async fn main() {
     let cli = Cli::parse();
+    if let Some(AdapterSubcommand::DumpDefaults) = cli.command {
+        println!("{}", dump_defaults::<Config>());
+        return;
+    }
     let plain = slog_term::PlainSyncDecorator::new(stdout());
     let drain = slog_term::FullFormat::new(plain)
         .build()
@@ -34,8 +40,13 @@ pub async fn main() {
         to_string_pretty(&config).unwrap()
     );
 
-    let adapter = Arc::new(Mutex::new(Adapter::new(&config, logger.clone())));
-    spawn_grpc_server(Arc::clone(&adapter));
+    let metrics_registry = MetricsRegistry::global();
+    let adapter = Arc::new(Mutex::new(Adapter::new(
+        &config,
+        logger.clone(),
+        &metrics_registry,
+    )));
+    spawn_grpc_server(Arc::clone(&adapter), metrics_registry);
 
     loop {
         adapter.lock().await.tick();
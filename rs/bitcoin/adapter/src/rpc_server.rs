@@ -1,18 +1,44 @@
+#[cfg(feature = "debug_rpc")]
+use crate::proto::{
+    btc_adapter_debug_server::{BtcAdapterDebug, BtcAdapterDebugServer},
+    AddressTransaction, GetTransactionsByAddressRequest, GetTransactionsByAddressResponse,
+};
 use crate::{
     adapter::Adapter,
     blockchainmanager::{GetSuccessorsRequest, GetSuccessorsResponse},
-    proto::btc_adapter_server::{BtcAdapter, BtcAdapterServer},
+    proto::{
+        btc_adapter_server::{BtcAdapter, BtcAdapterServer},
+        get_successors_stream_request, get_successors_stream_response, BlockChunk,
+        GetSuccessorsStreamEnd, GetSuccessorsStreamRequest, GetSuccessorsStreamResponse,
+    },
+};
+use bitcoin::{hashes::Hash, Block, BlockHash, BlockHeader, Transaction};
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use ic_adapter_metrics::{
+    proto::adapter_metrics_server::AdapterMetricsServer, AdapterMetricsExporter,
 };
-use bitcoin::{hashes::Hash, Block, BlockHash, BlockHeader};
 use ic_async_utils::{ensure_single_named_systemd_socket, incoming_from_first_systemd_socket};
+use ic_metrics::MetricsRegistry;
 use ic_protobuf::bitcoin::v1;
+use prost::Message;
+#[cfg(feature = "debug_rpc")]
+use std::str::FromStr;
 use std::{
     convert::{TryFrom, TryInto},
     sync::Arc,
 };
 use tokio::sync::Mutex;
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+/// The maximum size of the data of a [`BlockChunk`], well below the limits of
+/// the size of gRPC messages.
+const MAX_BLOCK_CHUNK_BYTES: usize = 1024 * 1024;
 
+/// The number of messages of a GetSuccessorsStream call that are buffered
+/// until the replica receives them.
+const SUCCESSORS_STREAM_BUFFER: usize = 4;
+
+#[derive(Clone)]
 struct BtcAdapterImpl {
     adapter: Arc<Mutex<Adapter>>,
 }
@@ -28,39 +54,40 @@ fn header_to_proto(header: &BlockHeader) -> v1::BlockHeader {
     }
 }
 
+/// Converts a `Transaction` into a protobuf struct.
+fn transaction_to_proto(t: &Transaction) -> v1::Transaction {
+    v1::Transaction {
+        version: t.version,
+        lock_time: t.lock_time,
+        input: t
+            .input
+            .iter()
+            .map(|i| v1::TxIn {
+                previous_output: Some(v1::OutPoint {
+                    txid: i.previous_output.txid.to_vec(),
+                    vout: i.previous_output.vout,
+                }),
+                script_sig: i.script_sig.to_bytes(),
+                sequence: i.sequence,
+                witness: i.witness.clone(),
+            })
+            .collect(),
+        output: t
+            .output
+            .iter()
+            .map(|o| v1::TxOut {
+                value: o.value,
+                script_pubkey: o.script_pubkey.to_bytes(),
+            })
+            .collect(),
+    }
+}
+
 /// Converts a `Block` into a protobuf struct.
 fn block_to_proto(block: &Block) -> v1::Block {
     v1::Block {
         header: Some(header_to_proto(&block.header)),
-        txdata: block
-            .txdata
-            .iter()
-            .map(|t| v1::Transaction {
-                version: t.version,
-                lock_time: t.lock_time,
-                input: t
-                    .input
-                    .iter()
-                    .map(|i| v1::TxIn {
-                        previous_output: Some(v1::OutPoint {
-                            txid: i.previous_output.txid.to_vec(),
-                            vout: i.previous_output.vout,
-                        }),
-                        script_sig: i.script_sig.to_bytes(),
-                        sequence: i.sequence,
-                        witness: i.witness.clone(),
-                    })
-                    .collect(),
-                output: t
-                    .output
-                    .iter()
-                    .map(|o| v1::TxOut {
-                        value: o.value,
-                        script_pubkey: o.script_pubkey.to_bytes(),
-                    })
-                    .collect(),
-            })
-            .collect(),
+        txdata: block.txdata.iter().map(transaction_to_proto).collect(),
     }
 }
 
@@ -96,8 +123,70 @@ impl From<GetSuccessorsResponse> for v1::GetSuccessorsResponse {
     }
 }
 
+/// Splits the protobuf encoding of `block` into the chunks of the block with
+/// index `block_index` of a GetSuccessorsStream call.
+fn block_chunks(block_index: u32, block: &v1::Block) -> Vec<BlockChunk> {
+    let encoding = block.encode_to_vec();
+    let chunk_count = ((encoding.len() + MAX_BLOCK_CHUNK_BYTES - 1) / MAX_BLOCK_CHUNK_BYTES).max(1);
+    (0..chunk_count)
+        .map(|i| BlockChunk {
+            block_index,
+            data: encoding
+                [i * MAX_BLOCK_CHUNK_BYTES..((i + 1) * MAX_BLOCK_CHUNK_BYTES).min(encoding.len())]
+                .to_vec(),
+            last: i + 1 == chunk_count,
+        })
+        .collect()
+}
+
+/// Sends the chunks of `response` to `outbound`, waiting for the replica to
+/// acknowledge each block on `inbound` before sending the next one. Stops
+/// early, without an error, once the replica stops receiving.
+async fn stream_successors(
+    response: v1::GetSuccessorsResponse,
+    inbound: &mut (impl Stream<Item = Result<GetSuccessorsStreamRequest, Status>> + Unpin),
+    outbound: &mut mpsc::Sender<Result<GetSuccessorsStreamResponse, Status>>,
+) -> Result<(), Status> {
+    for (index, block) in response.blocks.iter().enumerate() {
+        let index = index as u32;
+        for chunk in block_chunks(index, block) {
+            let message = GetSuccessorsStreamResponse {
+                response: Some(get_successors_stream_response::Response::BlockChunk(chunk)),
+            };
+            if outbound.send(Ok(message)).await.is_err() {
+                return Ok(());
+            }
+        }
+        match inbound.next().await.transpose()? {
+            Some(GetSuccessorsStreamRequest {
+                request: Some(get_successors_stream_request::Request::AckBlockIndex(acked)),
+            }) if acked == index => {}
+            // The replica stopped receiving, e.g. at the deadline of its call.
+            None => return Ok(()),
+            Some(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Expected the acknowledgement of block {}",
+                    index
+                )))
+            }
+        }
+    }
+    let end = GetSuccessorsStreamResponse {
+        response: Some(get_successors_stream_response::Response::End(
+            GetSuccessorsStreamEnd {
+                block_count: response.blocks.len() as u32,
+                next: response.next,
+            },
+        )),
+    };
+    let _ = outbound.send(Ok(end)).await;
+    Ok(())
+}
+
 #[tonic::async_trait]
 impl BtcAdapter for BtcAdapterImpl {
+    type GetSuccessorsStreamStream = mpsc::Receiver<Result<GetSuccessorsStreamResponse, Status>>;
+
     async fn get_successors(
         &self,
         request: Request<v1::GetSuccessorsRequest>,
@@ -107,6 +196,33 @@ impl BtcAdapter for BtcAdapterImpl {
         Ok(Response::new(response.into()))
     }
 
+    async fn get_successors_stream(
+        &self,
+        request: Request<Streaming<GetSuccessorsStreamRequest>>,
+    ) -> Result<Response<Self::GetSuccessorsStreamStream>, Status> {
+        let mut inbound = request.into_inner();
+        let request = match inbound.message().await? {
+            Some(GetSuccessorsStreamRequest {
+                request: Some(get_successors_stream_request::Request::Start(request)),
+            }) => request.try_into()?,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "The first message must start the call",
+                ))
+            }
+        };
+        let response = self.adapter.lock().await.get_successors(request);
+        let (mut outbound, receiver) = mpsc::channel(SUCCESSORS_STREAM_BUFFER);
+        tokio::spawn(async move {
+            if let Err(status) =
+                stream_successors(response.into(), &mut inbound, &mut outbound).await
+            {
+                let _ = outbound.send(Err(status)).await;
+            }
+        });
+        Ok(Response::new(receiver))
+    }
+
     async fn send_transaction(
         &self,
         request: Request<v1::SendTransactionRequest>,
@@ -117,20 +233,225 @@ impl BtcAdapter for BtcAdapterImpl {
     }
 }
 
+#[cfg(feature = "debug_rpc")]
+#[tonic::async_trait]
+impl BtcAdapterDebug for BtcAdapterImpl {
+    async fn get_transactions_by_address(
+        &self,
+        request: Request<GetTransactionsByAddressRequest>,
+    ) -> Result<Response<GetTransactionsByAddressResponse>, Status> {
+        let address = bitcoin::Address::from_str(&request.into_inner().address)
+            .map_err(|e| Status::invalid_argument(format!("Failed to parse address: {}", e)))?;
+        let adapter = self.adapter.lock().await;
+        let transactions = adapter
+            .get_transactions_by_address(&address)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "Address {} is not an address of the network of the adapter",
+                    address
+                ))
+            })?
+            .into_iter()
+            .map(|(block_hash, transaction)| AddressTransaction {
+                block_hash: block_hash.to_vec(),
+                txid: transaction.txid().to_vec(),
+                transaction: Some(transaction_to_proto(transaction)),
+            })
+            .collect();
+        Ok(Response::new(GetTransactionsByAddressResponse {
+            transactions,
+        }))
+    }
+}
+
 const IC_BTC_ADAPTER_SOCKET_NAME: &str = "ic-btc-adapter.socket";
 
-/// Spawns in a separate Tokio task the BTC adapter gRPC service.
-pub fn spawn_grpc_server(adapter: Arc<Mutex<Adapter>>) {
+/// Spawns in a separate Tokio task the BTC adapter gRPC service, which also
+/// serves the metrics gathered in `metrics_registry` to the replica. Builds
+/// with the `debug_rpc` feature serve the debug service as well.
+pub fn spawn_grpc_server(adapter: Arc<Mutex<Adapter>>, metrics_registry: MetricsRegistry) {
     // make sure we receive the correct socket from systemd (and only one)
     ensure_single_named_systemd_socket(IC_BTC_ADAPTER_SOCKET_NAME);
 
     tokio::spawn(async move {
         let btc_adapter_impl = BtcAdapterImpl { adapter };
 
-        Server::builder()
+        #[cfg(feature = "debug_rpc")]
+        let btc_adapter_debug_server = BtcAdapterDebugServer::new(btc_adapter_impl.clone());
+        let router = Server::builder()
             .add_service(BtcAdapterServer::new(btc_adapter_impl))
+            .add_service(AdapterMetricsServer::new(AdapterMetricsExporter::new(
+                metrics_registry,
+            )));
+        #[cfg(feature = "debug_rpc")]
+        let router = router.add_service(btc_adapter_debug_server);
+        router
             .serve_with_incoming(incoming_from_first_systemd_socket())
             .await
             .expect("gRPC server crashed");
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn block_of_size(size: usize) -> v1::Block {
+        v1::Block {
+            header: None,
+            txdata: vec![v1::Transaction {
+                version: 1,
+                lock_time: 0,
+                input: vec![],
+                output: vec![v1::TxOut {
+                    value: 0,
+                    script_pubkey: vec![0; size],
+                }],
+            }],
+        }
+    }
+
+    /// This function tests that the chunks of a block reassemble to its
+    /// encoding and that only the last chunk is marked as such.
+    #[test]
+    fn test_block_chunks() {
+        for size in &[0, 100, MAX_BLOCK_CHUNK_BYTES, 3 * MAX_BLOCK_CHUNK_BYTES + 1] {
+            let block = block_of_size(*size);
+            let chunks = block_chunks(7, &block);
+            let encoding = block.encode_to_vec();
+            assert_eq!(
+                chunks.len(),
+                (encoding.len() + MAX_BLOCK_CHUNK_BYTES - 1) / MAX_BLOCK_CHUNK_BYTES
+            );
+            assert!(chunks.iter().all(|c| c.block_index == 7));
+            assert!(chunks.iter().all(|c| c.data.len() <= MAX_BLOCK_CHUNK_BYTES));
+            assert_eq!(
+                chunks.iter().map(|c| c.last).collect::<Vec<_>>(),
+                (0..chunks.len())
+                    .map(|i| i + 1 == chunks.len())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                chunks
+                    .iter()
+                    .flat_map(|c| c.data.clone())
+                    .collect::<Vec<_>>(),
+                encoding
+            );
+        }
+    }
+
+    fn header() -> v1::BlockHeader {
+        v1::BlockHeader {
+            version: 1,
+            prev_blockhash: vec![1; 32],
+            merkle_root: vec![2; 32],
+            time: 3,
+            bits: 4,
+            nonce: 5,
+        }
+    }
+
+    fn ack(index: u32) -> Result<GetSuccessorsStreamRequest, Status> {
+        Ok(GetSuccessorsStreamRequest {
+            request: Some(get_successors_stream_request::Request::AckBlockIndex(index)),
+        })
+    }
+
+    fn expect_chunk(message: Option<Result<GetSuccessorsStreamResponse, Status>>) -> BlockChunk {
+        match message
+            .expect("the stream ended")
+            .expect("the stream failed")
+            .response
+        {
+            Some(get_successors_stream_response::Response::BlockChunk(chunk)) => chunk,
+            other => panic!("Expected a chunk, got {:?}", other),
+        }
+    }
+
+    /// Streams the successors `response` to a fake replica, returning the
+    /// sender of the acknowledgements of the replica, the receiver of the
+    /// messages of the adapter and the task of the stream.
+    fn start_stream(
+        response: v1::GetSuccessorsResponse,
+    ) -> (
+        mpsc::UnboundedSender<Result<GetSuccessorsStreamRequest, Status>>,
+        mpsc::Receiver<Result<GetSuccessorsStreamResponse, Status>>,
+        tokio::task::JoinHandle<Result<(), Status>>,
+    ) {
+        let (acks, mut inbound) = mpsc::unbounded();
+        let (mut outbound, received) = mpsc::channel(SUCCESSORS_STREAM_BUFFER);
+        let task =
+            tokio::spawn(
+                async move { stream_successors(response, &mut inbound, &mut outbound).await },
+            );
+        (acks, received, task)
+    }
+
+    /// This function tests that the chunks of a block are only sent once the
+    /// previous block is acknowledged, and that the end follows the
+    /// acknowledgement of the last block.
+    #[tokio::test]
+    async fn test_stream_successors_waits_for_acknowledgements() {
+        let response = v1::GetSuccessorsResponse {
+            blocks: vec![block_of_size(MAX_BLOCK_CHUNK_BYTES), block_of_size(1)],
+            next: vec![header()],
+        };
+        let (acks, mut received, task) = start_stream(response);
+
+        let first = expect_chunk(received.next().await);
+        let second = expect_chunk(received.next().await);
+        assert_eq!((first.block_index, first.last), (0, false));
+        assert_eq!((second.block_index, second.last), (0, true));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(received.try_next().is_err());
+
+        acks.unbounded_send(ack(0)).unwrap();
+        let chunk = expect_chunk(received.next().await);
+        assert_eq!((chunk.block_index, chunk.last), (1, true));
+
+        acks.unbounded_send(ack(1)).unwrap();
+        match received.next().await.unwrap().unwrap().response {
+            Some(get_successors_stream_response::Response::End(end)) => {
+                assert_eq!(end.block_count, 2);
+                assert_eq!(end.next, vec![header()]);
+            }
+            other => panic!("Expected the end, got {:?}", other),
+        }
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    /// This function tests that the stream fails if the replica acknowledges
+    /// a block other than the one that was sent.
+    #[tokio::test]
+    async fn test_stream_successors_rejects_unexpected_acknowledgement() {
+        let response = v1::GetSuccessorsResponse {
+            blocks: vec![block_of_size(1), block_of_size(1)],
+            next: vec![],
+        };
+        let (acks, mut received, task) = start_stream(response);
+
+        expect_chunk(received.next().await);
+        acks.unbounded_send(ack(1)).unwrap();
+
+        let status = task.await.unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    /// This function tests that the stream stops without an error and without
+    /// an end once the replica stops receiving, e.g. at its deadline.
+    #[tokio::test]
+    async fn test_stream_successors_stops_when_replica_stops() {
+        let response = v1::GetSuccessorsResponse {
+            blocks: vec![block_of_size(1), block_of_size(1)],
+            next: vec![header()],
+        };
+        let (acks, mut received, task) = start_stream(response);
+
+        expect_chunk(received.next().await);
+        drop(acks);
+
+        assert!(task.await.unwrap().is_ok());
+        assert!(received.next().await.is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::bandwidth::BandwidthAccounting;
 use bitcoin::{
     consensus::serialize,
     network::message::RawNetworkMessage,
@@ -67,6 +68,8 @@ pub struct StreamConfig {
     pub socks_proxy: Option<SocketAddr>,
     /// This field is used to send events from the stream back to the network and connection structs.
     pub stream_event_sender: Sender<StreamEvent>,
+    /// This field is used to account the bytes the stream exchanges with the BTC node.
+    pub bandwidth: BandwidthAccounting,
 }
 
 /// This struct is used to represent an event that has occurred within the Stream
@@ -97,6 +100,8 @@ pub enum StreamEventKind {
 pub struct Stream {
     /// This field is used to identity the node that the stream is connected to.
     address: SocketAddr,
+    /// This field is used to account the bytes exchanged with the connected node.
+    bandwidth: BandwidthAccounting,
     /// This field is used as the buffer for reading messages.
     data: Vec<u8>,
     /// This field contains the actual stream handling the network connection.
@@ -123,6 +128,7 @@ impl Stream {
             stream_event_sender,
             magic,
             network_message_receiver,
+            bandwidth,
             ..
         } = config;
 
@@ -156,6 +162,7 @@ impl Stream {
         };
         Ok(Self {
             address,
+            bandwidth,
             data,
             inner,
             magic,
@@ -189,6 +196,7 @@ impl Stream {
                             io::ErrorKind::UnexpectedEof,
                         )));
                     }
+                    self.bandwidth.record_received(self.address, count);
 
                     if let Some(slice) = self.data.get(0..count) {
                         self.unparsed.extend(slice.iter());
@@ -223,6 +231,7 @@ impl Stream {
         self.write(bytes.as_slice())
             .await
             .map_err(StreamError::Io)?;
+        self.bandwidth.record_sent(self.address, bytes.len());
         self.flush().await.map_err(StreamError::Io)
     }
 
@@ -267,6 +276,7 @@ pub async fn handle_stream(config: StreamConfig) {
     let logger = config.logger.clone();
     // Clone the sender here to handle errors that the Stream may return.
     let stream_event_sender = config.stream_event_sender.clone();
+    let bandwidth = config.bandwidth.clone();
     slog::debug!(logger, "Connecting to {}", address);
     let stream_result = Stream::connect(config).await;
     let mut stream = match stream_result {
@@ -305,6 +315,15 @@ pub async fn handle_stream(config: StreamConfig) {
 
     loop {
         if stream.tick().await.is_err() {
+            if let Some(peer) = bandwidth.remove_peer(&address) {
+                slog::debug!(
+                    logger,
+                    "Received {} bytes from and sent {} bytes to {}",
+                    peer.received_bytes,
+                    peer.sent_bytes,
+                    address
+                );
+            }
             stream_event_sender
                 .send(StreamEvent {
                     address,
@@ -200,6 +200,8 @@ mod test {
             Ok(())
         }
 
+        fn evict(&mut self, _: std::net::SocketAddr) {}
+
         fn available_connections(&self) -> Vec<std::net::SocketAddr> {
             vec![]
         }
@@ -45,6 +45,30 @@ pub fn validate_header(
     network: &Network,
     store: &impl HeaderStore,
     header: &BlockHeader,
+) -> Result<(), ValidateHeaderError> {
+    validate_header_with(network, store, header, || {
+        validate_header_pow(network, header)
+    })
+}
+
+/// Validates a header like [validate_header], but takes the result of
+/// [validate_header_pow] on the header instead of checking its proof of work
+/// again, e.g. because the proof of work of many headers was checked in
+/// parallel.
+pub fn validate_header_with_pow_result(
+    network: &Network,
+    store: &impl HeaderStore,
+    header: &BlockHeader,
+    pow_result: Result<(), ValidateHeaderError>,
+) -> Result<(), ValidateHeaderError> {
+    validate_header_with(network, store, header, || pow_result)
+}
+
+fn validate_header_with(
+    network: &Network,
+    store: &impl HeaderStore,
+    header: &BlockHeader,
+    validate_pow: impl FnOnce() -> Result<(), ValidateHeaderError>,
 ) -> Result<(), ValidateHeaderError> {
     let (prev_header, prev_height) = match store.get_header(&header.prev_blockhash) {
         Some(result) => result,
@@ -61,6 +85,25 @@ pub fn validate_header(
         return Err(ValidateHeaderError::DoesNotMatchCheckpoint);
     }
 
+    validate_pow()?;
+
+    // The proof of work is valid for the target of the header, so it is valid
+    // for the target computed from the previous headers iff the targets match.
+    let target = get_next_target(network, store, prev_header, prev_height, header);
+    if header.target() != target {
+        return Err(ValidateHeaderError::InvalidPoWForComputedTarget);
+    }
+
+    Ok(())
+}
+
+/// Validates the proof of work of the header against the target in the
+/// header. Unlike [validate_header], the check does not need the ancestors of
+/// the header, so headers can be checked independently of each other.
+pub fn validate_header_pow(
+    network: &Network,
+    header: &BlockHeader,
+) -> Result<(), ValidateHeaderError> {
     let header_target = header.target();
     if header_target > max_target(network) {
         return Err(ValidateHeaderError::TargetDifficultyAboveMax);
@@ -70,16 +113,6 @@ pub fn validate_header(
         return Err(ValidateHeaderError::InvalidPoWForHeaderTarget);
     }
 
-    let target = get_next_target(network, store, prev_header, prev_height, header);
-    if let Err(err) = header.validate_pow(&target) {
-        match err {
-            bitcoin::Error::BlockBadProofOfWork => println!("bad proof of work"),
-            bitcoin::Error::BlockBadTarget => println!("bad target"),
-            _ => {}
-        };
-        return Err(ValidateHeaderError::InvalidPoWForComputedTarget);
-    }
-
     Ok(())
 }
 
@@ -400,6 +433,18 @@ mod test {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_header_pow() {
+        let mut header_705601 = deserialize_header(MAINNET_HEADER_705601);
+        assert!(validate_header_pow(&Network::Bitcoin, &header_705601).is_ok());
+
+        header_705601.nonce = header_705601.nonce.wrapping_add(1);
+        assert!(matches!(
+            validate_header_pow(&Network::Bitcoin, &header_705601),
+            Err(ValidateHeaderError::InvalidPoWForHeaderTarget)
+        ));
+    }
+
     #[test]
     fn test_is_header_valid() {
         let header_586656 = deserialize_header(MAINNET_HEADER_586656);
@@ -563,6 +608,30 @@ mod test {
         ));
     }
 
+    #[test]
+    fn test_validate_header_with_pow_result() {
+        let header_705600 = deserialize_header(MAINNET_HEADER_705600);
+        let header = deserialize_header(MAINNET_HEADER_705601);
+        let store = SimpleHeaderStore::new(header_705600, 705_600);
+        assert!(
+            validate_header_with_pow_result(&Network::Bitcoin, &store, &header, Ok(())).is_ok()
+        );
+        assert!(matches!(
+            validate_header_with_pow_result(
+                &Network::Bitcoin,
+                &store,
+                &header,
+                Err(ValidateHeaderError::InvalidPoWForHeaderTarget)
+            ),
+            Err(ValidateHeaderError::InvalidPoWForHeaderTarget)
+        ));
+        // The target is still checked against the previous headers.
+        assert!(matches!(
+            validate_header_with_pow_result(&Network::Regtest, &store, &header, Ok(())),
+            Err(ValidateHeaderError::InvalidPoWForComputedTarget)
+        ));
+    }
+
     #[test]
     fn test_is_header_valid_target_difficulty_above_max() {
         let header_705600 = deserialize_header(MAINNET_HEADER_705600);
@@ -1,6 +1,10 @@
 mod constants;
 mod header;
 
-pub use crate::header::{validate_header, HeaderStore, ValidateHeaderError};
+pub use crate::constants::checkpoints;
+pub use crate::header::{
+    validate_header, validate_header_pow, validate_header_with_pow_result, HeaderStore,
+    ValidateHeaderError,
+};
 
 type BlockHeight = u32;
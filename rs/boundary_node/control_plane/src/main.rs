@@ -661,6 +661,7 @@ mod tests {
             impl_version: None,
             impl_hash: None,
             replica_health_status: Some(ReplicaHealthStatus::Healthy),
+            certified_height: None,
         };
         let bin = serde_cbor::to_vec(&a).unwrap();
         let b: HttpStatusResponse = serde_cbor::from_slice(&bin).unwrap();
@@ -696,6 +697,7 @@ mod tests {
             impl_version: None,
             impl_hash: None,
             replica_health_status: Some(ReplicaHealthStatus::Healthy),
+            certified_height: None,
         };
 
         let bin = serde_cbor::to_vec(&a).unwrap();
@@ -1,6 +1,9 @@
 //! An agent to talk to the Internet Computer through the public endpoints.
 use crate::{
-    cbor::{parse_canister_query_response, parse_read_state_response, RequestStatus},
+    cbor::{
+        parse_canister_query_response, parse_read_state_certificate, parse_read_state_response,
+        RequestStatus,
+    },
     http_client::{HttpClient, HttpClientConfig},
 };
 use backoff::backoff::Backoff;
@@ -475,6 +478,32 @@ impl Agent {
         parse_read_state_response(&request_id, cbor)
     }
 
+    /// Reads the given `paths` of the state tree using the `read_state` API,
+    /// and returns the certificate of the response.
+    ///
+    /// The certificate is not verified; the caller has to verify it, e.g.,
+    /// with `ic_certified_vars::verify_read_state_certificate`.
+    pub async fn read_state(
+        &self,
+        canister_id: &CanisterId,
+        paths: &[Path],
+    ) -> Result<Blob, String> {
+        let read_state_body = self
+            .prepare_read_state(paths)
+            .map_err(|e| format!("Failed to prepare read state: {:?}", e))?;
+        let bytes = self
+            .http_client
+            .post_with_response(
+                &self.url,
+                &read_state_path(*canister_id),
+                read_state_body,
+                tokio::time::Instant::now() + self.query_timeout,
+            )
+            .await?;
+        let cbor = bytes_to_cbor(bytes)?;
+        parse_read_state_certificate(cbor)
+    }
+
     async fn get_status(&self) -> Result<HttpStatusResponse, String> {
         let bytes = self
             .http_client
@@ -68,6 +68,13 @@ pub fn parse_read_state_response(
     })
 }
 
+/// Given a CBOR response from a `read_state`, extracts the certificate.
+pub(crate) fn parse_read_state_certificate(message: CBOR) -> Result<Blob, String> {
+    let response = serde_cbor::value::from_value::<HttpReadStateResponse>(message)
+        .map_err(|source| format!("decoding to HttpReadStateResponse failed: {}", source))?;
+    Ok(response.certificate)
+}
+
 /// Given a CBOR response from a `query`, extract the response.
 pub(crate) fn parse_canister_query_response(message: &CBOR) -> Result<RequestStatus, String> {
     let content = match message {
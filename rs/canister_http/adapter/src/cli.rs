@@ -0,0 +1,68 @@
+//! A parser for the command line flags of the adapter.
+use crate::config::{Config, ConfigError};
+use clap::{AppSettings, Clap};
+use ic_adapter_config::{load_config, AdapterSubcommand};
+use slog::Level;
+use std::path::PathBuf;
+
+/// The command line interface of the adapter.
+#[derive(Clap)]
+#[clap(version = "0.0.0", author = "DFINITY team <team@dfinity.org>")]
+#[clap(setting = AppSettings::ColoredHelp)]
+pub struct Cli {
+    /// The path to the JSON config file. If it is not given, the config
+    /// consists of the defaults and the overrides of the
+    /// `IC_CANISTER_HTTP_ADAPTER_*` environment variables.
+    pub config: Option<PathBuf>,
+
+    /// Logs debug messages, regardless of the `log_level` of the config.
+    #[clap(short, long)]
+    pub verbose: bool,
+
+    /// The subcommand to run instead of the adapter, if any.
+    #[clap(subcommand)]
+    pub command: Option<AdapterSubcommand>,
+}
+
+impl Cli {
+    /// Gets the log filter level from the verbose flag, or else from the
+    /// `log_level` of `config`.
+    pub fn get_logging_level(&self, config: &Config) -> Level {
+        if self.verbose {
+            Level::Debug
+        } else {
+            config.log_level.into()
+        }
+    }
+
+    /// Loads the config from the provided `config` argument, applies the
+    /// overrides of the environment and validates the result.
+    pub fn get_config(&self) -> Result<Config, ConfigError> {
+        load_config(self.config.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogLevel;
+
+    #[test]
+    fn test_verbose_overrides_the_log_level_of_the_config() {
+        let cli = |verbose| Cli {
+            config: None,
+            verbose,
+            command: None,
+        };
+        let config = Config {
+            log_level: LogLevel::Warning,
+            ..Config::default()
+        };
+        assert_eq!(cli(false).get_logging_level(&config), Level::Warning);
+        assert_eq!(cli(true).get_logging_level(&config), Level::Debug);
+        assert_eq!(
+            cli(false).get_logging_level(&Config::default()),
+            Level::Info
+        );
+    }
+}
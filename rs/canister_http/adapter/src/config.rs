@@ -0,0 +1,497 @@
+use crate::{
+    http_proxy::HttpProxyRoutes, outbound_headers::OutboundHeaders, rpc_server::PolicyConfigError,
+    spki_pinning::SpkiPins,
+};
+use ic_adapter_config::{load_config, AdapterConfig, LoadConfigError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The possible errors when loading the [`Config`].
+pub type ConfigError = LoadConfigError<PolicyConfigError>;
+
+/// The configuration of the canister HTTP adapter, provided by the node
+/// provider.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// SPKI pins of high-value destinations. Maps a hostname to the set of
+    /// base64-encoded SHA-256 hashes of the SubjectPublicKeyInfo that the
+    /// TLS certificate of the host may have.
+    #[serde(default)]
+    pub spki_pins: BTreeMap<String, BTreeSet<String>>,
+    /// Whether outgoing requests may target addresses that are not publicly
+    /// routable, e.g. loopback or private addresses. Only meant for testing.
+    #[serde(default)]
+    pub allow_private_destinations: bool,
+    /// The maximum window in milliseconds over which requests are spread by
+    /// delaying them pseudo-randomly, capping the window requested by the
+    /// replica.
+    #[serde(default = "default_max_pseudo_random_delay_window_ms")]
+    pub max_pseudo_random_delay_window_ms: u64,
+    /// The delay in milliseconds after which a connection attempt to the
+    /// other address family of a destination is started in parallel, if the
+    /// attempt to its preferred address family has not succeeded yet, as in
+    /// RFC 8305 (Happy Eyeballs). Zero disables the parallel attempts.
+    #[serde(default = "default_connection_attempt_delay_ms")]
+    pub connection_attempt_delay_ms: u64,
+    /// HTTP proxies that outgoing requests can be tunneled through with
+    /// CONNECT requests, by name.
+    #[serde(default)]
+    pub http_proxies: BTreeMap<String, HttpProxyConfig>,
+    /// Rules routing outgoing requests through the HTTP proxies by their
+    /// destination host. The first rule matching the host of a request
+    /// applies, requests matching no rule connect to their destination
+    /// directly.
+    #[serde(default)]
+    pub http_proxy_routes: Vec<HttpProxyRoute>,
+    /// The OTLP collector the spans of the outgoing requests are exported to,
+    /// if any. Only read at startup, not when the config is reloaded.
+    #[serde(default)]
+    pub otlp_exporter: Option<OtlpExporterConfig>,
+    /// The `User-Agent` of outgoing requests whose canister sets none. An
+    /// empty user agent sends none.
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// The stamping of outgoing requests with the ids of the subnet and node,
+    /// which partner APIs can allow-list. Off if not given.
+    #[serde(default)]
+    pub header_stamping: Option<HeaderStampingConfig>,
+    /// Where the adapter accepts the connections of the replica. Only read at
+    /// startup, not when the config is reloaded.
+    #[serde(default)]
+    pub incoming_source: IncomingSource,
+    /// The timeout in milliseconds of establishing the connection to the
+    /// destination of an outgoing request, including the DNS resolution.
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// The timeout in milliseconds of an outgoing request, from the start of
+    /// the connection until the whole response body is received. It does not
+    /// include the pseudo-random delay of the request.
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// The maximum number of requests that are processed concurrently on
+    /// each connection of the replica. Further requests wait until one of
+    /// them completes. Only read at startup, not when the config is
+    /// reloaded.
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// The maximum number of idle connections that are kept open per
+    /// destination host for later requests.
+    #[serde(default = "default_max_idle_connections_per_host")]
+    pub max_idle_connections_per_host: usize,
+    /// The level of the messages that are logged. Only read at startup, not
+    /// when the config is reloaded.
+    #[serde(default)]
+    pub log_level: LogLevel,
+}
+
+/// The source of the connections of the replica to the adapter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncomingSource {
+    /// The socket `ic-canister-http-adapter.socket` that systemd passes to
+    /// the adapter, as in the deployment on the replica image.
+    Systemd,
+    /// A unix domain socket that the adapter binds at the given absolute
+    /// path, e.g. to run the adapter outside of systemd.
+    Path(PathBuf),
+}
+
+impl Default for IncomingSource {
+    fn default() -> Self {
+        IncomingSource::Systemd
+    }
+}
+
+/// The level of the messages that are logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Critical,
+    Error,
+    Warning,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Default for LogLevel {
+    fn default() -> Self {
+        LogLevel::Info
+    }
+}
+
+impl From<LogLevel> for slog::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Critical => slog::Level::Critical,
+            LogLevel::Error => slog::Level::Error,
+            LogLevel::Warning => slog::Level::Warning,
+            LogLevel::Info => slog::Level::Info,
+            LogLevel::Debug => slog::Level::Debug,
+            LogLevel::Trace => slog::Level::Trace,
+        }
+    }
+}
+
+/// Errors returned when the timeouts, limits or incoming source of a config
+/// are invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LimitsConfigError {
+    #[error("connect_timeout_ms must be at least 1")]
+    ZeroConnectTimeout,
+    #[error("request_timeout_ms must be at least 1")]
+    ZeroRequestTimeout,
+    #[error(
+        "connect_timeout_ms of {connect_timeout_ms} exceeds request_timeout_ms of {request_timeout_ms}"
+    )]
+    ConnectTimeoutExceedsRequestTimeout {
+        connect_timeout_ms: u64,
+        request_timeout_ms: u64,
+    },
+    #[error("max_concurrent_requests must be at least 1")]
+    ZeroConcurrentRequests,
+    #[error("the socket path {0:?} of incoming_source is not absolute")]
+    RelativeSocketPath(PathBuf),
+}
+
+/// An HTTP proxy through which the node provider allows egress.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpProxyConfig {
+    /// The url of the proxy, e.g. `http://proxy.example.com:3128`. The
+    /// adapter connects to the proxy over plain HTTP.
+    pub url: String,
+    /// The credentials sent to the proxy, if it requires authentication.
+    #[serde(default)]
+    pub auth: Option<HttpProxyAuth>,
+}
+
+/// The credentials sent to an HTTP proxy in the `Proxy-Authorization`
+/// header.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpProxyAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+// The credentials must not end up in logs.
+impl fmt::Debug for HttpProxyAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::Bearer { .. } => f
+                .debug_struct("Bearer")
+                .field("token", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Routes the requests to a set of hosts through an HTTP proxy, or directly
+/// to their destination.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpProxyRoute {
+    /// Patterns of the hosts the rule applies to: a hostname matches itself,
+    /// `*.example.com` matches the subdomains of `example.com` and `*`
+    /// matches all hosts.
+    pub hosts: Vec<String>,
+    /// The name of the proxy in `http_proxies`, or none to connect directly.
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+/// The ids stamped on outgoing requests in the `X-IC-Subnet` and `X-IC-Node`
+/// headers. Canisters can not set these headers themselves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderStampingConfig {
+    /// The textual principal id of the subnet of the node.
+    pub subnet_id: String,
+    /// The textual principal id of the node, if requests are also stamped
+    /// with it.
+    #[serde(default)]
+    pub node_id: Option<String>,
+}
+
+/// The export of the tracing spans of the outgoing requests, with the
+/// durations of their DNS resolution, connection, TLS handshake, first byte
+/// and total.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtlpExporterConfig {
+    /// The gRPC endpoint of the collector, e.g. `http://localhost:4317`.
+    pub endpoint: String,
+    /// The percentage of the requests whose spans are exported. Values above
+    /// 100 export all requests.
+    #[serde(default = "default_sampling_percentage")]
+    pub sampling_percentage: u8,
+}
+
+impl OtlpExporterConfig {
+    /// The fraction of the requests whose spans are exported.
+    pub fn sampling_ratio(&self) -> f64 {
+        f64::from(self.sampling_percentage.min(100)) / 100.0
+    }
+}
+
+fn default_sampling_percentage() -> u8 {
+    100
+}
+
+fn default_max_pseudo_random_delay_window_ms() -> u64 {
+    2000
+}
+
+fn default_user_agent() -> String {
+    "ic-canister-http-adapter".to_string()
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_concurrent_requests() -> usize {
+    100
+}
+
+fn default_max_idle_connections_per_host() -> usize {
+    8
+}
+
+/// The connection attempt delay recommended by RFC 8305.
+fn default_connection_attempt_delay_ms() -> u64 {
+    250
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            spki_pins: BTreeMap::new(),
+            allow_private_destinations: false,
+            max_pseudo_random_delay_window_ms: default_max_pseudo_random_delay_window_ms(),
+            connection_attempt_delay_ms: default_connection_attempt_delay_ms(),
+            http_proxies: BTreeMap::new(),
+            http_proxy_routes: Vec::new(),
+            otlp_exporter: None,
+            user_agent: default_user_agent(),
+            header_stamping: None,
+            incoming_source: IncomingSource::default(),
+            connect_timeout_ms: default_connect_timeout_ms(),
+            request_timeout_ms: default_request_timeout_ms(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+            max_idle_connections_per_host: default_max_idle_connections_per_host(),
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from the JSON file at `path`, applies the overrides
+    /// of the environment and validates the result.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        load_config(Some(path))
+    }
+
+    /// Checks the timeouts, the limits and the incoming source.
+    pub fn validate_limits(&self) -> Result<(), LimitsConfigError> {
+        if self.connect_timeout_ms == 0 {
+            return Err(LimitsConfigError::ZeroConnectTimeout);
+        }
+        if self.request_timeout_ms == 0 {
+            return Err(LimitsConfigError::ZeroRequestTimeout);
+        }
+        if self.connect_timeout_ms > self.request_timeout_ms {
+            return Err(LimitsConfigError::ConnectTimeoutExceedsRequestTimeout {
+                connect_timeout_ms: self.connect_timeout_ms,
+                request_timeout_ms: self.request_timeout_ms,
+            });
+        }
+        if self.max_concurrent_requests == 0 {
+            return Err(LimitsConfigError::ZeroConcurrentRequests);
+        }
+        if let IncomingSource::Path(path) = &self.incoming_source {
+            if !path.is_absolute() {
+                return Err(LimitsConfigError::RelativeSocketPath(path.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AdapterConfig for Config {
+    const ENV_PREFIX: &'static str = "IC_CANISTER_HTTP_ADAPTER_";
+    type ValidationError = PolicyConfigError;
+
+    fn validate(&self) -> Result<(), PolicyConfigError> {
+        self.validate_limits()?;
+        SpkiPins::new(&self.spki_pins)?;
+        HttpProxyRoutes::new(&self.http_proxies, &self.http_proxy_routes)?;
+        OutboundHeaders::new(&self.user_agent, self.header_stamping.as_ref())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_adapter_config::load_config_with_env;
+
+    #[test]
+    fn test_otlp_exporter_defaults() {
+        let config: Config =
+            serde_json::from_str(r#"{ "otlp_exporter": { "endpoint": "http://localhost:4317" } }"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                otlp_exporter: Some(OtlpExporterConfig {
+                    endpoint: "http://localhost:4317".to_string(),
+                    sampling_percentage: 100,
+                }),
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Config>("{}").unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn test_sampling_ratio_is_capped() {
+        let exporter = |sampling_percentage| OtlpExporterConfig {
+            endpoint: "http://localhost:4317".to_string(),
+            sampling_percentage,
+        };
+        assert_eq!(exporter(0).sampling_ratio(), 0.0);
+        assert_eq!(exporter(25).sampling_ratio(), 0.25);
+        assert_eq!(exporter(250).sampling_ratio(), 1.0);
+    }
+
+    #[test]
+    fn test_env_overrides_are_validated() {
+        let env = vec![(
+            "IC_CANISTER_HTTP_ADAPTER_CONNECTION_ATTEMPT_DELAY_MS".to_string(),
+            "100".to_string(),
+        )];
+        let config: Config = load_config_with_env(None, env).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                connection_attempt_delay_ms: 100,
+                ..Config::default()
+            }
+        );
+
+        let env = vec![(
+            "IC_CANISTER_HTTP_ADAPTER_HTTP_PROXY_ROUTES".to_string(),
+            r#"[{ "hosts": ["*"], "proxy": "unknown" }]"#.to_string(),
+        )];
+        assert!(matches!(
+            load_config_with_env::<Config>(None, env),
+            Err(LoadConfigError::Invalid(PolicyConfigError::HttpProxy(_)))
+        ));
+    }
+
+    #[test]
+    fn test_limits_defaults() {
+        let config = Config::default();
+        assert_eq!(config.incoming_source, IncomingSource::Systemd);
+        assert_eq!(config.log_level, LogLevel::Info);
+        assert_eq!(config.validate_limits(), Ok(()));
+    }
+
+    #[test]
+    fn test_incoming_source_and_log_level_are_deserialized() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "incoming_source": { "path": "/run/ic-node/canister-http.sock" },
+                "log_level": "debug"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                incoming_source: IncomingSource::Path(PathBuf::from(
+                    "/run/ic-node/canister-http.sock"
+                )),
+                log_level: LogLevel::Debug,
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Config>(r#"{ "incoming_source": "systemd" }"#).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn test_invalid_limits_are_rejected() {
+        let validate = |config: Config| config.validate_limits();
+        assert_eq!(
+            validate(Config {
+                connect_timeout_ms: 0,
+                ..Config::default()
+            }),
+            Err(LimitsConfigError::ZeroConnectTimeout)
+        );
+        assert_eq!(
+            validate(Config {
+                request_timeout_ms: 0,
+                ..Config::default()
+            }),
+            Err(LimitsConfigError::ZeroRequestTimeout)
+        );
+        assert_eq!(
+            validate(Config {
+                connect_timeout_ms: 2000,
+                request_timeout_ms: 1000,
+                ..Config::default()
+            }),
+            Err(LimitsConfigError::ConnectTimeoutExceedsRequestTimeout {
+                connect_timeout_ms: 2000,
+                request_timeout_ms: 1000,
+            })
+        );
+        assert_eq!(
+            validate(Config {
+                max_concurrent_requests: 0,
+                ..Config::default()
+            }),
+            Err(LimitsConfigError::ZeroConcurrentRequests)
+        );
+        assert_eq!(
+            validate(Config {
+                incoming_source: IncomingSource::Path(PathBuf::from("adapter.sock")),
+                ..Config::default()
+            }),
+            Err(LimitsConfigError::RelativeSocketPath(PathBuf::from(
+                "adapter.sock"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_invalid_limits_fail_to_load() {
+        let env = vec![(
+            "IC_CANISTER_HTTP_ADAPTER_REQUEST_TIMEOUT_MS".to_string(),
+            "0".to_string(),
+        )];
+        assert!(matches!(
+            load_config_with_env::<Config>(None, env),
+            Err(LoadConfigError::Invalid(PolicyConfigError::Limits(
+                LimitsConfigError::ZeroRequestTimeout
+            )))
+        ));
+    }
+}
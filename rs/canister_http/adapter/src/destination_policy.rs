@@ -0,0 +1,240 @@
+use crate::request_tracing::dns_span;
+use hyper::client::connect::dns::{GaiResolver, Name};
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::Service;
+use tracing::Instrument;
+
+/// Errors returned when an outgoing request targets a denied destination.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum DeniedDestinationError {
+    #[error("destination address {ip} is not publicly routable")]
+    DeniedAddress { ip: IpAddr },
+    #[error("host {host} resolves to address {ip}, which is not publicly routable")]
+    DeniedResolvedAddress { host: String, ip: IpAddr },
+    #[error("host {host} does not resolve to any address")]
+    NoAddress { host: String },
+}
+
+/// Decides which IP addresses outgoing requests may connect to.
+///
+/// Unless private destinations are allowed, only publicly routable addresses
+/// are, so that canisters can not reach services on the node or in the data
+/// center network.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DestinationPolicy {
+    allow_private_destinations: bool,
+}
+
+impl DestinationPolicy {
+    pub fn new(allow_private_destinations: bool) -> Self {
+        Self {
+            allow_private_destinations,
+        }
+    }
+
+    /// Returns whether connecting to `ip` is denied.
+    pub fn is_denied(&self, ip: &IpAddr) -> bool {
+        !self.allow_private_destinations && !is_publicly_routable(ip)
+    }
+
+    /// Returns an error if `ip`, given literally in a url, is denied.
+    pub fn check_address(&self, ip: &IpAddr) -> Result<(), DeniedDestinationError> {
+        if self.is_denied(ip) {
+            return Err(DeniedDestinationError::DeniedAddress { ip: *ip });
+        }
+        Ok(())
+    }
+}
+
+fn is_publicly_routable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_publicly_routable_v4(ip),
+        IpAddr::V6(ip) => is_publicly_routable_v6(ip),
+    }
+}
+
+fn is_publicly_routable_v4(ip: &Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // Shared address space for carrier-grade NAT, 100.64.0.0/10
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        // "This network", 0.0.0.0/8
+        || octets[0] == 0
+        // Reserved for future use, 240.0.0.0/4
+        || octets[0] >= 240)
+}
+
+fn is_publicly_routable_v6(ip: &Ipv6Addr) -> bool {
+    if let Some(ipv4) = embedded_ipv4(ip) {
+        return is_publicly_routable_v4(&ipv4);
+    }
+    let first_segment = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local addresses, fc00::/7
+        || (first_segment & 0xfe00) == 0xfc00
+        // Link-local addresses, fe80::/10
+        || (first_segment & 0xffc0) == 0xfe80
+        // Documentation addresses, 2001:db8::/32
+        || (first_segment == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Returns the IPv4 address of an IPv4-mapped (::ffff:a.b.c.d) or NAT64
+/// (64:ff9b::a.b.c.d) address, which would otherwise bypass the IPv4 checks.
+fn embedded_ipv4(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let is_mapped = segments[..5] == [0; 5] && segments[5] == 0xffff;
+    let is_nat64 = segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0];
+    if is_mapped || is_nat64 {
+        let octets = ip.octets();
+        Some(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ))
+    } else {
+        None
+    }
+}
+
+/// Resolves hostnames and checks the resolved addresses against a
+/// [`DestinationPolicy`].
+///
+/// To prevent DNS rebinding, a host is resolved exactly once per connection
+/// and the connection is pinned to the resolved addresses that were checked:
+/// the resolver returns the first resolved address of each address family,
+/// so the connector can race an IPv6 and an IPv4 connection attempt as in
+/// RFC 8305 (Happy Eyeballs), but never connects to an address that was not
+/// checked. A host is rejected if any of its addresses is denied, since
+/// mixing public and private addresses in one answer is itself an attempt
+/// to reach a private destination.
+#[derive(Clone, Debug)]
+pub struct PinningResolver {
+    inner: GaiResolver,
+    policy: DestinationPolicy,
+}
+
+impl PinningResolver {
+    pub fn new(policy: DestinationPolicy) -> Self {
+        Self {
+            inner: GaiResolver::new(),
+            policy,
+        }
+    }
+}
+
+impl Service<Name> for PinningResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let host = name.as_str().to_string();
+        let policy = self.policy;
+        let span = dns_span(&host);
+        let resolving = self.inner.call(name);
+        Box::pin(
+            async move {
+                let addrs: Vec<SocketAddr> = resolving.await?.collect();
+                if let Some(denied) = addrs.iter().find(|addr| policy.is_denied(&addr.ip())) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        DeniedDestinationError::DeniedResolvedAddress {
+                            host,
+                            ip: denied.ip(),
+                        },
+                    ));
+                }
+                if addrs.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        DeniedDestinationError::NoAddress { host },
+                    ));
+                }
+                Ok(first_address_per_family(&addrs).into_iter())
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Returns the first of `addrs` of each address family, in the order of
+/// `addrs`.
+fn first_address_per_family(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let first_v6 = addrs.iter().find(|addr| addr.is_ipv6());
+    let first_v4 = addrs.iter().find(|addr| addr.is_ipv4());
+    let mut pinned: Vec<SocketAddr> = first_v6.into_iter().chain(first_v4).copied().collect();
+    if addrs.first().map_or(false, SocketAddr::is_ipv4) {
+        pinned.reverse();
+    }
+    pinned
+}
+
+/// Returns the destination error that caused `err`, if any.
+pub(crate) fn find_denied_destination_error<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a DeniedDestinationError> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(destination_error) = err.downcast_ref::<DeniedDestinationError>() {
+            return Some(destination_error);
+        }
+        // The resolver wraps the error in an io::Error, whose source is the
+        // source of the wrapped error rather than the wrapped error itself.
+        if let Some(io_error) = err.downcast_ref::<io::Error>() {
+            if let Some(destination_error) = io_error
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<DeniedDestinationError>())
+            {
+                return Some(destination_error);
+            }
+        }
+        source = err.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_first_address_per_family() {
+        assert_eq!(
+            first_address_per_family(&addrs(&[
+                "[2001:4860::1]:443",
+                "[2001:4860::2]:443",
+                "8.8.8.8:443",
+                "8.8.4.4:443",
+            ])),
+            addrs(&["[2001:4860::1]:443", "8.8.8.8:443"])
+        );
+        // The preferred family of the resolver is kept.
+        assert_eq!(
+            first_address_per_family(&addrs(&["8.8.8.8:443", "[2001:4860::1]:443"])),
+            addrs(&["8.8.8.8:443", "[2001:4860::1]:443"])
+        );
+        assert_eq!(
+            first_address_per_family(&addrs(&["8.8.8.8:443", "8.8.4.4:443"])),
+            addrs(&["8.8.8.8:443"])
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use crate::config::{HttpProxyAuth, HttpProxyConfig, HttpProxyRoute};
+use crate::destination_policy::PinningResolver;
+use crate::request_tracing::connect_span;
+use http::uri::Scheme;
+use http::{HeaderValue, Uri};
+use hyper::client::connect::dns::Name;
+use hyper::client::HttpConnector;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tower::Service;
+use tracing::Instrument;
+
+/// Maximum size of the response of a proxy to a CONNECT request.
+const MAX_CONNECT_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// Errors returned when the configured HTTP proxies or routes are invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HttpProxyConfigError {
+    #[error("invalid url {url} of HTTP proxy {proxy}: {reason}")]
+    InvalidUrl {
+        proxy: String,
+        url: String,
+        reason: String,
+    },
+    #[error("the credentials of HTTP proxy {proxy} are no valid header value")]
+    InvalidAuth { proxy: String },
+    #[error("invalid host pattern {pattern} in HTTP proxy route")]
+    InvalidHostPattern { pattern: String },
+    #[error("HTTP proxy route refers to unknown proxy {proxy}")]
+    UnknownProxy { proxy: String },
+}
+
+/// Errors returned when a tunnel through an HTTP proxy can not be
+/// established.
+#[derive(Debug, Error)]
+pub enum HttpProxyError {
+    #[error("failed to tunnel to {destination} through HTTP proxy {proxy}: {source}")]
+    Io {
+        proxy: String,
+        destination: String,
+        source: io::Error,
+    },
+    #[error("HTTP proxy {proxy} refused to tunnel to {destination} with status {status}")]
+    TunnelRefused {
+        proxy: String,
+        destination: String,
+        status: u16,
+    },
+    #[error("HTTP proxy {proxy} sent an invalid response to the CONNECT request: {reason}")]
+    InvalidResponse { proxy: String, reason: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum HostPattern {
+    Any,
+    /// The suffix of the subdomains, including its leading dot.
+    Subdomains(String),
+    Host(String),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Result<Self, HttpProxyConfigError> {
+        let pattern = pattern.to_lowercase();
+        let parsed = if pattern == "*" {
+            Self::Any
+        } else if let Some(domain) = pattern.strip_prefix("*.") {
+            Self::Subdomains(format!(".{}", domain))
+        } else {
+            Self::Host(pattern.clone())
+        };
+        let name: &str = match &parsed {
+            Self::Any => "*",
+            Self::Subdomains(suffix) => &suffix[1..],
+            Self::Host(host) => host,
+        };
+        if name.is_empty() || (parsed != Self::Any && name.contains('*')) {
+            return Err(HttpProxyConfigError::InvalidHostPattern { pattern });
+        }
+        Ok(parsed)
+    }
+
+    /// Returns whether the lowercase `host` matches the pattern.
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Subdomains(suffix) => host.len() > suffix.len() && host.ends_with(suffix),
+            Self::Host(pattern) => host == pattern,
+        }
+    }
+}
+
+struct HttpProxy {
+    name: String,
+    uri: Uri,
+    authorization: Option<HeaderValue>,
+}
+
+// The credentials must not end up in logs.
+impl fmt::Debug for HttpProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpProxy")
+            .field("name", &self.name)
+            .field("uri", &self.uri)
+            .field(
+                "authorization",
+                &self.authorization.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+fn parse_proxy(name: &str, config: &HttpProxyConfig) -> Result<HttpProxy, HttpProxyConfigError> {
+    let invalid_url = |reason: String| HttpProxyConfigError::InvalidUrl {
+        proxy: name.to_string(),
+        url: config.url.clone(),
+        reason,
+    };
+    let uri = config
+        .url
+        .parse::<Uri>()
+        .map_err(|e| invalid_url(e.to_string()))?;
+    if uri.scheme() != Some(&Scheme::HTTP) {
+        return Err(invalid_url("the scheme must be http".to_string()));
+    }
+    if uri.host().is_none() {
+        return Err(invalid_url("the url has no host".to_string()));
+    }
+    let authorization = config
+        .auth
+        .as_ref()
+        .map(|auth| {
+            let credentials = match auth {
+                HttpProxyAuth::Basic { username, password } => {
+                    format!(
+                        "Basic {}",
+                        base64::encode(format!("{}:{}", username, password))
+                    )
+                }
+                HttpProxyAuth::Bearer { token } => format!("Bearer {}", token),
+            };
+            HeaderValue::from_str(&credentials).map_err(|_| HttpProxyConfigError::InvalidAuth {
+                proxy: name.to_string(),
+            })
+        })
+        .transpose()?;
+    Ok(HttpProxy {
+        name: name.to_string(),
+        uri,
+        authorization,
+    })
+}
+
+/// The rules routing outgoing requests through HTTP proxies, in the order in
+/// which they are matched against the destination host.
+#[derive(Clone, Debug, Default)]
+pub struct HttpProxyRoutes {
+    routes: Arc<Vec<(HostPattern, Option<Arc<HttpProxy>>)>>,
+}
+
+impl HttpProxyRoutes {
+    /// Parses the `proxies`, keyed by name, and the `routes` referring to
+    /// them.
+    pub fn new(
+        proxies: &BTreeMap<String, HttpProxyConfig>,
+        routes: &[HttpProxyRoute],
+    ) -> Result<Self, HttpProxyConfigError> {
+        let proxies = proxies
+            .iter()
+            .map(|(name, config)| Ok((name.as_str(), Arc::new(parse_proxy(name, config)?))))
+            .collect::<Result<BTreeMap<_, _>, HttpProxyConfigError>>()?;
+        let mut parsed_routes = Vec::new();
+        for route in routes {
+            let proxy = match &route.proxy {
+                Some(name) => Some(Arc::clone(proxies.get(name.as_str()).ok_or_else(|| {
+                    HttpProxyConfigError::UnknownProxy {
+                        proxy: name.clone(),
+                    }
+                })?)),
+                None => None,
+            };
+            for pattern in &route.hosts {
+                parsed_routes.push((HostPattern::parse(pattern)?, proxy.clone()));
+            }
+        }
+        Ok(Self {
+            routes: Arc::new(parsed_routes),
+        })
+    }
+
+    /// Returns the proxy through which requests to `host` are tunneled, or
+    /// `None` if they connect to `host` directly.
+    fn proxy_for(&self, host: &str) -> Option<&Arc<HttpProxy>> {
+        let host = host.to_lowercase();
+        self.routes
+            .iter()
+            .find(|(pattern, _)| pattern.matches(&host))
+            .and_then(|(_, proxy)| proxy.as_ref())
+    }
+}
+
+/// Connects to the destination of a request directly or, if the routes
+/// select a proxy for its host, through a tunnel opened with a CONNECT
+/// request to the proxy. The TLS handshake with the destination happens
+/// inside the tunnel, so the proxy does not see the requests and the SPKI
+/// pins of the destination still apply.
+///
+/// Proxied hosts are resolved by the [`PinningResolver`] too, and the tunnel
+/// is opened to the checked address rather than to the hostname, so that the
+/// proxy can neither be used to reach private destinations nor resolve the
+/// hostname to a different address.
+#[derive(Clone, Debug)]
+pub struct HttpProxyConnector {
+    direct: HttpConnector<PinningResolver>,
+    resolver: PinningResolver,
+    proxy: HttpConnector,
+    routes: HttpProxyRoutes,
+}
+
+impl HttpProxyConnector {
+    pub fn new(
+        direct: HttpConnector<PinningResolver>,
+        resolver: PinningResolver,
+        routes: HttpProxyRoutes,
+    ) -> Self {
+        Self {
+            direct,
+            resolver,
+            proxy: HttpConnector::new(),
+            routes,
+        }
+    }
+}
+
+impl Service<Uri> for HttpProxyConnector {
+    type Response = TcpStream;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        futures::ready!(self.direct.poll_ready(cx))?;
+        self.proxy.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let proxy = dst
+            .host()
+            .and_then(|host| self.routes.proxy_for(host))
+            .cloned();
+        match proxy {
+            None => {
+                let connecting = self.direct.call(dst);
+                Box::pin(
+                    async move { connecting.await.map_err(Into::into) }
+                        .instrument(connect_span(None)),
+                )
+            }
+            Some(proxy) => {
+                let span = connect_span(Some(proxy.name.as_str()));
+                let resolver = self.resolver.clone();
+                let connecting = self.proxy.call(proxy.uri.clone());
+                Box::pin(
+                    async move {
+                        let destination = resolve_destination(resolver, &dst).await?;
+                        let stream = connecting.await?;
+                        tunnel(stream, &proxy, destination)
+                            .await
+                            .map_err(Into::into)
+                    }
+                    .instrument(span),
+                )
+            }
+        }
+    }
+}
+
+/// Returns the port of `dst`, or the default port of its scheme if it has
+/// none.
+fn destination_port(dst: &Uri) -> u16 {
+    dst.port_u16().unwrap_or_else(|| {
+        if dst.scheme() == Some(&Scheme::HTTPS) {
+            443
+        } else {
+            80
+        }
+    })
+}
+
+/// Returns the address to tunnel to for `dst`. A hostname is resolved with
+/// `resolver`, which fails if it resolves to a denied address, and the first
+/// address it pins is used. An IP address is returned as is, since the
+/// adapter checks it before connecting.
+async fn resolve_destination(
+    mut resolver: PinningResolver,
+    dst: &Uri,
+) -> Result<SocketAddr, io::Error> {
+    let port = destination_port(dst);
+    // The host of a URI keeps the brackets around IPv6 addresses.
+    let host = dst
+        .host()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+    let name =
+        Name::from_str(host).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    futures::future::poll_fn(|cx| resolver.poll_ready(cx)).await?;
+    let addr = resolver.call(name).await?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} resolved to no address", host),
+        )
+    })?;
+    Ok(SocketAddr::new(addr.ip(), port))
+}
+
+/// Opens a tunnel to `destination` through `proxy` over `stream`, a
+/// connection to the proxy.
+async fn tunnel(
+    mut stream: TcpStream,
+    proxy: &HttpProxy,
+    destination: SocketAddr,
+) -> Result<TcpStream, HttpProxyError> {
+    let destination = destination.to_string();
+    let io_error = |source| HttpProxyError::Io {
+        proxy: proxy.name.clone(),
+        destination: destination.clone(),
+        source,
+    };
+    let invalid_response = |reason: &str| HttpProxyError::InvalidResponse {
+        proxy: proxy.name.clone(),
+        reason: reason.to_string(),
+    };
+
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", destination).into_bytes();
+    if let Some(authorization) = &proxy.authorization {
+        request.extend_from_slice(b"Proxy-Authorization: ");
+        request.extend_from_slice(authorization.as_bytes());
+        request.extend_from_slice(b"\r\n");
+    }
+    request.extend_from_slice(b"\r\n");
+    stream.write_all(&request).await.map_err(io_error)?;
+
+    // The destination only speaks once the client does, so the proxy sends
+    // nothing after its response until the tunnel is used.
+    let mut response = Vec::new();
+    let mut buf = [0; 1024];
+    let response_len = loop {
+        let read = stream.read(&mut buf).await.map_err(io_error)?;
+        if read == 0 {
+            return Err(invalid_response("the connection was closed"));
+        }
+        response.extend_from_slice(&buf[..read]);
+        if let Some(end) = response.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if response.len() > MAX_CONNECT_RESPONSE_BYTES {
+            return Err(invalid_response("the response is too large"));
+        }
+    };
+    if response_len != response.len() {
+        return Err(invalid_response("unexpected data after the response"));
+    }
+
+    let status = response
+        .split(|b| *b == b'\r')
+        .next()
+        .and_then(|line| std::str::from_utf8(line).ok())
+        .and_then(|line| {
+            let mut parts = line.split(' ');
+            match (parts.next(), parts.next()) {
+                (Some(version), Some(status)) if version.starts_with("HTTP/1.") => {
+                    status.parse::<u16>().ok()
+                }
+                _ => None,
+            }
+        })
+        .ok_or_else(|| invalid_response("malformed status line"))?;
+    if !(200..300).contains(&status) {
+        return Err(HttpProxyError::TunnelRefused {
+            proxy: proxy.name.clone(),
+            destination,
+            status,
+        });
+    }
+    Ok(stream)
+}
+
+/// Returns the proxy error that caused `err`, if any.
+pub(crate) fn find_http_proxy_error<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a HttpProxyError> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(proxy_error) = err.downcast_ref::<HttpProxyError>() {
+            return Some(proxy_error);
+        }
+        source = err.source();
+    }
+    None
+}
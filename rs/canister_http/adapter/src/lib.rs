@@ -1,13 +1,49 @@
 //! The HTTP adapter makes http calls to the outside on behalf of the replica
 //! This is part of the http calls from canister feature
 
+/// The command line interface of the HTTP adapter.
+mod cli;
+/// Configuration of the HTTP adapter provided by the node provider.
+mod config;
+/// Checks of the destinations of outgoing requests, resolving each host once
+/// to protect against DNS rebinding.
+mod destination_policy;
+/// Tunneling of outgoing requests through the HTTP proxies of the node
+/// provider with CONNECT requests.
+mod http_proxy;
+/// The headers that the adapter adds to outgoing requests: the user agent and
+/// the optional stamps with the ids of the subnet and node.
+mod outbound_headers;
+/// Pseudo-random delays that spread the same request made by several
+/// replicas over time.
+mod request_delay;
+/// Tracing spans of the outgoing requests and their export to an OTLP
+/// collector.
+mod request_tracing;
 /// Main module of HTTP adapter. Receives gRPC calls from replica and makes outgoing requests
 mod rpc_server;
+/// Pinning of the TLS certificates of high-value destinations by the hash of
+/// their SubjectPublicKeyInfo (SPKI), as in the `pin-sha256` directive of
+/// RFC 7469.
+mod spki_pinning;
 
 /// This module contains the protobuf structs to send
 /// messages between the replica and the adapter.
-pub mod proto {
-    tonic::include_proto!("http_adapter");
-}
+pub use ic_canister_http_service as proto;
 
-pub use rpc_server::HttpFromCanister;
+pub use cli::Cli;
+pub use config::{
+    Config, ConfigError, HeaderStampingConfig, HttpProxyAuth, HttpProxyConfig, HttpProxyRoute,
+    IncomingSource, LimitsConfigError, LogLevel, OtlpExporterConfig,
+};
+pub use destination_policy::{DeniedDestinationError, DestinationPolicy, PinningResolver};
+pub use http_proxy::{HttpProxyConfigError, HttpProxyConnector, HttpProxyError, HttpProxyRoutes};
+pub use outbound_headers::{OutboundHeadersConfigError, NODE_HEADER, SUBNET_HEADER};
+pub use request_delay::PseudoRandomDelay;
+pub use request_tracing::{init_otlp_exporter, shutdown_otlp_exporter, TracingInitError};
+pub use rpc_server::{
+    HttpFromCanister, PolicyConfigError, RequestValidationError, MAX_REQUEST_HEADERS_BYTES,
+};
+pub use spki_pinning::{
+    spki_hash, SpkiHash, SpkiPinConfigError, SpkiPinError, SpkiPinningConnector, SpkiPins,
+};
@@ -3,33 +3,121 @@
 /// Relevant configuration files:
 /// systemd service ic-os/guestos/rootfs/etc/systemd/system/ic-canister-http-adapter.service
 /// systemd socket ic-os/guestos/rootfs/etc/systemd/system/ic-canister-http-adapter.socket
+/// Outside of systemd, the adapter binds the socket at the path of the `incoming_source` of
+/// its config instead.
+use clap::Clap;
 use tonic::transport::Server;
 
-use ic_async_utils::{ensure_single_named_systemd_socket, incoming_from_first_systemd_socket};
-use ic_canister_http_adapter::{proto::http_adapter_server::HttpAdapterServer, HttpFromCanister};
+use ic_adapter_config::{dump_defaults, AdapterSubcommand};
+use ic_adapter_metrics::{
+    proto::adapter_metrics_server::AdapterMetricsServer, AdapterMetricsExporter,
+};
+use ic_async_utils::{
+    ensure_single_named_systemd_socket, incoming_from_first_systemd_socket, incoming_from_path,
+};
+use ic_canister_http_adapter::{
+    init_otlp_exporter, proto::http_adapter_server::HttpAdapterServer, shutdown_otlp_exporter, Cli,
+    Config, HttpFromCanister, IncomingSource,
+};
+use ic_metrics::MetricsRegistry;
+use slog::{error, info, slog_o, Drain, Logger};
+use std::io::stdout;
+use std::path::PathBuf;
+use tokio::signal::unix::{signal, SignalKind};
 
 const IC_CANISTER_HTTP_SOCKET_NAME: &str = "ic-canister-http-adapter.socket";
 
 #[tokio::main]
 pub async fn main() {
-    // TODO: add logs (NET-853)
-    // TODO: add config/CLI (NET-880)
-
-    // Make sure we receive the correct socket from systemd (and only one).
-    // This function panics if multiple sockets are passed to this process or a wrongly named socket is passed.
-    ensure_single_named_systemd_socket(IC_CANISTER_HTTP_SOCKET_NAME);
+    let cli = Cli::parse();
+    if let Some(AdapterSubcommand::DumpDefaults) = cli.command {
+        println!("{}", dump_defaults::<Config>());
+        return;
+    }
+    let config = cli
+        .get_config()
+        .unwrap_or_else(|e| panic!("Failed to load the config: {}", e));
+    let plain = slog_term::PlainSyncDecorator::new(stdout());
+    let drain = slog_term::FullFormat::new(plain)
+        .build()
+        .filter_level(cli.get_logging_level(&config))
+        .fuse();
+    let logger = Logger::root(drain, slog_o!());
 
-    // Creates an async stream from the socket file descripter passed to this process by systemd (as FD #3).
-    // Make sure to only call this function once in this process. Calling it multiple times leads to multiple socket listeners
-    let incoming = incoming_from_first_systemd_socket();
+    if let Some(otlp_exporter) = &config.otlp_exporter {
+        init_otlp_exporter(otlp_exporter)
+            .unwrap_or_else(|e| panic!("Failed to set up the export of spans: {}", e));
+    }
 
-    let http_from_canister = HttpFromCanister::new();
-    let server = Server::builder()
+    let http_from_canister =
+        HttpFromCanister::with_config(&config).unwrap_or_else(|e| panic!("Invalid config: {}", e));
+    if let Some(path) = cli.config {
+        tokio::spawn(reload_config_on_sighup(
+            path,
+            http_from_canister.clone(),
+            logger.clone(),
+        ));
+    }
+    let router = Server::builder()
+        .concurrency_limit_per_connection(config.max_concurrent_requests)
         .add_service(HttpAdapterServer::new(http_from_canister))
-        .serve_with_incoming(incoming);
+        .add_service(AdapterMetricsServer::new(AdapterMetricsExporter::new(
+            MetricsRegistry::global(),
+        )));
 
     // Run this server for... forever!
-    if let Err(e) = server.await {
-        eprintln!("server error: {}", e);
+    let served = match &config.incoming_source {
+        IncomingSource::Systemd => {
+            // Make sure we receive the correct socket from systemd (and only one).
+            // This function panics if multiple sockets are passed to this process or a wrongly named socket is passed.
+            ensure_single_named_systemd_socket(IC_CANISTER_HTTP_SOCKET_NAME);
+
+            // Creates an async stream from the socket file descripter passed to this process by systemd (as FD #3).
+            // Make sure to only call this function once in this process. Calling it multiple times leads to multiple socket listeners
+            let incoming = incoming_from_first_systemd_socket();
+            info!(
+                logger,
+                "Serving on the systemd socket {}", IC_CANISTER_HTTP_SOCKET_NAME
+            );
+            router.serve_with_incoming(incoming).await
+        }
+        IncomingSource::Path(path) => {
+            let incoming = incoming_from_path(path)
+                .unwrap_or_else(|e| panic!("Failed to bind the socket at {:?}: {}", path, e));
+            info!(logger, "Serving on the socket at {:?}", path);
+            router.serve_with_incoming(incoming).await
+        }
+    };
+    if let Err(e) = served {
+        error!(logger, "server error: {}", e);
+    }
+    shutdown_otlp_exporter();
+}
+
+/// Reloads the config file at `config_path` into `http_from_canister` whenever
+/// the process receives a SIGHUP, e.g. from `systemctl reload`. A config that
+/// fails to load is reported and the current one is kept.
+async fn reload_config_on_sighup(
+    config_path: PathBuf,
+    http_from_canister: HttpFromCanister,
+    logger: Logger,
+) {
+    let mut sig_hup =
+        signal(SignalKind::hangup()).expect("failed to install SIGHUP signal handler");
+    while sig_hup.recv().await.is_some() {
+        let reloaded = Config::from_file(&config_path)
+            .map_err(|e| e.to_string())
+            .and_then(|config| {
+                http_from_canister
+                    .reload(&config)
+                    .map_err(|e| e.to_string())
+            });
+        match reloaded {
+            Ok(()) => info!(logger, "Reloaded config from {:?}", config_path),
+            Err(e) => error!(
+                logger,
+                "Failed to reload config from {:?}, keeping the current one: {}", config_path, e
+            ),
+        }
     }
 }
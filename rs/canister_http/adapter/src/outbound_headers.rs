@@ -0,0 +1,91 @@
+use crate::config::HeaderStampingConfig;
+use http::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use ic_base_types::PrincipalId;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The header that carries the id of the subnet of the node making a request,
+/// if headers are stamped.
+pub const SUBNET_HEADER: &str = "x-ic-subnet";
+
+/// The header that carries the id of the node making a request, if headers
+/// are stamped with it.
+pub const NODE_HEADER: &str = "x-ic-node";
+
+/// Errors returned when the configured outbound headers are invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OutboundHeadersConfigError {
+    #[error("user agent {user_agent:?} is no valid header value")]
+    InvalidUserAgent { user_agent: String },
+    #[error("{field} {value:?} of the header stamping is no principal id")]
+    InvalidPrincipalId { field: &'static str, value: String },
+}
+
+/// The headers that the adapter adds to outgoing requests: the configured
+/// `User-Agent`, unless the canister sets its own, and, if stamping is
+/// enabled, the ids of the subnet and node.
+///
+/// Only principal ids are accepted for stamping and they are sent in their
+/// canonical textual form, so the stamps carry nothing but the public identity
+/// of the node, and none of e.g. its addresses or keys.
+#[derive(Clone, Debug)]
+pub struct OutboundHeaders {
+    user_agent: Option<HeaderValue>,
+    stamps: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl OutboundHeaders {
+    /// Returns the outbound headers for `user_agent`, where an empty user
+    /// agent adds none, and the header `stamping`, if enabled.
+    pub fn new(
+        user_agent: &str,
+        stamping: Option<&HeaderStampingConfig>,
+    ) -> Result<Self, OutboundHeadersConfigError> {
+        let user_agent = match user_agent {
+            "" => None,
+            _ => Some(HeaderValue::from_str(user_agent).map_err(|_| {
+                OutboundHeadersConfigError::InvalidUserAgent {
+                    user_agent: user_agent.to_string(),
+                }
+            })?),
+        };
+        let mut stamps = Vec::new();
+        if let Some(stamping) = stamping {
+            stamps.push((
+                HeaderName::from_static(SUBNET_HEADER),
+                principal_id_header_value("subnet_id", &stamping.subnet_id)?,
+            ));
+            if let Some(node_id) = &stamping.node_id {
+                stamps.push((
+                    HeaderName::from_static(NODE_HEADER),
+                    principal_id_header_value("node_id", node_id)?,
+                ));
+            }
+        }
+        Ok(Self { user_agent, stamps })
+    }
+
+    /// Adds the outbound headers to the canonicalized `headers` of a request.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(user_agent) = &self.user_agent {
+            headers
+                .entry(USER_AGENT)
+                .or_insert_with(|| user_agent.clone());
+        }
+        for (name, value) in &self.stamps {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+fn principal_id_header_value(
+    field: &'static str,
+    value: &str,
+) -> Result<HeaderValue, OutboundHeadersConfigError> {
+    let invalid = || OutboundHeadersConfigError::InvalidPrincipalId {
+        field,
+        value: value.to_string(),
+    };
+    let principal_id = PrincipalId::from_str(value).map_err(|_| invalid())?;
+    HeaderValue::from_str(&principal_id.to_string()).map_err(|_| invalid())
+}
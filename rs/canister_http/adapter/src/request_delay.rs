@@ -0,0 +1,45 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Duration;
+
+/// Picks the delays by which the adapter spreads outgoing requests, so that
+/// the replicas of a subnet making the same request do not reach the
+/// destination all at once.
+#[derive(Debug)]
+pub struct PseudoRandomDelay {
+    salt: u64,
+    max_window: Duration,
+}
+
+impl PseudoRandomDelay {
+    /// Creates delays with a random salt, never exceeding `max_window`.
+    pub fn new(max_window: Duration) -> Self {
+        Self::with_salt(rand::thread_rng().gen(), max_window)
+    }
+
+    pub fn with_salt(salt: u64, max_window: Duration) -> Self {
+        Self { salt, max_window }
+    }
+
+    /// Creates delays with the salt of these delays, never exceeding
+    /// `max_window`, e.g. when the config of the adapter is reloaded.
+    pub fn with_max_window(&self, max_window: Duration) -> Self {
+        Self::with_salt(self.salt, max_window)
+    }
+
+    /// Returns the delay of the request with id `request_id`, which is
+    /// uniformly distributed over the requested window, capped at the
+    /// maximum window.
+    ///
+    /// The delay is derived from the request id and the salt: adapters of
+    /// different replicas have different salts and thus pick unrelated
+    /// delays, while a request that is retried by the same adapter is
+    /// delayed by the same duration.
+    pub fn delay(&self, request_id: u64, window_ms: u64) -> Duration {
+        let window_ms = window_ms.min(self.max_window.as_millis() as u64);
+        if window_ms == 0 {
+            return Duration::ZERO;
+        }
+        let mut rng = StdRng::seed_from_u64(self.salt ^ request_id);
+        Duration::from_millis(rng.gen_range(0..window_ms))
+    }
+}
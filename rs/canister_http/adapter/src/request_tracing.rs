@@ -0,0 +1,331 @@
+use crate::config::OtlpExporterConfig;
+use ic_interfaces::adapter_client::{PURPOSE_METADATA_KEY, TRACEPARENT_METADATA_KEY};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::trace::{self, Sampler};
+use opentelemetry::sdk::Resource;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use thiserror::Error;
+use tonic::metadata::{KeyRef, MetadataMap};
+use tracing::{field, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// The service name under which the spans are exported.
+const SERVICE_NAME: &str = "ic-canister-http-adapter";
+
+/// Errors returned when the export of the spans cannot be set up.
+#[derive(Debug, Error)]
+pub enum TracingInitError {
+    #[error("Failed to install the OTLP exporter: {0}")]
+    Exporter(#[from] opentelemetry::trace::TraceError),
+    #[error("Failed to install the tracing subscriber: {0}")]
+    Subscriber(#[from] tracing::subscriber::SetGlobalDefaultError),
+}
+
+/// Exports the spans of the outgoing requests to the OTLP collector of
+/// `config`, in batches from a background task of the Tokio runtime.
+///
+/// Must be called at most once, from within the runtime. If it is never
+/// called, the spans are not recorded at all.
+pub fn init_otlp_exporter(config: &OtlpExporterConfig) -> Result<(), TracingInitError> {
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(config.endpoint.clone()),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::TraceIdRatioBased(config.sampling_ratio()))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    SERVICE_NAME,
+                )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)?;
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok(())
+}
+
+/// Flushes the spans that were not exported yet.
+pub fn shutdown_otlp_exporter() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Reads the W3C trace context that the replica attached to a request.
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl<'a> Extractor for MetadataExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .map(|key| match key {
+                KeyRef::Ascii(key) => key.as_str(),
+                KeyRef::Binary(key) => key.as_str(),
+            })
+            .collect()
+    }
+}
+
+/// The span of a whole request from the replica, from its validation until
+/// the body of the response is read. The host and the status of the response
+/// are recorded once known.
+///
+/// The span is a child of the trace context that the replica sent in
+/// `metadata`, if any, so that the request is correlated with the round that
+/// triggered it.
+pub(crate) fn request_span(request_id: u64, metadata: &MetadataMap) -> Span {
+    let purpose = metadata
+        .get(PURPOSE_METADATA_KEY)
+        .and_then(|value| value.to_str().ok());
+    let span = info_span!(
+        "canister_http_request",
+        request_id,
+        purpose,
+        host = field::Empty,
+        status = field::Empty,
+    );
+    if metadata.contains_key(TRACEPARENT_METADATA_KEY) {
+        span.set_parent(TraceContextPropagator::new().extract(&MetadataExtractor(metadata)));
+    }
+    span
+}
+
+/// The span of the resolution of `host`.
+pub(crate) fn dns_span(host: &str) -> Span {
+    info_span!("dns", host)
+}
+
+/// The span of the TCP connection to the destination, or to the HTTP proxy
+/// `proxy` and the tunnel through it. Encloses the DNS span.
+pub(crate) fn connect_span(proxy: Option<&str>) -> Span {
+    info_span!("connect", proxy = field::debug(proxy))
+}
+
+/// The span of the connection to `host` up to the end of the TLS handshake
+/// and the check of the SPKI pins. Encloses the connect span, so the duration
+/// of the handshake is the difference of both.
+pub(crate) fn tls_span(host: &str) -> Span {
+    info_span!("tls", host)
+}
+
+/// The span from sending the request until the headers of the response are
+/// received. Encloses the TLS span unless a pooled connection is reused.
+pub(crate) fn first_byte_span() -> Span {
+    info_span!("first_byte")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::http_adapter_server::HttpAdapter;
+    use crate::{Config, HttpFromCanister};
+    use ic_protobuf::canister_http::v1::CanisterHttpRequest;
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tonic::metadata::MetadataValue;
+    use tonic::Request;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer};
+    use tracing_subscriber::registry::LookupSpan;
+
+    fn trace_id_of(span: &Span) -> TraceId {
+        span.context().span().span_context().trace_id()
+    }
+
+    /// A span as recorded by [SpanRecorder].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct RecordedSpan {
+        name: &'static str,
+        parent: Option<&'static str>,
+        fields: BTreeMap<&'static str, String>,
+    }
+
+    /// Records the names, parents and fields of all spans.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<(Id, RecordedSpan)>>>);
+
+    impl SpanRecorder {
+        fn spans(&self) -> Vec<RecordedSpan> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, span)| span.clone())
+                .collect()
+        }
+
+        fn span(&self, name: &str) -> RecordedSpan {
+            self.spans()
+                .into_iter()
+                .find(|span| span.name == name)
+                .unwrap_or_else(|| panic!("no span named {}", name))
+        }
+    }
+
+    struct FieldRecorder<'a>(&'a mut BTreeMap<&'static str, String>);
+
+    impl Visit for FieldRecorder<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let parent = ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name());
+            let mut fields = BTreeMap::new();
+            attrs.record(&mut FieldRecorder(&mut fields));
+            let span = RecordedSpan {
+                name: attrs.metadata().name(),
+                parent,
+                fields,
+            };
+            self.0.lock().unwrap().push((id.clone(), span));
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            let mut spans = self.0.lock().unwrap();
+            if let Some((_, span)) = spans.iter_mut().rev().find(|(span_id, _)| span_id == id) {
+                values.record(&mut FieldRecorder(&mut span.fields));
+            }
+        }
+    }
+
+    /// Starts an HTTP server on localhost that answers a single request with
+    /// an empty response. Returns the url of the server.
+    async fn spawn_server() -> String {
+        let listener = TcpListener::bind("localhost:0").await.unwrap();
+        let url = format!("http://localhost:{}", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_spans_of_request() {
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        let http_from_canister = HttpFromCanister::with_config(&Config {
+            allow_private_destinations: true,
+            ..Config::default()
+        })
+        .unwrap();
+        let request = CanisterHttpRequest {
+            url: spawn_server().await,
+            request_id: 42,
+            ..Default::default()
+        };
+
+        let response = http_from_canister
+            .send_http_request(Request::new(request))
+            .await
+            .unwrap();
+
+        assert_eq!(response.into_inner().status, 200);
+        let request_span = recorder.span("canister_http_request");
+        assert_eq!(request_span.parent, None);
+        assert_eq!(request_span.fields["request_id"], "42");
+        assert_eq!(request_span.fields["host"], "localhost");
+        assert_eq!(request_span.fields["status"], "200");
+        assert_eq!(
+            recorder.span("first_byte").parent,
+            Some("canister_http_request")
+        );
+        assert_eq!(recorder.span("tls").parent, Some("first_byte"));
+        assert_eq!(recorder.span("tls").fields["host"], "localhost");
+        assert_eq!(recorder.span("connect").parent, Some("tls"));
+        assert_eq!(recorder.span("connect").fields["proxy"], "None");
+        assert_eq!(recorder.span("dns").parent, Some("connect"));
+        assert_eq!(recorder.span("dns").fields["host"], "localhost");
+    }
+
+    #[tokio::test]
+    async fn test_spans_of_rejected_request_end_before_connecting() {
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        let request = CanisterHttpRequest {
+            url: "http://127.0.0.1:8080".to_string(),
+            ..Default::default()
+        };
+
+        HttpFromCanister::new()
+            .send_http_request(Request::new(request))
+            .await
+            .unwrap_err();
+
+        let names: Vec<_> = recorder.spans().iter().map(|span| span.name).collect();
+        assert_eq!(names, vec!["canister_http_request"]);
+        assert_eq!(
+            recorder.span("canister_http_request").fields["host"],
+            "127.0.0.1"
+        );
+    }
+
+    #[test]
+    fn test_request_span_is_child_of_replica_trace_context() {
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut metadata = MetadataMap::new();
+            metadata.insert(
+                TRACEPARENT_METADATA_KEY,
+                MetadataValue::from_static(
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                ),
+            );
+            metadata.insert(PURPOSE_METADATA_KEY, MetadataValue::from_static("test"));
+
+            let span = request_span(1, &metadata);
+
+            assert_eq!(
+                trace_id_of(&span),
+                TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn test_request_span_without_trace_context_is_root() {
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let span = request_span(1, &MetadataMap::new());
+
+            assert_eq!(trace_id_of(&span), TraceId::INVALID);
+        });
+    }
+}
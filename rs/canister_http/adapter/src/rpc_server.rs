@@ -1,62 +1,382 @@
+use crate::config::{Config, LimitsConfigError};
+use crate::destination_policy::{
+    find_denied_destination_error, DeniedDestinationError, DestinationPolicy, PinningResolver,
+};
+use crate::http_proxy::{
+    find_http_proxy_error, HttpProxyConfigError, HttpProxyConnector, HttpProxyRoutes,
+};
+use crate::outbound_headers::{
+    OutboundHeaders, OutboundHeadersConfigError, NODE_HEADER, SUBNET_HEADER,
+};
 use crate::proto::http_adapter_server::HttpAdapter;
+use crate::request_delay::PseudoRandomDelay;
+use crate::request_tracing::{first_byte_span, request_span};
+use crate::spki_pinning::{
+    find_spki_pin_error, SpkiPinConfigError, SpkiPinningConnector, SpkiPins,
+};
+use http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH};
 use http::Uri;
 use hyper::client::HttpConnector;
 use hyper::{body, Body, Client, Method};
 use hyper_tls::HttpsConnector;
+use ic_interfaces::canister_http::MAX_CANISTER_HTTP_REQUEST_BYTES;
 use ic_protobuf::canister_http::v1::{CanisterHttpRequest, CanisterHttpResponse, HttpHeader};
 use std::fmt::Debug;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
 use tonic::{Request, Response, Status};
+use tracing::{Instrument, Span};
 
+/// Maximum total size of the header names and values of an outgoing request.
+pub const MAX_REQUEST_HEADERS_BYTES: usize = 48 * 1024;
+
+/// Headers that canisters must not set: the hop-by-hop headers of RFC 7230,
+/// section 6.1, which concern the connection of the adapter rather than the
+/// request, the headers that frame the request on that connection, and the
+/// headers stamped by the adapter, which partners may trust.
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "connection",
+    "host",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    NODE_HEADER,
+    SUBNET_HEADER,
+];
+
+/// Errors returned when a request from the replica is rejected before any
+/// outgoing connection is made.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RequestValidationError {
+    #[error("request body of {size} bytes exceeds limit of {limit} bytes")]
+    BodyTooLarge { size: usize, limit: usize },
+    #[error("request headers of {size} bytes exceed limit of {limit} bytes")]
+    HeadersTooLarge { size: usize, limit: usize },
+    #[error("request header name {name:?} is invalid")]
+    InvalidHeaderName { name: String },
+    #[error("value of request header {name} is invalid")]
+    InvalidHeaderValue { name: String },
+    #[error("request header {name} is managed by the adapter and must not be set")]
+    ForbiddenHeader { name: String },
+    #[error("request header content-length {value:?} does not match the body of {size} bytes")]
+    ContentLengthMismatch { value: String, size: usize },
+}
+
+/// Errors returned when the policies of a config are invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PolicyConfigError {
+    #[error("{0}")]
+    SpkiPin(#[from] SpkiPinConfigError),
+    #[error("{0}")]
+    HttpProxy(#[from] HttpProxyConfigError),
+    #[error("{0}")]
+    OutboundHeaders(#[from] OutboundHeadersConfigError),
+    #[error("{0}")]
+    Limits(#[from] LimitsConfigError),
+}
+
+impl From<RequestValidationError> for Status {
+    fn from(err: RequestValidationError) -> Self {
+        Status::new(tonic::Code::InvalidArgument, err.to_string())
+    }
+}
+
+impl From<DeniedDestinationError> for Status {
+    fn from(err: DeniedDestinationError) -> Self {
+        Status::new(
+            tonic::Code::PermissionDenied,
+            format!("Destination denied: {}", err),
+        )
+    }
+}
+
+/// Returns the IP address of `host` if it is one, with IPv6 addresses
+/// enclosed in brackets as in urls.
+fn ip_address_of_host(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok()
+}
+
+fn headers_size(headers: &[HttpHeader]) -> usize {
+    headers.iter().map(|h| h.name.len() + h.value.len()).sum()
+}
+
+/// Fails with `OutOfRange` if a response of `response_size` bytes exceeds the
+/// `max_response_bytes` of its request, where 0 means no limit.
+fn check_response_size(response_size: usize, max_response_bytes: u64) -> Result<(), Status> {
+    if max_response_bytes != 0 && response_size as u64 > max_response_bytes {
+        return Err(Status::new(
+            tonic::Code::OutOfRange,
+            format!(
+                "Response of {} bytes exceeds the limit of {} bytes",
+                response_size, max_response_bytes
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the body and header sizes of `request` against the adapter limits,
+/// returning the total measured request size on success. The body is limited
+/// to the size that the replica admits for canister http requests.
+fn validate_request_size(request: &CanisterHttpRequest) -> Result<usize, RequestValidationError> {
+    if request.body.len() > MAX_CANISTER_HTTP_REQUEST_BYTES {
+        return Err(RequestValidationError::BodyTooLarge {
+            size: request.body.len(),
+            limit: MAX_CANISTER_HTTP_REQUEST_BYTES,
+        });
+    }
+    let headers_size = headers_size(&request.headers);
+    if headers_size > MAX_REQUEST_HEADERS_BYTES {
+        return Err(RequestValidationError::HeadersTooLarge {
+            size: headers_size,
+            limit: MAX_REQUEST_HEADERS_BYTES,
+        });
+    }
+    Ok(request.url.len() + headers_size + request.body.len())
+}
+
+/// Checks the headers of `request` and converts them to the headers of the
+/// outgoing request, with their names in canonical lowercase form.
+///
+/// The forbidden headers are rejected, since the adapter sets them itself
+/// for the connection to the destination. A content-length header is only
+/// accepted if it matches the size of the body.
+fn canonicalize_headers(
+    request: &CanisterHttpRequest,
+) -> Result<HeaderMap, RequestValidationError> {
+    let mut headers = HeaderMap::with_capacity(request.headers.len());
+    for header in &request.headers {
+        let name = HeaderName::from_bytes(header.name.as_bytes()).map_err(|_| {
+            RequestValidationError::InvalidHeaderName {
+                name: header.name.clone(),
+            }
+        })?;
+        if FORBIDDEN_HEADERS.contains(&name.as_str()) {
+            return Err(RequestValidationError::ForbiddenHeader {
+                name: name.to_string(),
+            });
+        }
+        let value = HeaderValue::from_bytes(&header.value).map_err(|_| {
+            RequestValidationError::InvalidHeaderValue {
+                name: name.to_string(),
+            }
+        })?;
+        if name == CONTENT_LENGTH
+            && value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                != Some(request.body.len())
+        {
+            return Err(RequestValidationError::ContentLengthMismatch {
+                value: String::from_utf8_lossy(&header.value).into_owned(),
+                size: request.body.len(),
+            });
+        }
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// The state of the adapter that is derived from its config. It is replaced as
+/// a whole when the config is reloaded.
 #[derive(Debug)]
+struct Policies {
+    https_client: Client<SpkiPinningConnector<HttpsConnector<HttpProxyConnector>>>,
+    destination_policy: DestinationPolicy,
+    outbound_headers: OutboundHeaders,
+    pseudo_random_delay: PseudoRandomDelay,
+    request_timeout: Duration,
+}
+
+impl Policies {
+    fn new(
+        config: &Config,
+        pseudo_random_delay: PseudoRandomDelay,
+    ) -> Result<Self, PolicyConfigError> {
+        config.validate_limits()?;
+        let pins = SpkiPins::new(&config.spki_pins)?;
+        let proxy_routes = HttpProxyRoutes::new(&config.http_proxies, &config.http_proxy_routes)?;
+        let outbound_headers =
+            OutboundHeaders::new(&config.user_agent, config.header_stamping.as_ref())?;
+        let destination_policy = DestinationPolicy::new(config.allow_private_destinations);
+        let resolver = PinningResolver::new(destination_policy);
+        let mut direct = HttpConnector::new_with_resolver(resolver.clone());
+        direct.enforce_http(false);
+        direct.set_happy_eyeballs_timeout(match config.connection_attempt_delay_ms {
+            0 => None,
+            delay_ms => Some(Duration::from_millis(delay_ms)),
+        });
+        direct.set_connect_timeout(Some(Duration::from_millis(config.connect_timeout_ms)));
+        let http = HttpProxyConnector::new(direct, resolver, proxy_routes);
+        let https = SpkiPinningConnector::new(HttpsConnector::new_with_connector(http), pins);
+        let https_client = Client::builder()
+            .pool_max_idle_per_host(config.max_idle_connections_per_host)
+            .build::<_, hyper::Body>(https);
+        Ok(Self {
+            https_client,
+            destination_policy,
+            outbound_headers,
+            pseudo_random_delay,
+            request_timeout: Duration::from_millis(config.request_timeout_ms),
+        })
+    }
+}
+
 /// implements RPC
+///
+/// Clones share their policies, so a clone kept aside of the gRPC server can
+/// [`reload`](HttpFromCanister::reload) the config of the server.
+#[derive(Clone, Debug)]
 pub struct HttpFromCanister {
-    https_client: Client<HttpsConnector<HttpConnector>>,
+    policies: Arc<RwLock<Arc<Policies>>>,
 }
 
 impl HttpFromCanister {
     /// initalize new hyper clients
     pub fn new() -> HttpFromCanister {
-        let https = HttpsConnector::new();
-        let https_client = Client::builder().build::<_, hyper::Body>(https);
-        Self { https_client }
+        Self::with_config(&Config::default()).expect("The default config is valid")
     }
-}
 
-impl Default for HttpFromCanister {
-    fn default() -> Self {
-        Self::new()
+    /// initalize new hyper clients that enforce the SPKI pins, the
+    /// destination policy and the HTTP proxy routes of `config`
+    pub fn with_config(config: &Config) -> Result<HttpFromCanister, PolicyConfigError> {
+        let pseudo_random_delay = PseudoRandomDelay::new(Duration::from_millis(
+            config.max_pseudo_random_delay_window_ms,
+        ));
+        let policies = Policies::new(config, pseudo_random_delay)?;
+        Ok(Self {
+            policies: Arc::new(RwLock::new(Arc::new(policies))),
+        })
     }
-}
 
-#[tonic::async_trait]
-impl HttpAdapter for HttpFromCanister {
-    async fn send_http_request(
+    /// Replaces the policies of the adapter by those of `config`.
+    ///
+    /// Requests that are in flight complete with the policies they started
+    /// with, all later requests are subject to the new policies. If `config`
+    /// is invalid, the current policies are left in place.
+    pub fn reload(&self, config: &Config) -> Result<(), PolicyConfigError> {
+        let pseudo_random_delay =
+            self.current_policies()
+                .pseudo_random_delay
+                .with_max_window(Duration::from_millis(
+                    config.max_pseudo_random_delay_window_ms,
+                ));
+        let policies = Arc::new(Policies::new(config, pseudo_random_delay)?);
+        *self.policies.write().unwrap() = policies;
+        Ok(())
+    }
+
+    fn current_policies(&self) -> Arc<Policies> {
+        Arc::clone(&self.policies.read().unwrap())
+    }
+
+    /// Makes the outgoing request of `req` and returns its response. The span
+    /// of the request must be entered.
+    async fn send(
         &self,
-        request: Request<CanisterHttpRequest>,
+        req: CanisterHttpRequest,
     ) -> Result<Response<CanisterHttpResponse>, Status> {
-        let req = request.into_inner();
+        let policies = self.current_policies();
+
+        let request_size = validate_request_size(&req)?;
+        let max_response_bytes = req.max_response_bytes;
+        let mut headers = canonicalize_headers(&req)?;
+        policies.outbound_headers.apply(&mut headers);
 
         let uri = req
             .url
             .parse::<Uri>()
             .map_err(|_| Status::new(tonic::Code::InvalidArgument, "Failed to parse url"))?;
+        Span::current().record("host", &uri.host().unwrap_or_default());
+
+        // Hosts given as IP addresses are connected to without resolution,
+        // so they are checked here rather than by the resolver.
+        if let Some(ip) = uri.host().and_then(ip_address_of_host) {
+            policies.destination_policy.check_address(&ip)?;
+        }
+
+        let delay = policies
+            .pseudo_random_delay
+            .delay(req.request_id, req.pseudo_random_delay_window_ms);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
 
         // TODO: Connect to SOCKS proxy (NET-881)
-        let http_req = hyper::Request::builder()
+        let mut http_req = hyper::Request::builder()
             .method(Method::GET)
             .uri(uri)
             .body(Body::from(req.body))
             .map_err(|_| {
                 Status::new(tonic::Code::InvalidArgument, "Failed to build http request")
             })?;
+        *http_req.headers_mut() = headers;
+
+        let request_timeout = policies.request_timeout;
+        tokio::time::timeout(request_timeout, Self::fetch(&policies, http_req))
+            .await
+            .map_err(|_| {
+                Status::new(
+                    tonic::Code::DeadlineExceeded,
+                    format!("Request timed out after {:?}", request_timeout),
+                )
+            })?
+            .and_then(|(status, headers, body_bytes)| {
+                let response_size = headers_size(&headers) + body_bytes.len();
+                check_response_size(response_size, max_response_bytes)?;
+                Ok(Response::new(CanisterHttpResponse {
+                    status,
+                    headers,
+                    content: body_bytes.to_vec(),
+                    request_size: request_size as u64,
+                    response_size: response_size as u64,
+                }))
+            })
+    }
 
-        let http_resp = self
+    /// Sends `http_req` with the client of `policies` and receives the status,
+    /// the headers and the body of its response.
+    async fn fetch(
+        policies: &Policies,
+        http_req: hyper::Request<Body>,
+    ) -> Result<(u32, Vec<HttpHeader>, body::Bytes), Status> {
+        let http_resp = policies
             .https_client
             .request(http_req)
+            .instrument(first_byte_span())
             .await
-            .map_err(|_| Status::new(tonic::Code::Unavailable, "Failed to connect"))?;
+            .map_err(|err| {
+                if let Some(destination_error) = find_denied_destination_error(&err) {
+                    return Status::from(destination_error.clone());
+                }
+                if let Some(proxy_error) = find_http_proxy_error(&err) {
+                    return Status::new(
+                        tonic::Code::Unavailable,
+                        format!("Failed to connect through HTTP proxy: {}", proxy_error),
+                    );
+                }
+                match find_spki_pin_error(&err) {
+                    Some(pin_error) => Status::new(
+                        tonic::Code::FailedPrecondition,
+                        format!("TLS certificate pinning failed: {}", pin_error),
+                    ),
+                    None => Status::new(tonic::Code::Unavailable, "Failed to connect"),
+                }
+            })?;
 
         let status = http_resp.status().as_u16() as u32;
+        Span::current().record("status", &status);
 
         let headers = http_resp
             .headers()
@@ -67,15 +387,29 @@ impl HttpAdapter for HttpFromCanister {
             })
             .collect::<Vec<HttpHeader>>();
 
-        // TODO: replace this with a bounded version with timeout. (NET-882)
+        // TODO: replace this with a bounded version. (NET-882)
         let body_bytes = body::to_bytes(http_resp)
             .await
             .map_err(|_| Status::new(tonic::Code::Unavailable, "Failed to fetch body"))?;
 
-        Ok(Response::new(CanisterHttpResponse {
-            status,
-            headers,
-            content: body_bytes.to_vec(),
-        }))
+        Ok((status, headers, body_bytes))
+    }
+}
+
+impl Default for HttpFromCanister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tonic::async_trait]
+impl HttpAdapter for HttpFromCanister {
+    async fn send_http_request(
+        &self,
+        request: Request<CanisterHttpRequest>,
+    ) -> Result<Response<CanisterHttpResponse>, Status> {
+        let span = request_span(request.get_ref().request_id, request.metadata());
+        let req = request.into_inner();
+        self.send(req).instrument(span).await
     }
 }
@@ -0,0 +1,212 @@
+use crate::request_tracing::tls_span;
+use http::Uri;
+use hyper_tls::MaybeHttpsStream;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower::Service;
+use tracing::Instrument;
+
+/// SHA-256 hash of a DER-encoded SubjectPublicKeyInfo.
+pub type SpkiHash = [u8; 32];
+
+/// Errors returned when the configured SPKI pins are invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SpkiPinConfigError {
+    #[error("no SPKI pins given for host {host}")]
+    NoPins { host: String },
+    #[error("invalid SPKI pin {pin} for host {host}: {reason}")]
+    InvalidPin {
+        host: String,
+        pin: String,
+        reason: String,
+    },
+}
+
+/// Errors returned when a connection to a pinned host is rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SpkiPinError {
+    #[error("connection to pinned host {host} does not use TLS")]
+    NotTls { host: String },
+    #[error("pinned host {host} did not present a certificate")]
+    NoCertificate { host: String },
+    #[error("failed to extract the SPKI from the certificate of pinned host {host}: {reason}")]
+    MalformedCertificate { host: String, reason: String },
+    #[error("SPKI hash {found} of the certificate of host {host} matches none of its pins")]
+    PinMismatch { host: String, found: String },
+}
+
+/// The SPKI pins of the pinned hosts, keyed by lowercase hostname.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpkiPins {
+    pins: Arc<BTreeMap<String, BTreeSet<SpkiHash>>>,
+}
+
+impl SpkiPins {
+    /// Parses the pins in `config`, which maps hostnames to base64-encoded
+    /// SPKI hashes.
+    pub fn new(config: &BTreeMap<String, BTreeSet<String>>) -> Result<Self, SpkiPinConfigError> {
+        let mut pins = BTreeMap::new();
+        for (host, host_pins) in config {
+            if host_pins.is_empty() {
+                return Err(SpkiPinConfigError::NoPins { host: host.clone() });
+            }
+            let hashes = host_pins
+                .iter()
+                .map(|pin| parse_pin(host, pin))
+                .collect::<Result<BTreeSet<_>, _>>()?;
+            pins.insert(host.to_lowercase(), hashes);
+        }
+        Ok(Self {
+            pins: Arc::new(pins),
+        })
+    }
+
+    /// Returns the pins of `host`, or `None` if the host is not pinned.
+    pub fn get(&self, host: &str) -> Option<&BTreeSet<SpkiHash>> {
+        self.pins.get(&host.to_lowercase())
+    }
+}
+
+fn parse_pin(host: &str, pin: &str) -> Result<SpkiHash, SpkiPinConfigError> {
+    let invalid_pin = |reason: String| SpkiPinConfigError::InvalidPin {
+        host: host.to_string(),
+        pin: pin.to_string(),
+        reason,
+    };
+    let bytes = base64::decode(pin).map_err(|e| invalid_pin(e.to_string()))?;
+    let mut hash = [0; 32];
+    if bytes.len() != hash.len() {
+        return Err(invalid_pin(format!(
+            "expected a SHA-256 hash of {} bytes but got {} bytes",
+            hash.len(),
+            bytes.len()
+        )));
+    }
+    hash.copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+/// Computes the SHA-256 hash of the SubjectPublicKeyInfo of the DER-encoded
+/// X.509 `certificate`.
+pub fn spki_hash(certificate: &[u8]) -> Result<SpkiHash, String> {
+    let certificate = openssl::x509::X509::from_der(certificate).map_err(|e| e.to_string())?;
+    let spki = certificate
+        .public_key()
+        .and_then(|public_key| public_key.public_key_to_der())
+        .map_err(|e| e.to_string())?;
+    Ok(openssl::sha::sha256(&spki))
+}
+
+/// Connects with the inner connector and, if the destination host is pinned,
+/// rejects the connection unless the TLS certificate presented by the host
+/// matches one of its pins. The check happens right after the TLS handshake,
+/// before any part of the request is sent.
+#[derive(Clone, Debug)]
+pub struct SpkiPinningConnector<C> {
+    inner: C,
+    pins: SpkiPins,
+}
+
+impl<C> SpkiPinningConnector<C> {
+    pub fn new(inner: C, pins: SpkiPins) -> Self {
+        Self { inner, pins }
+    }
+}
+
+impl<C, T> Service<Uri> for SpkiPinningConnector<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<T>>,
+    C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    C::Future: Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = MaybeHttpsStream<T>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let pinned = uri.host().and_then(|host| {
+            self.pins
+                .get(host)
+                .map(|pins| (host.to_string(), pins.clone()))
+        });
+        let span = tls_span(uri.host().unwrap_or_default());
+        // The inner connectors create their spans when called, so they are
+        // called within the TLS span to nest their spans in it.
+        let connecting = span.in_scope(|| self.inner.call(uri));
+        Box::pin(
+            async move {
+                let stream = connecting.await.map_err(Into::into)?;
+                if let Some((host, pins)) = pinned {
+                    verify_pins(&host, &pins, &stream)?;
+                }
+                Ok(stream)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+fn verify_pins<T>(
+    host: &str,
+    pins: &BTreeSet<SpkiHash>,
+    stream: &MaybeHttpsStream<T>,
+) -> Result<(), SpkiPinError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let tls = match stream {
+        MaybeHttpsStream::Https(tls) => tls,
+        MaybeHttpsStream::Http(_) => {
+            return Err(SpkiPinError::NotTls {
+                host: host.to_string(),
+            })
+        }
+    };
+    let malformed_certificate = |reason: String| SpkiPinError::MalformedCertificate {
+        host: host.to_string(),
+        reason,
+    };
+    let certificate = tls
+        .get_ref()
+        .peer_certificate()
+        .map_err(|e| malformed_certificate(e.to_string()))?
+        .ok_or_else(|| SpkiPinError::NoCertificate {
+            host: host.to_string(),
+        })?;
+    let der = certificate
+        .to_der()
+        .map_err(|e| malformed_certificate(e.to_string()))?;
+    let hash = spki_hash(&der).map_err(malformed_certificate)?;
+    if pins.contains(&hash) {
+        Ok(())
+    } else {
+        Err(SpkiPinError::PinMismatch {
+            host: host.to_string(),
+            found: base64::encode(hash),
+        })
+    }
+}
+
+/// Returns the pinning error that caused `err`, if any.
+pub(crate) fn find_spki_pin_error<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a SpkiPinError> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(pin_error) = err.downcast_ref::<SpkiPinError>() {
+            return Some(pin_error);
+        }
+        source = err.source();
+    }
+    None
+}
@@ -0,0 +1,326 @@
+//! Runs the gRPC service of the adapter against servers on localhost, e.g.
+//! servers that speak only HTTP/2, trickle their responses or present an
+//! untrusted certificate, so that the behavior of the adapter towards such
+//! destinations is covered without the system tests.
+use http::header::LOCATION;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Request, Response, StatusCode};
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::ssl::{Ssl, SslAcceptor, SslMethod};
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509NameBuilder, X509};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio_openssl::SslStream;
+use tonic::Status;
+
+use ic_canister_http_adapter::{
+    proto::http_adapter_client::HttpAdapterClient, Config, HttpFromCanister,
+};
+use ic_protobuf::canister_http::v1::{CanisterHttpRequest, CanisterHttpResponse};
+
+mod common;
+use common::setup_loop_channel_unix_with;
+
+const BODY: &str = "hello from localhost";
+
+#[tokio::test]
+async fn test_http1_server() {
+    let addr = spawn_server(Http::new().http1_only(true), |_| {
+        Response::builder()
+            .status(StatusCode::CREATED)
+            .header("x-test", "value")
+            .body(Body::from(BODY))
+            .unwrap()
+    })
+    .await;
+
+    let response = send(format!("http://{}", addr)).await.unwrap();
+
+    assert_eq!(response.status, StatusCode::CREATED.as_u16() as u32);
+    assert!(response
+        .headers
+        .iter()
+        .any(|h| h.name == "x-test" && h.value == b"value"));
+    assert_eq!(response.content, BODY.as_bytes());
+    let headers_size: usize = response
+        .headers
+        .iter()
+        .map(|h| h.name.len() + h.value.len())
+        .sum();
+    assert_eq!(response.response_size as usize, headers_size + BODY.len());
+}
+
+#[tokio::test]
+async fn test_server_with_http1_and_http2() {
+    let addr = spawn_server(Http::new(), ok).await;
+
+    let response = send(format!("http://{}", addr)).await.unwrap();
+
+    assert_eq!(response.status, StatusCode::OK.as_u16() as u32);
+    assert_eq!(response.content, BODY.as_bytes());
+}
+
+#[tokio::test]
+async fn test_http2_only_server() {
+    // The adapter speaks HTTP/1.1 to destinations without TLS and does not
+    // attempt HTTP/2 with prior knowledge.
+    let addr = spawn_server(Http::new().http2_only(true), ok).await;
+
+    let status = send(format!("http://{}", addr)).await.unwrap_err();
+
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+}
+
+#[tokio::test]
+async fn test_slow_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+            BODY.len(),
+            BODY
+        );
+        for byte in response.as_bytes() {
+            if stream.write_all(&[*byte]).await.is_err() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+
+    let response = send(format!("http://{}", addr)).await.unwrap();
+
+    assert_eq!(response.status, StatusCode::OK.as_u16() as u32);
+    assert_eq!(response.content, BODY.as_bytes());
+}
+
+#[tokio::test]
+async fn test_request_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        // Accepts the connection but never responds.
+        let (_stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    });
+
+    let status = send_with_config(
+        format!("http://{}", addr),
+        Config {
+            allow_private_destinations: true,
+            connect_timeout_ms: 100,
+            request_timeout_ms: 200,
+            ..Config::default()
+        },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+}
+
+#[tokio::test]
+async fn test_large_response() {
+    let addr = spawn_server(Http::new(), |_| {
+        Response::new(Body::from(vec![b'x'; 4 * 1024 * 1024]))
+    })
+    .await;
+
+    let response = send(format!("http://{}", addr)).await.unwrap();
+
+    assert_eq!(response.status, StatusCode::OK.as_u16() as u32);
+    assert_eq!(response.content.len(), 4 * 1024 * 1024);
+    assert!(response.response_size as usize >= response.content.len());
+}
+
+#[tokio::test]
+async fn test_response_exceeding_max_response_bytes() {
+    let addr = spawn_server(Http::new(), ok).await;
+    let config = Config {
+        allow_private_destinations: true,
+        ..Config::default()
+    };
+
+    let response = send_with_max_response_bytes(format!("http://{}", addr), config.clone(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(response.content, BODY.as_bytes());
+
+    let status = send_with_max_response_bytes(format!("http://{}", addr), config, 10)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::OutOfRange);
+}
+
+#[tokio::test]
+async fn test_redirects_are_not_followed() {
+    let addr = spawn_server(Http::new(), |req| match req.uri().path() {
+        "/final" => ok(req),
+        _ => Response::builder()
+            .status(StatusCode::FOUND)
+            .header(LOCATION, "/final")
+            .body(Body::empty())
+            .unwrap(),
+    })
+    .await;
+
+    let response = send(format!("http://{}/start", addr)).await.unwrap();
+
+    assert_eq!(response.status, StatusCode::FOUND.as_u16() as u32);
+    assert!(response
+        .headers
+        .iter()
+        .any(|h| h.name == LOCATION.as_str() && h.value == b"/final"));
+    assert!(response.content.is_empty());
+}
+
+#[tokio::test]
+async fn test_untrusted_tls_certificate() {
+    let addr = spawn_tls_server(ok).await;
+
+    let status = send(format!("https://localhost:{}", addr.port()))
+        .await
+        .unwrap_err();
+
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+}
+
+fn ok(_: Request<Body>) -> Response<Body> {
+    Response::new(Body::from(BODY))
+}
+
+/// Sends a GET request for `url` through an adapter that allows private
+/// destinations.
+async fn send(url: String) -> Result<CanisterHttpResponse, Status> {
+    send_with_config(
+        url,
+        Config {
+            allow_private_destinations: true,
+            ..Config::default()
+        },
+    )
+    .await
+}
+
+/// Sends a GET request for `url` through an adapter with `config`.
+async fn send_with_config(url: String, config: Config) -> Result<CanisterHttpResponse, Status> {
+    send_with_max_response_bytes(url, config, 0).await
+}
+
+/// Sends a GET request for `url`, whose response may not exceed
+/// `max_response_bytes`, through an adapter with `config`.
+async fn send_with_max_response_bytes(
+    url: String,
+    config: Config,
+    max_response_bytes: u64,
+) -> Result<CanisterHttpResponse, Status> {
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let request = CanisterHttpRequest {
+        url,
+        body: Vec::new(),
+        headers: Vec::new(),
+        request_id: 0,
+        pseudo_random_delay_window_ms: 0,
+        max_response_bytes,
+    };
+    client
+        .send_http_request(tonic::Request::new(request))
+        .await
+        .map(tonic::Response::into_inner)
+}
+
+/// Starts a server on localhost that serves the connections with `http` and
+/// answers every request with `respond`. Returns the address of the server.
+async fn spawn_server(http: Http, respond: fn(Request<Body>) -> Response<Body>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let http = http.clone();
+            tokio::spawn(async move {
+                let service =
+                    service_fn(move |req| async move { Ok::<_, Infallible>(respond(req)) });
+                let _ = http.serve_connection(stream, service).await;
+            });
+        }
+    });
+    addr
+}
+
+/// Starts a server on localhost that presents a self-signed certificate for
+/// `localhost` and answers every request with `respond`. Returns the address
+/// of the server.
+async fn spawn_tls_server(respond: fn(Request<Body>) -> Response<Body>) -> SocketAddr {
+    let acceptor = self_signed_acceptor();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let ssl = Ssl::new(acceptor.context()).unwrap();
+            tokio::spawn(async move {
+                let mut stream = SslStream::new(ssl, stream).unwrap();
+                // The handshake fails if the client rejects the certificate.
+                if Pin::new(&mut stream).accept().await.is_err() {
+                    return;
+                }
+                let service =
+                    service_fn(move |req| async move { Ok::<_, Infallible>(respond(req)) });
+                let _ = Http::new().serve_connection(stream, service).await;
+            });
+        }
+    });
+    addr
+}
+
+fn self_signed_acceptor() -> SslAcceptor {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "localhost")
+        .unwrap();
+    let name = name.build();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .build(&cert.x509v3_context(None, None))
+        .unwrap();
+    cert.append_extension(san).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    let cert = cert.build();
+
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    acceptor.build()
+}
@@ -1,17 +1,21 @@
-use futures::TryFutureExt;
 use http::StatusCode;
-use std::convert::TryFrom;
-use tokio::net::UnixStream;
-use tonic::transport::{Channel, Endpoint, Server, Uri};
-use tower::service_fn;
-use uuid::Uuid;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tonic::transport::Channel;
 
+use ic_base_types::PrincipalId;
 use ic_canister_http_adapter::{
-    proto::{http_adapter_client::HttpAdapterClient, http_adapter_server::HttpAdapterServer},
-    HttpFromCanister,
+    proto::http_adapter_client::HttpAdapterClient, Config, DestinationPolicy, HeaderStampingConfig,
+    HttpFromCanister, HttpProxyAuth, HttpProxyConfig, HttpProxyConfigError, HttpProxyRoute,
+    OutboundHeadersConfigError, PolicyConfigError, PseudoRandomDelay, SpkiPinConfigError,
 };
+use ic_interfaces::canister_http::MAX_CANISTER_HTTP_REQUEST_BYTES;
 use ic_protobuf::canister_http::v1::{CanisterHttpRequest, HttpHeader};
-use unix::UnixListenerDrop;
+
+mod common;
+use common::setup_loop_channel_unix_with;
 
 #[tokio::test]
 async fn test_https() {
@@ -65,143 +69,650 @@ async fn test_no_http() {
     assert!(response.is_err());
 }
 
-// TODO: increase functionality of this function (NET-883)
-fn build_http_canister_request(url: String) -> CanisterHttpRequest {
-    let headers = vec![HttpHeader {
-        name: "User-Agent".to_string(),
-        value: "test".as_bytes().to_vec(),
-    }];
+#[tokio::test]
+async fn test_request_body_too_large() {
+    let channel = setup_loop_channel_unix().await;
 
-    CanisterHttpRequest {
-        url,
-        body: "".to_string().into_bytes(),
-        headers,
-    }
-}
+    let mut client = HttpAdapterClient::new(channel);
 
-async fn setup_loop_channel_unix() -> Channel {
-    let uuid = Uuid::new_v4();
-    let path = "/tmp/canister-http-test-".to_string() + &uuid.to_string();
+    let mut request = build_http_canister_request("https://www.google.com".to_string());
+    request.body = vec![0; MAX_CANISTER_HTTP_REQUEST_BYTES + 1];
 
-    let canister_http = HttpFromCanister::new();
+    let response = client.send_http_request(tonic::Request::new(request)).await;
+    assert_eq!(response.unwrap_err().code(), tonic::Code::InvalidArgument);
+}
 
-    // anonymous type that implements stream trait with item type: Result<UnixStream, Error>.
-    let incoming = {
-        let uds = UnixListenerDrop::bind(path.clone()).unwrap();
+#[tokio::test]
+async fn test_forbidden_request_headers() {
+    let channel = setup_loop_channel_unix().await;
 
-        async_stream::stream! {
-            loop {
-                let item = uds.accept().map_ok(|(st, _)| unix::UnixStream(st)).await;
-                yield item;
-            }
-        }
-    };
+    let mut client = HttpAdapterClient::new(channel);
 
-    // spawn gRPC server
-    tokio::spawn(async move {
-        Server::builder()
-            .add_service(HttpAdapterServer::new(canister_http))
-            .serve_with_incoming(incoming)
+    for name in [
+        "Host",
+        "Transfer-Encoding",
+        "connection",
+        "Proxy-Authorization",
+        "X-IC-Subnet",
+        "X-IC-Node",
+    ]
+    .iter()
+    {
+        let mut request = build_http_canister_request("https://www.google.com".to_string());
+        request.headers.push(HttpHeader {
+            name: name.to_string(),
+            value: b"value".to_vec(),
+        });
+        let status = client
+            .send_http_request(tonic::Request::new(request))
             .await
-            .expect("server shutdown")
+            .unwrap_err();
+        assert_eq!(
+            status.code(),
+            tonic::Code::InvalidArgument,
+            "header {}",
+            name
+        );
+        assert!(
+            status.message().contains(&name.to_lowercase()),
+            "header {}",
+            name
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_content_length_mismatch() {
+    let channel = setup_loop_channel_unix().await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let mut request = build_http_canister_request("https://www.google.com".to_string());
+    request.body = b"body".to_vec();
+    request.headers.push(HttpHeader {
+        name: "Content-Length".to_string(),
+        value: b"5".to_vec(),
     });
+    let status = client
+        .send_http_request(tonic::Request::new(request))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(status.message().contains("content-length"));
+}
 
-    // port can be ignored
-    let channel = Endpoint::try_from("http://[::]:50151")
+#[tokio::test]
+async fn test_request_headers_are_canonicalized() {
+    let url = spawn_echo_server().await;
+    let canister_http = HttpFromCanister::with_config(&Config {
+        allow_private_destinations: true,
+        ..Config::default()
+    })
+    .unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let mut request = build_http_canister_request(url);
+    request.headers.push(HttpHeader {
+        name: "X-Custom-Header".to_string(),
+        value: b"Value".to_vec(),
+    });
+    let response = client
+        .send_http_request(tonic::Request::new(request))
+        .await
         .unwrap()
-        .connect_with_connector(service_fn(move |_: Uri| {
-            // Connect to a Uds socket
-            UnixStream::connect(path.clone())
-        }))
+        .into_inner();
+    let echoed_request = String::from_utf8(response.content).unwrap();
+    assert!(echoed_request.contains("\r\nx-custom-header: Value\r\n"));
+    assert!(echoed_request.contains("\r\nuser-agent: test\r\n"));
+}
+
+/// Sends a request without headers to an echo server through an adapter with
+/// `config` and returns the lines of the head of the request that the server
+/// received.
+async fn echoed_request_lines(config: Config) -> Vec<String> {
+    let url = spawn_echo_server().await;
+    let canister_http = HttpFromCanister::with_config(&Config {
+        allow_private_destinations: true,
+        ..config
+    })
+    .unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let mut request = build_http_canister_request(url);
+    request.headers.clear();
+    let response = client
+        .send_http_request(tonic::Request::new(request))
         .await
-        .unwrap();
+        .unwrap()
+        .into_inner();
+    String::from_utf8(response.content)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_default_outbound_headers() {
+    let lines = echoed_request_lines(Config::default()).await;
+    assert!(lines.contains(&"user-agent: ic-canister-http-adapter".to_string()));
+    assert!(!lines.iter().any(|line| line.starts_with("x-ic-")));
+
+    let lines = echoed_request_lines(Config {
+        user_agent: String::new(),
+        ..Config::default()
+    })
+    .await;
+    assert!(!lines.iter().any(|line| line.starts_with("user-agent:")));
+}
 
-    channel
+#[tokio::test]
+async fn test_stamped_headers_only_carry_the_configured_ids() {
+    let subnet_id = PrincipalId::new_subnet_test_id(1).to_string();
+    let node_id = PrincipalId::new_node_test_id(2).to_string();
+    let lines = echoed_request_lines(Config {
+        user_agent: "partner-test".to_string(),
+        header_stamping: Some(HeaderStampingConfig {
+            subnet_id: subnet_id.clone(),
+            node_id: Some(node_id.clone()),
+        }),
+        ..Config::default()
+    })
+    .await;
+
+    assert!(lines.contains(&"user-agent: partner-test".to_string()));
+    assert!(lines.contains(&format!("x-ic-subnet: {}", subnet_id)));
+    assert!(lines.contains(&format!("x-ic-node: {}", node_id)));
+    // Apart from the request line and the stamps, the request only carries
+    // the headers that every request carries, and nothing about the node.
+    for line in lines.iter().skip(1).filter(|line| !line.is_empty()) {
+        let name = line.split(':').next().unwrap();
+        assert!(
+            [
+                "host",
+                "user-agent",
+                "content-length",
+                "x-ic-subnet",
+                "x-ic-node"
+            ]
+            .contains(&name),
+            "unexpected header {}",
+            line
+        );
+    }
 }
-// implements unix listener that removes socket file when done
-// adapter does not need this because the socket is managed by systemd
-mod unix {
-    use std::path::{Path, PathBuf};
-    use std::{
-        pin::Pin,
-        sync::Arc,
-        task::{Context, Poll},
+
+#[test]
+fn test_invalid_outbound_headers_config() {
+    let config = Config {
+        user_agent: "agent\n".to_string(),
+        ..Config::default()
     };
-    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-    use tokio::net::unix::SocketAddr;
-    use tonic::transport::server::Connected;
+    assert!(matches!(
+        HttpFromCanister::with_config(&config),
+        Err(PolicyConfigError::OutboundHeaders(
+            OutboundHeadersConfigError::InvalidUserAgent { .. }
+        ))
+    ));
 
-    pub struct UnixListenerDrop {
-        path: PathBuf,
-        listener: tokio::net::UnixListener,
+    // Only principal ids can be stamped, e.g. no addresses of the node.
+    let subnet_id = PrincipalId::new_subnet_test_id(1).to_string();
+    let node_id = PrincipalId::new_node_test_id(1).to_string();
+    for (subnet_id, node_id) in [
+        ("10.0.0.1", None),
+        ("fd00::1", Some(node_id.as_str())),
+        (subnet_id.as_str(), Some("10.0.0.1")),
+    ]
+    .iter()
+    {
+        let config = Config {
+            header_stamping: Some(HeaderStampingConfig {
+                subnet_id: subnet_id.to_string(),
+                node_id: node_id.map(str::to_string),
+            }),
+            ..Config::default()
+        };
+        assert!(
+            matches!(
+                HttpFromCanister::with_config(&config),
+                Err(PolicyConfigError::OutboundHeaders(
+                    OutboundHeadersConfigError::InvalidPrincipalId { .. }
+                ))
+            ),
+            "subnet id {}, node id {:?}",
+            subnet_id,
+            node_id
+        );
     }
+}
 
-    impl UnixListenerDrop {
-        pub fn bind(path: impl AsRef<Path>) -> std::io::Result<Self> {
-            let path = path.as_ref().to_owned();
-            tokio::net::UnixListener::bind(&path)
-                .map(|listener| UnixListenerDrop { path, listener })
-        }
-        pub async fn accept(&self) -> tokio::io::Result<(tokio::net::UnixStream, SocketAddr)> {
-            self.listener.accept().await
-        }
+fn config_with_spki_pins(host: &str, pins: &[&str]) -> Config {
+    let mut spki_pins = BTreeMap::new();
+    spki_pins.insert(
+        host.to_string(),
+        pins.iter()
+            .map(|pin| pin.to_string())
+            .collect::<BTreeSet<_>>(),
+    );
+    Config {
+        spki_pins,
+        ..Config::default()
     }
+}
 
-    impl Drop for UnixListenerDrop {
-        fn drop(&mut self) {
-            // There's no way to return a useful error here
-            let _ = std::fs::remove_file(&self.path).unwrap();
-        }
+#[tokio::test]
+async fn test_spki_pin_mismatch() {
+    // base64 encoding of 32 zero bytes, which is no valid SPKI hash
+    let config = config_with_spki_pins(
+        "www.google.com",
+        &["AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="],
+    );
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let request = tonic::Request::new(build_http_canister_request(
+        "https://www.google.com".to_string(),
+    ));
+
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert!(status.message().contains("matches none of its pins"));
+}
+
+#[tokio::test]
+async fn test_spki_pins_apply_only_to_pinned_hosts() {
+    let config = config_with_spki_pins(
+        "www.bing.com",
+        &["AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="],
+    );
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let request = tonic::Request::new(build_http_canister_request(
+        "https://www.google.com".to_string(),
+    ));
+
+    let response = client.send_http_request(request).await;
+    assert_eq!(
+        response.unwrap().into_inner().status,
+        StatusCode::OK.as_u16() as u32
+    );
+}
+
+#[test]
+fn test_invalid_spki_pin_config() {
+    let config = config_with_spki_pins("www.google.com", &["not base64"]);
+    assert!(matches!(
+        HttpFromCanister::with_config(&config),
+        Err(PolicyConfigError::SpkiPin(
+            SpkiPinConfigError::InvalidPin { .. }
+        ))
+    ));
+
+    // base64 encoding of 16 zero bytes, which is too short for a SHA-256 hash
+    let config = config_with_spki_pins("www.google.com", &["AAAAAAAAAAAAAAAAAAAAAA=="]);
+    assert!(matches!(
+        HttpFromCanister::with_config(&config),
+        Err(PolicyConfigError::SpkiPin(
+            SpkiPinConfigError::InvalidPin { .. }
+        ))
+    ));
+
+    let config = config_with_spki_pins("www.google.com", &[]);
+    assert_eq!(
+        HttpFromCanister::with_config(&config).unwrap_err(),
+        PolicyConfigError::SpkiPin(SpkiPinConfigError::NoPins {
+            host: "www.google.com".to_string()
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_private_destinations_denied() {
+    let channel = setup_loop_channel_unix().await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    for url in [
+        "http://127.0.0.1:8080",
+        "https://[::1]",
+        "http://[::ffff:10.0.0.1]",
+        "http://169.254.169.254/latest/meta-data",
+        "http://localhost:8080",
+    ]
+    .iter()
+    {
+        let request = tonic::Request::new(build_http_canister_request(url.to_string()));
+        let status = client.send_http_request(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied, "url {}", url);
     }
+}
 
-    #[derive(Debug)]
-    pub struct UnixStream(pub tokio::net::UnixStream);
+#[tokio::test]
+async fn test_reload_config() {
+    let canister_http = HttpFromCanister::new();
+    let channel = setup_loop_channel_unix_with(canister_http.clone()).await;
 
-    impl Connected for UnixStream {
-        type ConnectInfo = UdsConnectInfo;
+    let mut client = HttpAdapterClient::new(channel);
 
-        fn connect_info(&self) -> Self::ConnectInfo {
-            UdsConnectInfo {
-                peer_addr: self.0.peer_addr().ok().map(Arc::new),
-                peer_cred: self.0.peer_cred().ok(),
-            }
-        }
+    // Nothing listens on port 1, so the connection is refused once the
+    // destination is allowed.
+    let url = "http://127.0.0.1:1".to_string();
+    let request = tonic::Request::new(build_http_canister_request(url.clone()));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    canister_http
+        .reload(&Config {
+            allow_private_destinations: true,
+            ..Config::default()
+        })
+        .unwrap();
+    let request = tonic::Request::new(build_http_canister_request(url.clone()));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    // An invalid config leaves the current policies in place.
+    let mut invalid_config = config_with_spki_pins("www.google.com", &[]);
+    invalid_config.allow_private_destinations = false;
+    assert!(canister_http.reload(&invalid_config).is_err());
+    let request = tonic::Request::new(build_http_canister_request(url));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+}
+
+fn config_with_http_proxy(url: String, auth: HttpProxyAuth, hosts: &[&str]) -> Config {
+    let mut http_proxies = BTreeMap::new();
+    http_proxies.insert(
+        "egress".to_string(),
+        HttpProxyConfig {
+            url,
+            auth: Some(auth),
+        },
+    );
+    Config {
+        http_proxies,
+        http_proxy_routes: vec![HttpProxyRoute {
+            hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            proxy: Some("egress".to_string()),
+        }],
+        ..Config::default()
     }
+}
+
+#[tokio::test]
+async fn test_http_proxy() {
+    let proxy_url = spawn_connect_proxy("Bearer secret").await;
+    let config = Config {
+        allow_private_destinations: true,
+        ..config_with_http_proxy(
+            proxy_url,
+            HttpProxyAuth::Bearer {
+                token: "secret".to_string(),
+            },
+            &["localhost", "*.localhost"],
+        )
+    };
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    let request = tonic::Request::new(build_http_canister_request("http://localhost".to_string()));
+    let response = client
+        .send_http_request(request)
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.status, StatusCode::OK.as_u16() as u32);
+    assert_eq!(response.content, b"tunneled");
+}
+
+#[tokio::test]
+async fn test_http_proxy_denies_private_destinations() {
+    let proxy_url = spawn_connect_proxy("Bearer secret").await;
+    let config = config_with_http_proxy(
+        proxy_url,
+        HttpProxyAuth::Bearer {
+            token: "secret".to_string(),
+        },
+        &["localhost"],
+    );
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
+
+    // Proxied hostnames are resolved and checked before the tunnel is opened.
+    let request = tonic::Request::new(build_http_canister_request("http://localhost".to_string()));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // Hosts matching no route are connected to directly.
+    let request = tonic::Request::new(build_http_canister_request(
+        "http://127.0.0.1:8080".to_string(),
+    ));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn test_http_proxy_authentication_failure() {
+    // base64 encoding of "user:password"
+    let proxy_url = spawn_connect_proxy("Basic dXNlcjpwYXNzd29yZA==").await;
+    let config = Config {
+        allow_private_destinations: true,
+        ..config_with_http_proxy(
+            proxy_url,
+            HttpProxyAuth::Basic {
+                username: "user".to_string(),
+                password: "wrong password".to_string(),
+            },
+            &["*"],
+        )
+    };
+    let canister_http = HttpFromCanister::with_config(&config).unwrap();
+    let channel = setup_loop_channel_unix_with(canister_http).await;
+
+    let mut client = HttpAdapterClient::new(channel);
 
-    #[derive(Clone, Debug)]
-    pub struct UdsConnectInfo {
-        pub peer_addr: Option<Arc<tokio::net::unix::SocketAddr>>,
-        pub peer_cred: Option<tokio::net::unix::UCred>,
+    let request = tonic::Request::new(build_http_canister_request("http://localhost".to_string()));
+    let status = client.send_http_request(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+    assert!(status.message().contains("status 407"));
+}
+
+#[test]
+fn test_invalid_http_proxy_config() {
+    let config = Config {
+        http_proxy_routes: vec![HttpProxyRoute {
+            hosts: vec!["*".to_string()],
+            proxy: Some("missing".to_string()),
+        }],
+        ..Config::default()
+    };
+    assert_eq!(
+        HttpFromCanister::with_config(&config).unwrap_err(),
+        PolicyConfigError::HttpProxy(HttpProxyConfigError::UnknownProxy {
+            proxy: "missing".to_string()
+        })
+    );
+
+    let bearer = HttpProxyAuth::Bearer {
+        token: "secret".to_string(),
+    };
+    let config = config_with_http_proxy(
+        "https://proxy.example.com".to_string(),
+        bearer.clone(),
+        &["*"],
+    );
+    assert!(matches!(
+        HttpFromCanister::with_config(&config),
+        Err(PolicyConfigError::HttpProxy(
+            HttpProxyConfigError::InvalidUrl { .. }
+        ))
+    ));
+
+    let config = config_with_http_proxy(
+        "http://proxy.example.com:3128".to_string(),
+        bearer,
+        &["www.*.com"],
+    );
+    assert!(matches!(
+        HttpFromCanister::with_config(&config),
+        Err(PolicyConfigError::HttpProxy(
+            HttpProxyConfigError::InvalidHostPattern { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_pseudo_random_delay() {
+    let delay = PseudoRandomDelay::with_salt(7, Duration::from_millis(1000));
+
+    assert_eq!(delay.delay(1, 0), Duration::ZERO);
+    for request_id in 0..100 {
+        assert!(delay.delay(request_id, 100) < Duration::from_millis(100));
+        // The requested window is capped at the maximum window.
+        assert!(delay.delay(request_id, 5000) < Duration::from_millis(1000));
+        // Retries of a request are delayed by the same duration.
+        assert_eq!(delay.delay(request_id, 100), delay.delay(request_id, 100));
     }
 
-    impl AsyncRead for UnixStream {
-        fn poll_read(
-            mut self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &mut ReadBuf<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Pin::new(&mut self.0).poll_read(cx, buf)
-        }
+    // Changing the maximum window keeps the salt.
+    let reloaded_delay = delay.with_max_window(Duration::from_millis(2000));
+    assert_eq!(reloaded_delay.delay(3, 100), delay.delay(3, 100));
+    assert!(reloaded_delay.delay(3, 5000) < Duration::from_millis(2000));
+
+    // Adapters with different salts spread the same requests differently.
+    let other_delay = PseudoRandomDelay::with_salt(8, Duration::from_millis(1000));
+    assert!((0..100)
+        .any(|request_id| delay.delay(request_id, 1000) != other_delay.delay(request_id, 1000)));
+}
+
+#[test]
+fn test_destination_policy() {
+    let policy = DestinationPolicy::default();
+    for ip in [
+        "0.0.0.0",
+        "10.1.2.3",
+        "100.64.0.1",
+        "127.0.0.1",
+        "172.16.0.1",
+        "192.168.1.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "::ffff:192.168.1.1",
+        "64:ff9b::7f00:1",
+        "fd00::1",
+        "fe80::1",
+    ]
+    .iter()
+    {
+        assert!(policy.is_denied(&ip.parse().unwrap()), "ip {}", ip);
+    }
+    for ip in ["8.8.8.8", "2001:4860:4860::8888", "::ffff:8.8.8.8"].iter() {
+        assert!(!policy.is_denied(&ip.parse().unwrap()), "ip {}", ip);
     }
 
-    impl AsyncWrite for UnixStream {
-        fn poll_write(
-            mut self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &[u8],
-        ) -> Poll<std::io::Result<usize>> {
-            Pin::new(&mut self.0).poll_write(cx, buf)
-        }
+    let permissive_policy = DestinationPolicy::new(true);
+    assert!(!permissive_policy.is_denied(&"127.0.0.1".parse().unwrap()));
+}
 
-        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
-            Pin::new(&mut self.0).poll_flush(cx)
+// TODO: increase functionality of this function (NET-883)
+fn build_http_canister_request(url: String) -> CanisterHttpRequest {
+    let headers = vec![HttpHeader {
+        name: "User-Agent".to_string(),
+        value: "test".as_bytes().to_vec(),
+    }];
+
+    CanisterHttpRequest {
+        url,
+        body: "".to_string().into_bytes(),
+        headers,
+        request_id: 0,
+        pseudo_random_delay_window_ms: 0,
+        max_response_bytes: 0,
+    }
+}
+
+/// Starts an HTTP proxy on localhost that accepts CONNECT requests to port 80
+/// of a loopback address carrying `expected_authorization` and answers the
+/// tunneled request itself. Returns the url of the proxy.
+async fn spawn_connect_proxy(expected_authorization: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let connect = read_request_head(&mut stream).await;
+                let authorization = format!("Proxy-Authorization: {}\r\n", expected_authorization);
+                let to_loopback = connect.starts_with("CONNECT 127.0.0.1:80 ")
+                    || connect.starts_with("CONNECT [::1]:80 ");
+                if !to_loopback || !connect.contains(&authorization) {
+                    stream
+                        .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                        .await
+                        .unwrap();
+                    return;
+                }
+                stream
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .await
+                    .unwrap();
+                read_request_head(&mut stream).await;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 8\r\n\r\ntunneled")
+                    .await
+                    .unwrap();
+            });
         }
+    });
+    url
+}
 
-        fn poll_shutdown(
-            mut self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Pin::new(&mut self.0).poll_shutdown(cx)
+/// Starts an HTTP server on localhost that answers every request with the
+/// head of the request. Returns the url of the server.
+async fn spawn_echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let head = read_request_head(&mut stream).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                    head.len(),
+                    head
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            });
         }
+    });
+    url
+}
+
+async fn read_request_head(stream: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte).await.unwrap();
+        head.push(byte[0]);
     }
+    String::from_utf8(head).unwrap()
+}
+
+async fn setup_loop_channel_unix() -> Channel {
+    setup_loop_channel_unix_with(HttpFromCanister::new()).await
 }
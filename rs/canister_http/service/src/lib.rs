@@ -0,0 +1,4 @@
+//! The protobuf structs and the gRPC service to send messages between the
+//! replica and the canister http adapter, so that the replica does not need
+//! to depend on the adapter itself.
+tonic::include_proto!("http_adapter");
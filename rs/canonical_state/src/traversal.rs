@@ -643,12 +643,14 @@ mod tests {
                 nodes: btreemap!{},
                 subnet_type: SubnetType::Application,
                 subnet_features: SubnetFeatures::default(),
+                max_reserved_ecdsa_quadruples: 0,
             },
             subnet_test_id(1) => SubnetTopology {
                 public_key: vec![5, 6, 7, 8],
                 nodes: btreemap!{},
                 subnet_type: SubnetType::Application,
                 subnet_features: SubnetFeatures::default(),
+                max_reserved_ecdsa_quadruples: 0,
             }
         };
         fn id_range(from: u64, to: u64) -> CanisterIdRange {
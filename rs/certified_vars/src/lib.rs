@@ -97,25 +97,7 @@ pub fn verify_certificate(
         canister: BTreeMap<CanisterId, CanisterView>,
     }
 
-    let certificate: Certificate = parse_certificate(certificate)?;
-
-    let key = if let Some(delegation) = &certificate.delegation {
-        let subnet_id = PrincipalId::try_from(&*delegation.subnet_id)
-            .map(SubnetId::from)
-            .map_err(|err| {
-                CertificateValidationError::DeserError(format!(
-                    "failed to parse delegation subnet id: {}",
-                    err
-                ))
-            })?;
-        verify_delegation_certificate(&delegation.certificate, &subnet_id, root_pk, canister_id)?
-    } else {
-        *root_pk
-    };
-
-    verify_certificate_signature(&certificate, &key)?;
-
-    let replica_labeled_tree = parse_tree(certificate.tree)?;
+    let replica_labeled_tree = verify_read_state_certificate(certificate, canister_id, root_pk)?;
     let replica_state = ReplicaState::deserialize(LabeledTreeDeserializer::new(
         &replica_labeled_tree,
     ))
@@ -147,6 +129,38 @@ pub fn verify_certificate(
     Ok(Time::from_nanos_since_unix_epoch(replica_state.time.0))
 }
 
+/// Checks if the specified certificate, e.g., the one of a `read_state`
+/// response, is signed by the subnet of the specified canister, and returns
+/// the tree it certifies.
+///
+/// Only the paths that were requested are present in the tree, the pruned
+/// parts of the state tree are omitted.
+pub fn verify_read_state_certificate(
+    certificate: &[u8],
+    canister_id: &CanisterId,
+    root_pk: &ThresholdSigPublicKey,
+) -> Result<LabeledTree<Vec<u8>>, CertificateValidationError> {
+    let certificate: Certificate = parse_certificate(certificate)?;
+
+    let key = if let Some(delegation) = &certificate.delegation {
+        let subnet_id = PrincipalId::try_from(&*delegation.subnet_id)
+            .map(SubnetId::from)
+            .map_err(|err| {
+                CertificateValidationError::DeserError(format!(
+                    "failed to parse delegation subnet id: {}",
+                    err
+                ))
+            })?;
+        verify_delegation_certificate(&delegation.certificate, &subnet_id, root_pk, canister_id)?
+    } else {
+        *root_pk
+    };
+
+    verify_certificate_signature(&certificate, &key)?;
+
+    parse_tree(certificate.tree)
+}
+
 fn verify_delegation_certificate(
     certificate: &[u8],
     subnet_id: &SubnetId,
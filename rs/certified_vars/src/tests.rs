@@ -11,7 +11,9 @@ use ic_crypto_tree_hash::{flatmap, Digest, Label, LabeledTree};
 use ic_crypto_utils_threshold_sig::parse_threshold_sig_key_from_der;
 use ic_types::Time;
 
-use crate::{verify_certificate, CanisterId, CertificateValidationError};
+use crate::{
+    verify_certificate, verify_read_state_certificate, CanisterId, CertificateValidationError,
+};
 
 #[test]
 fn should_validate_subnet_delegation_test_vector() {
@@ -435,6 +437,43 @@ fn should_fail_on_unexpected_tree() {
     ));
 }
 
+#[test]
+fn should_return_tree_of_read_state_certificate() {
+    let tree = LabeledTree::SubTree(flatmap![
+        Label::from("request_status") => LabeledTree::SubTree(flatmap![
+            Label::from("request_id") => LabeledTree::SubTree(flatmap![
+                Label::from("status") => LabeledTree::Leaf(b"replied".to_vec()),
+            ])
+        ]),
+        Label::from("time") => LabeledTree::Leaf(vec![1])
+    ]);
+    let (_cert, pk, cbor) = CertificateBuilder::new(CustomTree(tree.clone()))
+        .with_delegation(CertificateBuilder::new(SubnetData {
+            subnet_id: subnet_id(1),
+            canister_id_ranges: vec![(canister_id(0), canister_id(10))],
+        }))
+        .build();
+
+    let verification_result = verify_read_state_certificate(&cbor, &canister_id(1), &pk);
+
+    assert_eq!(verification_result.expect("expect valid signature"), tree);
+}
+
+#[test]
+fn should_fail_read_state_certificate_verification_with_wrong_public_key() {
+    let (_cert, _pk, cbor) =
+        CertificateBuilder::new(CustomTree(LabeledTree::Leaf(b"".to_vec()))).build();
+    let other_pk =
+        CertificateBuilder::new(CustomTree(LabeledTree::Leaf(b"".to_vec()))).get_root_public_key();
+
+    let verification_result = verify_read_state_certificate(&cbor, &canister_id(1), &other_pk);
+
+    assert!(matches!(
+        verification_result,
+        Err(CertificateValidationError::InvalidSignature(_))
+    ));
+}
+
 fn random_certified_data() -> Digest {
     let mut random_certified_data: [u8; 32] = [0; 32];
     thread_rng().fill(&mut random_certified_data);
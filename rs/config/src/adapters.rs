@@ -1,7 +1,61 @@
+use ic_types::canister_http::DEFAULT_CANISTER_HTTP_PSEUDO_RANDOM_DELAY_WINDOW;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AdaptersConfig {
     pub bitcoin_uds_path: Option<PathBuf>,
+    /// The socket of the canister http adapter. Without it, canister http
+    /// requests are not sent anywhere.
+    pub canister_http_uds_path: Option<PathBuf>,
+    /// When the client of the canister http adapter sheds divergence-prone
+    /// requests because the adapter is slow.
+    #[serde(default)]
+    pub canister_http_load_shedding: CanisterHttpLoadSheddingConfig,
+    /// The window in milliseconds over which the canister http adapters of
+    /// the replicas of the subnet spread sending the same request, so that
+    /// they do not reach the destination all at once.
+    #[serde(default = "default_canister_http_pseudo_random_delay_window_millis")]
+    pub canister_http_pseudo_random_delay_window_millis: u64,
+}
+
+fn default_canister_http_pseudo_random_delay_window_millis() -> u64 {
+    DEFAULT_CANISTER_HTTP_PSEUDO_RANDOM_DELAY_WINDOW.as_millis() as u64
+}
+
+impl Default for AdaptersConfig {
+    fn default() -> Self {
+        Self {
+            bitcoin_uds_path: None,
+            canister_http_uds_path: None,
+            canister_http_load_shedding: CanisterHttpLoadSheddingConfig::default(),
+            canister_http_pseudo_random_delay_window_millis:
+                default_canister_http_pseudo_random_delay_window_millis(),
+        }
+    }
+}
+
+/// The shedding of canister http requests while the adapter is slow.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanisterHttpLoadSheddingConfig {
+    /// The 95th percentile of the adapter latency in milliseconds above which
+    /// divergence-prone requests are shed.
+    pub latency_threshold_millis: u64,
+    /// The maximum number of recent latencies the percentile is computed
+    /// over.
+    pub max_samples: usize,
+    /// The age in milliseconds after which a latency no longer counts towards
+    /// the percentile.
+    pub max_sample_age_millis: u64,
+}
+
+impl Default for CanisterHttpLoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            latency_threshold_millis: 10_000,
+            max_samples: 100,
+            max_sample_age_millis: 60_000,
+        }
+    }
 }
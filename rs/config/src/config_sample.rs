@@ -129,13 +129,35 @@ pub const SAMPLE_CONFIG: &str = r#"
     consensus: {
         // Whether or not to detect starvation. Should only be set to false in tests.
         detect_starvation: true,
+        // The maximum number of threshold ECDSA transcript operations, e.g. creating the
+        // dealings for the transcripts of different quadruples, that run concurrently.
+        max_parallel_ecdsa_transcript_operations: 4,
+        // The number of registry versions by which the key transcript of a threshold ECDSA
+        // signature request may be older than the current key transcript of the subnet, for
+        // the node to still create signature shares for the request.
+        ecdsa_key_transcript_grace: 10,
     },
     // ============================================
     // Configuration of the node state persistence.
     // ============================================
     crypto: {
         // The directory that should be used to persist node's cryptographic keys.
-        crypto_root: "/tmp/ic_crypto"
+        crypto_root: "/tmp/ic_crypto",
+        // The time in milliseconds after which loading a secret key store at startup is
+        // reported as too slow.
+        secret_key_store_load_budget_millis: 5000,
+        // The time in milliseconds after which a call of the remote vault server is
+        // recorded as slow. The recorded calls are retrieved with the `slow_calls`
+        // method of the vault API.
+        vault_slow_call_threshold_millis: 1000,
+        // The number of slow calls the remote vault server keeps.
+        vault_slow_call_capacity: 100,
+        // The address on which the remote vault server exposes its Prometheus metrics.
+        // If unset, the metrics are not exported.
+        // vault_metrics_addr: "127.0.0.1:9095",
+        // The socket of a remote vault server that is validated against the local vault.
+        // If unset, only the local vault is used.
+        // dual_vault_socket_path: "/run/ic-node/crypto-csp/socket",
     },
     // ========================================
     // Configuration of the message scheduling.
@@ -320,7 +342,14 @@ pub const SAMPLE_CONFIG: &str = r#"
     },
     // =================================
     adapters_config: {
-        bitcoin_uds_path: "/tmp/bitcoin_uds"
+        bitcoin_uds_path: "/tmp/bitcoin_uds",
+        canister_http_uds_path: "/tmp/canister_http_uds",
+        canister_http_load_shedding: {
+            latency_threshold_millis: 10000,
+            max_samples: 100,
+            max_sample_age_millis: 60000,
+        },
+        canister_http_pseudo_random_delay_window_millis: 500,
     }
     // =================================
 }
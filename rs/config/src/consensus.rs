@@ -1,24 +1,114 @@
 use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+
+/// The default maximum number of threshold ECDSA transcript operations that
+/// the pre-signer runs concurrently.
+pub const DEFAULT_MAX_PARALLEL_ECDSA_TRANSCRIPT_OPERATIONS: usize = 4;
+
+/// The default number of registry versions by which the key transcript of a
+/// signature request may be older than the current key transcript, for the
+/// signer to still create signature shares for the request.
+pub const DEFAULT_ECDSA_KEY_TRANSCRIPT_GRACE: u64 = 10;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ConsensusConfig {
     detect_starvation: bool,
+    /// Zero is rejected when the config is parsed.
+    #[serde(default = "default_max_parallel_ecdsa_transcript_operations")]
+    max_parallel_ecdsa_transcript_operations: NonZeroUsize,
+    #[serde(default = "default_ecdsa_key_transcript_grace")]
+    ecdsa_key_transcript_grace: u64,
+}
+
+fn default_max_parallel_ecdsa_transcript_operations() -> NonZeroUsize {
+    NonZeroUsize::new(DEFAULT_MAX_PARALLEL_ECDSA_TRANSCRIPT_OPERATIONS).unwrap()
+}
+
+fn default_ecdsa_key_transcript_grace() -> u64 {
+    DEFAULT_ECDSA_KEY_TRANSCRIPT_GRACE
 }
 
 impl ConsensusConfig {
     pub fn new(detect_starvation: bool) -> Self {
-        Self { detect_starvation }
+        Self {
+            detect_starvation,
+            max_parallel_ecdsa_transcript_operations:
+                default_max_parallel_ecdsa_transcript_operations(),
+            ecdsa_key_transcript_grace: DEFAULT_ECDSA_KEY_TRANSCRIPT_GRACE,
+        }
+    }
+
+    /// Panics if `max_operations` is zero.
+    pub fn with_max_parallel_ecdsa_transcript_operations(self, max_operations: usize) -> Self {
+        Self {
+            max_parallel_ecdsa_transcript_operations: NonZeroUsize::new(max_operations)
+                .expect("at least one ECDSA transcript operation must be allowed to run"),
+            ..self
+        }
+    }
+
+    pub fn with_ecdsa_key_transcript_grace(self, grace: u64) -> Self {
+        Self {
+            ecdsa_key_transcript_grace: grace,
+            ..self
+        }
     }
 
     pub fn detect_starvation(&self) -> bool {
         self.detect_starvation
     }
+
+    /// The maximum number of threshold ECDSA transcript operations, e.g.
+    /// creating the dealings for the transcripts of different quadruples, that
+    /// the pre-signer runs concurrently.
+    pub fn max_parallel_ecdsa_transcript_operations(&self) -> usize {
+        self.max_parallel_ecdsa_transcript_operations.get()
+    }
+
+    /// The number of registry versions by which the key transcript of a
+    /// signature request may be older than the current key transcript of the
+    /// subnet, for the signer to still create signature shares for it.
+    pub fn ecdsa_key_transcript_grace(&self) -> u64 {
+        self.ecdsa_key_transcript_grace
+    }
 }
 
 impl Default for ConsensusConfig {
     fn default() -> Self {
-        Self {
-            detect_starvation: true,
-        }
+        Self::new(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_max_parallel_ecdsa_transcript_operations() {
+        let config: ConsensusConfig = json5::from_str(
+            "{ detect_starvation: true, max_parallel_ecdsa_transcript_operations: 2 }",
+        )
+        .unwrap();
+        assert_eq!(config.max_parallel_ecdsa_transcript_operations(), 2);
+
+        let config: ConsensusConfig = json5::from_str("{ detect_starvation: true }").unwrap();
+        assert_eq!(
+            config.max_parallel_ecdsa_transcript_operations(),
+            DEFAULT_MAX_PARALLEL_ECDSA_TRANSCRIPT_OPERATIONS
+        );
+    }
+
+    #[test]
+    fn should_reject_zero_max_parallel_ecdsa_transcript_operations() {
+        let result = json5::from_str::<ConsensusConfig>(
+            "{ detect_starvation: true, max_parallel_ecdsa_transcript_operations: 0 }",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one ECDSA transcript operation")]
+    fn should_panic_on_zero_max_parallel_ecdsa_transcript_operations() {
+        ConsensusConfig::default().with_max_parallel_ecdsa_transcript_operations(0);
     }
 }
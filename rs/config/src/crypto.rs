@@ -6,6 +6,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::net::SocketAddr;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
@@ -32,12 +33,71 @@ pub struct CryptoConfig {
         proptest(strategy = "any::<String>().prop_map(|x| PathBuf::from(x))")
     )]
     pub crypto_root: PathBuf,
+    /// The time in milliseconds that loading a secret key store at startup
+    /// is expected to take at most. A warning is logged if it takes longer,
+    /// as large stores slow down node restarts.
+    #[serde(default = "default_secret_key_store_load_budget_millis")]
+    pub secret_key_store_load_budget_millis: u64,
+    /// The time in milliseconds after which a call of the remote vault server
+    /// is recorded as slow, together with the sizes of its arguments.
+    #[serde(default = "default_vault_slow_call_threshold_millis")]
+    pub vault_slow_call_threshold_millis: u64,
+    /// The number of slow calls the remote vault server keeps. Once it is
+    /// reached, the oldest record is evicted. The kept calls are printed by
+    /// the `ic-crypto-csp-slow-calls` tool.
+    #[serde(default = "default_vault_slow_call_capacity")]
+    pub vault_slow_call_capacity: usize,
+    /// The address on which the remote vault server exposes its Prometheus
+    /// metrics, e.g. the number of keys in its secret key stores. If unset,
+    /// the metrics are not exported.
+    #[serde(default)]
+    pub vault_metrics_addr: Option<SocketAddr>,
+    /// The socket of a remote vault server that is validated against the
+    /// local vault: the deterministic vault calls are also sent to it, and
+    /// mismatching results are counted. If unset, only the local vault is
+    /// used.
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::option::of(any::<String>().prop_map(|x| PathBuf::from(x)))"
+        )
+    )]
+    #[serde(default)]
+    pub dual_vault_socket_path: Option<PathBuf>,
+}
+
+/// The default of [`CryptoConfig::secret_key_store_load_budget_millis`].
+pub const DEFAULT_SECRET_KEY_STORE_LOAD_BUDGET_MILLIS: u64 = 5_000;
+
+fn default_secret_key_store_load_budget_millis() -> u64 {
+    DEFAULT_SECRET_KEY_STORE_LOAD_BUDGET_MILLIS
+}
+
+/// The default of [`CryptoConfig::vault_slow_call_threshold_millis`].
+pub const DEFAULT_VAULT_SLOW_CALL_THRESHOLD_MILLIS: u64 = 1_000;
+
+fn default_vault_slow_call_threshold_millis() -> u64 {
+    DEFAULT_VAULT_SLOW_CALL_THRESHOLD_MILLIS
+}
+
+/// The default of [`CryptoConfig::vault_slow_call_capacity`].
+pub const DEFAULT_VAULT_SLOW_CALL_CAPACITY: usize = 100;
+
+fn default_vault_slow_call_capacity() -> usize {
+    DEFAULT_VAULT_SLOW_CALL_CAPACITY
 }
 
 impl CryptoConfig {
     /// Return a new CryptoConfig with the given crypto_root path.
     pub fn new(crypto_root: PathBuf) -> Self {
-        Self { crypto_root }
+        Self {
+            crypto_root,
+            secret_key_store_load_budget_millis: DEFAULT_SECRET_KEY_STORE_LOAD_BUDGET_MILLIS,
+            vault_slow_call_threshold_millis: DEFAULT_VAULT_SLOW_CALL_THRESHOLD_MILLIS,
+            vault_slow_call_capacity: DEFAULT_VAULT_SLOW_CALL_CAPACITY,
+            vault_metrics_addr: None,
+            dual_vault_socket_path: None,
+        }
     }
 
     /// Creates a new CryptoConfig in a temporary directory for testing.
@@ -156,6 +216,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn should_default_secret_key_store_load_budget() {
+        let config: CryptoConfig = json5::from_str("{ crypto_root: '/tmp/ic_crypto' }").unwrap();
+        assert_eq!(
+            config.secret_key_store_load_budget_millis,
+            DEFAULT_SECRET_KEY_STORE_LOAD_BUDGET_MILLIS
+        );
+    }
+
     #[test]
     fn should_create_path_as_directory() {
         CryptoConfig::run_with_temp_config(|config| assert!(config.crypto_root.is_dir()));
@@ -6,7 +6,7 @@ use std::time::Duration;
 use crate::execution_environment::SUBNET_HEAP_DELTA_CAPACITY;
 use ic_base_types::NumBytes;
 use ic_registry_subnet_type::SubnetType;
-use ic_types::{Cycles, NumInstructions};
+use ic_types::{canister_http::CanisterHttpPricing, Cycles, NumInstructions};
 use serde::{Deserialize, Serialize};
 
 const B: u64 = 1_000_000_000;
@@ -255,6 +255,18 @@ pub struct CyclesAccountManagerConfig {
 
     /// How often to charge canisters for memory and compute allocations.
     pub duration_between_allocation_charges: Duration,
+
+    /// Fee for every message hash that is signed with a call of
+    /// `sign_with_ecdsa` or `sign_with_ecdsa_batch`.
+    pub ecdsa_signature_fee: Cycles,
+
+    /// Fee for every ECDSA pre-signature that a canister newly reserves with
+    /// a call of `reserve_ecdsa_pre_signatures`.
+    pub ecdsa_pre_signature_reservation_fee: Cycles,
+
+    /// Fees for canister http requests on subnets whose record does not
+    /// configure a pricing in the registry.
+    pub canister_http_pricing: CanisterHttpPricing,
 }
 
 impl CyclesAccountManagerConfig {
@@ -279,6 +291,11 @@ impl CyclesAccountManagerConfig {
             // 4 SDR per GiB per year => 4e12 Cycles per year
             gib_storage_per_second_fee: Cycles::new(127_000),
             duration_between_allocation_charges: Duration::from_secs(10),
+            ecdsa_signature_fee: Cycles::new(10_000_000_000),
+            ecdsa_pre_signature_reservation_fee: Cycles::new(50_000_000_000),
+            // Canister http requests stay free until the registry configures
+            // a pricing for the subnet.
+            canister_http_pricing: CanisterHttpPricing::default(),
         }
     }
 
@@ -295,6 +312,9 @@ impl CyclesAccountManagerConfig {
             ingress_byte_reception_fee: Cycles::new(0),
             gib_storage_per_second_fee: Cycles::new(0),
             duration_between_allocation_charges: Duration::from_secs(10),
+            ecdsa_signature_fee: Cycles::new(0),
+            ecdsa_pre_signature_reservation_fee: Cycles::new(0),
+            canister_http_pricing: CanisterHttpPricing::default(),
         }
     }
 }
@@ -143,6 +143,8 @@ pub fn deliver_batches(
                     registry_version: block.context.registry_version,
                     time: block.context.time,
                     consensus_responses,
+                    // Blocks do not carry canister http responses yet.
+                    canister_http_responses: vec![],
                 };
                 let batch_height = batch.batch_number.get();
                 let ingress_count = batch.payload.ingress.message_count();
@@ -374,7 +376,7 @@ pub fn generate_responses_to_sign_with_mock_ecdsa_calls(
 }
 
 /// Creates responses to `SignWithECDSA` system calls with the computed
-/// signature.
+/// signature, or rejects them if consensus rejected the request.
 pub fn generate_responses_to_sign_with_ecdsa_calls(
     contexts: &BTreeMap<CallbackId, SignWithEcdsaContext>,
     ecdsa_payload: &ecdsa::EcdsaDataPayload,
@@ -383,22 +385,28 @@ pub fn generate_responses_to_sign_with_ecdsa_calls(
     let mut consensus_responses = Vec::<Response>::new();
     for (callback_id, context) in contexts.iter() {
         let request_id = ecdsa::RequestId::from(context.pseudo_random_id.to_vec());
-        if let Some(CompletedSignature::Unreported(response)) =
-            ecdsa_payload.signature_agreements.get(&request_id).cloned()
-        {
-            consensus_responses.push(Response {
-                originator: context.request.sender,
-                respondent: CanisterId::ic_00(),
-                originator_reply_callback: *callback_id,
-                refund: Cycles::zero(),
-                response_payload: messages::Payload::Data(
-                    SignWithECDSAReply {
-                        signature: response.signature,
-                    }
-                    .encode(),
-                ),
-            });
-        }
+        let response_payload = match ecdsa_payload.signature_agreements.get(&request_id) {
+            Some(CompletedSignature::Unreported(response)) => messages::Payload::Data(
+                SignWithECDSAReply {
+                    signature: response.signature.clone(),
+                }
+                .encode(),
+            ),
+            Some(CompletedSignature::UnreportedReject(message)) => {
+                messages::Payload::Reject(messages::RejectContext {
+                    code: ic_types::user_error::RejectCode::CanisterReject,
+                    message: message.clone(),
+                })
+            }
+            Some(CompletedSignature::ReportedToExecution) | None => continue,
+        };
+        consensus_responses.push(Response {
+            originator: context.request.sender,
+            respondent: CanisterId::ic_00(),
+            originator_reply_callback: *callback_id,
+            refund: Cycles::zero(),
+            response_payload,
+        });
     }
     consensus_responses
 }
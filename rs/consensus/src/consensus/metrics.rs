@@ -7,6 +7,7 @@ use ic_metrics::{
 use ic_types::consensus::{Block, BlockProposal, HasHeight, HasRank};
 use prometheus::{GaugeVec, Histogram, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec};
 use std::sync::RwLock;
+use std::time::Duration;
 
 // For certain metrics, we record metrics based on block's rank.
 // Since we can only record limited number of them, the follow is
@@ -421,9 +422,28 @@ impl EcdsaSignerMetrics {
     }
 }
 
+/// The key id label of signing requests whose key id is not known.
+pub const UNKNOWN_ECDSA_KEY_ID: &str = "unknown";
+
+/// The key id label of signing requests that do not name a key id, e.g.
+/// because they were received before requests carried one.
+pub const UNSPECIFIED_ECDSA_KEY_ID: &str = "unspecified";
+
+/// Returns the metrics label of the ECDSA key id `key_id`.
+fn ecdsa_key_id_label(key_id: &str) -> &str {
+    if key_id.is_empty() {
+        UNSPECIFIED_ECDSA_KEY_ID
+    } else {
+        key_id
+    }
+}
+
 pub struct EcdsaPayloadMetrics {
     pub payload_metrics: IntGaugeVec,
     pub payload_errors: IntCounterVec,
+    pub quadruples_consumed: IntCounterVec,
+    pub signature_completion_latency: HistogramVec,
+    pub signature_failures: IntCounterVec,
 }
 
 impl EcdsaPayloadMetrics {
@@ -439,6 +459,23 @@ impl EcdsaPayloadMetrics {
                 "ECDSA payload related errors",
                 &["type"],
             ),
+            quadruples_consumed: metrics_registry.int_counter_vec(
+                "ecdsa_quadruples_consumed_total",
+                "Number of pre-signature quadruples consumed by signing requests, by key id",
+                &["key_id"],
+            ),
+            signature_completion_latency: metrics_registry.histogram_vec(
+                "ecdsa_signature_completion_latency_seconds",
+                "Time from the batch that received a signing request until its signature is agreed, by key id",
+                // 0.1s, 0.2s, 0.5s, 1s, ..., 500s, 1000s
+                decimal_buckets(-1, 3),
+                &["key_id"],
+            ),
+            signature_failures: metrics_registry.int_counter_vec(
+                "ecdsa_signature_failures_total",
+                "Number of signatures that could not be delivered, by key id and reason",
+                &["key_id", "reason"],
+            ),
         }
     }
 
@@ -453,6 +490,24 @@ impl EcdsaPayloadMetrics {
     pub fn payload_errors_inc(&self, label: &str) {
         self.payload_errors.with_label_values(&[label]).inc();
     }
+
+    pub fn quadruples_consumed_inc(&self, key_id: &str) {
+        self.quadruples_consumed
+            .with_label_values(&[ecdsa_key_id_label(key_id)])
+            .inc();
+    }
+
+    pub fn signature_completion_latency_observe(&self, key_id: &str, latency: Duration) {
+        self.signature_completion_latency
+            .with_label_values(&[ecdsa_key_id_label(key_id)])
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn signature_failures_inc(&self, key_id: &str, reason: &str) {
+        self.signature_failures
+            .with_label_values(&[ecdsa_key_id_label(key_id), reason])
+            .inc();
+    }
 }
 
 pub fn timed_call<F, R>(label: &str, call_fn: F, metric: &HistogramVec) -> R
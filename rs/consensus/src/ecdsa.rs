@@ -196,6 +196,7 @@ pub(crate) mod signer;
 pub(crate) mod utils;
 
 pub(crate) use payload_builder::{create_data_payload, create_summary_payload};
+pub use signer::SigShareAdmissionError;
 
 /// Similar to consensus, we don't fetch artifacts too far ahead in future.
 const LOOK_AHEAD: u64 = 10;
@@ -212,7 +213,12 @@ pub struct EcdsaImpl {
 }
 
 impl EcdsaImpl {
-    /// Builds a new threshold ECDSA component
+    /// Builds a new threshold ECDSA component. The pre-signer runs up to
+    /// `max_parallel_transcript_operations` crypto operations concurrently.
+    /// The signer creates no signature shares for requests whose key
+    /// transcript is older than the current key transcript by more than
+    /// `key_transcript_grace` registry versions, see
+    /// [`SigShareAdmissionError`].
     pub fn new(
         node_id: NodeId,
         consensus_block_cache: Arc<dyn ConsensusBlockCache>,
@@ -220,6 +226,8 @@ impl EcdsaImpl {
         metrics_registry: MetricsRegistry,
         logger: ReplicaLogger,
         malicious_flags: MaliciousFlags,
+        max_parallel_transcript_operations: usize,
+        key_transcript_grace: u64,
     ) -> Self {
         let pre_signer = Box::new(EcdsaPreSignerImpl::new(
             node_id,
@@ -228,6 +236,7 @@ impl EcdsaImpl {
             metrics_registry.clone(),
             logger.clone(),
             malicious_flags,
+            max_parallel_transcript_operations,
         ));
         let signer = Box::new(EcdsaSignerImpl::new(
             node_id,
@@ -235,6 +244,7 @@ impl EcdsaImpl {
             crypto.clone(),
             metrics_registry.clone(),
             logger.clone(),
+            key_transcript_grace,
         ));
         let complaint_handler = Box::new(EcdsaComplaintHandlerImpl::new(
             node_id,
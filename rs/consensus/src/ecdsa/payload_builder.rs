@@ -6,7 +6,9 @@ use super::pre_signer::{EcdsaTranscriptBuilder, EcdsaTranscriptBuilderImpl};
 use super::signer::{EcdsaSignatureBuilder, EcdsaSignatureBuilderImpl};
 use super::utils::EcdsaBlockReaderImpl;
 use crate::consensus::{
-    crypto::ConsensusCrypto, metrics::EcdsaPayloadMetrics, pool_reader::PoolReader,
+    crypto::ConsensusCrypto,
+    metrics::{EcdsaPayloadMetrics, UNKNOWN_ECDSA_KEY_ID},
+    pool_reader::PoolReader,
 };
 use ic_artifact_pool::consensus_pool::build_consensus_block_chain;
 use ic_interfaces::{
@@ -35,7 +37,7 @@ use ic_types::{
     },
     messages::CallbackId,
     registry::RegistryClientError,
-    Height, NodeId, RegistryVersion, SubnetId,
+    CanisterId, Height, NodeId, RegistryVersion, SubnetId, Time,
 };
 use phantom_newtype::Id;
 use std::collections::{BTreeMap, BTreeSet};
@@ -43,6 +45,10 @@ use std::convert::TryFrom;
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
 
+/// The maximum number of signing requests that are matched to pre-signatures
+/// in a single round, if the subnet's ECDSA config does not specify it.
+const DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND: usize = 10;
+
 #[derive(Clone, Debug)]
 pub enum EcdsaPayloadError {
     RegistryClientError(RegistryClientError),
@@ -54,6 +60,10 @@ pub enum EcdsaPayloadError {
     EcdsaConfigNotFound(RegistryVersion),
     ThresholdEcdsaSigInputsCreationError(ThresholdEcdsaSigInputsCreationError),
     TranscriptCastError(ecdsa::TranscriptCastError),
+    UnsupportedAlgorithm {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 
 impl From<RegistryClientError> for EcdsaPayloadError {
@@ -240,6 +250,16 @@ pub(crate) fn create_data_payload(
     if !ecdsa_feature_is_enabled(subnet_id, registry_client, pool_reader, height)? {
         return Ok(None);
     }
+    // Don't propose IDKG operations that the crypto component of this node
+    // cannot execute, e.g. because it was not yet upgraded to a version that
+    // supports the algorithm.
+    let supported = crypto.supported_algorithms();
+    if !supported.contains(&AlgorithmId::ThresholdEcdsaSecp256k1) {
+        return Err(EcdsaPayloadError::UnsupportedAlgorithm {
+            algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+            supported,
+        });
+    }
     let block_payload = &parent_block.payload.as_ref();
     if block_payload.is_summary() {
         let summary = block_payload.as_summary();
@@ -326,6 +346,17 @@ pub(crate) fn create_data_payload(
                 let parent_chain =
                     build_consensus_block_chain(pool_reader.pool(), &summary_block, parent_block);
                 if let Some(key_transcript) = current_key_transcript {
+                    let ecdsa_config =
+                        registry_client.get_ecdsa_config(subnet_id, summary_registry_version)?;
+                    let max_signing_requests = ecdsa_config
+                        .as_ref()
+                        .map(max_signing_requests_per_round)
+                        .unwrap_or(DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND);
+                    let max_reserved_quadruples =
+                        ecdsa_config.map_or(0, |config| config.max_reserved_quadruples as usize);
+                    let held_key_ids = registry_client
+                        .get_ecdsa_key_ids(subnet_id, summary_registry_version)?
+                        .unwrap_or_default();
                     let state = state_manager.get_state_at(context.certified_height)?;
                     let signing_requests = get_signing_requests(
                         &state
@@ -339,14 +370,26 @@ pub(crate) fn create_data_payload(
                         parent_chain.clone(),
                         ecdsa_pool.clone(),
                         crypto,
+                        context.time,
                         &mut payload,
                         ecdsa_payload_metrics,
                         log.clone(),
                     );
                     let count = update_signing_requests(
                         &signing_requests,
+                        &held_key_ids,
                         key_transcript,
+                        max_signing_requests,
+                        &state
+                            .get_ref()
+                            .metadata
+                            .subnet_call_context_manager
+                            .active_ecdsa_quadruple_reservations(
+                                state.get_ref().metadata.batch_time,
+                            ),
+                        max_reserved_quadruples,
                         &mut payload,
+                        ecdsa_payload_metrics,
                         log.clone(),
                     )?;
                     // quadruples are consumed, need to produce more
@@ -527,6 +570,15 @@ fn start_making_new_quadruples(
     Ok(())
 }
 
+/// Return the maximum number of signing requests that may be matched to
+/// pre-signatures in a single round for the given config.
+fn max_signing_requests_per_round(ecdsa_config: &EcdsaConfig) -> usize {
+    match ecdsa_config.max_signing_requests_per_round {
+        0 => DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+        max => max as usize,
+    }
+}
+
 /// Turn the given sign_with_ecdsa_contexts into a mapping with request id as the key.
 fn get_signing_requests(
     sign_with_ecdsa_contexts: &BTreeMap<CallbackId, SignWithEcdsaContext>,
@@ -552,6 +604,7 @@ fn update_signature_agreements(
     chain: Arc<dyn ConsensusBlockChain>,
     ecdsa_pool: Arc<RwLock<dyn EcdsaPool>>,
     crypto: &dyn ConsensusCrypto,
+    time: Time,
     payload: &mut ecdsa::EcdsaDataPayload,
     metrics: &EcdsaPayloadMetrics,
     log: ReplicaLogger,
@@ -573,13 +626,25 @@ fn update_signature_agreements(
     }
     // Then we collect new signatures into the signature_agreements
     for (request_id, signature) in builder.get_completed_signatures(chain, ecdsa_pool.deref()) {
+        let context = signing_requests.get(&request_id);
+        let key_id = context
+            .map(|context| context.key_id.as_str())
+            .unwrap_or(UNKNOWN_ECDSA_KEY_ID);
         if payload.ongoing_signatures.remove(&request_id).is_none() {
             warn!(
                 log,
                 "ECDSA signing request {:?} is not found in payload but we have a signature for it",
                 request_id
             );
+            metrics.signature_failures_inc(key_id, "signature_without_ongoing_request");
         } else {
+            if context.is_none() {
+                // The agreement is dropped again with the next payload.
+                metrics.signature_failures_inc(key_id, "signing_request_not_found");
+            }
+            if let Some(context) = context.filter(|context| context.batch_time <= time) {
+                metrics.signature_completion_latency_observe(key_id, time - context.batch_time);
+            }
             payload
                 .signature_agreements
                 .insert(request_id, ecdsa::CompletedSignature::Unreported(signature));
@@ -592,14 +657,34 @@ fn update_signature_agreements(
 /// - Check if new signatures have been produced, and add them to
 /// signature agreements.
 /// - Check if there are new signing requests, and start to work on them.
+/// At most `max_signing_requests` new requests are matched to available
+/// quadruples in one batch, the rest is left for later rounds.
+///
+/// New requests for keys other than the `held_key_ids` of the subnet are
+/// rejected before they are matched to quadruples, so that they use up none,
+/// unless the subnet does not list its key ids. The rejection is added to the
+/// signature agreements once, which removes the request from the state when
+/// it is delivered, and is logged and counted as a failure once. Execution
+/// only accepts requests for keys of the subnet, so this only happens if the
+/// registry changes.
+///
+/// The quadruples that canisters reserved with `quadruple_reservations` are
+/// only matched to the requests of these canisters, but no more than
+/// `max_reserved_quadruples` of them are held back.
 ///
 /// Return the number of new signing requests that are worked on (or
 /// equivalently, the number of quadruples that are consumed).
 // Return new signing requests initiated from canisters.
+#[allow(clippy::too_many_arguments)]
 fn update_signing_requests(
     signing_requests: &BTreeMap<ecdsa::RequestId, &SignWithEcdsaContext>,
+    held_key_ids: &[String],
     key_transcript: &ecdsa::UnmaskedTranscript,
+    max_signing_requests: usize,
+    quadruple_reservations: &BTreeMap<String, BTreeMap<CanisterId, u32>>,
+    max_reserved_quadruples: usize,
     payload: &mut ecdsa::EcdsaDataPayload,
+    metrics: &EcdsaPayloadMetrics,
     log: ReplicaLogger,
 ) -> Result<usize, EcdsaPayloadError> {
     // Get the set of new signing requests that we have not signed, and are
@@ -609,11 +694,33 @@ fn update_signing_requests(
         .keys()
         .chain(payload.ongoing_signatures.keys())
         .collect::<BTreeSet<_>>();
+    let mut held_requests = BTreeMap::new();
+    let mut rejected_requests = Vec::new();
+    for (request_id, context) in signing_requests {
+        if held_key_ids.is_empty() || held_key_ids.contains(&context.key_id) {
+            held_requests.insert(request_id.clone(), *context);
+        } else if !existing_requests.contains(request_id) {
+            metrics.signature_failures_inc(&context.key_id, "key_not_held");
+            warn!(
+                log,
+                "ECDSA signing request {:?} is for key {}, which is not held by the subnet",
+                request_id,
+                context.key_id
+            );
+            rejected_requests.push((
+                request_id.clone(),
+                format!("ECDSA key {} is not held by the subnet", context.key_id),
+            ));
+        }
+    }
     let new_requests = get_new_signing_requests(
-        signing_requests,
+        &held_requests,
         &existing_requests,
         &mut payload.available_quadruples,
         key_transcript,
+        max_signing_requests,
+        quadruple_reservations,
+        max_reserved_quadruples,
     )?;
     debug!(
         log,
@@ -623,28 +730,75 @@ fn update_signing_requests(
     );
     let mut count = 0;
     for (request_id, sign_inputs) in new_requests {
+        if let Some(context) = signing_requests.get(&request_id) {
+            metrics.quadruples_consumed_inc(&context.key_id);
+        }
         payload.ongoing_signatures.insert(request_id, sign_inputs);
         count += 1;
     }
+    for (request_id, message) in rejected_requests {
+        payload.signature_agreements.insert(
+            request_id,
+            ecdsa::CompletedSignature::UnreportedReject(message),
+        );
+    }
     Ok(count)
 }
 
-// Return new signing requests initiated from canisters.
+// Return new signing requests initiated from canisters, matched to available
+// quadruples, but no more than `max_signing_requests` of them.
+//
+// In every round, as many quadruples as a canister reserved for a key are
+// held back for its requests for that key. The requests of other canisters
+// are only matched to the quadruples that are not held back, and wait for a
+// later round otherwise. No more quadruples than `max_reserved_quadruples`,
+// the current limit of the registry, and than are available are held back,
+// so reservations made under a higher limit do not starve other canisters.
 fn get_new_signing_requests(
     signing_requests: &BTreeMap<ecdsa::RequestId, &SignWithEcdsaContext>,
     existing_requests: &BTreeSet<&ecdsa::RequestId>,
     available_quadruples: &mut BTreeMap<ecdsa::QuadrupleId, ecdsa::PreSignatureQuadrupleRef>,
     key_transcript: &ecdsa::UnmaskedTranscript,
+    max_signing_requests: usize,
+    quadruple_reservations: &BTreeMap<String, BTreeMap<CanisterId, u32>>,
+    max_reserved_quadruples: usize,
 ) -> Result<Vec<(ecdsa::RequestId, ecdsa::ThresholdEcdsaSigInputsRef)>, EcdsaPayloadError> {
     let new_requests = signing_requests
         .iter()
         .filter(|(request_id, _)| !existing_requests.contains(request_id));
+    let mut unclaimed_reservations: BTreeMap<(&str, CanisterId), usize> = quadruple_reservations
+        .iter()
+        .flat_map(|(key_id, reservations)| {
+            reservations.iter().map(move |(canister_id, quadruples)| {
+                ((key_id.as_str(), *canister_id), *quadruples as usize)
+            })
+        })
+        .collect();
+    let mut held_back = unclaimed_reservations
+        .values()
+        .sum::<usize>()
+        .min(max_reserved_quadruples)
+        .min(available_quadruples.len());
 
     let mut ret = Vec::new();
     let mut consumed_quadruples = Vec::new();
-    for ((request_id, context), (quadruple_id, quadruple)) in
-        new_requests.zip(available_quadruples.iter())
-    {
+    let mut quadruples = available_quadruples.iter();
+    for (request_id, context) in new_requests {
+        if ret.len() >= max_signing_requests || quadruples.len() == 0 {
+            break;
+        }
+        match unclaimed_reservations.get_mut(&(context.key_id.as_str(), context.request.sender)) {
+            Some(unclaimed) if *unclaimed > 0 => {
+                *unclaimed -= 1;
+                held_back = held_back.saturating_sub(1);
+            }
+            _ if quadruples.len() <= held_back => continue,
+            _ => (),
+        }
+        let (quadruple_id, quadruple) = match quadruples.next() {
+            Some(next) => next,
+            None => break,
+        };
         let sign_inputs = build_signature_inputs(context, quadruple, key_transcript);
         ret.push((request_id.clone(), sign_inputs));
         consumed_quadruples.push(*quadruple_id);
@@ -978,6 +1132,7 @@ fn build_signature_inputs(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::consensus::metrics::UNSPECIFIED_ECDSA_KEY_ID;
     use crate::consensus::mocks::{dependencies, Dependencies};
     use crate::ecdsa::utils::test_utils::*;
     use ic_crypto_test_utils_canister_threshold_sigs::{
@@ -985,12 +1140,14 @@ mod tests {
         CanisterThresholdSigTestEnvironment,
     };
     use ic_logger::replica_logger::no_op_logger;
+    use ic_metrics::MetricsRegistry;
     use ic_test_artifact_pool::consensus_pool::TestConsensusPool;
     use ic_test_utilities::{
+        metrics::{fetch_int_counter_vec, metric_vec},
         mock_time,
         state::ReplicatedStateBuilder,
         types::{
-            ids::{node_test_id, subnet_test_id},
+            ids::{canister_test_id, node_test_id, subnet_test_id},
             messages::RequestBuilder,
         },
     };
@@ -1155,6 +1312,7 @@ mod tests {
                     message_hash: vec![],
                     derivation_path: vec![],
                     batch_time: mock_time(),
+                    key_id: "secp256k1".to_string(),
                 },
             );
         let signing_requests = get_signing_requests(
@@ -1175,6 +1333,9 @@ mod tests {
             &requests,
             &mut available_quadruples,
             ecdsa_transcript_ref,
+            DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+            &BTreeMap::new(),
+            0,
         );
         assert!(result.is_ok());
         let new_requests = result.unwrap();
@@ -1187,12 +1348,291 @@ mod tests {
             &requests,
             &mut available_quadruples,
             ecdsa_transcript_ref,
+            DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+            &BTreeMap::new(),
+            0,
         );
         assert!(result.is_ok());
         let new_requests = result.unwrap();
         assert_eq!(new_requests.len(), 0);
     }
 
+    #[test]
+    fn test_ecdsa_get_new_signing_requests_is_capped_per_round() {
+        let num_requests = 5;
+        let mut state = ReplicatedStateBuilder::default().build();
+        for i in 0..num_requests {
+            state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts
+                .insert(
+                    CallbackId::from(i as u64),
+                    SignWithEcdsaContext {
+                        request: RequestBuilder::new().build(),
+                        pseudo_random_id: [i as u8; 32],
+                        message_hash: vec![],
+                        derivation_path: vec![],
+                        batch_time: mock_time(),
+                        key_id: "secp256k1".to_string(),
+                    },
+                );
+        }
+        let signing_requests = get_signing_requests(
+            &state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts,
+        );
+        let requests = BTreeSet::new();
+        let sig_inputs = create_sig_inputs(10);
+        let quadruple_ref = &sig_inputs.sig_inputs_ref.presig_quadruple_ref;
+        let ecdsa_transcript_ref = &sig_inputs.sig_inputs_ref.key_transcript_ref;
+        let mut available_quadruples = BTreeMap::new();
+        for i in 0..num_requests {
+            available_quadruples.insert(ecdsa::QuadrupleId(i), quadruple_ref.clone());
+        }
+
+        // Only as many requests as the cap allows are matched in one batch
+        let new_requests = get_new_signing_requests(
+            &signing_requests,
+            &requests,
+            &mut available_quadruples,
+            ecdsa_transcript_ref,
+            3,
+            &BTreeMap::new(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(new_requests.len(), 3);
+        assert_eq!(available_quadruples.len(), 2);
+
+        // The remaining requests are matched in the next batch
+        let request_ids = new_requests
+            .iter()
+            .map(|(request_id, _)| request_id.clone())
+            .collect::<Vec<_>>();
+        let requests = request_ids.iter().collect::<BTreeSet<_>>();
+        let new_requests = get_new_signing_requests(
+            &signing_requests,
+            &requests,
+            &mut available_quadruples,
+            ecdsa_transcript_ref,
+            3,
+            &BTreeMap::new(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(new_requests.len(), 2);
+        assert!(available_quadruples.is_empty());
+    }
+
+    #[test]
+    fn test_ecdsa_update_signing_requests_rejects_keys_not_held() {
+        let subnet_id = subnet_test_id(1);
+        let key_ids = ["secp256k1", "other_key", ""];
+        let mut state = ReplicatedStateBuilder::default().build();
+        for (i, key_id) in key_ids.iter().enumerate() {
+            state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts
+                .insert(
+                    CallbackId::from(i as u64),
+                    SignWithEcdsaContext {
+                        request: RequestBuilder::new().build(),
+                        pseudo_random_id: [i as u8; 32],
+                        message_hash: vec![],
+                        derivation_path: vec![],
+                        batch_time: mock_time(),
+                        key_id: key_id.to_string(),
+                    },
+                );
+        }
+        let signing_requests = get_signing_requests(
+            &state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts,
+        );
+        let sig_inputs = create_sig_inputs(10);
+        let quadruple_ref = &sig_inputs.sig_inputs_ref.presig_quadruple_ref;
+        let ecdsa_transcript_ref = &sig_inputs.sig_inputs_ref.key_transcript_ref;
+        let mut payload = empty_ecdsa_data_payload(subnet_id);
+        for i in 0..key_ids.len() {
+            payload
+                .available_quadruples
+                .insert(ecdsa::QuadrupleId(i), quadruple_ref.clone());
+        }
+        let metrics_registry = MetricsRegistry::new();
+        let metrics = EcdsaPayloadMetrics::new(metrics_registry.clone());
+
+        let count = update_signing_requests(
+            &signing_requests,
+            &["secp256k1".to_string()],
+            ecdsa_transcript_ref,
+            DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+            &BTreeMap::new(),
+            0,
+            &mut payload,
+            &metrics,
+            no_op_logger(),
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(payload.ongoing_signatures.len(), 1);
+        assert_eq!(payload.available_quadruples.len(), key_ids.len() - 1);
+        assert_eq!(
+            fetch_int_counter_vec(&metrics_registry, "ecdsa_quadruples_consumed_total"),
+            metric_vec(&[(&[("key_id", "secp256k1")], 1)])
+        );
+        let rejected = |i: u8| ecdsa::RequestId::from(vec![i; 32]);
+        for i in 1..key_ids.len() as u8 {
+            assert!(matches!(
+                payload.signature_agreements.get(&rejected(i)),
+                Some(ecdsa::CompletedSignature::UnreportedReject(_))
+            ));
+        }
+
+        // The rejected requests are neither rejected nor counted again in
+        // the next round.
+        let count = update_signing_requests(
+            &signing_requests,
+            &["secp256k1".to_string()],
+            ecdsa_transcript_ref,
+            DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+            &BTreeMap::new(),
+            0,
+            &mut payload,
+            &metrics,
+            no_op_logger(),
+        )
+        .unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(payload.signature_agreements.len(), key_ids.len() - 1);
+        assert_eq!(
+            fetch_int_counter_vec(&metrics_registry, "ecdsa_signature_failures_total"),
+            metric_vec(&[
+                (&[("key_id", "other_key"), ("reason", "key_not_held")], 1),
+                (
+                    &[
+                        ("key_id", UNSPECIFIED_ECDSA_KEY_ID),
+                        ("reason", "key_not_held")
+                    ],
+                    1
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ecdsa_get_new_signing_requests_holds_back_reserved_quadruples() {
+        let reserving_canister = canister_test_id(1);
+        let other_canister = canister_test_id(2);
+        let mut state = ReplicatedStateBuilder::default().build();
+        for (i, sender) in [other_canister, other_canister, reserving_canister]
+            .iter()
+            .enumerate()
+        {
+            state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts
+                .insert(
+                    CallbackId::from(i as u64),
+                    SignWithEcdsaContext {
+                        request: RequestBuilder::new().sender(*sender).build(),
+                        pseudo_random_id: [i as u8; 32],
+                        message_hash: vec![],
+                        derivation_path: vec![],
+                        batch_time: mock_time(),
+                        key_id: "secp256k1".to_string(),
+                    },
+                );
+        }
+        state
+            .metadata
+            .subnet_call_context_manager
+            .set_ecdsa_quadruple_reservation("secp256k1", reserving_canister, 2, mock_time());
+        let signing_requests = get_signing_requests(
+            &state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts,
+        );
+        let requests = BTreeSet::new();
+        let sig_inputs = create_sig_inputs(10);
+        let quadruple_ref = &sig_inputs.sig_inputs_ref.presig_quadruple_ref;
+        let ecdsa_transcript_ref = &sig_inputs.sig_inputs_ref.key_transcript_ref;
+        let quadruple_reservations = state
+            .metadata
+            .subnet_call_context_manager
+            .active_ecdsa_quadruple_reservations(mock_time());
+        let get_new_request_ids = |max_reserved_quadruples| {
+            let mut available_quadruples = BTreeMap::new();
+            for i in 0..3 {
+                available_quadruples.insert(ecdsa::QuadrupleId(i), quadruple_ref.clone());
+            }
+            let new_requests = get_new_signing_requests(
+                &signing_requests,
+                &requests,
+                &mut available_quadruples,
+                ecdsa_transcript_ref,
+                DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND,
+                &quadruple_reservations,
+                max_reserved_quadruples,
+            )
+            .unwrap();
+            let request_ids = new_requests
+                .into_iter()
+                .map(|(request_id, _)| request_id)
+                .collect::<Vec<_>>();
+            (request_ids, available_quadruples.len())
+        };
+
+        // One of the three quadruples is not reserved, so only one request of
+        // the other canister is matched, while the request of the reserving
+        // canister is matched to a reserved quadruple.
+        assert_eq!(
+            get_new_request_ids(2),
+            (
+                vec![
+                    ecdsa::RequestId::from(vec![0; 32]),
+                    ecdsa::RequestId::from(vec![2; 32]),
+                ],
+                1
+            )
+        );
+
+        // If the registry lowers the limit below the reservation, only as many
+        // quadruples as the limit allows are held back.
+        assert_eq!(
+            get_new_request_ids(1),
+            (
+                vec![
+                    ecdsa::RequestId::from(vec![0; 32]),
+                    ecdsa::RequestId::from(vec![1; 32]),
+                    ecdsa::RequestId::from(vec![2; 32]),
+                ],
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn test_ecdsa_max_signing_requests_per_round() {
+        let ecdsa_config = EcdsaConfig::default();
+        assert_eq!(
+            max_signing_requests_per_round(&ecdsa_config),
+            DEFAULT_MAX_SIGNING_REQUESTS_PER_ROUND
+        );
+        let ecdsa_config = EcdsaConfig {
+            max_signing_requests_per_round: 4,
+            ..EcdsaConfig::default()
+        };
+        assert_eq!(max_signing_requests_per_round(&ecdsa_config), 4);
+    }
+
     #[test]
     fn test_ecdsa_update_next_key_transcript() {
         let num_of_nodes = 4;
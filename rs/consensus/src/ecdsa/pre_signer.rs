@@ -29,6 +29,8 @@ use ic_types::{Height, NodeId};
 use ic_types::crypto::canister_threshold_sig::idkg::IDkgDealing;
 
 use prometheus::IntCounterVec;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Debug, Formatter};
 use std::sync::Arc;
@@ -47,6 +49,7 @@ pub(crate) struct EcdsaPreSignerImpl {
     consensus_block_cache: Arc<dyn ConsensusBlockCache>,
     crypto: Arc<dyn ConsensusCrypto>,
     schedule: RoundRobin,
+    thread_pool: ThreadPool,
     metrics: EcdsaPreSignerMetrics,
     log: ReplicaLogger,
     malicious_flags: MaliciousFlags,
@@ -60,12 +63,24 @@ impl EcdsaPreSignerImpl {
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
         malicious_flags: MaliciousFlags,
+        max_parallel_transcript_operations: usize,
     ) -> Self {
+        // Rayon would pick the number of threads itself for zero.
+        assert!(
+            max_parallel_transcript_operations >= 1,
+            "at least one ECDSA transcript operation must be allowed to run"
+        );
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(max_parallel_transcript_operations)
+            .thread_name(|index| format!("ecdsa_pre_signer_{}", index))
+            .build()
+            .expect("failed to create the thread pool of the ECDSA pre-signer");
         Self {
             node_id,
             consensus_block_cache,
             crypto,
             schedule: RoundRobin::default(),
+            thread_pool,
             metrics: EcdsaPreSignerMetrics::new(metrics_registry),
             log,
             malicious_flags,
@@ -88,28 +103,44 @@ impl EcdsaPreSignerImpl {
             &self.log,
         );
 
-        requested_transcripts
-            .iter()
-            .filter(|transcript_params| {
-                // Issue a dealing if we are in the dealer list and we haven't
-                //already issued a dealing for this transcript
-                transcript_params.dealers().position(self.node_id).is_some()
-                    && !self.has_dealer_issued_dealing(
-                        ecdsa_pool,
-                        &transcript_params.transcript_id(),
-                        &self.node_id,
-                    )
-            })
-            .map(|transcript_params| {
-                self.crypto_create_dealing(
+        // Load the dependencies of the transcripts one after the other, as
+        // loading may need to read the pool, and only then create the
+        // dealings of the loaded transcripts concurrently.
+        let mut ret = Vec::new();
+        let mut loaded_transcripts = Vec::new();
+        for transcript_params in requested_transcripts.iter().filter(|transcript_params| {
+            // Issue a dealing if we are in the dealer list and we haven't
+            //already issued a dealing for this transcript
+            transcript_params.dealers().position(self.node_id).is_some()
+                && !self.has_dealer_issued_dealing(
                     ecdsa_pool,
-                    transcript_loader,
-                    block_reader,
-                    transcript_params,
+                    &transcript_params.transcript_id(),
+                    &self.node_id,
                 )
-            })
-            .flatten()
-            .collect()
+        }) {
+            match self.load_dependencies(
+                ecdsa_pool,
+                transcript_loader,
+                transcript_params,
+                block_reader.tip_height(),
+            ) {
+                Some(mut changes) => ret.append(&mut changes),
+                None => loaded_transcripts.push(transcript_params),
+            }
+        }
+
+        let crypto_ops = self.crypto_ops();
+        let requested_height = block_reader.tip_height();
+        let mut dealings: EcdsaChangeSet = self.thread_pool.install(|| {
+            loaded_transcripts
+                .par_iter()
+                .flat_map(|transcript_params| {
+                    crypto_ops.create_dealing(requested_height, transcript_params)
+                })
+                .collect()
+        });
+        ret.append(&mut dealings);
+        ret
     }
 
     /// Processes the dealings received from peer dealers
@@ -140,6 +171,7 @@ impl EcdsaPreSignerImpl {
         }
 
         let mut ret = Vec::new();
+        let mut dealings_to_verify = Vec::new();
         for (id, signed_dealing) in ecdsa_pool.unvalidated().signed_dealings() {
             let dealing = signed_dealing.get();
             // Remove the duplicate entries
@@ -187,15 +219,24 @@ impl EcdsaPreSignerImpl {
                             format!("Duplicate dealing: {}", signed_dealing),
                         ))
                     } else {
-                        let mut changes =
-                            self.crypto_verify_dealing(&id, transcript_params, signed_dealing);
-                        ret.append(&mut changes);
+                        dealings_to_verify.push((id, transcript_params, signed_dealing));
                     }
                 }
                 Action::Drop => ret.push(EcdsaChangeAction::RemoveUnvalidated(id)),
                 Action::Defer => {}
             }
         }
+
+        let crypto_ops = self.crypto_ops();
+        let mut verified: EcdsaChangeSet = self.thread_pool.install(|| {
+            dealings_to_verify
+                .par_iter()
+                .flat_map(|(id, transcript_params, signed_dealing)| {
+                    crypto_ops.verify_dealing(id, transcript_params, signed_dealing)
+                })
+                .collect()
+        });
+        ret.append(&mut verified);
         ret
     }
 
@@ -219,7 +260,7 @@ impl EcdsaPreSignerImpl {
             trancript_param_map.insert(transcript_params.transcript_id(), transcript_params);
         }
 
-        ecdsa_pool
+        let supported_dealings = ecdsa_pool
             .validated()
             .signed_dealings()
             .filter(|(_, signed_dealing)| {
@@ -252,11 +293,17 @@ impl EcdsaPreSignerImpl {
                     None
                 }
             })
-            .map(|(id, transcript_params, dealing)| {
-                self.crypto_create_dealing_support(&id, transcript_params, dealing)
-            })
-            .flatten()
-            .collect()
+            .collect::<Vec<_>>();
+
+        let crypto_ops = self.crypto_ops();
+        self.thread_pool.install(|| {
+            supported_dealings
+                .par_iter()
+                .flat_map(|(id, transcript_params, dealing)| {
+                    crypto_ops.create_dealing_support(id, transcript_params, dealing)
+                })
+                .collect()
+        })
     }
 
     /// Processes the received dealing support messages
@@ -299,6 +346,7 @@ impl EcdsaPreSignerImpl {
         }
 
         let mut ret = Vec::new();
+        let mut supports_to_verify = Vec::new();
         for (id, support) in ecdsa_pool.unvalidated().dealing_support() {
             let dealing = &support.content;
             let dealing_key = (
@@ -357,9 +405,7 @@ impl EcdsaPreSignerImpl {
                             format!("Duplicate support: {}", support),
                         ))
                     } else {
-                        let mut changes =
-                            self.crypto_verify_dealing_support(&id, transcript_params, support);
-                        ret.append(&mut changes);
+                        supports_to_verify.push((id, transcript_params, support));
                     }
                 }
                 Action::Drop => ret.push(EcdsaChangeAction::RemoveUnvalidated(id)),
@@ -367,6 +413,16 @@ impl EcdsaPreSignerImpl {
             }
         }
 
+        let crypto_ops = self.crypto_ops();
+        let mut verified: EcdsaChangeSet = self.thread_pool.install(|| {
+            supports_to_verify
+                .par_iter()
+                .flat_map(|(id, transcript_params, support)| {
+                    crypto_ops.verify_dealing_support(id, transcript_params, support)
+                })
+                .collect()
+        });
+        ret.append(&mut verified);
         ret
     }
 
@@ -438,22 +494,171 @@ impl EcdsaPreSignerImpl {
         ret
     }
 
-    /// Helper to create dealing
-    fn crypto_create_dealing(
+    fn crypto_ops(&self) -> PreSignerCryptoOps<'_> {
+        PreSignerCryptoOps {
+            node_id: self.node_id,
+            crypto: &*self.crypto,
+            metrics: &self.metrics,
+            log: &self.log,
+            malicious_flags: &self.malicious_flags,
+        }
+    }
+
+    /// Helper to load the transcripts the given transcript is dependent on.
+    /// Returns true if the dependencies were loaded successfully.
+    fn load_dependencies(
         &self,
         ecdsa_pool: &dyn EcdsaPool,
         transcript_loader: &dyn EcdsaTranscriptLoader,
-        block_reader: &dyn EcdsaBlockReader,
         transcript_params: &IDkgTranscriptParams,
-    ) -> EcdsaChangeSet {
-        if let Some(changes) = self.load_dependencies(
-            ecdsa_pool,
-            transcript_loader,
-            transcript_params,
-            block_reader.tip_height(),
-        ) {
-            return changes;
+        height: Height,
+    ) -> Option<EcdsaChangeSet> {
+        match &transcript_params.operation_type() {
+            IDkgTranscriptOperation::Random => None,
+            IDkgTranscriptOperation::ReshareOfMasked(t) => {
+                load_transcripts(ecdsa_pool, transcript_loader, &[t], height)
+            }
+            IDkgTranscriptOperation::ReshareOfUnmasked(t) => {
+                load_transcripts(ecdsa_pool, transcript_loader, &[t], height)
+            }
+            IDkgTranscriptOperation::UnmaskedTimesMasked(t1, t2) => {
+                load_transcripts(ecdsa_pool, transcript_loader, &[t1, t2], height)
+            }
         }
+    }
+
+    /// Checks if the we have a valid dealing from the dealer for the given
+    /// transcript
+    fn has_dealer_issued_dealing(
+        &self,
+        ecdsa_pool: &dyn EcdsaPool,
+        transcript_id: &IDkgTranscriptId,
+        dealer_id: &NodeId,
+    ) -> bool {
+        ecdsa_pool
+            .validated()
+            .signed_dealings()
+            .any(|(_, signed_dealing)| {
+                let dealing = signed_dealing.get();
+                dealing.idkg_dealing.dealer_id == *dealer_id
+                    && dealing.idkg_dealing.transcript_id == *transcript_id
+            })
+    }
+
+    /// Checks if the we have a valid dealing support from the node for the
+    /// given dealing
+    fn has_node_issued_dealing_support(
+        &self,
+        ecdsa_pool: &dyn EcdsaPool,
+        transcript_id: &IDkgTranscriptId,
+        dealer_id: &NodeId,
+        node_id: &NodeId,
+    ) -> bool {
+        ecdsa_pool
+            .validated()
+            .dealing_support()
+            .any(|(_, support)| {
+                support.content.idkg_dealing.dealer_id == *dealer_id
+                    && support.content.idkg_dealing.transcript_id == *transcript_id
+                    && support.signature.signer == *node_id
+            })
+    }
+
+    /// Checks if the dealing should be purged
+    fn should_purge(
+        &self,
+        dealing: &EcdsaDealing,
+        current_height: Height,
+        in_progress: &BTreeSet<IDkgTranscriptId>,
+    ) -> bool {
+        dealing.requested_height <= current_height
+            && !in_progress.contains(&dealing.idkg_dealing.transcript_id)
+    }
+}
+
+impl EcdsaPreSigner for EcdsaPreSignerImpl {
+    fn on_state_change(
+        &self,
+        ecdsa_pool: &dyn EcdsaPool,
+        transcript_loader: &dyn EcdsaTranscriptLoader,
+    ) -> EcdsaChangeSet {
+        let block_reader = EcdsaBlockReaderImpl::new(self.consensus_block_cache.finalized_chain());
+        let metrics = self.metrics.clone();
+
+        let send_dealings = || {
+            timed_call(
+                "send_dealings",
+                || self.send_dealings(ecdsa_pool, transcript_loader, &block_reader),
+                &metrics.on_state_change_duration,
+            )
+        };
+        let validate_dealings = || {
+            timed_call(
+                "validate_dealings",
+                || self.validate_dealings(ecdsa_pool, &block_reader),
+                &metrics.on_state_change_duration,
+            )
+        };
+        let send_dealing_support = || {
+            timed_call(
+                "send_dealing_support",
+                || self.send_dealing_support(ecdsa_pool, &block_reader),
+                &metrics.on_state_change_duration,
+            )
+        };
+        let validate_dealing_support = || {
+            timed_call(
+                "validate_dealing_support",
+                || self.validate_dealing_support(ecdsa_pool, &block_reader),
+                &metrics.on_state_change_duration,
+            )
+        };
+        let purge_artifacts = || {
+            timed_call(
+                "purge_artifacts",
+                || self.purge_artifacts(ecdsa_pool, &block_reader),
+                &metrics.on_state_change_duration,
+            )
+        };
+
+        let calls: [&'_ dyn Fn() -> EcdsaChangeSet; 5] = [
+            &send_dealings,
+            &validate_dealings,
+            &send_dealing_support,
+            &validate_dealing_support,
+            &purge_artifacts,
+        ];
+        self.schedule.call_next(&calls)
+    }
+}
+
+/// The crypto operations of the pre-signer that are run concurrently on its
+/// thread pool: creating and verifying the dealings for the transcripts of
+/// different quadruples, and issuing and verifying the support for them.
+struct PreSignerCryptoOps<'a> {
+    node_id: NodeId,
+    crypto: &'a dyn ConsensusCrypto,
+    metrics: &'a EcdsaPreSignerMetrics,
+    log: &'a ReplicaLogger,
+    malicious_flags: &'a MaliciousFlags,
+}
+
+impl<'a> PreSignerCryptoOps<'a> {
+    /// Helper to create and sign a dealing. Assumes the dependencies of the
+    /// transcript were loaded.
+    fn create_dealing(
+        &self,
+        requested_height: Height,
+        transcript_params: &IDkgTranscriptParams,
+    ) -> EcdsaChangeSet {
+        // Log the height and transcript id so that the crypto logs of the dealing can be
+        // correlated across nodes
+        debug!(
+            self.log,
+            "Creating dealing";
+            consensus.height => requested_height.get(),
+            crypto.idkg_transcript_id => format!("{:?}", transcript_params.transcript_id()),
+        );
 
         // Create the dealing
         let idkg_dealing = match IDkgProtocol::create_dealing(&*self.crypto, transcript_params) {
@@ -479,10 +684,10 @@ impl EcdsaPreSignerImpl {
 
         // Corrupt the dealing if malicious testing is enabled
         #[cfg(feature = "malicious_code")]
-        let idkg_dealing = self.crypto_corrupt_dealing(idkg_dealing, transcript_params);
+        let idkg_dealing = self.corrupt_dealing(idkg_dealing, transcript_params);
 
         let ecdsa_dealing = EcdsaDealing {
-            requested_height: block_reader.tip_height(),
+            requested_height,
             idkg_dealing,
         };
 
@@ -516,78 +721,9 @@ impl EcdsaPreSignerImpl {
         }
     }
 
-    /// Helper to verify a dealing received for a transcript we are building
-    fn crypto_verify_dealing(
-        &self,
-        id: &EcdsaMessageId,
-        transcript_params: &IDkgTranscriptParams,
-        signed_dealing: &EcdsaSignedDealing,
-    ) -> EcdsaChangeSet {
-        let dealing = signed_dealing.get();
-
-        // Verify the dealer signature
-        if let Err(error) = self
-            .crypto
-            .verify(signed_dealing, transcript_params.registry_version())
-        {
-            if error.is_replicated() {
-                self.metrics
-                    .pre_sign_errors_inc("verify_dealing_signature_permanent");
-                return vec![EcdsaChangeAction::HandleInvalid(
-                    id.clone(),
-                    format!(
-                        "Dealing signature validation(permanent error): {}, error = {:?}",
-                        signed_dealing, error
-                    ),
-                )];
-            } else {
-                // Defer in case of transient errors
-                debug!(
-                    self.log,
-                    "Dealing signature validation(transient error): {}, error = {:?}",
-                    signed_dealing,
-                    error
-                );
-                self.metrics
-                    .pre_sign_errors_inc("verify_dealing_signature_transient");
-                return Default::default();
-            }
-        }
-
-        IDkgProtocol::verify_dealing_public(&*self.crypto, transcript_params, &dealing.idkg_dealing)
-            .map_or_else(
-                |error| {
-                    if error.is_replicated() {
-                        self.metrics.pre_sign_errors_inc("verify_dealing_permanent");
-                        vec![EcdsaChangeAction::HandleInvalid(
-                            id.clone(),
-                            format!(
-                                "Dealing validation(permanent error): {}, error = {:?}",
-                                signed_dealing, error
-                            ),
-                        )]
-                    } else {
-                        // Defer in case of transient errors
-                        debug!(
-                            self.log,
-                            "Dealing validation(transient error): {}, error = {:?}",
-                            signed_dealing,
-                            error
-                        );
-                        self.metrics.pre_sign_errors_inc("verify_dealing_transient");
-                        Default::default()
-                    }
-                },
-                |()| {
-                    self.metrics.pre_sign_metrics_inc("dealing_received");
-                    vec![EcdsaChangeAction::MoveToValidated(id.clone())]
-                },
-            )
-    }
-
     /// Helper to corrupt the crypto dealing for malicious testing
     #[cfg(feature = "malicious_code")]
-    fn crypto_corrupt_dealing(
+    fn corrupt_dealing(
         &self,
         idkg_dealing: IDkgDealing,
         transcript_params: &IDkgTranscriptParams,
@@ -627,7 +763,7 @@ impl EcdsaPreSignerImpl {
 
     /// Helper to issue a support share for a dealing. Assumes we are a receiver
     /// for the dealing.
-    fn crypto_create_dealing_support(
+    fn create_dealing_support(
         &self,
         id: &EcdsaMessageId,
         transcript_params: &IDkgTranscriptParams,
@@ -687,8 +823,77 @@ impl EcdsaPreSignerImpl {
             )
     }
 
+    /// Helper to verify a dealing received for a transcript we are building
+    fn verify_dealing(
+        &self,
+        id: &EcdsaMessageId,
+        transcript_params: &IDkgTranscriptParams,
+        signed_dealing: &EcdsaSignedDealing,
+    ) -> EcdsaChangeSet {
+        let dealing = signed_dealing.get();
+
+        // Verify the dealer signature
+        if let Err(error) = self
+            .crypto
+            .verify(signed_dealing, transcript_params.registry_version())
+        {
+            if error.is_replicated() {
+                self.metrics
+                    .pre_sign_errors_inc("verify_dealing_signature_permanent");
+                return vec![EcdsaChangeAction::HandleInvalid(
+                    id.clone(),
+                    format!(
+                        "Dealing signature validation(permanent error): {}, error = {:?}",
+                        signed_dealing, error
+                    ),
+                )];
+            } else {
+                // Defer in case of transient errors
+                debug!(
+                    self.log,
+                    "Dealing signature validation(transient error): {}, error = {:?}",
+                    signed_dealing,
+                    error
+                );
+                self.metrics
+                    .pre_sign_errors_inc("verify_dealing_signature_transient");
+                return Default::default();
+            }
+        }
+
+        IDkgProtocol::verify_dealing_public(&*self.crypto, transcript_params, &dealing.idkg_dealing)
+            .map_or_else(
+                |error| {
+                    if error.is_replicated() {
+                        self.metrics.pre_sign_errors_inc("verify_dealing_permanent");
+                        vec![EcdsaChangeAction::HandleInvalid(
+                            id.clone(),
+                            format!(
+                                "Dealing validation(permanent error): {}, error = {:?}",
+                                signed_dealing, error
+                            ),
+                        )]
+                    } else {
+                        // Defer in case of transient errors
+                        debug!(
+                            self.log,
+                            "Dealing validation(transient error): {}, error = {:?}",
+                            signed_dealing,
+                            error
+                        );
+                        self.metrics.pre_sign_errors_inc("verify_dealing_transient");
+                        Default::default()
+                    }
+                },
+                |()| {
+                    self.metrics.pre_sign_metrics_inc("dealing_received");
+                    vec![EcdsaChangeAction::MoveToValidated(id.clone())]
+                },
+            )
+    }
+
     /// Helper to verify a support share for a dealing
-    fn crypto_verify_dealing_support(
+    fn verify_dealing_support(
         &self,
         id: &EcdsaMessageId,
         transcript_params: &IDkgTranscriptParams,
@@ -714,133 +919,6 @@ impl EcdsaPreSignerImpl {
                 },
             )
     }
-
-    /// Helper to load the transcripts the given transcript is dependent on.
-    /// Returns true if the dependencies were loaded successfully.
-    fn load_dependencies(
-        &self,
-        ecdsa_pool: &dyn EcdsaPool,
-        transcript_loader: &dyn EcdsaTranscriptLoader,
-        transcript_params: &IDkgTranscriptParams,
-        height: Height,
-    ) -> Option<EcdsaChangeSet> {
-        match &transcript_params.operation_type() {
-            IDkgTranscriptOperation::Random => None,
-            IDkgTranscriptOperation::ReshareOfMasked(t) => {
-                load_transcripts(ecdsa_pool, transcript_loader, &[t], height)
-            }
-            IDkgTranscriptOperation::ReshareOfUnmasked(t) => {
-                load_transcripts(ecdsa_pool, transcript_loader, &[t], height)
-            }
-            IDkgTranscriptOperation::UnmaskedTimesMasked(t1, t2) => {
-                load_transcripts(ecdsa_pool, transcript_loader, &[t1, t2], height)
-            }
-        }
-    }
-
-    /// Checks if the we have a valid dealing from the dealer for the given
-    /// transcript
-    fn has_dealer_issued_dealing(
-        &self,
-        ecdsa_pool: &dyn EcdsaPool,
-        transcript_id: &IDkgTranscriptId,
-        dealer_id: &NodeId,
-    ) -> bool {
-        ecdsa_pool
-            .validated()
-            .signed_dealings()
-            .any(|(_, signed_dealing)| {
-                let dealing = signed_dealing.get();
-                dealing.idkg_dealing.dealer_id == *dealer_id
-                    && dealing.idkg_dealing.transcript_id == *transcript_id
-            })
-    }
-
-    /// Checks if the we have a valid dealing support from the node for the
-    /// given dealing
-    fn has_node_issued_dealing_support(
-        &self,
-        ecdsa_pool: &dyn EcdsaPool,
-        transcript_id: &IDkgTranscriptId,
-        dealer_id: &NodeId,
-        node_id: &NodeId,
-    ) -> bool {
-        ecdsa_pool
-            .validated()
-            .dealing_support()
-            .any(|(_, support)| {
-                support.content.idkg_dealing.dealer_id == *dealer_id
-                    && support.content.idkg_dealing.transcript_id == *transcript_id
-                    && support.signature.signer == *node_id
-            })
-    }
-
-    /// Checks if the dealing should be purged
-    fn should_purge(
-        &self,
-        dealing: &EcdsaDealing,
-        current_height: Height,
-        in_progress: &BTreeSet<IDkgTranscriptId>,
-    ) -> bool {
-        dealing.requested_height <= current_height
-            && !in_progress.contains(&dealing.idkg_dealing.transcript_id)
-    }
-}
-
-impl EcdsaPreSigner for EcdsaPreSignerImpl {
-    fn on_state_change(
-        &self,
-        ecdsa_pool: &dyn EcdsaPool,
-        transcript_loader: &dyn EcdsaTranscriptLoader,
-    ) -> EcdsaChangeSet {
-        let block_reader = EcdsaBlockReaderImpl::new(self.consensus_block_cache.finalized_chain());
-        let metrics = self.metrics.clone();
-
-        let send_dealings = || {
-            timed_call(
-                "send_dealings",
-                || self.send_dealings(ecdsa_pool, transcript_loader, &block_reader),
-                &metrics.on_state_change_duration,
-            )
-        };
-        let validate_dealings = || {
-            timed_call(
-                "validate_dealings",
-                || self.validate_dealings(ecdsa_pool, &block_reader),
-                &metrics.on_state_change_duration,
-            )
-        };
-        let send_dealing_support = || {
-            timed_call(
-                "send_dealing_support",
-                || self.send_dealing_support(ecdsa_pool, &block_reader),
-                &metrics.on_state_change_duration,
-            )
-        };
-        let validate_dealing_support = || {
-            timed_call(
-                "validate_dealing_support",
-                || self.validate_dealing_support(ecdsa_pool, &block_reader),
-                &metrics.on_state_change_duration,
-            )
-        };
-        let purge_artifacts = || {
-            timed_call(
-                "purge_artifacts",
-                || self.purge_artifacts(ecdsa_pool, &block_reader),
-                &metrics.on_state_change_duration,
-            )
-        };
-
-        let calls: [&'_ dyn Fn() -> EcdsaChangeSet; 5] = [
-            &send_dealings,
-            &validate_dealings,
-            &send_dealing_support,
-            &validate_dealing_support,
-            &purge_artifacts,
-        ];
-        self.schedule.call_next(&calls)
-    }
 }
 
 pub(crate) trait EcdsaTranscriptBuilder: Send {
@@ -1267,6 +1345,41 @@ mod tests {
         })
     }
 
+    // Tests that the dealings for more transcripts than the pre signer has
+    // threads are all issued, in the order of the requested transcripts
+    #[test]
+    fn test_ecdsa_send_dealings_concurrently() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            with_test_replica_logger(|logger| {
+                let (ecdsa_pool, pre_signer) = create_pre_signer_dependencies(pool_config, logger);
+                let transcript_ids: Vec<_> = (1..=(3 * PRE_SIGNER_THREADS))
+                    .map(create_transcript_id)
+                    .collect();
+                let transcript_params = transcript_ids
+                    .iter()
+                    .map(|id| create_transcript_param(*id, &[NODE_1], &[NODE_2]))
+                    .collect();
+                let block_reader =
+                    TestEcdsaBlockReader::for_pre_signer_test(Height::from(100), transcript_params);
+                let transcript_loader: TestEcdsaTranscriptLoader = Default::default();
+
+                let change_set =
+                    pre_signer.send_dealings(&ecdsa_pool, &transcript_loader, &block_reader);
+
+                let dealt_transcript_ids: Vec<_> = change_set
+                    .iter()
+                    .map(|action| match action {
+                        EcdsaChangeAction::AddToValidated(EcdsaMessage::EcdsaSignedDealing(
+                            signed_dealing,
+                        )) => signed_dealing.get().idkg_dealing.transcript_id,
+                        action => panic!("Unexpected action: {:?}", action),
+                    })
+                    .collect();
+                assert_eq!(dealt_transcript_ids, transcript_ids);
+            })
+        })
+    }
+
     // Tests that dealing is not issued if the node is in the list of dealers
     // specified by the transcript params
     #[test]
@@ -1415,6 +1528,46 @@ mod tests {
         })
     }
 
+    // Tests that the dealings for more transcripts than the pre-signer has
+    // threads are all verified
+    #[test]
+    fn test_ecdsa_validate_dealings_concurrently() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            with_test_replica_logger(|logger| {
+                let (mut ecdsa_pool, pre_signer) =
+                    create_pre_signer_dependencies(pool_config, logger);
+                let time_source = FastForwardTimeSource::new();
+                let transcript_ids: Vec<_> = (1..=(3 * PRE_SIGNER_THREADS))
+                    .map(create_transcript_id)
+                    .collect();
+                let transcript_params = transcript_ids
+                    .iter()
+                    .map(|id| create_transcript_param(*id, &[NODE_2], &[NODE_1]))
+                    .collect();
+                let block_reader =
+                    TestEcdsaBlockReader::for_pre_signer_test(Height::from(100), transcript_params);
+
+                let mut msg_ids = Vec::new();
+                for id in &transcript_ids {
+                    let mut dealing = create_dealing(*id, NODE_2);
+                    dealing.content.requested_height = Height::from(100);
+                    msg_ids.push(EcdsaSignedDealing::key_to_outer_hash(&dealing.key()));
+                    ecdsa_pool.insert(UnvalidatedArtifact {
+                        message: EcdsaMessage::EcdsaSignedDealing(dealing),
+                        peer_id: NODE_2,
+                        timestamp: time_source.get_relative_time(),
+                    });
+                }
+
+                let change_set = pre_signer.validate_dealings(&ecdsa_pool, &block_reader);
+                assert_eq!(change_set.len(), msg_ids.len());
+                for msg_id in &msg_ids {
+                    assert!(is_moved_to_validated(&change_set, msg_id));
+                }
+            })
+        })
+    }
+
     // Tests that duplicate dealings from a dealer for the same transcript
     // are dropped.
     #[test]
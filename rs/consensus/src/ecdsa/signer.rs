@@ -13,11 +13,14 @@ use ic_interfaces::ecdsa::{EcdsaChangeAction, EcdsaChangeSet, EcdsaPool};
 use ic_logger::{debug, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_types::artifact::EcdsaMessageId;
-use ic_types::consensus::ecdsa::{EcdsaBlockReader, EcdsaMessage, EcdsaSigShare, RequestId};
+use ic_types::consensus::ecdsa::{
+    EcdsaBlockReader, EcdsaMessage, EcdsaSigShare, RequestId, TranscriptLookupError,
+};
+use ic_types::crypto::canister_threshold_sig::idkg::IDkgTranscriptId;
 use ic_types::crypto::canister_threshold_sig::{
     ThresholdEcdsaCombinedSignature, ThresholdEcdsaSigInputs, ThresholdEcdsaSigShare,
 };
-use ic_types::{Height, NodeId};
+use ic_types::{Height, NodeId, RegistryVersion};
 
 use prometheus::IntCounterVec;
 use std::collections::{BTreeMap, BTreeSet};
@@ -33,6 +36,42 @@ pub(crate) trait EcdsaSigner: Send {
     ) -> EcdsaChangeSet;
 }
 
+/// The reasons for the signer to not create a signature share for a request.
+#[derive(Clone, Debug)]
+pub enum SigShareAdmissionError {
+    /// The key transcript of the request is older than the current key
+    /// transcript of the subnet, as published in the summary block of the
+    /// current interval, by more than the configured grace.
+    StaleKeyTranscript {
+        key_transcript_id: IDkgTranscriptId,
+        registry_version: RegistryVersion,
+        current_registry_version: RegistryVersion,
+    },
+    /// The current key transcript of the subnet could not be looked up.
+    CurrentKeyTranscriptLookup(TranscriptLookupError),
+}
+
+impl fmt::Display for SigShareAdmissionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaleKeyTranscript {
+                key_transcript_id,
+                registry_version,
+                current_registry_version,
+            } => write!(
+                f,
+                "key transcript {:?} at registry version {} is stale, the current key transcript is at registry version {}",
+                key_transcript_id, registry_version, current_registry_version
+            ),
+            Self::CurrentKeyTranscriptLookup(error) => {
+                write!(f, "failed to look up the current key transcript: {:?}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SigShareAdmissionError {}
+
 pub(crate) struct EcdsaSignerImpl {
     node_id: NodeId,
     consensus_block_cache: Arc<dyn ConsensusBlockCache>,
@@ -40,6 +79,7 @@ pub(crate) struct EcdsaSignerImpl {
     schedule: RoundRobin,
     metrics: EcdsaSignerMetrics,
     log: ReplicaLogger,
+    key_transcript_grace: u64,
 }
 
 impl EcdsaSignerImpl {
@@ -49,6 +89,7 @@ impl EcdsaSignerImpl {
         crypto: Arc<dyn ConsensusCrypto>,
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
+        key_transcript_grace: u64,
     ) -> Self {
         Self {
             node_id,
@@ -57,6 +98,7 @@ impl EcdsaSignerImpl {
             schedule: RoundRobin::default(),
             metrics: EcdsaSignerMetrics::new(metrics_registry),
             log,
+            key_transcript_grace,
         }
     }
 
@@ -224,6 +266,38 @@ impl EcdsaSignerImpl {
         )
     }
 
+    /// Checks that the key transcript of the signature inputs is not older
+    /// than the current key transcript of the ECDSA summary of the interval
+    /// by more than `key_transcript_grace` registry versions, so that no
+    /// resources are spent on shares for superseded keys. Shares are
+    /// admitted while there is no current key transcript yet.
+    fn admit_signature_share(
+        &self,
+        block_reader: &dyn EcdsaBlockReader,
+        sig_inputs: &ThresholdEcdsaSigInputs,
+    ) -> Result<(), SigShareAdmissionError> {
+        let current_key_transcript = match block_reader.current_key_transcript() {
+            Some(current_key_transcript) => block_reader
+                .transcript(current_key_transcript.as_ref())
+                .map_err(SigShareAdmissionError::CurrentKeyTranscriptLookup)?,
+            None => return Ok(()),
+        };
+        let key_transcript = sig_inputs.key_transcript();
+        if key_transcript
+            .registry_version
+            .get()
+            .saturating_add(self.key_transcript_grace)
+            < current_key_transcript.registry_version.get()
+        {
+            return Err(SigShareAdmissionError::StaleKeyTranscript {
+                key_transcript_id: key_transcript.transcript_id,
+                registry_version: key_transcript.registry_version,
+                current_registry_version: current_key_transcript.registry_version,
+            });
+        }
+        Ok(())
+    }
+
     /// Helper to create the signature share
     fn crypto_create_signature_share(
         &self,
@@ -233,6 +307,20 @@ impl EcdsaSignerImpl {
         request_id: &RequestId,
         sig_inputs: &ThresholdEcdsaSigInputs,
     ) -> EcdsaChangeSet {
+        if let Err(error) = self.admit_signature_share(block_reader, sig_inputs) {
+            debug!(
+                self.log,
+                "Not creating share: request_id = {:?}, {}", request_id, error
+            );
+            self.metrics.sign_errors_inc(match error {
+                SigShareAdmissionError::StaleKeyTranscript { .. } => "stale_key_transcript",
+                SigShareAdmissionError::CurrentKeyTranscriptLookup(_) => {
+                    "current_key_transcript_lookup"
+                }
+            });
+            return Default::default();
+        }
+
         if let Some(changes) = self.load_dependencies(
             ecdsa_pool,
             transcript_loader,
@@ -242,6 +330,13 @@ impl EcdsaSignerImpl {
             return changes;
         }
 
+        debug!(
+            self.log,
+            "Creating signature share: request_id = {:?}", request_id;
+            consensus.height => block_reader.tip_height().get(),
+            crypto.idkg_transcript_id => format!("{:?}", sig_inputs.key_transcript().transcript_id),
+        );
+
         ThresholdEcdsaSigner::sign_share(&*self.crypto, sig_inputs).map_or_else(
             |error| {
                 warn!(
@@ -724,6 +819,59 @@ mod tests {
         })
     }
 
+    // Tests that signature shares are only sent for requests whose key transcript
+    // is at most KEY_TRANSCRIPT_GRACE registry versions older than the current one.
+    #[test]
+    fn test_ecdsa_send_signature_shares_for_fresh_key_transcripts() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            with_test_replica_logger(|logger| {
+                let (ecdsa_pool, signer) = create_signer_dependencies(pool_config, logger);
+                let id_1 = create_request_id(1);
+                let sig_inputs = create_sig_inputs(1);
+                let key_transcript = sig_inputs
+                    .idkg_transcripts
+                    .get(sig_inputs.sig_inputs_ref.key_transcript_ref.as_ref())
+                    .unwrap()
+                    .clone();
+                let transcript_loader: TestEcdsaTranscriptLoader = Default::default();
+
+                let block_reader_with_current_key = |registry_version: u64| {
+                    let mut block_reader = TestEcdsaBlockReader::for_signer_test(
+                        Height::from(100),
+                        vec![(id_1.clone(), create_sig_inputs(1))],
+                    );
+                    let mut current_key_transcript = key_transcript.clone();
+                    current_key_transcript.transcript_id = create_transcript_id(1000);
+                    current_key_transcript.registry_version =
+                        RegistryVersion::from(registry_version);
+                    block_reader.set_current_key_transcript(current_key_transcript);
+                    block_reader
+                };
+
+                // The key transcript of the request is within the grace
+                let block_reader = block_reader_with_current_key(
+                    key_transcript.registry_version.get() + KEY_TRANSCRIPT_GRACE,
+                );
+                let change_set =
+                    signer.send_signature_shares(&ecdsa_pool, &transcript_loader, &block_reader);
+                assert_eq!(change_set.len(), 1);
+                assert!(is_signature_share_added_to_validated(
+                    &change_set,
+                    &id_1,
+                    block_reader.tip_height()
+                ));
+
+                // The key transcript of the request is superseded
+                let block_reader = block_reader_with_current_key(
+                    key_transcript.registry_version.get() + KEY_TRANSCRIPT_GRACE + 1,
+                );
+                let change_set =
+                    signer.send_signature_shares(&ecdsa_pool, &transcript_loader, &block_reader);
+                assert!(change_set.is_empty());
+            })
+        })
+    }
+
     // Tests that complaints are generated and added to the pool if loading transcript
     // results in complaints.
     #[test]
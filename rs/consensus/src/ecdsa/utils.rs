@@ -6,7 +6,7 @@ use ic_interfaces::ecdsa::{EcdsaChangeAction, EcdsaChangeSet, EcdsaPool};
 use ic_types::consensus::ecdsa::{EcdsaBlockReader, TranscriptRef};
 use ic_types::consensus::ecdsa::{
     EcdsaDataPayload, EcdsaMessage, IDkgTranscriptParamsRef, RequestId, ThresholdEcdsaSigInputsRef,
-    TranscriptLookupError,
+    TranscriptLookupError, UnmaskedTranscript,
 };
 use ic_types::consensus::{Block, BlockPayload, HasHeight};
 use ic_types::crypto::canister_threshold_sig::idkg::IDkgTranscript;
@@ -18,6 +18,7 @@ pub(crate) struct EcdsaBlockReaderImpl {
     chain: Arc<dyn ConsensusBlockChain>,
     tip: Block,
     tip_ecdsa_payload: Option<EcdsaDataPayload>,
+    current_key_transcript: Option<UnmaskedTranscript>,
 }
 
 impl EcdsaBlockReaderImpl {
@@ -28,10 +29,18 @@ impl EcdsaBlockReaderImpl {
         } else {
             None
         };
+        let current_key_transcript = chain
+            .block(tip.payload.as_ref().dkg_interval_start_height())
+            .filter(|summary| summary.payload.is_summary())
+            .and_then(|summary| {
+                let ecdsa_summary = summary.payload.as_ref().as_summary().ecdsa.as_ref();
+                ecdsa_summary.map(|ecdsa_summary| ecdsa_summary.current_key_transcript)
+            });
         Self {
             chain,
             tip,
             tip_ecdsa_payload,
+            current_key_transcript,
         }
     }
 }
@@ -81,6 +90,10 @@ impl EcdsaBlockReader for EcdsaBlockReaderImpl {
             })
     }
 
+    fn current_key_transcript(&self) -> Option<&UnmaskedTranscript> {
+        self.current_key_transcript.as_ref()
+    }
+
     fn transcript(
         &self,
         transcript_ref: &TranscriptRef,
@@ -220,6 +233,7 @@ pub(crate) mod test_utils {
         requested_transcripts: Vec<IDkgTranscriptParamsRef>,
         requested_signatures: Vec<(RequestId, ThresholdEcdsaSigInputsRef)>,
         idkg_transcripts: BTreeMap<TranscriptRef, IDkgTranscript>,
+        current_key_transcript: Option<UnmaskedTranscript>,
     }
 
     impl TestEcdsaBlockReader {
@@ -229,6 +243,7 @@ pub(crate) mod test_utils {
                 requested_transcripts: Vec::new(),
                 requested_signatures: Vec::new(),
                 idkg_transcripts: BTreeMap::new(),
+                current_key_transcript: None,
             }
         }
 
@@ -250,6 +265,7 @@ pub(crate) mod test_utils {
                 requested_transcripts,
                 requested_signatures: vec![],
                 idkg_transcripts,
+                current_key_transcript: None,
             }
         }
 
@@ -271,6 +287,7 @@ pub(crate) mod test_utils {
                 requested_transcripts: vec![],
                 requested_signatures,
                 idkg_transcripts,
+                current_key_transcript: None,
             }
         }
 
@@ -288,6 +305,7 @@ pub(crate) mod test_utils {
                 requested_transcripts: vec![],
                 requested_signatures: vec![],
                 idkg_transcripts,
+                current_key_transcript: None,
             }
         }
 
@@ -298,6 +316,14 @@ pub(crate) mod test_utils {
         ) {
             self.idkg_transcripts.insert(transcript_ref, transcript);
         }
+
+        pub(crate) fn set_current_key_transcript(&mut self, transcript: IDkgTranscript) {
+            let key_transcript_ref =
+                UnmaskedTranscript::try_from((self.height, &transcript)).unwrap();
+            self.idkg_transcripts
+                .insert(*key_transcript_ref.as_ref(), transcript);
+            self.current_key_transcript = Some(key_transcript_ref);
+        }
     }
 
     impl EcdsaBlockReader for TestEcdsaBlockReader {
@@ -329,6 +355,10 @@ pub(crate) mod test_utils {
         fn active_transcripts(&self) -> Vec<TranscriptRef> {
             self.idkg_transcripts.keys().cloned().collect()
         }
+
+        fn current_key_transcript(&self) -> Option<&UnmaskedTranscript> {
+            self.current_key_transcript.as_ref()
+        }
     }
 
     pub(crate) enum TestTranscriptLoadStatus {
@@ -388,6 +418,13 @@ pub(crate) mod test_utils {
         }
     }
 
+    // The pre signer of the tests runs its crypto operations concurrently
+    pub(crate) const PRE_SIGNER_THREADS: usize = 4;
+
+    // The signer of the tests creates shares for key transcripts that are
+    // at most this many registry versions older than the current one
+    pub(crate) const KEY_TRANSCRIPT_GRACE: u64 = 2;
+
     // Sets up the dependencies and creates the pre signer
     pub(crate) fn create_pre_signer_dependencies(
         pool_config: ArtifactPoolConfig,
@@ -410,6 +447,7 @@ pub(crate) mod test_utils {
             metrics_registry.clone(),
             logger.clone(),
             MaliciousBehaviour::new(false).malicious_flags,
+            PRE_SIGNER_THREADS,
         );
         let ecdsa_pool = EcdsaPoolImpl::new(logger, metrics_registry);
 
@@ -437,6 +475,7 @@ pub(crate) mod test_utils {
             crypto,
             metrics_registry.clone(),
             logger.clone(),
+            KEY_TRANSCRIPT_GRACE,
         );
         let ecdsa_pool = EcdsaPoolImpl::new(logger, metrics_registry);
 
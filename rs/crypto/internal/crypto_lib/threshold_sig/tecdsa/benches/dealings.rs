@@ -47,6 +47,10 @@ fn dealings(c: &mut Criterion) {
     c.bench_function("create_dealing(Random, 5/9)", |b| {
         b.iter(|| create_random_dealing(5, 9))
     });
+
+    c.bench_function("create_dealing(Random, 14/40)", |b| {
+        b.iter(|| create_random_dealing(14, 40))
+    });
 }
 
 criterion_group!(benches, dealings);
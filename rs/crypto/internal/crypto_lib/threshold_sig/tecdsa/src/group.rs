@@ -516,6 +516,45 @@ impl EccPoint {
         }
     }
 
+    /// Multiply each of `points` by the same `scalar`
+    ///
+    /// This is equivalent to calling `scalar_mul` on each of the points, and
+    /// uses the constant time multiplication of the curve library for each
+    /// of them.
+    pub fn batch_scalar_mul(
+        points: &[Self],
+        scalar: &EccScalar,
+    ) -> ThresholdEcdsaResult<Vec<Self>> {
+        match scalar {
+            EccScalar::K256(s) => {
+                let points = points
+                    .iter()
+                    .map(|pt| match pt {
+                        Self::K256(pt) => Ok(*pt),
+                        _ => Err(ThresholdEcdsaError::CurveMismatch),
+                    })
+                    .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+                Ok(secp256k1::Point::batch_mul(&points, s)
+                    .into_iter()
+                    .map(Self::K256)
+                    .collect())
+            }
+            EccScalar::P256(s) => {
+                let points = points
+                    .iter()
+                    .map(|pt| match pt {
+                        Self::P256(pt) => Ok(*pt),
+                        _ => Err(ThresholdEcdsaError::CurveMismatch),
+                    })
+                    .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+                Ok(secp256r1::Point::batch_mul(&points, s)
+                    .into_iter()
+                    .map(Self::P256)
+                    .collect())
+            }
+        }
+    }
+
     /// Perform point doubling
     fn double(&self) -> Self {
         match self {
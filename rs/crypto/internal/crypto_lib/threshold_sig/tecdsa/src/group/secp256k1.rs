@@ -213,6 +213,11 @@ impl Point {
         Self::new(self.p * scalar.s)
     }
 
+    /// Multiply each of the points by the same scalar
+    pub fn batch_mul(points: &[Self], scalar: &Scalar) -> Vec<Self> {
+        points.iter().map(|pt| pt.mul(scalar)).collect()
+    }
+
     /// Serialize the point to bytes in compressed format
     pub fn serialize(&self) -> Vec<u8> {
         self.p.to_affine().to_bytes().to_vec()
@@ -5,7 +5,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 use ic_types::crypto::canister_threshold_sig::error::{
-    IDkgLoadTranscriptError, IDkgVerifyComplaintError, IDkgVerifyTranscriptError,
+    IDkgLoadTranscriptError, IDkgVerifyComplaintError, IDkgVerifyDealingPublicError,
+    IDkgVerifyTranscriptError,
 };
 pub use ic_types::crypto::canister_threshold_sig::EcdsaPublicKey;
 pub use ic_types::NodeIndex;
@@ -63,6 +64,18 @@ pub use crate::xmd::*;
 pub use crate::key_derivation::{DerivationIndex, DerivationPath};
 pub use sign::{ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaSigShareInternal};
 
+/// Create a proof of possession of a MEGa private key
+///
+/// The proof is bound to the `associated_data`, which must be given again
+/// when verifying the proof with [`verify_mega_key_proof_of_possession`].
+pub fn create_mega_key_proof_of_possession(
+    private_key: &MEGaPrivateKey,
+    associated_data: &[u8],
+    seed: Randomness,
+) -> Result<MEGaKeyProofOfPossession, ThresholdEcdsaError> {
+    MEGaKeyProofOfPossession::create(Seed::from_randomness(&seed), private_key, associated_data)
+}
+
 /// Create MEGa encryption keypair
 pub fn gen_keypair(
     curve_type: EccCurveType,
@@ -313,6 +326,24 @@ impl From<ThresholdEcdsaError> for IDkgVerifyDealingInternalError {
     }
 }
 
+impl From<IDkgVerifyDealingInternalError> for IDkgVerifyDealingPublicError {
+    fn from(verify_dealing_internal_error: IDkgVerifyDealingInternalError) -> Self {
+        type Vdie = IDkgVerifyDealingInternalError;
+        type Vdpe = IDkgVerifyDealingPublicError;
+        match verify_dealing_internal_error {
+            Vdie::UnsupportedAlgorithm => Vdpe::InvalidArgument {
+                internal_error: "unsupported algorithm".to_string(),
+            },
+            Vdie::InvalidCommitment | Vdie::InvalidProof | Vdie::InvalidRecipients => {
+                Vdpe::InvalidDealing {
+                    reason: format!("{:?}", verify_dealing_internal_error),
+                }
+            }
+            Vdie::InternalError(internal_error) => Vdpe::InternalError { internal_error },
+        }
+    }
+}
+
 /// Verifies a dealing using public information
 ///
 /// This function checks that the dealing has the expected type of
@@ -563,6 +594,15 @@ impl From<ThresholdEcdsaError> for ThresholdEcdsaCombineSigSharesInternalError {
 ///
 /// The signature shares must be verified prior to use, and there must
 /// be at least reconstruction_threshold many of them.
+///
+/// The combined signature is always normalized to low-S form, i.e. s is in
+/// [1,n/2], as required by the consensus rules of Bitcoin (BIP-146) and by
+/// Ethereum (EIP-2). This is not configurable, since
+/// [`verify_threshold_signature`] rejects signatures that are not normalized.
+///
+/// The combined signature also carries its recovery id, which is derived
+/// from the presignature transcript and available through
+/// [`ThresholdEcdsaCombinedSigInternal::recovery_id`].
 #[allow(clippy::too_many_arguments)]
 pub fn combine_sig_shares(
     derivation_path: &DerivationPath,
@@ -876,6 +916,7 @@ pub fn verify_dealing_opening(
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum MEGaKeyVerificationError {
     InvalidPublicKey,
+    InvalidProofOfPossession,
 }
 
 /// Verifies the validity of a MEGa public key
@@ -891,3 +932,21 @@ pub fn verify_mega_public_key(
         Err(MEGaKeyVerificationError::InvalidPublicKey)
     }
 }
+
+/// Verifies a proof of possession of the private key of a MEGa public key
+///
+/// Checks that the serialized `raw_public_key` is a valid MEGa public key
+/// and that `raw_proof` is a valid proof of possession of the corresponding
+/// private key for the `associated_data`.
+pub fn verify_mega_key_proof_of_possession(
+    curve_type: EccCurveType,
+    raw_public_key: &[u8],
+    raw_proof: &[u8],
+    associated_data: &[u8],
+) -> Result<(), MEGaKeyVerificationError> {
+    let public_key = MEGaPublicKey::deserialize(curve_type, raw_public_key)
+        .map_err(|_| MEGaKeyVerificationError::InvalidPublicKey)?;
+    MEGaKeyProofOfPossession::deserialize(curve_type, raw_proof)
+        .and_then(|proof| proof.verify(&public_key, associated_data))
+        .map_err(|_| MEGaKeyVerificationError::InvalidProofOfPossession)
+}
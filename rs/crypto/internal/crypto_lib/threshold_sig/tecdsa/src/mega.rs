@@ -14,6 +14,8 @@ const MEGA_SINGLE_SEED_DOMAIN_SEPARATOR: &str = "ic-crypto-tecdsa-mega-encryptio
 const MEGA_PAIR_ENC_DOMAIN_SEPARATOR: &str = "ic-crypto-tecdsa-mega-encryption-pair-encrypt";
 const MEGA_PAIR_SEED_DOMAIN_SEPARATOR: &str = "ic-crypto-tecdsa-mega-encryption-pair-seed";
 
+const MEGA_KEY_POP_DOMAIN_SEPARATOR: &str = "ic-crypto-tecdsa-mega-key-proof-of-possession";
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct MEGaPublicKey {
     point: EccPoint,
@@ -90,6 +92,57 @@ impl Debug for MEGaPrivateKey {
     }
 }
 
+/// A proof of possession of the private key of a MEGa public key
+///
+/// This is a proof of knowledge of the discrete logarithm of the public key,
+/// bound to some associated data (such as the ID of the node owning the key)
+/// so that it cannot be replayed for other keys or owners.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MEGaKeyProofOfPossession {
+    proof: zk::ProofOfDLog,
+}
+
+impl MEGaKeyProofOfPossession {
+    pub fn create(
+        seed: Seed,
+        private_key: &MEGaPrivateKey,
+        associated_data: &[u8],
+    ) -> ThresholdEcdsaResult<Self> {
+        let proof = zk::ProofOfDLog::create(
+            seed.derive(MEGA_KEY_POP_DOMAIN_SEPARATOR),
+            private_key.secret_scalar(),
+            &Self::domain_separated(associated_data),
+        )?;
+        Ok(Self { proof })
+    }
+
+    pub fn verify(
+        &self,
+        public_key: &MEGaPublicKey,
+        associated_data: &[u8],
+    ) -> ThresholdEcdsaResult<()> {
+        self.proof.verify(
+            public_key.public_point(),
+            &Self::domain_separated(associated_data),
+        )
+    }
+
+    pub fn deserialize(curve: EccCurveType, value: &[u8]) -> ThresholdEcdsaResult<Self> {
+        let proof = zk::ProofOfDLog::deserialize(curve, value)?;
+        Ok(Self { proof })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        self.proof.serialize()
+    }
+
+    fn domain_separated(associated_data: &[u8]) -> Vec<u8> {
+        let mut bytes = MEGA_KEY_POP_DOMAIN_SEPARATOR.as_bytes().to_vec();
+        bytes.extend_from_slice(associated_data);
+        bytes
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MEGaCiphertextSingle {
     pub ephemeral_key: EccPoint, // "v" in the paper
@@ -290,7 +343,7 @@ fn mega_shared_hash_to_scalars(
     recipient_index: NodeIndex,
     associated_data: &[u8],
     public_key: &EccPoint,
-    ephemeral_key: &EccPoint,
+    ephemeral_key: &[u8],
     shared_secret: &EccPoint,
 ) -> ThresholdEcdsaResult<Vec<EccScalar>> {
     let curve_type = public_key.curve_type();
@@ -300,7 +353,7 @@ fn mega_shared_hash_to_scalars(
     ro.add_usize("recipient_index", recipient_index as usize)?;
     ro.add_bytestring("associated_data", associated_data)?;
     ro.add_point("public_key", public_key)?;
-    ro.add_point("ephemeral_key", ephemeral_key)?;
+    ro.add_serialized_point("ephemeral_key", curve_type, ephemeral_key)?;
     ro.add_point("shared_secret", shared_secret)?;
     ro.output_scalars(curve_type, count)
 }
@@ -310,7 +363,7 @@ fn mega_hash_to_scalar(
     recipient_index: NodeIndex,
     associated_data: &[u8],
     public_key: &EccPoint,
-    ephemeral_key: &EccPoint,
+    ephemeral_key: &[u8],
     shared_secret: &EccPoint,
 ) -> ThresholdEcdsaResult<EccScalar> {
     let hm = mega_shared_hash_to_scalars(
@@ -332,7 +385,7 @@ fn mega_hash_to_scalars(
     recipient_index: NodeIndex,
     associated_data: &[u8],
     public_key: &EccPoint,
-    ephemeral_key: &EccPoint,
+    ephemeral_key: &[u8],
     shared_secret: &EccPoint,
 ) -> ThresholdEcdsaResult<(EccScalar, EccScalar)> {
     let hm = mega_shared_hash_to_scalars(
@@ -364,18 +417,28 @@ impl MEGaCiphertextSingle {
         let beta = EccScalar::random(curve_type, &mut rng)?;
         let v = EccPoint::mul_by_g(&beta)?;
 
-        let mut ctexts = Vec::with_capacity(recipients.len());
+        // The ephemeral key is input to the hash for each recipient, so it
+        // is serialized once, and the shared secrets of all recipients are
+        // computed up front by multiplying their public keys by beta.
+        let v_bytes = v.serialize();
+        let public_points = recipients.iter().map(|pk| pk.point).collect::<Vec<_>>();
+        let shared_secrets = EccPoint::batch_scalar_mul(&public_points, &beta)?;
 
-        for (index, (pubkey, ptext)) in recipients.iter().zip(plaintexts).enumerate() {
-            let ubeta = pubkey.point.scalar_mul(&beta)?;
+        let mut ctexts = Vec::with_capacity(recipients.len());
 
+        for (index, ((pubkey, ptext), ubeta)) in recipients
+            .iter()
+            .zip(plaintexts)
+            .zip(&shared_secrets)
+            .enumerate()
+        {
             let hm = mega_hash_to_scalar(
                 dealer_index,
                 index as NodeIndex,
                 associated_data,
                 &pubkey.point,
-                &v,
-                &ubeta,
+                &v_bytes,
+                ubeta,
             )?;
 
             let ctext = hm.add(ptext)?;
@@ -408,7 +471,7 @@ impl MEGaCiphertextSingle {
             recipient_index,
             associated_data,
             &recipient_public_key.point,
-            &self.ephemeral_key,
+            &self.ephemeral_key.serialize(),
             shared_secret,
         )?;
 
@@ -450,18 +513,28 @@ impl MEGaCiphertextPair {
         let beta = EccScalar::random(curve_type, &mut rng)?;
         let v = EccPoint::mul_by_g(&beta)?;
 
-        let mut ctexts = Vec::with_capacity(recipients.len());
+        // The ephemeral key is input to the hash for each recipient, so it
+        // is serialized once, and the shared secrets of all recipients are
+        // computed up front by multiplying their public keys by beta.
+        let v_bytes = v.serialize();
+        let public_points = recipients.iter().map(|pk| pk.point).collect::<Vec<_>>();
+        let shared_secrets = EccPoint::batch_scalar_mul(&public_points, &beta)?;
 
-        for (index, (pubkey, ptext)) in recipients.iter().zip(plaintexts).enumerate() {
-            let ubeta = pubkey.point.scalar_mul(&beta)?;
+        let mut ctexts = Vec::with_capacity(recipients.len());
 
+        for (index, ((pubkey, ptext), ubeta)) in recipients
+            .iter()
+            .zip(plaintexts)
+            .zip(&shared_secrets)
+            .enumerate()
+        {
             let hm = mega_hash_to_scalars(
                 dealer_index,
                 index as NodeIndex,
                 associated_data,
                 &pubkey.point,
-                &v,
-                &ubeta,
+                &v_bytes,
+                ubeta,
             )?;
 
             let ctext0 = hm.0.add(&ptext.0)?;
@@ -495,7 +568,7 @@ impl MEGaCiphertextPair {
             recipient_index,
             associated_data,
             &recipient_public_key.point,
-            &self.ephemeral_key,
+            &self.ephemeral_key.serialize(),
             shared_secret,
         )?;
 
@@ -549,6 +622,11 @@ macro_rules! generate_serializable_keyset {
 
             impl [<MEGaPublicKey $curve Bytes>] {
                 pub const SIZE: usize = $pub_size;
+
+                /// Returns the serialized (compressed) curve point
+                pub fn as_bytes(&self) -> &[u8] {
+                    &self.0
+                }
             }
 
             impl TryFrom<&MEGaPublicKey> for [<MEGaPublicKey $curve Bytes>] {
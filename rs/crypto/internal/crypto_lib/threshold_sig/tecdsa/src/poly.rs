@@ -529,6 +529,32 @@ pub enum PolynomialCommitment {
     Pedersen(PedersenCommitment),
 }
 
+const CBOR_BYTE_STRING: u8 = 2;
+const CBOR_TEXT_STRING: u8 = 3;
+const CBOR_ARRAY: u8 = 4;
+
+/// Writes the header of a CBOR item of major type `major_type` and length
+/// `len` in its shortest form (RFC 8949, section 4.2.1)
+fn write_cbor_header(out: &mut Vec<u8>, major_type: u8, len: usize) {
+    let major_type = major_type << 5;
+    let len = len as u64;
+    if len < 24 {
+        out.push(major_type | len as u8);
+    } else if len <= u8::MAX as u64 {
+        out.push(major_type | 24);
+        out.push(len as u8);
+    } else if len <= u16::MAX as u64 {
+        out.push(major_type | 25);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else if len <= u32::MAX as u64 {
+        out.push(major_type | 26);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    } else {
+        out.push(major_type | 27);
+        out.extend_from_slice(&len.to_be_bytes());
+    }
+}
+
 impl From<SimpleCommitment> for PolynomialCommitment {
     fn from(c: SimpleCommitment) -> Self {
         Self::Simple(c)
@@ -552,6 +578,42 @@ impl PolynomialCommitment {
             .map_err(|e| ThresholdEcdsaError::SerializationError(format!("{}", e)))
     }
 
+    /// Returns the deterministic CBOR encoding of this commitment
+    ///
+    /// The encoding is the array `[type, curve, [point, ...]]`, where the type
+    /// (`"Simple"` or `"Pedersen"`) and the curve (`"K256"` or `"P256"`) are
+    /// text strings and the points are byte strings in compressed SEC1 form.
+    /// All lengths use their shortest encoding.
+    ///
+    /// Unlike [`serialize`](Self::serialize), the encoding does not depend on
+    /// serde or on the layout of the commitment types, so identifiers that
+    /// are derived from it stay the same across versions. It must never
+    /// change.
+    pub fn stable_representation(&self) -> Vec<u8> {
+        let (ctype, points) = match self {
+            Self::Simple(c) => ("Simple", &c.points),
+            Self::Pedersen(c) => ("Pedersen", &c.points),
+        };
+        let curve = match self.curve_type() {
+            EccCurveType::K256 => "K256",
+            EccCurveType::P256 => "P256",
+        };
+
+        let mut encoding = Vec::new();
+        write_cbor_header(&mut encoding, CBOR_ARRAY, 3);
+        write_cbor_header(&mut encoding, CBOR_TEXT_STRING, ctype.len());
+        encoding.extend_from_slice(ctype.as_bytes());
+        write_cbor_header(&mut encoding, CBOR_TEXT_STRING, curve.len());
+        encoding.extend_from_slice(curve.as_bytes());
+        write_cbor_header(&mut encoding, CBOR_ARRAY, points.len());
+        for point in points {
+            let point = point.serialize();
+            write_cbor_header(&mut encoding, CBOR_BYTE_STRING, point.len());
+            encoding.extend_from_slice(&point);
+        }
+        encoding
+    }
+
     pub(crate) fn ctype(&self) -> PolynomialCommitmentType {
         match self {
             Self::Simple(_) => PolynomialCommitmentType::Simple,
@@ -115,6 +115,27 @@ impl RandomOracle {
         )
     }
 
+    /// Add a point, given in its serialized form, to the input
+    ///
+    /// This is equivalent to `add_point` with the point of `curve_type` that
+    /// `pt` is the serialization of, and allows a point that is input to many
+    /// random oracle invocations to be serialized only once.
+    ///
+    /// The name must be a unique identifier for this random oracle invocation
+    pub(crate) fn add_serialized_point(
+        &mut self,
+        name: &'static str,
+        curve_type: EccCurveType,
+        pt: &[u8],
+    ) -> ThresholdEcdsaResult<()> {
+        self.add_input(
+            name,
+            pt,
+            RandomOracleInputType::Point,
+            Some(curve_type.tag()),
+        )
+    }
+
     /// Add several points to the input
     ///
     /// The name must be a unique identifier for this random oracle invocation
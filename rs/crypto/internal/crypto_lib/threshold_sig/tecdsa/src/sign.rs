@@ -11,6 +11,25 @@ pub(crate) fn ecdsa_conversion_function(pt: &EccPoint) -> ThresholdEcdsaResult<E
     EccScalar::from_bytes_wide(pt.curve_type(), &x_bytes)
 }
 
+/// Returns the recovery id of an ECDSA signature with the given `r` whose
+/// nonce commitment k*G is `nonce_point`, or its negation if `s` was negated
+/// during normalization.
+///
+/// Bit 0 of the recovery id is the parity of the y coordinate of k*G, and bit 1
+/// is set if its x coordinate is not smaller than the group order, i.e., if
+/// it was reduced to obtain `r`. This is the value that Ethereum encodes in
+/// the `v` of a signature.
+pub(crate) fn ecdsa_recovery_id(
+    nonce_point: &EccPoint,
+    r: &EccScalar,
+    negated: bool,
+) -> ThresholdEcdsaResult<u8> {
+    // The compressed encoding is prefixed with 0x03 if y is odd
+    let y_is_odd = nonce_point.serialize()[0] == 0x03;
+    let x_is_reduced = nonce_point.affine_x()?.as_bytes() != r.serialize();
+    Ok(((y_is_odd ^ negated) as u8) | ((x_is_reduced as u8) << 1))
+}
+
 fn derive_rho(
     curve_type: EccCurveType,
     hashed_message: &[u8],
@@ -188,6 +207,7 @@ impl ThresholdEcdsaSigShareInternal {
 pub struct ThresholdEcdsaCombinedSigInternal {
     r: EccScalar,
     s: EccScalar,
+    recovery_id: u8,
 }
 
 impl ThresholdEcdsaCombinedSigInternal {
@@ -201,6 +221,22 @@ impl ThresholdEcdsaCombinedSigInternal {
         sig.extend_from_slice(&s_bytes);
         sig
     }
+
+    /// Return the recovery id of the signature
+    ///
+    /// This allows recovering the public key from the signature and the
+    /// message without trying all candidate keys, as required for instance
+    /// to compute the `v` value of an Ethereum signature.
+    pub fn recovery_id(&self) -> u8 {
+        self.recovery_id
+    }
+
+    /// Serialize the signature followed by its recovery id, i.e. r || s || v
+    pub fn serialize_with_recovery_id(&self) -> Vec<u8> {
+        let mut sig = self.serialize();
+        sig.push(self.recovery_id);
+        sig
+    }
 }
 
 impl ThresholdEcdsaCombinedSigInternal {
@@ -220,7 +256,7 @@ impl ThresholdEcdsaCombinedSigInternal {
             return Err(ThresholdEcdsaError::InsufficientDealings);
         }
 
-        let (rho, _key_tweak, _randomizer, _presig) = derive_rho(
+        let (rho, _key_tweak, _randomizer, pre_sig) = derive_rho(
             curve_type,
             hashed_message,
             &randomness,
@@ -260,15 +296,18 @@ impl ThresholdEcdsaCombinedSigInternal {
         let sigma = numerator.mul(&denominator.invert()?)?;
 
         // Always use the smaller value of s
-        let norm_sigma = if sigma.is_high() {
-            sigma.negate()
-        } else {
-            sigma
-        };
+        let negated = sigma.is_high();
+        let norm_sigma = if negated { sigma.negate() } else { sigma };
+
+        // Negating s corresponds to negating the nonce commitment, so the
+        // recovery id is computed from the (rerandomized) presignature,
+        // adjusted for the normalization of s
+        let recovery_id = ecdsa_recovery_id(&pre_sig, &rho, negated)?;
 
         Ok(Self {
             r: rho,
             s: norm_sigma,
+            recovery_id,
         })
     }
 
@@ -359,7 +398,13 @@ impl ThresholdEcdsaCombinedSigInternal {
         we only check the x coordinate.
         */
 
-        Ok(rp.affine_x()? == pre_sig.affine_x()?)
+        if rp.affine_x()? != pre_sig.affine_x()? {
+            return Ok(false);
+        }
+
+        // rp is the nonce commitment matching the normalized s, so its y
+        // parity determines the recovery id without further adjustment
+        Ok(self.recovery_id == ecdsa_recovery_id(&rp, &self.r, false)?)
     }
 }
 
@@ -1,5 +1,6 @@
 use crate::*;
 
+pub const PROOF_OF_DLOG_DST: &str = "ic-crypto-tecdsa-zk-proof-of-dlog";
 pub const PROOF_OF_DLOG_EQUIV_DST: &str = "ic-crypto-tecdsa-zk-proof-of-dlog-eq";
 pub const PROOF_OF_EQUAL_OPENINGS_DST: &str = "ic-crypto-tecdsa-zk-proof-of-equal-openings";
 pub const PROOF_OF_PRODUCT_DST: &str = "ic-crypto-tecdsa-zk-proof-of-product";
@@ -433,3 +434,128 @@ impl ProofOfDLogEquivalence {
         Ok(())
     }
 }
+
+/// A ZK proof of knowledge of a discrete logarithm (a Schnorr proof)
+///
+/// This is, a zero-knowledge proof for the following relation R:
+///
+/// Instance = `A` ∈  G,
+/// Witness = `x` ∈  Zₚ,
+/// such that:
+/// `A = g*x`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofOfDLog {
+    challenge: EccScalar,
+    response: EccScalar,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ProofOfDLogInstance {
+    curve_type: EccCurveType,
+    g: EccPoint,
+    g_x: EccPoint,
+}
+
+impl ProofOfDLogInstance {
+    fn from_witness(x: &EccScalar) -> ThresholdEcdsaResult<Self> {
+        let curve_type = x.curve_type();
+        let g = EccPoint::generator_g(curve_type)?;
+        let g_x = g.scalar_mul(x)?;
+        Ok(Self { curve_type, g, g_x })
+    }
+
+    fn from_commitment(g_x: &EccPoint) -> ThresholdEcdsaResult<Self> {
+        let curve_type = g_x.curve_type();
+        let g = EccPoint::generator_g(curve_type)?;
+        Ok(Self {
+            curve_type,
+            g,
+            g_x: *g_x,
+        })
+    }
+
+    fn recover_commitment(&self, proof: &ProofOfDLog) -> ThresholdEcdsaResult<EccPoint> {
+        let g_z = self.g.scalar_mul(&proof.response)?;
+        g_z.sub_points(&self.g_x.scalar_mul(&proof.challenge)?)
+    }
+
+    fn hash_to_challenge(
+        &self,
+        c: &EccPoint,
+        associated_data: &[u8],
+    ) -> ThresholdEcdsaResult<EccScalar> {
+        let mut ro = ro::RandomOracle::new(PROOF_OF_DLOG_DST);
+        ro.add_bytestring("associated_data", associated_data)?;
+        ro.add_point("instance_g", &self.g)?;
+        ro.add_point("instance_g_x", &self.g_x)?;
+        ro.add_point("commitment", c)?;
+        ro.output_scalar(self.curve_type)
+    }
+}
+
+impl ProofOfDLog {
+    /// Create a proof of knowledge of the discrete logarithm `x` of `g*x`
+    pub fn create(seed: Seed, x: &EccScalar, associated_data: &[u8]) -> ThresholdEcdsaResult<Self> {
+        let instance = ProofOfDLogInstance::from_witness(x)?;
+
+        // Compute blinding commitment:
+        let mut rng = seed.into_rng();
+        let r = EccScalar::random(instance.curve_type, &mut rng)?;
+        let r_com = instance.g.scalar_mul(&r)?;
+
+        // Compute the challenge:
+        let challenge = instance.hash_to_challenge(&r_com, associated_data)?;
+
+        // Computing the opening:
+        let response = x.mul(&challenge)?.add(&r)?;
+
+        Ok(Self {
+            challenge,
+            response,
+        })
+    }
+
+    /// Verify a proof of knowledge of the discrete logarithm of `g_x`
+    pub fn verify(&self, g_x: &EccPoint, associated_data: &[u8]) -> ThresholdEcdsaResult<()> {
+        if self.challenge.curve_type() != g_x.curve_type()
+            || self.response.curve_type() != g_x.curve_type()
+        {
+            return Err(ThresholdEcdsaError::CurveMismatch);
+        }
+
+        let instance = ProofOfDLogInstance::from_commitment(g_x)?;
+
+        let r_com = instance.recover_commitment(self)?;
+
+        if self.challenge != instance.hash_to_challenge(&r_com, associated_data)? {
+            return Err(ThresholdEcdsaError::InvalidProof);
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the proof as the concatenation of the challenge and the
+    /// response
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = self.challenge.serialize();
+        bytes.extend_from_slice(&self.response.serialize());
+        bytes
+    }
+
+    /// Deserialize a proof that was serialized with [`ProofOfDLog::serialize`]
+    pub fn deserialize(curve_type: EccCurveType, bytes: &[u8]) -> ThresholdEcdsaResult<Self> {
+        let scalar_bytes = curve_type.scalar_bytes();
+        if bytes.len() != 2 * scalar_bytes {
+            return Err(ThresholdEcdsaError::SerializationError(format!(
+                "Invalid length of a proof of discrete logarithm: {} bytes",
+                bytes.len()
+            )));
+        }
+        let challenge = EccScalar::deserialize(curve_type, &bytes[..scalar_bytes])?;
+        let response = EccScalar::deserialize(curve_type, &bytes[scalar_bytes..])?;
+        Ok(Self {
+            challenge,
+            response,
+        })
+    }
+}
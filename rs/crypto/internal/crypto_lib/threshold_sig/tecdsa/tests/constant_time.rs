@@ -0,0 +1,214 @@
+//! Timing analysis of MEGa decryption in the style of dudect
+//! (<https://eprint.iacr.org/2016/1123.pdf>).
+//!
+//! The measurements are noisy and slow, so these tests only run when the
+//! `constant_time_audit` feature is enabled, preferably in release mode on an
+//! otherwise idle machine:
+//!
+//! ```text
+//! cargo test --release --features constant_time_audit --test constant_time -- --nocapture
+//! ```
+//!
+//! Each test times an operation on two classes of inputs: a single fixed
+//! dealing, and a pool of freshly generated dealings that differ in their
+//! secret shares. If the running time depends on the decrypted secret bytes,
+//! Welch's t-statistic between the two timing distributions grows with the
+//! number of measurements.
+#![cfg(feature = "constant_time_audit")]
+
+use ic_crypto_internal_threshold_sig_ecdsa::*;
+use ic_types::*;
+use rand::Rng;
+use std::time::Instant;
+
+mod test_utils;
+
+use crate::test_utils::*;
+
+/// Number of timed executions per test
+const MEASUREMENTS: usize = 20_000;
+
+/// Number of distinct dealings in the "random" class
+const RANDOM_POOL_SIZE: usize = 64;
+
+/// dudect considers a |t| above 10 to be a definite timing leak
+const T_THRESHOLD: f64 = 10.0;
+
+/// Measurements above this percentile are discarded, which removes outliers
+/// caused e.g. by preemption.
+const CROP_PERCENTILE: f64 = 0.9;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum InputClass {
+    Fixed,
+    Random,
+}
+
+#[derive(Default)]
+struct WelchAccumulator {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl WelchAccumulator {
+    fn push(&mut self, x: f64) {
+        // Welford's online algorithm
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        if self.n < 2.0 {
+            return 0.0;
+        }
+        self.m2 / (self.n - 1.0)
+    }
+}
+
+/// Returns Welch's t-statistic of the two timing classes
+fn welch_t_statistic(timings: &[(InputClass, u128)]) -> f64 {
+    let mut sorted: Vec<u128> = timings.iter().map(|(_, t)| *t).collect();
+    sorted.sort_unstable();
+    let cutoff = sorted[((sorted.len() as f64) * CROP_PERCENTILE) as usize];
+
+    let mut fixed = WelchAccumulator::default();
+    let mut random = WelchAccumulator::default();
+    for (class, t) in timings.iter().filter(|(_, t)| *t <= cutoff) {
+        match class {
+            InputClass::Fixed => fixed.push(*t as f64),
+            InputClass::Random => random.push(*t as f64),
+        }
+    }
+
+    let denominator = (fixed.variance() / fixed.n + random.variance() / random.n).sqrt();
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    (fixed.mean - random.mean) / denominator
+}
+
+/// Times `op` on inputs of both classes, interleaved in random order, and
+/// returns the resulting t-statistic.
+fn measure<I, F>(fixed_input: &I, random_inputs: &[I], op: F) -> f64
+where
+    F: Fn(&I),
+{
+    let mut rng = rand::thread_rng();
+    let mut timings = Vec::with_capacity(MEASUREMENTS);
+
+    for _ in 0..MEASUREMENTS {
+        let (class, input) = if rng.gen::<bool>() {
+            (InputClass::Fixed, fixed_input)
+        } else {
+            let idx = rng.gen_range(0, random_inputs.len());
+            (InputClass::Random, &random_inputs[idx])
+        };
+
+        let start = Instant::now();
+        op(input);
+        timings.push((class, start.elapsed().as_nanos()));
+    }
+
+    welch_t_statistic(&timings)
+}
+
+fn assert_no_timing_leak(operation: &str, t: f64) {
+    println!("{}: |t| = {:.3}", operation, t.abs());
+    assert!(
+        t.abs() < T_THRESHOLD,
+        "{} shows a data-dependent timing difference (|t| = {:.3} >= {})",
+        operation,
+        t.abs(),
+        T_THRESHOLD
+    );
+}
+
+fn random_dealings(
+    setup: &ProtocolSetup,
+    count: usize,
+) -> ThresholdEcdsaResult<Vec<ProtocolRound>> {
+    let number_of_dealers = setup.receiver_info().len();
+    (0..count)
+        .map(|_| ProtocolRound::random(setup, number_of_dealers, 0))
+        .collect()
+}
+
+#[test]
+fn compute_secret_shares_should_not_leak_secret_dependent_timing() -> ThresholdEcdsaResult<()> {
+    let setup = ProtocolSetup::new(EccCurveType::K256, 4, 2, random_seed())?;
+    let (sk, pk, receiver_index) = setup.receiver_info()[0].clone();
+    let associated_data = setup.associated_data().to_vec();
+
+    let fixed = ProtocolRound::random(&setup, 4, 0)?;
+    let pool = random_dealings(&setup, RANDOM_POOL_SIZE)?;
+
+    let t = measure(&fixed, &pool, |round| {
+        let result = compute_secret_shares(
+            &round.dealings,
+            &round.transcript,
+            &associated_data,
+            receiver_index,
+            &sk,
+            &pk,
+        );
+        assert!(result.is_ok());
+    });
+
+    assert_no_timing_leak("compute_secret_shares", t);
+    Ok(())
+}
+
+#[test]
+fn open_dealing_should_not_leak_secret_dependent_timing() -> ThresholdEcdsaResult<()> {
+    let setup = ProtocolSetup::new(EccCurveType::K256, 4, 2, random_seed())?;
+    let (sk, pk, opener_index) = setup.receiver_info()[1].clone();
+    let associated_data = setup.associated_data().to_vec();
+    let dealer_index: NodeIndex = 0;
+
+    let dealing_of = |round: &ProtocolRound| -> IDkgDealingInternal {
+        round
+            .dealings
+            .get(&dealer_index)
+            .expect("missing dealing")
+            .clone()
+    };
+
+    let fixed = dealing_of(&ProtocolRound::random(&setup, 4, 0)?);
+    let pool = random_dealings(&setup, RANDOM_POOL_SIZE)?
+        .iter()
+        .map(dealing_of)
+        .collect::<Vec<_>>();
+
+    let t = measure(&fixed, &pool, |dealing| {
+        let result = open_dealing(
+            dealing,
+            &associated_data,
+            dealer_index,
+            opener_index,
+            &sk,
+            &pk,
+        );
+        assert!(result.is_ok());
+    });
+
+    assert_no_timing_leak("open_dealing", t);
+    Ok(())
+}
+
+#[test]
+fn welch_t_statistic_should_detect_obvious_timing_difference() {
+    let timings = (0..1000)
+        .map(|i| {
+            if i % 2 == 0 {
+                (InputClass::Fixed, 1000 + (i % 7))
+            } else {
+                (InputClass::Random, 2000 + (i % 7))
+            }
+        })
+        .collect::<Vec<(InputClass, u128)>>();
+
+    assert!(welch_t_statistic(&timings).abs() > T_THRESHOLD);
+}
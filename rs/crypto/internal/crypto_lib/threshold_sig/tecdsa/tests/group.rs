@@ -132,3 +132,39 @@ fn test_point_mul_by_node_index() -> ThresholdEcdsaResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_point_batch_scalar_mul() -> ThresholdEcdsaResult<()> {
+    let mut rng = Seed::from_bytes(b"ic-crypto-test-batch-scalar-mul").into_rng();
+
+    for curve in EccCurveType::all() {
+        let mut points = vec![EccPoint::identity(curve), EccPoint::generator_g(curve)?];
+        for _ in 0..10 {
+            points.push(EccPoint::mul_by_g(&EccScalar::random(curve, &mut rng)?)?);
+        }
+
+        let scalars = vec![
+            EccScalar::zero(curve),
+            EccScalar::one(curve),
+            EccScalar::one(curve).negate(),
+            EccScalar::random(curve, &mut rng)?,
+        ];
+        for scalar in &scalars {
+            let products = EccPoint::batch_scalar_mul(&points, scalar)?;
+            assert_eq!(products.len(), points.len());
+            for (point, product) in points.iter().zip(&products) {
+                assert_eq!(*product, point.scalar_mul(scalar)?);
+            }
+        }
+
+        assert!(EccPoint::batch_scalar_mul(&[], &scalars[3])?.is_empty());
+    }
+
+    let k256_points = [EccPoint::generator_g(EccCurveType::K256)?];
+    assert_eq!(
+        EccPoint::batch_scalar_mul(&k256_points, &EccScalar::one(EccCurveType::P256)),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use ic_crypto_internal_threshold_sig_ecdsa::*;
+use ic_types::Randomness;
 use std::convert::TryFrom;
 
 #[test]
@@ -151,3 +152,34 @@ fn mega_private_key_bytes_should_redact_logs() -> Result<(), ThresholdEcdsaError
 
     Ok(())
 }
+
+#[test]
+fn mega_key_proof_of_possession_should_verify() -> Result<(), ThresholdEcdsaError> {
+    let curve = EccCurveType::K256;
+
+    let mut rng = Seed::from_bytes(&[42; 32]).into_rng();
+
+    let sk = MEGaPrivateKey::generate(curve, &mut rng)?;
+    let pk = sk.public_key()?;
+    let other_pk = MEGaPrivateKey::generate(curve, &mut rng)?.public_key()?;
+
+    let pop = create_mega_key_proof_of_possession(&sk, b"node", Randomness::from([7; 32]))?;
+
+    assert!(pop.verify(&pk, b"node").is_ok());
+    assert!(pop.verify(&pk, b"other node").is_err());
+    assert!(pop.verify(&other_pk, b"node").is_err());
+
+    let pop_bytes = pop.serialize();
+    assert!(
+        verify_mega_key_proof_of_possession(curve, &pk.serialize(), &pop_bytes, b"node").is_ok()
+    );
+    assert!(matches!(
+        verify_mega_key_proof_of_possession(curve, &other_pk.serialize(), &pop_bytes, b"node"),
+        Err(MEGaKeyVerificationError::InvalidProofOfPossession)
+    ));
+    assert!(matches!(
+        verify_mega_key_proof_of_possession(curve, &[0; 33], &pop_bytes, b"node"),
+        Err(MEGaKeyVerificationError::InvalidPublicKey)
+    ));
+    Ok(())
+}
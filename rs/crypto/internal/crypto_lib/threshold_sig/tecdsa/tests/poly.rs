@@ -287,6 +287,36 @@ fn poly_pedersen_commitments() -> ThresholdEcdsaResult<()> {
     Ok(())
 }
 
+#[test]
+fn poly_commitment_stable_representation_remains_unchanged() -> ThresholdEcdsaResult<()> {
+    let points = vec![
+        EccPoint::mul_by_g(&EccScalar::from_u64(EccCurveType::K256, 1))?,
+        EccPoint::mul_by_g(&EccScalar::from_u64(EccCurveType::K256, 2))?,
+    ];
+
+    let simple = PolynomialCommitment::from(SimpleCommitment {
+        points: points.clone(),
+    });
+    assert_eq!(
+        hex::encode(simple.stable_representation()),
+        "836653696d706c65644b323536825821\
+         0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+         5821\
+         02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+    );
+
+    let pedersen = PolynomialCommitment::from(PedersenCommitment { points });
+    assert_eq!(
+        hex::encode(pedersen.stable_representation()),
+        "8368506564657273656e644b323536825821\
+         0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+         5821\
+         02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+    );
+
+    Ok(())
+}
+
 #[test]
 #[allow(clippy::identity_op)]
 fn poly_lagrange_coefficients_at_zero_are_correct() {
@@ -247,3 +247,70 @@ fn should_basic_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
 
     Ok(())
 }
+
+#[test]
+fn should_recover_public_key_with_recovery_id_of_signature() -> Result<(), ThresholdEcdsaError> {
+    let nodes = 4;
+    let threshold = 1;
+    let setup = SignatureProtocolSetup::new(EccCurveType::K256, nodes, threshold, random_seed())?;
+
+    let mut rng = rand::thread_rng();
+    let derivation_path = DerivationPath::new_bip32(&[1, 2, 3]);
+    let public_key = setup.public_key(&derivation_path)?;
+
+    // Both values of the y parity bit are expected to occur
+    for _trial in 0..10 {
+        let proto = SignatureProtocolExecution::new(
+            setup.clone(),
+            rng.gen::<[u8; 32]>().to_vec(),
+            Randomness::from(rng.gen::<[u8; 32]>()),
+            derivation_path.clone(),
+        );
+
+        let shares = proto.generate_shares()?;
+        let sig = proto.generate_signature(&shares).unwrap();
+        assert!(proto.verify_signature(&sig).is_ok());
+
+        assert_eq!(
+            proto.recover_public_key(&sig)?.serialize(),
+            public_key.public_key
+        );
+
+        let sig_with_recovery_id = sig.serialize_with_recovery_id();
+        assert_eq!(sig_with_recovery_id[..64], sig.serialize()[..]);
+        assert_eq!(sig_with_recovery_id[64], sig.recovery_id());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn should_combine_verified_shares_into_low_s_signatures() -> Result<(), ThresholdEcdsaError> {
+    let nodes = 4;
+    let threshold = 1;
+    let curve = EccCurveType::K256;
+    let setup = SignatureProtocolSetup::new(curve, nodes, threshold, random_seed())?;
+
+    let mut rng = rand::thread_rng();
+    let derivation_path = DerivationPath::new_bip32(&[4, 5, 6]);
+
+    // Without normalization, about half of the signatures would have a high s
+    for _trial in 0..20 {
+        let proto = SignatureProtocolExecution::new(
+            setup.clone(),
+            rng.gen::<[u8; 32]>().to_vec(),
+            Randomness::from(rng.gen::<[u8; 32]>()),
+            derivation_path.clone(),
+        );
+
+        // generate_shares verifies each of the shares
+        let shares = proto.generate_shares()?;
+        let sig = proto.generate_signature(&shares).unwrap();
+
+        let s = EccScalar::deserialize(curve, &sig.serialize()[curve.scalar_bytes()..])?;
+        assert!(!s.is_high());
+        assert!(proto.verify_signature(&sig).is_ok());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Conformance of threshold ECDSA with a reference implementation.
+//!
+//! The threshold protocol is run on fixed inputs, the secret key that the
+//! key transcript shares is reconstructed from the openings of the
+//! receivers, and the derived keys and signatures are compared with the ones
+//! computed from the reconstructed key by RustCrypto's `bip32` and `k256`
+//! crates. This catches math errors that a self-consistent implementation
+//! would not detect, e.g. in the key derivation or in the normalization of
+//! signatures.
+//!
+//! Reconstructing the key makes these tests slower than the other protocol
+//! tests, so they only run when the `reference_conformance` feature is
+//! enabled:
+//!
+//! ```text
+//! cargo test --features reference_conformance --test reference_conformance
+//! ```
+#![cfg(feature = "reference_conformance")]
+
+use ic_crypto_internal_threshold_sig_ecdsa::*;
+use ic_types::Randomness;
+use k256::ecdsa::signature::{Signature as _, Signer, Verifier};
+use rand::Rng;
+use std::convert::TryFrom;
+
+mod test_utils;
+
+use crate::test_utils::*;
+
+/// The inputs of the threshold protocol: the seed of the transcripts and the
+/// beacon, the BIP32 derivation path and the signed message.
+const FIXTURES: &[(&str, &[u32], &[u8])] = &[
+    ("ic-crypto-tecdsa-conformance-1", &[], b""),
+    ("ic-crypto-tecdsa-conformance-2", &[0], b"message"),
+    (
+        "ic-crypto-tecdsa-conformance-3",
+        &[1, 2, 3],
+        b"another message",
+    ),
+    (
+        "ic-crypto-tecdsa-conformance-4",
+        &[0x7FFF_FFFF, 42, 0],
+        &[0xFF; 1024],
+    ),
+];
+
+const NODES: usize = 7;
+const THRESHOLD: usize = 2;
+
+/// Reconstructs the secret shared by the transcript of `round` from the
+/// openings of all receivers.
+fn reconstruct_secret(round: &ProtocolRound) -> EccScalar {
+    let curve = EccCurveType::K256;
+    let mut x = Vec::with_capacity(round.openings.len());
+    let mut y = Vec::with_capacity(round.openings.len());
+    for (index, opening) in round.openings.iter().enumerate() {
+        match opening {
+            CommitmentOpening::Simple(value) => {
+                x.push(EccScalar::from_node_index(curve, index as NodeIndex));
+                y.push(*value);
+            }
+            CommitmentOpening::Pedersen(_, _) => panic!("The key must be unmasked"),
+        }
+    }
+    LagrangeCoefficients::at_zero(&x)
+        .and_then(|coefficients| coefficients.interpolate_scalar(&y))
+        .expect("Failed to reconstruct the secret")
+}
+
+/// Derives the signing key at `path` from the master `secret` with BIP32,
+/// starting from the all-zero chain code that threshold ECDSA uses.
+fn reference_signing_key(secret: &EccScalar, path: &[u32]) -> bip32::XPrv {
+    let mut key_bytes = [0u8; 33];
+    key_bytes[1..].copy_from_slice(&secret.serialize());
+    let master = bip32::XPrv::try_from(bip32::ExtendedKey {
+        prefix: bip32::Prefix::XPRV,
+        attrs: bip32::ExtendedKeyAttrs {
+            depth: 0,
+            parent_fingerprint: [0u8; 4],
+            child_number: bip32::ChildNumber(0),
+            chain_code: [0u8; 32],
+        },
+        key_bytes,
+    })
+    .expect("Failed to accept the reconstructed key");
+
+    path.iter().fold(master, |key, index| {
+        key.derive_child(bip32::ChildNumber(*index))
+            .expect("Failed to derive child")
+    })
+}
+
+#[test]
+fn should_match_reference_implementation() -> Result<(), ThresholdEcdsaError> {
+    for (seed, path, message) in FIXTURES {
+        let seed = Seed::from_bytes(seed.as_bytes());
+        let setup = SignatureProtocolSetup::new(
+            EccCurveType::K256,
+            NODES,
+            THRESHOLD,
+            seed.derive("setup"),
+        )?;
+        let random_beacon = Randomness::from(seed.derive("beacon").into_rng().gen::<[u8; 32]>());
+        let derivation_path = DerivationPath::new_bip32(path);
+
+        let secret = reconstruct_secret(&setup.key);
+        assert_eq!(
+            EccPoint::mul_by_g(&secret)?.serialize(),
+            setup.key.constant_term().serialize(),
+            "The reconstructed key does not match the master public key"
+        );
+
+        let reference_key = reference_signing_key(&secret, path);
+        let reference_public_key = reference_key.public_key();
+
+        // The derived public keys and chain codes agree
+        let public_key = setup.public_key(&derivation_path)?;
+        assert_eq!(
+            public_key.public_key,
+            reference_public_key.to_bytes().to_vec(),
+            "Derived public keys differ for path {:?}",
+            path
+        );
+        assert_eq!(
+            public_key.chain_key,
+            reference_key.attrs().chain_code.to_vec(),
+            "Derived chain codes differ for path {:?}",
+            path
+        );
+
+        // The threshold signature verifies under the reference key
+        let proto = SignatureProtocolExecution::new(
+            setup,
+            message.to_vec(),
+            random_beacon,
+            derivation_path,
+        );
+        let shares = proto.generate_shares()?;
+        let sig = proto.generate_signature(&shares).unwrap();
+        assert!(proto.verify_signature(&sig).is_ok());
+
+        let threshold_sig = k256::ecdsa::Signature::from_bytes(&sig.serialize())
+            .expect("Failed to parse the threshold signature");
+        let reference_verifying_key = reference_key.private_key().verifying_key();
+        assert!(reference_verifying_key
+            .verify(message, &threshold_sig)
+            .is_ok());
+        assert!(
+            threshold_sig.normalize_s().is_none(),
+            "The threshold signature is not normalized"
+        );
+
+        // The recovery id of the threshold signature recovers the reference key
+        let recoverable_sig = k256::ecdsa::recoverable::Signature::new(
+            &threshold_sig,
+            k256::ecdsa::recoverable::Id::new(sig.recovery_id()).expect("Invalid recovery id"),
+        )
+        .expect("Failed to build a recoverable signature");
+        assert_eq!(
+            recoverable_sig
+                .recover_verify_key(message)
+                .expect("Failed to recover the public key"),
+            reference_verifying_key
+        );
+
+        // A deterministic (RFC 6979) signature of the reference key verifies
+        // under the derived public key
+        let reference_sig: k256::ecdsa::Signature = reference_key.private_key().sign(message);
+        let verifying_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&public_key.public_key)
+            .expect("Failed to parse the derived public key");
+        assert!(verifying_key.verify(message, &reference_sig).is_ok());
+    }
+
+    Ok(())
+}
@@ -90,6 +90,10 @@ impl ProtocolSetup {
         self.threshold = NumberOfNodes::from(threshold as u32);
     }
 
+    pub fn associated_data(&self) -> &[u8] {
+        &self.ad
+    }
+
     pub fn receiver_info(&self) -> Vec<(MEGaPrivateKey, MEGaPublicKey, NodeIndex)> {
         let mut info = Vec::with_capacity(self.receivers);
         for i in 0..self.receivers {
@@ -748,6 +752,37 @@ impl SignatureProtocolExecution {
 
         Ok(())
     }
+
+    /// Recovers the public key from the signature, the message and the
+    /// recovery id of the signature
+    pub fn recover_public_key(
+        &self,
+        sig: &ThresholdEcdsaCombinedSigInternal,
+    ) -> ThresholdEcdsaResult<EccPoint> {
+        let curve = EccCurveType::K256;
+        let sig_bytes = sig.serialize();
+        let (r_bytes, s_bytes) = sig_bytes.split_at(curve.scalar_bytes());
+        let r = EccScalar::deserialize(curve, r_bytes)?;
+        let s = EccScalar::deserialize(curve, s_bytes)?;
+
+        // An x coordinate of k*G larger than the group order occurs with
+        // negligible probability for secp256k1
+        assert_eq!(sig.recovery_id() & 2, 0);
+
+        let mut nonce_point = vec![0x02 | (sig.recovery_id() & 1)];
+        nonce_point.extend_from_slice(r_bytes);
+        let nonce_point = EccPoint::deserialize(curve, &nonce_point)?;
+
+        // public_key = r^-1 * (s*R - e*G)
+        let e = EccScalar::from_bytes_wide(curve, &self.hashed_message)?;
+        let r_inv = r.invert()?;
+        EccPoint::mul_points(
+            &nonce_point,
+            &s.mul(&r_inv)?,
+            &EccPoint::generator_g(curve)?,
+            &e.mul(&r_inv)?.negate(),
+        )
+    }
 }
 
 pub fn random_seed() -> Seed {
@@ -112,3 +112,29 @@ fn should_zk_dlog_eq_proof_work() -> ThresholdEcdsaResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn should_zk_dlog_proof_work() -> ThresholdEcdsaResult<()> {
+    let curve = EccCurveType::K256;
+
+    let mut rng = rand::thread_rng();
+    let ad = rng.gen::<[u8; 32]>();
+
+    let seed = Seed::from_bytes(&rng.gen::<[u8; 32]>());
+
+    let x = EccScalar::random(curve, &mut rng)?;
+    let g_x = EccPoint::mul_by_g(&x)?;
+    let g_y = EccPoint::mul_by_g(&EccScalar::random(curve, &mut rng)?)?;
+
+    let proof = zk::ProofOfDLog::create(seed, &x, &ad)?;
+
+    assert!(proof.verify(&g_x, &ad).is_ok());
+    assert!(proof.verify(&g_y, &ad).is_err());
+    assert!(proof.verify(&g_x, &[0; 32]).is_err());
+
+    let deserialized = zk::ProofOfDLog::deserialize(curve, &proof.serialize())?;
+    assert_eq!(deserialized, proof);
+    assert!(zk::ProofOfDLog::deserialize(curve, &proof.serialize()[1..]).is_err());
+
+    Ok(())
+}
@@ -69,6 +69,32 @@ pub fn generate_tls_key_pair_der<R: Rng + CryptoRng>(
     der_encode_cert_and_secret_key(&key_pair, x509_cert)
 }
 
+/// Generate a key pair whose certificate is valid from `not_before` rather
+/// than from the current time, and return the certificate and private key in
+/// DER format.
+///
+/// The serial number is subject to the same constraints as for
+/// `generate_tls_key_pair_der`.
+pub fn generate_tls_key_pair_der_with_not_before<R: Rng + CryptoRng>(
+    csprng: &mut R,
+    common_name: &str,
+    serial: [u8; 19],
+    not_before: &Asn1Time,
+    not_after: &Asn1Time,
+) -> (TlsEd25519CertificateDerBytes, TlsEd25519SecretKeyDerBytes) {
+    let key_pair = ed25519_key_pair(csprng);
+    let x509_cert = x509_v3_certificate(
+        common_name,
+        serial,
+        &key_pair,
+        not_before,
+        not_after,
+        // Digest must be null for Ed25519 (see https://www.openssl.org/docs/man1.1.1/man7/Ed25519.html)
+        MessageDigest::null(),
+    );
+    der_encode_cert_and_secret_key(&key_pair, x509_cert)
+}
+
 /// Generate a key pair and return the certificate and private key.
 ///
 /// Note that the certificate serial number must be at most 20 octets according
@@ -82,10 +108,12 @@ pub fn generate_tls_key_pair<R: Rng + CryptoRng>(
     not_after: &Asn1Time,
 ) -> (X509, PKey<Private>) {
     let key_pair = ed25519_key_pair(csprng);
+    let now = Asn1Time::days_from_now(0).expect("unable to create Asn1Time");
     let x509_certificate = x509_v3_certificate(
         common_name,
         serial,
         &key_pair,
+        &now,
         not_after,
         // Digest must be null for Ed25519 (see https://www.openssl.org/docs/man1.1.1/man7/Ed25519.html)
         MessageDigest::null(),
@@ -104,11 +132,14 @@ fn x509_v3_certificate(
     common_name: &str,
     serial: [u8; 19],
     key_pair: &PKey<Private>,
+    not_before: &Asn1Time,
     not_after: &Asn1Time,
     message_digest: MessageDigest,
 ) -> X509 {
-    let now = Asn1Time::days_from_now(0).expect("unable to create Asn1Time");
-    assert!(not_after > &now, "'not after' date must not be in the past");
+    assert!(
+        not_after > not_before,
+        "'not after' date must not be in the past"
+    );
 
     let mut builder = X509::builder().expect("unable to create builder");
     // note that this sets the version to 3 (zero indexed):
@@ -127,7 +158,7 @@ fn x509_v3_certificate(
         .set_pubkey(key_pair)
         .expect("unable to set public key");
     builder
-        .set_not_before(&now)
+        .set_not_before(not_before)
         .expect("unable to set 'not before'");
     builder
         .set_not_after(not_after)
@@ -138,6 +138,34 @@ fn should_panic_if_not_after_date_is_in_the_past() {
     let _panic = generate_tls_key_pair(&mut csprng(), "common name", SERIAL, &date_in_the_past);
 }
 
+#[test]
+fn should_set_given_not_before() {
+    let not_before = Asn1Time::from_str_x509("20211004235959Z").unwrap();
+    let (cert_der, _sk) = generate_tls_key_pair_der_with_not_before(
+        &mut csprng(),
+        "common name",
+        SERIAL,
+        &not_before,
+        &not_after(),
+    );
+
+    let cert = X509::from_der(&cert_der.bytes).unwrap();
+    assert!(cert.not_before() == not_before);
+}
+
+#[test]
+#[should_panic(expected = "'not after' date must not be in the past")]
+fn should_panic_if_not_after_date_is_before_given_not_before() {
+    let not_before = Asn1Time::days_from_now(2 * VALIDITY_DAYS).unwrap();
+    let _panic = generate_tls_key_pair_der_with_not_before(
+        &mut csprng(),
+        "common name",
+        SERIAL,
+        &not_before,
+        &not_after(),
+    );
+}
+
 #[test]
 fn should_set_not_after_correctly() {
     let not_after = &not_after();
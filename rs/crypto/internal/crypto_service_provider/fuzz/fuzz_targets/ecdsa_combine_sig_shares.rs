@@ -0,0 +1,248 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use ic_crypto_internal_csp::api::CspThresholdEcdsaSigVerifier;
+use ic_crypto_internal_csp::secret_key_store::volatile_store::VolatileSecretKeyStore;
+use ic_crypto_internal_csp::Csp;
+use ic_crypto_internal_threshold_sig_ecdsa::*;
+use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
+use ic_types::crypto::AlgorithmId;
+use ic_types::{NumberOfNodes, PrincipalId, Randomness};
+use lazy_static::lazy_static;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Feeds adversarial signature shares, transcripts, thresholds and algorithm
+// IDs into `Csp::ecdsa_combine_sig_shares`. None of the inputs are honestly
+// generated signature shares, so besides not panicking, any signature that is
+// returned must not verify under the key derived from the fixture transcripts.
+
+const NUMBER_OF_NODES: usize = 4;
+const THRESHOLD: usize = 2;
+const SCALAR_BYTES: usize = 32;
+const SHARE_BYTES: usize = 2 + 4 * SCALAR_BYTES;
+
+struct Fixture {
+    key_transcript: IDkgTranscriptInternal,
+    presig_transcript: IDkgTranscriptInternal,
+    hashed_message: Vec<u8>,
+    nonce: Randomness,
+    derivation_path: ExtendedDerivationPath,
+}
+
+lazy_static! {
+    static ref FIXTURE: Fixture = Fixture::new();
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let rng = &mut ChaCha20Rng::from_seed([42; 32]);
+        let context_data = rng.gen::<[u8; 32]>().to_vec();
+
+        let mut private_keys = Vec::with_capacity(NUMBER_OF_NODES);
+        let mut public_keys = Vec::with_capacity(NUMBER_OF_NODES);
+        for _ in 0..NUMBER_OF_NODES {
+            let (pk, sk) = gen_keypair(EccCurveType::K256, Randomness::from(rng.gen::<[u8; 32]>()))
+                .expect("failed to generate MEGa key pair");
+            public_keys.push(pk);
+            private_keys.push(sk);
+        }
+
+        let run_round = |rng: &mut ChaCha20Rng,
+                         shares: &[SecretShares],
+                         mode: &IDkgTranscriptOperationInternal| {
+            let dealings = shares
+                .iter()
+                .enumerate()
+                .map(|(dealer_index, share)| {
+                    let dealing = create_dealing(
+                        AlgorithmId::ThresholdEcdsaSecp256k1,
+                        &context_data,
+                        dealer_index as NodeIndex,
+                        NumberOfNodes::from(THRESHOLD as u32),
+                        &public_keys,
+                        share,
+                        Randomness::from(rng.gen::<[u8; 32]>()),
+                    )
+                    .expect("failed to create dealing");
+                    (dealer_index as NodeIndex, dealing)
+                })
+                .collect::<BTreeMap<_, _>>();
+            let transcript = create_transcript(
+                AlgorithmId::ThresholdEcdsaSecp256k1,
+                NumberOfNodes::from(THRESHOLD as u32),
+                &dealings,
+                mode,
+            )
+            .expect("failed to create transcript");
+            let openings = (0..NUMBER_OF_NODES)
+                .map(|receiver_index| {
+                    compute_secret_shares(
+                        &dealings,
+                        &transcript,
+                        &context_data,
+                        receiver_index as NodeIndex,
+                        &private_keys[receiver_index],
+                        &public_keys[receiver_index],
+                    )
+                    .expect("failed to compute secret shares")
+                })
+                .collect::<Vec<_>>();
+            (transcript, openings)
+        };
+
+        // Both the key and the pre-signature are unmasked transcripts, i.e.
+        // reshares of random (masked) transcripts.
+        let unmasked_transcript = |rng: &mut ChaCha20Rng| {
+            let random_shares = vec![SecretShares::Random; NUMBER_OF_NODES];
+            let (masked, masked_openings) = run_round(
+                rng,
+                &random_shares,
+                &IDkgTranscriptOperationInternal::Random,
+            );
+            let reshare_shares = masked_openings
+                .iter()
+                .map(|opening| match opening {
+                    CommitmentOpening::Pedersen(v, m) => SecretShares::ReshareOfMasked(*v, *m),
+                    CommitmentOpening::Simple(_) => panic!("unexpected opening type"),
+                })
+                .collect::<Vec<_>>();
+            let mode = IDkgTranscriptOperationInternal::ReshareOfMasked(
+                masked.combined_commitment.commitment().clone(),
+            );
+            run_round(rng, &reshare_shares, &mode).0
+        };
+
+        let key_transcript = unmasked_transcript(rng);
+        let presig_transcript = unmasked_transcript(rng);
+
+        Fixture {
+            key_transcript,
+            presig_transcript,
+            hashed_message: rng.gen::<[u8; 32]>().to_vec(),
+            nonce: Randomness::from(rng.gen::<[u8; 32]>()),
+            derivation_path: ExtendedDerivationPath {
+                caller: PrincipalId::new_user_test_id(1),
+                derivation_path: vec![],
+            },
+        }
+    }
+}
+
+/// Mirrors the (private) fields of `ThresholdEcdsaSigShareInternal` so that
+/// arbitrary shares can be built through its serialization.
+#[derive(Serialize)]
+struct SigShareFields {
+    sigma_numerator: CommitmentOpening,
+    sigma_denominator: CommitmentOpening,
+}
+
+fn opening_from_bytes(kind: u8, bytes: &[u8]) -> Option<CommitmentOpening> {
+    let value = EccScalar::from_bytes_wide(EccCurveType::K256, &bytes[..SCALAR_BYTES]).ok()?;
+    let mask = EccScalar::from_bytes_wide(EccCurveType::K256, &bytes[SCALAR_BYTES..]).ok()?;
+    if kind % 2 == 0 {
+        Some(CommitmentOpening::Pedersen(value, mask))
+    } else {
+        Some(CommitmentOpening::Simple(value))
+    }
+}
+
+/// Each share is encoded as a node index byte, a byte selecting the opening
+/// types, and four scalars.
+fn sig_shares_from_bytes(data: &[u8]) -> BTreeMap<NodeIndex, ThresholdEcdsaSigShareInternal> {
+    let mut shares = BTreeMap::new();
+    for chunk in data.chunks_exact(SHARE_BYTES) {
+        let index = NodeIndex::from(chunk[0]);
+        let kinds = chunk[1];
+        let scalars = &chunk[2..];
+        let (numerator, denominator) = match (
+            opening_from_bytes(kinds, &scalars[..2 * SCALAR_BYTES]),
+            opening_from_bytes(kinds >> 1, &scalars[2 * SCALAR_BYTES..]),
+        ) {
+            (Some(n), Some(d)) => (n, d),
+            _ => continue,
+        };
+        let fields = SigShareFields {
+            sigma_numerator: numerator,
+            sigma_denominator: denominator,
+        };
+        let bytes = serde_cbor::to_vec(&fields).expect("failed to serialize share fields");
+        let share = ThresholdEcdsaSigShareInternal::deserialize(&bytes)
+            .expect("failed to deserialize share");
+        shares.insert(index, share);
+    }
+    shares
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let fixture = &*FIXTURE;
+
+    let algorithm_id = AlgorithmId::from(i32::from(data[0] % 20));
+    let reconstruction_threshold = NumberOfNodes::from(u32::from(data[1] % 8));
+
+    // Optionally replace the transcripts with adversarial ones: swapped, or
+    // decoded from the input.
+    let (key_transcript, presig_transcript, shares_bytes) = match data[2] % 3 {
+        0 => (
+            fixture.key_transcript.clone(),
+            fixture.presig_transcript.clone(),
+            &data[3..],
+        ),
+        1 => (
+            fixture.presig_transcript.clone(),
+            fixture.key_transcript.clone(),
+            &data[3..],
+        ),
+        _ => {
+            let mut deserializer = serde_cbor::Deserializer::from_slice(&data[3..]);
+            let key = serde::Deserialize::deserialize(&mut deserializer);
+            let presig = serde::Deserialize::deserialize(&mut deserializer);
+            match (key, presig) {
+                (Ok(key), Ok(presig)) => {
+                    let offset = 3 + deserializer.byte_offset();
+                    (key, presig, &data[offset..])
+                }
+                _ => return,
+            }
+        }
+    };
+
+    let sig_shares = sig_shares_from_bytes(shares_bytes);
+
+    let csp = Csp::of(
+        ChaCha20Rng::from_seed([0; 32]),
+        VolatileSecretKeyStore::new(),
+    );
+
+    let result = csp.ecdsa_combine_sig_shares(
+        &fixture.derivation_path,
+        &fixture.hashed_message,
+        &fixture.nonce,
+        &key_transcript,
+        &presig_transcript,
+        reconstruction_threshold,
+        &sig_shares,
+        algorithm_id,
+    );
+
+    if let Ok(signature) = result {
+        assert!(
+            verify_threshold_signature(
+                &signature,
+                &(&fixture.derivation_path).into(),
+                &fixture.hashed_message,
+                fixture.nonce,
+                &fixture.presig_transcript,
+                &fixture.key_transcript,
+                AlgorithmId::ThresholdEcdsaSecp256k1,
+            )
+            .is_err(),
+            "combining adversarial signature shares produced a valid signature"
+        );
+    }
+});
@@ -2,22 +2,60 @@
 
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
-    ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaSigShareInternal,
 };
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgCreateTranscriptError, IDkgLoadTranscriptError,
-    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyTranscriptError,
-    ThresholdEcdsaCombineSigSharesError, ThresholdEcdsaSignShareError,
+    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyDealingPrivateError,
+    IDkgVerifyDealingPublicError, IDkgVerifyTranscriptError, ThresholdEcdsaCombineSigSharesError,
+    ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::AlgorithmId;
-use ic_types::{NodeIndex, NumberOfNodes, Randomness};
-use std::collections::BTreeMap;
+use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 pub mod errors;
 pub use errors::*;
 
+/// How a successful call to
+/// [`idkg_load_transcript`](CspIDkgProtocol::idkg_load_transcript) completed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum IDkgLoadTranscriptOutcome {
+    /// The secret shares of the transcript were already stored, e.g. because
+    /// the transcript was loaded before, so nothing was computed.
+    OpeningAlreadyStored,
+    /// The secret shares of the transcript were computed and stored.
+    SharesComputed,
+    /// The secret shares of the transcript could not be computed because some
+    /// dealings are invalid for this receiver, so complaints against them
+    /// were generated instead, keyed by dealer index.
+    ComplaintsGenerated(BTreeMap<NodeIndex, IDkgComplaintInternal>),
+}
+
+impl IDkgLoadTranscriptOutcome {
+    /// Returns the generated complaints, which are empty unless the outcome
+    /// is [`ComplaintsGenerated`](Self::ComplaintsGenerated).
+    pub fn into_complaints(self) -> BTreeMap<NodeIndex, IDkgComplaintInternal> {
+        match self {
+            IDkgLoadTranscriptOutcome::ComplaintsGenerated(complaints) => complaints,
+            IDkgLoadTranscriptOutcome::OpeningAlreadyStored
+            | IDkgLoadTranscriptOutcome::SharesComputed => BTreeMap::new(),
+        }
+    }
+
+    /// Returns the label under which the outcome is counted in the metrics.
+    pub fn metric_label(&self) -> &'static str {
+        match self {
+            IDkgLoadTranscriptOutcome::OpeningAlreadyStored => "opening_already_stored",
+            IDkgLoadTranscriptOutcome::SharesComputed => "shares_computed",
+            IDkgLoadTranscriptOutcome::ComplaintsGenerated(_) => "complaints_generated",
+        }
+    }
+}
+
 /// Crypto service provider (CSP) client for interactive distributed key
 /// generation (IDkg) for canister threshold signatures.
 pub trait CspIDkgProtocol {
@@ -32,6 +70,38 @@ pub trait CspIDkgProtocol {
         transcript_operation: &IDkgTranscriptOperationInternal,
     ) -> Result<IDkgDealingInternal, IDkgCreateDealingError>;
 
+    /// Publicly verifies a dealing, i.e. checks that its ciphertext and
+    /// commitment have the types expected for `operation_mode` and verifies
+    /// its zero knowledge proofs.
+    ///
+    /// This only uses public information, so it neither calls the vault nor
+    /// accesses any secret key store.
+    #[allow(clippy::too_many_arguments)]
+    fn idkg_verify_dealing_public(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        operation_mode: &IDkgTranscriptOperationInternal,
+        reconstruction_threshold: NumberOfNodes,
+        dealer_index: NodeIndex,
+        number_of_receivers: NumberOfNodes,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPublicError>;
+
+    /// Privately verifies the share of a publicly verified dealing that is
+    /// encrypted to the receiver with index `receiver_index`, i.e. decrypts
+    /// it with the MEGa private key of `receiver_public_key` and checks it
+    /// against the commitment of the dealing.
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_public_key: &MEGaPublicKey,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError>;
+
     /// Generates an IDkg transcript from verified IDkg dealings
     fn idkg_create_transcript(
         &self,
@@ -52,6 +122,10 @@ pub trait CspIDkgProtocol {
 
     /// Compute secret from transcript and store in SKS, generating complaints
     /// if necessary.
+    ///
+    /// The returned outcome tells whether the call exited early because the
+    /// secret shares were already stored, computed them, or generated
+    /// complaints. The outcomes are also counted in the metrics.
     fn idkg_load_transcript(
         &self,
         dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
@@ -59,7 +133,7 @@ pub trait CspIDkgProtocol {
         receiver_index: NodeIndex,
         public_key: &MEGaPublicKey,
         transcript: &IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError>;
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError>;
 
     /// Computes a secret share from a transcript and openings, and stores it
     /// in the canister secret key store.
@@ -75,10 +149,15 @@ pub trait CspIDkgProtocol {
 
     /// Generate a MEGa key pair for encrypting threshold key shares in transmission
     /// from dealers to receivers.
+    ///
+    /// If `pop_node_id` is given, also returns a proof of possession of the
+    /// private key that is bound to that node ID, which can be verified with
+    /// `ic_crypto_internal_threshold_sig_ecdsa::verify_mega_key_proof_of_possession`.
     fn idkg_create_mega_key_pair(
-        &mut self,
+        &self,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError>;
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError>;
 
     /// Verifies that the given `complaint` about `dealing` is correct/justified.
     /// A complaint is created, e.g., when loading of a transcript fails.
@@ -101,6 +180,34 @@ pub trait CspIDkgProtocol {
         opener_index: NodeIndex,
         opener_public_key: &MEGaPublicKey,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError>;
+
+    /// Checks that the secret key store holds a valid MEGa key pair for
+    /// `public_key`, e.g. the node's MEGa encryption key from the registry.
+    /// No secret key material is revealed.
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError>;
+
+    /// Removes the MEGa key pairs of `public_keys` from the secret key store,
+    /// e.g. the dealing encryption keys that were rotated out and are no
+    /// longer referenced by any active transcript.
+    ///
+    /// Key pairs that are not in the secret key store are ignored.
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError>;
+
+    /// Returns the algorithms of canister threshold signatures that the CSP
+    /// supports.
+    ///
+    /// The IDkg and threshold ECDSA operations of the CSP fail with an
+    /// `UnsupportedAlgorithmId` error naming these algorithms if they are
+    /// called with any other algorithm. Callers can use this to avoid
+    /// starting operations that this node cannot execute, e.g. while the
+    /// nodes of a subnet run different versions during an upgrade.
+    fn supported_threshold_algorithms(&self) -> BTreeSet<AlgorithmId>;
 }
 
 /// Crypto service provider (CSP) client for threshold ECDSA signature share
@@ -126,6 +233,8 @@ pub trait CspThresholdEcdsaSigner {
 /// verification.
 pub trait CspThresholdEcdsaSigVerifier {
     /// Combine signature shares.
+    ///
+    /// The combined signature is always in low-S form.
     #[allow(clippy::too_many_arguments)]
     fn ecdsa_combine_sig_shares(
         &self,
@@ -1,6 +1,6 @@
 //! Errors encountered during CSP canister threshold signature operations.
 use ic_crypto_internal_threshold_sig_ecdsa::ThresholdEcdsaError;
-use ic_types::crypto::AlgorithmId;
+use ic_types::crypto::{AlgorithmId, KeyId};
 use serde::{Deserialize, Serialize};
 
 /// Errors encountered during generation of a MEGa encryption key pair.
@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 pub enum CspCreateMEGaKeyError {
     UnsupportedAlgorithm { algorithm_id: AlgorithmId },
     FailedKeyGeneration(ThresholdEcdsaError),
+    FailedProofOfPossessionGeneration(ThresholdEcdsaError),
     SerializationError(ThresholdEcdsaError),
+    DuplicateKeyId { key_id: KeyId },
     CspServerError { internal_error: String },
 }
 
@@ -25,11 +27,21 @@ impl std::fmt::Display for CspCreateMEGaKeyError {
                 "Error creating MEGa keypair: Underlying operation failed: {:?}",
                 tecdsa_err
             ),
+            Self::FailedProofOfPossessionGeneration(tecdsa_err) => write!(
+                f,
+                "Error creating proof of possession of MEGa keypair: Underlying operation failed: {:?}",
+                tecdsa_err
+            ),
             Self::SerializationError(tecdsa_err) => write!(
                 f,
                 "Error (de)serializing MEGa keypair: Underlying operation failed: {:?}",
                 tecdsa_err
             ),
+            Self::DuplicateKeyId { key_id } => write!(
+                f,
+                "Error creating MEGa keypair: A key with ID {} has already been stored",
+                key_id
+            ),
             Self::CspServerError { internal_error } => write!(
                 f,
                 "Error creating MEGa keypair: CSP server operation failed: {:?}",
@@ -38,3 +50,71 @@ impl std::fmt::Display for CspCreateMEGaKeyError {
         }
     }
 }
+
+/// Errors encountered when checking that the secret key store holds a valid
+/// MEGa encryption key pair for a given public key.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CspCheckMEGaKeyPairError {
+    UnsupportedAlgorithm { algorithm_id: AlgorithmId },
+    SecretKeyNotFound { key_id: KeyId },
+    MalformedKeyPair { internal_error: String },
+    PublicKeyMismatch { key_id: KeyId },
+    CspServerError { internal_error: String },
+}
+
+impl std::fmt::Display for CspCheckMEGaKeyPairError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedAlgorithm { algorithm_id } => write!(
+                f,
+                "Error checking MEGa keypair: Algorithm '{:?}' is not supported",
+                algorithm_id
+            ),
+            Self::SecretKeyNotFound { key_id } => write!(
+                f,
+                "Error checking MEGa keypair: No secret key with ID {} found",
+                key_id
+            ),
+            Self::MalformedKeyPair { internal_error } => write!(
+                f,
+                "Error checking MEGa keypair: Stored key pair is malformed: {}",
+                internal_error
+            ),
+            Self::PublicKeyMismatch { key_id } => write!(
+                f,
+                "Error checking MEGa keypair: Secret key with ID {} does not match the public key",
+                key_id
+            ),
+            Self::CspServerError { internal_error } => write!(
+                f,
+                "Error checking MEGa keypair: CSP server operation failed: {:?}",
+                internal_error
+            ),
+        }
+    }
+}
+
+/// Errors encountered when retiring MEGa encryption key pairs from the secret
+/// key store.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CspRetireMEGaKeysError {
+    UnsupportedAlgorithm { algorithm_id: AlgorithmId },
+    CspServerError { internal_error: String },
+}
+
+impl std::fmt::Display for CspRetireMEGaKeysError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedAlgorithm { algorithm_id } => write!(
+                f,
+                "Error retiring MEGa keypairs: Algorithm '{:?}' is not supported",
+                algorithm_id
+            ),
+            Self::CspServerError { internal_error } => write!(
+                f,
+                "Error retiring MEGa keypairs: CSP server operation failed: {:?}",
+                internal_error
+            ),
+        }
+    }
+}
@@ -7,7 +7,8 @@ mod threshold;
 mod tls_stub;
 
 pub use canister_threshold::{
-    CspCreateMEGaKeyError, CspIDkgProtocol, CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner,
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspIDkgProtocol, CspRetireMEGaKeysError,
+    CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner, IDkgLoadTranscriptOutcome,
 };
 pub use keygen::{CspKeyGenerator, CspSecretKeyStoreChecker, NodePublicKeyData};
 pub use sign::CspSigner;
@@ -4,37 +4,61 @@
 //! including the secret key store and random number generator, and the
 //! stateless crypto lib.
 
+pub(crate) mod secret_not_found;
 #[cfg(test)]
 mod tests;
 
 use crate::api::{
-    CspCreateMEGaKeyError, CspIDkgProtocol, CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner,
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspIDkgProtocol, CspRetireMEGaKeysError,
+    CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner, IDkgLoadTranscriptOutcome,
 };
-use crate::keygen::mega_key_id;
+use crate::canister_threshold::secret_not_found::load_transcript_missing_secret;
+use crate::keygen::{commitment_key_id, mega_key_id};
 use crate::secret_key_store::SecretKeyStore;
 use crate::Csp;
 use ic_crypto_internal_threshold_sig_ecdsa::{
     combine_sig_shares as tecdsa_combine_sig_shares, create_transcript as tecdsa_create_transcript,
+    publicly_verify_dealing as tecdsa_verify_dealing_public,
     verify_complaint as tecdsa_verify_complaint, verify_transcript as tecdsa_verify_transcript,
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
-    ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::scope::{ConstScope, Scope};
 use ic_logger::debug;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgCreateTranscriptError, IDkgLoadTranscriptError,
-    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyTranscriptError,
-    ThresholdEcdsaCombineSigSharesError, ThresholdEcdsaSignShareError,
+    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyDealingPrivateError,
+    IDkgVerifyDealingPublicError, IDkgVerifyTranscriptError, ThresholdEcdsaCombineSigSharesError,
+    ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
-use ic_types::crypto::AlgorithmId;
-use ic_types::{NodeIndex, NumberOfNodes, Randomness};
+use ic_types::crypto::{AlgorithmId, KeyId};
+use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
 use rand::{CryptoRng, Rng};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub const IDKG_MEGA_SCOPE: Scope = Scope::Const(ConstScope::IDkgMEGaEncryptionKeys);
 
+/// The algorithms of canister threshold signatures that the CSP supports.
+const SUPPORTED_THRESHOLD_ALGORITHMS: [AlgorithmId; 1] = [AlgorithmId::ThresholdEcdsaSecp256k1];
+
+fn supported_threshold_algorithms() -> BTreeSet<AlgorithmId> {
+    SUPPORTED_THRESHOLD_ALGORITHMS.iter().copied().collect()
+}
+
+/// Returns the supported algorithms as error if `algorithm_id` is not one of
+/// them.
+fn ensure_supported_threshold_algorithm(
+    algorithm_id: AlgorithmId,
+) -> Result<(), BTreeSet<AlgorithmId>> {
+    if SUPPORTED_THRESHOLD_ALGORITHMS.contains(&algorithm_id) {
+        Ok(())
+    } else {
+        Err(supported_threshold_algorithms())
+    }
+}
+
 /// Interactive distributed key generation client
 ///
 /// Please see the trait definition for full documentation.
@@ -52,14 +76,87 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
     ) -> Result<IDkgDealingInternal, IDkgCreateDealingError> {
         debug!(self.logger; crypto.method_name => "idkg_create_dealing");
 
-        self.csp_vault.idkg_create_dealing(
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            IDkgCreateDealingError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
+
+        let result = self.csp_vault.idkg_create_dealing(
             algorithm_id,
             context_data,
             dealer_index,
             reconstruction_threshold,
             receiver_keys,
             transcript_operation,
-        )
+        );
+        self.secret_not_found_tracker
+            .observe("idkg_create_dealing", result)
+    }
+
+    fn idkg_verify_dealing_public(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        operation_mode: &IDkgTranscriptOperationInternal,
+        reconstruction_threshold: NumberOfNodes,
+        dealer_index: NodeIndex,
+        number_of_receivers: NumberOfNodes,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPublicError> {
+        debug!(self.logger; crypto.method_name => "idkg_verify_dealing_public");
+
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            IDkgVerifyDealingPublicError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
+
+        Ok(tecdsa_verify_dealing_public(
+            algorithm_id,
+            dealing,
+            operation_mode,
+            reconstruction_threshold,
+            dealer_index,
+            number_of_receivers,
+            context_data,
+        )?)
+    }
+
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_public_key: &MEGaPublicKey,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        let receiver_key_id = mega_key_id(receiver_public_key);
+        debug!(self.logger;
+            crypto.method_name => "idkg_verify_dealing_private",
+            crypto.key_id => receiver_key_id.to_string(),
+        );
+
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            IDkgVerifyDealingPrivateError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
+
+        let result = self.read_only_vault().idkg_verify_dealing_private(
+            algorithm_id,
+            dealing,
+            dealer_index,
+            receiver_index,
+            &receiver_key_id,
+            context_data,
+        );
+        self.secret_not_found_tracker
+            .observe("idkg_verify_dealing_private", result)
     }
 
     fn idkg_create_transcript(
@@ -71,6 +168,13 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
     ) -> Result<IDkgTranscriptInternal, IDkgCreateTranscriptError> {
         debug!(self.logger; crypto.method_name => "idkg_create_transcript");
 
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            IDkgCreateTranscriptError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
+
         tecdsa_create_transcript(
             algorithm_id,
             reconstruction_threshold,
@@ -90,7 +194,17 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
         verified_dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
         operation_mode: &IDkgTranscriptOperationInternal,
     ) -> Result<(), IDkgVerifyTranscriptError> {
-        debug!(self.logger; crypto.method_name => "idkg_verify_transcript");
+        debug!(self.logger;
+            crypto.method_name => "idkg_verify_transcript",
+            crypto.key_id => commitment_key_id(transcript.combined_commitment.commitment()).to_string(),
+        );
+
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            IDkgVerifyTranscriptError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
 
         Ok(tecdsa_verify_transcript(
             transcript,
@@ -108,18 +222,31 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
         receiver_index: NodeIndex,
         public_key: &MEGaPublicKey,
         transcript: &IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError> {
-        debug!(self.logger; crypto.method_name => "idkg_load_transcript");
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError> {
+        debug!(self.logger;
+            crypto.method_name => "idkg_load_transcript",
+            crypto.key_id => commitment_key_id(transcript.combined_commitment.commitment()).to_string(),
+        );
 
         let key_id = mega_key_id(public_key);
 
-        self.csp_vault.idkg_load_transcript(
-            dealings,
-            context_data,
-            receiver_index,
-            &key_id,
-            transcript,
-        )
+        let outcome = self
+            .csp_vault
+            .idkg_load_transcript(dealings, context_data, receiver_index, &key_id, transcript)
+            .map_err(|e| {
+                if let Some(secret) = load_transcript_missing_secret(&e, &KeyId::from(key_id)) {
+                    self.secret_not_found_tracker
+                        .record("idkg_load_transcript", secret);
+                }
+                e
+            })?;
+        debug!(self.logger;
+            crypto.method_name => "idkg_load_transcript",
+            crypto.description => outcome.metric_label(),
+        );
+        self.metrics
+            .inc_idkg_load_transcript(outcome.metric_label());
+        Ok(outcome)
     }
 
     fn idkg_load_transcript_with_openings(
@@ -131,27 +258,40 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
         public_key: &MEGaPublicKey,
         transcript: &IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError> {
-        debug!(self.logger; crypto.method_name => "idkg_load_transcript_with_openings");
+        debug!(self.logger;
+            crypto.method_name => "idkg_load_transcript_with_openings",
+            crypto.key_id => commitment_key_id(transcript.combined_commitment.commitment()).to_string(),
+        );
 
         let key_id = mega_key_id(public_key);
 
-        self.csp_vault.idkg_load_transcript_with_openings(
-            dealings,
-            openings,
-            context_data,
-            receiver_index,
-            &key_id,
-            transcript,
-        )
+        self.csp_vault
+            .idkg_load_transcript_with_openings(
+                dealings,
+                openings,
+                context_data,
+                receiver_index,
+                &key_id,
+                transcript,
+            )
+            .map_err(|e| {
+                if let Some(secret) = load_transcript_missing_secret(&e, &KeyId::from(key_id)) {
+                    self.secret_not_found_tracker
+                        .record("idkg_load_transcript_with_openings", secret);
+                }
+                e
+            })
     }
 
     fn idkg_create_mega_key_pair(
-        &mut self,
+        &self,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError> {
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError> {
         debug!(self.logger; crypto.method_name => "idkg_create_mega_key_pair");
 
-        self.csp_vault.idkg_gen_mega_key_pair(algorithm_id)
+        self.csp_vault
+            .idkg_gen_mega_key_pair(algorithm_id, pop_node_id)
     }
 
     fn idkg_verify_complaint(
@@ -184,13 +324,42 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
         opener_public_key: &MEGaPublicKey,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
         let opener_key_id = mega_key_id(opener_public_key);
-        self.csp_vault.idkg_open_dealing(
+        debug!(self.logger;
+            crypto.method_name => "idkg_open_dealing",
+            crypto.key_id => opener_key_id.to_string(),
+        );
+
+        let result = self.csp_vault.idkg_open_dealing(
             dealing,
             dealer_index,
             context_data,
             opener_index,
             &opener_key_id,
-        )
+        );
+        self.secret_not_found_tracker
+            .observe("idkg_open_dealing", result)
+    }
+
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        debug!(self.logger; crypto.method_name => "idkg_check_mega_key_pair");
+
+        self.read_only_vault().idkg_check_mega_key_pair(public_key)
+    }
+
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError> {
+        debug!(self.logger; crypto.method_name => "idkg_retire_mega_keys");
+
+        self.csp_vault.idkg_retire_mega_keys(public_keys)
+    }
+
+    fn supported_threshold_algorithms(&self) -> BTreeSet<AlgorithmId> {
+        supported_threshold_algorithms()
     }
 }
 
@@ -212,9 +381,19 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
         key_times_lambda: &IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError> {
-        debug!(self.logger; crypto.method_name => "ecdsa_sign_share");
+        debug!(self.logger;
+            crypto.method_name => "ecdsa_sign_share",
+            crypto.key_id => commitment_key_id(key.combined_commitment.commitment()).to_string(),
+        );
+
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            ThresholdEcdsaSignShareError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
 
-        self.csp_vault.ecdsa_sign_share(
+        let result = self.csp_vault.ecdsa_sign_share(
             derivation_path,
             hashed_message,
             nonce,
@@ -224,7 +403,9 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
             kappa_times_lambda,
             key_times_lambda,
             algorithm_id,
-        )
+        );
+        self.secret_not_found_tracker
+            .observe("ecdsa_sign_share", result)
     }
 }
 
@@ -245,7 +426,17 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
         sig_shares: &BTreeMap<NodeIndex, ThresholdEcdsaSigShareInternal>,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaCombineSigSharesError> {
-        debug!(self.logger; crypto.method_name => "ecdsa_combine_sig_shares");
+        debug!(self.logger;
+            crypto.method_name => "ecdsa_combine_sig_shares",
+            crypto.key_id => commitment_key_id(key_transcript.combined_commitment.commitment()).to_string(),
+        );
+
+        ensure_supported_threshold_algorithm(algorithm_id).map_err(|supported| {
+            ThresholdEcdsaCombineSigSharesError::UnsupportedAlgorithmId {
+                algorithm_id,
+                supported,
+            }
+        })?;
 
         tecdsa_combine_sig_shares(
             &derivation_path.into(),
@@ -0,0 +1,231 @@
+//! Classification of errors due to secrets missing from the secret key store.
+//!
+//! A node that does not hold the secret shares of a transcript, or the MEGa
+//! private key needed to load one, fails the same way on every retry. Without
+//! further context these failures only surface as opaque validation failures
+//! in consensus, so they are counted here and reported with rate-limited
+//! warnings.
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_logger::{warn, ReplicaLogger};
+use ic_types::crypto::canister_threshold_sig::error::{
+    IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
+};
+use ic_types::crypto::KeyId;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+/// The minimum time between two warnings about the same missing secret.
+pub const SECRET_NOT_FOUND_WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The maximum number of missing secrets that are tracked at any time.
+/// If more secrets go missing, the tracking starts anew.
+const MAX_TRACKED_SECRETS: usize = 1000;
+
+/// A secret that was not found in the secret key store.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MissingSecret {
+    /// The secret shares of the commitment with the given description, which
+    /// are stored in the canister secret key store.
+    SecretShares { commitment_string: String },
+    /// The MEGa private key with the given id, which is stored in the node
+    /// secret key store.
+    PrivateKey { key_id: KeyId },
+}
+
+impl MissingSecret {
+    fn label(&self) -> &'static str {
+        match self {
+            MissingSecret::SecretShares { .. } => "secret_shares",
+            MissingSecret::PrivateKey { .. } => "private_key",
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match self {
+            MissingSecret::SecretShares { .. } => {
+                "the transcript of this commitment was not loaded on this node, \
+                 or its secret shares were already removed; check that the node \
+                 loaded the transcript and did not retain only newer transcripts"
+            }
+            MissingSecret::PrivateKey { .. } => {
+                "the MEGa encryption key registered for this node is not in the \
+                 secret key store; check that the key in the registry matches the \
+                 key generated on this node"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for MissingSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingSecret::SecretShares { commitment_string } => {
+                write!(f, "secret shares of commitment {}", commitment_string)
+            }
+            MissingSecret::PrivateKey { key_id } => {
+                write!(f, "MEGa private key with id {}", key_id)
+            }
+        }
+    }
+}
+
+/// Returns the secret whose absence caused `error`, if any.
+pub trait AsMissingSecret {
+    fn missing_secret(&self) -> Option<MissingSecret>;
+}
+
+impl AsMissingSecret for IDkgCreateDealingError {
+    fn missing_secret(&self) -> Option<MissingSecret> {
+        match self {
+            IDkgCreateDealingError::SecretSharesNotFound { commitment_string } => {
+                Some(MissingSecret::SecretShares {
+                    commitment_string: commitment_string.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AsMissingSecret for IDkgOpenTranscriptError {
+    fn missing_secret(&self) -> Option<MissingSecret> {
+        match self {
+            IDkgOpenTranscriptError::PrivateKeyNotFound { key_id } => {
+                Some(MissingSecret::PrivateKey { key_id: *key_id })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AsMissingSecret for IDkgVerifyDealingPrivateError {
+    fn missing_secret(&self) -> Option<MissingSecret> {
+        match self {
+            IDkgVerifyDealingPrivateError::PrivateKeyNotFound { key_id } => {
+                Some(MissingSecret::PrivateKey { key_id: *key_id })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AsMissingSecret for ThresholdEcdsaSignShareError {
+    fn missing_secret(&self) -> Option<MissingSecret> {
+        match self {
+            ThresholdEcdsaSignShareError::SecretSharesNotFound { commitment_string } => {
+                Some(MissingSecret::SecretShares {
+                    commitment_string: commitment_string.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the missing secret if `error` is `PrivateKeyNotFound`. The error
+/// does not carry the key id, so it has to be given by the caller.
+pub fn load_transcript_missing_secret(
+    error: &IDkgLoadTranscriptError,
+    key_id: &KeyId,
+) -> Option<MissingSecret> {
+    match error {
+        IDkgLoadTranscriptError::PrivateKeyNotFound => {
+            Some(MissingSecret::PrivateKey { key_id: *key_id })
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Occurrences {
+    count: u64,
+    last_warning: Instant,
+}
+
+/// Counts the method calls that failed due to a missing secret and warns
+/// about each missing secret at most once every
+/// [`SECRET_NOT_FOUND_WARNING_INTERVAL`].
+pub struct SecretNotFoundTracker {
+    logger: ReplicaLogger,
+    metrics: Arc<CryptoMetrics>,
+    occurrences: Mutex<BTreeMap<MissingSecret, Occurrences>>,
+}
+
+impl SecretNotFoundTracker {
+    pub fn new(logger: ReplicaLogger, metrics: Arc<CryptoMetrics>) -> Self {
+        Self {
+            logger,
+            metrics,
+            occurrences: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records the missing secret of `result`, if any, and returns `result`
+    /// unchanged.
+    pub fn observe<T, E: AsMissingSecret>(
+        &self,
+        method_name: &str,
+        result: Result<T, E>,
+    ) -> Result<T, E> {
+        if let Err(error) = &result {
+            if let Some(secret) = error.missing_secret() {
+                self.record(method_name, secret);
+            }
+        }
+        result
+    }
+
+    /// Records that a call of `method_name` failed because `secret` is
+    /// missing.
+    pub fn record(&self, method_name: &str, secret: MissingSecret) {
+        self.metrics
+            .inc_idkg_secret_not_found(method_name, secret.label());
+        if let Some(count) = self.count_and_check_warning(&secret, Instant::now()) {
+            warn!(
+                self.logger,
+                "{} failed {} time(s) because the {} was not found: {}",
+                method_name,
+                count,
+                secret,
+                secret.hint();
+                crypto.method_name => method_name,
+            );
+        }
+    }
+
+    /// Counts an occurrence of `secret` at time `now`, and returns the number
+    /// of occurrences since the last warning if a warning is due.
+    fn count_and_check_warning(&self, secret: &MissingSecret, now: Instant) -> Option<u64> {
+        let mut occurrences = self.occurrences.lock();
+        if let Some(entry) = occurrences.get_mut(secret) {
+            entry.count += 1;
+            if now.saturating_duration_since(entry.last_warning) < SECRET_NOT_FOUND_WARNING_INTERVAL
+            {
+                return None;
+            }
+            let count = entry.count;
+            *entry = Occurrences {
+                count: 0,
+                last_warning: now,
+            };
+            return Some(count);
+        }
+        if occurrences.len() >= MAX_TRACKED_SECRETS {
+            occurrences.clear();
+        }
+        occurrences.insert(
+            secret.clone(),
+            Occurrences {
+                count: 0,
+                last_warning: now,
+            },
+        );
+        Some(1)
+    }
+}
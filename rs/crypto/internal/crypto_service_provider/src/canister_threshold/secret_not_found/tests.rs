@@ -0,0 +1,116 @@
+use super::*;
+use ic_logger::replica_logger::no_op_logger;
+
+fn tracker() -> SecretNotFoundTracker {
+    SecretNotFoundTracker::new(no_op_logger(), Arc::new(CryptoMetrics::none()))
+}
+
+fn secret_shares(commitment_string: &str) -> MissingSecret {
+    MissingSecret::SecretShares {
+        commitment_string: commitment_string.to_string(),
+    }
+}
+
+#[test]
+fn should_warn_on_first_occurrence() {
+    let tracker = tracker();
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c"), Instant::now()),
+        Some(1)
+    );
+}
+
+#[test]
+fn should_rate_limit_warnings_per_secret() {
+    let tracker = tracker();
+    let start = Instant::now();
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c"), start),
+        Some(1)
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c"), start + Duration::from_secs(1)),
+        None
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c"), start + Duration::from_secs(2)),
+        None
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(
+            &secret_shares("c"),
+            start + SECRET_NOT_FOUND_WARNING_INTERVAL
+        ),
+        Some(3)
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(
+            &secret_shares("c"),
+            start + SECRET_NOT_FOUND_WARNING_INTERVAL + Duration::from_secs(1)
+        ),
+        None
+    );
+}
+
+#[test]
+fn should_warn_independently_for_different_secrets() {
+    let tracker = tracker();
+    let now = Instant::now();
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c1"), now),
+        Some(1)
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(&secret_shares("c2"), now),
+        Some(1)
+    );
+    assert_eq!(
+        tracker.count_and_check_warning(
+            &MissingSecret::PrivateKey {
+                key_id: KeyId::from([0; 32])
+            },
+            now
+        ),
+        Some(1)
+    );
+}
+
+#[test]
+fn should_classify_missing_secrets_of_errors() {
+    assert_eq!(
+        ThresholdEcdsaSignShareError::SecretSharesNotFound {
+            commitment_string: "c".to_string()
+        }
+        .missing_secret(),
+        Some(secret_shares("c"))
+    );
+    assert_eq!(
+        IDkgCreateDealingError::SecretSharesNotFound {
+            commitment_string: "c".to_string()
+        }
+        .missing_secret(),
+        Some(secret_shares("c"))
+    );
+    assert_eq!(
+        IDkgOpenTranscriptError::PrivateKeyNotFound {
+            key_id: KeyId::from([1; 32])
+        }
+        .missing_secret(),
+        Some(MissingSecret::PrivateKey {
+            key_id: KeyId::from([1; 32])
+        })
+    );
+    assert_eq!(
+        load_transcript_missing_secret(
+            &IDkgLoadTranscriptError::PrivateKeyNotFound,
+            &KeyId::from([2; 32])
+        ),
+        Some(MissingSecret::PrivateKey {
+            key_id: KeyId::from([2; 32])
+        })
+    );
+    assert_eq!(
+        ThresholdEcdsaSignShareError::NotAReceiver.missing_secret(),
+        None
+    );
+}
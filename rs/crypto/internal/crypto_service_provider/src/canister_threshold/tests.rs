@@ -1 +1,125 @@
+#![allow(clippy::unwrap_used)]
 // TODO(CRP-1380): add tests for the functionality of this module
+use super::*;
+use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_metrics::MetricsRegistry;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::sync::Arc;
+
+fn csp() -> Csp<ChaCha20Rng, VolatileSecretKeyStore, VolatileSecretKeyStore> {
+    Csp::of(
+        ChaCha20Rng::seed_from_u64(42),
+        VolatileSecretKeyStore::new(),
+    )
+}
+
+#[test]
+fn should_support_only_secp256k1_threshold_ecdsa() {
+    assert_eq!(
+        csp().supported_threshold_algorithms(),
+        vec![AlgorithmId::ThresholdEcdsaSecp256k1]
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+    );
+}
+
+#[test]
+fn should_name_supported_algorithms_when_creating_dealing_with_unsupported_algorithm() {
+    let csp = csp();
+
+    let result = csp.idkg_create_dealing(
+        AlgorithmId::EcdsaSecp256k1,
+        b"context",
+        0,
+        NumberOfNodes::new(1),
+        &[],
+        &IDkgTranscriptOperationInternal::Random,
+    );
+
+    assert_eq!(
+        result.unwrap_err(),
+        IDkgCreateDealingError::UnsupportedAlgorithmId {
+            algorithm_id: AlgorithmId::EcdsaSecp256k1,
+            supported: csp.supported_threshold_algorithms(),
+        }
+    );
+}
+
+#[test]
+fn should_name_supported_algorithms_when_creating_transcript_with_unsupported_algorithm() {
+    let csp = csp();
+
+    let result = csp.idkg_create_transcript(
+        AlgorithmId::ThresBls12_381,
+        NumberOfNodes::new(1),
+        &BTreeMap::new(),
+        &IDkgTranscriptOperationInternal::Random,
+    );
+
+    assert_eq!(
+        result.unwrap_err(),
+        IDkgCreateTranscriptError::UnsupportedAlgorithmId {
+            algorithm_id: AlgorithmId::ThresBls12_381,
+            supported: csp.supported_threshold_algorithms(),
+        }
+    );
+}
+
+#[test]
+fn should_count_outcomes_of_transcript_loads() {
+    let registry = MetricsRegistry::new();
+    let mut csp = csp();
+    csp.metrics = Arc::new(CryptoMetrics::new(Some(&registry)));
+    let (receiver_key, _pop) = csp
+        .idkg_create_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .unwrap();
+    let dealing = csp
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context",
+            0,
+            NumberOfNodes::new(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .unwrap();
+    let dealings: BTreeMap<_, _> = vec![(0, dealing)].into_iter().collect();
+    let transcript = csp
+        .idkg_create_transcript(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            NumberOfNodes::new(1),
+            &dealings,
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .unwrap();
+    let load = || {
+        csp.idkg_load_transcript(&dealings, b"context", 0, &receiver_key, &transcript)
+            .unwrap()
+    };
+
+    assert_eq!(load(), IDkgLoadTranscriptOutcome::SharesComputed);
+    assert_eq!(load(), IDkgLoadTranscriptOutcome::OpeningAlreadyStored);
+    assert_eq!(load(), IDkgLoadTranscriptOutcome::OpeningAlreadyStored);
+
+    assert_eq!(load_count(&registry, "shares_computed"), Some(1));
+    assert_eq!(load_count(&registry, "opening_already_stored"), Some(2));
+    assert_eq!(load_count(&registry, "complaints_generated"), None);
+}
+
+fn load_count(registry: &MetricsRegistry, outcome: &str) -> Option<u64> {
+    registry
+        .prometheus_registry()
+        .gather()
+        .iter()
+        .filter(|family| family.get_name() == "ic_crypto_idkg_load_transcript_total")
+        .flat_map(|family| family.get_metric().iter())
+        .find(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .any(|label| label.get_name() == "outcome" && label.get_value() == outcome)
+        })
+        .map(|metric| metric.get_counter().get_value() as u64)
+}
@@ -4,7 +4,7 @@ use crate::api::{CspKeyGenerator, CspSecretKeyStoreChecker};
 use crate::secret_key_store::{SecretKeyStore, SecretKeyStoreError};
 use crate::types::{CspPop, CspPublicKey, CspSecretKey};
 use crate::Csp;
-use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, MEGaPublicKey};
+use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, MEGaPublicKey, PolynomialCommitment};
 use ic_crypto_internal_tls::keygen::generate_tls_key_pair_der;
 use ic_crypto_internal_types::encrypt::forward_secure::CspFsEncryptionPublicKey;
 use ic_crypto_sha::Sha256;
@@ -14,11 +14,17 @@ use ic_types::crypto::{AlgorithmId, CryptoError, KeyId};
 use ic_types::NodeId;
 use openssl::asn1::Asn1Time;
 use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+use std::fmt;
 pub use tls_keygen::tls_cert_hash_as_key_id;
 
 const KEY_ID_DOMAIN: &str = "ic-key-id";
+const COMMITMENT_KEY_ID_DOMAIN: &str = "ic-key-id-idkg-commitment-v2";
+const LEGACY_COMMITMENT_KEY_ID_DOMAIN: &str = "ic-key-id-idkg-commitment";
 
+#[cfg(any(test, feature = "test_keygen"))]
+pub mod test_keygen;
 #[cfg(test)]
 mod tests;
 
@@ -60,7 +66,7 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
     CspSecretKeyStoreChecker for Csp<R, S, C>
 {
     fn sks_contains(&self, key_id: &KeyId) -> bool {
-        self.csp_vault.sks_contains(key_id)
+        self.read_only_vault().sks_contains(key_id)
     }
 
     fn sks_contains_tls_key(&self, cert: &TlsPublicKeyCert) -> bool {
@@ -119,17 +125,84 @@ pub fn forward_secure_key_id(public_key: &CspFsEncryptionPublicKey) -> KeyId {
     KeyId::from(hash.finish())
 }
 
+/// The identifier of a MEGa encryption key pair in the node secret key store.
+///
+/// Distinct from [`CommitmentKeyId`], so that the identifier of a key pair
+/// can not be confused with the identifier of the secret shares of a
+/// transcript. It is converted into a [`KeyId`] only to access the store.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct MegaKeyId(KeyId);
+
+impl From<MegaKeyId> for KeyId {
+    fn from(key_id: MegaKeyId) -> Self {
+        key_id.0
+    }
+}
+
+impl fmt::Display for MegaKeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The identifier of the secret shares of a transcript in the canister secret
+/// key store. See [`MegaKeyId`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct CommitmentKeyId(KeyId);
+
+impl From<CommitmentKeyId> for KeyId {
+    fn from(key_id: CommitmentKeyId) -> Self {
+        key_id.0
+    }
+}
+
+impl fmt::Display for CommitmentKeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Compute the key identifier for a MEGa encryption public key
-pub fn mega_key_id(public_key: &MEGaPublicKey) -> KeyId {
+pub fn mega_key_id(public_key: &MEGaPublicKey) -> MegaKeyId {
     match public_key.curve_type() {
-        EccCurveType::K256 => bytes_hash_as_key_id(
+        EccCurveType::K256 => MegaKeyId(bytes_hash_as_key_id(
             AlgorithmId::ThresholdEcdsaSecp256k1,
             &public_key.serialize(),
-        ),
+        )),
         c => panic!("unsupported curve: {:?}", c),
     }
 }
 
+/// Compute the key identifier under which the secret shares of a transcript
+/// with the given commitment are stored
+///
+/// The identifier is the hash of the [stable
+/// representation](PolynomialCommitment::stable_representation) of the
+/// commitment, so it does not change with serde or the layout of the
+/// commitment types.
+pub fn commitment_key_id(commitment: &PolynomialCommitment) -> CommitmentKeyId {
+    let mut hash = Sha256::new_with_context(&DomainSeparationContext::new(
+        COMMITMENT_KEY_ID_DOMAIN.to_string(),
+    ));
+    hash.write(&commitment.stable_representation());
+    CommitmentKeyId(KeyId::from(hash.finish()))
+}
+
+/// Compute the key identifier under which the secret shares of a transcript
+/// with the given commitment were stored before [`commitment_key_id`] was
+/// derived from the stable representation of the commitment
+///
+/// The identifier is the hash of the serde CBOR serialization of the
+/// commitment. It is only used to find the secret shares stored by earlier
+/// versions, which are then moved to their current key identifier.
+pub fn legacy_commitment_key_id(commitment: &PolynomialCommitment) -> CommitmentKeyId {
+    let mut hash = Sha256::new_with_context(&DomainSeparationContext::new(
+        LEGACY_COMMITMENT_KEY_ID_DOMAIN.to_string(),
+    ));
+    hash.write(&serde_cbor::to_vec(commitment).expect("Failed to serialize commitment"));
+    CommitmentKeyId(KeyId::from(hash.finish()))
+}
+
 mod tls_keygen {
     use super::*;
     use ic_crypto_internal_tls::keygen::TlsEd25519SecretKeyDerBytes;
@@ -176,6 +249,7 @@ pub mod utils {
                 proof_data: Some(serde_cbor::to_vec(&pop).expect(
                     "Failed to serialize DKG dealing encryption key proof of possession (PoP) to CBOR",
                 )),
+                timestamp: None,
             },
             _=> panic!("Unsupported types")
         }
@@ -0,0 +1,110 @@
+//! Deterministic derivation of iDKG keys for tests
+//!
+//! Tests involving several nodes need fixtures that are the same in every
+//! run, e.g. to assert on key ids or to let each node hold the keys that the
+//! other nodes expect. Instead of sharing serialized secret key stores, such
+//! tests derive the keys of each node from a label with a [`TestKeygen`].
+//!
+//! The derivation is domain separated: distinct labels, and distinct kinds of
+//! keys derived from the same label, result in unrelated keys.
+//!
+//! The keys are derived from public labels and are therefore NOT secret.
+//! This module is only compiled for tests and with the `test_keygen`
+//! feature, which must never be enabled in production.
+use super::{commitment_key_id, mega_key_id, CommitmentKeyId, MegaKeyId};
+use crate::types::CspSecretKey;
+use ic_crypto_internal_threshold_sig_ecdsa::{
+    CommitmentOpening, CommitmentOpeningBytes, EccCurveType, EccScalar, MEGaKeySetK256Bytes,
+    MEGaPrivateKey, MEGaPrivateKeyK256Bytes, MEGaPublicKey, MEGaPublicKeyK256Bytes, NodeIndex,
+    Polynomial, PolynomialCommitment, Seed, SimpleCommitment,
+};
+use std::convert::TryFrom;
+
+const TEST_KEYGEN_DOMAIN: &str = "ic-crypto-csp-test-keygen";
+const SUB_LABEL_DOMAIN: &str = "ic-crypto-csp-test-keygen-sub-label";
+const MEGA_KEY_DOMAIN: &str = "ic-crypto-csp-test-keygen-mega-key";
+const COMMITMENT_DOMAIN: &str = "ic-crypto-csp-test-keygen-commitment";
+
+/// Derives reproducible keys from a label. See the [module
+/// documentation](self).
+#[derive(Clone, Debug)]
+pub struct TestKeygen {
+    seed: Seed,
+}
+
+impl TestKeygen {
+    /// Creates the generator of the keys labelled `label`.
+    pub fn new(label: &str) -> Self {
+        Self {
+            seed: Seed::from_bytes(label.as_bytes()).derive(TEST_KEYGEN_DOMAIN),
+        }
+    }
+
+    /// Creates the generator of the keys labelled `label` within the scope of
+    /// this generator, e.g. for each node of the test this generator is
+    /// labelled after.
+    pub fn derive(&self, label: &str) -> Self {
+        Self {
+            seed: self.seed.derive(&format!("{}-{}", SUB_LABEL_DOMAIN, label)),
+        }
+    }
+
+    /// Derives a MEGa key pair on secp256k1, returning its key id, its public
+    /// key and the key set as stored in the node secret key store.
+    pub fn mega_key_pair(&self) -> (MegaKeyId, MEGaPublicKey, CspSecretKey) {
+        let mut rng = self.seed.derive(MEGA_KEY_DOMAIN).into_rng();
+        let private_key = MEGaPrivateKey::generate(EccCurveType::K256, &mut rng)
+            .expect("failed to generate MEGa private key");
+        let public_key = private_key
+            .public_key()
+            .expect("private key should have public key");
+        let key_set = CspSecretKey::MEGaEncryptionK256(MEGaKeySetK256Bytes {
+            public_key: MEGaPublicKeyK256Bytes::try_from(&public_key)
+                .expect("derived key should serialize"),
+            private_key: MEGaPrivateKeyK256Bytes::try_from(&private_key)
+                .expect("derived key should serialize"),
+        });
+        (mega_key_id(&public_key), public_key, key_set)
+    }
+
+    /// Derives a simple commitment on secp256k1 to a polynomial with
+    /// `num_coefficients` coefficients, together with its opening for the
+    /// receiver with index `receiver_index`. Returns the key id of the
+    /// commitment, the commitment, the opening, and the opening as stored in
+    /// the canister secret key store.
+    pub fn commitment_opening(
+        &self,
+        num_coefficients: usize,
+        receiver_index: NodeIndex,
+    ) -> (
+        CommitmentKeyId,
+        PolynomialCommitment,
+        CommitmentOpening,
+        CspSecretKey,
+    ) {
+        let mut rng = self.seed.derive(COMMITMENT_DOMAIN).into_rng();
+        let polynomial = Polynomial::random(EccCurveType::K256, num_coefficients, &mut rng)
+            .expect("failed to generate polynomial");
+        let commitment = PolynomialCommitment::from(
+            SimpleCommitment::create(&polynomial, num_coefficients)
+                .expect("failed to commit to polynomial"),
+        );
+        let opening = CommitmentOpening::Simple(
+            polynomial
+                .evaluate_at(&EccScalar::from_node_index(
+                    EccCurveType::K256,
+                    receiver_index,
+                ))
+                .expect("failed to evaluate polynomial"),
+        );
+        let opening_bytes = CspSecretKey::IDkgCommitmentOpening(
+            CommitmentOpeningBytes::try_from(&opening).expect("derived opening should serialize"),
+        );
+        (
+            commitment_key_id(&commitment),
+            commitment,
+            opening,
+            opening_bytes,
+        )
+    }
+}
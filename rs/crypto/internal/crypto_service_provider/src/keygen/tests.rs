@@ -330,3 +330,103 @@ mod tls {
         cert.as_x509().serial_number().to_bn().unwrap()
     }
 }
+
+mod commitment_key_ids {
+    use super::*;
+    use ic_crypto_internal_threshold_sig_ecdsa::{
+        EccPoint, EccScalar, PedersenCommitment, SimpleCommitment,
+    };
+
+    fn points() -> Vec<EccPoint> {
+        (1..=2)
+            .map(|n| {
+                EccPoint::mul_by_g(&EccScalar::from_u64(EccCurveType::K256, n))
+                    .expect("failed to compute point")
+            })
+            .collect()
+    }
+
+    fn key_id(hex: &str) -> CommitmentKeyId {
+        CommitmentKeyId(KeyId::from(hex_to_32_bytes(hex)))
+    }
+
+    /// This test checks that the functionality is consistent; the values are
+    /// not "correct" but they must never change, or the secret shares stored
+    /// under them can no longer be found.
+    #[test]
+    fn commitment_key_id_is_stable() {
+        assert_eq!(
+            commitment_key_id(&PolynomialCommitment::from(SimpleCommitment {
+                points: points()
+            })),
+            key_id("5f7a5350939792ad18bf5c73d43657f1a558c24abfd1e94cc5e331fe70acbb4c")
+        );
+        assert_eq!(
+            commitment_key_id(&PolynomialCommitment::from(PedersenCommitment {
+                points: points()
+            })),
+            key_id("e045fd85695c5031884393f7b8e156d04ccba760b87c6a3f84d396fc1065c713")
+        );
+    }
+
+    /// The legacy key ids must never change, or the secret shares stored by
+    /// earlier versions can no longer be migrated.
+    #[test]
+    fn legacy_commitment_key_id_is_stable() {
+        assert_eq!(
+            legacy_commitment_key_id(&PolynomialCommitment::from(SimpleCommitment {
+                points: points()
+            })),
+            key_id("faa53e8afbbd1847172b4b337758e39fc0be42339ed47b65809c9905a7cb64d7")
+        );
+        assert_eq!(
+            legacy_commitment_key_id(&PolynomialCommitment::from(PedersenCommitment {
+                points: points()
+            })),
+            key_id("d0c5fdead42bbb8832f8cc3c15e741fc51115040565dd798f93c2f341f857b41")
+        );
+    }
+}
+
+mod test_keygen {
+    use super::*;
+    use crate::keygen::test_keygen::TestKeygen;
+
+    #[test]
+    fn should_derive_same_keys_from_same_label() {
+        let (key_id_1, public_key_1, key_set_1) = TestKeygen::new("node").mega_key_pair();
+        let (key_id_2, public_key_2, key_set_2) = TestKeygen::new("node").mega_key_pair();
+
+        assert_eq!(key_id_1, key_id_2);
+        assert_eq!(public_key_1, public_key_2);
+        assert_eq!(key_set_1, key_set_2);
+        assert_eq!(key_id_1, mega_key_id(&public_key_1));
+    }
+
+    #[test]
+    fn should_derive_distinct_keys_from_distinct_labels() {
+        let keygen = TestKeygen::new("subnet");
+        let (key_id_1, _, _) = keygen.derive("node-1").mega_key_pair();
+        let (key_id_2, _, _) = keygen.derive("node-2").mega_key_pair();
+        let (key_id_3, _, _) = TestKeygen::new("node-1").mega_key_pair();
+        let (key_id_4, _, _) = keygen.mega_key_pair();
+
+        assert_ne!(key_id_1, key_id_2);
+        assert_ne!(key_id_1, key_id_3);
+        assert_ne!(key_id_1, key_id_4);
+    }
+
+    #[test]
+    fn should_derive_opening_of_commitment() {
+        let keygen = TestKeygen::new("transcript");
+
+        let (key_id, commitment, opening, _) = keygen.commitment_opening(3, 2);
+
+        assert_eq!(key_id, commitment_key_id(&commitment));
+        assert_eq!(commitment.check_opening(2, &opening), Ok(true));
+        assert_eq!(commitment.check_opening(1, &opening), Ok(false));
+        let (other_key_id, other_commitment, _, _) = keygen.commitment_opening(3, 1);
+        assert_eq!(other_key_id, key_id);
+        assert_eq!(other_commitment, commitment);
+    }
+}
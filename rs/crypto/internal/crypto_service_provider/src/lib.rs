@@ -17,8 +17,12 @@ pub mod types;
 mod vault;
 
 pub use crate::vault::api::TlsHandshakeCspVault;
-pub use crate::vault::local_csp_vault::LocalCspVault;
+pub use crate::vault::local_csp_vault::{LocalCspVault, LocalCspVaultBuilder};
+pub use crate::vault::read_only_csp_vault::ReadOnlyCspVault;
 pub use crate::vault::remote_csp_vault::run_csp_vault_server;
+pub use crate::vault::remote_csp_vault::{
+    ArgumentShape, RemoteCspVault, RemoteCspVaultError, SlowCallRecord, SlowCallTracerConfig,
+};
 
 use crate::api::{
     CspIDkgProtocol, CspKeyGenerator, CspSecretKeyStoreChecker, CspSigner,
@@ -26,16 +30,18 @@ use crate::api::{
     CspTlsHandshakeSignerProvider, CspTlsServerHandshake, NiDkgCspClient, NodePublicKeyData,
     ThresholdSignatureCspClient,
 };
+use crate::canister_threshold::secret_not_found::SecretNotFoundTracker;
 use crate::keygen::{forward_secure_key_id, public_key_hash_as_key_id};
 use crate::public_key_store::read_node_public_keys;
 use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
 use crate::secret_key_store::SecretKeyStore;
 use crate::types::CspPublicKey;
 use crate::vault::api::CspVault;
+use crate::vault::dual_csp_vault::DualCspVault;
 use ic_config::crypto::CryptoConfig;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_types::encrypt::forward_secure::CspFsEncryptionPublicKey;
-use ic_logger::{new_logger, replica_logger::no_op_logger, ReplicaLogger};
+use ic_logger::{new_logger, replica_logger::no_op_logger, warn, ReplicaLogger};
 use ic_protobuf::crypto::v1::NodePublicKeys;
 use ic_types::crypto::KeyId;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
@@ -43,8 +49,9 @@ use rand::rngs::OsRng;
 use rand::{CryptoRng, Rng};
 use secret_key_store::proto_store::ProtoSecretKeyStore;
 use std::convert::TryFrom;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 const SKS_DATA_FILENAME: &str = "sks_data.pb";
 const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
@@ -133,11 +140,17 @@ pub struct Csp<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKey
     csp_vault: Arc<dyn CspVault>,
     public_key_data: PublicKeyData,
     logger: ReplicaLogger,
+    secret_not_found_tracker: SecretNotFoundTracker,
+    metrics: Arc<CryptoMetrics>,
     // TODO(CRP-1325): remove S, C generics.
     _marker: std::marker::PhantomData<(S, C)>,
 }
 
 /// This lock provides the option to add metrics about lock acquisition times.
+///
+/// The lock is a `parking_lot` lock, which is not poisoned if a thread panics
+/// while holding it: the lock is released during unwinding, so a single failed
+/// thread does not prevent all other threads from using the guarded value.
 struct CspRwLock<T> {
     name: String,
     rw_lock: RwLock<T>,
@@ -195,42 +208,124 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
     fn rng_write_lock(&self) -> RwLockWriteGuard<'_, R> {
         self.csprng.write()
     }
+
+    /// Returns a handle on the vault of this CSP that can not change the key
+    /// material, for components that must never mutate it, such as the
+    /// checks of the secret key store and the verification of dealings.
+    pub fn read_only_vault(&self) -> ReadOnlyCspVault {
+        ReadOnlyCspVault::new(Arc::clone(&self.csp_vault))
+    }
 }
 
 impl Csp<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore> {
     /// Creates a production-grade crypto service provider.
+    ///
+    /// If the `dual_vault_socket_path` of the `config` is set, the local vault
+    /// is validated against the remote vault listening there (see
+    /// [`Self::new_with_dual_vault`]). If the remote vault can not be reached,
+    /// only the local vault is used.
     pub fn new(
         config: &CryptoConfig,
         logger: Option<ReplicaLogger>,
         metrics: Arc<CryptoMetrics>,
     ) -> Self {
         let logger = logger.unwrap_or_else(no_op_logger);
-        let secret_key_store = ProtoSecretKeyStore::open(
+        if let Some(socket_path) = &config.dual_vault_socket_path {
+            match Self::new_with_dual_vault(
+                config,
+                socket_path,
+                Some(new_logger!(&logger)),
+                Arc::clone(&metrics),
+            ) {
+                Ok(csp) => return csp,
+                Err(e) => warn!(
+                    logger,
+                    "Failed to connect to the remote vault at {}, using the local vault only: {:?}",
+                    socket_path.display(),
+                    e
+                ),
+            }
+        }
+        let local_vault = Self::local_csp_vault(config, &logger, Arc::clone(&metrics));
+        Self::new_with_vault(config, local_vault, logger, metrics)
+    }
+
+    /// Creates a production-grade crypto service provider whose vault calls
+    /// are served by the local vault, and additionally sent to the remote
+    /// vault listening at `remote_vault_socket_path` to compare the results of
+    /// both vaults (see [`DualCspVault`]).
+    pub fn new_with_dual_vault(
+        config: &CryptoConfig,
+        remote_vault_socket_path: &Path,
+        logger: Option<ReplicaLogger>,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Result<Self, RemoteCspVaultError> {
+        let logger = logger.unwrap_or_else(no_op_logger);
+        let remote_vault = RemoteCspVault::new(remote_vault_socket_path)?;
+        let local_vault = Self::local_csp_vault(config, &logger, Arc::clone(&metrics));
+        let dual_vault = DualCspVault::new(
+            local_vault,
+            Arc::new(remote_vault),
+            new_logger!(&logger),
+            Arc::clone(&metrics),
+        );
+        Ok(Self::new_with_vault(
+            config,
+            Arc::new(dual_vault),
+            logger,
+            metrics,
+        ))
+    }
+
+    fn local_csp_vault(
+        config: &CryptoConfig,
+        logger: &ReplicaLogger,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Arc<dyn CspVault> {
+        let secret_key_store = ProtoSecretKeyStore::open_with_metrics(
             &config.crypto_root,
             SKS_DATA_FILENAME,
             Some(new_logger!(&logger)),
+            Arc::clone(&metrics),
+            Duration::from_millis(config.secret_key_store_load_budget_millis),
         );
-        let canister_key_store = ProtoSecretKeyStore::open(
+        let canister_key_store = ProtoSecretKeyStore::open_with_metrics(
             &config.crypto_root,
             CANISTER_SKS_DATA_FILENAME,
             Some(new_logger!(&logger)),
+            Arc::clone(&metrics),
+            Duration::from_millis(config.secret_key_store_load_budget_millis),
         );
+        Arc::new(
+            LocalCspVault::builder(secret_key_store, canister_key_store)
+                .with_metrics(metrics)
+                .with_logger(new_logger!(logger))
+                .build(),
+        )
+    }
+
+    fn new_with_vault(
+        config: &CryptoConfig,
+        csp_vault: Arc<dyn CspVault>,
+        logger: ReplicaLogger,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Self {
         let node_public_keys = match read_node_public_keys(&config.crypto_root) {
             Ok(node_pks) => node_pks,
             Err(_) => Default::default(),
         };
         let public_key_data = PublicKeyData::new(node_public_keys);
 
+        let secret_not_found_tracker =
+            SecretNotFoundTracker::new(new_logger!(&logger), Arc::clone(&metrics));
+
         Csp {
             csprng: CspRwLock::new_for_rng(OsRng::default(), Arc::clone(&metrics)),
             public_key_data,
-            csp_vault: Arc::new(LocalCspVault::new(
-                secret_key_store,
-                canister_key_store,
-                metrics,
-                new_logger!(&logger),
-            )),
+            csp_vault,
             logger,
+            secret_not_found_tracker,
+            metrics,
             _marker: std::marker::PhantomData,
         }
     }
@@ -249,14 +344,20 @@ impl<R: 'static + Rng + CryptoRng + Send + Sync + Clone>
             Err(_) => Default::default(),
         };
         let public_key_data = PublicKeyData::new(node_public_keys);
+        let metrics = Arc::new(CryptoMetrics::none());
         Csp {
-            csprng: CspRwLock::new_for_rng(csprng.clone(), Arc::new(CryptoMetrics::none())),
+            csprng: CspRwLock::new_for_rng(csprng.clone(), Arc::clone(&metrics)),
             public_key_data,
             csp_vault: Arc::new(LocalCspVault::new_for_test(
                 csprng,
                 ProtoSecretKeyStore::open(&config.crypto_root, SKS_DATA_FILENAME, None),
             )),
             logger: no_op_logger(),
+            secret_not_found_tracker: SecretNotFoundTracker::new(
+                no_op_logger(),
+                Arc::clone(&metrics),
+            ),
+            metrics,
             _marker: std::marker::PhantomData,
         }
     }
@@ -307,10 +408,15 @@ impl<R: 'static + Rng + CryptoRng + Send + Sync + Clone, S: 'static + SecretKeyS
         let public_key_data = PublicKeyData::new(node_public_keys);
         let metrics = Arc::new(CryptoMetrics::none());
         Csp {
-            csprng: CspRwLock::new_for_rng(csprng.clone(), metrics),
+            csprng: CspRwLock::new_for_rng(csprng.clone(), Arc::clone(&metrics)),
             public_key_data,
             csp_vault: Arc::new(LocalCspVault::new_for_test(csprng, secret_key_store)),
             logger: no_op_logger(),
+            secret_not_found_tracker: SecretNotFoundTracker::new(
+                no_op_logger(),
+                Arc::clone(&metrics),
+            ),
+            metrics,
             _marker: std::marker::PhantomData,
         }
     }
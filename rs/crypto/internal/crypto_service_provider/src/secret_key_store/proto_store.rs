@@ -4,34 +4,49 @@ use crate::secret_key_store::{Scope, SecretKeyStore, SecretKeyStoreError};
 use crate::threshold::ni_dkg::{NIDKG_FS_SCOPE, NIDKG_THRESHOLD_SCOPE};
 use crate::types::CspSecretKey;
 use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::groth20_bls12_381::types::convert_keyset_to_keyset_with_pop;
 use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::types::CspFsEncryptionKeySet;
-use ic_logger::{info, replica_logger::no_op_logger, ReplicaLogger};
+use ic_logger::{info, replica_logger::no_op_logger, warn, ReplicaLogger};
 use ic_types::crypto::KeyId;
+use nix::fcntl::{flock, FlockArg};
 use parking_lot::RwLock;
 use prost::Message;
 use std::borrow::{Borrow, BorrowMut};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::fmt;
 use std::fs;
 use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 const CURRENT_SKS_VERSION: u32 = 2;
 
+/// The time that loading a store is expected to take at most, unless another
+/// budget is given when opening it.
+const DEFAULT_LOAD_BUDGET: Duration = Duration::from_secs(5);
+
 // TODO(CRP-523): turn this to FromStr-trait once KeyId is not public.
 const KEY_ID_PREFIX: &str = "KeyId(0x";
 const KEY_ID_SUFFIX: &str = ")";
 fn key_id_from_display_string(s: &str) -> KeyId {
+    try_key_id_from_display_string(s).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn try_key_id_from_display_string(s: &str) -> Result<KeyId, String> {
     if s.starts_with(KEY_ID_PREFIX) && s.ends_with(KEY_ID_SUFFIX) {
         let key_id_hex = s
             .get(KEY_ID_PREFIX.len()..s.len() - KEY_ID_SUFFIX.len())
-            .unwrap_or_else(|| panic!("Invalid display string for KeyId: {}", s));
-        key_id_from_hex(key_id_hex)
+            .ok_or_else(|| format!("Invalid display string for KeyId: {}", s))?;
+        try_key_id_from_hex(key_id_hex)
     } else {
-        panic!("Invalid display string for KeyId: {}", s)
+        Err(format!("Invalid display string for KeyId: {}", s))
     }
 }
 
@@ -40,12 +55,16 @@ fn key_id_to_hex(key_id: &KeyId) -> String {
 }
 
 fn key_id_from_hex(key_id_hex: &str) -> KeyId {
+    try_key_id_from_hex(key_id_hex).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn try_key_id_from_hex(key_id_hex: &str) -> Result<KeyId, String> {
     let parsed = hex::decode(key_id_hex)
-        .unwrap_or_else(|e| panic!("Error parsing hex KeyId {}: {}", key_id_hex, e));
+        .map_err(|e| format!("Error parsing hex KeyId {}: {}", key_id_hex, e))?;
     let bytes: [u8; 32] = parsed[..]
         .try_into()
-        .unwrap_or_else(|_| panic!("KeyId {} should have 32 bytes", key_id_hex));
-    KeyId::from(bytes)
+        .map_err(|_| format!("KeyId {} should have 32 bytes", key_id_hex))?;
+    Ok(KeyId::from(bytes))
 }
 
 /// The secret key store protobuf definitions
@@ -60,25 +79,179 @@ type SecretKeys = HashMap<KeyId, (CspSecretKey, Option<Scope>)>;
 /// serialization
 pub struct ProtoSecretKeyStore {
     proto_file: PathBuf,
-    keys: Arc<RwLock<SecretKeys>>,
+    keys: Arc<RwLock<CachedSecretKeys>>,
     logger: ReplicaLogger,
+    metrics: Arc<CryptoMetrics>,
+    // The compaction of the file started when the store was opened, if any.
+    compaction: Option<thread::JoinHandle<()>>,
+}
+
+/// The state of an SKS file when a store last loaded or wrote it. Files are
+/// replaced on every write, so a write by another store changes the stamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SksFileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    inode: u64,
+}
+
+impl SksFileStamp {
+    /// Returns the stamp of `proto_file`, or `None` if it does not exist.
+    fn of(proto_file: &Path) -> Option<Self> {
+        fs::metadata(proto_file).ok().map(|metadata| SksFileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            inode: metadata.ino(),
+        })
+    }
+}
+
+/// The keys of a store, as of the `file_stamp` of its file.
+struct CachedSecretKeys {
+    keys: SecretKeys,
+    file_stamp: Option<SksFileStamp>,
+    // The number of keys by key type. Key types are kept once their last key
+    // is deleted, so that their exported counts drop to zero.
+    key_counts: BTreeMap<&'static str, usize>,
+}
+
+impl CachedSecretKeys {
+    fn new(keys: SecretKeys, file_stamp: Option<SksFileStamp>) -> Self {
+        let mut cached = CachedSecretKeys {
+            keys,
+            file_stamp,
+            key_counts: BTreeMap::new(),
+        };
+        cached.recount();
+        cached
+    }
+
+    /// Returns whether the file changed since the keys were loaded or written.
+    fn is_stale(&self, proto_file: &Path) -> bool {
+        SksFileStamp::of(proto_file) != self.file_stamp
+    }
+
+    fn recount(&mut self) {
+        self.key_counts.values_mut().for_each(|count| *count = 0);
+        for (csp_key, _) in self.keys.values() {
+            *self.key_counts.entry(csp_key.into()).or_insert(0) += 1;
+        }
+    }
+
+    fn insert(&mut self, id: KeyId, csp_key: CspSecretKey, scope: Option<Scope>) {
+        *self.key_counts.entry((&csp_key).into()).or_insert(0) += 1;
+        self.keys.insert(id, (csp_key, scope));
+    }
+
+    fn remove(&mut self, id: &KeyId) -> bool {
+        match self.keys.remove(id) {
+            Some((csp_key, _)) => {
+                if let Some(count) = self.key_counts.get_mut(<&'static str>::from(&csp_key)) {
+                    *count = count.saturating_sub(1);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// An exclusive lock of the file of a store, which is released when dropped.
+///
+/// Stores that are opened on the same file, e.g. by the replica and by the
+/// orchestrator, hold the lock while they reload the file and write it, so
+/// that none of them overwrites the keys that another one inserted.
+struct SksFileLock(fs::File);
+
+impl SksFileLock {
+    fn acquire(proto_file: &Path) -> Self {
+        let mut lock_file = proto_file.as_os_str().to_owned();
+        lock_file.push(".lock");
+        let lock_file = PathBuf::from(lock_file);
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&lock_file)
+            .unwrap_or_else(|e| {
+                panic!("Error opening SKS lock file {}: {}", lock_file.display(), e)
+            });
+        flock(file.as_raw_fd(), FlockArg::LockExclusive).unwrap_or_else(|e| {
+            panic!("Error locking SKS lock file {}: {}", lock_file.display(), e)
+        });
+        SksFileLock(file)
+    }
+}
+
+/// The keys read from an SKS file.
+struct LoadedSecretKeys {
+    keys: SecretKeys,
+    /// Whether the file is larger than the encoding of the keys in the
+    /// current version, e.g. because it is in a legacy version.
+    needs_compaction: bool,
 }
 
 impl ProtoSecretKeyStore {
     /// Creates a database instance.
     pub fn open(dir: &Path, file_name: &str, logger: Option<ReplicaLogger>) -> Self {
+        Self::open_with_metrics(
+            dir,
+            file_name,
+            logger,
+            Arc::new(CryptoMetrics::none()),
+            DEFAULT_LOAD_BUDGET,
+        )
+    }
+
+    /// Creates a database instance that exports the number of keys it holds,
+    /// by key type, the size of its file and the time it took to load to
+    /// `metrics`. A warning is logged if loading the keys takes longer than
+    /// `load_budget`.
+    ///
+    /// If the file is larger than the encoding of its keys, it is rewritten
+    /// in a background thread, which the store waits for when dropped.
+    pub fn open_with_metrics(
+        dir: &Path,
+        file_name: &str,
+        logger: Option<ReplicaLogger>,
+        metrics: Arc<CryptoMetrics>,
+        load_budget: Duration,
+    ) -> Self {
         CryptoConfig::check_dir_has_required_permissions(dir)
             .expect("wrong crypto root permissions");
         let proto_file = dir.join(file_name);
-        let secret_keys = match Self::read_sks_data_from_disk(&proto_file) {
-            Some(sks_proto) => sks_proto,
-            None => SecretKeys::new(),
-        };
-        ProtoSecretKeyStore {
+        let start_time = Instant::now();
+        let file_stamp = SksFileStamp::of(&proto_file);
+        let loaded = Self::read_sks_data_from_disk(&proto_file);
+        let load_duration = start_time.elapsed();
+        let needs_compaction = loaded
+            .as_ref()
+            .map_or(false, |loaded| loaded.needs_compaction);
+        let keys = loaded.map(|loaded| loaded.keys).unwrap_or_default();
+        let mut store = ProtoSecretKeyStore {
             proto_file,
-            keys: Arc::new(RwLock::new(secret_keys)),
+            keys: Arc::new(RwLock::new(CachedSecretKeys::new(keys, file_stamp))),
             logger: logger.unwrap_or_else(no_op_logger),
+            metrics,
+            compaction: None,
+        };
+        let store_name = store.store_name();
+        store
+            .metrics
+            .set_secret_key_store_load_duration(&store_name, load_duration);
+        if load_duration > load_budget {
+            warn!(
+                store.logger,
+                "Loading the secret key store {} took {:?}, more than the budget of {:?}",
+                store.proto_file.display(),
+                load_duration,
+                load_budget
+            );
+        }
+        if needs_compaction {
+            store.compaction = Some(store.compact_in_background());
         }
+        store.observe_key_counts(&store.keys.read());
+        store
     }
 
     /// Returns the path to the protobuf file storing the keys.
@@ -86,12 +259,111 @@ impl ProtoSecretKeyStore {
         self.proto_file.as_path()
     }
 
-    fn read_sks_data_from_disk(sks_data_file: &Path) -> Option<SecretKeys> {
+    /// The name of the store in the metrics: the file name of the store.
+    fn store_name(&self) -> String {
+        self.proto_file
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Rewrites the file of the store with the current encoding of its keys
+    /// in a background thread.
+    fn compact_in_background(&self) -> thread::JoinHandle<()> {
+        let keys = Arc::clone(&self.keys);
+        let proto_file = self.proto_file.clone();
+        let store_name = self.store_name();
+        let logger = self.logger.clone();
+        let metrics = Arc::clone(&self.metrics);
+        thread::spawn(move || {
+            // The locks exclude concurrent writes of the file by this store and
+            // by other stores opened on the same file.
+            with_write_lock(&keys, |cached| {
+                let _lock = ProtoSecretKeyStore::lock_and_reload(&proto_file, cached);
+                ProtoSecretKeyStore::write_and_stamp(&proto_file, cached);
+                Ok(())
+            })
+            .expect("lambda unexpectedly returned Err");
+            metrics.inc_secret_key_store_compactions(&store_name);
+            if let Ok(metadata) = fs::metadata(&proto_file) {
+                metrics.set_secret_key_store_file_size(&store_name, metadata.len());
+            }
+            info!(
+                logger,
+                "Compacted the secret key store {}",
+                proto_file.display()
+            );
+        })
+    }
+
+    /// Exports the number of keys held by the store, by key type, and the size
+    /// of its file.
+    fn observe_key_counts(&self, cached: &CachedSecretKeys) {
+        let store = self.store_name();
+        for (key_type, count) in &cached.key_counts {
+            self.metrics
+                .set_secret_key_store_key_count(&store, key_type, *count);
+        }
+        if let Some(file_stamp) = cached.file_stamp {
+            self.metrics
+                .set_secret_key_store_file_size(&store, file_stamp.len);
+        }
+    }
+
+    /// Locks the file of the store and, if another store opened on the same
+    /// file wrote it since this store loaded or wrote it, replaces the cached
+    /// keys with the keys in the file, so that writing them while the returned
+    /// lock is held does not drop keys that the other store inserted.
+    fn lock_and_reload(proto_file: &Path, cached: &mut CachedSecretKeys) -> SksFileLock {
+        let lock = SksFileLock::acquire(proto_file);
+        if cached.is_stale(proto_file) {
+            // The stamp is taken before reading, so that a later write is
+            // never mistaken for the one that was read.
+            cached.file_stamp = SksFileStamp::of(proto_file);
+            if let Some(loaded) = ProtoSecretKeyStore::read_sks_data_from_disk(proto_file) {
+                cached.keys = loaded.keys;
+            }
+            cached.recount();
+        }
+        lock
+    }
+
+    /// Writes the cached keys to the file of the store and stamps them with
+    /// the written file. Must be called while holding the lock of the file.
+    fn write_and_stamp(proto_file: &Path, cached: &mut CachedSecretKeys) {
+        ProtoSecretKeyStore::write_secret_keys_to_disk(proto_file, &cached.keys);
+        cached.file_stamp = SksFileStamp::of(proto_file);
+    }
+
+    /// Looks up the key with the given `id` in the file of the store, in case
+    /// another store opened on the same file inserted it. The file is only
+    /// read if it changed since this store loaded or wrote it.
+    fn reload_and_get(&self, id: &KeyId) -> Option<CspSecretKey> {
+        if !self.keys.read().is_stale(&self.proto_file) {
+            return None;
+        }
+        with_write_lock(&self.keys, |cached| {
+            let _lock = ProtoSecretKeyStore::lock_and_reload(&self.proto_file, cached);
+            self.observe_key_counts(cached);
+            Ok(cached.keys.get(id).map(|(csp_key, _)| csp_key.to_owned()))
+        })
+        .expect("lambda unexpectedly returned Err")
+    }
+
+    fn read_sks_data_from_disk(sks_data_file: &Path) -> Option<LoadedSecretKeys> {
         match fs::read(sks_data_file) {
             Ok(data) => {
                 let sks_pb = pb::SecretKeyStore::decode(&*data).expect("error parsing SKS data");
+                // Decoding drops unknown fields and all but the last of the
+                // entries with the same key ID, so the decoded protobuf is
+                // only smaller than the file if the file holds such leftovers.
+                let needs_compaction =
+                    sks_pb.version != CURRENT_SKS_VERSION || sks_pb.encoded_len() < data.len();
                 let keys = ProtoSecretKeyStore::migrate_to_current_version(sks_pb);
-                Some(keys)
+                Some(LoadedSecretKeys {
+                    keys,
+                    needs_compaction,
+                })
             }
             Err(err) => {
                 if err.kind() == ErrorKind::NotFound {
@@ -227,6 +499,16 @@ impl ProtoSecretKeyStore {
     }
 }
 
+impl Drop for ProtoSecretKeyStore {
+    fn drop(&mut self) {
+        // Another store opened on the same file must not have its writes
+        // overwritten by a late compaction.
+        if let Some(compaction) = self.compaction.take() {
+            let _ = compaction.join();
+        }
+    }
+}
+
 impl SecretKeyStore for ProtoSecretKeyStore {
     fn insert(
         &mut self,
@@ -234,20 +516,25 @@ impl SecretKeyStore for ProtoSecretKeyStore {
         key: CspSecretKey,
         scope: Option<Scope>,
     ) -> Result<(), SecretKeyStoreError> {
-        with_write_lock(&self.keys, |keys| match keys.get(&id) {
-            Some(_) => Err(SecretKeyStoreError::DuplicateKeyId(id)),
-            None => {
-                keys.insert(id, (key, scope));
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
-                Ok(())
+        with_write_lock(&self.keys, |cached| {
+            let _lock = ProtoSecretKeyStore::lock_and_reload(&self.proto_file, cached);
+            match cached.keys.get(&id) {
+                Some(_) => Err(SecretKeyStoreError::DuplicateKeyId(id)),
+                None => {
+                    cached.insert(id, key, scope);
+                    ProtoSecretKeyStore::write_and_stamp(&self.proto_file, cached);
+                    self.observe_key_counts(cached);
+                    Ok(())
+                }
             }
         })
     }
 
     fn get(&self, id: &KeyId) -> Option<CspSecretKey> {
-        with_read_lock(&self.keys, |keys| {
-            keys.get(id).map(|(csp_key, _)| csp_key.to_owned())
+        with_read_lock(&self.keys, |cached| {
+            cached.keys.get(id).map(|(csp_key, _)| csp_key.to_owned())
         })
+        .or_else(|| self.reload_and_get(id))
     }
 
     fn contains(&self, id: &KeyId) -> bool {
@@ -255,13 +542,15 @@ impl SecretKeyStore for ProtoSecretKeyStore {
     }
 
     fn remove(&mut self, id: &KeyId) -> bool {
-        let result = with_write_lock(&self.keys, |keys| match keys.get(id) {
-            Some(_) => {
-                keys.remove(id);
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
+        let result = with_write_lock(&self.keys, |cached| {
+            let _lock = ProtoSecretKeyStore::lock_and_reload(&self.proto_file, cached);
+            if cached.remove(id) {
+                ProtoSecretKeyStore::write_and_stamp(&self.proto_file, cached);
+                self.observe_key_counts(cached);
                 Ok(true)
+            } else {
+                Ok(false)
             }
-            None => Ok(false),
         });
         result.expect("lambda unexpectedly returned Err")
     }
@@ -270,29 +559,162 @@ impl SecretKeyStore for ProtoSecretKeyStore {
     where
         F: Fn(&KeyId, &CspSecretKey) -> bool,
     {
-        with_write_lock(&self.keys, |keys| {
-            let mut all_keys = SecretKeys::new();
-            core::mem::swap(&mut all_keys, keys);
-            let orig_keys_count = all_keys.len();
-            for (key_id, (csp_key, maybe_scope)) in all_keys.drain() {
-                if maybe_scope != Some(scope) || filter(&key_id, &csp_key) {
-                    keys.insert(key_id, (csp_key, maybe_scope));
-                } else {
-                    info!(
-                        self.logger,
-                        "Deleting key with ID {} with scope {}", key_id, scope
-                    );
-                }
+        with_write_lock(&self.keys, |cached| {
+            let _lock = ProtoSecretKeyStore::lock_and_reload(&self.proto_file, cached);
+            let deleted_key_ids: Vec<KeyId> = cached
+                .keys
+                .iter()
+                .filter(|(key_id, (csp_key, maybe_scope))| {
+                    *maybe_scope == Some(scope) && !filter(key_id, csp_key)
+                })
+                .map(|(key_id, _)| *key_id)
+                .collect();
+            for key_id in &deleted_key_ids {
+                info!(
+                    self.logger,
+                    "Deleting key with ID {} with scope {}", key_id, scope
+                );
+                cached.remove(key_id);
             }
-            if keys.len() < orig_keys_count {
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
+            if !deleted_key_ids.is_empty() {
+                ProtoSecretKeyStore::write_and_stamp(&self.proto_file, cached);
             }
+            self.observe_key_counts(cached);
             Ok(())
         })
         .unwrap_or_else(|e| panic!("retain failed for scope {} with error {}", scope, e));
     }
 }
 
+/// The summary of a key stored in an SKS file, as reported by
+/// [`inspect_sks_file`]. It never contains secret key material.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SksEntrySummary {
+    /// The ID of the key, as stored in the file.
+    pub key_id: String,
+    /// The variant of the key, or `None` if the key can not be decoded.
+    pub variant: Option<&'static str>,
+    /// The scope of the key, as stored in the file, if any.
+    pub scope: Option<String>,
+    /// The size of the encoded key in bytes.
+    pub size: usize,
+    /// The problems found with the encoding of the entry.
+    pub errors: Vec<String>,
+}
+
+impl SksEntrySummary {
+    /// Returns whether the entry is encoded correctly.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// The summary of an SKS file, as reported by [`inspect_sks_file`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SksFileSummary {
+    /// The version of the format of the file.
+    pub version: u32,
+    /// The keys stored in the file, ordered by key ID.
+    pub entries: Vec<SksEntrySummary>,
+}
+
+/// Errors returned when an SKS file can not be inspected at all.
+#[derive(Debug)]
+pub enum SksInspectionError {
+    /// The file can not be read.
+    Io(std::io::Error),
+    /// The file is no valid SKS protobuf.
+    MalformedProto(prost::DecodeError),
+    /// The version of the file is not supported.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SksInspectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SksInspectionError::Io(e) => write!(f, "Error reading SKS data: {}", e),
+            SksInspectionError::MalformedProto(e) => write!(f, "Error parsing SKS data: {}", e),
+            SksInspectionError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported SecretKeyStore-proto version: {}", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SksInspectionError {}
+
+/// Reads the SKS file at `path` and summarizes the keys it stores, validating
+/// their encoding, without modifying the file.
+///
+/// Unlike [`ProtoSecretKeyStore::open`], this neither checks the permissions
+/// of the directory nor panics on malformed entries, so that it can be used to
+/// investigate broken stores. Malformed entries are reported in the
+/// [errors](SksEntrySummary::errors) of their summary.
+pub fn inspect_sks_file(path: &Path) -> Result<SksFileSummary, SksInspectionError> {
+    let data = fs::read(path).map_err(SksInspectionError::Io)?;
+    let sks_proto =
+        pb::SecretKeyStore::decode(&*data).map_err(SksInspectionError::MalformedProto)?;
+    let mut entries: Vec<SksEntrySummary> = match sks_proto.version {
+        0 => sks_proto
+            .key_id_to_csp_secret_key
+            .iter()
+            .map(|(key_id_string, key_bytes)| {
+                let errors = try_key_id_from_display_string(key_id_string)
+                    .err()
+                    .into_iter()
+                    .collect();
+                summarize_entry(key_id_string, key_bytes, None, errors)
+            })
+            .collect(),
+        1 | CURRENT_SKS_VERSION => sks_proto
+            .key_id_to_secret_key_v1
+            .iter()
+            .map(|(key_id_hex, sk_proto)| {
+                let mut errors: Vec<String> =
+                    try_key_id_from_hex(key_id_hex).err().into_iter().collect();
+                let scope = if sk_proto.scope.is_empty() {
+                    None
+                } else {
+                    if Scope::from_str(&sk_proto.scope).is_err() {
+                        errors.push(format!("Unknown scope: {}", sk_proto.scope));
+                    }
+                    Some(sk_proto.scope.clone())
+                };
+                summarize_entry(key_id_hex, &sk_proto.csp_secret_key, scope, errors)
+            })
+            .collect(),
+        version => return Err(SksInspectionError::UnsupportedVersion(version)),
+    };
+    entries.sort_by(|a, b| a.key_id.cmp(&b.key_id));
+    Ok(SksFileSummary {
+        version: sks_proto.version,
+        entries,
+    })
+}
+
+fn summarize_entry(
+    key_id: &str,
+    key_bytes: &[u8],
+    scope: Option<String>,
+    mut errors: Vec<String>,
+) -> SksEntrySummary {
+    // The decoded key is zeroized when dropped.
+    let variant = match serde_cbor::from_slice::<CspSecretKey>(key_bytes) {
+        Ok(csp_key) => Some((&csp_key).into()),
+        Err(e) => {
+            errors.push(format!("Error deserializing key: {}", e));
+            None
+        }
+    };
+    SksEntrySummary {
+        key_id: key_id.to_string(),
+        variant,
+        scope,
+        size: key_bytes.len(),
+        errors,
+    }
+}
+
 fn with_write_lock<T, I, R, F>(v: T, f: F) -> Result<R, SecretKeyStoreError>
 where
     T: AsRef<RwLock<I>>,
@@ -315,8 +737,10 @@ where
 pub mod tests {
     use super::super::test_utils;
     use super::*;
+    use crate::keygen::test_keygen::TestKeygen;
     use crate::secret_key_store::test_utils::TempSecretKeyStore;
     use ic_crypto_internal_csp_test_utils::files::mk_temp_dir_with_permissions;
+    use ic_metrics::MetricsRegistry;
     use proptest::prelude::*;
     use tempfile::tempdir as tempdir_deleted_at_end_of_scope;
 
@@ -384,6 +808,354 @@ pub mod tests {
         test_utils::should_retain_expected_keys(proto_key_store());
     }
 
+    #[test]
+    fn should_export_key_counts_by_key_type() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let registry = MetricsRegistry::new();
+        let metrics = Arc::new(CryptoMetrics::new(Some(&registry)));
+        let mut store = ProtoSecretKeyStore::open_with_metrics(
+            dir.path(),
+            "sks_data.pb",
+            None,
+            Arc::clone(&metrics),
+            DEFAULT_LOAD_BUDGET,
+        );
+        let (mega_key_id, _, mega_key_set) = TestKeygen::new("sks").mega_key_pair();
+        store.insert(mega_key_id, mega_key_set, None).unwrap();
+        store
+            .insert(
+                test_utils::make_key_id(1),
+                test_utils::make_secret_key(1),
+                None,
+            )
+            .unwrap();
+        store
+            .insert(
+                test_utils::make_key_id(2),
+                test_utils::make_secret_key(2),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(key_count(&registry, "MEGaEncryptionK256"), Some(1));
+        assert_eq!(key_count(&registry, "Ed25519"), Some(2));
+
+        assert!(store.remove(&mega_key_id));
+        assert!(store.remove(&test_utils::make_key_id(1)));
+
+        assert_eq!(key_count(&registry, "MEGaEncryptionK256"), Some(0));
+        assert_eq!(key_count(&registry, "Ed25519"), Some(1));
+
+        // The counts of the keys already on disk are exported when reopening.
+        let reopened_registry = MetricsRegistry::new();
+        let _reopened_store = ProtoSecretKeyStore::open_with_metrics(
+            dir.path(),
+            "sks_data.pb",
+            None,
+            Arc::new(CryptoMetrics::new(Some(&reopened_registry))),
+            DEFAULT_LOAD_BUDGET,
+        );
+        assert_eq!(key_count(&reopened_registry, "Ed25519"), Some(1));
+        assert_eq!(key_count(&reopened_registry, "MEGaEncryptionK256"), None);
+    }
+
+    #[test]
+    fn should_export_file_size_and_load_duration() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let registry = MetricsRegistry::new();
+        let mut store = ProtoSecretKeyStore::open_with_metrics(
+            dir.path(),
+            "sks_data.pb",
+            None,
+            Arc::new(CryptoMetrics::new(Some(&registry))),
+            DEFAULT_LOAD_BUDGET,
+        );
+        assert!(gauge(
+            &registry,
+            "ic_crypto_secret_key_store_load_duration_seconds"
+        )
+        .is_some());
+        assert_eq!(
+            gauge(&registry, "ic_crypto_secret_key_store_file_size_bytes"),
+            None
+        );
+
+        store
+            .insert(
+                test_utils::make_key_id(1),
+                test_utils::make_secret_key(1),
+                None,
+            )
+            .unwrap();
+
+        let file_size = fs::metadata(store.proto_file_path()).unwrap().len();
+        assert_eq!(
+            gauge(&registry, "ic_crypto_secret_key_store_file_size_bytes"),
+            Some(file_size as f64)
+        );
+    }
+
+    #[test]
+    fn should_compact_sks_file_of_legacy_version() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let file = dir.path().join("sks_data.pb");
+        let key_id = test_utils::make_key_id(1);
+        let mut sks_proto = pb::SecretKeyStore {
+            version: 1,
+            ..Default::default()
+        };
+        sks_proto.key_id_to_secret_key_v1.insert(
+            key_id_to_hex(&key_id),
+            pb::SecretKeyV1 {
+                csp_secret_key: serde_cbor::to_vec(&test_utils::make_secret_key(1)).unwrap(),
+                scope: String::new(),
+            },
+        );
+        ic_utils::fs::write_protobuf_using_tmp_file(&file, &sks_proto).unwrap();
+        let registry = MetricsRegistry::new();
+
+        let store = ProtoSecretKeyStore::open_with_metrics(
+            dir.path(),
+            "sks_data.pb",
+            None,
+            Arc::new(CryptoMetrics::new(Some(&registry))),
+            DEFAULT_LOAD_BUDGET,
+        );
+        assert!(store.contains(&key_id));
+        // Dropping the store waits for the compaction.
+        drop(store);
+
+        assert_eq!(
+            counter(&registry, "ic_crypto_secret_key_store_compactions_total"),
+            Some(1.0)
+        );
+        let summary = inspect_sks_file(&file).unwrap();
+        assert_eq!(summary.version, CURRENT_SKS_VERSION);
+        let reopened_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        assert!(reopened_store.contains(&key_id));
+        assert!(reopened_store.compaction.is_none());
+    }
+
+    #[test]
+    fn should_compact_sks_file_with_unknown_fields() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let mut store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        let key_id = test_utils::make_key_id(1);
+        store
+            .insert(key_id, test_utils::make_secret_key(1), None)
+            .unwrap();
+        assert!(store.compaction.is_none());
+        let file = store.proto_file_path().to_path_buf();
+        drop(store);
+        let compact_size = fs::metadata(&file).unwrap().len();
+        // Field 15 with wire type 2 (length-delimited), holding 3 bytes.
+        let mut data = fs::read(&file).unwrap();
+        data.extend_from_slice(&[0x7a, 3, 1, 2, 3]);
+        fs::write(&file, &data).unwrap();
+
+        let store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        assert!(store.compaction.is_some());
+        drop(store);
+
+        assert_eq!(fs::metadata(&file).unwrap().len(), compact_size);
+        let reopened_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        assert!(reopened_store.contains(&key_id));
+    }
+
+    #[test]
+    fn should_keep_keys_inserted_by_another_store_on_the_same_file() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let mut replica_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        let mut orchestrator_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        let (key_id_1, key_id_2, key_id_3) = (
+            test_utils::make_key_id(1),
+            test_utils::make_key_id(2),
+            test_utils::make_key_id(3),
+        );
+        replica_store
+            .insert(key_id_1, test_utils::make_secret_key(1), None)
+            .unwrap();
+
+        orchestrator_store
+            .insert(key_id_2, test_utils::make_secret_key(2), None)
+            .unwrap();
+        // The key inserted by the other store is found on disk.
+        assert!(replica_store.contains(&key_id_2));
+        replica_store
+            .insert(key_id_3, test_utils::make_secret_key(3), None)
+            .unwrap();
+        assert!(replica_store.remove(&key_id_1));
+
+        let reopened_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        assert!(!reopened_store.contains(&key_id_1));
+        assert!(reopened_store.contains(&key_id_2));
+        assert!(reopened_store.contains(&key_id_3));
+        assert!(orchestrator_store.contains(&key_id_3));
+    }
+
+    #[test]
+    fn should_export_key_counts_of_keys_inserted_by_another_store() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let registry = MetricsRegistry::new();
+        let replica_store = ProtoSecretKeyStore::open_with_metrics(
+            dir.path(),
+            "sks_data.pb",
+            None,
+            Arc::new(CryptoMetrics::new(Some(&registry))),
+            DEFAULT_LOAD_BUDGET,
+        );
+        let mut orchestrator_store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        let key_id = test_utils::make_key_id(1);
+        assert!(!replica_store.contains(&key_id));
+
+        orchestrator_store
+            .insert(key_id, test_utils::make_secret_key(1), None)
+            .unwrap();
+
+        assert!(replica_store.contains(&key_id));
+        assert_eq!(key_count(&registry, "Ed25519"), Some(1));
+        assert!(!replica_store.contains(&test_utils::make_key_id(2)));
+        assert_eq!(key_count(&registry, "Ed25519"), Some(1));
+    }
+
+    #[test]
+    fn should_inspect_sks_file() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let mut store = ProtoSecretKeyStore::open(dir.path(), "sks_data.pb", None);
+        let (mega_key_id, _, mega_key_set) = TestKeygen::new("sks").mega_key_pair();
+        store.insert(mega_key_id, mega_key_set, None).unwrap();
+        let key_id = test_utils::make_key_id(1);
+        store
+            .insert(key_id, test_utils::make_secret_key(1), Some(NIDKG_FS_SCOPE))
+            .unwrap();
+
+        let summary = inspect_sks_file(store.proto_file_path()).unwrap();
+
+        assert_eq!(summary.version, CURRENT_SKS_VERSION);
+        let mut expected_key_ids = vec![key_id_to_hex(&mega_key_id), key_id_to_hex(&key_id)];
+        expected_key_ids.sort();
+        let key_ids: Vec<_> = summary.entries.iter().map(|e| e.key_id.clone()).collect();
+        assert_eq!(key_ids, expected_key_ids);
+        for entry in &summary.entries {
+            assert!(entry.is_valid(), "{:?}", entry);
+            assert!(entry.size > 0);
+            if entry.key_id == key_id_to_hex(&key_id) {
+                assert_eq!(entry.variant, Some("Ed25519"));
+                assert_eq!(entry.scope, Some(String::from(&NIDKG_FS_SCOPE)));
+            } else {
+                assert_eq!(entry.variant, Some("MEGaEncryptionK256"));
+                assert_eq!(entry.scope, None);
+            }
+        }
+    }
+
+    #[test]
+    fn should_report_malformed_entries_when_inspecting_sks_file() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let file = dir.path().join("sks_data.pb");
+        let valid_key = serde_cbor::to_vec(&test_utils::make_secret_key(1)).unwrap();
+        let mut sks_proto = pb::SecretKeyStore {
+            version: CURRENT_SKS_VERSION,
+            ..Default::default()
+        };
+        let entries = vec![
+            ("invalid key id".to_string(), valid_key.clone(), ""),
+            ("11".repeat(32), vec![1, 2, 3], ""),
+            ("22".repeat(32), valid_key, "unknown scope"),
+        ];
+        for (key_id, csp_secret_key, scope) in entries {
+            sks_proto.key_id_to_secret_key_v1.insert(
+                key_id,
+                pb::SecretKeyV1 {
+                    csp_secret_key,
+                    scope: scope.to_string(),
+                },
+            );
+        }
+        ic_utils::fs::write_protobuf_using_tmp_file(&file, &sks_proto).unwrap();
+
+        let summary = inspect_sks_file(&file).unwrap();
+
+        assert_eq!(summary.entries.len(), 3);
+        let undecodable_key = &summary.entries[0];
+        assert_eq!(undecodable_key.variant, None);
+        assert_eq!(undecodable_key.size, 3);
+        assert_eq!(undecodable_key.errors.len(), 1);
+        let unknown_scope = &summary.entries[1];
+        assert_eq!(unknown_scope.variant, Some("Ed25519"));
+        assert_eq!(unknown_scope.scope, Some(String::from("unknown scope")));
+        assert_eq!(unknown_scope.errors.len(), 1);
+        let invalid_key_id = &summary.entries[2];
+        assert_eq!(invalid_key_id.key_id, "invalid key id");
+        assert_eq!(invalid_key_id.variant, Some("Ed25519"));
+        assert_eq!(invalid_key_id.errors.len(), 1);
+    }
+
+    #[test]
+    fn should_fail_inspecting_malformed_sks_file() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let file = dir.path().join("sks_data.pb");
+
+        assert!(matches!(
+            inspect_sks_file(&file),
+            Err(SksInspectionError::Io(_))
+        ));
+
+        fs::write(&file, b"not a protobuf").unwrap();
+        assert!(matches!(
+            inspect_sks_file(&file),
+            Err(SksInspectionError::MalformedProto(_))
+        ));
+
+        let sks_proto = pb::SecretKeyStore {
+            version: CURRENT_SKS_VERSION + 1,
+            ..Default::default()
+        };
+        ic_utils::fs::write_protobuf_using_tmp_file(&file, &sks_proto).unwrap();
+        assert!(matches!(
+            inspect_sks_file(&file),
+            Err(SksInspectionError::UnsupportedVersion(version)) if version == CURRENT_SKS_VERSION + 1
+        ));
+    }
+
+    fn key_count(registry: &MetricsRegistry, key_type: &str) -> Option<i64> {
+        registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == "ic_crypto_secret_key_store_keys")
+            .flat_map(|family| family.get_metric().iter())
+            .find(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "key_type" && label.get_value() == key_type)
+            })
+            .map(|metric| metric.get_gauge().get_value() as i64)
+    }
+
+    fn gauge(registry: &MetricsRegistry, name: &str) -> Option<f64> {
+        registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == name)
+            .flat_map(|family| family.get_metric().iter())
+            .map(|metric| metric.get_gauge().get_value())
+            .next()
+    }
+
+    fn counter(registry: &MetricsRegistry, name: &str) -> Option<f64> {
+        registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == name)
+            .flat_map(|family| family.get_metric().iter())
+            .map(|metric| metric.get_counter().get_value())
+            .next()
+    }
+
     fn proto_key_store() -> TempSecretKeyStore {
         TempSecretKeyStore::new()
     }
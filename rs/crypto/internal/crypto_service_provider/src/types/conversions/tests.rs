@@ -46,6 +46,7 @@ mod proto_to_csp_pop_tests {
             key_value: [42; 10].to_vec(),
             version: 1,
             proof_data: Some(pop_bytes.clone()),
+            timestamp: None,
         };
 
         let deserialized_pop = CspPop::try_from(&pk_proto).unwrap();
@@ -65,6 +66,7 @@ mod proto_to_csp_pop_tests {
             key_value: [42; 10].to_vec(),
             version: 1,
             proof_data: None,
+            timestamp: None,
         };
 
         let error = CspPop::try_from(&pk_proto).unwrap_err();
@@ -80,6 +82,7 @@ mod proto_to_csp_pop_tests {
             key_value: [42; 10].to_vec(),
             version: 1,
             proof_data: Some(vec![42; multi_types::IndividualSignatureBytes::SIZE]),
+            timestamp: None,
         };
 
         let error = CspPop::try_from(&pk_proto).unwrap_err();
@@ -100,6 +103,7 @@ mod proto_to_csp_pop_tests {
             key_value: [42; 10].to_vec(),
             version: 1,
             proof_data: Some(malformed_proof_data.clone()),
+            timestamp: None,
         };
 
         let error = CspPop::try_from(&pk_proto).unwrap_err();
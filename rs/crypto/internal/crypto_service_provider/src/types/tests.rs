@@ -289,6 +289,7 @@ fn should_correctly_convert_ed25519_pk_proto_to_csp_public_key() {
         key_value: hex_to_byte_vec(TESTVEC_RFC8032_ED25519_SHA_ABC_PK),
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let ed25519_csp_pk = CspPublicKey::try_from(pk_proto).unwrap();
 
@@ -305,6 +306,7 @@ fn should_correctly_convert_multi_bls12_381_pk_proto_to_csp_public_key() {
         key_value: hex_to_byte_vec(TESTVEC_MULTI_BLS12_381_1_PK),
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let multi_bls_csp_pk = CspPublicKey::try_from(pk_proto).unwrap();
 
@@ -321,6 +323,7 @@ fn should_fail_conversion_to_csp_public_key_if_ed25519_pk_proto_is_too_short() {
         key_value: vec![0; ed25519_types::PublicKeyBytes::SIZE - 1],
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let ed25519_csp_pk_result = CspPublicKey::try_from(pk_proto);
     assert!(ed25519_csp_pk_result.is_err());
@@ -334,6 +337,7 @@ fn should_fail_conversion_to_csp_public_key_if_ed25519_pk_proto_is_too_long() {
         key_value: vec![0; ed25519_types::PublicKeyBytes::SIZE + 1],
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let ed25519_csp_pk_result = CspPublicKey::try_from(pk_proto);
     assert!(ed25519_csp_pk_result.is_err());
@@ -347,6 +351,7 @@ fn should_fail_conversion_to_csp_public_key_if_multi_bls12_381_pk_proto_is_too_s
         key_value: vec![0; multi_types::PublicKeyBytes::SIZE - 1],
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let multi_csp_pk_result = CspPublicKey::try_from(pk_proto);
     assert!(multi_csp_pk_result.is_err());
@@ -360,6 +365,7 @@ fn should_fail_conversion_to_csp_public_key_if_multi_bls12_381_pk_proto_is_too_l
         key_value: vec![0; multi_types::PublicKeyBytes::SIZE + 1],
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     let multi_csp_pk_result = CspPublicKey::try_from(pk_proto);
     assert!(multi_csp_pk_result.is_err());
@@ -1,11 +1,16 @@
-use crate::api::{CspCreateMEGaKeyError, CspThresholdSignError};
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError, CspThresholdSignError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::MegaKeyId;
 use crate::secret_key_store::{Scope, SecretKeyStoreError};
 use crate::types::{CspPop, CspPublicKey, CspSignature};
 use crate::types::{CspPublicCoefficients, CspSecretKey};
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    CommitmentOpening, IDkgDealingInternal, IDkgTranscriptInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -16,7 +21,7 @@ use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
-    ThresholdEcdsaSignShareError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::{AlgorithmId, KeyId};
@@ -430,6 +435,22 @@ pub trait IDkgProtocolCspVault {
         transcript_operation: &IDkgTranscriptOperationInternal,
     ) -> Result<IDkgDealingInternal, IDkgCreateDealingError>;
 
+    /// Privately verifies the share of `dealing` encrypted to the receiver
+    /// with index `receiver_index`: decrypts the share with the MEGa private
+    /// key `receiver_key_id`, and checks that it is consistent with the
+    /// commitment of the dealing.
+    ///
+    /// The dealing must have been verified publicly before.
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: &MegaKeyId,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError>;
+
     /// Compute secret from transcript and store in SKS, generating complaints
     /// if necessary.
     fn idkg_load_transcript(
@@ -437,9 +458,9 @@ pub trait IDkgProtocolCspVault {
         dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError>;
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError>;
 
     /// See [`CspIDkgProtocol::idkg_load_transcript_with_openings`].
     fn idkg_load_transcript_with_openings(
@@ -448,15 +469,19 @@ pub trait IDkgProtocolCspVault {
         openings: &BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError>;
 
     /// Generate a MEGa keypair, for encrypting/decrypting IDkg dealing shares.
+    ///
+    /// If `pop_node_id` is given, also returns a proof of possession of the
+    /// private key that is bound to that node ID.
     fn idkg_gen_mega_key_pair(
         &self,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError>;
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError>;
 
     /// Opens the dealing from dealer specified by `dealer_index`.
     fn idkg_open_dealing(
@@ -465,8 +490,22 @@ pub trait IDkgProtocolCspVault {
         dealer_index: NodeIndex,
         context_data: &[u8],
         opener_index: NodeIndex,
-        opener_key_id: &KeyId,
+        opener_key_id: &MegaKeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError>;
+
+    /// Checks that the secret key store contains a well-formed MEGa key pair
+    /// for `public_key`, without revealing any of the secret key material.
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError>;
+
+    /// Removes the MEGa key pairs of `public_keys` from the secret key store.
+    /// Key pairs that are not in the secret key store are ignored.
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError>;
 }
 
 /// Operations of `CspVault` related to threshold-ECDSA (cf.
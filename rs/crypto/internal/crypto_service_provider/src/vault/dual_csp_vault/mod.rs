@@ -0,0 +1,424 @@
+//! A vault that validates a secondary vault against a primary vault.
+//!
+//! The `DualCspVault` serves every call from its primary vault. Deterministic
+//! calls that neither use nor reveal secret key material, i.e. the lookups of
+//! keys in the secret key store and the verification of dealings, are
+//! additionally sent to the secondary vault, and the results of both vaults
+//! are compared. Mismatches are logged and counted, but never affect the
+//! result returned to the caller.
+//!
+//! This allows running a new vault, e.g. a `RemoteCspVault`, side by side with
+//! the vault it is meant to replace, before switching over to it. Both vaults
+//! are expected to operate on the same secret key store, e.g. a remote vault
+//! server on the crypto root of the node. All other calls, such as the
+//! generation of keys and signatures, are only sent to the primary vault, so
+//! that keys are generated and used once.
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError, CspThresholdSignError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::MegaKeyId;
+use crate::secret_key_store::{Scope, SecretKeyStoreError};
+use crate::types::{CspPop, CspPublicCoefficients, CspPublicKey, CspSecretKey, CspSignature};
+use crate::vault::api::{
+    BasicSignatureCspVault, CspBasicSignatureError, CspBasicSignatureKeygenError,
+    CspMultiSignatureError, CspMultiSignatureKeygenError, CspThresholdSignatureKeygenError,
+    CspTlsKeygenError, CspTlsSignError, CspVault, IDkgProtocolCspVault, MultiSignatureCspVault,
+    NiDkgCspVault, SecretKeyStoreCspVault, ThresholdEcdsaSignerCspVault,
+    ThresholdSignatureCspVault,
+};
+use crate::TlsHandshakeCspVault;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
+use ic_crypto_internal_threshold_sig_ecdsa::{
+    CommitmentOpening, IDkgDealingInternal, IDkgTranscriptInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaSigShareInternal,
+};
+use ic_crypto_internal_types::encrypt::forward_secure::{
+    CspFsEncryptionPop, CspFsEncryptionPublicKey,
+};
+use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
+    CspNiDkgDealing, CspNiDkgTranscript, Epoch,
+};
+use ic_crypto_tls_interfaces::TlsPublicKeyCert;
+use ic_logger::{warn, ReplicaLogger};
+use ic_types::crypto::canister_threshold_sig::error::{
+    IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
+};
+use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
+use ic_types::crypto::{AlgorithmId, KeyId};
+use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// An implementation of `CspVault`-trait that returns the results of a
+/// primary vault, and compares them to the results of a secondary vault.
+pub struct DualCspVault {
+    primary: Arc<dyn CspVault>,
+    secondary: Arc<dyn CspVault>,
+    logger: ReplicaLogger,
+    metrics: Arc<CryptoMetrics>,
+}
+
+impl DualCspVault {
+    /// Creates a vault that serves calls from `primary` and validates
+    /// `secondary` against it.
+    pub fn new(
+        primary: Arc<dyn CspVault>,
+        secondary: Arc<dyn CspVault>,
+        logger: ReplicaLogger,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            logger,
+            metrics,
+        }
+    }
+
+    /// Returns the `primary` result of the call of `method_name`, after
+    /// comparing it to the result of `secondary_call` on the secondary vault.
+    fn compare<T: Debug + PartialEq>(
+        &self,
+        method_name: &str,
+        primary: T,
+        secondary_call: impl FnOnce(&dyn CspVault) -> T,
+    ) -> T {
+        let secondary = secondary_call(self.secondary.as_ref());
+        if primary != secondary {
+            self.report_mismatch(
+                method_name,
+                &format!("{:?}", primary),
+                &format!("{:?}", secondary),
+            );
+        }
+        primary
+    }
+
+    fn report_mismatch(&self, method_name: &str, primary: &str, secondary: &str) {
+        warn!(
+            self.logger,
+            "The vaults returned different results for {}: primary: {}, secondary: {}",
+            method_name,
+            primary,
+            secondary
+        );
+        self.metrics.inc_vault_result_mismatch(method_name);
+    }
+}
+
+impl BasicSignatureCspVault for DualCspVault {
+    fn sign(
+        &self,
+        algorithm_id: AlgorithmId,
+        message: &[u8],
+        key_id: KeyId,
+    ) -> Result<CspSignature, CspBasicSignatureError> {
+        self.primary.sign(algorithm_id, message, key_id)
+    }
+
+    fn gen_key_pair(
+        &self,
+        algorithm_id: AlgorithmId,
+    ) -> Result<(KeyId, CspPublicKey), CspBasicSignatureKeygenError> {
+        self.primary.gen_key_pair(algorithm_id)
+    }
+}
+
+impl MultiSignatureCspVault for DualCspVault {
+    fn multi_sign(
+        &self,
+        algorithm_id: AlgorithmId,
+        message: &[u8],
+        key_id: KeyId,
+    ) -> Result<CspSignature, CspMultiSignatureError> {
+        self.primary.multi_sign(algorithm_id, message, key_id)
+    }
+
+    fn gen_key_pair_with_pop(
+        &self,
+        algorithm_id: AlgorithmId,
+    ) -> Result<(KeyId, CspPublicKey, CspPop), CspMultiSignatureKeygenError> {
+        self.primary.gen_key_pair_with_pop(algorithm_id)
+    }
+}
+
+impl ThresholdSignatureCspVault for DualCspVault {
+    fn threshold_keygen_for_test(
+        &self,
+        algorithm_id: AlgorithmId,
+        threshold: NumberOfNodes,
+        signatory_eligibility: &[bool],
+    ) -> Result<(CspPublicCoefficients, Vec<Option<KeyId>>), CspThresholdSignatureKeygenError> {
+        self.primary
+            .threshold_keygen_for_test(algorithm_id, threshold, signatory_eligibility)
+    }
+
+    fn threshold_sign(
+        &self,
+        algorithm_id: AlgorithmId,
+        message: &[u8],
+        key_id: KeyId,
+    ) -> Result<CspSignature, CspThresholdSignError> {
+        self.primary.threshold_sign(algorithm_id, message, key_id)
+    }
+}
+
+impl NiDkgCspVault for DualCspVault {
+    fn gen_forward_secure_key_pair(
+        &self,
+        node_id: NodeId,
+        algorithm_id: AlgorithmId,
+    ) -> Result<(CspFsEncryptionPublicKey, CspFsEncryptionPop), ni_dkg_errors::CspDkgCreateFsKeyError>
+    {
+        self.primary
+            .gen_forward_secure_key_pair(node_id, algorithm_id)
+    }
+
+    fn update_forward_secure_epoch(
+        &self,
+        algorithm_id: AlgorithmId,
+        key_id: KeyId,
+        epoch: Epoch,
+    ) -> Result<(), ni_dkg_errors::CspDkgUpdateFsEpochError> {
+        self.primary
+            .update_forward_secure_epoch(algorithm_id, key_id, epoch)
+    }
+
+    fn create_dealing(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealer_index: NodeIndex,
+        threshold: NumberOfNodes,
+        epoch: Epoch,
+        receiver_keys: &BTreeMap<NodeIndex, CspFsEncryptionPublicKey>,
+        maybe_resharing_secret: Option<KeyId>,
+    ) -> Result<CspNiDkgDealing, ni_dkg_errors::CspDkgCreateReshareDealingError> {
+        self.primary.create_dealing(
+            algorithm_id,
+            dealer_index,
+            threshold,
+            epoch,
+            receiver_keys,
+            maybe_resharing_secret,
+        )
+    }
+
+    fn load_threshold_signing_key(
+        &self,
+        algorithm_id: AlgorithmId,
+        epoch: Epoch,
+        csp_transcript: CspNiDkgTranscript,
+        fs_key_id: KeyId,
+        receiver_index: NodeIndex,
+    ) -> Result<(), ni_dkg_errors::CspDkgLoadPrivateKeyError> {
+        self.primary.load_threshold_signing_key(
+            algorithm_id,
+            epoch,
+            csp_transcript,
+            fs_key_id,
+            receiver_index,
+        )
+    }
+
+    fn retain_threshold_keys_if_present(&self, active_key_ids: BTreeSet<KeyId>) {
+        self.primary
+            .retain_threshold_keys_if_present(active_key_ids)
+    }
+}
+
+impl SecretKeyStoreCspVault for DualCspVault {
+    fn sks_contains(&self, key_id: &KeyId) -> bool {
+        self.compare("sks_contains", self.primary.sks_contains(key_id), |vault| {
+            vault.sks_contains(key_id)
+        })
+    }
+
+    fn insert_secret_key(
+        &self,
+        id: KeyId,
+        key: CspSecretKey,
+        scope: Option<Scope>,
+    ) -> Result<(), SecretKeyStoreError> {
+        self.primary.insert_secret_key(id, key, scope)
+    }
+
+    fn get_secret_key(&self, id: &KeyId) -> Option<CspSecretKey> {
+        self.primary.get_secret_key(id)
+    }
+}
+
+impl TlsHandshakeCspVault for DualCspVault {
+    fn gen_tls_key_pair(
+        &self,
+        node: NodeId,
+        not_after: &str,
+    ) -> Result<(KeyId, TlsPublicKeyCert), CspTlsKeygenError> {
+        self.primary.gen_tls_key_pair(node, not_after)
+    }
+
+    fn tls_sign(&self, message: &[u8], key_id: &KeyId) -> Result<CspSignature, CspTlsSignError> {
+        self.primary.tls_sign(message, key_id)
+    }
+}
+
+impl IDkgProtocolCspVault for DualCspVault {
+    fn idkg_create_dealing(
+        &self,
+        algorithm_id: AlgorithmId,
+        context_data: &[u8],
+        dealer_index: NodeIndex,
+        reconstruction_threshold: NumberOfNodes,
+        receiver_keys: &[MEGaPublicKey],
+        transcript_operation: &IDkgTranscriptOperationInternal,
+    ) -> Result<IDkgDealingInternal, IDkgCreateDealingError> {
+        self.primary.idkg_create_dealing(
+            algorithm_id,
+            context_data,
+            dealer_index,
+            reconstruction_threshold,
+            receiver_keys,
+            transcript_operation,
+        )
+    }
+
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: &MegaKeyId,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        let verify = |vault: &dyn CspVault| {
+            vault.idkg_verify_dealing_private(
+                algorithm_id,
+                dealing,
+                dealer_index,
+                receiver_index,
+                receiver_key_id,
+                context_data,
+            )
+        };
+        self.compare(
+            "idkg_verify_dealing_private",
+            verify(self.primary.as_ref()),
+            verify,
+        )
+    }
+
+    fn idkg_load_transcript(
+        &self,
+        dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
+        context_data: &[u8],
+        receiver_index: NodeIndex,
+        key_id: &MegaKeyId,
+        transcript: &IDkgTranscriptInternal,
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError> {
+        self.primary.idkg_load_transcript(
+            dealings,
+            context_data,
+            receiver_index,
+            key_id,
+            transcript,
+        )
+    }
+
+    fn idkg_load_transcript_with_openings(
+        &self,
+        dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
+        openings: &BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
+        context_data: &[u8],
+        receiver_index: NodeIndex,
+        key_id: &MegaKeyId,
+        transcript: &IDkgTranscriptInternal,
+    ) -> Result<(), IDkgLoadTranscriptError> {
+        self.primary.idkg_load_transcript_with_openings(
+            dealings,
+            openings,
+            context_data,
+            receiver_index,
+            key_id,
+            transcript,
+        )
+    }
+
+    fn idkg_gen_mega_key_pair(
+        &self,
+        algorithm_id: AlgorithmId,
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError> {
+        self.primary
+            .idkg_gen_mega_key_pair(algorithm_id, pop_node_id)
+    }
+
+    fn idkg_open_dealing(
+        &self,
+        dealing: IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        context_data: &[u8],
+        opener_index: NodeIndex,
+        opener_key_id: &MegaKeyId,
+    ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
+        self.primary.idkg_open_dealing(
+            dealing,
+            dealer_index,
+            context_data,
+            opener_index,
+            opener_key_id,
+        )
+    }
+
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        self.compare(
+            "idkg_check_mega_key_pair",
+            self.primary.idkg_check_mega_key_pair(public_key),
+            |vault| vault.idkg_check_mega_key_pair(public_key),
+        )
+    }
+
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError> {
+        self.primary.idkg_retire_mega_keys(public_keys)
+    }
+}
+
+impl ThresholdEcdsaSignerCspVault for DualCspVault {
+    fn ecdsa_sign_share(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        hashed_message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+        lambda_masked: &IDkgTranscriptInternal,
+        kappa_times_lambda: &IDkgTranscriptInternal,
+        key_times_lambda: &IDkgTranscriptInternal,
+        algorithm_id: AlgorithmId,
+    ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError> {
+        self.primary.ecdsa_sign_share(
+            derivation_path,
+            hashed_message,
+            nonce,
+            key,
+            kappa_unmasked,
+            lambda_masked,
+            kappa_times_lambda,
+            key_times_lambda,
+            algorithm_id,
+        )
+    }
+}
@@ -0,0 +1,116 @@
+use super::*;
+use crate::secret_key_store::test_utils::{make_key_id, make_secret_key};
+use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
+use crate::vault::local_csp_vault::LocalCspVault;
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn local_vault() -> Arc<dyn CspVault> {
+    Arc::new(LocalCspVault::new_for_test(
+        ChaChaRng::seed_from_u64(42),
+        VolatileSecretKeyStore::new(),
+    ))
+}
+
+fn dual_vault(
+    primary: Arc<dyn CspVault>,
+    secondary: Arc<dyn CspVault>,
+    registry: &MetricsRegistry,
+) -> DualCspVault {
+    DualCspVault::new(
+        primary,
+        secondary,
+        no_op_logger(),
+        Arc::new(CryptoMetrics::new(Some(registry))),
+    )
+}
+
+fn mismatch_count(registry: &MetricsRegistry, method_name: &str) -> u64 {
+    registry
+        .prometheus_registry()
+        .gather()
+        .iter()
+        .filter(|family| family.get_name() == "ic_crypto_vault_result_mismatch_total")
+        .flat_map(|family| family.get_metric().iter())
+        .find(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .any(|label| label.get_name() == "method_name" && label.get_value() == method_name)
+        })
+        .map(|metric| metric.get_counter().get_value() as u64)
+        .unwrap_or(0)
+}
+
+#[test]
+fn should_not_count_mismatch_if_vaults_hold_same_keys() {
+    let (primary, secondary) = (local_vault(), local_vault());
+    let key_id = make_key_id(1);
+    for vault in [&primary, &secondary] {
+        vault
+            .insert_secret_key(key_id, make_secret_key(1), None)
+            .expect("failed to insert the secret key");
+    }
+    let registry = MetricsRegistry::new();
+    let vault = dual_vault(primary, secondary, &registry);
+
+    assert!(vault.sks_contains(&key_id));
+    assert!(!vault.sks_contains(&make_key_id(2)));
+
+    assert_eq!(mismatch_count(&registry, "sks_contains"), 0);
+}
+
+#[test]
+fn should_return_primary_result_and_count_mismatch_if_secondary_differs() {
+    let (primary, secondary) = (local_vault(), local_vault());
+    let key_id = make_key_id(1);
+    primary
+        .insert_secret_key(key_id, make_secret_key(1), None)
+        .expect("failed to insert the secret key");
+    let expected_signature = primary
+        .sign(AlgorithmId::Ed25519, b"message", key_id)
+        .expect("failed to sign");
+    let registry = MetricsRegistry::new();
+    let vault = dual_vault(primary, secondary, &registry);
+
+    assert_eq!(
+        vault.sign(AlgorithmId::Ed25519, b"message", key_id),
+        Ok(expected_signature)
+    );
+    assert!(vault.sks_contains(&key_id));
+
+    assert_eq!(mismatch_count(&registry, "sks_contains"), 1);
+}
+
+#[test]
+fn should_only_send_signing_to_primary() {
+    let (primary, secondary) = (local_vault(), local_vault());
+    let key_id = make_key_id(1);
+    primary
+        .insert_secret_key(key_id, make_secret_key(1), None)
+        .expect("failed to insert the secret key");
+    secondary
+        .insert_secret_key(key_id, make_secret_key(2), None)
+        .expect("failed to insert the secret key");
+    let registry = MetricsRegistry::new();
+    let vault = dual_vault(primary, secondary, &registry);
+
+    assert!(vault.sign(AlgorithmId::Ed25519, b"message", key_id).is_ok());
+
+    assert_eq!(mismatch_count(&registry, "sign"), 0);
+}
+
+#[test]
+fn should_only_send_key_generation_to_primary() {
+    let (primary, secondary) = (local_vault(), local_vault());
+    let registry = MetricsRegistry::new();
+    let vault = dual_vault(primary, Arc::clone(&secondary), &registry);
+
+    let (key_id, _public_key) = vault
+        .gen_key_pair(AlgorithmId::Ed25519)
+        .expect("failed to generate a key pair");
+
+    assert!(!secondary.sks_contains(&key_id));
+}
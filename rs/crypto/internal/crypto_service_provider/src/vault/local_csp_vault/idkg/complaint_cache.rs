@@ -0,0 +1,95 @@
+use crate::keygen::{CommitmentKeyId, MegaKeyId};
+use ic_crypto_internal_threshold_sig_ecdsa::IDkgComplaintInternal;
+use ic_types::NodeIndex;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, VecDeque};
+
+/// The maximum number of transcript loads whose complaints are cached. Once
+/// it is reached, the complaints of the oldest load are evicted.
+pub const MAX_CACHED_COMPLAINT_LOADS: usize = 1024;
+
+type Complaints = BTreeMap<NodeIndex, IDkgComplaintInternal>;
+
+/// The transcript, the index of the receiver and the MEGa key pair with which
+/// the receiver tried to load it.
+type CacheKey = (CommitmentKeyId, NodeIndex, MegaKeyId);
+
+/// Caches the complaints generated when a receiver fails to load a transcript,
+/// keyed by the transcript, the receiver and its MEGa key pair.
+///
+/// A transcript that could not be loaded is loaded again in later rounds. The
+/// proofs of the complaints are randomized, so without the cache every
+/// attempt would return different complaints against the same dealings. The
+/// complaints are bound to the MEGa key pair, so a receiver that rotated its
+/// key pair generates new complaints rather than reusing stale ones.
+///
+/// The cache is held in memory only and is lost when the vault restarts, so
+/// the first load after a restart generates complaints with fresh proofs.
+/// Complaints with different proofs against the same dealing are equally
+/// valid, so this only costs the verification of the new complaints.
+#[derive(Default)]
+pub struct ComplaintCache {
+    inner: Mutex<ComplaintCacheInner>,
+}
+
+#[derive(Default)]
+struct ComplaintCacheInner {
+    complaints: BTreeMap<CacheKey, Complaints>,
+    insertion_order: VecDeque<CacheKey>,
+}
+
+impl ComplaintCache {
+    /// Returns the complaints cached for the transcript with commitment
+    /// `commitment_key_id`, the receiver `receiver_index` and its MEGa key
+    /// pair `mega_key_id`, if any.
+    pub fn get(
+        &self,
+        commitment_key_id: CommitmentKeyId,
+        receiver_index: NodeIndex,
+        mega_key_id: MegaKeyId,
+    ) -> Option<Complaints> {
+        self.inner
+            .lock()
+            .complaints
+            .get(&(commitment_key_id, receiver_index, mega_key_id))
+            .cloned()
+    }
+
+    /// Caches `complaints` for the transcript with commitment
+    /// `commitment_key_id`, the receiver `receiver_index` and its MEGa key
+    /// pair `mega_key_id`, and returns the cached complaints. If complaints
+    /// were cached concurrently, they are kept and returned instead.
+    pub fn insert(
+        &self,
+        commitment_key_id: CommitmentKeyId,
+        receiver_index: NodeIndex,
+        mega_key_id: MegaKeyId,
+        complaints: Complaints,
+    ) -> Complaints {
+        let mut inner = self.inner.lock();
+        let key = (commitment_key_id, receiver_index, mega_key_id);
+        if let Some(cached) = inner.complaints.get(&key) {
+            return cached.clone();
+        }
+        if inner.insertion_order.len() >= MAX_CACHED_COMPLAINT_LOADS {
+            if let Some(oldest) = inner.insertion_order.pop_front() {
+                inner.complaints.remove(&oldest);
+            }
+        }
+        inner.insertion_order.push_back(key);
+        inner.complaints.insert(key, complaints.clone());
+        complaints
+    }
+
+    /// Forgets the complaints of all receivers of the transcript with
+    /// commitment `commitment_key_id`, e.g. once it was loaded.
+    pub fn remove_transcript(&self, commitment_key_id: CommitmentKeyId) {
+        let mut inner = self.inner.lock();
+        inner
+            .complaints
+            .retain(|(key_id, _, _), _| *key_id != commitment_key_id);
+        inner
+            .insertion_order
+            .retain(|(key_id, _, _)| *key_id != commitment_key_id);
+    }
+}
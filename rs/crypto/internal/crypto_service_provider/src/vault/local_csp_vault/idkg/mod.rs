@@ -1,29 +1,37 @@
-use crate::api::CspCreateMEGaKeyError;
-use crate::keygen::mega_key_id;
-use crate::secret_key_store::SecretKeyStore;
+pub(crate) mod complaint_cache;
+
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::{commitment_key_id, mega_key_id, MegaKeyId};
+use crate::secret_key_store::{SecretKeyStore, SecretKeyStoreError};
 use crate::types::CspSecretKey;
 use crate::vault::api::IDkgProtocolCspVault;
 use crate::vault::local_csp_vault::LocalCspVault;
 use ic_crypto_internal_threshold_sig_ecdsa::{
     compute_secret_shares, compute_secret_shares_with_openings,
-    create_dealing as tecdsa_create_dealing, gen_keypair, generate_complaints, open_dealing,
-    CommitmentOpening, CommitmentOpeningBytes, EccCurveType, IDkgComplaintInternal,
-    IDkgComputeSecretSharesInternalError, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaKeySetK256Bytes, MEGaPrivateKey, MEGaPrivateKeyK256Bytes,
-    MEGaPublicKey, MEGaPublicKeyK256Bytes, PolynomialCommitment, SecretShares, Seed,
+    create_dealing as tecdsa_create_dealing, create_mega_key_proof_of_possession, gen_keypair,
+    generate_complaints, open_dealing, privately_verify_dealing, CommitmentOpening,
+    CommitmentOpeningBytes, EccCurveType, IDkgComputeSecretSharesInternalError,
+    IDkgDealingInternal, IDkgTranscriptInternal, IDkgTranscriptOperationInternal,
+    IDkgVerifyDealingInternalError, MEGaKeyProofOfPossession, MEGaKeySetK256Bytes, MEGaPrivateKey,
+    MEGaPrivateKeyK256Bytes, MEGaPublicKey, MEGaPublicKeyK256Bytes, PolynomialCommitment,
+    SecretShares, Seed,
 };
-use ic_crypto_sha::{DomainSeparationContext, Sha256};
 use ic_logger::debug;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
+    IDkgVerifyDealingPrivateError,
 };
 use ic_types::crypto::{AlgorithmId, KeyId};
-use ic_types::{NodeIndex, NumberOfNodes, Randomness};
+use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
 use rand::{CryptoRng, Rng};
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
-const COMMITMENT_KEY_ID_DOMAIN: &str = "ic-key-id-idkg-commitment";
+#[cfg(test)]
+mod tests;
 
 impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDkgProtocolCspVault
     for LocalCspVault<R, S, C>
@@ -57,20 +65,67 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
         })
     }
 
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: &MegaKeyId,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        debug!(self.logger; crypto.method_name => "idkg_verify_dealing_private");
+
+        let (receiver_public_key, receiver_private_key) = self
+            .mega_keyset_from_sks(receiver_key_id)
+            .map_err(|e| match e {
+                IDkgLoadTranscriptError::PrivateKeyNotFound => {
+                    IDkgVerifyDealingPrivateError::PrivateKeyNotFound {
+                        key_id: KeyId::from(*receiver_key_id),
+                    }
+                }
+                _ => IDkgVerifyDealingPrivateError::InternalError {
+                    internal_error: e.to_string(),
+                },
+            })?;
+        privately_verify_dealing(
+            algorithm_id,
+            dealing,
+            &receiver_private_key,
+            &receiver_public_key,
+            context_data,
+            dealer_index,
+            receiver_index,
+        )
+        .map_err(|e| match e {
+            IDkgVerifyDealingInternalError::UnsupportedAlgorithm => {
+                IDkgVerifyDealingPrivateError::InvalidArgument {
+                    internal_error: format!("unsupported algorithm {:?}", algorithm_id),
+                }
+            }
+            IDkgVerifyDealingInternalError::InternalError(internal_error) => {
+                IDkgVerifyDealingPrivateError::InternalError { internal_error }
+            }
+            _ => IDkgVerifyDealingPrivateError::InvalidDealing {
+                reason: format!("{:?}", e),
+            },
+        })
+    }
+
     fn idkg_load_transcript(
         &self,
         dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError> {
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError> {
         // If secret share has already been stored in the C-SKS, nothing to do
         if self
             .commitment_opening_from_sks(transcript.combined_commitment.commitment())
             .is_ok()
         {
-            return Ok(BTreeMap::new());
+            return Ok(IDkgLoadTranscriptOutcome::OpeningAlreadyStored);
         }
 
         let (public_key, private_key) = self.mega_keyset_from_sks(key_id)?;
@@ -91,14 +146,24 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
                         internal_error: format!("{:?}", e),
                     }
                 })?;
-                self.store_canister_secret_key_or_panic(
-                    CspSecretKey::IDkgCommitmentOpening(opening_bytes),
-                    commitment_key_id(transcript.combined_commitment.commitment()),
+                self.store_commitment_opening(
+                    transcript.combined_commitment.commitment(),
+                    opening_bytes,
                 );
-                Ok(BTreeMap::new())
+                Ok(IDkgLoadTranscriptOutcome::SharesComputed)
             }
             Err(IDkgComputeSecretSharesInternalError::InconsistentCommitments) => {
-                let seed = Seed::from_rng(&mut *self.csprng.write());
+                // Loading the transcript again returns the complaints of the
+                // first attempt, rather than complaints with fresh proofs.
+                let commitment_key_id =
+                    commitment_key_id(transcript.combined_commitment.commitment());
+                if let Some(complaints) =
+                    self.complaint_cache
+                        .get(commitment_key_id, receiver_index, *key_id)
+                {
+                    return Ok(IDkgLoadTranscriptOutcome::ComplaintsGenerated(complaints));
+                }
+                let seed = Seed::from_rng(&mut *self.rng_write_lock());
                 let complaints = generate_complaints(
                     dealings,
                     context_data,
@@ -107,7 +172,13 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
                     &public_key,
                     seed,
                 )?;
-                Ok(complaints)
+                let complaints = self.complaint_cache.insert(
+                    commitment_key_id,
+                    receiver_index,
+                    *key_id,
+                    complaints,
+                );
+                Ok(IDkgLoadTranscriptOutcome::ComplaintsGenerated(complaints))
             }
             Err(IDkgComputeSecretSharesInternalError::InternalError(e)) => {
                 Err(IDkgLoadTranscriptError::InternalError {
@@ -123,7 +194,7 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
         openings: &BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError> {
         // If secret share has already been stored in the C-SKS, nothing to do
@@ -151,10 +222,13 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
                         internal_error: format!("{:?}", e),
                     }
                 })?;
-                self.store_canister_secret_key_or_panic(
-                    CspSecretKey::IDkgCommitmentOpening(opening_bytes),
-                    commitment_key_id(transcript.combined_commitment.commitment()),
+                self.store_commitment_opening(
+                    transcript.combined_commitment.commitment(),
+                    opening_bytes,
                 );
+                self.complaint_cache.remove_transcript(commitment_key_id(
+                    transcript.combined_commitment.commitment(),
+                ));
                 Ok(())
             }
             Err(IDkgComputeSecretSharesInternalError::InconsistentCommitments) => {
@@ -172,7 +246,8 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
     fn idkg_gen_mega_key_pair(
         &self,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError> {
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError> {
         debug!(self.logger; crypto.method_name => "idkg_gen_mega_key_pair");
 
         let seed = Randomness::from(self.rng_write_lock().gen::<[u8; 32]>());
@@ -188,15 +263,33 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
         let private_key_bytes = MEGaPrivateKeyK256Bytes::try_from(&private_key)
             .map_err(CspCreateMEGaKeyError::SerializationError)?;
 
-        self.store_secret_key_or_panic(
+        let proof_of_possession = match pop_node_id {
+            Some(node_id) => {
+                let pop_seed = Randomness::from(self.rng_write_lock().gen::<[u8; 32]>());
+                let pop = create_mega_key_proof_of_possession(
+                    &private_key,
+                    node_id.get().as_slice(),
+                    pop_seed,
+                )
+                .map_err(CspCreateMEGaKeyError::FailedProofOfPossessionGeneration)?;
+                Some(pop)
+            }
+            None => None,
+        };
+
+        let key_id = mega_key_id(&public_key);
+        self.store_secret_key(
             CspSecretKey::MEGaEncryptionK256(MEGaKeySetK256Bytes {
                 public_key: public_key_bytes,
                 private_key: private_key_bytes,
             }),
-            mega_key_id(&public_key),
-        );
+            KeyId::from(key_id),
+        )
+        .map_err(|SecretKeyStoreError::DuplicateKeyId(key_id)| {
+            CspCreateMEGaKeyError::DuplicateKeyId { key_id }
+        })?;
 
-        Ok(public_key)
+        Ok((public_key, proof_of_possession))
     }
 
     fn idkg_open_dealing(
@@ -205,14 +298,14 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
         dealer_index: NodeIndex,
         context_data: &[u8],
         opener_index: NodeIndex,
-        opener_key_id: &KeyId,
+        opener_key_id: &MegaKeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
         let (opener_public_key, opener_private_key) = self
             .mega_keyset_from_sks(opener_key_id)
             .map_err(|e| match e {
                 IDkgLoadTranscriptError::PrivateKeyNotFound => {
                     IDkgOpenTranscriptError::PrivateKeyNotFound {
-                        key_id: *opener_key_id,
+                        key_id: KeyId::from(*opener_key_id),
                     }
                 }
                 _ => IDkgOpenTranscriptError::InternalError {
@@ -231,6 +324,67 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> IDk
             internal_error: format!("{:?}", e),
         })
     }
+
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        debug!(self.logger; crypto.method_name => "idkg_check_mega_key_pair");
+
+        if public_key.curve_type() != EccCurveType::K256 {
+            return Err(CspCheckMEGaKeyPairError::UnsupportedAlgorithm {
+                algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+            });
+        }
+        let key_id = mega_key_id(public_key);
+        let (stored_public_key, private_key) =
+            self.mega_keyset_from_sks(&key_id).map_err(|e| match e {
+                IDkgLoadTranscriptError::PrivateKeyNotFound => {
+                    CspCheckMEGaKeyPairError::SecretKeyNotFound {
+                        key_id: KeyId::from(key_id),
+                    }
+                }
+                _ => CspCheckMEGaKeyPairError::MalformedKeyPair {
+                    internal_error: e.to_string(),
+                },
+            })?;
+        let derived_public_key =
+            private_key
+                .public_key()
+                .map_err(|e| CspCheckMEGaKeyPairError::MalformedKeyPair {
+                    internal_error: format!("{:?}", e),
+                })?;
+        if stored_public_key != *public_key || derived_public_key != *public_key {
+            return Err(CspCheckMEGaKeyPairError::PublicKeyMismatch {
+                key_id: KeyId::from(key_id),
+            });
+        }
+        Ok(())
+    }
+
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError> {
+        debug!(self.logger; crypto.method_name => "idkg_retire_mega_keys");
+
+        if public_keys
+            .iter()
+            .any(|public_key| public_key.curve_type() != EccCurveType::K256)
+        {
+            return Err(CspRetireMEGaKeysError::UnsupportedAlgorithm {
+                algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+            });
+        }
+        let mut sks = self.sks_write_lock();
+        for public_key in public_keys {
+            let key_id = KeyId::from(mega_key_id(public_key));
+            if sks.remove(&key_id) {
+                debug!(self.logger; crypto.description => format!("retired MEGa key {}", key_id));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
@@ -263,12 +417,29 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
         }
     }
 
+    /// Stores the opening of `commitment` in the canister secret key store.
+    ///
+    /// The key id of an opening is derived from the commitment, so an opening
+    /// that is already stored was stored by a concurrent load of the same
+    /// transcript and storing it again is a no-op.
+    fn store_commitment_opening(
+        &self,
+        commitment: &PolynomialCommitment,
+        opening_bytes: CommitmentOpeningBytes,
+    ) {
+        match self.store_canister_secret_key(
+            CspSecretKey::IDkgCommitmentOpening(opening_bytes),
+            KeyId::from(commitment_key_id(commitment)),
+        ) {
+            Ok(()) | Err(SecretKeyStoreError::DuplicateKeyId(_)) => (),
+        }
+    }
+
     fn commitment_opening_from_sks(
         &self,
         commitment: &PolynomialCommitment,
     ) -> Result<CommitmentOpeningBytes, IDkgCreateDealingError> {
-        let key_id = commitment_key_id(commitment);
-        let opening = self.canister_sks_read_lock().get(&key_id);
+        let opening = self.commitment_opening_from_canister_sks(commitment);
         match &opening {
             Some(CspSecretKey::IDkgCommitmentOpening(bytes)) => Ok(bytes.clone()),
             _ => Err(IDkgCreateDealingError::SecretSharesNotFound {
@@ -279,9 +450,9 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
 
     fn mega_keyset_from_sks(
         &self,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
     ) -> Result<(MEGaPublicKey, MEGaPrivateKey), IDkgLoadTranscriptError> {
-        match &self.sks_read_lock().get(key_id) {
+        match &self.sks_read_lock().get(&KeyId::from(*key_id)) {
             Some(CspSecretKey::MEGaEncryptionK256(keyset_bytes)) => {
                 let public_key =
                     MEGaPublicKey::try_from(&keyset_bytes.public_key).map_err(|e| {
@@ -301,11 +472,3 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
         }
     }
 }
-
-pub(crate) fn commitment_key_id(commitment: &PolynomialCommitment) -> KeyId {
-    let mut hash = Sha256::new_with_context(&DomainSeparationContext::new(
-        COMMITMENT_KEY_ID_DOMAIN.to_string(),
-    ));
-    hash.write(&serde_cbor::to_vec(commitment).expect("Failed to serialize commitment"));
-    KeyId::from(hash.finish())
-}
@@ -0,0 +1,16 @@
+//! Verifies the implementation of IDkgProtocolCspVault for LocalCspVault.
+use crate::vault::local_csp_vault::test_utils::new_csp_vault;
+use crate::vault::test_utils;
+
+#[test]
+fn should_check_mega_key_pair_only_where_it_is_stored() {
+    test_utils::idkg::should_check_mega_key_pair_only_where_it_is_stored(
+        new_csp_vault(),
+        new_csp_vault(),
+    );
+}
+
+#[test]
+fn should_fail_to_check_mega_key_pair_on_unsupported_curve() {
+    test_utils::idkg::should_fail_to_check_mega_key_pair_on_unsupported_curve(new_csp_vault());
+}
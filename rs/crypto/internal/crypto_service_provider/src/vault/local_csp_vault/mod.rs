@@ -11,15 +11,20 @@ mod tests;
 mod threshold_sig;
 mod tls;
 
+use crate::keygen::{commitment_key_id, legacy_commitment_key_id};
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
 use crate::secret_key_store::{SecretKeyStore, SecretKeyStoreError};
 use crate::types::CspSecretKey;
+use crate::vault::local_csp_vault::idkg::complaint_cache::ComplaintCache;
 use crate::CspRwLock;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_internal_threshold_sig_ecdsa::PolynomialCommitment;
+use ic_interfaces::time_source::{SysTimeSource, TimeSource};
 use ic_logger::replica_logger::no_op_logger;
 use ic_logger::ReplicaLogger;
 use ic_types::crypto::KeyId;
+use ic_types::time::Time;
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use rand::rngs::OsRng;
 use rand::{CryptoRng, Rng};
@@ -33,50 +38,123 @@ pub struct LocalCspVault<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C:
     node_secret_key_store: CspRwLock<S>,
     #[allow(dead_code)]
     canister_secret_key_store: CspRwLock<C>,
+    // The source of the current time, e.g. for the validity of generated
+    // certificates. Never read the system time directly, so that tests can
+    // simulate the passing of time.
+    time_source: VaultTimeSource,
+    // The complaints generated by transcripts that failed to load, so that
+    // retries return the same complaints. Held in memory only, so they are
+    // lost on restart.
+    complaint_cache: ComplaintCache,
     logger: ReplicaLogger,
 }
 
+/// The source of the current time of a vault.
+enum VaultTimeSource {
+    /// The system time, which is updated whenever it is read.
+    System(SysTimeSource),
+    /// A time source set by the creator of the vault, e.g. a simulated clock.
+    Injected(Arc<dyn TimeSource>),
+}
+
+impl VaultTimeSource {
+    fn current_time(&self) -> Time {
+        match self {
+            VaultTimeSource::System(sys_time_source) => {
+                // If the system time went backwards, the vault keeps the last
+                // time it read.
+                let _ = sys_time_source.update_time();
+                sys_time_source.get_relative_time()
+            }
+            VaultTimeSource::Injected(time_source) => time_source.get_relative_time(),
+        }
+    }
+}
+
 impl LocalCspVault<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore> {
-    /// Creates a production-grade local CSP vault.
+    /// Returns a builder of a local CSP vault that uses the OS Rng and the
+    /// given secret key stores.
     ///
-    /// The `node_secret_key_store` and the `canister_secret_key_store`
-    /// must be ProtoSecretKeyStore using distinct protobuf files.
-    pub fn new(
+    /// # Panics
+    /// If the node secret key store and the canister secret key store use the
+    /// same file.
+    pub fn builder(
         node_secret_key_store: ProtoSecretKeyStore,
         canister_secret_key_store: ProtoSecretKeyStore,
-        metrics: Arc<CryptoMetrics>,
-        logger: ReplicaLogger,
-    ) -> Self {
+    ) -> LocalCspVaultBuilder<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore> {
         if node_secret_key_store.proto_file_path() == canister_secret_key_store.proto_file_path() {
             panic!("The node secret-key-store and the canister secret-key-store must use different files")
         }
-        LocalCspVault::new_with_os_rng(
+        LocalCspVaultBuilder::new(
+            OsRng::default(),
             node_secret_key_store,
             canister_secret_key_store,
-            metrics,
-            logger,
         )
     }
 }
 
-impl<S: SecretKeyStore, C: SecretKeyStore> LocalCspVault<OsRng, S, C> {
-    /// Creates a local CSP vault setting the `csprng` to use the OS Rng.
-    pub fn new_with_os_rng(
-        node_secret_key_store: S,
-        canister_secret_key_store: C,
-        metrics: Arc<CryptoMetrics>,
-        logger: ReplicaLogger,
-    ) -> Self {
-        let csprng = OsRng::default();
-        let csprng = CspRwLock::new_for_rng(csprng, Arc::clone(&metrics));
-        LocalCspVault {
+/// A builder of a [`LocalCspVault`].
+///
+/// The metrics and the logger default to none, and the source of the current
+/// time defaults to the system time.
+pub struct LocalCspVaultBuilder<R, S, C> {
+    csprng: R,
+    node_secret_key_store: S,
+    canister_secret_key_store: C,
+    time_source: VaultTimeSource,
+    metrics: Arc<CryptoMetrics>,
+    logger: ReplicaLogger,
+}
+
+impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
+    LocalCspVaultBuilder<R, S, C>
+{
+    /// Creates a builder of a local CSP vault that uses the given `csprng` and
+    /// secret key stores.
+    pub fn new(csprng: R, node_secret_key_store: S, canister_secret_key_store: C) -> Self {
+        Self {
             csprng,
+            node_secret_key_store,
+            canister_secret_key_store,
+            time_source: VaultTimeSource::System(SysTimeSource::new()),
+            metrics: Arc::new(CryptoMetrics::none()),
+            logger: no_op_logger(),
+        }
+    }
+
+    /// Sets the metrics that the vault reports to.
+    pub fn with_metrics(mut self, metrics: Arc<CryptoMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sets the logger of the vault.
+    pub fn with_logger(mut self, logger: ReplicaLogger) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Sets the source of the current time of the vault.
+    pub fn with_time_source(mut self, time_source: Arc<dyn TimeSource>) -> Self {
+        self.time_source = VaultTimeSource::Injected(time_source);
+        self
+    }
+
+    /// Creates the local CSP vault.
+    pub fn build(self) -> LocalCspVault<R, S, C> {
+        LocalCspVault {
+            csprng: CspRwLock::new_for_rng(self.csprng, Arc::clone(&self.metrics)),
             node_secret_key_store: CspRwLock::new_for_sks(
-                node_secret_key_store,
-                Arc::clone(&metrics),
+                self.node_secret_key_store,
+                Arc::clone(&self.metrics),
             ),
-            canister_secret_key_store: CspRwLock::new_for_csks(canister_secret_key_store, metrics),
-            logger,
+            canister_secret_key_store: CspRwLock::new_for_csks(
+                self.canister_secret_key_store,
+                self.metrics,
+            ),
+            time_source: self.time_source,
+            complaint_cache: ComplaintCache::default(),
+            logger: self.logger,
         }
     }
 }
@@ -89,19 +167,19 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore>
     /// Note: This MUST NOT be used in production as the secrecy of the secret
     /// key store is not guaranteed.
     pub fn new_for_test(csprng: R, node_secret_key_store: S) -> Self {
-        let metrics = Arc::new(CryptoMetrics::none());
-        LocalCspVault {
-            csprng: CspRwLock::new_for_rng(csprng, Arc::clone(&metrics)),
-            node_secret_key_store: CspRwLock::new_for_sks(
-                node_secret_key_store,
-                Arc::clone(&metrics),
-            ),
-            canister_secret_key_store: CspRwLock::new_for_csks(
-                VolatileSecretKeyStore::new(),
-                metrics,
-            ),
-            logger: no_op_logger(),
-        }
+        LocalCspVaultBuilder::new(csprng, node_secret_key_store, VolatileSecretKeyStore::new())
+            .build()
+    }
+}
+
+impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
+    LocalCspVault<R, S, C>
+{
+    /// Replaces the source of the current time of the vault, which defaults
+    /// to the system time, e.g. by a simulated clock in tests.
+    pub fn with_time_source(mut self, time_source: Arc<dyn TimeSource>) -> Self {
+        self.time_source = VaultTimeSource::Injected(time_source);
+        self
     }
 }
 
@@ -129,26 +207,59 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
         self.canister_secret_key_store.read()
     }
 
-    fn store_secret_key_or_panic(&self, csp_secret_key: CspSecretKey, key_id: KeyId) {
-        match &self.sks_write_lock().insert(key_id, csp_secret_key, None) {
-            Ok(()) => {}
-            Err(SecretKeyStoreError::DuplicateKeyId(key_id)) => {
-                panic!("A key with ID {} has already been inserted", key_id);
-            }
-        };
+    /// Returns the opening of `commitment` from the canister secret key store.
+    ///
+    /// An opening stored under the [legacy key id](legacy_commitment_key_id)
+    /// of its commitment is moved to the current key id when it is first
+    /// retrieved.
+    fn commitment_opening_from_canister_sks(
+        &self,
+        commitment: &PolynomialCommitment,
+    ) -> Option<CspSecretKey> {
+        let key_id = KeyId::from(commitment_key_id(commitment));
+        if let Some(opening) = self.canister_sks_read_lock().get(&key_id) {
+            return Some(opening);
+        }
+        let legacy_key_id = KeyId::from(legacy_commitment_key_id(commitment));
+        let mut canister_sks = self.canister_sks_write_lock();
+        // The opening may have been migrated since the read lock was released.
+        if let Some(opening) = canister_sks.get(&key_id) {
+            return Some(opening);
+        }
+        let opening = canister_sks.get(&legacy_key_id)?;
+        match canister_sks.insert(key_id, opening.clone(), None) {
+            Ok(()) | Err(SecretKeyStoreError::DuplicateKeyId(_)) => (),
+        }
+        canister_sks.remove(&legacy_key_id);
+        Some(opening)
+    }
+
+    fn current_time(&self) -> Time {
+        self.time_source.current_time()
+    }
+
+    fn store_secret_key(
+        &self,
+        csp_secret_key: CspSecretKey,
+        key_id: KeyId,
+    ) -> Result<(), SecretKeyStoreError> {
+        self.sks_write_lock().insert(key_id, csp_secret_key, None)
     }
 
-    fn store_canister_secret_key_or_panic(&self, csp_secret_key: CspSecretKey, key_id: KeyId) {
-        match &self
-            .canister_sks_write_lock()
+    fn store_canister_secret_key(
+        &self,
+        csp_secret_key: CspSecretKey,
+        key_id: KeyId,
+    ) -> Result<(), SecretKeyStoreError> {
+        self.canister_sks_write_lock()
             .insert(key_id, csp_secret_key, None)
-        {
+    }
+
+    fn store_secret_key_or_panic(&self, csp_secret_key: CspSecretKey, key_id: KeyId) {
+        match &self.store_secret_key(csp_secret_key, key_id) {
             Ok(()) => {}
             Err(SecretKeyStoreError::DuplicateKeyId(key_id)) => {
-                panic!(
-                    "A canister secret share with ID {} has already been inserted",
-                    key_id
-                );
+                panic!("A key with ID {} has already been inserted", key_id);
             }
         };
     }
@@ -1,7 +1,6 @@
 use crate::secret_key_store::SecretKeyStore;
 use crate::types::CspSecretKey;
 use crate::vault::api::ThresholdEcdsaSignerCspVault;
-use crate::vault::local_csp_vault::idkg::commitment_key_id;
 use crate::vault::local_csp_vault::LocalCspVault;
 use ic_crypto_internal_threshold_sig_ecdsa::{
     sign_share as tecdsa_sign_share, CombinedCommitment, CommitmentOpening, IDkgTranscriptInternal,
@@ -65,8 +64,7 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
             | CombinedCommitment::ByInterpolation(commitment) => commitment,
         };
 
-        let key_id = commitment_key_id(commitment);
-        let opening = self.canister_sks_read_lock().get(&key_id);
+        let opening = self.commitment_opening_from_canister_sks(commitment);
         match &opening {
             Some(CspSecretKey::IDkgCommitmentOpening(bytes)) => CommitmentOpening::try_from(bytes)
                 .map_err(|e| ThresholdEcdsaSignShareError::InternalError {
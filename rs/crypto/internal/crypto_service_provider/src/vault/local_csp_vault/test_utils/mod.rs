@@ -3,6 +3,8 @@ pub mod temp_local_csp_server;
 use crate::secret_key_store::test_utils::TempSecretKeyStore;
 use crate::vault::api::CspVault;
 use crate::vault::local_csp_vault::LocalCspVault;
+use ic_interfaces::time_source::TimeSource;
+use ic_types::time::Time;
 use rand::{thread_rng, Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use std::sync::Arc;
@@ -12,3 +14,12 @@ pub fn new_csp_vault() -> Arc<dyn CspVault> {
     let csprng = ChaChaRng::from_seed(thread_rng().gen::<[u8; 32]>());
     Arc::new(LocalCspVault::new_for_test(csprng, key_store))
 }
+
+/// A time source that always returns the same time.
+pub struct FixedTimeSource(pub Time);
+
+impl TimeSource for FixedTimeSource {
+    fn get_relative_time(&self) -> Time {
+        self.0
+    }
+}
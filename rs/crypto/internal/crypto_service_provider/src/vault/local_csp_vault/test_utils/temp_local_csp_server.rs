@@ -1,10 +1,7 @@
 //! Utilities to test the local CSP vault.
 
 use ic_crypto_internal_csp_test_utils::files::mk_temp_dir_with_permissions;
-use ic_crypto_internal_logmon::metrics::CryptoMetrics;
-use ic_logger::replica_logger::no_op_logger;
 use rand_core::OsRng;
-use std::sync::Arc;
 use tempfile::TempDir;
 
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
@@ -33,12 +30,7 @@ impl TempLocalCspVault {
         let sks = ProtoSecretKeyStore::open(temp_dir.path(), sks_file, None);
         let canister_sks = ProtoSecretKeyStore::open(temp_dir.path(), canister_sks_file, None);
         Self {
-            vault: LocalCspVault::new(
-                sks,
-                canister_sks,
-                Arc::new(CryptoMetrics::none()),
-                no_op_logger(),
-            ),
+            vault: LocalCspVault::builder(sks, canister_sks).build(),
             tempdir: temp_dir,
         }
     }
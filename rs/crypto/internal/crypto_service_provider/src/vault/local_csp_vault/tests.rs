@@ -1,8 +1,16 @@
 //! Tests for Local CSP vault
 
+use crate::api::IDkgLoadTranscriptOutcome;
+use crate::keygen::{commitment_key_id, legacy_commitment_key_id, mega_key_id};
 use crate::secret_key_store::test_utils::{make_key_id, make_secret_key};
 use crate::secret_key_store::SecretKeyStore;
+use crate::vault::api::IDkgProtocolCspVault;
 use crate::vault::local_csp_vault::test_utils::temp_local_csp_server::TempLocalCspVault;
+use ic_crypto_internal_threshold_sig_ecdsa::{create_transcript, IDkgTranscriptOperationInternal};
+use ic_types::crypto::canister_threshold_sig::error::IDkgVerifyDealingPrivateError;
+use ic_types::crypto::{AlgorithmId, KeyId};
+use ic_types::NumberOfNodes;
+use std::collections::{BTreeMap, BTreeSet};
 #[test]
 #[should_panic(
     expected = "The node secret-key-store and the canister secret-key-store must use different files"
@@ -52,3 +60,388 @@ fn should_insert_keys_in_canister_sks() {
     // Key should be in the canister secret key store after insertion
     assert!(temp_csp.vault.canister_sks_read_lock().contains(&key_id));
 }
+
+#[test]
+fn should_not_wedge_sks_if_thread_panics_while_holding_lock() {
+    let temp_csp = std::sync::Arc::new(TempLocalCspVault::new());
+    let key_id = make_key_id(42);
+
+    let panicking_csp = std::sync::Arc::clone(&temp_csp);
+    let result = std::thread::spawn(move || {
+        let _guard = panicking_csp.vault.sks_write_lock();
+        panic!("thread panics while holding the secret key store lock");
+    })
+    .join();
+    assert!(result.is_err());
+
+    assert!(temp_csp
+        .vault
+        .sks_write_lock()
+        .insert(key_id, make_secret_key(42), None)
+        .is_ok());
+    assert!(temp_csp.vault.sks_read_lock().contains(&key_id));
+}
+
+#[test]
+fn should_generate_mega_key_pairs_concurrently() {
+    const THREADS: usize = 8;
+    let temp_csp = std::sync::Arc::new(TempLocalCspVault::new());
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let temp_csp = std::sync::Arc::clone(&temp_csp);
+            std::thread::spawn(move || {
+                temp_csp
+                    .vault
+                    .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+                    .expect("failed to generate MEGa key pair")
+                    .0
+            })
+        })
+        .collect();
+    let public_keys: Vec<_> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("thread panicked"))
+        .collect();
+
+    let key_ids: BTreeSet<_> = public_keys
+        .iter()
+        .map(|public_key| KeyId::from(mega_key_id(public_key)))
+        .collect();
+    assert_eq!(key_ids.len(), THREADS);
+    for key_id in key_ids {
+        assert!(temp_csp.vault.sks_read_lock().contains(&key_id));
+    }
+}
+
+#[test]
+fn should_create_dealings_concurrently() {
+    const THREADS: u32 = 8;
+    let temp_csp = std::sync::Arc::new(TempLocalCspVault::new());
+    let (receiver_key, _pop) = temp_csp
+        .vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate MEGa key pair");
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|dealer_index| {
+            let temp_csp = std::sync::Arc::clone(&temp_csp);
+            let receiver_key = receiver_key.clone();
+            std::thread::spawn(move || {
+                temp_csp
+                    .vault
+                    .idkg_create_dealing(
+                        AlgorithmId::ThresholdEcdsaSecp256k1,
+                        b"context data",
+                        dealer_index,
+                        NumberOfNodes::from(1),
+                        &[receiver_key],
+                        &IDkgTranscriptOperationInternal::Random,
+                    )
+                    .expect("failed to create dealing")
+            })
+        })
+        .collect();
+    let dealings: Vec<_> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("thread panicked"))
+        .collect();
+
+    // Each dealing was created from fresh randomness.
+    for (i, dealing) in dealings.iter().enumerate() {
+        assert!(dealings[i + 1..].iter().all(|other| other != dealing));
+    }
+}
+
+#[test]
+fn should_retire_only_the_given_mega_keys() {
+    let temp_csp = TempLocalCspVault::new();
+    let gen_mega_key_pair = || {
+        temp_csp
+            .vault
+            .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+            .expect("failed to generate MEGa key pair")
+            .0
+    };
+    let old_key = gen_mega_key_pair();
+    let new_key = gen_mega_key_pair();
+
+    assert!(temp_csp
+        .vault
+        .idkg_retire_mega_keys(&[old_key.clone()])
+        .is_ok());
+
+    assert!(!temp_csp
+        .vault
+        .sks_read_lock()
+        .contains(&KeyId::from(mega_key_id(&old_key))));
+    assert!(temp_csp.vault.idkg_check_mega_key_pair(&new_key).is_ok());
+    // Retiring a key that is no longer stored is a no-op.
+    assert!(temp_csp.vault.idkg_retire_mega_keys(&[old_key]).is_ok());
+    assert!(temp_csp.vault.idkg_check_mega_key_pair(&new_key).is_ok());
+}
+
+#[test]
+fn should_verify_dealing_private_only_while_mega_key_is_stored() {
+    let temp_csp = TempLocalCspVault::new();
+    let (receiver_key, _pop) = temp_csp
+        .vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate MEGa key pair");
+    let receiver_key_id = mega_key_id(&receiver_key);
+    let dealing = temp_csp
+        .vault
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context data",
+            0,
+            NumberOfNodes::from(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .expect("failed to create dealing");
+    let verify = |context_data: &[u8]| {
+        temp_csp.vault.idkg_verify_dealing_private(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            &dealing,
+            0,
+            0,
+            &receiver_key_id,
+            context_data,
+        )
+    };
+
+    assert_eq!(verify(b"context data"), Ok(()));
+    assert!(matches!(
+        verify(b"other context data"),
+        Err(IDkgVerifyDealingPrivateError::InvalidDealing { .. })
+    ));
+
+    assert!(temp_csp
+        .vault
+        .idkg_retire_mega_keys(&[receiver_key])
+        .is_ok());
+    assert_eq!(
+        verify(b"context data"),
+        Err(IDkgVerifyDealingPrivateError::PrivateKeyNotFound {
+            key_id: KeyId::from(receiver_key_id)
+        })
+    );
+}
+
+#[test]
+fn should_report_opening_already_stored_when_reloading_transcript() {
+    let temp_csp = TempLocalCspVault::new();
+    let (receiver_key, _pop) = temp_csp
+        .vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate MEGa key pair");
+    let dealing = temp_csp
+        .vault
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context data",
+            0,
+            NumberOfNodes::from(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .expect("failed to create dealing");
+    let dealings: BTreeMap<_, _> = vec![(0, dealing)].into_iter().collect();
+    let transcript = create_transcript(
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        NumberOfNodes::from(1),
+        &dealings,
+        &IDkgTranscriptOperationInternal::Random,
+    )
+    .expect("failed to create transcript");
+    let load = || {
+        temp_csp.vault.idkg_load_transcript(
+            &dealings,
+            b"context data",
+            0,
+            &mega_key_id(&receiver_key),
+            &transcript,
+        )
+    };
+
+    assert_eq!(load(), Ok(IDkgLoadTranscriptOutcome::SharesComputed));
+    assert_eq!(load(), Ok(IDkgLoadTranscriptOutcome::OpeningAlreadyStored));
+}
+
+#[test]
+fn should_migrate_opening_stored_under_legacy_key_id() {
+    let temp_csp = TempLocalCspVault::new();
+    let (receiver_key, _pop) = temp_csp
+        .vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate MEGa key pair");
+    let dealing = temp_csp
+        .vault
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context data",
+            0,
+            NumberOfNodes::from(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .expect("failed to create dealing");
+    let dealings: BTreeMap<_, _> = vec![(0, dealing)].into_iter().collect();
+    let transcript = create_transcript(
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        NumberOfNodes::from(1),
+        &dealings,
+        &IDkgTranscriptOperationInternal::Random,
+    )
+    .expect("failed to create transcript");
+    let load = || {
+        temp_csp.vault.idkg_load_transcript(
+            &dealings,
+            b"context data",
+            0,
+            &mega_key_id(&receiver_key),
+            &transcript,
+        )
+    };
+    assert_eq!(load(), Ok(IDkgLoadTranscriptOutcome::SharesComputed));
+    // Move the opening to where earlier versions stored it.
+    let commitment = transcript.combined_commitment.commitment();
+    let key_id = KeyId::from(commitment_key_id(commitment));
+    let legacy_key_id = KeyId::from(legacy_commitment_key_id(commitment));
+    let opening = {
+        let mut canister_sks = temp_csp.vault.canister_sks_write_lock();
+        let opening = canister_sks.get(&key_id).expect("opening not stored");
+        assert!(canister_sks.remove(&key_id));
+        assert!(canister_sks
+            .insert(legacy_key_id, opening.clone(), None)
+            .is_ok());
+        opening
+    };
+
+    assert_eq!(load(), Ok(IDkgLoadTranscriptOutcome::OpeningAlreadyStored));
+    let canister_sks = temp_csp.vault.canister_sks_read_lock();
+    assert_eq!(canister_sks.get(&key_id), Some(opening));
+    assert!(!canister_sks.contains(&legacy_key_id));
+}
+
+#[test]
+fn should_return_identical_complaints_when_reloading_transcript() {
+    let temp_csp = TempLocalCspVault::new();
+    let (receiver_key, _pop) = temp_csp
+        .vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate MEGa key pair");
+    let dealing = temp_csp
+        .vault
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context data",
+            0,
+            NumberOfNodes::from(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .expect("failed to create dealing");
+    let dealings: BTreeMap<_, _> = vec![(0, dealing)].into_iter().collect();
+    let transcript = create_transcript(
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        NumberOfNodes::from(1),
+        &dealings,
+        &IDkgTranscriptOperationInternal::Random,
+    )
+    .expect("failed to create transcript");
+    // The dealing can not be decrypted with other context data, so loading
+    // the transcript generates a complaint against it.
+    let load = || {
+        temp_csp
+            .vault
+            .idkg_load_transcript(
+                &dealings,
+                b"other context data",
+                0,
+                &mega_key_id(&receiver_key),
+                &transcript,
+            )
+            .expect("failed to load transcript")
+    };
+
+    let complaints = load().into_complaints();
+    assert_eq!(complaints.keys().collect::<Vec<_>>(), vec![&0]);
+    assert_eq!(load().into_complaints(), complaints);
+}
+
+#[test]
+fn should_not_return_complaints_cached_for_other_mega_key() {
+    let temp_csp = TempLocalCspVault::new();
+    let gen_mega_key = || {
+        temp_csp
+            .vault
+            .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+            .expect("failed to generate MEGa key pair")
+            .0
+    };
+    let receiver_key = gen_mega_key();
+    let rotated_receiver_key = gen_mega_key();
+    let dealing = temp_csp
+        .vault
+        .idkg_create_dealing(
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+            b"context data",
+            0,
+            NumberOfNodes::from(1),
+            &[receiver_key.clone()],
+            &IDkgTranscriptOperationInternal::Random,
+        )
+        .expect("failed to create dealing");
+    let dealings: BTreeMap<_, _> = vec![(0, dealing)].into_iter().collect();
+    let transcript = create_transcript(
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        NumberOfNodes::from(1),
+        &dealings,
+        &IDkgTranscriptOperationInternal::Random,
+    )
+    .expect("failed to create transcript");
+    let load = |key| {
+        temp_csp
+            .vault
+            .idkg_load_transcript(
+                &dealings,
+                b"other context data",
+                0,
+                &mega_key_id(key),
+                &transcript,
+            )
+            .expect("failed to load transcript")
+            .into_complaints()
+    };
+
+    let complaints = load(&receiver_key);
+    let rotated_complaints = load(&rotated_receiver_key);
+
+    assert_eq!(rotated_complaints.keys().collect::<Vec<_>>(), vec![&0]);
+    assert_ne!(rotated_complaints, complaints);
+    assert_eq!(load(&rotated_receiver_key), rotated_complaints);
+    assert_eq!(load(&receiver_key), complaints);
+}
+
+#[test]
+fn should_build_vault_with_injected_time_source() {
+    use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
+    use crate::vault::local_csp_vault::test_utils::FixedTimeSource;
+    use crate::vault::local_csp_vault::LocalCspVaultBuilder;
+    use ic_types::time::Time;
+    use rand::rngs::OsRng;
+    use std::sync::Arc;
+
+    let time = Time::from_nanos_since_unix_epoch(42);
+    let vault = LocalCspVaultBuilder::new(
+        OsRng::default(),
+        VolatileSecretKeyStore::new(),
+        VolatileSecretKeyStore::new(),
+    )
+    .with_time_source(Arc::new(FixedTimeSource(time)))
+    .build();
+
+    assert_eq!(vault.current_time(), time);
+}
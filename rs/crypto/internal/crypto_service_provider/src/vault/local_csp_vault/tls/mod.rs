@@ -5,7 +5,7 @@ use crate::types::{CspSecretKey, CspSignature};
 use crate::vault::api::{CspTlsKeygenError, CspTlsSignError, TlsHandshakeCspVault};
 use crate::vault::local_csp_vault::LocalCspVault;
 use ic_crypto_internal_basic_sig_ed25519::types as ed25519_types;
-use ic_crypto_internal_tls::keygen::generate_tls_key_pair_der;
+use ic_crypto_internal_tls::keygen::generate_tls_key_pair_der_with_not_before;
 use ic_crypto_internal_tls::keygen::TlsEd25519SecretKeyDerBytes;
 use ic_crypto_secrets_containers::{SecretArray, SecretVec};
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
@@ -30,8 +30,17 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Tls
         let common_name = &node.get().to_string()[..];
         let not_after = Asn1Time::from_str_x509(not_after)
             .expect("invalid X.509 certificate expiration date (not_after)");
-        let (cert, secret_key) =
-            generate_tls_key_pair_der(&mut *self.rng_write_lock(), common_name, serial, &not_after);
+        let not_before = Asn1Time::from_unix(
+            (self.current_time().as_nanos_since_unix_epoch() / 1_000_000_000) as i64,
+        )
+        .expect("unable to create Asn1Time");
+        let (cert, secret_key) = generate_tls_key_pair_der_with_not_before(
+            &mut *self.rng_write_lock(),
+            common_name,
+            serial,
+            &not_before,
+            &not_after,
+        );
 
         let x509_pk_cert = TlsPublicKeyCert::new_from_der(cert.bytes)
             .expect("generated X509 certificate has malformed DER encoding");
@@ -10,10 +10,13 @@ mod keygen {
     use super::*;
     use crate::secret_key_store::test_utils::MockSecretKeyStore;
     use crate::secret_key_store::SecretKeyStoreError;
-    use crate::vault::local_csp_vault::test_utils::new_csp_vault;
+    use crate::vault::local_csp_vault::test_utils::{new_csp_vault, FixedTimeSource};
     use crate::vault::local_csp_vault::LocalCspVault;
     use crate::TlsHandshakeCspVault;
     use ic_types::crypto::KeyId;
+    use ic_types::time::Time;
+    use openssl::asn1::Asn1Time;
+    use openssl::x509::X509;
     use rand::Rng;
     use std::sync::Arc;
 
@@ -113,6 +116,43 @@ mod keygen {
         let _panic =
             csp_vault.gen_tls_key_pair(node_test_id(test_utils::tls::NODE_1), date_in_the_past);
     }
+
+    #[test]
+    fn should_set_cert_not_before_to_time_of_time_source() {
+        // 2021-10-04 23:59:59 UTC
+        let csp_vault = vault_at_unix_time(1_633_391_999);
+
+        let (_key_id, cert) = csp_vault
+            .gen_tls_key_pair(
+                node_test_id(test_utils::tls::NODE_1),
+                test_utils::tls::NOT_AFTER,
+            )
+            .expect("failed to generate TLS key pair");
+
+        let x509_cert = X509::from_der(cert.as_der()).unwrap();
+        assert!(x509_cert.not_before() == Asn1Time::from_unix(1_633_391_999).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "'not after' date must not be in the past")]
+    fn should_panic_if_not_after_date_is_in_the_past_of_time_source() {
+        // 2600-01-01 00:00:00 UTC, after `NOT_AFTER`
+        let csp_vault = vault_at_unix_time(19_880_899_200);
+
+        let _panic = csp_vault.gen_tls_key_pair(
+            node_test_id(test_utils::tls::NODE_1),
+            test_utils::tls::NOT_AFTER,
+        );
+    }
+
+    /// Returns a vault whose time source returns `secs` seconds after the
+    /// UNIX epoch.
+    fn vault_at_unix_time(secs: u64) -> impl TlsHandshakeCspVault {
+        let csprng = ChaCha20Rng::from_seed(thread_rng().gen::<[u8; 32]>());
+        LocalCspVault::new_for_test(csprng, TempSecretKeyStore::new()).with_time_source(Arc::new(
+            FixedTimeSource(Time::from_nanos_since_unix_epoch(secs * 1_000_000_000)),
+        ))
+    }
 }
 
 mod sign {
@@ -7,7 +7,9 @@ use crate::vault::api::{
 use ic_types::crypto::CryptoError;
 
 pub mod api;
+pub mod dual_csp_vault;
 pub mod local_csp_vault;
+pub mod read_only_csp_vault;
 pub mod remote_csp_vault;
 #[cfg(test)]
 mod test_utils;
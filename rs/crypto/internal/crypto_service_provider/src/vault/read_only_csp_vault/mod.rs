@@ -0,0 +1,66 @@
+//! A handle on a vault that can not change the key material it manages.
+//!
+//! The `ReadOnlyCspVault` wraps a `CspVault` and only exposes the operations
+//! that neither generate keys nor write to the secret key store. Components
+//! that only verify, e.g. dealings or the presence of keys, are given a
+//! `ReadOnlyCspVault` instead of the full `CspVault`, so that they can not
+//! mutate key material by mistake: the operations that could are not part of
+//! its type.
+use crate::api::CspCheckMEGaKeyPairError;
+use crate::keygen::MegaKeyId;
+use crate::vault::api::CspVault;
+use ic_crypto_internal_threshold_sig_ecdsa::{IDkgDealingInternal, MEGaPublicKey};
+use ic_types::crypto::canister_threshold_sig::error::IDkgVerifyDealingPrivateError;
+use ic_types::crypto::{AlgorithmId, KeyId};
+use ic_types::NodeIndex;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// A handle on a `CspVault` that only exposes the operations that leave the
+/// key material of the vault unchanged.
+#[derive(Clone)]
+pub struct ReadOnlyCspVault {
+    vault: Arc<dyn CspVault>,
+}
+
+impl ReadOnlyCspVault {
+    /// Creates a read-only handle on `vault`.
+    pub fn new(vault: Arc<dyn CspVault>) -> Self {
+        Self { vault }
+    }
+
+    /// See `SecretKeyStoreCspVault::sks_contains`.
+    pub fn sks_contains(&self, key_id: &KeyId) -> bool {
+        self.vault.sks_contains(key_id)
+    }
+
+    /// See `IDkgProtocolCspVault::idkg_verify_dealing_private`.
+    pub fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: &MegaKeyId,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        self.vault.idkg_verify_dealing_private(
+            algorithm_id,
+            dealing,
+            dealer_index,
+            receiver_index,
+            receiver_key_id,
+            context_data,
+        )
+    }
+
+    /// See `IDkgProtocolCspVault::idkg_check_mega_key_pair`.
+    pub fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        self.vault.idkg_check_mega_key_pair(public_key)
+    }
+}
@@ -0,0 +1,48 @@
+use super::*;
+use crate::secret_key_store::test_utils::{make_key_id, make_secret_key};
+use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
+use crate::vault::local_csp_vault::LocalCspVault;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn local_vault() -> Arc<dyn CspVault> {
+    Arc::new(LocalCspVault::new_for_test(
+        ChaChaRng::seed_from_u64(42),
+        VolatileSecretKeyStore::new(),
+    ))
+}
+
+#[test]
+fn should_see_keys_inserted_into_underlying_vault() {
+    let vault = local_vault();
+    let read_only_vault = ReadOnlyCspVault::new(Arc::clone(&vault));
+    let key_id = make_key_id(1);
+    assert!(!read_only_vault.sks_contains(&key_id));
+
+    vault
+        .insert_secret_key(key_id, make_secret_key(1), None)
+        .expect("failed to insert the secret key");
+
+    assert!(read_only_vault.sks_contains(&key_id));
+}
+
+#[test]
+fn should_check_mega_key_pair_generated_by_underlying_vault() {
+    let vault = local_vault();
+    let read_only_vault = ReadOnlyCspVault::new(Arc::clone(&vault));
+    let (public_key, _pop) = vault
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("failed to generate the MEGa key pair");
+
+    assert!(read_only_vault
+        .idkg_check_mega_key_pair(&public_key)
+        .is_ok());
+
+    vault
+        .idkg_retire_mega_keys(&[public_key.clone()])
+        .expect("failed to retire the MEGa key pair");
+    assert!(matches!(
+        read_only_vault.idkg_check_mega_key_pair(&public_key),
+        Err(CspCheckMEGaKeyPairError::SecretKeyNotFound { .. })
+    ));
+}
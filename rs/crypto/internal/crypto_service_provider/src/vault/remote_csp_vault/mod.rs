@@ -1,14 +1,20 @@
-use crate::api::{CspCreateMEGaKeyError, CspThresholdSignError};
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError, CspThresholdSignError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::MegaKeyId;
 use crate::types::{CspPop, CspPublicCoefficients, CspPublicKey, CspSignature};
 use crate::vault::api::{
     CspBasicSignatureError, CspBasicSignatureKeygenError, CspMultiSignatureError,
     CspMultiSignatureKeygenError, CspThresholdSignatureKeygenError, CspTlsKeygenError,
     CspTlsSignError,
 };
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    CommitmentOpening, IDkgDealingInternal, IDkgTranscriptInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -19,21 +25,92 @@ use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
-    ThresholdEcdsaSignShareError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::{AlgorithmId, KeyId};
 use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use std::sync::Arc;
 use tokio::net::UnixListener;
 
+mod slow_call_tracer;
 mod tarpc_csp_vault_client;
 mod tarpc_csp_vault_server;
 
+pub use slow_call_tracer::{
+    ArgumentShape, SlowCallRecord, SlowCallTracerConfig, DEFAULT_SLOW_CALL_CAPACITY,
+    DEFAULT_SLOW_CALL_THRESHOLD,
+};
+pub use tarpc_csp_vault_client::{RemoteCspVault, RemoteCspVaultError};
+
 #[cfg(test)]
 mod tests;
 
+/// The revision of the vault API, i.e. of [`TarpcCspVault`], implemented by
+/// this crate. It must be incremented with every change of the API that is
+/// not backwards compatible, so that a replica and a vault server that are
+/// upgraded independently detect that they can not work together.
+pub const CSP_VAULT_API_REVISION: u32 = 2;
+
+/// The oldest revision of the vault API that this crate still works with,
+/// both as the revision of a server a client connects to and as the revision
+/// of a client a server serves.
+pub const MIN_CSP_VAULT_API_REVISION: u32 = 1;
+
+/// The revision of the vault API that introduced
+/// [`TarpcCspVault::slow_calls`]. Clients must not call it on servers of an
+/// older revision.
+pub const SLOW_CALLS_API_REVISION: u32 = 2;
+
+/// What a vault server offers, as reported to a client in the handshake that
+/// the client performs when connecting.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CspVaultCapabilities {
+    /// The revision of the vault API implemented by the server.
+    pub api_revision: u32,
+    /// The oldest revision of the vault API of a client the server serves.
+    pub min_client_api_revision: u32,
+    /// The algorithms for which the server generates and uses keys.
+    pub supported_algorithms: BTreeSet<AlgorithmId>,
+}
+
+impl CspVaultCapabilities {
+    /// The capabilities of a vault server of this crate.
+    pub fn of_this_server() -> Self {
+        Self {
+            api_revision: CSP_VAULT_API_REVISION,
+            min_client_api_revision: MIN_CSP_VAULT_API_REVISION,
+            supported_algorithms: [
+                AlgorithmId::Ed25519,
+                AlgorithmId::MultiBls12_381,
+                AlgorithmId::ThresBls12_381,
+                AlgorithmId::NiDkg_Groth20_Bls12_381,
+                AlgorithmId::Tls,
+                AlgorithmId::ThresholdEcdsaSecp256k1,
+            ]
+            .iter()
+            .copied()
+            .collect(),
+        }
+    }
+
+    /// Returns whether a client of this crate can work with a server with
+    /// these capabilities.
+    pub fn is_compatible_with_this_client(&self) -> bool {
+        self.api_revision >= MIN_CSP_VAULT_API_REVISION
+            && self.min_client_api_revision <= CSP_VAULT_API_REVISION
+    }
+
+    /// Returns whether a server with these capabilities serves a client that
+    /// implements revision `client_api_revision` of the vault API.
+    pub fn serves_client(&self, client_api_revision: u32) -> bool {
+        client_api_revision >= self.min_client_api_revision
+    }
+}
+
 // The actual `tarpc`-based CspVault trait.
 // As `tarpc` does not support composed traits (i.e. we cannot just write
 // that this trait implements e.g. BasicSignatureCspVault-trait)
@@ -138,14 +215,24 @@ pub trait TarpcCspVault {
         transcript_operation: IDkgTranscriptOperationInternal,
     ) -> Result<IDkgDealingInternal, IDkgCreateDealingError>;
 
+    // Corresponds to `IDkgProtocolCspVault.idkg_verify_dealing_private`
+    async fn idkg_verify_dealing_private(
+        algorithm_id: AlgorithmId,
+        dealing: IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: MegaKeyId,
+        context_data: Vec<u8>,
+    ) -> Result<(), IDkgVerifyDealingPrivateError>;
+
     // Corresponds to `IDkgProtocolCspVault.idkg_load_transcript`
     async fn idkg_load_transcript(
         dealings: BTreeMap<NodeIndex, IDkgDealingInternal>,
         context_data: Vec<u8>,
         receiver_index: NodeIndex,
-        key_id: KeyId,
+        key_id: MegaKeyId,
         transcript: IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError>;
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError>;
 
     // Corresponds to `IDkgProtocolCspVault.idkg_load_transcript_with_openings`
     #[allow(clippy::too_many_arguments)]
@@ -154,14 +241,15 @@ pub trait TarpcCspVault {
         openings: BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
         context_data: Vec<u8>,
         receiver_index: NodeIndex,
-        key_id: KeyId,
+        key_id: MegaKeyId,
         transcript: IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError>;
 
     // Corresponds to `IDkgProtocolCspVault.idkg_gen_mega_key_pair`
     async fn idkg_gen_mega_key_pair(
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError>;
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError>;
 
     // Corresponds to `IDkgProtocolCspVault.idkg_open_dealing`
     async fn idkg_open_dealing(
@@ -169,9 +257,19 @@ pub trait TarpcCspVault {
         dealer_index: NodeIndex,
         context_data: Vec<u8>,
         opener_index: NodeIndex,
-        opener_key_id: KeyId,
+        opener_key_id: MegaKeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError>;
 
+    // Corresponds to `IDkgProtocolCspVault.idkg_check_mega_key_pair`
+    async fn idkg_check_mega_key_pair(
+        public_key: MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError>;
+
+    // Corresponds to `IDkgProtocolCspVault.idkg_retire_mega_keys`
+    async fn idkg_retire_mega_keys(
+        public_keys: Vec<MEGaPublicKey>,
+    ) -> Result<(), CspRetireMEGaKeysError>;
+
     // Corresponds to `ThresholdEcdsaSignerCspVault.ecdsa_sign_share`
     #[allow(clippy::too_many_arguments)]
     async fn ecdsa_sign_share(
@@ -185,9 +283,34 @@ pub trait TarpcCspVault {
         key_times_lambda: IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError>;
+
+    // The handshake of a client with the server, which is not part of any
+    // `CspVault`-trait. The client reports the revision of the vault API it
+    // implements and the server closes the connection on any other call
+    // before a handshake with a client it serves. Methods are identified by
+    // their position in the service, so new methods are added after the
+    // existing ones and the signature of this method must never change.
+    // Since API revision 1.
+    async fn capabilities(client_api_revision: u32) -> CspVaultCapabilities;
+
+    // An admin method, which is not part of any `CspVault`-trait. Returns the
+    // calls that took longer than the threshold of the slow call tracer of
+    // the server, from the oldest to the newest. Since API revision 2.
+    async fn slow_calls() -> Vec<SlowCallRecord>;
 }
 
-pub async fn run_csp_vault_server(sks_dir: &Path, listener: UnixListener) {
-    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new(sks_dir, listener);
+/// Runs a vault server on `listener` with the secret key stores in `sks_dir`,
+/// tracing slow calls as configured by `slow_call_tracer_config` and
+/// reporting to `metrics`.
+pub async fn run_csp_vault_server(
+    sks_dir: &Path,
+    listener: UnixListener,
+    slow_call_tracer_config: SlowCallTracerConfig,
+    metrics: Arc<CryptoMetrics>,
+) {
+    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new_with_metrics(
+        sks_dir, listener, metrics,
+    )
+    .with_slow_call_tracer_config(slow_call_tracer_config);
     server.run().await
 }
@@ -0,0 +1,113 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The default of [`SlowCallTracerConfig::threshold`].
+pub const DEFAULT_SLOW_CALL_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// The default of [`SlowCallTracerConfig::capacity`].
+pub const DEFAULT_SLOW_CALL_CAPACITY: usize = 100;
+
+/// Configures which calls of the vault server are traced as slow and how
+/// many of them are kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlowCallTracerConfig {
+    /// Calls that take longer than this are recorded.
+    pub threshold: Duration,
+    /// The number of slow calls that are kept. Once it is reached, the oldest
+    /// record is evicted. No calls are recorded if it is zero.
+    pub capacity: usize,
+}
+
+impl Default for SlowCallTracerConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_SLOW_CALL_THRESHOLD,
+            capacity: DEFAULT_SLOW_CALL_CAPACITY,
+        }
+    }
+}
+
+/// The shape of an argument of a call. Shapes never contain secret data, only
+/// the sizes of arguments and public values such as algorithm ids and node
+/// indices.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArgumentShape {
+    /// The length of a byte string or of an encoding of the argument.
+    Bytes(usize),
+    /// The number of elements of a collection.
+    Count(usize),
+    /// A public value, formatted with `Debug`.
+    Value(String),
+}
+
+impl ArgumentShape {
+    pub fn value<T: std::fmt::Debug>(value: T) -> Self {
+        ArgumentShape::Value(format!("{:?}", value))
+    }
+}
+
+/// A call of the vault server that took longer than the configured
+/// threshold.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowCallRecord {
+    /// The name of the method of the vault API.
+    pub method: String,
+    /// The shapes of the arguments of the call, by argument name.
+    pub arguments: Vec<(String, ArgumentShape)>,
+    /// The time at which the call completed.
+    pub completed_at: SystemTime,
+    pub duration: Duration,
+}
+
+/// Records the shapes of the arguments of calls that take longer than a
+/// threshold in a ring buffer, to debug rare stalls of the vault server.
+pub(crate) struct SlowCallTracer {
+    config: SlowCallTracerConfig,
+    records: Mutex<VecDeque<SlowCallRecord>>,
+}
+
+impl SlowCallTracer {
+    pub fn new(config: SlowCallTracerConfig) -> Self {
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(config.capacity)),
+            config,
+        }
+    }
+
+    /// Runs `call` and records it if it took longer than the threshold. The
+    /// shapes of the arguments are only computed, with `arguments`, if the
+    /// call is recorded.
+    pub fn trace<T, A, C>(&self, method: &str, arguments: A, call: C) -> T
+    where
+        A: FnOnce() -> Vec<(&'static str, ArgumentShape)>,
+        C: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let result = call();
+        let duration = start.elapsed();
+        if duration > self.config.threshold && self.config.capacity > 0 {
+            let record = SlowCallRecord {
+                method: method.to_string(),
+                arguments: arguments()
+                    .into_iter()
+                    .map(|(name, shape)| (name.to_string(), shape))
+                    .collect(),
+                completed_at: SystemTime::now(),
+                duration,
+            };
+            let mut records = self.records.lock();
+            if records.len() >= self.config.capacity {
+                records.pop_front();
+            }
+            records.push_back(record);
+        }
+        result
+    }
+
+    /// Returns the recorded slow calls, from the oldest to the newest.
+    pub fn slow_calls(&self) -> Vec<SlowCallRecord> {
+        self.records.lock().iter().cloned().collect()
+    }
+}
@@ -1,4 +1,8 @@
-use crate::api::{CspCreateMEGaKeyError, CspThresholdSignError};
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError, CspThresholdSignError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::MegaKeyId;
 use crate::secret_key_store::{Scope, SecretKeyStoreError};
 use crate::types::{CspPop, CspPublicCoefficients, CspPublicKey, CspSecretKey, CspSignature};
 use crate::vault::api::{
@@ -8,7 +12,10 @@ use crate::vault::api::{
     NiDkgCspVault, SecretKeyStoreCspVault, ThresholdEcdsaSignerCspVault,
     ThresholdSignatureCspVault,
 };
-use crate::vault::remote_csp_vault::TarpcCspVaultClient;
+use crate::vault::remote_csp_vault::{
+    CspVaultCapabilities, SlowCallRecord, TarpcCspVaultClient, CSP_VAULT_API_REVISION,
+    MIN_CSP_VAULT_API_REVISION, SLOW_CALLS_API_REVISION,
+};
 use crate::TlsHandshakeCspVault;
 use futures::executor::block_on;
 use ic_crypto_internal_threshold_sig_bls12381::api::dkg_errors::InternalError;
@@ -17,8 +24,9 @@ use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
     CspDkgUpdateFsEpochError,
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    CommitmentOpening, IDkgDealingInternal, IDkgTranscriptInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -30,7 +38,7 @@ use ic_crypto_internal_types::NodeIndex;
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
-    ThresholdEcdsaSignShareError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::{AlgorithmId, KeyId};
@@ -48,6 +56,8 @@ use tokio_util::codec::length_delimited::LengthDelimitedCodec;
 #[allow(dead_code)]
 pub struct RemoteCspVault {
     tarpc_csp_client: TarpcCspVaultClient,
+    server_address: String,
+    server_capabilities: CspVaultCapabilities,
 }
 
 #[allow(dead_code)]
@@ -57,6 +67,35 @@ pub enum RemoteCspVaultError {
         server_address: String,
         message: String,
     },
+    /// The server did not answer the handshake, e.g. because it implements
+    /// a revision of the vault API without handshake.
+    HandshakeError {
+        server_address: String,
+        message: String,
+    },
+    /// The revisions of the vault API of the client and of the server are
+    /// incompatible.
+    ApiRevisionMismatch {
+        server_address: String,
+        client_api_revision: u32,
+        min_client_api_revision: u32,
+        server_api_revision: u32,
+        min_server_api_revision: u32,
+    },
+    /// The server does not support some of the algorithms the client
+    /// requires.
+    UnsupportedAlgorithms {
+        server_address: String,
+        algorithms: Vec<AlgorithmId>,
+    },
+    /// The revision of the vault API implemented by the server is older than
+    /// the one that introduced the called method.
+    UnsupportedMethod {
+        server_address: String,
+        method: String,
+        server_api_revision: u32,
+        min_server_api_revision: u32,
+    },
 }
 
 #[allow(dead_code)]
@@ -65,19 +104,93 @@ impl RemoteCspVault {
     /// with a server via a Unix socket specified by `socket_path`.
     /// The socket must exist before this constructor is called,
     /// otherwise the constructor will fail.
+    ///
+    /// The constructor performs a handshake with the server, which the server
+    /// requires before any other call, and fails if the revision of the vault
+    /// API implemented by the server is not compatible with the one of this
+    /// client.
     pub fn new(socket_path: &Path) -> Result<Self, RemoteCspVaultError> {
         let codec_builder = LengthDelimitedCodec::builder();
+        let server_address = socket_path.to_string_lossy().to_string();
 
         let conn = block_on(UnixStream::connect(socket_path)).map_err(|e| {
             RemoteCspVaultError::TransportError {
-                server_address: socket_path.to_string_lossy().to_string(),
+                server_address: server_address.clone(),
                 message: e.to_string(),
             }
         })?;
         let transport = serde_transport::new(codec_builder.new_framed(conn), Bincode::default());
         let client = TarpcCspVaultClient::new(Default::default(), transport).spawn();
+        let server_capabilities =
+            block_on(client.capabilities(tarpc::context::current(), CSP_VAULT_API_REVISION))
+                .map_err(|e| RemoteCspVaultError::HandshakeError {
+                    server_address: server_address.clone(),
+                    message: e.to_string(),
+                })?;
+        if !server_capabilities.is_compatible_with_this_client() {
+            return Err(RemoteCspVaultError::ApiRevisionMismatch {
+                server_address,
+                client_api_revision: CSP_VAULT_API_REVISION,
+                min_client_api_revision: server_capabilities.min_client_api_revision,
+                server_api_revision: server_capabilities.api_revision,
+                min_server_api_revision: MIN_CSP_VAULT_API_REVISION,
+            });
+        }
         Ok(RemoteCspVault {
             tarpc_csp_client: client,
+            server_address,
+            server_capabilities,
+        })
+    }
+
+    /// Like [`RemoteCspVault::new`], but additionally fails if the server
+    /// does not support all of the `required_algorithms`.
+    pub fn new_with_required_algorithms(
+        socket_path: &Path,
+        required_algorithms: &[AlgorithmId],
+    ) -> Result<Self, RemoteCspVaultError> {
+        let vault = Self::new(socket_path)?;
+        let unsupported: Vec<AlgorithmId> = required_algorithms
+            .iter()
+            .filter(|algorithm_id| {
+                !vault
+                    .server_capabilities
+                    .supported_algorithms
+                    .contains(algorithm_id)
+            })
+            .copied()
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(RemoteCspVaultError::UnsupportedAlgorithms {
+                server_address: socket_path.to_string_lossy().to_string(),
+                algorithms: unsupported,
+            });
+        }
+        Ok(vault)
+    }
+
+    /// The capabilities the server reported in the handshake.
+    pub fn server_capabilities(&self) -> &CspVaultCapabilities {
+        &self.server_capabilities
+    }
+
+    /// Returns the calls that took longer than the threshold of the slow call
+    /// tracer of the server, from the oldest to the newest. Fails if the
+    /// server does not trace slow calls yet.
+    pub fn slow_calls(&self) -> Result<Vec<SlowCallRecord>, RemoteCspVaultError> {
+        if self.server_capabilities.api_revision < SLOW_CALLS_API_REVISION {
+            return Err(RemoteCspVaultError::UnsupportedMethod {
+                server_address: self.server_address.clone(),
+                method: "slow_calls".to_string(),
+                server_api_revision: self.server_capabilities.api_revision,
+                min_server_api_revision: SLOW_CALLS_API_REVISION,
+            });
+        }
+        block_on(self.tarpc_csp_client.slow_calls(tarpc::context::current())).map_err(|e| {
+            RemoteCspVaultError::TransportError {
+                server_address: self.server_address.clone(),
+                message: e.to_string(),
+            }
         })
     }
 }
@@ -334,14 +447,39 @@ impl IDkgProtocolCspVault for RemoteCspVault {
         })
     }
 
+    fn idkg_verify_dealing_private(
+        &self,
+        algorithm_id: AlgorithmId,
+        dealing: &IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: &MegaKeyId,
+        context_data: &[u8],
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        block_on(self.tarpc_csp_client.idkg_verify_dealing_private(
+            tarpc::context::current(),
+            algorithm_id,
+            dealing.clone(),
+            dealer_index,
+            receiver_index,
+            *receiver_key_id,
+            context_data.to_vec(),
+        ))
+        .unwrap_or_else(|e| {
+            Err(IDkgVerifyDealingPrivateError::InternalError {
+                internal_error: e.to_string(),
+            })
+        })
+    }
+
     fn idkg_load_transcript(
         &self,
         dealings: &BTreeMap<NodeIndex, IDkgDealingInternal>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError> {
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError> {
         block_on(self.tarpc_csp_client.idkg_load_transcript(
             tarpc::context::current(),
             dealings.clone(),
@@ -363,7 +501,7 @@ impl IDkgProtocolCspVault for RemoteCspVault {
         openings: &BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
         context_data: &[u8],
         receiver_index: NodeIndex,
-        key_id: &KeyId,
+        key_id: &MegaKeyId,
         transcript: &IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError> {
         block_on(self.tarpc_csp_client.idkg_load_transcript_with_openings(
@@ -385,11 +523,13 @@ impl IDkgProtocolCspVault for RemoteCspVault {
     fn idkg_gen_mega_key_pair(
         &self,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError> {
-        block_on(
-            self.tarpc_csp_client
-                .idkg_gen_mega_key_pair(tarpc::context::current(), algorithm_id),
-        )
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError> {
+        block_on(self.tarpc_csp_client.idkg_gen_mega_key_pair(
+            tarpc::context::current(),
+            algorithm_id,
+            pop_node_id,
+        ))
         .unwrap_or_else(|e| {
             Err(CspCreateMEGaKeyError::CspServerError {
                 internal_error: e.to_string(),
@@ -403,7 +543,7 @@ impl IDkgProtocolCspVault for RemoteCspVault {
         dealer_index: NodeIndex,
         context_data: &[u8],
         opener_index: NodeIndex,
-        opener_key_id: &KeyId,
+        opener_key_id: &MegaKeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
         block_on(self.tarpc_csp_client.idkg_open_dealing(
             tarpc::context::current(),
@@ -419,6 +559,36 @@ impl IDkgProtocolCspVault for RemoteCspVault {
             })
         })
     }
+
+    fn idkg_check_mega_key_pair(
+        &self,
+        public_key: &MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        block_on(
+            self.tarpc_csp_client
+                .idkg_check_mega_key_pair(tarpc::context::current(), *public_key),
+        )
+        .unwrap_or_else(|e| {
+            Err(CspCheckMEGaKeyPairError::CspServerError {
+                internal_error: e.to_string(),
+            })
+        })
+    }
+
+    fn idkg_retire_mega_keys(
+        &self,
+        public_keys: &[MEGaPublicKey],
+    ) -> Result<(), CspRetireMEGaKeysError> {
+        block_on(
+            self.tarpc_csp_client
+                .idkg_retire_mega_keys(tarpc::context::current(), public_keys.to_vec()),
+        )
+        .unwrap_or_else(|e| {
+            Err(CspRetireMEGaKeysError::CspServerError {
+                internal_error: e.to_string(),
+            })
+        })
+    }
 }
 
 impl ThresholdEcdsaSignerCspVault for RemoteCspVault {
@@ -1,4 +1,8 @@
-use crate::api::{CspCreateMEGaKeyError, CspThresholdSignError};
+use crate::api::{
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspRetireMEGaKeysError, CspThresholdSignError,
+    IDkgLoadTranscriptOutcome,
+};
+use crate::keygen::MegaKeyId;
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::types::{CspPop, CspPublicCoefficients, CspPublicKey, CspSignature};
 use crate::vault::api::{
@@ -9,16 +13,22 @@ use crate::vault::api::{
     ThresholdSignatureCspVault,
 };
 use crate::vault::local_csp_vault::LocalCspVault;
-use crate::vault::remote_csp_vault::TarpcCspVault;
+use crate::vault::remote_csp_vault::slow_call_tracer::SlowCallTracer;
+use crate::vault::remote_csp_vault::{
+    ArgumentShape, CspVaultCapabilities, SlowCallRecord, SlowCallTracerConfig, TarpcCspVault,
+    TarpcCspVaultRequest,
+};
 use crate::{TlsHandshakeCspVault, CANISTER_SKS_DATA_FILENAME, SKS_DATA_FILENAME};
+use futures::StreamExt;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
     CspDkgCreateFsKeyError, CspDkgCreateReshareDealingError, CspDkgLoadPrivateKeyError,
     CspDkgUpdateFsEpochError,
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    CommitmentOpening, IDkgDealingInternal, IDkgTranscriptInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -32,7 +42,7 @@ use ic_logger::new_logger;
 use ic_logger::replica_logger::no_op_logger;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
-    ThresholdEcdsaSignShareError,
+    IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::{AlgorithmId, KeyId};
@@ -40,6 +50,7 @@ use ic_types::{NodeId, NumberOfNodes, Randomness};
 use rand::rngs::OsRng;
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tarpc::server::BaseChannel;
 #[allow(unused_imports)]
@@ -51,12 +62,16 @@ use tokio_util::codec::length_delimited::LengthDelimitedCodec;
 
 pub(crate) struct TarpcCspVaultServerImpl {
     local_csp_vault: Arc<LocalCspVault<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore>>,
+    slow_call_tracer: Arc<SlowCallTracer>,
     listener: UnixListener,
 }
 
 #[derive(Clone)]
 struct TarpcCspVaultServerWorker {
     local_csp_vault: Arc<LocalCspVault<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore>>,
+    slow_call_tracer: Arc<SlowCallTracer>,
+    // Whether the client of the connection completed the handshake.
+    handshake_completed: Arc<AtomicBool>,
 }
 
 #[tarpc::server]
@@ -69,7 +84,16 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         msg: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspBasicSignatureError> {
-        self.local_csp_vault.sign(algorithm_id, &*msg, key_id)
+        self.slow_call_tracer.trace(
+            "sign",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("msg", ArgumentShape::Bytes(msg.len())),
+                ]
+            },
+            || self.local_csp_vault.sign(algorithm_id, &*msg, key_id),
+        )
     }
 
     async fn gen_key_pair(
@@ -77,7 +101,11 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         _: context::Context,
         algorithm_id: AlgorithmId,
     ) -> Result<(KeyId, CspPublicKey), CspBasicSignatureKeygenError> {
-        self.local_csp_vault.gen_key_pair(algorithm_id)
+        self.slow_call_tracer.trace(
+            "gen_key_pair",
+            || vec![("algorithm_id", ArgumentShape::value(algorithm_id))],
+            || self.local_csp_vault.gen_key_pair(algorithm_id),
+        )
     }
 
     // `MultiSignatureCspVault`-methods.
@@ -88,8 +116,19 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspMultiSignatureError> {
-        self.local_csp_vault
-            .multi_sign(algorithm_id, &*message, key_id)
+        self.slow_call_tracer.trace(
+            "multi_sign",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("message", ArgumentShape::Bytes(message.len())),
+                ]
+            },
+            || {
+                self.local_csp_vault
+                    .multi_sign(algorithm_id, &*message, key_id)
+            },
+        )
     }
 
     async fn gen_key_pair_with_pop(
@@ -97,7 +136,11 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         _: context::Context,
         algorithm_id: AlgorithmId,
     ) -> Result<(KeyId, CspPublicKey, CspPop), CspMultiSignatureKeygenError> {
-        self.local_csp_vault.gen_key_pair_with_pop(algorithm_id)
+        self.slow_call_tracer.trace(
+            "gen_key_pair_with_pop",
+            || vec![("algorithm_id", ArgumentShape::value(algorithm_id))],
+            || self.local_csp_vault.gen_key_pair_with_pop(algorithm_id),
+        )
     }
 
     // `ThresholdSignatureCspVault`-methods.
@@ -108,8 +151,19 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspThresholdSignError> {
-        self.local_csp_vault
-            .threshold_sign(algorithm_id, &*message, key_id)
+        self.slow_call_tracer.trace(
+            "threshold_sign",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("message", ArgumentShape::Bytes(message.len())),
+                ]
+            },
+            || {
+                self.local_csp_vault
+                    .threshold_sign(algorithm_id, &*message, key_id)
+            },
+        )
     }
 
     async fn threshold_keygen_for_test(
@@ -119,10 +173,25 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         threshold: NumberOfNodes,
         signatory_eligibility: Vec<bool>,
     ) -> Result<(CspPublicCoefficients, Vec<Option<KeyId>>), CspThresholdSignatureKeygenError> {
-        self.local_csp_vault.threshold_keygen_for_test(
-            algorithm_id,
-            threshold,
-            &*signatory_eligibility,
+        self.slow_call_tracer.trace(
+            "threshold_keygen_for_test",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("threshold", ArgumentShape::value(threshold)),
+                    (
+                        "signatory_eligibility",
+                        ArgumentShape::Count(signatory_eligibility.len()),
+                    ),
+                ]
+            },
+            || {
+                self.local_csp_vault.threshold_keygen_for_test(
+                    algorithm_id,
+                    threshold,
+                    &*signatory_eligibility,
+                )
+            },
         )
     }
 
@@ -133,8 +202,14 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         node_id: NodeId,
         algorithm_id: AlgorithmId,
     ) -> Result<(CspFsEncryptionPublicKey, CspFsEncryptionPop), CspDkgCreateFsKeyError> {
-        self.local_csp_vault
-            .gen_forward_secure_key_pair(node_id, algorithm_id)
+        self.slow_call_tracer.trace(
+            "gen_forward_secure_key_pair",
+            || vec![("algorithm_id", ArgumentShape::value(algorithm_id))],
+            || {
+                self.local_csp_vault
+                    .gen_forward_secure_key_pair(node_id, algorithm_id)
+            },
+        )
     }
 
     async fn update_forward_secure_epoch(
@@ -144,8 +219,19 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         key_id: KeyId,
         epoch: Epoch,
     ) -> Result<(), CspDkgUpdateFsEpochError> {
-        self.local_csp_vault
-            .update_forward_secure_epoch(algorithm_id, key_id, epoch)
+        self.slow_call_tracer.trace(
+            "update_forward_secure_epoch",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("epoch", ArgumentShape::value(epoch)),
+                ]
+            },
+            || {
+                self.local_csp_vault
+                    .update_forward_secure_epoch(algorithm_id, key_id, epoch)
+            },
+        )
     }
 
     async fn create_dealing(
@@ -158,13 +244,31 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         receiver_keys: BTreeMap<NodeIndex, CspFsEncryptionPublicKey>,
         maybe_resharing_secret: Option<KeyId>,
     ) -> Result<CspNiDkgDealing, CspDkgCreateReshareDealingError> {
-        self.local_csp_vault.create_dealing(
-            algorithm_id,
-            dealer_index,
-            threshold,
-            epoch,
-            &receiver_keys,
-            maybe_resharing_secret,
+        self.slow_call_tracer.trace(
+            "create_dealing",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("dealer_index", ArgumentShape::value(dealer_index)),
+                    ("threshold", ArgumentShape::value(threshold)),
+                    ("epoch", ArgumentShape::value(epoch)),
+                    ("receiver_keys", ArgumentShape::Count(receiver_keys.len())),
+                    (
+                        "maybe_resharing_secret",
+                        ArgumentShape::Count(maybe_resharing_secret.iter().count()),
+                    ),
+                ]
+            },
+            || {
+                self.local_csp_vault.create_dealing(
+                    algorithm_id,
+                    dealer_index,
+                    threshold,
+                    epoch,
+                    &receiver_keys,
+                    maybe_resharing_secret,
+                )
+            },
         )
     }
 
@@ -177,12 +281,24 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         fs_key_id: KeyId,
         receiver_index: NodeIndex,
     ) -> Result<(), CspDkgLoadPrivateKeyError> {
-        self.local_csp_vault.load_threshold_signing_key(
-            algorithm_id,
-            epoch,
-            csp_transcript,
-            fs_key_id,
-            receiver_index,
+        self.slow_call_tracer.trace(
+            "load_threshold_signing_key",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("epoch", ArgumentShape::value(epoch)),
+                    ("receiver_index", ArgumentShape::value(receiver_index)),
+                ]
+            },
+            || {
+                self.local_csp_vault.load_threshold_signing_key(
+                    algorithm_id,
+                    epoch,
+                    csp_transcript,
+                    fs_key_id,
+                    receiver_index,
+                )
+            },
         )
     }
 
@@ -191,13 +307,22 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         _: context::Context,
         active_key_ids: BTreeSet<KeyId>,
     ) {
-        self.local_csp_vault
-            .retain_threshold_keys_if_present(active_key_ids)
+        let active_key_count = active_key_ids.len();
+        self.slow_call_tracer.trace(
+            "retain_threshold_keys_if_present",
+            || vec![("active_key_ids", ArgumentShape::Count(active_key_count))],
+            || {
+                self.local_csp_vault
+                    .retain_threshold_keys_if_present(active_key_ids)
+            },
+        )
     }
 
     // SecretKeyStoreCspVault-methods.
     async fn sks_contains(self, _: context::Context, key_id: KeyId) -> bool {
-        self.local_csp_vault.sks_contains(&key_id)
+        self.slow_call_tracer.trace("sks_contains", Vec::new, || {
+            self.local_csp_vault.sks_contains(&key_id)
+        })
     }
 
     // 'TlsHandshakeCspVault'-methods.
@@ -207,7 +332,10 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         node: NodeId,
         not_after: String,
     ) -> Result<(KeyId, TlsPublicKeyCert), CspTlsKeygenError> {
-        self.local_csp_vault.gen_tls_key_pair(node, &not_after)
+        self.slow_call_tracer
+            .trace("gen_tls_key_pair", Vec::new, || {
+                self.local_csp_vault.gen_tls_key_pair(node, &not_after)
+            })
     }
 
     async fn tls_sign(
@@ -216,7 +344,11 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspTlsSignError> {
-        self.local_csp_vault.tls_sign(&*message, &key_id)
+        self.slow_call_tracer.trace(
+            "tls_sign",
+            || vec![("message", ArgumentShape::Bytes(message.len()))],
+            || self.local_csp_vault.tls_sign(&*message, &key_id),
+        )
     }
 
     // `IDkgProtocolCspVault`-methods.
@@ -230,13 +362,68 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         receiver_keys: Vec<MEGaPublicKey>,
         transcript_operation: IDkgTranscriptOperationInternal,
     ) -> Result<IDkgDealingInternal, IDkgCreateDealingError> {
-        self.local_csp_vault.idkg_create_dealing(
-            algorithm_id,
-            &context_data,
-            dealer_index,
-            reconstruction_threshold,
-            &receiver_keys,
-            &transcript_operation,
+        self.slow_call_tracer.trace(
+            "idkg_create_dealing",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("context_data", ArgumentShape::Bytes(context_data.len())),
+                    ("dealer_index", ArgumentShape::value(dealer_index)),
+                    (
+                        "reconstruction_threshold",
+                        ArgumentShape::value(reconstruction_threshold),
+                    ),
+                    ("receiver_keys", ArgumentShape::Count(receiver_keys.len())),
+                    (
+                        "transcript_operation",
+                        transcript_operation_shape(&transcript_operation),
+                    ),
+                ]
+            },
+            || {
+                self.local_csp_vault.idkg_create_dealing(
+                    algorithm_id,
+                    &context_data,
+                    dealer_index,
+                    reconstruction_threshold,
+                    &receiver_keys,
+                    &transcript_operation,
+                )
+            },
+        )
+    }
+
+    async fn idkg_verify_dealing_private(
+        self,
+        _: context::Context,
+        algorithm_id: AlgorithmId,
+        dealing: IDkgDealingInternal,
+        dealer_index: NodeIndex,
+        receiver_index: NodeIndex,
+        receiver_key_id: MegaKeyId,
+        context_data: Vec<u8>,
+    ) -> Result<(), IDkgVerifyDealingPrivateError> {
+        self.slow_call_tracer.trace(
+            "idkg_verify_dealing_private",
+            || {
+                vec![
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                    ("dealing", dealing_shape(&dealing)),
+                    ("dealer_index", ArgumentShape::value(dealer_index)),
+                    ("receiver_index", ArgumentShape::value(receiver_index)),
+                    ("context_data", ArgumentShape::Bytes(context_data.len())),
+                ]
+            },
+            || {
+                self.local_csp_vault.idkg_verify_dealing_private(
+                    algorithm_id,
+                    &dealing,
+                    dealer_index,
+                    receiver_index,
+                    &receiver_key_id,
+                    &context_data,
+                )
+            },
         )
     }
 
@@ -246,15 +433,28 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         dealings: BTreeMap<NodeIndex, IDkgDealingInternal>,
         context_data: Vec<u8>,
         receiver_index: NodeIndex,
-        key_id: KeyId,
+        key_id: MegaKeyId,
         transcript: IDkgTranscriptInternal,
-    ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError> {
-        self.local_csp_vault.idkg_load_transcript(
-            &dealings,
-            &context_data,
-            receiver_index,
-            &key_id,
-            &transcript,
+    ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError> {
+        self.slow_call_tracer.trace(
+            "idkg_load_transcript",
+            || {
+                vec![
+                    ("dealings", ArgumentShape::Count(dealings.len())),
+                    ("context_data", ArgumentShape::Bytes(context_data.len())),
+                    ("receiver_index", ArgumentShape::value(receiver_index)),
+                    ("transcript", transcript_shape(&transcript)),
+                ]
+            },
+            || {
+                self.local_csp_vault.idkg_load_transcript(
+                    &dealings,
+                    &context_data,
+                    receiver_index,
+                    &key_id,
+                    &transcript,
+                )
+            },
         )
     }
 
@@ -265,16 +465,33 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         openings: BTreeMap<NodeIndex, BTreeMap<NodeIndex, CommitmentOpening>>,
         context_data: Vec<u8>,
         receiver_index: NodeIndex,
-        key_id: KeyId,
+        key_id: MegaKeyId,
         transcript: IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError> {
-        self.local_csp_vault.idkg_load_transcript_with_openings(
-            &dealings,
-            &openings,
-            &context_data,
-            receiver_index,
-            &key_id,
-            &transcript,
+        self.slow_call_tracer.trace(
+            "idkg_load_transcript_with_openings",
+            || {
+                vec![
+                    ("dealings", ArgumentShape::Count(dealings.len())),
+                    (
+                        "openings",
+                        ArgumentShape::Count(openings.values().map(BTreeMap::len).sum()),
+                    ),
+                    ("context_data", ArgumentShape::Bytes(context_data.len())),
+                    ("receiver_index", ArgumentShape::value(receiver_index)),
+                    ("transcript", transcript_shape(&transcript)),
+                ]
+            },
+            || {
+                self.local_csp_vault.idkg_load_transcript_with_openings(
+                    &dealings,
+                    &openings,
+                    &context_data,
+                    receiver_index,
+                    &key_id,
+                    &transcript,
+                )
+            },
         )
     }
 
@@ -282,8 +499,16 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         self,
         _: context::Context,
         algorithm_id: AlgorithmId,
-    ) -> Result<MEGaPublicKey, CspCreateMEGaKeyError> {
-        self.local_csp_vault.idkg_gen_mega_key_pair(algorithm_id)
+        pop_node_id: Option<NodeId>,
+    ) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError> {
+        self.slow_call_tracer.trace(
+            "idkg_gen_mega_key_pair",
+            || vec![("algorithm_id", ArgumentShape::value(algorithm_id))],
+            || {
+                self.local_csp_vault
+                    .idkg_gen_mega_key_pair(algorithm_id, pop_node_id)
+            },
+        )
     }
 
     async fn idkg_open_dealing(
@@ -293,14 +518,52 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         dealer_index: NodeIndex,
         context_data: Vec<u8>,
         opener_index: NodeIndex,
-        opener_key_id: KeyId,
+        opener_key_id: MegaKeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
-        self.local_csp_vault.idkg_open_dealing(
-            dealing,
-            dealer_index,
-            &context_data,
-            opener_index,
-            &opener_key_id,
+        // The dealing is moved into the call, so its shape is taken before.
+        let dealing_shape = dealing_shape(&dealing);
+        self.slow_call_tracer.trace(
+            "idkg_open_dealing",
+            || {
+                vec![
+                    ("dealing", dealing_shape),
+                    ("dealer_index", ArgumentShape::value(dealer_index)),
+                    ("context_data", ArgumentShape::Bytes(context_data.len())),
+                    ("opener_index", ArgumentShape::value(opener_index)),
+                ]
+            },
+            || {
+                self.local_csp_vault.idkg_open_dealing(
+                    dealing,
+                    dealer_index,
+                    &context_data,
+                    opener_index,
+                    &opener_key_id,
+                )
+            },
+        )
+    }
+
+    async fn idkg_check_mega_key_pair(
+        self,
+        _: context::Context,
+        public_key: MEGaPublicKey,
+    ) -> Result<(), CspCheckMEGaKeyPairError> {
+        self.slow_call_tracer
+            .trace("idkg_check_mega_key_pair", Vec::new, || {
+                self.local_csp_vault.idkg_check_mega_key_pair(&public_key)
+            })
+    }
+
+    async fn idkg_retire_mega_keys(
+        self,
+        _: context::Context,
+        public_keys: Vec<MEGaPublicKey>,
+    ) -> Result<(), CspRetireMEGaKeysError> {
+        self.slow_call_tracer.trace(
+            "idkg_retire_mega_keys",
+            || vec![("public_keys", ArgumentShape::Count(public_keys.len()))],
+            || self.local_csp_vault.idkg_retire_mega_keys(&public_keys),
         )
     }
 
@@ -318,43 +581,128 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         key_times_lambda: IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError> {
-        self.local_csp_vault.ecdsa_sign_share(
-            &derivation_path,
-            &hashed_message,
-            &nonce,
-            &key,
-            &kappa_unmasked,
-            &lambda_masked,
-            &kappa_times_lambda,
-            &key_times_lambda,
-            algorithm_id,
+        self.slow_call_tracer.trace(
+            "ecdsa_sign_share",
+            || {
+                vec![
+                    (
+                        "derivation_path",
+                        ArgumentShape::Count(derivation_path.derivation_path.len()),
+                    ),
+                    ("hashed_message", ArgumentShape::Bytes(hashed_message.len())),
+                    ("key", transcript_shape(&key)),
+                    ("kappa_unmasked", transcript_shape(&kappa_unmasked)),
+                    ("lambda_masked", transcript_shape(&lambda_masked)),
+                    ("kappa_times_lambda", transcript_shape(&kappa_times_lambda)),
+                    ("key_times_lambda", transcript_shape(&key_times_lambda)),
+                    ("algorithm_id", ArgumentShape::value(algorithm_id)),
+                ]
+            },
+            || {
+                self.local_csp_vault.ecdsa_sign_share(
+                    &derivation_path,
+                    &hashed_message,
+                    &nonce,
+                    &key,
+                    &kappa_unmasked,
+                    &lambda_masked,
+                    &kappa_times_lambda,
+                    &key_times_lambda,
+                    algorithm_id,
+                )
+            },
         )
     }
+
+    async fn capabilities(
+        self,
+        _: context::Context,
+        client_api_revision: u32,
+    ) -> CspVaultCapabilities {
+        let capabilities = CspVaultCapabilities::of_this_server();
+        if capabilities.serves_client(client_api_revision) {
+            self.handshake_completed.store(true, Ordering::SeqCst);
+        }
+        capabilities
+    }
+
+    async fn slow_calls(self, _: context::Context) -> Vec<SlowCallRecord> {
+        self.slow_call_tracer.slow_calls()
+    }
+}
+
+/// The shape of a dealing is the number of its recipients.
+fn dealing_shape(dealing: &IDkgDealingInternal) -> ArgumentShape {
+    ArgumentShape::Count(dealing.ciphertext.recipients())
+}
+
+/// The shape of a transcript is the size of the encoding of its commitment,
+/// which grows with the reconstruction threshold.
+fn transcript_shape(transcript: &IDkgTranscriptInternal) -> ArgumentShape {
+    ArgumentShape::Bytes(
+        transcript
+            .combined_commitment
+            .commitment()
+            .stable_representation()
+            .len(),
+    )
+}
+
+fn transcript_operation_shape(operation: &IDkgTranscriptOperationInternal) -> ArgumentShape {
+    let name = match operation {
+        IDkgTranscriptOperationInternal::Random => "Random",
+        IDkgTranscriptOperationInternal::ReshareOfMasked(_) => "ReshareOfMasked",
+        IDkgTranscriptOperationInternal::ReshareOfUnmasked(_) => "ReshareOfUnmasked",
+        IDkgTranscriptOperationInternal::UnmaskedTimesMasked(_, _) => "UnmaskedTimesMasked",
+    };
+    ArgumentShape::Value(name.to_string())
 }
 
 impl TarpcCspVaultServerImpl {
     pub fn new(sks_dir: &Path, listener: UnixListener) -> Self {
+        Self::new_with_metrics(sks_dir, listener, Arc::new(CryptoMetrics::none()))
+    }
+
+    /// Creates a server whose vault and secret key stores report to
+    /// `metrics`, e.g. the number of keys held by the stores.
+    pub fn new_with_metrics(
+        sks_dir: &Path,
+        listener: UnixListener,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Self {
         // TODO(CRP-1254: add a real logger.
         let logger = no_op_logger();
-        let node_secret_key_store =
-            ProtoSecretKeyStore::open(sks_dir, SKS_DATA_FILENAME, Some(new_logger!(&logger)));
-        let canister_secret_key_store = ProtoSecretKeyStore::open(
+        let node_secret_key_store = ProtoSecretKeyStore::open_with_metrics(
+            sks_dir,
+            SKS_DATA_FILENAME,
+            Some(new_logger!(&logger)),
+            Arc::clone(&metrics),
+        );
+        let canister_secret_key_store = ProtoSecretKeyStore::open_with_metrics(
             sks_dir,
             CANISTER_SKS_DATA_FILENAME,
             Some(new_logger!(&logger)),
+            Arc::clone(&metrics),
+        );
+        let local_csp_server = Arc::new(
+            LocalCspVault::builder(node_secret_key_store, canister_secret_key_store)
+                .with_logger(new_logger!(&logger))
+                .with_metrics(metrics)
+                .build(),
         );
-        let local_csp_server = Arc::new(LocalCspVault::new(
-            node_secret_key_store,
-            canister_secret_key_store,
-            Arc::new(CryptoMetrics::none()),
-            new_logger!(&logger),
-        ));
         Self {
             local_csp_vault: local_csp_server,
+            slow_call_tracer: Arc::new(SlowCallTracer::new(SlowCallTracerConfig::default())),
             listener,
         }
     }
 
+    /// Traces the slow calls of all clients as configured by `config`.
+    pub fn with_slow_call_tracer_config(mut self, config: SlowCallTracerConfig) -> Self {
+        self.slow_call_tracer = Arc::new(SlowCallTracer::new(config));
+        self
+    }
+
     pub async fn run(self) {
         // Wrap data in telegrams with a length header.
         let codec_builder = LengthDelimitedCodec::builder();
@@ -369,15 +717,33 @@ impl TarpcCspVaultServerImpl {
                 )
             });
             let local_csp_server = Arc::clone(&self.local_csp_vault);
+            let slow_call_tracer = Arc::clone(&self.slow_call_tracer);
             tokio::spawn(async move {
                 let framed = codec_builder.new_framed(conn);
                 let transport = serde_transport::new(framed, Bincode::default());
                 let worker = TarpcCspVaultServerWorker {
                     local_csp_vault: local_csp_server,
+                    slow_call_tracer,
+                    handshake_completed: Arc::new(AtomicBool::new(false)),
                 };
-                let channel_executor =
-                    BaseChannel::with_defaults(transport).execute(worker.serve());
-                channel_executor.await;
+                let requests = BaseChannel::with_defaults(transport).requests();
+                futures::pin_mut!(requests);
+                while let Some(Ok(request)) = requests.next().await {
+                    match request.get().message {
+                        // The handshake is executed before the next request is
+                        // read, so that a client may send its calls right after
+                        // the handshake without waiting for its response.
+                        TarpcCspVaultRequest::Capabilities { .. } => {
+                            request.execute(worker.clone().serve()).await
+                        }
+                        _ if worker.handshake_completed.load(Ordering::SeqCst) => {
+                            tokio::spawn(request.execute(worker.clone().serve()));
+                        }
+                        // Close the connection of a client that did not
+                        // complete the handshake.
+                        _ => break,
+                    }
+                }
             });
         }
     }
@@ -6,6 +6,7 @@
 use crate::vault::api::CspVault;
 use crate::vault::remote_csp_vault::tarpc_csp_vault_client::RemoteCspVault;
 use crate::vault::remote_csp_vault::tarpc_csp_vault_server;
+use crate::vault::remote_csp_vault::SlowCallTracerConfig;
 use crate::vault::test_utils;
 use ic_crypto_internal_csp_test_utils::files::mk_temp_dir_with_permissions;
 use std::path::PathBuf;
@@ -13,6 +14,12 @@ use std::sync::Arc;
 use tokio::net::UnixListener;
 
 fn start_new_csp_vault_server() -> PathBuf {
+    start_new_csp_vault_server_with_slow_call_tracer_config(SlowCallTracerConfig::default())
+}
+
+fn start_new_csp_vault_server_with_slow_call_tracer_config(
+    slow_call_tracer_config: SlowCallTracerConfig,
+) -> PathBuf {
     let socket_path = test_utils::get_temp_file_path();
     let return_socket_path = socket_path.clone();
     let _ignore_if_file_does_not_exist = std::fs::remove_file(&socket_path);
@@ -24,7 +31,8 @@ fn start_new_csp_vault_server() -> PathBuf {
             e
         )
     });
-    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new(sks_dir.path(), listener);
+    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new(sks_dir.path(), listener)
+        .with_slow_call_tracer_config(slow_call_tracer_config);
     tokio::spawn(async move {
         let _move_temp_dir_here_to_ensure_it_is_not_cleaned_up = sks_dir;
         server.run().await;
@@ -153,6 +161,70 @@ mod secret_key_store {
     }
 }
 
+mod metrics {
+    use super::*;
+    use crate::vault::api::BasicSignatureCspVault;
+    use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+    use ic_metrics::MetricsRegistry;
+    use ic_types::crypto::AlgorithmId;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_count_keys_of_secret_key_store() {
+        let registry = MetricsRegistry::new();
+        let socket_path = test_utils::get_temp_file_path();
+        let _ignore_if_file_does_not_exist = std::fs::remove_file(&socket_path);
+        let sks_dir = mk_temp_dir_with_permissions(0o700);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new_with_metrics(
+            sks_dir.path(),
+            listener,
+            Arc::new(CryptoMetrics::new(Some(&registry))),
+        );
+        tokio::spawn(async move {
+            let _move_temp_dir_here_to_ensure_it_is_not_cleaned_up = sks_dir;
+            server.run().await;
+        });
+        let vault = RemoteCspVault::new(&socket_path).expect("Could not create RemoteCspVault");
+
+        vault.gen_key_pair(AlgorithmId::Ed25519).unwrap();
+
+        assert_eq!(key_count(&registry, "Ed25519"), Some(1));
+    }
+
+    fn key_count(registry: &MetricsRegistry, key_type: &str) -> Option<i64> {
+        registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == "ic_crypto_secret_key_store_keys")
+            .flat_map(|family| family.get_metric().iter())
+            .find(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "key_type" && label.get_value() == key_type)
+            })
+            .map(|metric| metric.get_gauge().get_value() as i64)
+    }
+}
+
+mod idkg {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_check_mega_key_pair_only_where_it_is_stored() {
+        test_utils::idkg::should_check_mega_key_pair_only_where_it_is_stored(
+            new_csp_vault(),
+            new_csp_vault(),
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_fail_to_check_mega_key_pair_on_unsupported_curve() {
+        test_utils::idkg::should_fail_to_check_mega_key_pair_on_unsupported_curve(new_csp_vault());
+    }
+}
+
 mod ni_dkg {
     use super::*;
     use crate::vault::test_utils;
@@ -314,3 +386,229 @@ mod tls_sign {
         test_utils::tls::should_fail_to_sign_if_secret_key_in_store_has_wrong_type(new_csp_vault());
     }
 }
+
+mod handshake {
+    use super::*;
+    use crate::vault::remote_csp_vault::tarpc_csp_vault_client::RemoteCspVaultError;
+    use crate::vault::remote_csp_vault::{
+        CspVaultCapabilities, TarpcCspVaultClient, CSP_VAULT_API_REVISION,
+        MIN_CSP_VAULT_API_REVISION,
+    };
+    use ic_types::crypto::{AlgorithmId, KeyId};
+    use std::path::Path;
+    use tarpc::context;
+    use tarpc::serde_transport;
+    use tarpc::tokio_serde::formats::Bincode;
+    use tokio::net::UnixStream;
+    use tokio_util::codec::length_delimited::LengthDelimitedCodec;
+
+    async fn connect_without_handshake(socket_path: &Path) -> TarpcCspVaultClient {
+        let conn = UnixStream::connect(socket_path).await.unwrap();
+        let transport = serde_transport::new(
+            LengthDelimitedCodec::builder().new_framed(conn),
+            Bincode::default(),
+        );
+        TarpcCspVaultClient::new(Default::default(), transport).spawn()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_receive_capabilities_of_server() {
+        let socket_path = start_new_csp_vault_server();
+
+        let vault = RemoteCspVault::new(&socket_path).unwrap();
+
+        assert_eq!(
+            vault.server_capabilities(),
+            &CspVaultCapabilities::of_this_server()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_connect_if_required_algorithms_are_supported() {
+        let socket_path = start_new_csp_vault_server();
+
+        let result = RemoteCspVault::new_with_required_algorithms(
+            &socket_path,
+            &[AlgorithmId::Ed25519, AlgorithmId::ThresholdEcdsaSecp256k1],
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_fail_if_required_algorithms_are_not_supported() {
+        let socket_path = start_new_csp_vault_server();
+
+        let result = RemoteCspVault::new_with_required_algorithms(
+            &socket_path,
+            &[
+                AlgorithmId::Ed25519,
+                AlgorithmId::RsaSha256,
+                AlgorithmId::EcdsaP256,
+            ],
+        );
+
+        assert_eq!(
+            result.err(),
+            Some(RemoteCspVaultError::UnsupportedAlgorithms {
+                server_address: socket_path.to_string_lossy().to_string(),
+                algorithms: vec![AlgorithmId::RsaSha256, AlgorithmId::EcdsaP256],
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_serve_calls_after_handshake() {
+        let socket_path = start_new_csp_vault_server();
+        let client = connect_without_handshake(&socket_path).await;
+
+        client
+            .capabilities(context::current(), CSP_VAULT_API_REVISION)
+            .await
+            .unwrap();
+        let result = client
+            .sks_contains(context::current(), KeyId::from([0; 32]))
+            .await;
+
+        assert_eq!(result.ok(), Some(false));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_close_connection_of_client_skipping_handshake() {
+        let socket_path = start_new_csp_vault_server();
+        let client = connect_without_handshake(&socket_path).await;
+
+        let result = client
+            .sks_contains(context::current(), KeyId::from([0; 32]))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_close_connection_of_client_of_unserved_api_revision() {
+        let socket_path = start_new_csp_vault_server();
+        let client = connect_without_handshake(&socket_path).await;
+
+        let capabilities = client
+            .capabilities(context::current(), MIN_CSP_VAULT_API_REVISION - 1)
+            .await
+            .unwrap();
+        let result = client
+            .sks_contains(context::current(), KeyId::from([0; 32]))
+            .await;
+
+        assert!(!capabilities.serves_client(MIN_CSP_VAULT_API_REVISION - 1));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_serve_only_clients_of_supported_api_revisions() {
+        let this_server = CspVaultCapabilities::of_this_server();
+
+        assert!(this_server.serves_client(CSP_VAULT_API_REVISION));
+        assert!(this_server.serves_client(MIN_CSP_VAULT_API_REVISION));
+        assert!(!this_server.serves_client(MIN_CSP_VAULT_API_REVISION - 1));
+    }
+
+    #[test]
+    fn should_be_compatible_only_with_overlapping_api_revisions() {
+        let this_server = CspVaultCapabilities::of_this_server();
+        assert!(this_server.is_compatible_with_this_client());
+
+        let newer_server = CspVaultCapabilities {
+            api_revision: CSP_VAULT_API_REVISION + 1,
+            ..this_server.clone()
+        };
+        assert!(newer_server.is_compatible_with_this_client());
+
+        let server_dropping_this_client = CspVaultCapabilities {
+            api_revision: CSP_VAULT_API_REVISION + 2,
+            min_client_api_revision: CSP_VAULT_API_REVISION + 1,
+            ..this_server.clone()
+        };
+        assert!(!server_dropping_this_client.is_compatible_with_this_client());
+
+        let outdated_server = CspVaultCapabilities {
+            api_revision: MIN_CSP_VAULT_API_REVISION - 1,
+            min_client_api_revision: 0,
+            ..this_server
+        };
+        assert!(!outdated_server.is_compatible_with_this_client());
+    }
+}
+
+mod slow_calls {
+    use super::*;
+    use crate::vault::api::BasicSignatureCspVault;
+    use crate::vault::remote_csp_vault::ArgumentShape;
+    use ic_types::crypto::AlgorithmId;
+    use std::time::Duration;
+
+    fn remote_vault_tracing(threshold: Duration, capacity: usize) -> RemoteCspVault {
+        let socket_path =
+            start_new_csp_vault_server_with_slow_call_tracer_config(SlowCallTracerConfig {
+                threshold,
+                capacity,
+            });
+        RemoteCspVault::new(&socket_path).expect("Could not create RemoteCspVault")
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_record_shapes_of_calls_slower_than_threshold() {
+        let vault = remote_vault_tracing(Duration::ZERO, 10);
+        let (key_id, _) = vault.gen_key_pair(AlgorithmId::Ed25519).unwrap();
+        vault
+            .sign(AlgorithmId::Ed25519, &[42; 123], key_id)
+            .unwrap();
+
+        let slow_calls = vault.slow_calls().unwrap();
+
+        assert_eq!(slow_calls.len(), 2);
+        assert_eq!(slow_calls[0].method, "gen_key_pair");
+        assert_eq!(slow_calls[1].method, "sign");
+        assert_eq!(
+            slow_calls[1].arguments,
+            vec![
+                (
+                    "algorithm_id".to_string(),
+                    ArgumentShape::Value("Ed25519".to_string())
+                ),
+                ("msg".to_string(), ArgumentShape::Bytes(123)),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_not_record_calls_faster_than_threshold() {
+        let vault = remote_vault_tracing(Duration::from_secs(3600), 10);
+        let (key_id, _) = vault.gen_key_pair(AlgorithmId::Ed25519).unwrap();
+        vault
+            .sign(AlgorithmId::Ed25519, b"message", key_id)
+            .unwrap();
+
+        assert_eq!(vault.slow_calls().unwrap(), vec![]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_evict_oldest_calls_once_capacity_is_reached() {
+        let vault = remote_vault_tracing(Duration::ZERO, 2);
+        let (key_id, _) = vault.gen_key_pair(AlgorithmId::Ed25519).unwrap();
+        vault.sign(AlgorithmId::Ed25519, &[1; 1], key_id).unwrap();
+        vault.sign(AlgorithmId::Ed25519, &[2; 2], key_id).unwrap();
+
+        let slow_calls = vault.slow_calls().unwrap();
+
+        let shapes: Vec<_> = slow_calls
+            .iter()
+            .map(|call| (call.method.as_str(), call.arguments[1].1.clone()))
+            .collect();
+        assert_eq!(
+            shapes,
+            vec![
+                ("sign", ArgumentShape::Bytes(1)),
+                ("sign", ArgumentShape::Bytes(2))
+            ]
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use crate::api::CspCheckMEGaKeyPairError;
+use crate::keygen::mega_key_id;
+use crate::vault::api::CspVault;
+use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, EccPoint, MEGaPublicKey};
+use ic_types::crypto::{AlgorithmId, KeyId};
+use std::sync::Arc;
+
+/// A MEGa key pair should check out only in the vault that generated it.
+pub fn should_check_mega_key_pair_only_where_it_is_stored(
+    csp_vault1: Arc<dyn CspVault>,
+    csp_vault2: Arc<dyn CspVault>,
+) {
+    let (public_key, _pop) = csp_vault1
+        .idkg_gen_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, None)
+        .expect("Test setup failed: Failed to generate MEGa key pair");
+
+    assert_eq!(csp_vault1.idkg_check_mega_key_pair(&public_key), Ok(()));
+    assert_eq!(
+        csp_vault2.idkg_check_mega_key_pair(&public_key),
+        Err(CspCheckMEGaKeyPairError::SecretKeyNotFound {
+            key_id: KeyId::from(mega_key_id(&public_key)),
+        })
+    );
+}
+
+/// A MEGa public key on a curve other than secp256k1 should be rejected.
+pub fn should_fail_to_check_mega_key_pair_on_unsupported_curve(csp_vault: Arc<dyn CspVault>) {
+    let public_key = MEGaPublicKey::new(
+        EccPoint::generator_g(EccCurveType::P256).expect("failed to get the P256 generator"),
+    );
+
+    assert_eq!(
+        csp_vault.idkg_check_mega_key_pair(&public_key),
+        Err(CspCheckMEGaKeyPairError::UnsupportedAlgorithm {
+            algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+        })
+    );
+}
@@ -1,9 +1,9 @@
 //! Metrics exported by crypto
 
 use ic_metrics::MetricsRegistry;
-use prometheus::HistogramVec;
+use prometheus::{GaugeVec, HistogramVec, IntCounterVec, IntGaugeVec};
 use std::time;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Provides metrics for the crypto component.
 ///
@@ -71,6 +71,86 @@ impl CryptoMetrics {
                 .observe(start_time.elapsed().as_secs_f64());
         }
     }
+
+    /// Counts an iDKG or threshold ECDSA method call that failed because a
+    /// secret was missing from the secret key store. The `secret` label is
+    /// either 'secret_shares' or 'private_key'.
+    pub fn inc_idkg_secret_not_found(&self, method_name: &str, secret: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_idkg_secret_not_found_total
+                .with_label_values(&[method_name, secret])
+                .inc();
+        }
+    }
+
+    /// Counts a successful iDKG transcript load by its outcome. The `outcome`
+    /// label is either 'opening_already_stored' if the transcript was loaded
+    /// before, 'shares_computed', or 'complaints_generated'.
+    pub fn inc_idkg_load_transcript(&self, outcome: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_idkg_load_transcript_total
+                .with_label_values(&[outcome])
+                .inc();
+        }
+    }
+
+    /// Sets the number of keys of type `key_type` held by the secret key store
+    /// `store`. The `key_type` label is the name of the `CspSecretKey`
+    /// variant, such as `MEGaEncryptionK256`.
+    pub fn set_secret_key_store_key_count(&self, store: &str, key_type: &str, count: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_secret_key_store_keys
+                .with_label_values(&[store, key_type])
+                .set(count as i64);
+        }
+    }
+
+    /// Sets the size in bytes of the file of the secret key store `store`.
+    pub fn set_secret_key_store_file_size(&self, store: &str, size: u64) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_secret_key_store_file_size_bytes
+                .with_label_values(&[store])
+                .set(size as i64);
+        }
+    }
+
+    /// Sets the time it took to load the secret key store `store` from its
+    /// file.
+    pub fn set_secret_key_store_load_duration(&self, store: &str, duration: Duration) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_secret_key_store_load_duration_seconds
+                .with_label_values(&[store])
+                .set(duration.as_secs_f64());
+        }
+    }
+
+    /// Counts a rewrite of the file of the secret key store `store` that
+    /// dropped the parts of the file that do not encode its keys.
+    pub fn inc_secret_key_store_compactions(&self, store: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_secret_key_store_compactions_total
+                .with_label_values(&[store])
+                .inc();
+        }
+    }
+
+    /// Counts a vault call for which the secondary vault of a dual vault
+    /// returned a different result than the primary vault. The `method_name`
+    /// indicates the vault method, such as `sign`.
+    pub fn inc_vault_result_mismatch(&self, method_name: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .ic_crypto_vault_result_mismatch_total
+                .with_label_values(&[method_name])
+                .inc();
+        }
+    }
 }
 
 struct Metrics {
@@ -80,6 +160,31 @@ struct Metrics {
     /// Histogram of `NiDkgAlgorithm` method call times. The 'method_name' label
     /// indicates the method name, such as `load_transcript`.
     pub ic_crypto_ni_dkg_method_duration_seconds: HistogramVec,
+    /// Counter of iDKG and threshold ECDSA method calls that failed because
+    /// a secret was not found. The 'secret' label is either 'secret_shares'
+    /// or 'private_key'.
+    pub ic_crypto_idkg_secret_not_found_total: IntCounterVec,
+    /// Counter of successful iDKG transcript loads. The 'outcome' label is
+    /// either 'opening_already_stored', 'shares_computed', or
+    /// 'complaints_generated'.
+    pub ic_crypto_idkg_load_transcript_total: IntCounterVec,
+    /// Gauge of the number of keys held by a secret key store. The 'store'
+    /// label is the file name of the store, the 'key_type' label is the name
+    /// of the `CspSecretKey` variant.
+    pub ic_crypto_secret_key_store_keys: IntGaugeVec,
+    /// Gauge of the size of the file of a secret key store. The 'store' label
+    /// is the file name of the store.
+    pub ic_crypto_secret_key_store_file_size_bytes: IntGaugeVec,
+    /// Gauge of the time it took to load a secret key store from its file
+    /// when it was opened. The 'store' label is the file name of the store.
+    pub ic_crypto_secret_key_store_load_duration_seconds: GaugeVec,
+    /// Counter of compactions of the file of a secret key store. The 'store'
+    /// label is the file name of the store.
+    pub ic_crypto_secret_key_store_compactions_total: IntCounterVec,
+    /// Counter of vault calls for which the primary and the secondary vault
+    /// of a dual vault returned different results. The 'method_name' label
+    /// indicates the vault method.
+    pub ic_crypto_vault_result_mismatch_total: IntCounterVec,
 }
 
 impl Metrics {
@@ -100,6 +205,41 @@ impl Metrics {
                 ],
                 &["method_name"],
             ),
+            ic_crypto_idkg_secret_not_found_total: r.int_counter_vec(
+                "ic_crypto_idkg_secret_not_found_total",
+                "Number of iDKG and threshold ECDSA method calls that failed due to a missing secret",
+                &["method_name", "secret"],
+            ),
+            ic_crypto_idkg_load_transcript_total: r.int_counter_vec(
+                "ic_crypto_idkg_load_transcript_total",
+                "Number of successful iDKG transcript loads, by outcome",
+                &["outcome"],
+            ),
+            ic_crypto_secret_key_store_keys: r.int_gauge_vec(
+                "ic_crypto_secret_key_store_keys",
+                "Number of keys held by a secret key store, by key type",
+                &["store", "key_type"],
+            ),
+            ic_crypto_secret_key_store_file_size_bytes: r.int_gauge_vec(
+                "ic_crypto_secret_key_store_file_size_bytes",
+                "Size of the file of a secret key store",
+                &["store"],
+            ),
+            ic_crypto_secret_key_store_load_duration_seconds: r.gauge_vec(
+                "ic_crypto_secret_key_store_load_duration_seconds",
+                "Time it took to load a secret key store from its file when it was opened",
+                &["store"],
+            ),
+            ic_crypto_secret_key_store_compactions_total: r.int_counter_vec(
+                "ic_crypto_secret_key_store_compactions_total",
+                "Number of rewrites of the file of a secret key store that dropped the parts not encoding its keys",
+                &["store"],
+            ),
+            ic_crypto_vault_result_mismatch_total: r.int_counter_vec(
+                "ic_crypto_vault_result_mismatch_total",
+                "Number of vault calls for which the primary and the secondary vault returned different results",
+                &["method_name"],
+            ),
         }
     }
 }
@@ -23,6 +23,13 @@
 //! * the public key's proof of possession (PoP) is valid
 //! * the public key is a point on the curve and in the right subgroup
 //!
+//! Validation of a *node's I-DKG dealing encryption key*, which is done
+//! separately with `validate_idkg_dealing_encryption_key`, includes verifying
+//! that
+//! * the key is well-formed and uses the MEGa secp256k1 algorithm
+//! * the public key is a point on the curve
+//! * the public key's proof of possession (PoP) is valid for the `node_id`
+//!
 //! How a *node's TLS certificate* is validated is described in the Rust doc of
 //! `ic_crypto_tls_cert_validation::validate_tls_certificate`. Note that the
 //! certificate is required to be present.
@@ -35,8 +42,10 @@ use ic_base_types::{NodeId, PrincipalId};
 use ic_crypto_internal_basic_sig_ed25519::types::PublicKeyBytes as BasicSigEd25519PublicKeyBytes;
 use ic_crypto_internal_multi_sig_bls12381::types::PopBytes as MultiSigBls12381PopBytes;
 use ic_crypto_internal_multi_sig_bls12381::types::PublicKeyBytes as MultiSigBls12381PublicKeyBytes;
+use ic_crypto_internal_threshold_sig_ecdsa::{verify_mega_key_proof_of_possession, EccCurveType};
 use ic_crypto_tls_cert_validation::TlsCertValidationError;
 use ic_protobuf::crypto::v1::NodePublicKeys;
+use ic_protobuf::registry::crypto::v1::AlgorithmId as AlgorithmIdProto;
 use ic_protobuf::registry::crypto::v1::PublicKey;
 use ic_protobuf::registry::crypto::v1::X509PublicKeyCert;
 use std::convert::TryFrom;
@@ -214,6 +223,32 @@ fn validate_dkg_dealing_encryption_key(
     Ok(())
 }
 
+/// Validates a node's I-DKG dealing encryption key
+///
+/// See the crate documentation for the exact checks that are performed.
+pub fn validate_idkg_dealing_encryption_key(
+    idkg_dealing_encryption_key: &PublicKey,
+    node_id: NodeId,
+) -> Result<(), KeyValidationError> {
+    if idkg_dealing_encryption_key.algorithm != AlgorithmIdProto::MegaSecp256k1 as i32 {
+        return Err(invalid_idkg_dealing_enc_pubkey_error(format!(
+            "unsupported algorithm {:?}",
+            AlgorithmIdProto::from_i32(idkg_dealing_encryption_key.algorithm)
+        )));
+    }
+    let pop = idkg_dealing_encryption_key
+        .proof_data
+        .as_ref()
+        .ok_or_else(|| invalid_idkg_dealing_enc_pubkey_error("proof of possession is missing"))?;
+    verify_mega_key_proof_of_possession(
+        EccCurveType::K256,
+        &idkg_dealing_encryption_key.key_value,
+        pop,
+        node_id.get().as_slice(),
+    )
+    .map_err(|e| invalid_idkg_dealing_enc_pubkey_error(format!("{:?}", e)))
+}
+
 pub fn validate_tls_certificate(
     tls_certificate: &Option<X509PublicKeyCert>,
     node_id: NodeId,
@@ -253,6 +288,15 @@ fn invalid_dkg_dealing_enc_pubkey_error<S: Into<String>>(internal_error: S) -> K
     }
 }
 
+fn invalid_idkg_dealing_enc_pubkey_error<S: Into<String>>(internal_error: S) -> KeyValidationError {
+    KeyValidationError {
+        error: format!(
+            "invalid I-DKG dealing encryption key: {}",
+            internal_error.into()
+        ),
+    }
+}
+
 impl From<TlsCertValidationError> for KeyValidationError {
     fn from(e: TlsCertValidationError) -> Self {
         let TlsCertValidationError { error } = e;
@@ -339,6 +339,77 @@ mod tls_certificate_validation {
     }
 }
 
+mod idkg_dealing_encryption_key_validation {
+    use super::*;
+    use ic_crypto::utils::{
+        generate_idkg_dealing_encryption_keys, generate_idkg_dealing_encryption_keys_with_pop,
+    };
+
+    #[test]
+    fn should_succeed_on_valid_idkg_dealing_encryption_key() {
+        let temp_dir = temp_dir();
+        let key = generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id(1));
+
+        assert!(validate_idkg_dealing_encryption_key(&key, node_id(1)).is_ok());
+    }
+
+    #[test]
+    fn should_fail_if_idkg_dealing_encryption_key_pop_is_missing() {
+        let temp_dir = temp_dir();
+        let key = generate_idkg_dealing_encryption_keys(temp_dir.path());
+
+        let result = validate_idkg_dealing_encryption_key(&key, node_id(1));
+
+        assert!(matches!(result, Err(KeyValidationError { error })
+            if error.contains("invalid I-DKG dealing encryption key: proof of possession is missing")
+        ));
+    }
+
+    #[test]
+    fn should_fail_if_idkg_dealing_encryption_key_pop_is_for_other_node() {
+        let temp_dir = temp_dir();
+        let key = generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id(1));
+
+        let result = validate_idkg_dealing_encryption_key(&key, node_id(2));
+
+        assert!(matches!(result, Err(KeyValidationError { error })
+            if error.contains("InvalidProofOfPossession")
+        ));
+    }
+
+    #[test]
+    fn should_fail_if_idkg_dealing_encryption_key_pop_is_for_other_key() {
+        let temp_dir = temp_dir();
+        let key = generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id(1));
+        let other_key = generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id(1));
+        let key = PublicKey {
+            proof_data: other_key.proof_data,
+            ..key
+        };
+
+        let result = validate_idkg_dealing_encryption_key(&key, node_id(1));
+
+        assert!(matches!(result, Err(KeyValidationError { error })
+            if error.contains("InvalidProofOfPossession")
+        ));
+    }
+
+    #[test]
+    fn should_fail_if_idkg_dealing_encryption_key_algorithm_is_wrong() {
+        let temp_dir = temp_dir();
+        let key = PublicKey {
+            algorithm: AlgorithmIdProto::Ed25519 as i32,
+            ..generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id(1))
+        };
+
+        let result = validate_idkg_dealing_encryption_key(&key, node_id(1));
+
+        assert!(matches!(result, Err(KeyValidationError { error })
+            if error.contains("unsupported algorithm")
+        ));
+    }
+}
+
 fn invalidate_valid_ed25519_pubkey(
     valid_pubkey: BasicSigEd25519PublicKeyBytes,
 ) -> BasicSigEd25519PublicKeyBytes {
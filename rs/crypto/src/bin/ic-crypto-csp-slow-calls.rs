@@ -0,0 +1,65 @@
+//! Prints the slow calls that the remote CSP vault server of a node recorded,
+//! to debug rare stalls of the vault, e.g. of the signing of shares.
+//!
+//! The calls that took longer than the threshold configured with
+//! `vault_slow_call_threshold_millis` are printed from the oldest to the
+//! newest, with the shapes of their arguments. Secret key material is never
+//! recorded, so it is never printed either. Exits with a non-zero status if
+//! the server cannot be reached or does not trace slow calls.
+use clap::{App, Arg};
+use ic_crypto_internal_csp::{ArgumentShape, RemoteCspVault};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+const DEFAULT_SOCKET_PATH: &str = "/run/ic-node/crypto-csp/socket";
+
+fn main() {
+    let flags = App::new("CspVault slow calls")
+        .version("0.1")
+        .author("Internet Computer Developers")
+        .about("Prints the slow calls recorded by the remote CspVault server")
+        .arg(
+            Arg::with_name("socket-path")
+                .long("socket-path")
+                .value_name("PATH")
+                .help("The socket of the remote CspVault server")
+                .default_value(DEFAULT_SOCKET_PATH)
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let socket_path = flags
+        .value_of("socket-path")
+        .expect("PATH has a default value");
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create the Tokio runtime");
+    let _guard = runtime.enter();
+    let slow_calls = RemoteCspVault::new(Path::new(socket_path))
+        .and_then(|vault| vault.slow_calls())
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to get the slow calls from {}: {:?}", socket_path, e);
+            std::process::exit(1);
+        });
+
+    println!("{}: {} slow calls", socket_path, slow_calls.len());
+    for call in &slow_calls {
+        let completed_at = call
+            .completed_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        println!(
+            "{}.{:03}\t{} ms\t{}",
+            completed_at.as_secs(),
+            completed_at.subsec_millis(),
+            call.duration.as_millis(),
+            call.method
+        );
+        for (name, shape) in &call.arguments {
+            let shape = match shape {
+                ArgumentShape::Bytes(size) => format!("{} bytes", size),
+                ArgumentShape::Count(count) => format!("{} elements", count),
+                ArgumentShape::Value(value) => value.clone(),
+            };
+            println!("\t{}: {}", name, shape);
+        }
+    }
+}
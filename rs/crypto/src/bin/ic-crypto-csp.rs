@@ -1,7 +1,15 @@
 use clap::{App, Arg};
+use ic_config::metrics::{Config as MetricsConfig, Exporter};
 use ic_config::{Config, ConfigSource};
+use ic_crypto_internal_csp::SlowCallTracerConfig;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_logger::LoggerImpl;
+use ic_metrics::MetricsRegistry;
+use ic_metrics_exporter::MetricsRuntimeImpl;
 use std::os::unix::io::FromRawFd;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 const IC_CRYPTO_CSP_SOCKET_NAME: &str = "ic-crypto-csp.socket";
 
@@ -42,7 +50,29 @@ async fn main() {
             .expect("failed to get local socket address"),
         sks_dir.display()
     );
-    ic_crypto_internal_csp::run_csp_vault_server(sks_dir, systemd_socket_listener).await;
+    let slow_call_tracer_config = SlowCallTracerConfig {
+        threshold: Duration::from_millis(ic_config.crypto.vault_slow_call_threshold_millis),
+        capacity: ic_config.crypto.vault_slow_call_capacity,
+    };
+    let logger = LoggerImpl::new(&ic_config.logger, "ic-crypto-csp".to_string());
+    let metrics_registry = MetricsRegistry::new();
+    let _metrics_runtime = ic_config.crypto.vault_metrics_addr.map(|addr| {
+        MetricsRuntimeImpl::new_insecure(
+            tokio::runtime::Handle::current(),
+            MetricsConfig {
+                exporter: Exporter::Http(addr),
+            },
+            metrics_registry.clone(),
+            &logger.root,
+        )
+    });
+    ic_crypto_internal_csp::run_csp_vault_server(
+        sks_dir,
+        systemd_socket_listener,
+        slow_call_tracer_config,
+        Arc::new(CryptoMetrics::new(Some(&metrics_registry))),
+    )
+    .await;
 }
 
 fn get_ic_config(replica_config_file: PathBuf) -> Config {
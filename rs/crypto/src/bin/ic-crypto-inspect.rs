@@ -0,0 +1,59 @@
+//! Inspects a secret key store (SKS) file offline, e.g. on a node whose
+//! crypto component fails to start.
+//!
+//! The file is only read, never written. For every stored key, the key ID,
+//! the variant, the scope, and the size of the encoded key are printed, along
+//! with the problems found with its encoding. Secret key material is never
+//! printed. Exits with a non-zero status if the file or any of its entries is
+//! malformed.
+use clap::{App, Arg};
+use ic_crypto_internal_csp::secret_key_store::proto_store::inspect_sks_file;
+use std::path::Path;
+
+fn main() {
+    let flags = App::new("SKS inspector")
+        .version("0.1")
+        .author("Internet Computer Developers")
+        .about("Prints the keys stored in a secret key store file without their secret bytes")
+        .arg(
+            Arg::with_name("sks-file")
+                .value_name("FILE")
+                .help("The SKS file to inspect, e.g. sks_data.pb in the crypto root")
+                .required(true)
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let sks_file = flags.value_of("sks-file").expect("FILE is required");
+    let summary = inspect_sks_file(Path::new(sks_file)).unwrap_or_else(|e| {
+        eprintln!("Failed to inspect {}: {}", sks_file, e);
+        std::process::exit(1);
+    });
+
+    println!(
+        "{}: version {}, {} keys",
+        sks_file,
+        summary.version,
+        summary.entries.len()
+    );
+    let mut malformed_entries = 0;
+    for entry in &summary.entries {
+        println!(
+            "{}\t{}\t{}\t{} bytes",
+            entry.key_id,
+            entry.variant.unwrap_or("<unknown variant>"),
+            entry.scope.as_deref().unwrap_or("<no scope>"),
+            entry.size
+        );
+        for error in &entry.errors {
+            println!("\tERROR: {}", error);
+        }
+        if !entry.is_valid() {
+            malformed_entries += 1;
+        }
+    }
+    if malformed_entries > 0 {
+        eprintln!("{} of the keys are malformed", malformed_entries);
+        std::process::exit(1);
+    }
+}
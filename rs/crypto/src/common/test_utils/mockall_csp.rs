@@ -9,11 +9,11 @@ use ic_crypto_internal_csp::api::tls_errors::{
     CspTlsClientHandshakeError, CspTlsServerHandshakeError,
 };
 use ic_crypto_internal_csp::api::{
-    CspCreateMEGaKeyError, CspIDkgProtocol, CspKeyGenerator, CspSecretKeyStoreChecker, CspSigner,
-    CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner, CspThresholdSignError,
-    CspTlsClientHandshake, CspTlsHandshakeSignerProvider, CspTlsServerHandshake,
-    DistributedKeyGenerationCspClient, NiDkgCspClient, NodePublicKeyData,
-    ThresholdSignatureCspClient,
+    CspCheckMEGaKeyPairError, CspCreateMEGaKeyError, CspIDkgProtocol, CspKeyGenerator,
+    CspRetireMEGaKeysError, CspSecretKeyStoreChecker, CspSigner, CspThresholdEcdsaSigVerifier,
+    CspThresholdEcdsaSigner, CspThresholdSignError, CspTlsClientHandshake,
+    CspTlsHandshakeSignerProvider, CspTlsServerHandshake, DistributedKeyGenerationCspClient,
+    IDkgLoadTranscriptOutcome, NiDkgCspClient, NodePublicKeyData, ThresholdSignatureCspClient,
 };
 use ic_crypto_internal_csp::tls_stub::cert_chain::CspCertificateChain;
 use ic_crypto_internal_csp::types::{
@@ -29,8 +29,8 @@ use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
-    ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaKeyProofOfPossession, MEGaPublicKey,
+    ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::sign::threshold_sig::dkg::encryption_public_key::CspEncryptionPublicKey;
 use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
@@ -41,8 +41,9 @@ use ic_crypto_tls_interfaces::{TlsPublicKeyCert, TlsStream};
 use ic_protobuf::crypto::v1::NodePublicKeys;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgCreateTranscriptError, IDkgLoadTranscriptError,
-    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyTranscriptError,
-    ThresholdEcdsaCombineSigSharesError, ThresholdEcdsaSignShareError,
+    IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyDealingPrivateError,
+    IDkgVerifyDealingPublicError, IDkgVerifyTranscriptError, ThresholdEcdsaCombineSigSharesError,
+    ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::ExtendedDerivationPath;
 use ic_types::crypto::threshold_sig::ni_dkg::NiDkgId;
@@ -377,6 +378,28 @@ mock! {
             transcript_operation: &IDkgTranscriptOperationInternal,
         ) -> Result<IDkgDealingInternal, IDkgCreateDealingError>;
 
+        #[allow(clippy::too_many_arguments)]
+        fn idkg_verify_dealing_public(
+            &self,
+            algorithm_id: AlgorithmId,
+            dealing: &IDkgDealingInternal,
+            operation_mode: &IDkgTranscriptOperationInternal,
+            reconstruction_threshold: NumberOfNodes,
+            dealer_index: NodeIndex,
+            number_of_receivers: NumberOfNodes,
+            context_data: &[u8],
+        ) -> Result<(), IDkgVerifyDealingPublicError>;
+
+        fn idkg_verify_dealing_private(
+            &self,
+            algorithm_id: AlgorithmId,
+            dealing: &IDkgDealingInternal,
+            dealer_index: NodeIndex,
+            receiver_index: NodeIndex,
+            receiver_public_key: &MEGaPublicKey,
+            context_data: &[u8],
+        ) -> Result<(), IDkgVerifyDealingPrivateError>;
+
         fn idkg_create_transcript(
             &self,
             algorithm_id: AlgorithmId,
@@ -401,7 +424,7 @@ mock! {
             receiver_index: NodeIndex,
             public_key: &MEGaPublicKey,
             transcript: &IDkgTranscriptInternal,
-        ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError>;
+        ) -> Result<IDkgLoadTranscriptOutcome, IDkgLoadTranscriptError>;
 
         fn idkg_load_transcript_with_openings(
             &self,
@@ -413,7 +436,7 @@ mock! {
             transcript: &IDkgTranscriptInternal,
         ) -> Result<(), IDkgLoadTranscriptError>;
 
-        fn idkg_create_mega_key_pair(&mut self, algorithm_id: AlgorithmId) -> Result<MEGaPublicKey, CspCreateMEGaKeyError>;
+        fn idkg_create_mega_key_pair(&self, algorithm_id: AlgorithmId, pop_node_id: Option<NodeId>) -> Result<(MEGaPublicKey, Option<MEGaKeyProofOfPossession>), CspCreateMEGaKeyError>;
 
         fn idkg_verify_complaint(
             &self,
@@ -433,6 +456,18 @@ mock! {
             opener_index: NodeIndex,
             opener_public_key: &MEGaPublicKey,
         ) -> Result<CommitmentOpening, IDkgOpenTranscriptError>;
+
+        fn idkg_check_mega_key_pair(
+            &self,
+            public_key: &MEGaPublicKey,
+        ) -> Result<(), CspCheckMEGaKeyPairError>;
+
+        fn idkg_retire_mega_keys(
+            &self,
+            public_keys: &[MEGaPublicKey],
+        ) -> Result<(), CspRetireMEGaKeysError>;
+
+        fn supported_threshold_algorithms(&self) -> BTreeSet<AlgorithmId>;
     }
 
     pub trait CspThresholdEcdsaSigner {
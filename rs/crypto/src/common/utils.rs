@@ -55,16 +55,42 @@ pub fn generate_dkg_dealing_encryption_keys(crypto_root: &Path, node_id: NodeId)
 /// for storing crypto state](CryptoConfig::check_dir_has_required_permissions).
 /// If there exists no key store in `crypto_root`, a new one is created.
 pub fn generate_idkg_dealing_encryption_keys(crypto_root: &Path) -> PublicKeyProto {
-    let mut csp = csp_at_root(crypto_root);
-    let pubkey = csp
-        .idkg_create_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1)
+    generate_idkg_dealing_encryption_keys_for_node(crypto_root, None)
+}
+
+/// Generates (MEGa) I-DKG dealing encryption key material, together with a
+/// proof of possession of the secret key that is bound to `node_id`.
+///
+/// Stores the secret key in the key store at `crypto_root` and returns the
+/// corresponding public key, with the proof of possession as `proof_data`.
+/// The proof can be verified with
+/// `ic_crypto_node_key_validation::validate_idkg_dealing_encryption_key`.
+///
+/// The `crypto_root` directory must exist and have the [permissions required
+/// for storing crypto state](CryptoConfig::check_dir_has_required_permissions).
+/// If there exists no key store in `crypto_root`, a new one is created.
+pub fn generate_idkg_dealing_encryption_keys_with_pop(
+    crypto_root: &Path,
+    node_id: NodeId,
+) -> PublicKeyProto {
+    generate_idkg_dealing_encryption_keys_for_node(crypto_root, Some(node_id))
+}
+
+fn generate_idkg_dealing_encryption_keys_for_node(
+    crypto_root: &Path,
+    pop_node_id: Option<NodeId>,
+) -> PublicKeyProto {
+    let csp = csp_at_root(crypto_root);
+    let (pubkey, pop) = csp
+        .idkg_create_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, pop_node_id)
         .expect("Failed to generate IDkg dealing encryption keys");
 
     PublicKeyProto {
         version: 0,
         algorithm: AlgorithmIdProto::MegaSecp256k1 as i32,
         key_value: pubkey.serialize(),
-        proof_data: None,
+        proof_data: pop.map(|pop| pop.serialize()),
+        timestamp: None,
     }
 }
 
@@ -146,6 +172,7 @@ fn generate_node_signing_keys(crypto_root: &Path) -> PublicKeyProto {
             key_value: pk.0.to_vec(),
             version: 0,
             proof_data: None,
+            timestamp: None,
         },
         _ => panic!("Unexpected types"),
     }
@@ -228,6 +255,7 @@ fn generate_committee_signing_keys(crypto_root: &Path) -> PublicKeyProto {
                 key_value: pk_bytes.0.to_vec(),
                 version: 0,
                 proof_data: Some(pop_bytes.0.to_vec()),
+                timestamp: None,
             }
         }
         _ => panic!("Unexpected types"),
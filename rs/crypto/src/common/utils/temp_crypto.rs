@@ -39,8 +39,8 @@ use ic_types::crypto::canister_threshold_sig::{
 };
 use ic_types::crypto::threshold_sig::ni_dkg::DkgId;
 use ic_types::crypto::{
-    BasicSigOf, CanisterSigOf, CombinedMultiSigOf, CombinedThresholdSigOf, CryptoResult,
-    IndividualMultiSigOf, ThresholdSigShareOf, UserPublicKey,
+    AlgorithmId, BasicSigOf, CanisterSigOf, CombinedMultiSigOf, CombinedThresholdSigOf,
+    CryptoResult, IndividualMultiSigOf, ThresholdSigShareOf, UserPublicKey,
 };
 use ic_types::{NodeId, Randomness, RegistryVersion, SubnetId};
 use rand::rngs::OsRng;
@@ -478,6 +478,10 @@ impl<C: CryptoServiceProvider> IDkgProtocol for TempCryptoComponentGeneric<C> {
         self.crypto_component
             .retain_active_transcripts(active_transcripts)
     }
+
+    fn supported_algorithms(&self) -> BTreeSet<AlgorithmId> {
+        self.crypto_component.supported_algorithms()
+    }
 }
 
 impl<C: CryptoServiceProvider> ThresholdEcdsaSigner for TempCryptoComponentGeneric<C> {
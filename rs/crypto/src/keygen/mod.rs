@@ -1,20 +1,24 @@
 use crate::{key_from_registry, CryptoComponentFatClient};
+use ic_crypto_internal_csp::api::{CspCheckMEGaKeyPairError, CspCreateMEGaKeyError};
 use ic_crypto_internal_csp::keygen::{forward_secure_key_id, public_key_hash_as_key_id};
 use ic_crypto_internal_csp::types::conversions::CspPopFromPublicKeyProtoError;
 use ic_crypto_internal_csp::types::{CspPop, CspPublicKey};
 use ic_crypto_internal_csp::CryptoServiceProvider;
+use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, MEGaPublicKey};
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
 };
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_interfaces::crypto::KeyManager;
 use ic_protobuf::crypto::v1::NodePublicKeys;
+use ic_protobuf::registry::crypto::v1::AlgorithmId as AlgorithmIdProto;
 use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_registry_client::helper::crypto::CryptoRegistry;
 use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult, KeyPurpose};
 use ic_types::RegistryVersion;
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 impl<C: CryptoServiceProvider> KeyManager for CryptoComponentFatClient<C> {
     fn node_public_keys(&self) -> NodePublicKeys {
@@ -28,6 +32,88 @@ impl<C: CryptoServiceProvider> KeyManager for CryptoComponentFatClient<C> {
         self.ensure_tls_key_material_is_set_up(registry_version)?;
         Ok(())
     }
+
+    fn check_idkg_dealing_encryption_key_with_registry(
+        &self,
+        registry_version: RegistryVersion,
+    ) -> CryptoResult<()> {
+        let pk_proto = key_from_registry(
+            Arc::clone(&self.registry_client),
+            self.node_id,
+            KeyPurpose::IDkgMEGaEncryption,
+            registry_version,
+        )?;
+        if AlgorithmId::from(pk_proto.algorithm) != AlgorithmId::MegaSecp256k1 {
+            return Err(CryptoError::PublicKeyNotFound {
+                node_id: self.node_id,
+                key_purpose: KeyPurpose::IDkgMEGaEncryption,
+                registry_version,
+            });
+        }
+        let public_key = MEGaPublicKey::deserialize(EccCurveType::K256, &pk_proto.key_value)
+            .map_err(|e| CryptoError::MalformedPublicKey {
+                algorithm: AlgorithmId::MegaSecp256k1,
+                key_bytes: Some(pk_proto.key_value.clone()),
+                internal_error: format!("{:?}", e),
+            })?;
+        self.csp
+            .idkg_check_mega_key_pair(&public_key)
+            .map_err(|e| match e {
+                CspCheckMEGaKeyPairError::SecretKeyNotFound { key_id } => {
+                    CryptoError::SecretKeyNotFound {
+                        algorithm: AlgorithmId::MegaSecp256k1,
+                        key_id,
+                    }
+                }
+                CspCheckMEGaKeyPairError::UnsupportedAlgorithm { algorithm_id } => {
+                    CryptoError::AlgorithmNotSupported {
+                        algorithm: algorithm_id,
+                        reason: e.to_string(),
+                    }
+                }
+                CspCheckMEGaKeyPairError::MalformedKeyPair { .. }
+                | CspCheckMEGaKeyPairError::PublicKeyMismatch { .. } => {
+                    CryptoError::MalformedSecretKey {
+                        algorithm: AlgorithmId::MegaSecp256k1,
+                        internal_error: e.to_string(),
+                    }
+                }
+                CspCheckMEGaKeyPairError::CspServerError { internal_error } => {
+                    CryptoError::TransientInternalError { internal_error }
+                }
+            })
+    }
+
+    fn rotate_idkg_dealing_encryption_key(&self) -> CryptoResult<PublicKeyProto> {
+        let (public_key, pop) = self
+            .csp
+            .idkg_create_mega_key_pair(AlgorithmId::ThresholdEcdsaSecp256k1, Some(self.node_id))
+            .map_err(|e| match e {
+                CspCreateMEGaKeyError::UnsupportedAlgorithm { algorithm_id } => {
+                    CryptoError::AlgorithmNotSupported {
+                        algorithm: algorithm_id,
+                        reason: e.to_string(),
+                    }
+                }
+                CspCreateMEGaKeyError::CspServerError { internal_error } => {
+                    CryptoError::TransientInternalError { internal_error }
+                }
+                _ => CryptoError::InvalidArgument {
+                    message: e.to_string(),
+                },
+            })?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the UNIX epoch")
+            .as_millis() as u64;
+        Ok(PublicKeyProto {
+            version: 0,
+            algorithm: AlgorithmIdProto::MegaSecp256k1 as i32,
+            key_value: public_key.serialize(),
+            proof_data: pop.map(|pop| pop.serialize()),
+            timestamp: Some(timestamp),
+        })
+    }
 }
 
 // Helpers for implementing `KeyManager`-trait.
@@ -98,6 +98,7 @@ pub fn combine_sig_shares<C: CspThresholdEcdsaSigVerifier>(
 
     Ok(ThresholdEcdsaCombinedSignature {
         signature: internal_combined_sig.serialize(),
+        recovery_id: Some(internal_combined_sig.recovery_id()),
     })
 }
 
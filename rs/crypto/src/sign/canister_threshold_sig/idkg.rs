@@ -1,8 +1,9 @@
 use crate::sign::log_err;
 use crate::CryptoComponentFatClient;
+use ic_crypto_internal_csp::api::CspIDkgProtocol;
 use ic_crypto_internal_csp::CryptoServiceProvider;
 use ic_interfaces::crypto::IDkgProtocol;
-use ic_logger::{debug, new_logger};
+use ic_logger::{debug, info, new_logger};
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgCreateTranscriptError, IDkgLoadTranscriptError,
     IDkgOpenTranscriptError, IDkgVerifyComplaintError, IDkgVerifyDealingPrivateError,
@@ -12,12 +13,14 @@ use ic_types::crypto::canister_threshold_sig::idkg::{
     IDkgComplaint, IDkgDealing, IDkgMultiSignedDealing, IDkgOpening, IDkgTranscript,
     IDkgTranscriptParams,
 };
+use ic_types::crypto::AlgorithmId;
 use ic_types::NodeId;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 mod complaint;
 mod dealing;
 mod mocks;
+mod retain_active_keys;
 mod transcript;
 mod utils;
 
@@ -31,6 +34,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "create_dealing",
+            crypto.idkg_transcript_id => format!("{:?}", params.transcript_id()),
             crypto.registry_version => params.registry_version().get(),
             crypto.dkg_config => format!("{:?}", params),
         );
@@ -55,11 +59,12 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "verify_dealing_public",
+            crypto.idkg_transcript_id => format!("{:?}", dealing.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
         );
-        let result = mocks::verify_dealing_public(params, dealing);
+        let result = dealing::verify_dealing_public(&self.csp, params, dealing);
         debug!(logger;
             crypto.description => "end",
             crypto.is_ok => result.is_ok(),
@@ -76,11 +81,18 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "verify_dealing_private",
+            crypto.idkg_transcript_id => format!("{:?}", dealing.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
         );
-        let result = mocks::verify_dealing_private(params, dealing);
+        let result = dealing::verify_dealing_private(
+            &self.csp,
+            &self.node_id,
+            &self.registry_client,
+            params,
+            dealing,
+        );
         debug!(logger;
             crypto.description => "end",
             crypto.is_ok => result.is_ok(),
@@ -97,6 +109,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "create_transcript",
+            crypto.idkg_transcript_id => format!("{:?}", params.transcript_id()),
             crypto.registry_version => params.registry_version().get(),
             crypto.dkg_config => format!("{:?}", params),
         );
@@ -121,6 +134,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "verify_transcript",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -142,6 +156,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "load_transcript",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -169,6 +184,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "verify_complaint",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -197,6 +213,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "open_transcript",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -227,6 +244,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "verify_opening",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -248,6 +266,7 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "IDkgProtocol",
             crypto.method_name => "load_transcript_with_openings",
+            crypto.idkg_transcript_id => format!("{:?}", transcript.transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -275,11 +294,29 @@ impl<C: CryptoServiceProvider> IDkgProtocol for CryptoComponentFatClient<C> {
         debug!(logger;
             crypto.description => "start",
         );
-        mocks::retain_active_transcripts(active_transcripts);
+        let result = retain_active_keys::retain_active_keys(
+            &self.csp,
+            self.node_id,
+            &self.registry_client,
+            active_transcripts,
+        );
+        if let Ok(retired_keys) = &result {
+            if !retired_keys.is_empty() {
+                info!(logger;
+                    crypto.description => format!(
+                        "retired {} iDKG dealing encryption keys", retired_keys.len()
+                    ),
+                );
+            }
+        }
         debug!(logger;
             crypto.description => "end",
-            crypto.is_ok => true,
-            crypto.error => "none".to_string(),
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
         );
     }
+
+    fn supported_algorithms(&self) -> BTreeSet<AlgorithmId> {
+        self.csp.supported_threshold_algorithms()
+    }
 }
@@ -1,10 +1,16 @@
 //! Implementations of IDkgProtocol related to dealings
 
-use crate::sign::canister_threshold_sig::idkg::utils::idkg_encryption_keys_from_registry;
+use crate::sign::canister_threshold_sig::idkg::utils::{
+    get_mega_pubkey, idkg_encryption_keys_from_registry,
+};
 use ic_crypto_internal_csp::api::CspIDkgProtocol;
-use ic_crypto_internal_threshold_sig_ecdsa::IDkgTranscriptOperationInternal;
+use ic_crypto_internal_threshold_sig_ecdsa::{
+    IDkgDealingInternal, IDkgTranscriptOperationInternal,
+};
 use ic_interfaces::registry::RegistryClient;
-use ic_types::crypto::canister_threshold_sig::error::IDkgCreateDealingError;
+use ic_types::crypto::canister_threshold_sig::error::{
+    IDkgCreateDealingError, IDkgVerifyDealingPrivateError, IDkgVerifyDealingPublicError,
+};
 use ic_types::crypto::canister_threshold_sig::idkg::{IDkgDealing, IDkgTranscriptParams};
 use ic_types::NodeId;
 use std::convert::TryFrom;
@@ -57,3 +63,68 @@ pub fn create_dealing<C: CspIDkgProtocol>(
         internal_dealing_raw,
     })
 }
+
+pub fn verify_dealing_public<C: CspIDkgProtocol>(
+    csp_client: &C,
+    params: &IDkgTranscriptParams,
+    dealing: &IDkgDealing,
+) -> Result<(), IDkgVerifyDealingPublicError> {
+    if dealing.transcript_id != params.transcript_id() {
+        return Err(IDkgVerifyDealingPublicError::TranscriptIdMismatch);
+    }
+    let dealer_index = params.dealer_index(dealing.dealer_id).ok_or(
+        IDkgVerifyDealingPublicError::InvalidDealer {
+            node_id: dealing.dealer_id,
+        },
+    )?;
+    let internal_dealing = IDkgDealingInternal::deserialize(&dealing.internal_dealing_raw)
+        .map_err(|e| IDkgVerifyDealingPublicError::InvalidDealing {
+            reason: format!("failed to deserialize internal dealing: {:?}", e),
+        })?;
+    let csp_operation_type = IDkgTranscriptOperationInternal::try_from(params.operation_type())
+        .map_err(|e| IDkgVerifyDealingPublicError::InvalidArgument {
+            internal_error: format!("failed to convert transcript operation: {:?}", e),
+        })?;
+
+    csp_client.idkg_verify_dealing_public(
+        params.algorithm_id(),
+        &internal_dealing,
+        &csp_operation_type,
+        params.reconstruction_threshold(),
+        dealer_index,
+        params.receivers().count(),
+        &params.context_data(),
+    )
+}
+
+pub fn verify_dealing_private<C: CspIDkgProtocol>(
+    csp_client: &C,
+    self_node_id: &NodeId,
+    registry: &Arc<dyn RegistryClient>,
+    params: &IDkgTranscriptParams,
+    dealing: &IDkgDealing,
+) -> Result<(), IDkgVerifyDealingPrivateError> {
+    let self_receiver_index = params
+        .receivers()
+        .position(*self_node_id)
+        .ok_or(IDkgVerifyDealingPrivateError::NotAReceiver)?;
+    let dealer_index = params.dealer_index(dealing.dealer_id).ok_or_else(|| {
+        IDkgVerifyDealingPrivateError::InvalidArgument {
+            internal_error: format!("failed to get index of dealer {}", dealing.dealer_id),
+        }
+    })?;
+    let internal_dealing = IDkgDealingInternal::deserialize(&dealing.internal_dealing_raw)
+        .map_err(|e| IDkgVerifyDealingPrivateError::InvalidArgument {
+            internal_error: format!("failed to deserialize internal dealing: {:?}", e),
+        })?;
+    let self_mega_pubkey = get_mega_pubkey(self_node_id, registry, params.registry_version())?;
+
+    csp_client.idkg_verify_dealing_private(
+        params.algorithm_id(),
+        &internal_dealing,
+        dealer_index,
+        self_receiver_index,
+        &self_mega_pubkey,
+        &params.context_data(),
+    )
+}
@@ -1,25 +1,7 @@
-use ic_types::crypto::canister_threshold_sig::error::{
-    IDkgVerifyDealingPrivateError, IDkgVerifyDealingPublicError, IDkgVerifyOpeningError,
-};
-use ic_types::crypto::canister_threshold_sig::idkg::{
-    IDkgComplaint, IDkgDealing, IDkgOpening, IDkgTranscript, IDkgTranscriptParams,
-};
+use ic_types::crypto::canister_threshold_sig::error::IDkgVerifyOpeningError;
+use ic_types::crypto::canister_threshold_sig::idkg::{IDkgComplaint, IDkgOpening, IDkgTranscript};
 use ic_types::NodeId;
 
-pub fn verify_dealing_public(
-    _params: &IDkgTranscriptParams,
-    _dealing: &IDkgDealing,
-) -> Result<(), IDkgVerifyDealingPublicError> {
-    Ok(())
-}
-
-pub fn verify_dealing_private(
-    _params: &IDkgTranscriptParams,
-    _dealing: &IDkgDealing,
-) -> Result<(), IDkgVerifyDealingPrivateError> {
-    Ok(())
-}
-
 pub fn verify_opening(
     _transcript: &IDkgTranscript,
     _opener: NodeId,
@@ -28,5 +10,3 @@ pub fn verify_opening(
 ) -> Result<(), IDkgVerifyOpeningError> {
     Ok(())
 }
-
-pub fn retain_active_transcripts(_active_transcripts: &[IDkgTranscript]) {}
@@ -0,0 +1,151 @@
+//! Retirement of the iDKG dealing encryption keys of rotated-out
+//! registrations
+//!
+//! When the iDKG dealing encryption (MEGa) key of a node is rotated, the
+//! secret key of the previous registration must be retained as long as an
+//! active transcript references it, because the transcript was created for
+//! the key that was registered at the registry version of the transcript.
+//! Once all active transcripts of which this node is a receiver reference a
+//! registry version at which a newer key was registered, the secret keys of
+//! the previous registrations are removed from the secret key store.
+use crate::sign::canister_threshold_sig::idkg::utils::{
+    mega_public_key_from_proto, MegaKeyFromRegistryError,
+};
+use ic_crypto_internal_csp::api::{CspIDkgProtocol, CspRetireMEGaKeysError};
+use ic_crypto_internal_threshold_sig_ecdsa::MEGaPublicKey;
+use ic_interfaces::registry::RegistryClient;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
+use ic_registry_keys::make_crypto_node_key;
+use ic_types::crypto::canister_threshold_sig::idkg::IDkgTranscript;
+use ic_types::crypto::KeyPurpose;
+use ic_types::{NodeId, RegistryVersion};
+use prost::Message;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// Errors encountered while retiring the iDKG dealing encryption keys that
+/// no active transcript references.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RetainActiveKeysError {
+    MegaKeyFromRegistryError(MegaKeyFromRegistryError),
+    MalformedRegistryRecord { internal_error: String },
+    CspError(CspRetireMEGaKeysError),
+}
+
+impl std::fmt::Display for RetainActiveKeysError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MegaKeyFromRegistryError(e) => {
+                write!(f, "Error retrieving public key from the registry: {:?}", e)
+            }
+            Self::MalformedRegistryRecord { internal_error } => {
+                write!(f, "Malformed public key record: {}", internal_error)
+            }
+            Self::CspError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<MegaKeyFromRegistryError> for RetainActiveKeysError {
+    fn from(e: MegaKeyFromRegistryError) -> Self {
+        RetainActiveKeysError::MegaKeyFromRegistryError(e)
+    }
+}
+
+/// Retires the iDKG dealing encryption keys of `node_id` that were replaced
+/// in the registry before the oldest registry version of the
+/// `active_transcripts` of which `node_id` is a receiver.
+///
+/// Returns the retired public keys. Keys that were never registered, e.g. a
+/// key whose registration is still pending after a rotation, are never
+/// retired. Nothing is retired if `node_id` is not a receiver of any of the
+/// `active_transcripts`.
+pub fn retain_active_keys<C: CspIDkgProtocol>(
+    csp_client: &C,
+    node_id: NodeId,
+    registry: &Arc<dyn RegistryClient>,
+    active_transcripts: &[IDkgTranscript],
+) -> Result<Vec<MEGaPublicKey>, RetainActiveKeysError> {
+    let oldest_registry_version = match active_transcripts
+        .iter()
+        .filter(|transcript| transcript.receivers.position(node_id).is_some())
+        .map(|transcript| transcript.registry_version)
+        .min()
+    {
+        Some(version) => version,
+        None => return Ok(vec![]),
+    };
+    let active_keys = vec![
+        registered_mega_key(node_id, registry, oldest_registry_version)?,
+        registered_mega_key(node_id, registry, registry.get_latest_version())?,
+    ];
+    let retired_keys: Vec<_> =
+        mega_keys_replaced_before(node_id, registry, oldest_registry_version)?
+            .into_iter()
+            .filter(|key| {
+                !active_keys
+                    .iter()
+                    .any(|(_, active)| active.as_ref() == Some(key))
+            })
+            .collect();
+    if !retired_keys.is_empty() {
+        csp_client
+            .idkg_retire_mega_keys(&retired_keys)
+            .map_err(RetainActiveKeysError::CspError)?;
+    }
+    Ok(retired_keys)
+}
+
+/// Returns the MEGa key of `node_id` registered at `registry_version`,
+/// together with the registry version at which it was registered.
+fn registered_mega_key(
+    node_id: NodeId,
+    registry: &Arc<dyn RegistryClient>,
+    registry_version: RegistryVersion,
+) -> Result<(RegistryVersion, Option<MEGaPublicKey>), RetainActiveKeysError> {
+    let record = registry
+        .get_versioned_value(
+            &make_crypto_node_key(node_id, KeyPurpose::IDkgMEGaEncryption),
+            registry_version,
+        )
+        .map_err(MegaKeyFromRegistryError::RegistryError)?;
+    let key = match record.value {
+        Some(bytes) => {
+            let proto = PublicKeyProto::decode(&bytes[..]).map_err(|e| {
+                RetainActiveKeysError::MalformedRegistryRecord {
+                    internal_error: format!("{:?}", e),
+                }
+            })?;
+            Some(mega_public_key_from_proto(&proto, &node_id)?)
+        }
+        None => None,
+    };
+    Ok((record.version, key))
+}
+
+/// Returns the MEGa keys of `node_id` that were registered before the one
+/// registered at `registry_version`, walking the registry history backwards.
+fn mega_keys_replaced_before(
+    node_id: NodeId,
+    registry: &Arc<dyn RegistryClient>,
+    registry_version: RegistryVersion,
+) -> Result<Vec<MEGaPublicKey>, RetainActiveKeysError> {
+    let mut replaced_keys = vec![];
+    let (mut registered_at, _) = registered_mega_key(node_id, registry, registry_version)?;
+    while registered_at.get() > 1 {
+        let (previously_registered_at, key) = registered_mega_key(
+            node_id,
+            registry,
+            RegistryVersion::from(registered_at.get() - 1),
+        )?;
+        match key {
+            Some(key) => replaced_keys.push(key),
+            None if previously_registered_at.get() == 0 => break,
+            None => {}
+        }
+        registered_at = previously_registered_at;
+    }
+    Ok(replaced_keys)
+}
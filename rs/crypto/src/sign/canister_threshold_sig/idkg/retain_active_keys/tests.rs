@@ -0,0 +1,152 @@
+#![allow(clippy::unwrap_used)]
+use super::*;
+use crate::common::test_utils::mockall_csp::MockAllCryptoServiceProvider;
+use ic_crypto_internal_threshold_sig_ecdsa::EccCurveType;
+use ic_protobuf::registry::crypto::v1::AlgorithmId as AlgorithmIdProto;
+use ic_registry_client::fake::FakeRegistryClient;
+use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
+use ic_test_utilities::types::ids::{NODE_1, NODE_2, SUBNET_42};
+use ic_types::crypto::canister_threshold_sig::idkg::{
+    IDkgMaskedTranscriptOrigin, IDkgReceivers, IDkgTranscriptId, IDkgTranscriptType,
+};
+use ic_types::crypto::AlgorithmId;
+use ic_types::Randomness;
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[test]
+fn should_retire_keys_replaced_before_the_oldest_active_transcript() {
+    let keys = [
+        generate_mega_public_key(),
+        generate_mega_public_key(),
+        generate_mega_public_key(),
+    ];
+    let registry = registry_with_keys_of(NODE_1, &[(1, &keys[0]), (3, &keys[1]), (5, &keys[2])]);
+    let expected_retired = vec![keys[0].clone()];
+    let mut csp = MockAllCryptoServiceProvider::new();
+    csp.expect_idkg_retire_mega_keys()
+        .withf(move |public_keys| public_keys == &expected_retired[..])
+        .times(1)
+        .return_const(Ok(()));
+
+    let retired = retain_active_keys(
+        &csp,
+        NODE_1,
+        &registry,
+        &[
+            transcript_at(4, &[NODE_1, NODE_2]),
+            transcript_at(6, &[NODE_1]),
+        ],
+    );
+
+    assert_eq!(retired, Ok(vec![keys[0].clone()]));
+}
+
+#[test]
+fn should_retain_keys_of_all_active_transcripts() {
+    let keys = [generate_mega_public_key(), generate_mega_public_key()];
+    let registry = registry_with_keys_of(NODE_1, &[(1, &keys[0]), (3, &keys[1])]);
+    let mut csp = MockAllCryptoServiceProvider::new();
+    csp.expect_idkg_retire_mega_keys().times(0);
+
+    let retired = retain_active_keys(
+        &csp,
+        NODE_1,
+        &registry,
+        &[transcript_at(2, &[NODE_1]), transcript_at(3, &[NODE_1])],
+    );
+
+    assert_eq!(retired, Ok(vec![]));
+}
+
+#[test]
+fn should_ignore_transcripts_without_node_as_receiver() {
+    let keys = [generate_mega_public_key(), generate_mega_public_key()];
+    let registry = registry_with_keys_of(NODE_1, &[(1, &keys[0]), (3, &keys[1])]);
+    let mut csp = MockAllCryptoServiceProvider::new();
+    csp.expect_idkg_retire_mega_keys().times(0);
+
+    let retired = retain_active_keys(&csp, NODE_1, &registry, &[transcript_at(4, &[NODE_2])]);
+
+    assert_eq!(retired, Ok(vec![]));
+}
+
+#[test]
+fn should_not_retire_key_that_is_registered_again() {
+    let keys = [generate_mega_public_key(), generate_mega_public_key()];
+    let registry = registry_with_keys_of(NODE_1, &[(1, &keys[0]), (3, &keys[1]), (5, &keys[0])]);
+    let expected_retired = vec![keys[1].clone()];
+    let mut csp = MockAllCryptoServiceProvider::new();
+    csp.expect_idkg_retire_mega_keys()
+        .withf(move |public_keys| public_keys == &expected_retired[..])
+        .times(1)
+        .return_const(Ok(()));
+
+    let retired = retain_active_keys(&csp, NODE_1, &registry, &[transcript_at(5, &[NODE_1])]);
+
+    assert_eq!(retired, Ok(vec![keys[1].clone()]));
+}
+
+#[test]
+fn should_return_error_if_csp_fails_to_retire_keys() {
+    let keys = [generate_mega_public_key(), generate_mega_public_key()];
+    let registry = registry_with_keys_of(NODE_1, &[(1, &keys[0]), (3, &keys[1])]);
+    let csp_error = CspRetireMEGaKeysError::CspServerError {
+        internal_error: "vault is unreachable".to_string(),
+    };
+    let mut csp = MockAllCryptoServiceProvider::new();
+    csp.expect_idkg_retire_mega_keys()
+        .times(1)
+        .return_const(Err(csp_error.clone()));
+
+    let retired = retain_active_keys(&csp, NODE_1, &registry, &[transcript_at(3, &[NODE_1])]);
+
+    assert_eq!(retired, Err(RetainActiveKeysError::CspError(csp_error)));
+}
+
+fn registry_with_keys_of(
+    node_id: NodeId,
+    keys: &[(u64, &MEGaPublicKey)],
+) -> Arc<dyn RegistryClient> {
+    let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+    for (version, key) in keys {
+        data_provider
+            .add(
+                &make_crypto_node_key(node_id, KeyPurpose::IDkgMEGaEncryption),
+                RegistryVersion::from(*version),
+                Some(PublicKeyProto {
+                    version: 0,
+                    algorithm: AlgorithmIdProto::MegaSecp256k1 as i32,
+                    key_value: key.serialize(),
+                    proof_data: None,
+                    timestamp: Some(*version),
+                }),
+            )
+            .expect("Could not add public key to registry");
+    }
+    let registry_client = Arc::new(FakeRegistryClient::new(data_provider));
+    registry_client.update_to_latest_version();
+    registry_client
+}
+
+fn transcript_at(registry_version: u64, receivers: &[NodeId]) -> IDkgTranscript {
+    IDkgTranscript {
+        transcript_id: IDkgTranscriptId::new(SUBNET_42, registry_version as usize),
+        receivers: IDkgReceivers::new(receivers.iter().copied().collect::<BTreeSet<_>>()).unwrap(),
+        registry_version: RegistryVersion::from(registry_version),
+        verified_dealings: BTreeMap::new(),
+        transcript_type: IDkgTranscriptType::Masked(IDkgMaskedTranscriptOrigin::Random),
+        algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+        internal_transcript_raw: vec![],
+    }
+}
+
+fn generate_mega_public_key() -> MEGaPublicKey {
+    let rng = &mut thread_rng();
+    let (mega_pk, _mega_sk) = ic_crypto_internal_threshold_sig_ecdsa::gen_keypair(
+        EccCurveType::K256,
+        Randomness::new(rng.gen()),
+    )
+    .expect("failed to generate keypair");
+    mega_pk
+}
@@ -166,13 +166,15 @@ pub fn load_transcript<C: CspIDkgProtocol>(
             internal_error: format!("{:?}", e),
         }
     })?;
-    let internal_complaints = csp_client.idkg_load_transcript(
-        &internal_dealings,
-        &transcript.context_data(),
-        self_index,
-        &self_mega_pubkey,
-        &internal_transcript,
-    )?;
+    let internal_complaints = csp_client
+        .idkg_load_transcript(
+            &internal_dealings,
+            &transcript.context_data(),
+            self_index,
+            &self_mega_pubkey,
+            &internal_transcript,
+        )?
+        .into_complaints();
     let complaints = complaints_from_internal_complaints(&internal_complaints, transcript)?;
 
     Ok(complaints)
@@ -57,7 +57,7 @@ pub fn get_mega_pubkey(
 }
 
 /// Deserialize a Protobuf public key to a MEGaPublicKey.
-fn mega_public_key_from_proto(
+pub fn mega_public_key_from_proto(
     proto: &PublicKeyProto,
     node_id: &NodeId,
 ) -> Result<MEGaPublicKey, MegaKeyFromRegistryError> {
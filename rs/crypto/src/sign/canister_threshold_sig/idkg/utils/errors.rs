@@ -1,6 +1,7 @@
 use ic_protobuf::registry::crypto::v1::AlgorithmId as AlgorithmIdProto;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
+    IDkgVerifyDealingPrivateError,
 };
 use ic_types::registry::RegistryClientError;
 use ic_types::{NodeId, RegistryVersion};
@@ -64,6 +65,29 @@ impl From<MegaKeyFromRegistryError> for IDkgLoadTranscriptError {
     }
 }
 
+impl From<MegaKeyFromRegistryError> for IDkgVerifyDealingPrivateError {
+    fn from(error: MegaKeyFromRegistryError) -> Self {
+        match error {
+            MegaKeyFromRegistryError::RegistryError(e) => {
+                IDkgVerifyDealingPrivateError::RegistryError(e)
+            }
+            MegaKeyFromRegistryError::PublicKeyNotFound {
+                node_id,
+                registry_version,
+            } => IDkgVerifyDealingPrivateError::PublicKeyNotInRegistry {
+                node_id,
+                registry_version,
+            },
+            MegaKeyFromRegistryError::UnsupportedAlgorithm { algorithm_id } => {
+                IDkgVerifyDealingPrivateError::UnsupportedAlgorithm { algorithm_id }
+            }
+            MegaKeyFromRegistryError::MalformedPublicKey { node_id, key_bytes } => {
+                IDkgVerifyDealingPrivateError::MalformedPublicKey { node_id, key_bytes }
+            }
+        }
+    }
+}
+
 impl From<MegaKeyFromRegistryError> for IDkgOpenTranscriptError {
     fn from(e: MegaKeyFromRegistryError) -> Self {
         IDkgOpenTranscriptError::InternalError {
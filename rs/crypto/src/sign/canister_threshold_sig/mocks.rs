@@ -30,7 +30,10 @@ pub fn combine_sig_shares(
     _inputs: &ThresholdEcdsaSigInputs,
     _shares: &BTreeMap<NodeId, ThresholdEcdsaSigShare>,
 ) -> Result<ThresholdEcdsaCombinedSignature, ThresholdEcdsaCombineSigSharesError> {
-    Ok(ThresholdEcdsaCombinedSignature { signature: vec![] })
+    Ok(ThresholdEcdsaCombinedSignature {
+        signature: vec![],
+        recovery_id: None,
+    })
 }
 
 pub fn verify_combined_sig(
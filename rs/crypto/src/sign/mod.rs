@@ -453,6 +453,7 @@ impl<C: CryptoServiceProvider> ThresholdEcdsaSigner for CryptoComponentFatClient
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "ThresholdEcdsaSigner",
             crypto.method_name => "sign_share",
+            crypto.idkg_transcript_id => format!("{:?}", inputs.key_transcript().transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -477,6 +478,7 @@ impl<C: CryptoServiceProvider> ThresholdEcdsaSigVerifier for CryptoComponentFatC
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "ThresholdEcdsaSigVerifier",
             crypto.method_name => "verify_sig_share",
+            crypto.idkg_transcript_id => format!("{:?}", inputs.key_transcript().transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -498,6 +500,7 @@ impl<C: CryptoServiceProvider> ThresholdEcdsaSigVerifier for CryptoComponentFatC
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "ThresholdEcdsaSigVerifier",
             crypto.method_name => "combine_sig_shares",
+            crypto.idkg_transcript_id => format!("{:?}", inputs.key_transcript().transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
@@ -519,6 +522,7 @@ impl<C: CryptoServiceProvider> ThresholdEcdsaSigVerifier for CryptoComponentFatC
         let logger = new_logger!(&self.logger;
             crypto.trait_name => "ThresholdEcdsaSigVerifier",
             crypto.method_name => "verify_combined_sig",
+            crypto.idkg_transcript_id => format!("{:?}", inputs.key_transcript().transcript_id),
         );
         debug!(logger;
             crypto.description => "start",
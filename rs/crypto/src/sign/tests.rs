@@ -48,6 +48,7 @@ pub fn node_signing_record_with(
             key_value: public_key,
             version: 0,
             proof_data: None,
+            timestamp: None,
         },
         registry_version,
     }
@@ -69,6 +70,7 @@ pub fn committee_signing_record_with(
             key_value: public_key,
             version: 0,
             proof_data: None,
+            timestamp: None,
         },
         registry_version,
     }
@@ -89,6 +91,7 @@ pub fn dealing_encryption_pk_record_with(
             key_value,
             version: 0,
             proof_data: None,
+            timestamp: None,
         },
         registry_version,
     }
@@ -109,6 +112,7 @@ pub fn mega_encryption_pk_record_with(
             key_value,
             version: 0,
             proof_data: None,
+            timestamp: None,
         },
         registry_version,
     }
@@ -131,6 +135,7 @@ pub fn to_new_registry_record(
         key_value: record.value.key_value.clone(),
         version: 0,
         proof_data: None,
+        timestamp: None,
     };
     (key, record.registry_version, pk)
 }
@@ -19,7 +19,8 @@ use ic_test_utilities::types::ids::NODE_1;
 use ic_types::consensus::ecdsa::EcdsaDealing;
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgCreateTranscriptError, IDkgOpenTranscriptError,
-    IDkgVerifyComplaintError, ThresholdEcdsaCombineSigSharesError, ThresholdEcdsaSignShareError,
+    IDkgVerifyComplaintError, IDkgVerifyDealingPrivateError, IDkgVerifyDealingPublicError,
+    ThresholdEcdsaCombineSigSharesError, ThresholdEcdsaSignShareError,
 };
 use ic_types::crypto::canister_threshold_sig::idkg::{
     IDkgComplaint, IDkgDealing, IDkgMaskedTranscriptOrigin, IDkgMultiSignedDealing, IDkgOpening,
@@ -1212,29 +1213,144 @@ fn should_derive_differing_ecdsa_public_keys() {
 }
 
 #[test]
-fn should_run_verify_dealing_public() {
-    let crypto_components = temp_crypto_components_for(&[NODE_1]);
-    let params = fake_params_for(NODE_1);
-    let dealing = IDkgDealing {
-        transcript_id: dummy_idkg_transcript_id_for_tests(1),
-        dealer_id: NodeId::from(PrincipalId::new_node_test_id(0)),
-        internal_dealing_raw: vec![],
-    };
-    let result = crypto_for(NODE_1, &crypto_components).verify_dealing_public(&params, &dealing);
-    assert!(result.is_ok());
+fn should_verify_dealing_public() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    let verifier_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(verifier_id, &env.crypto_components).verify_dealing_public(&params, &dealing);
+
+    assert_eq!(result, Ok(()));
 }
 
 #[test]
-fn should_run_verify_dealing_private() {
-    let crypto_components = temp_crypto_components_for(&[NODE_1]);
-    let params = fake_params_for(NODE_1);
-    let dealing = IDkgDealing {
-        transcript_id: dummy_idkg_transcript_id_for_tests(1),
-        dealer_id: NodeId::from(PrincipalId::new_node_test_id(0)),
-        internal_dealing_raw: vec![],
-    };
-    let result = crypto_for(NODE_1, &crypto_components).verify_dealing_private(&params, &dealing);
-    assert!(result.is_ok());
+fn should_fail_verify_dealing_public_with_wrong_transcript_id() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let mut dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    dealing.transcript_id = dealing.transcript_id.increment();
+    let verifier_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(verifier_id, &env.crypto_components).verify_dealing_public(&params, &dealing);
+
+    assert_eq!(
+        result,
+        Err(IDkgVerifyDealingPublicError::TranscriptIdMismatch)
+    );
+}
+
+#[test]
+fn should_fail_verify_dealing_public_with_invalid_dealer() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let mut dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    let bad_dealer_id = random_node_id_excluding(params.dealers().get());
+    dealing.dealer_id = bad_dealer_id;
+    let verifier_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(verifier_id, &env.crypto_components).verify_dealing_public(&params, &dealing);
+
+    assert_eq!(
+        result,
+        Err(IDkgVerifyDealingPublicError::InvalidDealer {
+            node_id: bad_dealer_id
+        })
+    );
+}
+
+#[test]
+fn should_fail_verify_dealing_public_with_malformed_internal_dealing() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let mut dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    let truncated_len = dealing.internal_dealing_raw.len() / 2;
+    dealing.internal_dealing_raw.truncate(truncated_len);
+    let verifier_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(verifier_id, &env.crypto_components).verify_dealing_public(&params, &dealing);
+
+    assert!(matches!(
+        result,
+        Err(IDkgVerifyDealingPublicError::InvalidDealing { .. })
+    ));
+}
+
+#[test]
+fn should_verify_dealing_private() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    let receiver_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(receiver_id, &env.crypto_components).verify_dealing_private(&params, &dealing);
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn should_fail_verify_dealing_private_if_not_a_receiver() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let mut env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+
+    let not_a_receiver_id = random_node_id_excluding(params.receivers().get());
+    let crypto_not_a_receiver =
+        TempCryptoComponent::new(Arc::clone(&env.registry) as Arc<_>, not_a_receiver_id);
+    env.crypto_components
+        .insert(not_a_receiver_id, crypto_not_a_receiver);
+
+    let result = crypto_for(not_a_receiver_id, &env.crypto_components)
+        .verify_dealing_private(&params, &dealing);
+
+    assert_eq!(result, Err(IDkgVerifyDealingPrivateError::NotAReceiver));
+}
+
+#[test]
+fn should_fail_verify_dealing_private_with_corrupted_ciphertext() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let mut dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    corrupt_dealing_for_all_receivers(&mut dealing);
+    let receiver_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(receiver_id, &env.crypto_components).verify_dealing_private(&params, &dealing);
+
+    assert!(matches!(
+        result,
+        Err(IDkgVerifyDealingPrivateError::InvalidDealing { .. })
+    ));
+}
+
+#[test]
+fn should_fail_verify_dealing_private_with_unknown_dealer() {
+    let subnet_size = thread_rng().gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+    let params = env.params_for_random_sharing(AlgorithmId::ThresholdEcdsaSecp256k1);
+    let mut dealing = create_dealing(&params, &env.crypto_components, random_dealer_id(&params));
+    dealing.dealer_id = random_node_id_excluding(params.dealers().get());
+    let receiver_id = random_receiver_id(&params);
+
+    let result =
+        crypto_for(receiver_id, &env.crypto_components).verify_dealing_private(&params, &dealing);
+
+    assert!(matches!(
+        result,
+        Err(IDkgVerifyDealingPrivateError::InvalidArgument { .. })
+    ));
 }
 
 #[test]
@@ -1272,21 +1388,6 @@ fn should_run_retain_active_transcripts() {
     crypto_for(NODE_1, &crypto_components).retain_active_transcripts(&[]);
 }
 
-fn fake_params_for(node_id: NodeId) -> IDkgTranscriptParams {
-    let mut nodes = BTreeSet::new();
-    nodes.insert(node_id);
-
-    IDkgTranscriptParams::new(
-        dummy_idkg_transcript_id_for_tests(1),
-        nodes.clone(),
-        nodes,
-        RegistryVersion::from(1),
-        AlgorithmId::ThresholdEcdsaSecp256k1,
-        IDkgTranscriptOperation::Random,
-    )
-    .expect("failed to generate fake parameters")
-}
-
 fn fake_transcript() -> IDkgTranscript {
     let mut nodes = BTreeSet::new();
     nodes.insert(NODE_1);
@@ -1433,13 +1534,15 @@ fn corrupt_signed_dealings_for_all_receivers(
         .for_each(|(_idx, dealing)| corrupt_signed_dealing_for_all_receivers(dealing));
 }
 
-/// Corrupts the dealing by multiplying the ephemeral_key EccPoint with a random node index
 fn corrupt_signed_dealing_for_all_receivers(signed_dealing: &mut IDkgMultiSignedDealing) {
+    corrupt_dealing_for_all_receivers(&mut signed_dealing.dealing.idkg_dealing);
+}
+
+/// Corrupts the dealing by multiplying the ephemeral_key EccPoint with a random node index
+fn corrupt_dealing_for_all_receivers(dealing: &mut IDkgDealing) {
     let invalidated_internal_dealing_raw = {
-        let mut internal_dealing = IDkgDealingInternal::deserialize(
-            &signed_dealing.dealing.idkg_dealing.internal_dealing_raw,
-        )
-        .expect("failed to deserialize internal dealing");
+        let mut internal_dealing = IDkgDealingInternal::deserialize(&dealing.internal_dealing_raw)
+            .expect("failed to deserialize internal dealing");
         match internal_dealing.ciphertext {
             MEGaCiphertext::Single(ref mut ctext) => {
                 let corrupted_key = ctext
@@ -1460,7 +1563,7 @@ fn corrupt_signed_dealing_for_all_receivers(signed_dealing: &mut IDkgMultiSigned
             .serialize()
             .expect("failed to serialize internal dealing")
     };
-    signed_dealing.dealing.idkg_dealing.internal_dealing_raw = invalidated_internal_dealing_raw;
+    dealing.internal_dealing_raw = invalidated_internal_dealing_raw;
 }
 
 fn check_dealer_indexes(params: &IDkgTranscriptParams, transcript: &IDkgTranscript) {
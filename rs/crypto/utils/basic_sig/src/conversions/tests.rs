@@ -135,6 +135,7 @@ fn should_fail_convert_pubkey_nodeid_bad_bytes() {
         algorithm: 0,
         key_value: bad_bytes,
         proof_data: None,
+        timestamp: None,
     };
 
     let result = derive_node_id(&bad_proto_key);
@@ -154,6 +155,7 @@ fn should_convert_pubkey_nodeid_known_result() {
         algorithm: 0,
         key_value: vec![1; 32], // length is all that matters
         proof_data: None,
+        timestamp: None,
     };
 
     let result = derive_node_id(&proto_key);
@@ -0,0 +1,77 @@
+//! Translates MEGa public keys between the raw encoding stored in the
+//! registry and the DER/PEM encodings of a SubjectPublicKeyInfo, so that they
+//! can be consumed by external tools.
+//!
+//! Raw and DER keys are read and written as hex, PEM keys as text. The input
+//! is read from the given file, or from stdin if none is given.
+use clap::{App, Arg};
+use ic_crypto_internal_threshold_sig_ecdsa::MEGaPublicKeyK256Bytes;
+use ic_crypto_utils_canister_threshold_sig::conversions::{
+    mega_public_key_k256_from_bytes, MEGaPublicKeyConversions,
+};
+use std::io::Read;
+
+const FORMATS: [&str; 3] = ["raw", "der", "pem"];
+
+fn main() {
+    let flags = App::new("MEGa public key converter")
+        .version("0.1")
+        .author("Internet Computer Developers")
+        .about("Converts MEGa public keys over secp256k1 between raw, DER and PEM encodings")
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .value_name("FORMAT")
+                .help("The encoding of the input key")
+                .possible_values(&FORMATS)
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .value_name("FORMAT")
+                .help("The encoding of the output key")
+                .possible_values(&FORMATS)
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("input")
+                .value_name("FILE")
+                .help("The file to read the key from, stdin if not given")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let input = match flags.value_of("input") {
+        Some(path) => std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e)),
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .expect("Failed to read stdin");
+            input
+        }
+    };
+
+    let key = match flags.value_of("from").expect("--from is required") {
+        "raw" => mega_public_key_k256_from_bytes(&decode_hex(&input))
+            .unwrap_or_else(|e| panic!("Invalid raw key: {:?}", e)),
+        "der" => MEGaPublicKeyK256Bytes::from_der(&decode_hex(&input))
+            .unwrap_or_else(|e| panic!("Invalid DER key: {:?}", e)),
+        _ => MEGaPublicKeyK256Bytes::from_pem(&input)
+            .unwrap_or_else(|e| panic!("Invalid PEM key: {:?}", e)),
+    };
+
+    match flags.value_of("to").expect("--to is required") {
+        "raw" => println!("{}", hex::encode(key.as_bytes())),
+        "der" => println!("{}", hex::encode(key.to_der())),
+        _ => print!("{}", key.to_pem()),
+    }
+}
+
+fn decode_hex(input: &str) -> Vec<u8> {
+    hex::decode(input.trim()).unwrap_or_else(|e| panic!("Invalid hex: {}", e))
+}
@@ -0,0 +1,119 @@
+//! Conversion of MEGa encryption keys into various formats
+use ic_crypto_internal_basic_sig_der_utils as der_utils;
+use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, MEGaPublicKey, MEGaPublicKeyK256Bytes};
+use ic_crypto_utils_basic_sig::conversions::pem;
+use simple_asn1::{oid, ASN1Block, OID};
+use std::convert::TryFrom;
+
+#[cfg(test)]
+mod tests;
+
+/// Convenience methods for DER/PEM encoding and decoding of MEGa public keys.
+///
+/// A MEGa public key over secp256k1 is a compressed curve point, which is
+/// encoded as an elliptic curve public key in a SubjectPublicKeyInfo, see
+/// https://tools.ietf.org/html/rfc5480 for the spec.
+pub trait MEGaPublicKeyConversions {
+    /// Encodes this key into a DER-encoded SubjectPublicKeyInfo.
+    fn to_der(&self) -> Vec<u8>;
+
+    /// Tries to parse `bytes` as a DER-encoded SubjectPublicKeyInfo
+    fn from_der(bytes: &[u8]) -> Result<Self, MEGaPublicKeyDerParseError>
+    where
+        Self: Sized;
+
+    /// Encodes this key into a PEM-encoded SubjectPublicKeyInfo.
+    fn to_pem(&self) -> String;
+
+    /// Tries to parse `bytes` as a PEM-encoded SubjectPublicKeyInfo
+    fn from_pem(bytes: &str) -> Result<Self, MEGaPublicKeyPemParseError>
+    where
+        Self: Sized;
+}
+
+#[derive(Debug)]
+pub enum MEGaPublicKeyDerParseError {
+    OidExtractionError(String),
+    AlgorithmIdentifierValueError(der_utils::PkixAlgorithmIdentifier),
+    MalformedPublicKey(String),
+}
+
+#[derive(Debug)]
+pub enum MEGaPublicKeyPemParseError {
+    InvalidPem(std::io::Error),
+    InvalidDer(MEGaPublicKeyDerParseError),
+}
+
+impl MEGaPublicKeyConversions for MEGaPublicKeyK256Bytes {
+    fn to_der(&self) -> Vec<u8> {
+        let algorithm = ASN1Block::Sequence(
+            0,
+            vec![
+                ASN1Block::ObjectIdentifier(0, ec_public_key_oid()),
+                ASN1Block::ObjectIdentifier(0, secp256k1_oid()),
+            ],
+        );
+        let key = self.as_bytes();
+        let subject_public_key = ASN1Block::BitString(0, key.len() * 8, key.to_vec());
+        let subject_public_key_info = ASN1Block::Sequence(0, vec![algorithm, subject_public_key]);
+        simple_asn1::to_der(&subject_public_key_info)
+            .expect("failed to encode a SubjectPublicKeyInfo as DER")
+    }
+
+    fn from_der(pk_der: &[u8]) -> Result<Self, MEGaPublicKeyDerParseError>
+    where
+        Self: Sized,
+    {
+        let (algo_id, pk_bytes) = der_utils::algo_id_and_public_key_bytes_from_der(pk_der)
+            .map_err(|e| MEGaPublicKeyDerParseError::OidExtractionError(e.internal_error))?;
+        if algo_id != secp256k1_algorithm_identifier() {
+            return Err(MEGaPublicKeyDerParseError::AlgorithmIdentifierValueError(
+                algo_id,
+            ));
+        }
+        mega_public_key_k256_from_bytes(&pk_bytes)
+    }
+
+    fn to_pem(&self) -> String {
+        let der = self.to_der();
+        pem::der_to_pem(&der, pem::PUBLIC_KEY)
+    }
+
+    fn from_pem(pem: &str) -> Result<Self, MEGaPublicKeyPemParseError>
+    where
+        Self: Sized,
+    {
+        let der = pem::pem_to_der(pem, pem::PUBLIC_KEY)
+            .map_err(MEGaPublicKeyPemParseError::InvalidPem)?;
+        Self::from_der(&der[..]).map_err(MEGaPublicKeyPemParseError::InvalidDer)
+    }
+}
+
+/// Parses `bytes` as a serialized (compressed) MEGa public key over
+/// secp256k1, as stored in the registry.
+pub fn mega_public_key_k256_from_bytes(
+    bytes: &[u8],
+) -> Result<MEGaPublicKeyK256Bytes, MEGaPublicKeyDerParseError> {
+    let public_key = MEGaPublicKey::deserialize(EccCurveType::K256, bytes)
+        .map_err(|e| MEGaPublicKeyDerParseError::MalformedPublicKey(format!("{:?}", e)))?;
+    MEGaPublicKeyK256Bytes::try_from(&public_key)
+        .map_err(|e| MEGaPublicKeyDerParseError::MalformedPublicKey(format!("{:?}", e)))
+}
+
+/// The ASN.1 OID for elliptic curve public keys
+///
+/// OID for id-ecPublicKey is 1.2.840.10045.2.1, see https://tools.ietf.org/html/rfc5480
+fn ec_public_key_oid() -> OID {
+    oid!(1, 2, 840, 10045, 2, 1)
+}
+
+/// The ASN.1 OID for the secp256k1 curve
+///
+/// OID for secp256k1 is 1.3.132.0.10, see https://www.secg.org/sec2-v2.pdf
+fn secp256k1_oid() -> OID {
+    oid!(1, 3, 132, 0, 10)
+}
+
+fn secp256k1_algorithm_identifier() -> der_utils::PkixAlgorithmIdentifier {
+    der_utils::PkixAlgorithmIdentifier::new_with_oid_param(ec_public_key_oid(), secp256k1_oid())
+}
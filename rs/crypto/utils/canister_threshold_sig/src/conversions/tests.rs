@@ -0,0 +1,80 @@
+use super::*;
+
+/// The generator of secp256k1 in compressed form
+const SECP256K1_GENERATOR_HEX: &str =
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+/// The generator of secp256k1 as a DER-encoded SubjectPublicKeyInfo
+const SECP256K1_GENERATOR_DER_HEX: &str = "3036301006072a8648ce3d020106052b8104000a0322000279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+const SECP256K1_GENERATOR_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MDYwEAYHKoZIzj0CAQYFK4EEAAoDIgACeb5mfvncu6xVoGKVzocLBwKb/Nst
+zijZWfKBWxb4F5g=
+-----END PUBLIC KEY-----
+";
+
+fn generator() -> MEGaPublicKeyK256Bytes {
+    mega_public_key_k256_from_bytes(
+        &hex::decode(SECP256K1_GENERATOR_HEX).expect("Invalid hex in test data"),
+    )
+    .expect("Invalid public key in test data")
+}
+
+#[test]
+fn der_encoding_should_match_test_data() {
+    assert_eq!(
+        hex::encode(generator().to_der()),
+        SECP256K1_GENERATOR_DER_HEX
+    );
+}
+
+#[test]
+fn der_decoding_should_match_test_data() {
+    let der = hex::decode(SECP256K1_GENERATOR_DER_HEX).expect("Invalid hex in test data");
+    let decoded = MEGaPublicKeyK256Bytes::from_der(&der).expect("Conversion from der failed");
+    assert_eq!(decoded, generator());
+}
+
+#[test]
+fn pem_encoding_should_match_test_data() {
+    assert_eq!(generator().to_pem(), SECP256K1_GENERATOR_PEM);
+}
+
+#[test]
+fn pem_encoding_should_roundtrip() {
+    let key = generator();
+    let decoded =
+        MEGaPublicKeyK256Bytes::from_pem(&key.to_pem()).expect("Conversion from pem failed");
+    assert_eq!(decoded, key);
+}
+
+#[test]
+fn der_decoding_should_fail_on_wrong_curve() {
+    // The generator of secp256k1 with the OID of secp256r1 (1.2.840.10045.3.1.7)
+    let der = hex::decode("3039301306072a8648ce3d020106082a8648ce3d0301070322000279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+        .expect("Invalid hex in test data");
+    assert!(matches!(
+        MEGaPublicKeyK256Bytes::from_der(&der),
+        Err(MEGaPublicKeyDerParseError::AlgorithmIdentifierValueError(_))
+    ));
+}
+
+#[test]
+fn der_decoding_should_fail_on_invalid_point() {
+    let mut der = hex::decode(SECP256K1_GENERATOR_DER_HEX).expect("Invalid hex in test data");
+    // A compressed point must start with 0x02 or 0x03.
+    der[23] = 0x05;
+    assert!(matches!(
+        MEGaPublicKeyK256Bytes::from_der(&der),
+        Err(MEGaPublicKeyDerParseError::MalformedPublicKey(_))
+    ));
+}
+
+#[test]
+fn pem_decoding_should_fail_on_wrong_label() {
+    let pem = SECP256K1_GENERATOR_PEM.replace("PUBLIC KEY", "PRIVATE KEY");
+    assert!(matches!(
+        MEGaPublicKeyK256Bytes::from_pem(&pem),
+        Err(MEGaPublicKeyPemParseError::InvalidPem(_))
+    ));
+}
@@ -0,0 +1,4 @@
+#![forbid(unsafe_code)]
+#![deny(clippy::unwrap_used)]
+
+pub mod conversions;
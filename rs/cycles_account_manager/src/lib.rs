@@ -21,6 +21,7 @@ use ic_nns_constants::CYCLES_MINTING_CANISTER_ID;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{CanisterState, SystemState};
 use ic_types::{
+    canister_http::{CanisterHttpPricing, CanisterHttpRequestContext},
     ic00::{
         CanisterIdRecord, InstallCodeArgs, Method, Payload, SetControllerArgs, UpdateSettingsArgs,
     },
@@ -233,6 +234,15 @@ impl CyclesAccountManager {
             .consumed_cycles_since_replica_started += NominalCycles::from_cycles(cycles);
     }
 
+    /// Takes `cycles` that were observed as consumed out of the consumed
+    /// cycles again, because they are refunded after all, e.g. the fee for the
+    /// response bytes that a canister http request did not use.
+    pub fn observe_refunded_cycles(&self, system_state: &mut SystemState, cycles: Cycles) {
+        system_state
+            .canister_metrics
+            .consumed_cycles_since_replica_started -= NominalCycles::from_cycles(cycles);
+    }
+
     /// Subtracts the corresponding cycles worth of the provided
     /// `num_instructions` from the canister's balance.
     ///
@@ -344,10 +354,13 @@ impl CyclesAccountManager {
                 | Ok(Method::SetupInitialDKG)
                 | Ok(Method::DepositCycles)
                 | Ok(Method::HttpRequest)
+                | Ok(Method::HttpRequestDivergences)
                 | Ok(Method::RawRand)
                 | Ok(Method::GetECDSAPublicKey)
                 | Ok(Method::GetMockECDSAPublicKey)
                 | Ok(Method::SignWithECDSA)
+                | Ok(Method::SignWithECDSABatch)
+                | Ok(Method::ReserveECDSAPreSignatures)
                 | Ok(Method::SignWithMockECDSA)
                 | Ok(Method::BitcoinTestnetGetBalance)
                 | Ok(Method::BitcoinTestnetGetUtxos)
@@ -479,6 +492,61 @@ impl CyclesAccountManager {
         )
     }
 
+    /// Returns the fee for signing `signatures` message hashes with
+    /// `sign_with_ecdsa` or `sign_with_ecdsa_batch`. Every signature costs the
+    /// same, whether it is requested on its own or as part of a batch.
+    pub fn ecdsa_signature_fee(&self, signatures: u64) -> Cycles {
+        self.config.ecdsa_signature_fee * signatures
+    }
+
+    /// Returns the fee for newly reserving `pre_signatures` ECDSA
+    /// pre-signatures with a call of `reserve_ecdsa_pre_signatures`.
+    pub fn ecdsa_pre_signature_reservation_fee(&self, pre_signatures: u32) -> Cycles {
+        self.config.ecdsa_pre_signature_reservation_fee * u64::from(pre_signatures)
+    }
+
+    /// Returns the pricing of canister http requests: the `registry_pricing`
+    /// of the subnet if the registry configures one, otherwise the default
+    /// pricing of the subnet type.
+    pub fn http_request_pricing<'a>(
+        &'a self,
+        registry_pricing: Option<&'a CanisterHttpPricing>,
+    ) -> &'a CanisterHttpPricing {
+        registry_pricing.unwrap_or(&self.config.canister_http_pricing)
+    }
+
+    /// Returns the fee for a canister http request with a payload of
+    /// `request_size` bytes under the pricing returned by
+    /// [`Self::http_request_pricing`]. Like for xnet calls, the fee covers the
+    /// largest possible response, i.e. `max_response_size` bytes.
+    pub fn http_request_fee(
+        &self,
+        registry_pricing: Option<&CanisterHttpPricing>,
+        request_size: NumBytes,
+        max_response_size: NumBytes,
+    ) -> Cycles {
+        let pricing = self.http_request_pricing(registry_pricing);
+        pricing.base_fee
+            + pricing.fee_per_request_byte * request_size.get()
+            + pricing.fee_per_response_byte * max_response_size.get()
+    }
+
+    /// Returns the cycles to refund with a canister http response of
+    /// `response_size` bytes: the fee for the bytes of the largest response
+    /// that the request paid for, at the price charged for the request, that
+    /// the response does not use. Returns `None` if the response exceeds the
+    /// bytes that were paid for, i.e. it must not be delivered.
+    pub fn http_response_refund(
+        &self,
+        context: &CanisterHttpRequestContext,
+        response_size: NumBytes,
+    ) -> Option<Cycles> {
+        let unused_bytes = context
+            .max_response_bytes
+            .checked_sub(response_size.get())?;
+        Some(context.fee_per_response_byte * unused_bytes)
+    }
+
     /// Refunds the cycles from the response. In particular, adds leftover
     /// cycles from the what was reserved when the corresponding `Request` was
     /// sent earlier.
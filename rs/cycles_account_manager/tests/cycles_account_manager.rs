@@ -1,4 +1,4 @@
-use ic_base_types::NumSeconds;
+use ic_base_types::{HttpMethodType, NumSeconds};
 use ic_config::subnet_config::SubnetConfigs;
 use ic_cycles_account_manager::{IngressInductionCost, IngressInductionCostError};
 use ic_interfaces::execution_environment::CanisterOutOfCyclesError;
@@ -6,14 +6,16 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::SystemState;
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
+    mock_time,
     state::{new_canister_state, SystemStateBuilder},
     types::{
         ids::{canister_test_id, subnet_test_id, user_test_id},
-        messages::SignedIngressBuilder,
+        messages::{RequestBuilder, SignedIngressBuilder},
     },
     with_test_replica_logger,
 };
 use ic_types::{
+    canister_http::{CanisterHttpPricing, CanisterHttpRequestContext},
     ic00::{CanisterIdRecord, Payload, IC_00},
     messages::SignedIngressContent,
     nominal_cycles::NominalCycles,
@@ -684,3 +686,89 @@ fn verify_refund() {
         initial_consumed_cycles - NominalCycles::from(cycles)
     );
 }
+
+#[test]
+fn http_request_fee_covers_request_and_maximum_response_bytes() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let pricing = CanisterHttpPricing {
+        base_fee: Cycles::new(1_000_000),
+        fee_per_request_byte: Cycles::new(100),
+        fee_per_response_byte: Cycles::new(10),
+    };
+
+    assert_eq!(
+        cycles_account_manager.http_request_fee(
+            Some(&pricing),
+            NumBytes::from(200),
+            NumBytes::from(2_000)
+        ),
+        Cycles::new(1_000_000 + 100 * 200 + 10 * 2_000)
+    );
+    assert_eq!(
+        cycles_account_manager.http_request_fee(
+            Some(&CanisterHttpPricing::default()),
+            NumBytes::from(200),
+            NumBytes::from(2_000)
+        ),
+        Cycles::zero()
+    );
+}
+
+#[test]
+fn http_request_fee_falls_back_to_the_pricing_of_the_subnet_type() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_type(SubnetType::System)
+        .build();
+    let pricing = CanisterHttpPricing {
+        base_fee: Cycles::new(1_000_000),
+        fee_per_request_byte: Cycles::new(100),
+        fee_per_response_byte: Cycles::new(10),
+    };
+
+    assert_eq!(
+        cycles_account_manager.http_request_pricing(None),
+        &SubnetConfigs::default()
+            .own_subnet_config(SubnetType::System)
+            .cycles_account_manager_config
+            .canister_http_pricing
+    );
+    assert_eq!(
+        cycles_account_manager.http_request_pricing(Some(&pricing)),
+        &pricing
+    );
+    assert_eq!(
+        cycles_account_manager.http_request_fee(None, NumBytes::from(200), NumBytes::from(2_000)),
+        Cycles::zero()
+    );
+}
+
+#[test]
+fn http_response_refund_covers_unused_response_bytes() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let context = CanisterHttpRequestContext {
+        request: RequestBuilder::new().build(),
+        url: "https://example.com".to_string(),
+        body: None,
+        http_method: HttpMethodType::GET,
+        transform_method_name: None,
+        time: mock_time(),
+        timeout: mock_time(),
+        max_response_bytes: 2_000,
+        fee_per_response_byte: Cycles::new(10),
+    };
+
+    assert_eq!(
+        cycles_account_manager.http_response_refund(&context, NumBytes::from(500)),
+        Some(Cycles::new(10 * 1_500))
+    );
+    assert_eq!(
+        cycles_account_manager.http_response_refund(&context, NumBytes::from(2_000)),
+        Some(Cycles::zero())
+    );
+    // A response that exceeds the bytes that were paid for is not refunded,
+    // it must not be delivered.
+    assert_eq!(
+        cycles_account_manager.http_response_refund(&context, NumBytes::from(3_000)),
+        None
+    );
+}
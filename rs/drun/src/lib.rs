@@ -318,6 +318,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
         registry_version: RegistryVersion::from(1),
         time: mock_time(),
         consensus_responses: vec![],
+        canister_http_responses: vec![],
     }
 }
 /// Block till the given ingress message has finished executing and
@@ -162,13 +162,17 @@ impl CanisterManager {
             | Ok(Ic00Method::CreateCanister)
             | Ok(Ic00Method::GetECDSAPublicKey)
             | Ok(Ic00Method::GetMockECDSAPublicKey)
+            | Ok(Ic00Method::ReserveECDSAPreSignatures)
             | Ok(Ic00Method::SetupInitialDKG)
             | Ok(Ic00Method::SignWithECDSA)
+            | Ok(Ic00Method::SignWithECDSABatch)
             | Ok(Ic00Method::SignWithMockECDSA)
             // "DepositCycles" can be called by anyone however as ingress message
             // cannot carry cycles, it does not make sense to allow them from users.
             | Ok(Ic00Method::DepositCycles)
-            | Ok(Ic00Method::HttpRequest) => rejected_canister_err,
+            | Ok(Ic00Method::HttpRequest)
+            // Users read the canister http divergences with a query.
+            | Ok(Ic00Method::HttpRequestDivergences) => rejected_canister_err,
 
             // These methods are only valid if they are sent by the controller
             // of the canister. We assume that the canister always wants to
@@ -819,6 +823,11 @@ impl CanisterManager {
 
         // Take out the canister from `ReplicatedState`.
         let _canister_to_delete = state.take_canister_state(&canister_id_to_delete).unwrap();
+        // Its reserved ECDSA pre-signatures become available to other canisters
+        // and the divergence reports of its canister http requests are dropped.
+        let manager = &mut state.metadata.subnet_call_context_manager;
+        manager.remove_ecdsa_quadruple_reservations(&canister_id_to_delete);
+        manager.remove_http_request_divergences(&canister_id_to_delete);
 
         let layout = canister_layout(state.path(), &canister_id_to_delete);
         layout
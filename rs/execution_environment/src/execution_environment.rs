@@ -1,7 +1,7 @@
 use crate::{
     canister_manager::{CanisterManager, CanisterMgrConfig, StopCanisterResult},
     canister_settings::CanisterSettings,
-    execution_environment_metrics::ExecutionEnvironmentMetrics,
+    execution_environment_metrics::{ExecutionEnvironmentMetrics, UNKNOWN_ECDSA_KEY_ID_LABEL},
     hypervisor::Hypervisor,
     QueryExecutionType,
 };
@@ -11,10 +11,13 @@ use ic_config::execution_environment::Config as ExecutionConfig;
 use ic_crypto::derive_tecdsa_public_key;
 use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_ic00_types::{
-    CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs, CreateCanisterArgs, EmptyBlob,
-    GetECDSAPublicKeyArgs, GetECDSAPublicKeyResponse, InstallCodeArgs, Method as Ic00Method,
-    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, UpdateSettingsArgs, IC_00,
+    CanisterHttpDivergence, CanisterHttpDivergencesResult, CanisterHttpRequestArgs,
+    CanisterIdRecord, CanisterSettingsArgs, CreateCanisterArgs, EmptyBlob, GetECDSAPublicKeyArgs,
+    GetECDSAPublicKeyResponse, InstallCodeArgs, Method as Ic00Method, Payload as Ic00Payload,
+    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    ReserveECDSAPreSignaturesArgs, SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs,
+    SignWithECDSABatchArgs, SignWithECDSABatchReply, SignWithECDSABatchResult, SignWithECDSAReply,
+    UpdateSettingsArgs, IC_00, MAX_SIGN_WITH_ECDSA_BATCH_SIZE,
 };
 use ic_interfaces::{
     execution_environment::{
@@ -29,11 +32,14 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::RoutingTable;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    metadata_state::subnet_call_context_manager::{SetupInitialDkgContext, SignWithEcdsaContext},
+    metadata_state::subnet_call_context_manager::{
+        SetupInitialDkgContext, SignWithEcdsaBatchContext, SignWithEcdsaBatchItem,
+        SignWithEcdsaContext,
+    },
     CallContextAction, CallOrigin, CanisterState, ReplicatedState,
 };
 use ic_types::{
-    canister_http::CanisterHttpRequestContext,
+    canister_http::{CanisterHttpRequestContext, CANISTER_HTTP_TIMEOUT_INTERVAL},
     canonical_error::{not_found_error, permission_denied_error, CanonicalError},
     crypto::canister_threshold_sig::{ExtendedDerivationPath, MasterEcdsaPublicKey},
     crypto::threshold_sig::ni_dkg::NiDkgTargetId,
@@ -168,19 +174,83 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
 
         let mut msg = match msg {
             CanisterInputMessage::Response(response) => {
-                let request = state
-                    .metadata
-                    .subnet_call_context_manager
-                    .retrieve_request(response.originator_reply_callback, &self.log);
+                let manager = &mut state.metadata.subnet_call_context_manager;
+                // The signatures of the message hashes of a
+                // `sign_with_ecdsa_batch` call are collected until the call
+                // can be replied to with all of them.
+                if manager.is_sign_with_ecdsa_batch_item(response.originator_reply_callback) {
+                    manager.retrieve_request(response.originator_reply_callback, &self.log);
+                    let outcome = match &response.response_payload {
+                        Payload::Data(data) => match SignWithECDSAReply::decode(data) {
+                            Ok(reply) => SignWithEcdsaBatchItem::Signed(reply.signature),
+                            Err(err) => SignWithEcdsaBatchItem::Rejected(err.to_string()),
+                        },
+                        Payload::Reject(context) => {
+                            SignWithEcdsaBatchItem::Rejected(context.message.clone())
+                        }
+                    };
+                    if let Some(context) = manager.complete_sign_with_ecdsa_batch_item(
+                        response.originator_reply_callback,
+                        outcome,
+                    ) {
+                        let reply = sign_with_ecdsa_batch_reply(context.items);
+                        state.push_subnet_output_response(Response {
+                            originator: context.request.sender,
+                            respondent: CanisterId::from(self.own_subnet_id),
+                            originator_reply_callback: context.request.sender_reply_callback,
+                            refund: context.request.payment,
+                            response_payload: Payload::Data(reply.encode()),
+                        });
+                    }
+                    return (state, instructions_limit);
+                }
+                // The response bytes of a canister http request that were
+                // paid for but not used are refunded with the response. A
+                // response that exceeds the bytes that were paid for is
+                // rejected instead of delivered.
+                let mut response_payload = response.response_payload;
+                let http_response_refund = match manager
+                    .canister_http_request_contexts
+                    .get(&response.originator_reply_callback)
+                {
+                    None => Cycles::zero(),
+                    Some(context) => match self
+                        .cycles_account_manager
+                        .http_response_refund(context, response_payload.size_of())
+                    {
+                        Some(refund) => refund,
+                        None => {
+                            response_payload = Payload::Reject(RejectContext {
+                                code: RejectCode::SysFatal,
+                                message: format!(
+                                    "Http response of {} bytes exceeds the max_response_bytes of {} bytes.",
+                                    response_payload.size_of(),
+                                    context.max_response_bytes
+                                ),
+                            });
+                            self.cycles_account_manager
+                                .http_response_refund(context, response_payload.size_of())
+                                .unwrap_or_else(Cycles::zero)
+                        }
+                    },
+                };
+                let request =
+                    manager.retrieve_request(response.originator_reply_callback, &self.log);
                 return match request {
                     None => (state, instructions_limit),
                     Some(request) => {
+                        if let Some(canister) = state.canister_state_mut(&request.sender) {
+                            self.cycles_account_manager.observe_refunded_cycles(
+                                &mut canister.system_state,
+                                http_response_refund,
+                            );
+                        }
                         state.push_subnet_output_response(Response {
                             originator: request.sender,
                             respondent: CanisterId::from(self.own_subnet_id),
                             originator_reply_callback: request.sender_reply_callback,
-                            refund: request.payment,
-                            response_payload: response.response_payload,
+                            refund: request.payment + http_response_refund,
+                            response_payload,
                         });
                         (state, instructions_limit)
                     }
@@ -369,6 +439,16 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 (Some((res, msg.take_cycles())), instructions_limit)
             }
 
+            Ok(Ic00Method::HttpRequestDivergences) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(err.into()),
+                    Ok(args) => {
+                        get_http_request_divergences(*msg.sender(), args.get_canister_id(), &state)
+                    }
+                };
+                (Some((res, msg.take_cycles())), instructions_limit)
+            }
+
             Ok(Ic00Method::StartCanister) => {
                 let res = match CanisterIdRecord::decode(payload) {
                     Err(err) => Err(err.into()),
@@ -447,18 +527,101 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                             instructions_limit,
                         ),
                         Ok(args) => {
-                            state
+                            let limits = &state.metadata.own_subnet_canister_http_limits;
+                            let in_flight_requests = state
                                 .metadata
                                 .subnet_call_context_manager
-                                .push_http_request(CanisterHttpRequestContext {
-                                    request: request.clone(),
-                                    url: args.url,
-                                    body: args.body,
-                                    http_method: args.http_method,
-                                    transform_method_name: args.transform_method_name,
-                                    time: state.time(),
-                                });
-                            (None, instructions_limit)
+                                .canister_http_request_contexts
+                                .len();
+                            let max_response_bytes =
+                                args.max_response_bytes.unwrap_or(limits.max_response_bytes);
+                            let http_request_fee = self.cycles_account_manager.http_request_fee(
+                                limits.pricing.as_ref(),
+                                NumBytes::from(payload.len() as u64),
+                                NumBytes::from(max_response_bytes),
+                            );
+                            let fee_per_response_byte = self
+                                .cycles_account_manager
+                                .http_request_pricing(limits.pricing.as_ref())
+                                .fee_per_response_byte;
+                            if !limits.allows_url(&args.url) {
+                                let user_error = UserError::new(
+                                    ErrorCode::InvalidManagementPayload,
+                                    format!(
+                                        "Url {} does not use one of the allowed schemes {:?}.",
+                                        args.url, limits.allowed_url_schemes
+                                    ),
+                                );
+                                (
+                                    Some((Err(user_error), msg.take_cycles())),
+                                    instructions_limit,
+                                )
+                            } else if max_response_bytes > limits.max_response_bytes {
+                                let user_error = UserError::new(
+                                    ErrorCode::InvalidManagementPayload,
+                                    format!(
+                                        "max_response_bytes {} exceeds the limit of {} bytes of the subnet.",
+                                        max_response_bytes, limits.max_response_bytes
+                                    ),
+                                );
+                                (
+                                    Some((Err(user_error), msg.take_cycles())),
+                                    instructions_limit,
+                                )
+                            } else if in_flight_requests >= limits.max_concurrent_requests as usize
+                            {
+                                let user_error = UserError::new(
+                                    ErrorCode::SubnetOversubscribed,
+                                    format!(
+                                        "The subnet already has {} canister http requests in flight, which is the maximum.",
+                                        in_flight_requests
+                                    ),
+                                );
+                                (
+                                    Some((Err(user_error), msg.take_cycles())),
+                                    instructions_limit,
+                                )
+                            } else if request.payment < http_request_fee {
+                                let user_error = UserError::new(
+                                    ErrorCode::InsufficientCyclesInCall,
+                                    format!(
+                                        "Canister http request requires a fee of {} cycles but only {} cycles were received with the request.",
+                                        http_request_fee, request.payment
+                                    ),
+                                );
+                                (
+                                    Some((Err(user_error), msg.take_cycles())),
+                                    instructions_limit,
+                                )
+                            } else {
+                                // The fee is consumed now, the rest of the
+                                // attached cycles, and the fee for the response
+                                // bytes that are not used, is refunded with the
+                                // response.
+                                let mut request = request.clone();
+                                request.payment -= http_request_fee;
+                                if let Some(canister) = state.canister_state_mut(&request.sender) {
+                                    self.cycles_account_manager.observe_consumed_cycles(
+                                        &mut canister.system_state,
+                                        http_request_fee,
+                                    );
+                                }
+                                state
+                                    .metadata
+                                    .subnet_call_context_manager
+                                    .push_http_request(CanisterHttpRequestContext {
+                                        request,
+                                        url: args.url,
+                                        body: args.body,
+                                        http_method: args.http_method,
+                                        transform_method_name: args.transform_method_name,
+                                        time: state.time(),
+                                        timeout: state.time() + CANISTER_HTTP_TIMEOUT_INTERVAL,
+                                        max_response_bytes,
+                                        fee_per_response_byte,
+                                    });
+                                (None, instructions_limit)
+                            }
                         }
                     }
                 }
@@ -508,8 +671,10 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                     let mut reject_message = String::new();
                     if !state.metadata.own_subnet_features.ecdsa_signatures {
                         reject_message = "This API is not enabled on this subnet".to_string();
+                        self.observe_ecdsa_signature_request_rejected(payload, "not_enabled");
                     } else if payload.is_empty() {
                         reject_message = "An empty message cannot be signed".to_string();
+                        self.observe_ecdsa_signature_request_rejected(payload, "empty_message");
                     }
 
                     if !reject_message.is_empty() {
@@ -528,7 +693,13 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                     }
 
                     let res = match SignWithECDSAArgs::decode(payload) {
-                        Err(err) => Some((Err(err.into()), msg.take_cycles())),
+                        Err(err) => {
+                            self.metrics.observe_ecdsa_signature_request(
+                                UNKNOWN_ECDSA_KEY_ID_LABEL,
+                                "invalid_args",
+                            );
+                            Some((Err(err.into()), msg.take_cycles()))
+                        }
                         Ok(args) => self
                             .sign_with_ecdsa(
                                 request.clone(),
@@ -556,6 +727,77 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 }
             },
 
+            Ok(Ic00Method::SignWithECDSABatch) => match &msg {
+                RequestOrIngress::Request(request) => {
+                    let res = if !state.metadata.own_subnet_features.ecdsa_signatures {
+                        Some(Err(UserError::new(
+                            ErrorCode::CanisterRejectedMessage,
+                            "This API is not enabled on this subnet",
+                        )))
+                    } else {
+                        match SignWithECDSABatchArgs::decode(payload) {
+                            Err(err) => Some(Err(err.into())),
+                            Ok(args) => self
+                                .sign_with_ecdsa_batch(request.clone(), args, &mut state, rng)
+                                .transpose()
+                                .map(|res| res.map(|reply| reply.encode())),
+                        }
+                    };
+                    (res.map(|res| (res, msg.take_cycles())), instructions_limit)
+                }
+                RequestOrIngress::Ingress(_) => {
+                    error!(self.log, "[EXC-BUG] Ingress messages to SignWithECDSABatch should've been filtered earlier.");
+                    let error_string = format!(
+                        "SignWithECDSABatch is called by user {}. It can only be called by a canister.",
+                        msg.sender()
+                    );
+                    let user_error =
+                        UserError::new(ErrorCode::CanisterContractViolation, error_string);
+                    let res = Some((Err(user_error), msg.take_cycles()));
+                    (res, instructions_limit)
+                }
+            },
+
+            Ok(Ic00Method::ReserveECDSAPreSignatures) => match &msg {
+                RequestOrIngress::Request(request) => {
+                    let res = if !state.metadata.own_subnet_features.ecdsa_signatures {
+                        Err(UserError::new(
+                            ErrorCode::CanisterRejectedMessage,
+                            "This API is not enabled on this subnet",
+                        ))
+                    } else {
+                        match ReserveECDSAPreSignaturesArgs::decode(payload) {
+                            Err(err) => Err(err.into()),
+                            Ok(args) => self.reserve_ecdsa_pre_signatures(
+                                request.sender,
+                                request.payment,
+                                args,
+                                &mut state,
+                            ),
+                        }
+                    };
+                    let cycles = msg.take_cycles();
+                    let res = match res {
+                        // The fee is consumed, the rest of the attached cycles
+                        // is refunded.
+                        Ok(fee) => (Ok(EmptyBlob::encode()), cycles - fee),
+                        Err(err) => (Err(err), cycles),
+                    };
+                    (Some(res), instructions_limit)
+                }
+                RequestOrIngress::Ingress(_) => {
+                    error!(self.log, "[EXC-BUG] Ingress messages to ReserveECDSAPreSignatures should've been filtered earlier.");
+                    let error_string = format!(
+                        "ReserveECDSAPreSignatures is called by user {}. It can only be called by a canister.",
+                        msg.sender()
+                    );
+                    let user_error =
+                        UserError::new(ErrorCode::CanisterContractViolation, error_string);
+                    let res = Some((Err(user_error), msg.take_cycles()));
+                    (res, instructions_limit)
+                }
+            },
+
             Ok(Ic00Method::SignWithMockECDSA) => {
                 let res = match &msg {
                     RequestOrIngress::Request(request) => {
@@ -1954,7 +2196,7 @@ impl ExecutionEnvironmentImpl {
     #[allow(clippy::too_many_arguments)]
     fn sign_with_ecdsa(
         &self,
-        request: Request,
+        mut request: Request,
         message_hash: Vec<u8>,
         derivation_path: Vec<Vec<u8>>,
         key_id: &str,
@@ -1962,18 +2204,30 @@ impl ExecutionEnvironmentImpl {
         state: &mut ReplicatedState,
         rng: &mut (dyn RngCore + 'static),
     ) -> Result<(), UserError> {
+        let observe = |outcome| {
+            if !is_mock {
+                self.metrics
+                    .observe_ecdsa_signature_request(ecdsa_key_id_label(key_id), outcome);
+            }
+        };
         if message_hash.len() != 32 {
+            observe("invalid_message_hash");
             return Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
                 "message_hash must be 32 bytes",
             ));
         }
-        if key_id != "secp256k1" {
-            return Err(UserError::new(
-                ErrorCode::CanisterRejectedMessage,
-                "key_id must be \"secp256k1\"",
-            ));
-        };
+        if let Err((outcome, err)) = self.check_ecdsa_signing_key(key_id, is_mock, state) {
+            observe(outcome);
+            return Err(err);
+        }
+        if !is_mock {
+            if let Err(err) = self.consume_ecdsa_signature_fee(&mut request, 1, state) {
+                observe("insufficient_cycles");
+                return Err(err);
+            }
+        }
+        observe("accepted");
 
         let mut pseudo_random_id = [0u8; 32];
         rng.fill_bytes(&mut pseudo_random_id);
@@ -1994,12 +2248,248 @@ impl ExecutionEnvironmentImpl {
                     derivation_path,
                     pseudo_random_id,
                     batch_time: state.metadata.batch_time,
+                    key_id: key_id.to_string(),
                 },
                 is_mock,
             );
         Ok(())
     }
 
+    /// Checks that this subnet may sign with the ECDSA key `key_id`. Otherwise,
+    /// returns the outcome under which the request is counted and the error
+    /// to reject it with.
+    fn check_ecdsa_signing_key(
+        &self,
+        key_id: &str,
+        is_mock: bool,
+        state: &ReplicatedState,
+    ) -> Result<(), (&'static str, UserError)> {
+        if key_id != SUPPORTED_ECDSA_KEY_ID {
+            return Err((
+                "unknown_key_id",
+                UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    "key_id must be \"secp256k1\"",
+                ),
+            ));
+        };
+        if !is_mock
+            && !state
+                .metadata
+                .network_topology
+                .may_sign_with_ecdsa_key(self.own_subnet_id, key_id)
+        {
+            return Err((
+                "signing_not_permitted",
+                UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Subnet {} is not allowed to sign with key_id \"{}\"",
+                        self.own_subnet_id, key_id
+                    ),
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Consumes the fee for signing `signatures` message hashes out of the
+    /// cycles attached to `request` and records it as consumed by the sender.
+    /// The rest of the attached cycles is refunded with the reply.
+    fn consume_ecdsa_signature_fee(
+        &self,
+        request: &mut Request,
+        signatures: usize,
+        state: &mut ReplicatedState,
+    ) -> Result<(), UserError> {
+        let fee = self
+            .cycles_account_manager
+            .ecdsa_signature_fee(signatures as u64);
+        if request.payment < fee {
+            return Err(UserError::new(
+                ErrorCode::InsufficientCyclesInCall,
+                format!(
+                    "Signing {} message hashes requires a fee of {} cycles but only {} cycles were received with the request.",
+                    signatures, fee, request.payment
+                ),
+            ));
+        }
+        request.payment -= fee;
+        if let Some(canister) = state.canister_state_mut(&request.sender) {
+            self.cycles_account_manager
+                .observe_consumed_cycles(&mut canister.system_state, fee);
+        }
+        Ok(())
+    }
+
+    /// Passes each message hash of a `sign_with_ecdsa_batch` call on to
+    /// consensus to be signed under the same derivation path, charging the
+    /// signature fee for each of them. Message hashes that are not 32 bytes long are
+    /// rejected individually and not charged for. Returns the reply right
+    /// away if none of the message hashes can be signed; otherwise the call
+    /// is replied to once all of its message hashes are signed.
+    fn sign_with_ecdsa_batch(
+        &self,
+        mut request: Request,
+        args: SignWithECDSABatchArgs,
+        state: &mut ReplicatedState,
+        rng: &mut (dyn RngCore + 'static),
+    ) -> Result<Option<SignWithECDSABatchReply>, UserError> {
+        const INVALID_MESSAGE_HASH: &str = "message_hash must be 32 bytes";
+        let SignWithECDSABatchArgs {
+            message_hashes,
+            derivation_path,
+            key_id,
+        } = args;
+        let observe = |outcome, count| {
+            self.metrics.observe_ecdsa_signature_requests(
+                ecdsa_key_id_label(&key_id),
+                outcome,
+                count,
+            );
+        };
+        if message_hashes.is_empty() || message_hashes.len() > MAX_SIGN_WITH_ECDSA_BATCH_SIZE {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "message_hashes must contain between 1 and {} message hashes, but it contains {}",
+                    MAX_SIGN_WITH_ECDSA_BATCH_SIZE,
+                    message_hashes.len()
+                ),
+            ));
+        }
+        if let Err((outcome, err)) = self.check_ecdsa_signing_key(&key_id, false, state) {
+            observe(outcome, message_hashes.len());
+            return Err(err);
+        }
+
+        let signatures = message_hashes
+            .iter()
+            .filter(|hash| hash.len() == 32)
+            .count();
+        observe("invalid_message_hash", message_hashes.len() - signatures);
+        if signatures == 0 {
+            return Ok(Some(SignWithECDSABatchReply {
+                results: vec![
+                    SignWithECDSABatchResult::Error(INVALID_MESSAGE_HASH.to_string());
+                    message_hashes.len()
+                ],
+            }));
+        }
+        if let Err(err) = self.consume_ecdsa_signature_fee(&mut request, signatures, state) {
+            observe("insufficient_cycles", signatures);
+            return Err(err);
+        }
+        observe("accepted", signatures);
+
+        info!(
+            self.log,
+            "Accepted a sign_with_ecdsa_batch request with {} message hashes from {:?}",
+            message_hashes.len(),
+            request.sender()
+        );
+        let batch_time = state.metadata.batch_time;
+        let manager = &mut state.metadata.subnet_call_context_manager;
+        let items = message_hashes
+            .into_iter()
+            .map(|message_hash| {
+                if message_hash.len() != 32 {
+                    return SignWithEcdsaBatchItem::Rejected(INVALID_MESSAGE_HASH.to_string());
+                }
+                let mut pseudo_random_id = [0u8; 32];
+                rng.fill_bytes(&mut pseudo_random_id);
+                let callback_id = manager.push_sign_with_ecdsa_request(
+                    SignWithEcdsaContext {
+                        request: request.clone(),
+                        message_hash,
+                        derivation_path: derivation_path.clone(),
+                        pseudo_random_id,
+                        batch_time,
+                        key_id: key_id.clone(),
+                    },
+                    false,
+                );
+                SignWithEcdsaBatchItem::Pending(callback_id)
+            })
+            .collect();
+        manager.push_sign_with_ecdsa_batch_request(SignWithEcdsaBatchContext { request, items });
+        Ok(None)
+    }
+
+    /// Sets the number of pre-signatures of the ECDSA key `key_id` that are
+    /// reserved for the signing requests of `canister_id`, charging the fee
+    /// for the pre-signatures it reserves in addition to its current
+    /// reservation. The reservations of all canisters together may not
+    /// exceed the limit that the registry configures for this subnet.
+    /// Reservations that expired because they were not used count as none.
+    /// Returns the fee that is consumed out of `payment`.
+    fn reserve_ecdsa_pre_signatures(
+        &self,
+        canister_id: CanisterId,
+        payment: Cycles,
+        args: ReserveECDSAPreSignaturesArgs,
+        state: &mut ReplicatedState,
+    ) -> Result<Cycles, UserError> {
+        let ReserveECDSAPreSignaturesArgs {
+            key_id,
+            pre_signatures,
+        } = args;
+        if let Err((_, err)) = self.check_ecdsa_signing_key(&key_id, false, state) {
+            return Err(err);
+        }
+        let limit = state
+            .metadata
+            .network_topology
+            .subnets
+            .get(&self.own_subnet_id)
+            .map_or(0, |subnet| subnet.max_reserved_ecdsa_quadruples);
+        let now = state.metadata.batch_time;
+        let manager = &mut state.metadata.subnet_call_context_manager;
+        let current = manager.ecdsa_quadruple_reservation(&key_id, &canister_id, now);
+        let reserved_by_others = manager.reserved_ecdsa_quadruples(&key_id, now) - current;
+        if u64::from(reserved_by_others) + u64::from(pre_signatures) > u64::from(limit) {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "Cannot reserve {} pre-signatures of key_id \"{}\": {} of the at most {} pre-signatures that may be reserved on this subnet are reserved by other canisters.",
+                    pre_signatures, key_id, reserved_by_others, limit
+                ),
+            ));
+        }
+        let fee = self
+            .cycles_account_manager
+            .ecdsa_pre_signature_reservation_fee(pre_signatures.saturating_sub(current));
+        if payment < fee {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "Reserving {} pre-signatures requires a fee of {} cycles but only {} cycles were received with the request.",
+                    pre_signatures, fee, payment
+                ),
+            ));
+        }
+
+        info!(
+            self.log,
+            "Canister {} reserved {} pre-signatures of key_id \"{}\"",
+            canister_id,
+            pre_signatures,
+            key_id
+        );
+        manager.set_ecdsa_quadruple_reservation(&key_id, canister_id, pre_signatures, now);
+        Ok(fee)
+    }
+
+    /// Counts a `sign_with_ecdsa` request with the given `payload` that is
+    /// rejected before its arguments are validated.
+    fn observe_ecdsa_signature_request_rejected(&self, payload: &[u8], outcome: &str) {
+        let key_id = SignWithECDSAArgs::decode(payload)
+            .map(|args| ecdsa_key_id_label(&args.key_id).to_string())
+            .unwrap_or_else(|_| UNKNOWN_ECDSA_KEY_ID_LABEL.to_string());
+        self.metrics
+            .observe_ecdsa_signature_request(&key_id, outcome);
+    }
+
     fn get_ingress_status(
         &self,
         canister: &mut CanisterState,
@@ -2105,6 +2595,87 @@ impl ExecutionEnvironmentImpl {
     }
 }
 
+/// The only ECDSA key id that `sign_with_ecdsa` requests may use.
+const SUPPORTED_ECDSA_KEY_ID: &str = "secp256k1";
+
+/// Returns the reply to a `sign_with_ecdsa_batch` call whose message hashes
+/// all have been signed or rejected. A message hash that is still pending
+/// breaks this invariant and is reported as an internal error.
+fn sign_with_ecdsa_batch_reply(items: Vec<SignWithEcdsaBatchItem>) -> SignWithECDSABatchReply {
+    SignWithECDSABatchReply {
+        results: items
+            .into_iter()
+            .map(|item| match item {
+                SignWithEcdsaBatchItem::Signed(signature) => {
+                    SignWithECDSABatchResult::Signature(signature)
+                }
+                SignWithEcdsaBatchItem::Rejected(message) => {
+                    SignWithECDSABatchResult::Error(message)
+                }
+                SignWithEcdsaBatchItem::Pending(callback_id) => {
+                    debug_assert!(
+                        false,
+                        "Replying to a sign_with_ecdsa_batch call with the pending item {}",
+                        callback_id
+                    );
+                    SignWithECDSABatchResult::Error(format!(
+                        "Internal error: the message hash of callback {} was not signed",
+                        callback_id
+                    ))
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Returns the metrics label of the ECDSA key id `key_id`.
+fn ecdsa_key_id_label(key_id: &str) -> &str {
+    if key_id == SUPPORTED_ECDSA_KEY_ID {
+        key_id
+    } else {
+        UNKNOWN_ECDSA_KEY_ID_LABEL
+    }
+}
+
+/// Returns the encoded divergence reports of the canister http requests of
+/// `canister_id`, which only the canister and its controllers may read. Also
+/// answers `http_request_divergences` queries.
+pub(crate) fn get_http_request_divergences(
+    sender: PrincipalId,
+    canister_id: CanisterId,
+    state: &ReplicatedState,
+) -> Result<Vec<u8>, UserError> {
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found.", &canister_id),
+        )
+    })?;
+    if sender != canister_id.get() && !canister.controllers().contains(&sender) {
+        return Err(UserError::new(
+            ErrorCode::CanisterInvalidController,
+            format!(
+                "Only the canister {} and its controllers may read its canister http divergences, but the sender is {}.",
+                canister_id, sender
+            ),
+        ));
+    }
+    let divergences = state
+        .metadata
+        .subnet_call_context_manager
+        .http_request_divergences(&canister_id)
+        .map(|report| CanisterHttpDivergence {
+            request_id: report.request_id.get(),
+            url: report.url.clone(),
+            distinct_responses: report.distinct_responses,
+            response_shares: report.response_shares,
+            transform_method_name: report.transform_method_name.clone(),
+            time: report.time.as_nanos_since_unix_epoch(),
+        })
+        .collect();
+    Ok(CanisterHttpDivergencesResult { divergences }.encode())
+}
+
 fn get_canister_mut(
     canister_id: CanisterId,
     state: &mut ReplicatedState,
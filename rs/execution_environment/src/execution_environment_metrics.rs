@@ -1,14 +1,20 @@
 use ic_metrics::buckets::decimal_buckets;
 use ic_metrics::{MetricsRegistry, Timer};
 use ic_types::{ic00, user_error::UserError};
-use prometheus::HistogramVec;
+use prometheus::{HistogramVec, IntCounterVec};
 use std::str::FromStr;
 
 /// Metrics used to monitor the performance of the execution environment.
 pub(crate) struct ExecutionEnvironmentMetrics {
     subnet_messages: HistogramVec,
+    ecdsa_signature_requests: IntCounterVec,
 }
 
+/// The label of the ECDSA key ids that are not supported. The key id of a
+/// request is chosen by the calling canister, so it can not be used as a label
+/// value unless it is known.
+pub(crate) const UNKNOWN_ECDSA_KEY_ID_LABEL: &str = "unknown";
+
 impl ExecutionEnvironmentMetrics {
     pub fn new(metrics_registry: &MetricsRegistry) -> Self {
         Self {
@@ -22,9 +28,31 @@ impl ExecutionEnvironmentMetrics {
                 // The `outcome` label is deprecated and should be replaced by `status` eventually.
                 &["method_name", "outcome", "status"],
             ),
+            ecdsa_signature_requests: metrics_registry.int_counter_vec(
+                "execution_ecdsa_signature_requests_total",
+                "Total number of sign_with_ecdsa requests received, by ECDSA key id and outcome.",
+                &["key_id", "outcome"],
+            ),
         }
     }
 
+    /// Counts a `sign_with_ecdsa` request for the ECDSA key labeled `key_id`.
+    /// The outcome is `accepted` if the request is passed on to consensus,
+    /// and the reason of the rejection otherwise.
+    pub fn observe_ecdsa_signature_request(&self, key_id: &str, outcome: &str) {
+        self.ecdsa_signature_requests
+            .with_label_values(&[key_id, outcome])
+            .inc();
+    }
+
+    /// Counts `count` message hashes of a `sign_with_ecdsa_batch` request
+    /// with the same outcome, each like a `sign_with_ecdsa` request.
+    pub fn observe_ecdsa_signature_requests(&self, key_id: &str, outcome: &str, count: usize) {
+        self.ecdsa_signature_requests
+            .with_label_values(&[key_id, outcome])
+            .inc_by(count as u64);
+    }
+
     /// Observe the duration and count of subnet messages.
     ///
     /// The observation is divided by the name of the method as well as by the
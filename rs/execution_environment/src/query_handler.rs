@@ -7,11 +7,15 @@ mod query_context;
 mod tests;
 
 use crate::{
+    execution_environment::get_http_request_divergences,
     hypervisor::Hypervisor,
     metrics::{MeasurementScope, QueryHandlerMetrics},
 };
 use ic_config::execution_environment::Config;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
+use ic_ic00_types::{
+    CanisterHttpResponsePayload, CanisterIdRecord, HttpHeader, Method as Ic00Method, Payload,
+};
 use ic_interfaces::{
     execution_environment::{QueryExecutionService, QueryHandler, SubnetAvailableMemory},
     state_manager::StateReader,
@@ -21,20 +25,25 @@ use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
+    canister_http::{
+        CanisterHttpHeader, CanisterHttpReply, CanisterHttpRequestContext,
+        CanisterHttpTransformError,
+    },
     ingress::WasmResult,
     messages::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
     user_error::{ErrorCode, RejectCode, UserError},
-    CanisterId, NumInstructions, SubnetId,
+    CanisterId, NumInstructions, PrincipalId, SubnetId, UserId,
 };
 use query_allocations::QueryAllocationsUsed;
 use serde::Serialize;
 use std::{
-    convert::Infallible,
+    convert::{Infallible, TryFrom},
     future::Future,
     pin::Pin,
+    str::FromStr,
     sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
 };
@@ -127,14 +136,15 @@ impl InternalHttpQueryHandler {
     }
 }
 
-impl QueryHandler for InternalHttpQueryHandler {
-    type State = ReplicatedState;
-
-    fn query(
+impl InternalHttpQueryHandler {
+    /// Executes `query` against `state`, allowing each message of the query
+    /// to execute at most `max_instructions_per_message` instructions.
+    fn execute_query(
         &self,
         query: UserQuery,
         state: Arc<ReplicatedState>,
         data_certificate: Vec<u8>,
+        max_instructions_per_message: NumInstructions,
     ) -> Result<WasmResult, UserError> {
         let measurement_scope = MeasurementScope::root(&self.metrics.query);
         // Note that This assumes that the QueryHandler is always called with the
@@ -163,10 +173,127 @@ impl QueryHandler for InternalHttpQueryHandler {
             self.query_allocations_used.clone(),
             subnet_available_memory,
             max_canister_memory_size,
-            self.max_instructions_per_message,
+            max_instructions_per_message,
         );
         context.run(query, &self.metrics, &measurement_scope)
     }
+
+    /// Answers the methods of the management canister that only read the
+    /// state and can therefore be called as queries.
+    fn query_management_canister(
+        &self,
+        query: &UserQuery,
+        state: &ReplicatedState,
+    ) -> Result<WasmResult, UserError> {
+        match Ic00Method::from_str(&query.method_name) {
+            Ok(Ic00Method::HttpRequestDivergences) => {
+                let args = CanisterIdRecord::decode(&query.method_payload)?;
+                get_http_request_divergences(query.source.get(), args.get_canister_id(), state)
+                    .map(WasmResult::Reply)
+            }
+            _ => Err(UserError::new(
+                ErrorCode::CanisterMethodNotFound,
+                format!(
+                    "Management canister has no query method '{}'",
+                    query.method_name
+                ),
+            )),
+        }
+    }
+}
+
+impl QueryHandler for InternalHttpQueryHandler {
+    type State = ReplicatedState;
+
+    fn query(
+        &self,
+        query: UserQuery,
+        state: Arc<ReplicatedState>,
+        data_certificate: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        if query.receiver == CanisterId::ic_00() {
+            return self.query_management_canister(&query, &state);
+        }
+        self.execute_query(
+            query,
+            state,
+            data_certificate,
+            self.max_instructions_per_message,
+        )
+    }
+
+    fn transform_canister_http_reply(
+        &self,
+        context: &CanisterHttpRequestContext,
+        reply: CanisterHttpReply,
+        state: Arc<ReplicatedState>,
+    ) -> Result<CanisterHttpReply, CanisterHttpTransformError> {
+        let method_name = match &context.transform_method_name {
+            Some(method_name) => method_name.clone(),
+            None => return Ok(reply),
+        };
+        let limits = &state.metadata.own_subnet_canister_http_limits;
+        let max_instructions = limits
+            .max_transform_instructions
+            .min(self.max_instructions_per_message);
+
+        let payload = CanisterHttpResponsePayload {
+            status: reply.status as u64,
+            headers: reply
+                .headers
+                .iter()
+                .map(|header| HttpHeader {
+                    name: header.name.clone(),
+                    value: String::from_utf8_lossy(&header.value).into_owned(),
+                })
+                .collect(),
+            body: reply.body.clone(),
+        };
+        let query = UserQuery {
+            source: UserId::from(PrincipalId::new_anonymous()),
+            receiver: context.request.sender,
+            method_name,
+            method_payload: payload.encode(),
+            ingress_expiry: 0,
+            nonce: None,
+        };
+
+        // Only the instruction budget is enforced: it bounds how long the
+        // transform can take, and unlike the wall clock it is the same on all
+        // replicas.
+        match self.execute_query(query, state, vec![], max_instructions) {
+            Ok(WasmResult::Reply(bytes)) => {
+                let transformed = CanisterHttpResponsePayload::decode(&bytes)
+                    .map_err(|err| CanisterHttpTransformError::InvalidReply(err.to_string()))?;
+                let status = u32::try_from(transformed.status).map_err(|_| {
+                    CanisterHttpTransformError::InvalidReply(format!(
+                        "Invalid status code {}",
+                        transformed.status
+                    ))
+                })?;
+                Ok(CanisterHttpReply {
+                    id: reply.id,
+                    status,
+                    headers: transformed
+                        .headers
+                        .into_iter()
+                        .map(|header| CanisterHttpHeader {
+                            name: header.name,
+                            value: header.value.into_bytes(),
+                        })
+                        .collect(),
+                    body: transformed.body,
+                })
+            }
+            Ok(WasmResult::Reject(message)) => Err(CanisterHttpTransformError::Rejected(message)),
+            Err(err) if err.code() == ErrorCode::CanisterCyclesLimitExceeded => {
+                Err(CanisterHttpTransformError::InstructionLimitExceeded {
+                    limit: max_instructions,
+                })
+            }
+            Err(err) => Err(CanisterHttpTransformError::Rejected(err.to_string())),
+        }
+    }
 }
 
 impl HttpQueryHandler {
@@ -204,6 +331,16 @@ impl QueryHandler for HttpQueryHandler {
     ) -> Result<WasmResult, UserError> {
         self.internal.query(query, state, data_certificate)
     }
+
+    fn transform_canister_http_reply(
+        &self,
+        context: &CanisterHttpRequestContext,
+        reply: CanisterHttpReply,
+        state: Arc<Self::State>,
+    ) -> Result<CanisterHttpReply, CanisterHttpTransformError> {
+        self.internal
+            .transform_canister_http_reply(context, reply, state)
+    }
 }
 
 impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
@@ -4,8 +4,10 @@ use crate::{
     hypervisor::Hypervisor,
     IngressHistoryWriterImpl, InternalHttpQueryHandler,
 };
-use ic_base_types::NumSeconds;
+use assert_matches::assert_matches;
+use ic_base_types::{HttpMethodType, NumSeconds};
 use ic_config::execution_environment::Config;
+use ic_ic00_types::{CanisterHttpDivergencesResult, CanisterIdRecord, Method, Payload};
 use ic_interfaces::execution_environment::{
     ExecutionMode, ExecutionParameters, QueryHandler, SubnetAvailableMemory,
 };
@@ -15,15 +17,23 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
+    mock_time,
     types::{
         ids::{canister_test_id, subnet_test_id, user_test_id},
-        messages::InstallCodeContextBuilder,
+        messages::{InstallCodeContextBuilder, RequestBuilder},
     },
     universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM},
     with_test_replica_logger,
 };
 use ic_types::{
-    ingress::WasmResult, messages::UserQuery, user_error::ErrorCode, ComputeAllocation,
+    canister_http::{
+        CanisterHttpHeader, CanisterHttpReply, CanisterHttpRequestContext,
+        CanisterHttpRequestDivergence, CanisterHttpTransformError,
+    },
+    ingress::WasmResult,
+    messages::{CallbackId, UserQuery},
+    user_error::ErrorCode,
+    ComputeAllocation, UserId,
 };
 use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions, SubnetId};
 use maplit::btreemap;
@@ -102,6 +112,14 @@ where
 fn universal_canister(
     canister_manager: &CanisterManager,
     state: &mut ReplicatedState,
+) -> CanisterId {
+    install_canister(canister_manager, state, UNIVERSAL_CANISTER_WASM.to_vec())
+}
+
+fn install_canister(
+    canister_manager: &CanisterManager,
+    state: &mut ReplicatedState,
+    wasm_module: Vec<u8>,
 ) -> CanisterId {
     let sender = canister_test_id(1).get();
     let sender_subnet_id = subnet_test_id(1);
@@ -122,7 +140,7 @@ fn universal_canister(
             InstallCodeContextBuilder::default()
                 .sender(sender)
                 .canister_id(canister_id)
-                .wasm_module(UNIVERSAL_CANISTER_WASM.to_vec())
+                .wasm_module(wasm_module)
                 .build(),
             state,
             ExecutionParameters {
@@ -420,3 +438,180 @@ fn query_compilied_once() {
         },
     );
 }
+
+// A canister whose `transform` query echoes the response it is given and
+// whose `loop` query never terminates.
+const TRANSFORM_WAT: &str = r#"
+    (module
+      (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+      (import "ic0" "msg_arg_data_copy" (func $msg_arg_data_copy (param i32 i32 i32)))
+      (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i32 i32)))
+      (import "ic0" "msg_reply" (func $msg_reply))
+      (func $transform
+        (call $msg_arg_data_copy (i32.const 0) (i32.const 0) (call $msg_arg_data_size))
+        (call $msg_reply_data_append (i32.const 0) (call $msg_arg_data_size))
+        (call $msg_reply))
+      (func $loop
+        (loop $again (br $again)))
+      (memory 1)
+      (export "canister_query transform" (func $transform))
+      (export "canister_query loop" (func $loop)))"#;
+
+fn canister_http_request_context(
+    canister_id: CanisterId,
+    transform_method_name: Option<&str>,
+) -> CanisterHttpRequestContext {
+    CanisterHttpRequestContext {
+        request: RequestBuilder::new().sender(canister_id).build(),
+        url: "https://example.com".to_string(),
+        body: None,
+        http_method: HttpMethodType::GET,
+        transform_method_name: transform_method_name.map(|name| name.to_string()),
+        time: mock_time(),
+        timeout: mock_time(),
+        max_response_bytes: 0,
+        fee_per_response_byte: Cycles::zero(),
+    }
+}
+
+fn canister_http_reply() -> CanisterHttpReply {
+    CanisterHttpReply {
+        id: CallbackId::from(7),
+        status: 200,
+        headers: vec![CanisterHttpHeader {
+            name: "content-type".to_string(),
+            value: b"text/plain".to_vec(),
+        }],
+        body: b"hello".to_vec(),
+    }
+}
+
+#[test]
+fn canister_http_reply_is_transformed_by_the_canister() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = install_canister(
+                &canister_manager,
+                &mut state,
+                wabt::wat2wasm(TRANSFORM_WAT).unwrap(),
+            );
+            let transformed = query_handler.transform_canister_http_reply(
+                &canister_http_request_context(canister_id, Some("transform")),
+                canister_http_reply(),
+                Arc::new(state),
+            );
+            assert_eq!(transformed, Ok(canister_http_reply()));
+        },
+    );
+}
+
+#[test]
+fn canister_http_reply_without_transform_is_returned_unchanged() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = universal_canister(&canister_manager, &mut state);
+            let transformed = query_handler.transform_canister_http_reply(
+                &canister_http_request_context(canister_id, None),
+                canister_http_reply(),
+                Arc::new(state),
+            );
+            assert_eq!(transformed, Ok(canister_http_reply()));
+        },
+    );
+}
+
+#[test]
+fn canister_http_transform_is_stopped_at_the_instruction_budget_of_the_subnet() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = install_canister(
+                &canister_manager,
+                &mut state,
+                wabt::wat2wasm(TRANSFORM_WAT).unwrap(),
+            );
+            let limit = NumInstructions::from(1_000_000);
+            state
+                .metadata
+                .own_subnet_canister_http_limits
+                .max_transform_instructions = limit;
+            let transformed = query_handler.transform_canister_http_reply(
+                &canister_http_request_context(canister_id, Some("loop")),
+                canister_http_reply(),
+                Arc::new(state),
+            );
+            assert_eq!(
+                transformed,
+                Err(CanisterHttpTransformError::InstructionLimitExceeded { limit })
+            );
+        },
+    );
+}
+
+#[test]
+fn canister_http_transform_that_traps_is_rejected() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = install_canister(
+                &canister_manager,
+                &mut state,
+                wabt::wat2wasm(TRANSFORM_WAT).unwrap(),
+            );
+            let transformed = query_handler.transform_canister_http_reply(
+                &canister_http_request_context(canister_id, Some("missing")),
+                canister_http_reply(),
+                Arc::new(state),
+            );
+            assert_matches!(transformed, Err(CanisterHttpTransformError::Rejected(_)));
+        },
+    );
+}
+
+#[test]
+fn http_request_divergences_can_be_queried_by_the_controllers() {
+    with_setup(
+        SubnetType::Application,
+        |query_handler, canister_manager, mut state| {
+            let canister_id = universal_canister(&canister_manager, &mut state);
+            let manager = &mut state.metadata.subnet_call_context_manager;
+            let callback_id =
+                manager.push_http_request(canister_http_request_context(canister_id, None));
+            manager
+                .record_diverged_http_request(
+                    &CanisterHttpRequestDivergence::new(callback_id, vec![]),
+                    mock_time(),
+                )
+                .unwrap();
+            let state = Arc::new(state);
+            let query = |source: UserId| UserQuery {
+                source,
+                receiver: CanisterId::ic_00(),
+                method_name: Method::HttpRequestDivergences.to_string(),
+                method_payload: CanisterIdRecord::from(canister_id).encode(),
+                ingress_expiry: 0,
+                nonce: None,
+            };
+
+            // The canister was created by `canister_test_id(1)`.
+            let controller = UserId::from(canister_test_id(1).get());
+            let result = query_handler.query(query(controller), Arc::clone(&state), vec![]);
+            match result {
+                Ok(WasmResult::Reply(bytes)) => {
+                    let result = CanisterHttpDivergencesResult::decode(&bytes).unwrap();
+                    assert_eq!(result.divergences.len(), 1);
+                    assert_eq!(result.divergences[0].request_id, callback_id.get());
+                }
+                result => panic!("Unexpected result {:?}", result),
+            }
+
+            let result = query_handler.query(query(user_test_id(2)), state, vec![]);
+            assert_eq!(
+                result.unwrap_err().code(),
+                ErrorCode::CanisterInvalidController
+            );
+        },
+    );
+}
@@ -1503,10 +1503,12 @@ fn get_instructions_limit_for_subnet_message(
             | GetECDSAPublicKey
             | GetMockECDSAPublicKey
             | RawRand
+            | ReserveECDSAPreSignatures
             | SetController
             | HttpRequest
             | SetupInitialDKG
             | SignWithECDSA
+            | SignWithECDSABatch
             | SignWithMockECDSA
             | StartCanister
             | StopCanister
@@ -22,14 +22,14 @@ use ic_replicated_state::{
     canister_state::{ENFORCE_MESSAGE_MEMORY_USAGE, QUEUE_INDEX_NONE},
     testing::{CanisterQueuesTesting, ReplicatedStateTesting, SystemStateTesting},
     CallContextManager, CallOrigin, CanisterState, CanisterStatus, InputQueueType, ReplicatedState,
-    SchedulerState, SystemState,
+    SchedulerState, SubnetTopology, SystemState,
 };
 use ic_test_utilities::state::get_stopping_canister_on_nns;
 use ic_test_utilities::{
     crypto::mock_random_number_generator,
     cycles_account_manager::CyclesAccountManagerBuilder,
     history::MockIngressHistory,
-    metrics::{fetch_histogram_vec_count, metric_vec},
+    metrics::{fetch_histogram_vec_count, fetch_int_counter_vec, metric_vec},
     mock_time,
     state::{
         get_running_canister, get_running_canister_with_args, get_running_canister_with_balance,
@@ -44,18 +44,26 @@ use ic_test_utilities::{
     with_test_replica_logger,
 };
 use ic_types::{
+    canister_http::{
+        CanisterHttpLimits, CanisterHttpPricing, CanisterHttpRequestContext,
+        CanisterHttpRequestDivergence,
+    },
     canonical_error::{not_found_error, permission_denied_error},
     ic00,
     ic00::{
-        CanisterHttpRequestArgs, CanisterIdRecord, CanisterStatusResultV2, EmptyBlob,
-        InstallCodeArgs, Method, Payload as Ic00Payload, IC_00,
+        CanisterHttpDivergencesResult, CanisterHttpRequestArgs, CanisterIdRecord,
+        CanisterStatusResultV2, EmptyBlob, InstallCodeArgs, Method, Payload as Ic00Payload,
+        ReserveECDSAPreSignaturesArgs, SignWithECDSAArgs, SignWithECDSABatchArgs,
+        SignWithECDSABatchReply, SignWithECDSABatchResult, SignWithECDSAReply, IC_00,
+        MAX_SIGN_WITH_ECDSA_BATCH_SIZE,
     },
     ingress::{IngressStatus, WasmResult},
     messages::{
-        CallbackId, CanisterInstallMode, MessageId, Payload, RejectContext, RequestOrResponse,
-        Response, StopCanisterContext, MAX_RESPONSE_COUNT_BYTES,
+        CallbackId, CanisterInstallMode, MessageId, Payload, RejectContext, Request,
+        RequestOrResponse, Response, StopCanisterContext, MAX_RESPONSE_COUNT_BYTES,
     },
     methods::{Callback, WasmClosure},
+    nominal_cycles::NominalCycles,
     user_error::{ErrorCode, RejectCode, UserError},
     CanisterId, CanisterStatusType, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
     NumInstructions, PrincipalId, QueueIndex, RegistryVersion, SubnetId, Time,
@@ -1911,6 +1919,24 @@ fn get_execution_environment(
     sender_subnet_id: SubnetId,
     subnet_type: SubnetType,
     log: ReplicaLogger,
+) -> (ReplicatedState, ExecutionEnvironmentImpl) {
+    get_execution_environment_with_metrics(
+        nns_subnet_id,
+        own_subnet_id,
+        sender_subnet_id,
+        subnet_type,
+        log,
+        &MetricsRegistry::new(),
+    )
+}
+
+fn get_execution_environment_with_metrics(
+    nns_subnet_id: SubnetId,
+    own_subnet_id: SubnetId,
+    sender_subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    log: ReplicaLogger,
+    metrics_registry: &MetricsRegistry,
 ) -> (ReplicatedState, ExecutionEnvironmentImpl) {
     let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
 
@@ -1924,7 +1950,6 @@ fn get_execution_environment(
     state.metadata.network_topology.routing_table = routing_table;
     state.metadata.network_topology.nns_subnet_id = nns_subnet_id;
 
-    let metrics_registry = MetricsRegistry::new();
     let cycles_account_manager = Arc::new(
         CyclesAccountManagerBuilder::new()
             .with_subnet_type(subnet_type)
@@ -1932,20 +1957,20 @@ fn get_execution_environment(
     );
     let hypervisor = Hypervisor::new(
         execution_environment::Config::default(),
-        &metrics_registry,
+        metrics_registry,
         own_subnet_id,
         subnet_type,
         log.clone(),
         Arc::clone(&cycles_account_manager),
     );
     let hypervisor = Arc::new(hypervisor);
-    let ingress_history_writer = IngressHistoryWriterImpl::new(log.clone(), &metrics_registry);
+    let ingress_history_writer = IngressHistoryWriterImpl::new(log.clone(), metrics_registry);
     let ingress_history_writer = Arc::new(ingress_history_writer);
     let exec_env = ExecutionEnvironmentImpl::new(
         log,
         hypervisor,
         ingress_history_writer,
-        &metrics_registry,
+        metrics_registry,
         own_subnet_id,
         subnet_type,
         1,
@@ -3160,6 +3185,7 @@ fn execute_canister_http_request() {
             body: None,
             http_method: HttpMethodType::GET,
             transform_method_name: transform_method_name.clone(),
+            max_response_bytes: None,
         };
 
         // Create request to HTTP_REQUEST method.
@@ -3212,3 +3238,992 @@ fn execute_canister_http_request() {
         assert_eq!(http_request_context.request, request);
     });
 }
+
+fn execute_canister_http_request_with_limits(
+    url: &str,
+    limits: CanisterHttpLimits,
+) -> ReplicatedState {
+    execute_canister_http_request_with_payment(url, limits, Cycles::zero())
+}
+
+fn execute_canister_http_request_with_payment(
+    url: &str,
+    limits: CanisterHttpLimits,
+    payment: Cycles,
+) -> ReplicatedState {
+    execute_canister_http_request_and_response(url, limits, payment, None, None)
+}
+
+/// Executes a canister http request to `url` and, if a `response_payload` is
+/// given, the response to it.
+fn execute_canister_http_request_and_response(
+    url: &str,
+    limits: CanisterHttpLimits,
+    payment: Cycles,
+    max_response_bytes: Option<u64>,
+    response_payload: Option<Payload>,
+) -> ReplicatedState {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let (mut state, exec_env) = get_execution_environment(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+        );
+        state.metadata.own_subnet_canister_http_limits = limits;
+        state.put_canister_state(
+            CanisterStateBuilder::new()
+                .with_canister_id(canister_test_id(257))
+                .build(),
+        );
+
+        let mut state =
+            execute_canister_http_request_in(&exec_env, state, url, payment, max_response_bytes);
+
+        if let Some(response_payload) = response_payload {
+            let response = ResponseBuilder::new()
+                .originator(CanisterId::from(own_subnet_id))
+                .respondent(CanisterId::from(own_subnet_id))
+                .originator_reply_callback(CallbackId::from(0))
+                .response_payload(response_payload)
+                .build();
+            state = exec_env
+                .execute_subnet_message(
+                    CanisterInputMessage::Response(response),
+                    state,
+                    MAX_NUM_INSTRUCTIONS,
+                    &mut mock_random_number_generator(),
+                    &None,
+                    &ProvisionalWhitelist::Set(BTreeSet::new()),
+                    MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+                    MAX_NUMBER_OF_CANISTERS,
+                )
+                .0;
+        }
+        state
+    })
+}
+
+/// Executes `num_requests` canister http requests to `url` one after the
+/// other, without responding to any of them.
+fn execute_canister_http_requests(
+    url: &str,
+    limits: CanisterHttpLimits,
+    num_requests: usize,
+) -> ReplicatedState {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let (mut state, exec_env) = get_execution_environment(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+        );
+        state.metadata.own_subnet_canister_http_limits = limits;
+
+        for _ in 0..num_requests {
+            state = execute_canister_http_request_in(&exec_env, state, url, Cycles::zero(), None);
+        }
+        state
+    })
+}
+
+/// Pushes a canister http request to `url` to the subnet queues of `state`
+/// and executes it.
+fn execute_canister_http_request_in(
+    exec_env: &ExecutionEnvironmentImpl,
+    mut state: ReplicatedState,
+    url: &str,
+    payment: Cycles,
+    max_response_bytes: Option<u64>,
+) -> ReplicatedState {
+    let request_payload = CanisterHttpRequestArgs {
+        url: url.to_string(),
+        body: None,
+        http_method: HttpMethodType::GET,
+        transform_method_name: None,
+        max_response_bytes,
+    };
+    let request = RequestBuilder::new()
+        .sender(canister_test_id(257))
+        .receiver(IC_00)
+        .method_name(Method::HttpRequest)
+        .method_payload(Encode!(&request_payload).unwrap())
+        .payment(payment)
+        .build();
+    state
+        .subnet_queues_mut()
+        .push_input(
+            QUEUE_INDEX_NONE,
+            RequestOrResponse::Request(request),
+            InputQueueType::LocalSubnet,
+        )
+        .unwrap();
+
+    exec_env
+        .execute_subnet_message(
+            state.subnet_queues_mut().pop_input().unwrap(),
+            state,
+            MAX_NUM_INSTRUCTIONS,
+            &mut mock_random_number_generator(),
+            &None,
+            &ProvisionalWhitelist::Set(BTreeSet::new()),
+            MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+            MAX_NUMBER_OF_CANISTERS,
+        )
+        .0
+}
+
+#[test]
+fn canister_http_request_with_disallowed_url_scheme_is_rejected() {
+    let state = execute_canister_http_request_with_limits(
+        "http://example.com",
+        CanisterHttpLimits::default(),
+    );
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+
+    let state = execute_canister_http_request_with_limits(
+        "http://example.com",
+        CanisterHttpLimits {
+            allowed_url_schemes: vec!["https".to_string(), "http".to_string()],
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn canister_http_request_is_rejected_when_subnet_is_at_capacity() {
+    let state = execute_canister_http_request_with_limits(
+        "https://example.com",
+        CanisterHttpLimits {
+            max_concurrent_requests: 0,
+            ..Default::default()
+        },
+    );
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+
+    let state = execute_canister_http_requests(
+        "https://example.com",
+        CanisterHttpLimits {
+            max_concurrent_requests: 2,
+            ..Default::default()
+        },
+        3,
+    );
+    assert_eq!(
+        state
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .len(),
+        2
+    );
+}
+
+fn canister_http_limits_with_pricing() -> CanisterHttpLimits {
+    CanisterHttpLimits {
+        max_response_bytes: 1000,
+        pricing: Some(CanisterHttpPricing {
+            base_fee: Cycles::new(1_000_000),
+            fee_per_request_byte: Cycles::new(100),
+            fee_per_response_byte: Cycles::new(10),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn canister_http_request_is_charged_the_configured_fee() {
+    let limits = canister_http_limits_with_pricing();
+    let payment = Cycles::new(50_000_000);
+    let state = execute_canister_http_request_with_payment("https://example.com", limits, payment);
+
+    let contexts = &state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts;
+    assert_eq!(contexts.len(), 1);
+    let request = &contexts.values().next().unwrap().request;
+    let fee = Cycles::new(1_000_000)
+        + Cycles::new(100) * request.method_payload.len()
+        + Cycles::new(10) * 1000_u64;
+    assert_eq!(request.payment, payment - fee);
+    assert_eq!(
+        consumed_cycles_since_replica_started(&state, canister_test_id(257)),
+        NominalCycles::from_cycles(fee)
+    );
+}
+
+fn consumed_cycles_since_replica_started(
+    state: &ReplicatedState,
+    canister_id: CanisterId,
+) -> NominalCycles {
+    state
+        .canister_state(&canister_id)
+        .unwrap()
+        .system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started
+}
+
+#[test]
+fn canister_http_request_is_charged_for_the_requested_max_response_bytes() {
+    let limits = canister_http_limits_with_pricing();
+    let payment = Cycles::new(50_000_000);
+    let state = execute_canister_http_request_and_response(
+        "https://example.com",
+        limits,
+        payment,
+        Some(400),
+        None,
+    );
+
+    let contexts = &state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts;
+    assert_eq!(contexts.len(), 1);
+    let context = contexts.values().next().unwrap();
+    assert_eq!(context.max_response_bytes, 400);
+    assert_eq!(context.fee_per_response_byte, Cycles::new(10));
+    let fee = Cycles::new(1_000_000)
+        + Cycles::new(100) * context.request.method_payload.len()
+        + Cycles::new(10) * 400_u64;
+    assert_eq!(context.request.payment, payment - fee);
+}
+
+#[test]
+fn canister_http_request_with_max_response_bytes_above_limit_is_rejected() {
+    let limits = canister_http_limits_with_pricing();
+    let state = execute_canister_http_request_and_response(
+        "https://example.com",
+        limits,
+        Cycles::new(50_000_000),
+        Some(1001),
+        None,
+    );
+
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+}
+
+#[test]
+fn canister_http_response_refunds_the_unused_response_bytes() {
+    let limits = canister_http_limits_with_pricing();
+    let payment = Cycles::new(50_000_000);
+    let mut state = execute_canister_http_request_and_response(
+        "https://example.com",
+        limits,
+        payment,
+        Some(400),
+        Some(Payload::Data(vec![0; 150])),
+    );
+
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+    let response = match state
+        .subnet_queues_mut()
+        .pop_canister_output(&canister_test_id(257))
+    {
+        Some((_, RequestOrResponse::Response(response))) => response,
+        _ => panic!("No response found"),
+    };
+    let request_payload_size = Encode!(&CanisterHttpRequestArgs {
+        url: "https://example.com".to_string(),
+        body: None,
+        http_method: HttpMethodType::GET,
+        transform_method_name: None,
+        max_response_bytes: Some(400),
+    })
+    .unwrap()
+    .len();
+    // Only the 150 response bytes that were used are charged, and recorded
+    // as consumed.
+    let fee = Cycles::new(1_000_000)
+        + Cycles::new(100) * request_payload_size
+        + Cycles::new(10) * 150_u64;
+    assert_eq!(response.refund, payment - fee);
+    assert_eq!(
+        consumed_cycles_since_replica_started(&state, canister_test_id(257)),
+        NominalCycles::from_cycles(fee)
+    );
+}
+
+#[test]
+fn canister_http_response_exceeding_max_response_bytes_is_rejected() {
+    let limits = canister_http_limits_with_pricing();
+    let mut state = execute_canister_http_request_and_response(
+        "https://example.com",
+        limits,
+        Cycles::new(50_000_000),
+        Some(400),
+        Some(Payload::Data(vec![0; 401])),
+    );
+
+    let response = match state
+        .subnet_queues_mut()
+        .pop_canister_output(&canister_test_id(257))
+    {
+        Some((_, RequestOrResponse::Response(response))) => response,
+        _ => panic!("No response found"),
+    };
+    match response.response_payload {
+        Payload::Reject(context) => assert_eq!(context.code, RejectCode::SysFatal),
+        Payload::Data(_) => panic!("The oversized response was delivered"),
+    }
+}
+
+#[test]
+fn canister_http_request_with_insufficient_cycles_is_rejected() {
+    let limits = canister_http_limits_with_pricing();
+    let payment = Cycles::new(1_000_000);
+    let mut state =
+        execute_canister_http_request_with_payment("https://example.com", limits, payment);
+
+    assert!(state
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .is_empty());
+    let response = match state
+        .subnet_queues_mut()
+        .pop_canister_output(&canister_test_id(257))
+    {
+        Some((_, RequestOrResponse::Response(response))) => response,
+        _ => panic!("No response found"),
+    };
+    assert_eq!(response.refund, payment);
+    match response.response_payload {
+        Payload::Reject(context) => assert_eq!(
+            context.code,
+            UserError::new(ErrorCode::InsufficientCyclesInCall, "").reject_code()
+        ),
+        Payload::Data(_) => panic!("The request was not rejected"),
+    }
+    assert_eq!(
+        consumed_cycles_since_replica_started(&state, canister_test_id(257)),
+        NominalCycles::from_cycles(Cycles::zero())
+    );
+}
+
+#[test]
+fn canister_http_divergences_are_only_readable_by_the_canister_and_its_controllers() {
+    with_setup(SubnetType::Application, |exec_env, mut state, _, _, _| {
+        let canister_id = canister_test_id(0);
+        let controller = canister_test_id(1);
+        let other = canister_test_id(2);
+        let subnet_id = subnet_test_id(1);
+        state.put_canister_state(
+            CanisterStateBuilder::new()
+                .with_canister_id(canister_id)
+                .with_controller(controller)
+                .build(),
+        );
+        let manager = &mut state.metadata.subnet_call_context_manager;
+        let callback_id = manager.push_http_request(CanisterHttpRequestContext {
+            request: RequestBuilder::new()
+                .sender(canister_id)
+                .receiver(IC_00)
+                .method_name(Method::HttpRequest)
+                .build(),
+            url: "https://example.com".to_string(),
+            body: None,
+            http_method: HttpMethodType::GET,
+            transform_method_name: Some("transform".to_string()),
+            time: mock_time(),
+            timeout: mock_time(),
+            max_response_bytes: 0,
+            fee_per_response_byte: Cycles::zero(),
+        });
+        manager
+            .record_diverged_http_request(
+                &CanisterHttpRequestDivergence::new(callback_id, vec![]),
+                mock_time(),
+            )
+            .unwrap();
+
+        for sender in &[canister_id, controller, other] {
+            state
+                .subnet_queues_mut()
+                .push_input(
+                    QUEUE_INDEX_NONE,
+                    RequestOrResponse::Request(
+                        RequestBuilder::new()
+                            .sender(*sender)
+                            .receiver(CanisterId::from(subnet_id))
+                            .method_name(Method::HttpRequestDivergences)
+                            .method_payload(Encode!(&CanisterIdRecord::from(canister_id)).unwrap())
+                            .build(),
+                    ),
+                    InputQueueType::RemoteSubnet,
+                )
+                .unwrap();
+            state = exec_env
+                .execute_subnet_message(
+                    state.subnet_queues_mut().pop_input().unwrap(),
+                    state,
+                    MAX_NUM_INSTRUCTIONS,
+                    &mut mock_random_number_generator(),
+                    &None,
+                    &ProvisionalWhitelist::Set(BTreeSet::new()),
+                    MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+                    MAX_NUMBER_OF_CANISTERS,
+                )
+                .0;
+
+            let response = match state.subnet_queues_mut().pop_canister_output(sender) {
+                Some((_, RequestOrResponse::Response(response))) => response,
+                _ => panic!("No response found"),
+            };
+            match response.response_payload {
+                Payload::Data(payload) if sender != &other => {
+                    let result = CanisterHttpDivergencesResult::decode(&payload).unwrap();
+                    assert_eq!(result.divergences.len(), 1);
+                    assert_eq!(result.divergences[0].request_id, callback_id.get());
+                    assert_eq!(
+                        result.divergences[0].transform_method_name,
+                        Some("transform".to_string())
+                    );
+                }
+                Payload::Reject(reject) if sender == &other => {
+                    assert_eq!(reject.code, RejectCode::CanisterError);
+                }
+                payload => panic!("Unexpected payload {:?} for sender {}", payload, sender),
+            }
+        }
+    });
+}
+
+#[test]
+fn get_http_request_divergences_of_nonexisting_canister() {
+    test_request_nonexistent_canister(Method::HttpRequestDivergences);
+}
+
+#[test]
+fn sign_with_ecdsa_requests_are_counted_per_key_id() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let metrics_registry = MetricsRegistry::new();
+        let (mut state, exec_env) = get_execution_environment_with_metrics(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+            &metrics_registry,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+
+        for key_id in &["secp256k1", "some_other_key"] {
+            let request = RequestBuilder::new()
+                .sender(canister_test_id(1))
+                .receiver(IC_00)
+                .method_name(Method::SignWithECDSA)
+                .method_payload(
+                    Encode!(&SignWithECDSAArgs {
+                        message_hash: vec![0; 32],
+                        derivation_path: vec![],
+                        key_id: key_id.to_string(),
+                    })
+                    .unwrap(),
+                )
+                .payment(Cycles::new(10_000_000_000))
+                .build();
+            state
+                .subnet_queues_mut()
+                .push_input(
+                    QUEUE_INDEX_NONE,
+                    RequestOrResponse::Request(request),
+                    InputQueueType::LocalSubnet,
+                )
+                .unwrap();
+            state = exec_env
+                .execute_subnet_message(
+                    state.subnet_queues_mut().pop_input().unwrap(),
+                    state,
+                    MAX_NUM_INSTRUCTIONS,
+                    &mut mock_random_number_generator(),
+                    &None,
+                    &ProvisionalWhitelist::Set(BTreeSet::new()),
+                    MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+                    MAX_NUMBER_OF_CANISTERS,
+                )
+                .0;
+        }
+
+        assert_eq!(
+            state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts
+                .len(),
+            1
+        );
+        assert_eq!(
+            fetch_int_counter_vec(
+                &metrics_registry,
+                "execution_ecdsa_signature_requests_total"
+            ),
+            metric_vec(&[
+                (&[("key_id", "secp256k1"), ("outcome", "accepted")], 1),
+                (&[("key_id", "unknown"), ("outcome", "unknown_key_id")], 1),
+            ])
+        );
+    });
+}
+
+#[test]
+fn sign_with_ecdsa_is_rejected_on_subnets_not_allowed_to_sign_with_the_key() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let signing_subnet_id = subnet_test_id(3);
+        let metrics_registry = MetricsRegistry::new();
+        let (mut state, exec_env) = get_execution_environment_with_metrics(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+            &metrics_registry,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+
+        let sender = canister_test_id(1);
+        for signing_subnets in &[vec![signing_subnet_id], vec![own_subnet_id]] {
+            state.metadata.network_topology.ecdsa_signing_subnets = btreemap! {
+                "secp256k1".to_string() => signing_subnets.clone(),
+            };
+            let request = RequestBuilder::new()
+                .sender(sender)
+                .receiver(IC_00)
+                .method_name(Method::SignWithECDSA)
+                .method_payload(
+                    Encode!(&SignWithECDSAArgs {
+                        message_hash: vec![0; 32],
+                        derivation_path: vec![],
+                        key_id: "secp256k1".to_string(),
+                    })
+                    .unwrap(),
+                )
+                .payment(Cycles::new(10_000_000_000))
+                .build();
+            state
+                .subnet_queues_mut()
+                .push_input(
+                    QUEUE_INDEX_NONE,
+                    RequestOrResponse::Request(request),
+                    InputQueueType::LocalSubnet,
+                )
+                .unwrap();
+            state = exec_env
+                .execute_subnet_message(
+                    state.subnet_queues_mut().pop_input().unwrap(),
+                    state,
+                    MAX_NUM_INSTRUCTIONS,
+                    &mut mock_random_number_generator(),
+                    &None,
+                    &ProvisionalWhitelist::Set(BTreeSet::new()),
+                    MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+                    MAX_NUMBER_OF_CANISTERS,
+                )
+                .0;
+        }
+
+        // Only the request on the permitted subnet created a signing context.
+        assert_eq!(
+            state
+                .metadata
+                .subnet_call_context_manager
+                .sign_with_ecdsa_contexts
+                .len(),
+            1
+        );
+        let response = match state.subnet_queues_mut().pop_canister_output(&sender) {
+            Some((_, RequestOrResponse::Response(response))) => response,
+            _ => panic!("No response found"),
+        };
+        match response.response_payload {
+            Payload::Reject(reject) => {
+                assert_eq!(reject.code, RejectCode::CanisterReject);
+                assert!(reject.message.contains("is not allowed to sign"));
+            }
+            payload => panic!("Unexpected payload {:?}", payload),
+        }
+        assert_eq!(
+            fetch_int_counter_vec(
+                &metrics_registry,
+                "execution_ecdsa_signature_requests_total"
+            ),
+            metric_vec(&[
+                (&[("key_id", "secp256k1"), ("outcome", "accepted")], 1),
+                (
+                    &[
+                        ("key_id", "secp256k1"),
+                        ("outcome", "signing_not_permitted")
+                    ],
+                    1
+                ),
+            ])
+        );
+    });
+}
+
+fn sign_with_ecdsa_batch_request(message_hashes: Vec<Vec<u8>>, payment: Cycles) -> Request {
+    RequestBuilder::new()
+        .sender(canister_test_id(1))
+        .receiver(IC_00)
+        .method_name(Method::SignWithECDSABatch)
+        .method_payload(
+            Encode!(&SignWithECDSABatchArgs {
+                message_hashes,
+                derivation_path: vec![vec![1, 2, 3]],
+                key_id: "secp256k1".to_string(),
+            })
+            .unwrap(),
+        )
+        .payment(payment)
+        .build()
+}
+
+fn execute_subnet_input(
+    exec_env: &ExecutionEnvironmentImpl,
+    mut state: ReplicatedState,
+    msg: CanisterInputMessage,
+) -> ReplicatedState {
+    if let CanisterInputMessage::Request(request) = msg {
+        state
+            .subnet_queues_mut()
+            .push_input(
+                QUEUE_INDEX_NONE,
+                RequestOrResponse::Request(request),
+                InputQueueType::LocalSubnet,
+            )
+            .unwrap();
+        let msg = state.subnet_queues_mut().pop_input().unwrap();
+        return execute_subnet_input(exec_env, state, msg);
+    }
+    exec_env
+        .execute_subnet_message(
+            msg,
+            state,
+            MAX_NUM_INSTRUCTIONS,
+            &mut mock_random_number_generator(),
+            &None,
+            &ProvisionalWhitelist::Set(BTreeSet::new()),
+            MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+            MAX_NUMBER_OF_CANISTERS,
+        )
+        .0
+}
+
+#[test]
+fn sign_with_ecdsa_batch_is_replied_to_once_all_message_hashes_are_signed() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let metrics_registry = MetricsRegistry::new();
+        let (mut state, exec_env) = get_execution_environment_with_metrics(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+            &metrics_registry,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+        state.put_canister_state(
+            CanisterStateBuilder::new()
+                .with_canister_id(canister_test_id(1))
+                .build(),
+        );
+        let payment = Cycles::new(100_000_000_000);
+        let request =
+            sign_with_ecdsa_batch_request(vec![vec![0; 32], vec![0; 31], vec![1; 32]], payment);
+
+        let mut state =
+            execute_subnet_input(&exec_env, state, CanisterInputMessage::Request(request));
+
+        // Only the two valid message hashes are passed on to be signed.
+        let manager = &state.metadata.subnet_call_context_manager;
+        let callback_ids: Vec<CallbackId> =
+            manager.sign_with_ecdsa_contexts.keys().cloned().collect();
+        assert_eq!(callback_ids.len(), 2);
+        assert_eq!(manager.sign_with_ecdsa_batch_contexts.len(), 1);
+        for context in manager.sign_with_ecdsa_contexts.values() {
+            assert_eq!(context.derivation_path, vec![vec![1, 2, 3]]);
+        }
+
+        let responses = vec![
+            Payload::Data(
+                SignWithECDSAReply {
+                    signature: vec![7; 64],
+                }
+                .encode(),
+            ),
+            Payload::Reject(RejectContext {
+                code: RejectCode::CanisterReject,
+                message: "signing failed".to_string(),
+            }),
+        ];
+        for (callback_id, response_payload) in callback_ids.into_iter().zip(responses) {
+            assert!(state
+                .subnet_queues_mut()
+                .pop_canister_output(&canister_test_id(1))
+                .is_none());
+            let response = ResponseBuilder::new()
+                .originator(CanisterId::from(own_subnet_id))
+                .respondent(CanisterId::from(own_subnet_id))
+                .originator_reply_callback(callback_id)
+                .response_payload(response_payload)
+                .build();
+            state =
+                execute_subnet_input(&exec_env, state, CanisterInputMessage::Response(response));
+        }
+
+        let manager = &state.metadata.subnet_call_context_manager;
+        assert!(manager.sign_with_ecdsa_contexts.is_empty());
+        assert!(manager.sign_with_ecdsa_batch_contexts.is_empty());
+        let response = match state
+            .subnet_queues_mut()
+            .pop_canister_output(&canister_test_id(1))
+        {
+            Some((_, RequestOrResponse::Response(response))) => response,
+            _ => panic!("No response found"),
+        };
+        // The signature fee is only charged for the valid message hashes, and
+        // recorded as consumed by the sender.
+        let fee = Cycles::new(10_000_000_000) * 2_u64;
+        assert_eq!(response.refund, payment - fee);
+        assert_eq!(
+            state
+                .canister_state(&canister_test_id(1))
+                .unwrap()
+                .system_state
+                .canister_metrics
+                .consumed_cycles_since_replica_started,
+            NominalCycles::from_cycles(fee)
+        );
+        let reply = match response.response_payload {
+            Payload::Data(data) => SignWithECDSABatchReply::decode(&data).unwrap(),
+            payload => panic!("Unexpected payload {:?}", payload),
+        };
+        assert_eq!(
+            reply.results,
+            vec![
+                SignWithECDSABatchResult::Signature(vec![7; 64]),
+                SignWithECDSABatchResult::Error("message_hash must be 32 bytes".to_string()),
+                SignWithECDSABatchResult::Error("signing failed".to_string()),
+            ]
+        );
+        assert_eq!(
+            fetch_int_counter_vec(
+                &metrics_registry,
+                "execution_ecdsa_signature_requests_total"
+            ),
+            metric_vec(&[
+                (&[("key_id", "secp256k1"), ("outcome", "accepted")], 2),
+                (
+                    &[("key_id", "secp256k1"), ("outcome", "invalid_message_hash")],
+                    1
+                ),
+            ])
+        );
+    });
+}
+
+#[test]
+fn sign_with_ecdsa_batch_is_rejected_if_too_large_or_underpaid() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let (mut state, exec_env) = get_execution_environment(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+        let requests = vec![
+            sign_with_ecdsa_batch_request(
+                vec![vec![0; 32]; MAX_SIGN_WITH_ECDSA_BATCH_SIZE + 1],
+                Cycles::new(1_000_000_000_000),
+            ),
+            sign_with_ecdsa_batch_request(vec![], Cycles::new(1_000_000_000_000)),
+            // Enough for one signature, but not for two.
+            sign_with_ecdsa_batch_request(vec![vec![0; 32]; 2], Cycles::new(11_000_000_000)),
+        ];
+
+        for request in requests {
+            let payment = request.payment;
+            state = execute_subnet_input(&exec_env, state, CanisterInputMessage::Request(request));
+            let response = match state
+                .subnet_queues_mut()
+                .pop_canister_output(&canister_test_id(1))
+            {
+                Some((_, RequestOrResponse::Response(response))) => response,
+                _ => panic!("No response found"),
+            };
+            assert_eq!(response.refund, payment);
+            assert_matches!(response.response_payload, Payload::Reject(_));
+        }
+        let manager = &state.metadata.subnet_call_context_manager;
+        assert!(manager.sign_with_ecdsa_contexts.is_empty());
+        assert!(manager.sign_with_ecdsa_batch_contexts.is_empty());
+    });
+}
+
+#[test]
+fn sign_with_ecdsa_with_insufficient_cycles_is_rejected_with_insufficient_cycles_in_call() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let (mut state, exec_env) = get_execution_environment(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+        let payment = Cycles::new(1_000_000);
+        let request = sign_with_ecdsa_batch_request(vec![vec![0; 32]], payment);
+
+        let mut state =
+            execute_subnet_input(&exec_env, state, CanisterInputMessage::Request(request));
+
+        assert!(state
+            .metadata
+            .subnet_call_context_manager
+            .sign_with_ecdsa_contexts
+            .is_empty());
+        let response = match state
+            .subnet_queues_mut()
+            .pop_canister_output(&canister_test_id(1))
+        {
+            Some((_, RequestOrResponse::Response(response))) => response,
+            _ => panic!("No response found"),
+        };
+        assert_eq!(response.refund, payment);
+        match response.response_payload {
+            Payload::Reject(context) => assert_eq!(
+                context.code,
+                UserError::new(ErrorCode::InsufficientCyclesInCall, "").reject_code()
+            ),
+            Payload::Data(_) => panic!("The request was not rejected"),
+        }
+    });
+}
+
+fn reserve_ecdsa_pre_signatures_request(
+    sender: CanisterId,
+    pre_signatures: u32,
+    payment: Cycles,
+) -> Request {
+    RequestBuilder::new()
+        .sender(sender)
+        .receiver(IC_00)
+        .method_name(Method::ReserveECDSAPreSignatures)
+        .method_payload(
+            Encode!(&ReserveECDSAPreSignaturesArgs {
+                key_id: "secp256k1".to_string(),
+                pre_signatures,
+            })
+            .unwrap(),
+        )
+        .payment(payment)
+        .build()
+}
+
+#[test]
+fn reserve_ecdsa_pre_signatures_charges_new_reservations_up_to_the_subnet_limit() {
+    with_test_replica_logger(|log| {
+        let own_subnet_id = subnet_test_id(1);
+        let (mut state, exec_env) = get_execution_environment(
+            subnet_test_id(2),
+            own_subnet_id,
+            own_subnet_id,
+            SubnetType::Application,
+            log,
+        );
+        state.metadata.own_subnet_features.ecdsa_signatures = true;
+        state.metadata.network_topology.subnets.insert(
+            own_subnet_id,
+            SubnetTopology {
+                max_reserved_ecdsa_quadruples: 3,
+                ..SubnetTopology::default()
+            },
+        );
+        let reservation_fee = Cycles::new(50_000_000_000);
+        let (first, second) = (canister_test_id(1), canister_test_id(2));
+        // (sender, pre-signatures, payment, expected refund, accepted)
+        let requests = vec![
+            // Two new pre-signatures are charged for.
+            (first, 2, reservation_fee * 3_u64, reservation_fee, true),
+            // Together with the reservation of the first canister, the limit
+            // of the subnet would be exceeded.
+            (
+                second,
+                2,
+                reservation_fee * 3_u64,
+                reservation_fee * 3_u64,
+                false,
+            ),
+            // Shrinking a reservation is free.
+            (first, 1, Cycles::new(10), Cycles::new(10), true),
+            // Two new pre-signatures are not covered by the payment.
+            (second, 2, reservation_fee, reservation_fee, false),
+        ];
+
+        for (sender, pre_signatures, payment, refund, accepted) in requests {
+            let request = reserve_ecdsa_pre_signatures_request(sender, pre_signatures, payment);
+            state = execute_subnet_input(&exec_env, state, CanisterInputMessage::Request(request));
+            let response = match state.subnet_queues_mut().pop_canister_output(&sender) {
+                Some((_, RequestOrResponse::Response(response))) => response,
+                _ => panic!("No response found"),
+            };
+            assert_eq!(response.refund, refund);
+            if accepted {
+                assert_eq!(
+                    response.response_payload,
+                    Payload::Data(EmptyBlob::encode())
+                );
+            } else {
+                assert_matches!(response.response_payload, Payload::Reject(_));
+            }
+        }
+
+        let manager = &state.metadata.subnet_call_context_manager;
+        let now = state.metadata.batch_time;
+        assert_eq!(
+            manager.ecdsa_quadruple_reservation("secp256k1", &first, now),
+            1
+        );
+        assert_eq!(
+            manager.ecdsa_quadruple_reservation("secp256k1", &second, now),
+            0
+        );
+    });
+}
@@ -93,6 +93,7 @@ impl Service<Body> for StatusService {
             impl_version: Some(ReplicaVersion::default().to_string()),
             impl_hash: REPLICA_BINARY_HASH.get().map(|s| s.to_string()),
             replica_health_status: Some(self.replica_health_status.read().unwrap().clone()),
+            certified_height: Some(self.state_reader.latest_certified_height()),
         };
         Box::pin(async move { Ok(common::cbor_response(&response)) })
     }
@@ -28,6 +28,7 @@ pub struct InternetComputer {
     pub experimental_vm_test: bool,
     pub unassigned_nodes: Vec<Node>,
     pub ssh_readonly_access_to_unassigned_nodes: Vec<String>,
+    pub ecdsa_signing_subnets: BTreeMap<String, Vec<u64>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -87,6 +88,14 @@ impl InternetComputer {
         self
     }
 
+    /// Restricts signing with the threshold ECDSA key `key_id` to the subnets
+    /// with the given indices, in the order in which they were added.
+    pub fn with_ecdsa_signing_subnets(mut self, key_id: &str, subnet_indices: Vec<u64>) -> Self {
+        self.ecdsa_signing_subnets
+            .insert(key_id.to_string(), subnet_indices);
+        self
+    }
+
     pub fn summarize(&self) -> String {
         // XXX: The prefix of this name exposes information to fondue about the
         // environment in which the pot is to be run. Strictly speaking, this
@@ -9,7 +9,7 @@ use crate::prod_tests::{cli::AuthorizedSshAccount, farm};
 use anyhow::Result;
 use ic_prep_lib::prep_state_directory::IcPrepStateDir;
 use ic_registry_subnet_type::SubnetType;
-use ic_types::messages::{HttpStatusResponse, ReplicaHealthStatus};
+use ic_replica_status::{fetch_status, ReplicaStatus};
 use ic_types::SubnetId;
 use slog::info;
 use slog::Logger;
@@ -253,32 +253,8 @@ impl<'a> IcHandle {
 
 impl<'a> IcEndpoint {
     /// Returns the status of a replica. It is requested from a public API.
-    pub async fn status(&self) -> Result<HttpStatusResponse> {
-        let response = reqwest::Client::builder()
-            .timeout(READY_RESPONSE_TIMEOUT)
-            .build()
-            .expect("cannot build a reqwest client")
-            .get(
-                self.url
-                    .clone()
-                    .join("api/v2/status")
-                    .expect("failed to join URLs"),
-            )
-            .send()
-            .await?;
-
-        let cbor_response = serde_cbor::from_slice(
-            &response
-                .bytes()
-                .await
-                .expect("failed to convert a response to bytes")
-                .to_vec(),
-        )
-        .expect("response is not encoded as cbor");
-        let status = serde_cbor::value::from_value::<HttpStatusResponse>(cbor_response)
-            .expect("failed to deserialize a response to HttpStatusResponse");
-
-        Ok(status)
+    pub async fn status(&self) -> Result<ReplicaStatus> {
+        Ok(fetch_status(&self.url, READY_RESPONSE_TIMEOUT).await?)
     }
 
     /// Returns true if [IcEndpoint] is healthy, i.e. up and running and ready
@@ -287,9 +263,8 @@ impl<'a> IcEndpoint {
     pub async fn healthy(&self) -> Result<(bool, Option<Vec<u8>>)> {
         //        pub async fn healthy(&self) -> Result<bool> {
         let status = self.status().await?;
-        //Ok(Some(ReplicaHealthStatus::Healthy) == status.replica_health_status)
+        let is_healthy = status.is_healthy();
         let root_key = status.root_key.map(|x| x.0);
-        let is_healthy = Some(ReplicaHealthStatus::Healthy) == status.replica_health_status;
         Ok((is_healthy, root_key))
     }
 
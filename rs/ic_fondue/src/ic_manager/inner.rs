@@ -339,7 +339,7 @@ impl IcManager {
         let nns_subnet_idx = Some(0);
 
         let whitelist = ProvisionalWhitelist::All;
-        let ic_config = IcConfig::new(
+        let mut ic_config = IcConfig::new(
             working_dir.as_path(),
             ic_topology,
             Some(initial_replica.replica_version),
@@ -360,6 +360,10 @@ impl IcManager {
             ic.ssh_readonly_access_to_unassigned_nodes.clone(),
         );
 
+        for (key_id, subnet_indices) in &ic.ecdsa_signing_subnets {
+            ic_config.set_ecdsa_signing_subnets(key_id, subnet_indices.clone());
+        }
+
         debug!(logger, "ic_config.initialize");
         let init_ic = ic_config.initialize().expect("can't fail");
 
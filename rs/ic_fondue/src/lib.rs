@@ -4,6 +4,7 @@ pub mod iterator;
 pub mod pot;
 pub mod prod_tests;
 pub mod result;
+pub mod triage;
 
 use signal_hook::consts::TERM_SIGNALS;
 use signal_hook::flag;
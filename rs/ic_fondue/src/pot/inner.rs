@@ -25,7 +25,7 @@ use nix::unistd::Pid;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
+use std::panic::{catch_unwind, AssertUnwindSafe, RefUnwindSafe, UnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::{path::PathBuf, thread};
 
@@ -45,6 +45,7 @@ pub struct Context {
     pub rng: ChaCha8Rng,
     pub logger: Logger,
     pub is_nns_installed: Arc<Mutex<bool>>,
+    steps: Arc<Mutex<Vec<TestResultNode>>>,
 }
 
 #[allow(clippy::mutex_atomic)]
@@ -54,8 +55,40 @@ impl Context {
             rng,
             logger,
             is_nns_installed: Arc::new(Mutex::new(false)),
+            steps: Arc::new(Mutex::new(vec![])),
         }
     }
+
+    /// Runs `f` as a named step of the current test and records its duration
+    /// and result, so that they show up in the test results. A panic in `f`
+    /// marks the step as failed and is propagated, failing the test.
+    pub fn step<T>(&self, name: &str, f: impl FnOnce() -> T) -> T {
+        let started_at = std::time::Instant::now();
+        info!(self.logger, "Starting step: {}", name);
+        let res = catch_unwind(AssertUnwindSafe(f));
+        let (result, message) = match &res {
+            Ok(_) => (TestResult::Passed, None),
+            Err(panic_res) => (TestResult::Failed, Some(panic_message(panic_res.as_ref()))),
+        };
+        self.steps.lock().unwrap().push(TestResultNode {
+            name: name.to_string(),
+            started_at,
+            duration: started_at.elapsed(),
+            result,
+            message,
+            ..TestResultNode::default()
+        });
+        match res {
+            Ok(v) => v,
+            Err(panic_res) => std::panic::resume_unwind(panic_res),
+        }
+    }
+
+    /// Returns the results of the steps run so far, in the order in which
+    /// they were run, and forgets them.
+    pub fn take_steps(&self) -> Vec<TestResultNode> {
+        std::mem::take(&mut *self.steps.lock().unwrap())
+    }
 }
 
 /// An [Pot] has an associated environment configuration
@@ -452,7 +485,7 @@ pub fn run_isolated_test(man: &IcManager, ctx: &Context, test: IsolatedTest) ->
         started_at,
         duration,
         result,
-        children: vec![],
+        ..TestResultNode::default()
     }
 }
 
@@ -481,7 +514,7 @@ pub fn run_composable_tests(
             started_at,
             duration,
             result,
-            children: vec![],
+            ..TestResultNode::default()
         });
     }
     results
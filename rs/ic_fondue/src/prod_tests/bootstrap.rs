@@ -147,7 +147,7 @@ pub fn init_ic<P: AsRef<Path>>(
     }
 
     let whitelist = ProvisionalWhitelist::All;
-    let ic_config = IcConfig::new(
+    let mut ic_config = IcConfig::new(
         working_dir.as_path(),
         ic_topology,
         Some(initial_replica.replica_version),
@@ -166,6 +166,10 @@ pub fn init_ic<P: AsRef<Path>>(
         ic.ssh_readonly_access_to_unassigned_nodes.clone(),
     );
 
+    for (key_id, subnet_indices) in &ic.ecdsa_signing_subnets {
+        ic_config.set_ecdsa_signing_subnets(key_id, subnet_indices.clone());
+    }
+
     let init_ic = ic_config.initialize().expect("can't fail");
 
     let malicious_nodes: MaliciousNodes = init_ic
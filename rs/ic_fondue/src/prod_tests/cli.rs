@@ -18,7 +18,7 @@ const RND_SEED_DEFAULT: u64 = 42;
 pub struct CliArgs {
     #[structopt(
         long = "log-base-dir",
-        about = "If set, specifies where to write demultiplexed test-specific logs."
+        about = "If set, specifies where to write demultiplexed test-specific logs, as well as JSON and JUnit XML reports of the test results."
     )]
     log_base_dir: Option<PathBuf>,
     #[structopt(
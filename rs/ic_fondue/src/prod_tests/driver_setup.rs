@@ -79,6 +79,7 @@ pub fn create_driver_context_from_cli(
         journalbeat_hosts: cli_args.journalbeat_hosts,
         log_debug_overrides: cli_args.log_debug_overrides,
         pot_timeout: cli_args.pot_timeout,
+        post_pot_checks: vec![],
     }
 }
 
@@ -111,11 +112,24 @@ pub fn tee_logger(ctx: &DriverContext, test_path: &pot_dsl::TestPath) -> Logger
     }
 }
 
+/// Returns the path of the log file of the test at `test_path`, if logs are
+/// written to files.
+pub fn test_log_file(ctx: &DriverContext, test_path: &pot_dsl::TestPath) -> Option<PathBuf> {
+    ctx.logs_base_dir
+        .as_ref()
+        .map(|base_dir| log_filepath(base_dir, test_path))
+}
+
 fn set_up_filepath(base_dir: &Path, test_path: &pot_dsl::TestPath) -> PathBuf {
+    let path = log_filepath(base_dir, test_path);
+    fs::create_dir_all(path.parent().expect("log file path has no parent")).unwrap();
+    path
+}
+
+fn log_filepath(base_dir: &Path, test_path: &pot_dsl::TestPath) -> PathBuf {
     let mut tp = test_path.clone();
     let filename = tp.pop();
     let mut path = tp.to_filepath(base_dir);
-    fs::create_dir_all(&path).unwrap();
     path.push(filename);
     path.set_extension("log");
     path
@@ -153,10 +167,20 @@ pub struct DriverContext {
     pub journalbeat_hosts: Vec<String>,
     pub log_debug_overrides: Vec<String>,
     pub pot_timeout: Duration,
+    /// The checks run after the tests of each pot, by name.
+    pub post_pot_checks: Vec<(String, Arc<dyn pot_dsl::PostPotCheckFn>)>,
 }
 
 impl DriverContext {
     pub fn logger(&self) -> slog::Logger {
         self.logger.clone()
     }
+
+    /// Adds a check that is run after the tests of each pot, and reported as
+    /// an additional test of the pot named `name`.
+    pub fn with_post_pot_check<F: pot_dsl::PostPotCheckFn>(mut self, name: &str, check: F) -> Self {
+        self.post_pot_checks
+            .push((name.to_string(), Arc::new(check)));
+        self
+    }
 }
@@ -1,13 +1,14 @@
-use std::{panic::catch_unwind, time::Instant};
+use std::{panic::catch_unwind, sync::Arc, time::Instant};
 
 use super::driver_setup::DriverContext;
-use super::pot_dsl::{ExecutionMode, Pot, Suite, Test, TestPath, TestSet};
+use super::pot_dsl::{t, ExecutionMode, Pot, Suite, Test, TestPath, TestSet};
 use crate::ic_instance::InternetComputer;
 use crate::ic_manager::IcHandle;
 use crate::pot::Context;
-use crate::prod_tests::driver_setup::tee_logger;
+use crate::prod_tests::driver_setup::{tee_logger, test_log_file};
 use crate::prod_tests::farm::GroupSpec;
 use crate::result::*;
+use crate::triage;
 use anyhow::{bail, Result};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use slog::{error, info, warn};
@@ -56,6 +57,7 @@ pub fn evaluate(ctx: &DriverContext, ts: Suite) -> TestResultNode {
         duration: started_at.elapsed(),
         result: infer_result(children.as_slice()),
         children,
+        ..TestResultNode::default()
     }
 }
 
@@ -71,6 +73,7 @@ fn evaluate_pot_and_propagate_result(
         TestResultNode {
             name,
             result: TestResult::Failed,
+            message: Some(format!("failed to execute pot: {}", e)),
             ..TestResultNode::default()
         }
     });
@@ -162,7 +165,27 @@ fn evaluate_pot_with_group(
     for jh in join_handles {
         jh.join().expect("waiting for thread failed!");
     }
-    let children = collect_n_children(receiver, tests_num);
+    let mut children = collect_n_children(receiver, tests_num);
+
+    // The post-pot checks only make sense if a test may have changed the
+    // Internet Computer.
+    if children.iter().any(|c| c.result != TestResult::Skipped) {
+        let (sender, receiver) = bounded(ctx.post_pot_checks.len());
+        for (name, check) in ctx.post_pot_checks.iter() {
+            let check = Arc::clone(check);
+            let test = t(name, move |ic_handle: IcHandle, test_ctx: &Context| {
+                check(ic_handle, test_ctx)
+            });
+            evaluate_test(
+                ctx,
+                ic_handle.clone(),
+                sender.clone(),
+                test,
+                pot_path.clone(),
+            );
+        }
+        children.extend(collect_n_children(receiver, ctx.post_pot_checks.len()));
+    }
 
     Ok(TestResultNode {
         name: pot.name.clone(),
@@ -171,6 +194,7 @@ fn evaluate_pot_with_group(
         duration: started_at.elapsed(),
         result: infer_result(children.as_slice()),
         children,
+        ..TestResultNode::default()
     })
 }
 
@@ -195,22 +219,24 @@ fn evaluate_test(
     let path = path.join(&t.name);
     let test_ctx = Context::new(ctx.rng.clone(), tee_logger(ctx, &path));
     info!(test_ctx.logger, "Starting test: {}", path);
+    triage::reset();
     let t_res = catch_unwind(|| (t.f)(ic_handle, &test_ctx));
     if let Err(panic_res) = t_res {
-        if let Some(s) = panic_res.downcast_ref::<String>() {
-            warn!(test_ctx.logger, "{} FAILED: {}", path, s);
-        } else if let Some(s) = panic_res.downcast_ref::<&str>() {
-            warn!(test_ctx.logger, "{} FAILED: {}", path, s);
-        } else {
-            warn!(test_ctx.logger, "{} FAILED (): {:?}", path, panic_res);
-        }
+        let message = panic_message(panic_res.as_ref());
+        warn!(test_ctx.logger, "{} FAILED: {}", path, message);
         result.result = TestResult::Failed;
+        result.message = Some(message);
+        result.triage = triage::take_records();
     } else {
         info!(test_ctx.logger, "{} SUCCESS.", path);
         result.result = TestResult::Passed;
     }
+    // Helpers whose failure the test tolerated are no reason to triage it.
+    triage::reset();
 
     result.duration = result.started_at.elapsed();
+    result.children = test_ctx.take_steps();
+    result.artifacts = test_log_file(ctx, &path).into_iter().collect();
     parent
         .send(result)
         .expect("failed to send result to parent node");
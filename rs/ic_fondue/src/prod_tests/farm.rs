@@ -134,6 +134,16 @@ impl Farm {
         Ok(())
     }
 
+    /// Returns the output that the VM `vm_name` wrote to its serial console so
+    /// far. As this does not depend on the network configuration of the VM,
+    /// it is also available when the VM fails to boot.
+    pub fn get_vm_console_output(&self, group_name: &str, vm_name: &str) -> FarmResult<String> {
+        let path = format!("group/{}/vm/{}/console/", group_name, vm_name);
+        let rb = self.get(&path).header("Accept", "text/plain");
+        let resp = self.retry_until_success(rb)?;
+        Ok(resp.text()?)
+    }
+
     pub fn delete_group(&self, group_name: &str) -> FarmResult<()> {
         let path = format!("group/{}", group_name);
         let rb = self.delete(&path);
@@ -141,6 +151,11 @@ impl Farm {
         Ok(())
     }
 
+    fn get(&self, path: &str) -> RequestBuilder {
+        let url = self.url_from_path(path);
+        self.client.get(url)
+    }
+
     fn post(&self, path: &str) -> RequestBuilder {
         let url = self.url_from_path(path);
         self.client.post(url)
@@ -5,5 +5,6 @@ pub mod evaluation;
 pub mod farm;
 pub mod ic_setup;
 pub mod pot_dsl;
+pub mod reporting;
 pub mod resource;
 pub mod test_setup;
@@ -1,7 +1,7 @@
 use std::fmt::Display;
-use std::panic::{catch_unwind, UnwindSafe};
+use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
 
-use crate::pot::FondueTestFn;
+use crate::pot::{Context, FondueTestFn};
 use crate::{ic_instance::InternetComputer, ic_manager::IcHandle};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -9,6 +9,12 @@ use std::time::Duration;
 pub trait PotConfigFn: FnOnce() -> InternetComputer + UnwindSafe + Send + Sync + 'static {}
 impl<T: FnOnce() -> InternetComputer + UnwindSafe + Send + Sync + 'static> PotConfigFn for T {}
 
+/// A check run against the Internet Computer of each pot once all its tests
+/// completed, e.g. to assert that the tests left the registry in a valid
+/// state. Unlike a test, it is shared by all pots of a suite.
+pub trait PostPotCheckFn: Fn(IcHandle, &Context) + RefUnwindSafe + Send + Sync + 'static {}
+impl<T: Fn(IcHandle, &Context) + RefUnwindSafe + Send + Sync + 'static> PostPotCheckFn for T {}
+
 pub fn suite(name: &str, pots: Vec<Pot>) -> Suite {
     let name = name.to_string();
     Suite { name, pots }
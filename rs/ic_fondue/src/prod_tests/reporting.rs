@@ -0,0 +1,310 @@
+//! Structured reporting of the results of a test suite.
+//!
+//! The result tree of a suite is written as JSON and as JUnit XML, so that CI
+//! systems can render the results of every pot, test and step without
+//! scraping the logs. In the JUnit report, every pot is a `<testsuite>` and
+//! every test a `<testcase>`. JUnit has no notion of steps, so the steps of a
+//! test are reported as additional test cases named `<test>::<step>`. The
+//! triage hints of a failed test are reported as properties of its test case.
+use crate::result::{TestResult, TestResultNode};
+use anyhow::Result;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// The name of the file the JSON report is written to.
+pub const JSON_REPORT_FILE: &str = "test-results.json";
+/// The name of the file the JUnit XML report is written to.
+pub const JUNIT_REPORT_FILE: &str = "junit.xml";
+
+/// Writes the JSON and the JUnit XML reports of the results of `suite` into
+/// the directory `dir`.
+pub fn write_reports(dir: &Path, suite: &TestResultNode) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let f = std::fs::File::create(dir.join(JSON_REPORT_FILE))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(f), suite)?;
+    std::fs::write(dir.join(JUNIT_REPORT_FILE), junit_xml(suite))?;
+    Ok(())
+}
+
+/// Renders the results of `suite` as a JUnit XML document.
+pub fn junit_xml(suite: &TestResultNode) -> String {
+    let mut out = String::new();
+    let counts = Counts::of(suite.children.iter().flat_map(test_cases));
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<testsuites name="{}" tests="{}" failures="{}" skipped="{}" time="{}">"#,
+        escape(&suite.name),
+        counts.tests,
+        counts.failures,
+        counts.skipped,
+        seconds(suite.duration)
+    )
+    .unwrap();
+    for pot in suite.children.iter() {
+        let classname = format!("{}::{}", suite.name, pot.name);
+        let cases: Vec<_> = test_cases(pot).collect();
+        let counts = Counts::of(cases.iter().cloned());
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}" time="{}">"#,
+            escape(&classname),
+            counts.tests,
+            counts.failures,
+            counts.skipped,
+            seconds(pot.duration)
+        )
+        .unwrap();
+        if let Some(group_name) = &pot.group_name {
+            writeln!(out, "    <properties>").unwrap();
+            writeln!(
+                out,
+                r#"      <property name="group_name" value="{}"/>"#,
+                escape(group_name)
+            )
+            .unwrap();
+            writeln!(out, "    </properties>").unwrap();
+        }
+        for (name, case) in cases {
+            write_test_case(&mut out, &classname, &name, case);
+        }
+        writeln!(out, "  </testsuite>").unwrap();
+    }
+    writeln!(out, "</testsuites>").unwrap();
+    out
+}
+
+/// Returns the tests of `pot` followed by their steps, together with the
+/// names under which they are reported. A pot that failed before any test was
+/// run, e.g. during setup, is reported as a single failed `setup` test case.
+fn test_cases(pot: &TestResultNode) -> impl Iterator<Item = (String, &TestResultNode)> {
+    let failed_setup = if pot.children.is_empty() && pot.result == TestResult::Failed {
+        Some(("setup".to_string(), pot))
+    } else {
+        None
+    };
+    failed_setup
+        .into_iter()
+        .chain(pot.children.iter().flat_map(|test| {
+            std::iter::once((test.name.clone(), test)).chain(
+                test.children
+                    .iter()
+                    .map(move |step| (format!("{}::{}", test.name, step.name), step)),
+            )
+        }))
+}
+
+fn write_test_case(out: &mut String, classname: &str, name: &str, node: &TestResultNode) {
+    writeln!(
+        out,
+        r#"    <testcase classname="{}" name="{}" time="{}">"#,
+        escape(classname),
+        escape(name),
+        seconds(node.duration)
+    )
+    .unwrap();
+    match node.result {
+        TestResult::Passed => (),
+        TestResult::Failed => {
+            let message = node.message.as_deref().unwrap_or("failed");
+            writeln!(
+                out,
+                r#"      <failure message="{}">{}</failure>"#,
+                escape(first_line(message)),
+                escape(message)
+            )
+            .unwrap();
+        }
+        TestResult::Skipped => writeln!(out, "      <skipped/>").unwrap(),
+    }
+    if !node.triage.is_empty() {
+        writeln!(out, "      <properties>").unwrap();
+        for record in node.triage.iter() {
+            let mut properties = vec![
+                ("triage.helper", record.helper.clone()),
+                ("triage.error", record.error.clone()),
+            ];
+            properties.extend(
+                record
+                    .hints
+                    .subsystems
+                    .iter()
+                    .map(|subsystem| ("triage.subsystem", subsystem.clone())),
+            );
+            properties.extend(
+                record
+                    .hints
+                    .owner
+                    .iter()
+                    .map(|owner| ("triage.owner", owner.clone())),
+            );
+            properties.extend(
+                record
+                    .hints
+                    .links
+                    .iter()
+                    .map(|link| ("triage.link", link.clone())),
+            );
+            for (name, value) in properties {
+                writeln!(
+                    out,
+                    r#"        <property name="{}" value="{}"/>"#,
+                    name,
+                    escape(&value)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "      </properties>").unwrap();
+    }
+    // Artifacts are attached using the convention understood by GitLab and
+    // Jenkins.
+    for artifact in node.artifacts.iter() {
+        writeln!(
+            out,
+            "      <system-out>[[ATTACHMENT|{}]]</system-out>",
+            escape(&artifact.display().to_string())
+        )
+        .unwrap();
+    }
+    writeln!(out, "    </testcase>").unwrap();
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn of<'a>(cases: impl Iterator<Item = (String, &'a TestResultNode)>) -> Self {
+        let mut counts = Counts::default();
+        for (_, case) in cases {
+            counts.tests += 1;
+            match case.result {
+                TestResult::Passed => (),
+                TestResult::Failed => counts.failures += 1,
+                TestResult::Skipped => counts.skipped += 1,
+            }
+        }
+        counts
+    }
+}
+
+fn seconds(d: Duration) -> String {
+    format!("{:.3}", d.as_secs_f64())
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            // Other control characters are not allowed in XML 1.0.
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::triage::{TriageHints, TriageRecord};
+    use std::path::PathBuf;
+
+    fn node(name: &str, result: TestResult, children: Vec<TestResultNode>) -> TestResultNode {
+        TestResultNode {
+            name: name.to_string(),
+            duration: Duration::from_millis(1500),
+            result,
+            children,
+            ..TestResultNode::default()
+        }
+    }
+
+    #[test]
+    fn junit_report_contains_tests_and_steps() {
+        let mut failed_step = node("install", TestResult::Failed, vec![]);
+        failed_step.message = Some("canister <a> & \"b\" trapped\nbacktrace".to_string());
+        let mut test = node(
+            "test_a",
+            TestResult::Failed,
+            vec![node("setup", TestResult::Passed, vec![]), failed_step],
+        );
+        test.artifacts = vec![PathBuf::from("logs/suite/pot/test_a.log")];
+        test.triage = vec![TriageRecord {
+            helper: "await_status_is_healthy".to_string(),
+            error: "Not ready!".to_string(),
+            hints: TriageHints::new()
+                .subsystem("replica")
+                .owner("team-a")
+                .link("https://example.com/runbook?a=1&b=2"),
+        }];
+        let suite = node(
+            "suite",
+            TestResult::Failed,
+            vec![
+                node(
+                    "pot",
+                    TestResult::Failed,
+                    vec![test, node("test_b", TestResult::Skipped, vec![])],
+                ),
+                node("broken_pot", TestResult::Failed, vec![]),
+            ],
+        );
+
+        let xml = junit_xml(&suite);
+
+        assert!(xml.contains(
+            r#"<testsuites name="suite" tests="5" failures="3" skipped="1" time="1.500">"#
+        ));
+        assert!(xml.contains(r#"<testsuite name="suite::pot" tests="4" failures="2" skipped="1""#));
+        assert!(xml.contains(r#"<testcase classname="suite::pot" name="test_a::install""#));
+        assert!(
+            xml.contains(r#"<failure message="canister &lt;a&gt; &amp; &quot;b&quot; trapped">"#)
+        );
+        assert!(xml.contains("[[ATTACHMENT|logs/suite/pot/test_a.log]]"));
+        assert!(xml.contains(r#"<property name="triage.helper" value="await_status_is_healthy"/>"#));
+        assert!(xml.contains(r#"<property name="triage.subsystem" value="replica"/>"#));
+        assert!(xml.contains(r#"<property name="triage.owner" value="team-a"/>"#));
+        assert!(xml.contains(
+            r#"<property name="triage.link" value="https://example.com/runbook?a=1&amp;b=2"/>"#
+        ));
+        assert!(xml.contains(r#"<testcase classname="suite::broken_pot" name="setup""#));
+    }
+
+    #[test]
+    fn reports_are_written_to_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let suite = node(
+            "suite",
+            TestResult::Passed,
+            vec![node(
+                "pot",
+                TestResult::Passed,
+                vec![node("test", TestResult::Passed, vec![])],
+            )],
+        );
+
+        write_reports(dir.path(), &suite).unwrap();
+
+        let json = std::fs::read_to_string(dir.path().join(JSON_REPORT_FILE)).unwrap();
+        let parsed: TestResultNode = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.children[0].children[0].name, "test");
+        let xml = std::fs::read_to_string(dir.path().join(JUNIT_REPORT_FILE)).unwrap();
+        assert_eq!(xml, junit_xml(&suite));
+    }
+}
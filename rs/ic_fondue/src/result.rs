@@ -1,7 +1,9 @@
 #![allow(clippy::ptr_arg)]
 use crate::pot::PotResult;
+use crate::triage::TriageRecord;
 use nix::unistd::Pid;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 /// Execution will fork and run one pot per process. Pots themselves
@@ -22,6 +24,9 @@ impl ExecutionResult {
                     started_at: test.started_at,
                     duration: test.duration,
                     result: test.result,
+                    message: test.message,
+                    artifacts: test.artifacts,
+                    triage: test.triage,
                     children: vec![],
                 })
                 .collect()
@@ -42,6 +47,9 @@ impl ExecutionResult {
                     started_at: pot.started_at,
                     duration: pot.duration,
                     result,
+                    message: None,
+                    artifacts: vec![],
+                    triage: vec![],
                     children: pot.result.map_or(vec![], to_test_result),
                 }
             })
@@ -54,6 +62,9 @@ impl ExecutionResult {
             duration: Instant::now() - started_at,
             children,
             result,
+            message: None,
+            artifacts: vec![],
+            triage: vec![],
         }
     }
 
@@ -146,6 +157,16 @@ pub struct TestResultNode {
     pub started_at: Instant,
     pub duration: Duration,
     pub result: TestResult,
+    /// Describes why the node failed, e.g. the message of a panic.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Paths of files produced while executing the node, e.g. logs.
+    #[serde(default)]
+    pub artifacts: Vec<PathBuf>,
+    /// Hints on how to triage the failure of the node, recorded by the helpers
+    /// that failed while executing it.
+    #[serde(default)]
+    pub triage: Vec<TriageRecord>,
     pub children: Vec<TestResultNode>,
 }
 
@@ -164,6 +185,9 @@ impl Default for TestResultNode {
             started_at: Instant::now(),
             duration: Duration::default(),
             result: TestResult::Skipped,
+            message: None,
+            artifacts: vec![],
+            triage: vec![],
             children: vec![],
         }
     }
@@ -179,3 +203,14 @@ pub fn infer_result(tests: &[TestResultNode]) -> TestResult {
         TestResult::Passed
     }
 }
+
+/// Returns the message carried by the payload of a panic.
+pub fn panic_message(panic_res: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = panic_res.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(s) = panic_res.downcast_ref::<&str>() {
+        s.to_string()
+    } else {
+        format!("{:?}", panic_res)
+    }
+}
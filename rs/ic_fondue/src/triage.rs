@@ -0,0 +1,168 @@
+//! Triage hints for failing system tests.
+//!
+//! When an assertion helper, e.g. `await_status_is_healthy`, fails, it
+//! records a [TriageRecord] with the hints it suggests by default, e.g. the
+//! subsystems that are usually at fault. Tests can attach further hints to a
+//! helper with [attach_hints], e.g. the owner of the test or a link to a
+//! runbook. When the test fails, the records are added to its
+//! [TestResultNode](crate::result::TestResultNode), and hence to the
+//! structured reports, so that tickets for flaky tests can be routed without
+//! reading the logs first.
+//!
+//! Hints and records are kept per thread: only helpers that fail on the
+//! thread of the test itself are recorded.
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+/// Hints on where to look when a helper fails.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TriageHints {
+    /// Labels of the subsystems that are suspected to be at fault, e.g.
+    /// `replica` or `networking`.
+    #[serde(default)]
+    pub subsystems: BTreeSet<String>,
+    /// The team or person that owns the failing test.
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Links that help triaging the failure, e.g. to runbooks or dashboards.
+    #[serde(default)]
+    pub links: Vec<String>,
+}
+
+impl TriageHints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subsystem(mut self, subsystem: &str) -> Self {
+        self.subsystems.insert(subsystem.to_string());
+        self
+    }
+
+    pub fn owner(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_string());
+        self
+    }
+
+    pub fn link(mut self, link: &str) -> Self {
+        self.links.push(link.to_string());
+        self
+    }
+
+    /// Adds the hints of `other` to these hints. The owner of `other` takes
+    /// precedence.
+    fn merge(mut self, other: TriageHints) -> Self {
+        self.subsystems.extend(other.subsystems);
+        self.owner = other.owner.or(self.owner);
+        for link in other.links {
+            if !self.links.contains(&link) {
+                self.links.push(link);
+            }
+        }
+        self
+    }
+}
+
+/// The failure of a helper, together with the hints on how to triage it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TriageRecord {
+    /// The name of the failing helper, e.g. `await_status_is_healthy`.
+    pub helper: String,
+    /// The error returned by the helper.
+    pub error: String,
+    pub hints: TriageHints,
+}
+
+thread_local! {
+    static ATTACHED_HINTS: RefCell<BTreeMap<String, TriageHints>> = RefCell::new(BTreeMap::new());
+    static RECORDS: RefCell<Vec<TriageRecord>> = RefCell::new(Vec::new());
+}
+
+/// Attaches `hints` to the failures of `helper` for the rest of the current
+/// test. Hints attached to the same helper multiple times are merged.
+pub fn attach_hints(helper: &str, hints: TriageHints) {
+    ATTACHED_HINTS.with(|attached| {
+        let mut attached = attached.borrow_mut();
+        let merged = attached.remove(helper).unwrap_or_default().merge(hints);
+        attached.insert(helper.to_string(), merged);
+    });
+}
+
+/// Records that `helper` failed with `error`. The record carries the
+/// `default_hints` of the helper, together with the hints the test attached
+/// to it.
+pub fn helper_failed(helper: &str, default_hints: TriageHints, error: &dyn Display) {
+    let attached = ATTACHED_HINTS.with(|attached| attached.borrow().get(helper).cloned());
+    let hints = default_hints.merge(attached.unwrap_or_default());
+    RECORDS.with(|records| {
+        records.borrow_mut().push(TriageRecord {
+            helper: helper.to_string(),
+            error: error.to_string(),
+            hints,
+        })
+    });
+}
+
+/// Forgets the hints and records of the current thread, e.g. before a test is
+/// run.
+pub(crate) fn reset() {
+    ATTACHED_HINTS.with(|attached| attached.borrow_mut().clear());
+    RECORDS.with(|records| records.borrow_mut().clear());
+}
+
+/// Returns the records of the current thread, in the order in which the
+/// helpers failed, and forgets them.
+pub(crate) fn take_records() -> Vec<TriageRecord> {
+    RECORDS.with(|records| std::mem::take(&mut *records.borrow_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_default_and_attached_hints() {
+        reset();
+        attach_hints(
+            "await_healthy",
+            TriageHints::new().subsystem("networking").owner("team-a"),
+        );
+        attach_hints("await_healthy", TriageHints::new().link("https://runbook"));
+        attach_hints("other_helper", TriageHints::new().subsystem("execution"));
+
+        helper_failed(
+            "await_healthy",
+            TriageHints::new().subsystem("replica").owner("default"),
+            &"timed out",
+        );
+
+        assert_eq!(
+            take_records(),
+            vec![TriageRecord {
+                helper: "await_healthy".to_string(),
+                error: "timed out".to_string(),
+                hints: TriageHints::new()
+                    .subsystem("networking")
+                    .subsystem("replica")
+                    .owner("team-a")
+                    .link("https://runbook"),
+            }]
+        );
+        assert!(take_records().is_empty());
+    }
+
+    #[test]
+    fn reset_forgets_hints_and_records() {
+        attach_hints("await_healthy", TriageHints::new().owner("team-a"));
+        helper_failed("await_healthy", TriageHints::new(), &"timed out");
+
+        reset();
+        helper_failed("await_healthy", TriageHints::new(), &"timed out");
+
+        let records = take_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].hints, TriageHints::new());
+    }
+}
@@ -0,0 +1,184 @@
+//! The options of the calls from the replica to adapters.
+//!
+//! The same adapter may be called by consensus, which must not block for more
+//! than a few milliseconds, and by background tasks, which can afford to wait
+//! for a couple of seconds. Callers therefore express with [`Options`] how
+//! long a call may take, how often it is attempted, and its [`Priority`]
+//! relative to the other calls to the same adapter. An [`OptionsBuilder`]
+//! additionally attaches the [`TraceContext`] and the purpose of a call, which
+//! are sent to the adapter as gRPC metadata so that the logs of the adapter
+//! can be correlated with the replica round that triggered the call.
+use std::time::Duration;
+
+/// The gRPC metadata key of the [`TraceContext`] of a call, in the W3C
+/// `traceparent` format.
+pub const TRACEPARENT_METADATA_KEY: &str = "traceparent";
+
+/// The gRPC metadata key of the purpose of a call.
+pub const PURPOSE_METADATA_KEY: &str = "ic-call-purpose";
+
+/// How often a call that failed to reach the adapter is attempted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one. A policy with
+    /// 0 attempts is treated as a single attempt.
+    pub max_attempts: u32,
+    /// The time to wait between two attempts.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that attempts a call only once.
+    pub const fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_millis(0),
+        }
+    }
+
+    /// A policy that attempts a call up to `max_attempts` times, waiting
+    /// `backoff` between two attempts.
+    pub const fn attempts(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::no_retries()
+    }
+}
+
+/// The priority of a call relative to the other calls to the same adapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Calls that may wait for other calls, e.g. from background tasks. The
+    /// clients bound the number of such calls in flight.
+    Background,
+    /// Calls that consensus waits for. They never wait for other calls.
+    ConsensusCritical,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::ConsensusCritical
+    }
+}
+
+/// The trace and span that a call belongs to, e.g. those of the round that
+/// triggered it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+}
+
+impl TraceContext {
+    /// Renders the context as a sampled W3C `traceparent` header value.
+    pub fn to_traceparent(&self) -> String {
+        format!("00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The deadline of the call, counting all its attempts and the time it
+    /// waits for other calls.
+    pub timeout: Option<Duration>,
+    pub retries: RetryPolicy,
+    pub priority: Priority,
+    pub trace_context: Option<TraceContext>,
+    /// A short label of why the call is made, e.g. `"get_successors"`. It must
+    /// consist of visible ASCII characters to be sent to the adapter.
+    pub purpose: Option<&'static str>,
+}
+
+impl Options {
+    /// A single attempt within 10ms, for calls on the consensus thread.
+    pub fn consensus_critical() -> Self {
+        Self {
+            // Since we are allowed to block only for few milliseconds the consensus thread,
+            // set reasonable defaults.
+            timeout: Some(Duration::from_millis(10)),
+            retries: RetryPolicy::no_retries(),
+            priority: Priority::ConsensusCritical,
+            trace_context: None,
+            purpose: None,
+        }
+    }
+
+    /// Up to 3 attempts within 2s, for calls from background tasks.
+    pub fn background() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(2)),
+            retries: RetryPolicy::attempts(3, Duration::from_millis(100)),
+            priority: Priority::Background,
+            trace_context: None,
+            purpose: None,
+        }
+    }
+
+    /// A builder starting from the [default](Options::default) options.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::new(Self::default())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::consensus_critical()
+    }
+}
+
+/// Builds [`Options`] by overriding some of the fields of base options.
+///
+/// ```
+/// # use ic_interfaces::adapter_client::{Options, OptionsBuilder, TraceContext};
+/// let opts = OptionsBuilder::new(Options::background())
+///     .purpose("send_transaction")
+///     .trace_context(TraceContext { trace_id: 1, span_id: 2 })
+///     .build();
+/// assert_eq!(opts.timeout, Options::background().timeout);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionsBuilder {
+    opts: Options,
+}
+
+impl OptionsBuilder {
+    pub fn new(base: Options) -> Self {
+        Self { opts: base }
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.timeout = timeout;
+        self
+    }
+
+    pub fn retries(mut self, retries: RetryPolicy) -> Self {
+        self.opts.retries = retries;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.opts.priority = priority;
+        self
+    }
+
+    pub fn trace_context(mut self, trace_context: TraceContext) -> Self {
+        self.opts.trace_context = Some(trace_context);
+        self
+    }
+
+    pub fn purpose(mut self, purpose: &'static str) -> Self {
+        self.opts.purpose = Some(purpose);
+        self
+    }
+
+    pub fn build(self) -> Options {
+        self.opts
+    }
+}
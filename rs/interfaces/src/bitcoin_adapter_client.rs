@@ -1,9 +1,10 @@
 use ic_protobuf::bitcoin::v1::{
     GetSuccessorsRequest, GetSuccessorsResponse, SendTransactionRequest, SendTransactionResponse,
 };
-use std::time::Duration;
 use tonic::Status;
 
+pub use crate::adapter_client::{Options, OptionsBuilder, Priority, RetryPolicy, TraceContext};
+
 /// Describe RPC error -- can be either related to transport (i.e.
 /// failure to transport or parse a message) or to server (i.e. server
 /// responded, but gave us a message indicating an error).
@@ -17,19 +18,6 @@ pub enum RpcError {
 
 pub type RpcResult<T> = Result<T, RpcError>;
 
-pub struct Options {
-    pub timeout: Option<Duration>,
-}
-
-impl Default for Options {
-    fn default() -> Self {
-        Self {
-            // Since we are allowed to block only for few milliseconds the consensus thread,
-            // set reasonable defaults.
-            timeout: Some(Duration::from_millis(10)),
-        }
-    }
-}
 /// Sync interface for communicating with the bitcoin adapter. Note the function calls block the
 /// running thread. Also the calls may panic if called from async context.
 pub trait BitcoinAdapterClient {
@@ -0,0 +1,299 @@
+//! The canister http interface shared by execution and consensus.
+//!
+//! Execution sends the requests of canisters to the canister http adapter
+//! through a [`CanisterHttpAdapterClient`], and consensus picks up the replies
+//! of the adapter from the same client. Both sides validate what they hand
+//! over with the helpers in this module. Consensus reports the requests whose
+//! responses did not reach agreement through a
+//! [`CanisterHttpDivergenceReporter`].
+use crate::rpc_bridge::{RpcBridge, RpcBridgeReceiveError, RpcBridgeSendError};
+use ic_types::{
+    canister_http::{
+        CanisterHttpDivergenceReport, CanisterHttpLimits, CanisterHttpReply, CanisterHttpRequest,
+        CanisterHttpRequestContext, MAX_CANISTER_HTTP_RESPONSE_BYTES,
+    },
+    CountBytes,
+};
+
+/// The maximum size of a canister http request, i.e. its url and body.
+pub const MAX_CANISTER_HTTP_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// The maximum size of a canister http reply, i.e. its headers and body.
+/// Subnets may configure a lower limit in the registry.
+pub const MAX_CANISTER_HTTP_REPLY_BYTES: usize = MAX_CANISTER_HTTP_RESPONSE_BYTES as usize;
+
+/// The maximum length of the url of a canister http request.
+pub const MAX_CANISTER_HTTP_URL_LENGTH: usize = 8192;
+
+/// A channel that neither blocks on sending requests nor on receiving
+/// responses, so that it can be driven from the execution and consensus
+/// threads.
+pub trait NonBlockingChannel<Request> {
+    type Response;
+
+    /// Sends a request, failing immediately if the channel can not take it.
+    /// A bounded channel fails with [`RpcBridgeSendError::Full`] while it holds
+    /// as many requests as it is bounded to, handing the request back so that
+    /// the caller can send it again once responses were received.
+    fn send(&mut self, request: Request) -> Result<(), RpcBridgeSendError<Request>>;
+
+    /// Returns the next available response, if any.
+    fn try_receive(&mut self) -> Result<Self::Response, RpcBridgeReceiveError>;
+}
+
+impl<Request, Response> NonBlockingChannel<Request> for RpcBridge<Request, Response>
+where
+    Request: Send,
+    Response: Send + 'static,
+{
+    type Response = Response;
+
+    fn send(&mut self, request: Request) -> Result<(), RpcBridgeSendError<Request>> {
+        RpcBridge::send(self, request)
+    }
+
+    fn try_receive(&mut self) -> Result<Response, RpcBridgeReceiveError> {
+        RpcBridge::try_receive(self)
+    }
+}
+
+/// The client of the canister http adapter: requests are sent without
+/// waiting for the adapter, and consensus polls for the replies with
+/// [`NonBlockingChannel::try_receive`] until none are pending. The client can
+/// be moved to the thread that polls it.
+pub trait CanisterHttpAdapterClient:
+    NonBlockingChannel<CanisterHttpRequest, Response = CanisterHttpReply> + Send
+{
+}
+
+impl<T> CanisterHttpAdapterClient for T where
+    T: NonBlockingChannel<CanisterHttpRequest, Response = CanisterHttpReply> + Send
+{
+}
+
+/// Reports the canister http requests whose responses did not reach
+/// consensus, e.g. to metrics, so that it can be told why outcalls fail.
+pub trait CanisterHttpDivergenceReporter: Send + Sync {
+    /// Reports that consensus gave up on the request of `report`, whose
+    /// replicas signed `report.distinct_responses` different responses.
+    fn report_divergence(&self, report: &CanisterHttpDivergenceReport);
+}
+
+/// Errors found when validating canister http requests and replies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CanisterHttpValidationError {
+    UrlTooLong { length: usize, limit: usize },
+    UnsupportedUrlScheme { url: String, allowed: Vec<String> },
+    RequestTooLarge { size: usize, limit: usize },
+    ReplyTooLarge { size: usize, limit: usize },
+    InvalidStatus { status: u32 },
+}
+
+impl std::fmt::Display for CanisterHttpValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UrlTooLong { length, limit } => write!(
+                f,
+                "Url of {} characters exceeds the limit of {} characters",
+                length, limit
+            ),
+            Self::UnsupportedUrlScheme { url, allowed } => write!(
+                f,
+                "Url {} does not use one of the allowed schemes {:?}",
+                url, allowed
+            ),
+            Self::RequestTooLarge { size, limit } => write!(
+                f,
+                "Request of {} bytes exceeds the limit of {} bytes",
+                size, limit
+            ),
+            Self::ReplyTooLarge { size, limit } => write!(
+                f,
+                "Reply of {} bytes exceeds the limit of {} bytes",
+                size, limit
+            ),
+            Self::InvalidStatus { status } => write!(f, "Invalid http status code {}", status),
+        }
+    }
+}
+
+/// Checks that `request` can be submitted to the adapter: its url uses one of
+/// the schemes allowed by `limits` and is not too long, and the request does
+/// not exceed [`MAX_CANISTER_HTTP_REQUEST_BYTES`].
+pub fn validate_canister_http_request(
+    request: &CanisterHttpRequest,
+    limits: &CanisterHttpLimits,
+) -> Result<(), CanisterHttpValidationError> {
+    let url = &request.content.url;
+    if url.len() > MAX_CANISTER_HTTP_URL_LENGTH {
+        return Err(CanisterHttpValidationError::UrlTooLong {
+            length: url.len(),
+            limit: MAX_CANISTER_HTTP_URL_LENGTH,
+        });
+    }
+    if !limits.allows_url(url) {
+        return Err(CanisterHttpValidationError::UnsupportedUrlScheme {
+            url: url.clone(),
+            allowed: limits.allowed_url_schemes.clone(),
+        });
+    }
+    let size = request.count_bytes();
+    if size > MAX_CANISTER_HTTP_REQUEST_BYTES {
+        return Err(CanisterHttpValidationError::RequestTooLarge {
+            size,
+            limit: MAX_CANISTER_HTTP_REQUEST_BYTES,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `reply` has a valid http status code and exceeds neither the
+/// `max_response_bytes` that the request of `context` paid for nor the
+/// maximum response size of `limits`.
+pub fn validate_canister_http_reply(
+    reply: &CanisterHttpReply,
+    context: &CanisterHttpRequestContext,
+    limits: &CanisterHttpLimits,
+) -> Result<(), CanisterHttpValidationError> {
+    let max_reply_bytes = (context.max_response_bytes.min(limits.max_response_bytes) as usize)
+        .min(MAX_CANISTER_HTTP_REPLY_BYTES);
+    if !(100..600).contains(&reply.status) {
+        return Err(CanisterHttpValidationError::InvalidStatus {
+            status: reply.status,
+        });
+    }
+    let size = reply.count_bytes();
+    if size > max_reply_bytes {
+        return Err(CanisterHttpValidationError::ReplyTooLarge {
+            size,
+            limit: max_reply_bytes,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::HttpMethodType;
+    use ic_types::{
+        messages::{CallbackId, Request},
+        time::UNIX_EPOCH,
+        CanisterId, Cycles,
+    };
+
+    fn request_with(url: &str, body: Vec<u8>) -> CanisterHttpRequest {
+        CanisterHttpRequest {
+            id: CallbackId::from(1),
+            content: CanisterHttpRequestContext {
+                request: Request {
+                    receiver: CanisterId::ic_00(),
+                    sender: CanisterId::from_u64(1),
+                    sender_reply_callback: CallbackId::from(1),
+                    payment: Cycles::zero(),
+                    method_name: "http_request".to_string(),
+                    method_payload: vec![],
+                },
+                url: url.to_string(),
+                body: Some(body),
+                http_method: HttpMethodType::GET,
+                transform_method_name: None,
+                time: UNIX_EPOCH,
+                timeout: UNIX_EPOCH,
+                max_response_bytes: 0,
+                fee_per_response_byte: Cycles::zero(),
+            },
+        }
+    }
+
+    fn reply_with(status: u32, body: Vec<u8>) -> CanisterHttpReply {
+        CanisterHttpReply {
+            id: CallbackId::from(1),
+            status,
+            headers: vec![],
+            body,
+        }
+    }
+
+    fn limits_with(max_response_bytes: u64, allowed_url_schemes: &[&str]) -> CanisterHttpLimits {
+        CanisterHttpLimits {
+            max_response_bytes,
+            allowed_url_schemes: allowed_url_schemes.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_canister_http_request() {
+        let limits = CanisterHttpLimits::default();
+        let request = request_with("https://example.com", vec![0; 10]);
+        assert_eq!(validate_canister_http_request(&request, &limits), Ok(()));
+
+        let request = request_with("http://example.com", vec![]);
+        assert_eq!(
+            validate_canister_http_request(&request, &limits),
+            Err(CanisterHttpValidationError::UnsupportedUrlScheme {
+                url: "http://example.com".to_string(),
+                allowed: vec!["https".to_string()],
+            })
+        );
+        assert_eq!(
+            validate_canister_http_request(&request, &limits_with(10, &["https", "http"])),
+            Ok(())
+        );
+
+        let request = request_with(
+            "https://example.com",
+            vec![0; MAX_CANISTER_HTTP_REQUEST_BYTES],
+        );
+        assert_eq!(
+            validate_canister_http_request(&request, &limits),
+            Err(CanisterHttpValidationError::RequestTooLarge {
+                size: MAX_CANISTER_HTTP_REQUEST_BYTES + 19,
+                limit: MAX_CANISTER_HTTP_REQUEST_BYTES
+            })
+        );
+    }
+
+    fn context_with(max_response_bytes: u64) -> CanisterHttpRequestContext {
+        CanisterHttpRequestContext {
+            max_response_bytes,
+            ..request_with("https://example.com", vec![]).content
+        }
+    }
+
+    #[test]
+    fn test_validate_canister_http_reply() {
+        let context = context_with(MAX_CANISTER_HTTP_RESPONSE_BYTES);
+        let limits = limits_with(10, &["https"]);
+        assert_eq!(
+            validate_canister_http_reply(&reply_with(200, vec![0; 10]), &context, &limits),
+            Ok(())
+        );
+        assert_eq!(
+            validate_canister_http_reply(&reply_with(200, vec![0; 11]), &context, &limits),
+            Err(CanisterHttpValidationError::ReplyTooLarge {
+                size: 11,
+                limit: 10
+            })
+        );
+        assert_eq!(
+            validate_canister_http_reply(&reply_with(600, vec![]), &context, &limits),
+            Err(CanisterHttpValidationError::InvalidStatus { status: 600 })
+        );
+    }
+
+    #[test]
+    fn test_validate_canister_http_reply_against_max_response_bytes_of_request() {
+        let context = context_with(5);
+        let limits = limits_with(10, &["https"]);
+        assert_eq!(
+            validate_canister_http_reply(&reply_with(200, vec![0; 5]), &context, &limits),
+            Ok(())
+        );
+        assert_eq!(
+            validate_canister_http_reply(&reply_with(200, vec![0; 6]), &context, &limits),
+            Err(CanisterHttpValidationError::ReplyTooLarge { size: 6, limit: 5 })
+        );
+    }
+}
@@ -53,6 +53,8 @@ impl ErrorReplication for CryptoError {
             CryptoError::DkgTranscriptNotFound { .. } => true,
             // true, as the registry is guaranteed to be consistent across replicas
             CryptoError::RootSubnetPublicKeyNotFound { .. } => true,
+            // false, as the error may not occur again when retried
+            CryptoError::TransientInternalError { .. } => false,
         }
     }
 }
@@ -125,14 +127,28 @@ impl ErrorReplication for IDkgVerifyTranscriptError {
             IDkgVerifyTranscriptError::SerializationError(_) => true,
             // true, as the transcript does not become valid through retrying
             IDkgVerifyTranscriptError::InvalidTranscript => true,
+            // false, as replicas running different code versions during an upgrade
+            // may support different algorithms
+            IDkgVerifyTranscriptError::UnsupportedAlgorithmId { .. } => false,
         }
     }
 }
 
 impl ErrorReplication for IDkgVerifyDealingPublicError {
     fn is_replicated(&self) -> bool {
-        // TODO correctly implement this function
-        false
+        // The match below is intentionally explicit on all possible values,
+        // to avoid defaults, which might be error-prone.
+        // Upon addition of any new error this match has to be updated.
+        match self {
+            IDkgVerifyDealingPublicError::TranscriptIdMismatch => true,
+            IDkgVerifyDealingPublicError::InvalidDealer { .. } => true,
+            IDkgVerifyDealingPublicError::InvalidArgument { .. } => true,
+            IDkgVerifyDealingPublicError::InvalidDealing { .. } => true,
+            IDkgVerifyDealingPublicError::InternalError { .. } => false,
+            // false, as replicas running different code versions during an upgrade
+            // may support different algorithms
+            IDkgVerifyDealingPublicError::UnsupportedAlgorithmId { .. } => false,
+        }
     }
 }
 
@@ -181,6 +197,25 @@ impl ErrorReplication for IDkgVerifyDealingPrivateError {
                 // Logic error. Everyone thinks a non-receiver is a receiver.
                 true
             }
+            // true, as the dealing fails to deserialize on every replica
+            IDkgVerifyDealingPrivateError::InvalidArgument { .. } => true,
+            // false, as only this receiver can decrypt its share of the dealing,
+            // so the shares of other receivers may be valid
+            IDkgVerifyDealingPrivateError::InvalidDealing { .. } => false,
+            // false, as the secret key store is local to this replica
+            IDkgVerifyDealingPrivateError::PrivateKeyNotFound { .. } => false,
+            // true, as the registry is guaranteed to be consistent across replicas
+            IDkgVerifyDealingPrivateError::PublicKeyNotInRegistry { .. }
+            | IDkgVerifyDealingPrivateError::MalformedPublicKey { .. }
+            | IDkgVerifyDealingPrivateError::UnsupportedAlgorithm { .. } => true,
+            IDkgVerifyDealingPrivateError::RegistryError(registry_client_error) => {
+                error_replication_of_registry_client_error(registry_client_error)
+            }
+            // false, as the next attempt may succeed
+            IDkgVerifyDealingPrivateError::InternalError { .. } => false,
+            // false, as replicas running different code versions during an upgrade
+            // may support different algorithms
+            IDkgVerifyDealingPrivateError::UnsupportedAlgorithmId { .. } => false,
         }
     }
 }
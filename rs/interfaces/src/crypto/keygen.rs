@@ -1,4 +1,5 @@
 use ic_protobuf::crypto::v1::NodePublicKeys;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_types::crypto::CryptoResult;
 use ic_types::RegistryVersion;
 
@@ -10,6 +11,35 @@ pub trait KeyManager {
     /// contains the corresponding secret keys.
     fn check_keys_with_registry(&self, registry_version: RegistryVersion) -> CryptoResult<()>;
 
+    /// Checks whether the crypto component's secret key store contains a
+    /// valid secret key for the node's iDKG dealing encryption (MEGa) public
+    /// key that is registered in the registry at `registry_version`.
+    ///
+    /// This does not reveal any secret key material and is meant to detect
+    /// nodes with missing keys before they fail to participate in iDKG.
+    fn check_idkg_dealing_encryption_key_with_registry(
+        &self,
+        registry_version: RegistryVersion,
+    ) -> CryptoResult<()>;
+
+    /// Generates a new iDKG dealing encryption (MEGa) key pair for rotating
+    /// the node's registered one, and returns the public key to be registered.
+    ///
+    /// The returned public key carries a proof of possession bound to the
+    /// node ID and the time of its generation as `timestamp`. The secret keys
+    /// of the previous public keys are retained, because active transcripts
+    /// may still reference them. They are retired once no active transcript
+    /// references them anymore, see `IDkgProtocol::retain_active_transcripts`.
+    ///
+    /// The new secret key may be generated by another process than the one
+    /// that uses it, e.g. by the orchestrator for the replica: the secret key
+    /// stores of both processes reload their shared file before writing it.
+    ///
+    /// # Errors
+    /// * `CryptoError::TransientInternalError` if the remote CSP vault could
+    ///   not be reached, in which case the call may be retried.
+    fn rotate_idkg_dealing_encryption_key(&self) -> CryptoResult<PublicKeyProto>;
+
     /// Returns node public keys that were read when this crypto component was
     /// created. Node public keys stay the same throughout the lifetime of
     /// the component.
@@ -16,7 +16,8 @@ use ic_types::crypto::canister_threshold_sig::idkg::{
 use ic_types::crypto::canister_threshold_sig::{
     ThresholdEcdsaCombinedSignature, ThresholdEcdsaSigInputs, ThresholdEcdsaSigShare,
 };
-use std::collections::BTreeMap;
+use ic_types::crypto::AlgorithmId;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A Crypto Component interface to run Interactive-DKG
 /// (for canister threshold signatures).
@@ -183,6 +184,15 @@ pub trait IDkgProtocol {
 
     /// Retain only the given transcripts in the local state.
     fn retain_active_transcripts(&self, active_transcripts: &[IDkgTranscript]);
+
+    /// Returns the algorithms for which this node can run I-DKG and create
+    /// and combine threshold signature shares.
+    ///
+    /// Operations with any other algorithm fail with an
+    /// `UnsupportedAlgorithmId` error, so consensus should not propose them,
+    /// e.g. while the nodes of a subnet are upgraded to a version that
+    /// supports a new algorithm.
+    fn supported_algorithms(&self) -> BTreeSet<AlgorithmId>;
 }
 
 /// A Crypto Component interface to generate ECDSA threshold signature shares.
@@ -8,6 +8,7 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_sys::{PageBytes, PageIndex};
 use ic_types::{
+    canister_http::{CanisterHttpReply, CanisterHttpRequestContext, CanisterHttpTransformError},
     canonical_error::CanonicalError,
     crypto::canister_threshold_sig::MasterEcdsaPublicKey,
     ingress::{IngressStatus, WasmResult},
@@ -161,6 +162,17 @@ pub trait QueryHandler: Send + Sync {
         state: Arc<Self::State>,
         data_certificate: Vec<u8>,
     ) -> Result<WasmResult, UserError>;
+
+    /// Runs the transform function of the canister that made the canister
+    /// http request of `context` on `reply`, within the transform budget
+    /// of the subnet. Returns `reply` unchanged if the request names no
+    /// transform function.
+    fn transform_canister_http_reply(
+        &self,
+        context: &CanisterHttpRequestContext,
+        reply: CanisterHttpReply,
+        state: Arc<Self::State>,
+    ) -> Result<CanisterHttpReply, CanisterHttpTransformError>;
 }
 
 /// Errors that can be returned when reading/writing from/to ingress history.
@@ -2,9 +2,11 @@
 //!
 //! Having the public interfaces defined separately from their components
 //! helps reduce unnecessary dependencies between them.
+pub mod adapter_client;
 pub mod artifact_manager;
 pub mod artifact_pool;
 pub mod bitcoin_adapter_client;
+pub mod canister_http;
 pub mod certification;
 pub mod certified_stream_store;
 pub mod consensus;
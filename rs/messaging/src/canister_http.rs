@@ -0,0 +1,80 @@
+//! Reporting of canister http requests whose responses did not reach
+//! consensus.
+use ic_interfaces::canister_http::CanisterHttpDivergenceReporter;
+use ic_metrics::{buckets::linear_buckets, MetricsRegistry};
+use ic_types::canister_http::CanisterHttpDivergenceReport;
+use prometheus::{Histogram, IntCounterVec};
+
+/// Reports the divergences of canister http requests to metrics. The
+/// divergences are counted by whether the requests have a transform, since
+/// requests without one diverge whenever the responses differ slightly
+/// between replicas.
+#[derive(Clone)]
+pub(crate) struct CanisterHttpDivergenceMetrics {
+    divergences: IntCounterVec,
+    distinct_responses: Histogram,
+}
+
+impl CanisterHttpDivergenceMetrics {
+    /// Registers the metrics in `metrics_registry`.
+    pub(crate) fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            divergences: metrics_registry.int_counter_vec(
+                "mr_canister_http_divergences_total",
+                "Total number of canister http requests whose responses did not reach consensus, by whether the request has a transform.",
+                &["transform"],
+            ),
+            distinct_responses: metrics_registry.histogram(
+                "mr_canister_http_divergence_distinct_responses",
+                "The number of different responses the replicas signed for canister http requests that did not reach consensus.",
+                // 1, 2, ..., 13
+                linear_buckets(1.0, 1.0, 13),
+            ),
+        }
+    }
+}
+
+impl CanisterHttpDivergenceReporter for CanisterHttpDivergenceMetrics {
+    fn report_divergence(&self, report: &CanisterHttpDivergenceReport) {
+        let transform = match report.transform_method_name {
+            Some(_) => "yes",
+            None => "no",
+        };
+        self.divergences.with_label_values(&[transform]).inc();
+        self.distinct_responses
+            .observe(report.distinct_responses as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::canister_test_id;
+    use ic_types::{messages::CallbackId, time::UNIX_EPOCH};
+
+    fn report(transform_method_name: Option<String>) -> CanisterHttpDivergenceReport {
+        CanisterHttpDivergenceReport {
+            request_id: CallbackId::from(1),
+            canister_id: canister_test_id(1),
+            url: "https://example.com".to_string(),
+            distinct_responses: 3,
+            response_shares: 4,
+            transform_method_name,
+            time: UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_divergences_are_counted_by_transform() {
+        let metrics = CanisterHttpDivergenceMetrics::new(&MetricsRegistry::new());
+
+        metrics.report_divergence(&report(None));
+        metrics.report_divergence(&report(None));
+        metrics.report_divergence(&report(Some("transform".to_string())));
+
+        assert_eq!(metrics.divergences.with_label_values(&["no"]).get(), 2);
+        assert_eq!(metrics.divergences.with_label_values(&["yes"]).get(), 1);
+        assert_eq!(metrics.distinct_responses.get_sample_count(), 3);
+        assert_eq!(metrics.distinct_responses.get_sample_sum(), 9.0);
+    }
+}
@@ -2,6 +2,7 @@
 //! (ii) inter-canister message routing within a subnet and across subnets (also
 //! known as cross-net or XNet transfer).
 
+mod canister_http;
 pub mod certified_slice_pool;
 pub(crate) mod hyper;
 mod message_routing;
@@ -1,4 +1,5 @@
 use crate::{
+    canister_http::CanisterHttpDivergenceMetrics,
     routing, scheduling,
     state_machine::{StateMachine, StateMachineImpl},
 };
@@ -31,6 +32,7 @@ use ic_replicated_state::{
 };
 use ic_types::{
     batch::Batch,
+    canister_http::CanisterHttpLimits,
     ingress::IngressStatus,
     messages::MessageId,
     registry::RegistryClientError,
@@ -521,6 +523,8 @@ impl BatchProcessorImpl {
                 get_subnet_public_key(Arc::clone(&self.registry), *subnet_id, registry_version)?;
             let subnet_type = self.get_subnet_type(*subnet_id, registry_version);
             let subnet_features = self.get_subnet_features(*subnet_id, registry_version);
+            let max_reserved_ecdsa_quadruples =
+                self.get_max_reserved_ecdsa_quadruples(*subnet_id, registry_version);
             subnets.insert(
                 *subnet_id,
                 SubnetTopology {
@@ -528,6 +532,7 @@ impl BatchProcessorImpl {
                     nodes,
                     subnet_type,
                     subnet_features,
+                    max_reserved_ecdsa_quadruples,
                 },
             );
         }
@@ -535,11 +540,15 @@ impl BatchProcessorImpl {
         let routing_table_record = self.registry.get_routing_table(registry_version)?;
         let routing_table = routing_table_record.unwrap_or_default();
         let nns_subnet_id = self.get_nns_subnet_id(registry_version);
+        let ecdsa_signing_subnets = self
+            .registry
+            .get_all_ecdsa_signing_subnets(registry_version)?;
 
         Ok(NetworkTopology {
             subnets,
             routing_table: Arc::new(routing_table),
             nns_subnet_id,
+            ecdsa_signing_subnets,
         })
     }
 
@@ -600,6 +609,26 @@ impl BatchProcessorImpl {
         record.features.unwrap_or_default().into()
     }
 
+    fn get_max_reserved_ecdsa_quadruples(
+        &self,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+    ) -> u32 {
+        let record = self.get_subnet_record(subnet_id, registry_version);
+        record
+            .ecdsa_config
+            .map_or(0, |config| config.max_reserved_quadruples)
+    }
+
+    fn get_canister_http_limits(
+        &self,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+    ) -> CanisterHttpLimits {
+        let record = self.get_subnet_record(subnet_id, registry_version);
+        record.canister_http_config.unwrap_or_default().into()
+    }
+
     fn get_max_number_of_canisters(
         &self,
         subnet_id: SubnetId,
@@ -672,6 +701,8 @@ impl BatchProcessor for BatchProcessorImpl {
         let provisional_whitelist = self.get_provisional_whitelist(batch.registry_version);
         let subnet_features =
             self.get_subnet_features(state.metadata.own_subnet_id, batch.registry_version);
+        let canister_http_limits =
+            self.get_canister_http_limits(state.metadata.own_subnet_id, batch.registry_version);
         let max_number_of_canisters =
             self.get_max_number_of_canisters(state.metadata.own_subnet_id, batch.registry_version);
 
@@ -684,6 +715,7 @@ impl BatchProcessor for BatchProcessorImpl {
             batch,
             provisional_whitelist,
             subnet_features,
+            canister_http_limits,
             max_number_of_canisters,
         );
         self.observe_canisters_memory_usage(&state_after_round);
@@ -913,6 +945,7 @@ impl MessageRoutingImpl {
             stream_builder,
             log.clone(),
             Arc::clone(&metrics),
+            Box::new(CanisterHttpDivergenceMetrics::new(metrics_registry)),
         ));
 
         let batch_processor = Box::new(BatchProcessorImpl::new(
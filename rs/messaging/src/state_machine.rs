@@ -1,12 +1,20 @@
 use crate::message_routing::MessageRoutingMetrics;
 use crate::routing::{demux::Demux, stream_builder::StreamBuilder};
-use ic_interfaces::execution_environment::Scheduler;
+use ic_interfaces::{
+    canister_http::CanisterHttpDivergenceReporter, execution_environment::Scheduler,
+};
 use ic_logger::{fatal, ReplicaLogger};
 use ic_metrics::Timer;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_replicated_state::{NetworkTopology, ReplicatedState};
-use ic_types::{batch::Batch, ExecutionRound};
+use ic_types::{
+    batch::Batch,
+    canister_http::{CanisterHttpLimits, CanisterHttpResponse},
+    messages::{Payload, RejectContext, Response},
+    user_error::RejectCode,
+    CanisterId, Cycles, ExecutionRound,
+};
 use std::sync::Arc;
 
 #[cfg(test)]
@@ -24,6 +32,7 @@ pub(crate) trait StateMachine: Send {
         batch: Batch,
         provisional_whitelist: ProvisionalWhitelist,
         subnet_features: SubnetFeatures,
+        canister_http_limits: CanisterHttpLimits,
         max_number_of_canisters: u64,
     ) -> ReplicatedState;
 }
@@ -33,6 +42,7 @@ pub(crate) struct StateMachineImpl {
     stream_builder: Box<dyn StreamBuilder>,
     log: ReplicaLogger,
     metrics: Arc<MessageRoutingMetrics>,
+    canister_http_divergence_reporter: Box<dyn CanisterHttpDivergenceReporter>,
 }
 
 impl StateMachineImpl {
@@ -42,6 +52,7 @@ impl StateMachineImpl {
         stream_builder: Box<dyn StreamBuilder>,
         log: ReplicaLogger,
         metrics: Arc<MessageRoutingMetrics>,
+        canister_http_divergence_reporter: Box<dyn CanisterHttpDivergenceReporter>,
     ) -> Self {
         Self {
             scheduler,
@@ -49,6 +60,84 @@ impl StateMachineImpl {
             stream_builder,
             log,
             metrics,
+            canister_http_divergence_reporter,
+        }
+    }
+
+    /// Records why the responses to the canister http requests in `responses`
+    /// diverged, reports it and rejects the requests through the consensus
+    /// queue, so that execution refunds them like any other response.
+    fn process_canister_http_responses(
+        &self,
+        state: &mut ReplicatedState,
+        responses: Vec<CanisterHttpResponse>,
+    ) {
+        let batch_time = state.metadata.batch_time;
+        for response in responses {
+            match response {
+                CanisterHttpResponse::Divergence(divergence) => {
+                    let report = match state
+                        .metadata
+                        .subnet_call_context_manager
+                        .record_diverged_http_request(&divergence, batch_time)
+                    {
+                        Some(report) => report,
+                        // The request was already answered.
+                        None => continue,
+                    };
+                    self.canister_http_divergence_reporter
+                        .report_divergence(&report);
+                    state.consensus_queue.push(Response {
+                        originator: CanisterId::ic_00(),
+                        respondent: CanisterId::ic_00(),
+                        originator_reply_callback: divergence.id(),
+                        refund: Cycles::zero(),
+                        response_payload: Payload::Reject(RejectContext {
+                            code: RejectCode::SysTransient,
+                            message: format!(
+                                "No consensus could be reached on the response to {}: the replicas signed {} different responses.",
+                                report.url, report.distinct_responses
+                            ),
+                        }),
+                    });
+                }
+                // The content of a response with consensus does not carry
+                // the reply yet, and timed out requests are not delivered
+                // through batches.
+                CanisterHttpResponse::WithConsensus(_) | CanisterHttpResponse::Timeout(_) => {}
+            }
+        }
+    }
+
+    /// Rejects the canister http requests that have timed out at the batch
+    /// time through the consensus queue, so that execution refunds them like
+    /// any other response. Requests that are already answered in the
+    /// consensus queue are left to that answer.
+    fn reject_timed_out_canister_http_requests(&self, state: &mut ReplicatedState) {
+        let timed_out = state
+            .metadata
+            .subnet_call_context_manager
+            .timed_out_http_requests(state.metadata.batch_time);
+        for callback_id in timed_out {
+            if state
+                .consensus_queue
+                .iter()
+                .any(|response| response.originator_reply_callback == callback_id)
+            {
+                continue;
+            }
+            state.consensus_queue.push(Response {
+                originator: CanisterId::ic_00(),
+                respondent: CanisterId::ic_00(),
+                originator_reply_callback: callback_id,
+                refund: Cycles::zero(),
+                response_payload: Payload::Reject(RejectContext {
+                    code: RejectCode::SysTransient,
+                    message:
+                        "The canister http request timed out before its response reached consensus."
+                            .to_string(),
+                }),
+            });
         }
     }
 
@@ -70,6 +159,7 @@ impl StateMachine for StateMachineImpl {
         batch: Batch,
         provisional_whitelist: ProvisionalWhitelist,
         subnet_features: SubnetFeatures,
+        canister_http_limits: CanisterHttpLimits,
         max_number_of_canisters: u64,
     ) -> ReplicatedState {
         let phase_timer = Timer::start();
@@ -78,6 +168,7 @@ impl StateMachine for StateMachineImpl {
         metadata.batch_time = batch.time;
         metadata.network_topology = network_topology;
         metadata.own_subnet_features = subnet_features;
+        metadata.own_subnet_canister_http_limits = canister_http_limits;
         state.set_system_metadata(metadata);
 
         // Preprocess messages and add messages to the induction pool through the Demux.
@@ -90,6 +181,11 @@ impl StateMachine for StateMachineImpl {
             )
         }
         state_with_messages.consensus_queue = batch.consensus_responses;
+        self.process_canister_http_responses(
+            &mut state_with_messages,
+            batch.canister_http_responses,
+        );
+        self.reject_timed_out_canister_http_requests(&mut state_with_messages);
         self.observe_phase_duration(PHASE_INDUCTION, &phase_timer);
 
         let phase_timer = Timer::start();
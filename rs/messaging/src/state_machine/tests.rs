@@ -1,7 +1,7 @@
 use super::*;
 use crate::{
-    routing::demux::MockDemux, routing::stream_builder::MockStreamBuilder,
-    state_machine::StateMachineImpl,
+    canister_http::CanisterHttpDivergenceMetrics, routing::demux::MockDemux,
+    routing::stream_builder::MockStreamBuilder, state_machine::StateMachineImpl,
 };
 use ic_interfaces::{execution_environment::Scheduler, state_manager::StateManager};
 use ic_metrics::MetricsRegistry;
@@ -9,14 +9,18 @@ use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{ReplicatedState, SubnetTopology};
 use ic_test_utilities::{
+    mock_time,
     state_manager::FakeStateManager,
     types::batch::{BatchBuilder, IngressPayloadBuilder, PayloadBuilder},
-    types::ids::subnet_test_id,
-    types::messages::SignedIngressBuilder,
+    types::ids::{canister_test_id, subnet_test_id},
+    types::messages::{RequestBuilder, SignedIngressBuilder},
     with_test_replica_logger,
 };
+use ic_types::canister_http::{
+    CanisterHttpRequestContext, CanisterHttpRequestDivergence, HttpMethodType,
+};
 use ic_types::crypto::canister_threshold_sig::MasterEcdsaPublicKey;
-use ic_types::messages::SignedIngress;
+use ic_types::messages::{CallbackId, SignedIngress};
 use ic_types::{Height, PrincipalId, SubnetId};
 use mockall::{mock, predicate::*, Sequence};
 use std::collections::{BTreeMap, BTreeSet};
@@ -46,6 +50,7 @@ struct StateMachineTestFixture {
     initial_state: ReplicatedState,
     network_topology: NetworkTopology,
     metrics: Arc<MessageRoutingMetrics>,
+    canister_http_divergence_metrics: Box<dyn CanisterHttpDivergenceReporter>,
 }
 
 /// Returns a test fixture for state machine tests with Mocks for Demux,
@@ -58,6 +63,8 @@ fn test_fixture(provided_batch: &Batch) -> StateMachineTestFixture {
     let (_height, initial_state) = state_manager.take_tip();
     let metrics_registry = MetricsRegistry::new();
     let metrics = Arc::new(MessageRoutingMetrics::new(&metrics_registry));
+    let canister_http_divergence_metrics =
+        Box::new(CanisterHttpDivergenceMetrics::new(&metrics_registry));
 
     let round = ExecutionRound::from(initial_height.get() + 1);
     let provisional_whitelist = ProvisionalWhitelist::Set(BTreeSet::new());
@@ -104,6 +111,7 @@ fn test_fixture(provided_batch: &Batch) -> StateMachineTestFixture {
             nodes: BTreeMap::new(),
             subnet_type: SubnetType::Application,
             subnet_features: SubnetFeatures::default(),
+            max_reserved_ecdsa_quadruples: 0,
         },
     );
 
@@ -111,6 +119,7 @@ fn test_fixture(provided_batch: &Batch) -> StateMachineTestFixture {
         subnets,
         routing_table: Default::default(),
         nns_subnet_id: SubnetId::from(PrincipalId::new_subnet_test_id(0)),
+        ecdsa_signing_subnets: BTreeMap::new(),
     };
 
     StateMachineTestFixture {
@@ -120,6 +129,7 @@ fn test_fixture(provided_batch: &Batch) -> StateMachineTestFixture {
         initial_state,
         network_topology,
         metrics,
+        canister_http_divergence_metrics,
     }
 }
 
@@ -142,6 +152,7 @@ fn state_machine_populates_network_topology() {
             fixture.stream_builder,
             log,
             fixture.metrics,
+            fixture.canister_http_divergence_metrics,
         ));
 
         assert_ne!(
@@ -155,6 +166,7 @@ fn state_machine_populates_network_topology() {
             provided_batch,
             ProvisionalWhitelist::Set(BTreeSet::new()),
             Default::default(),
+            Default::default(),
             MAX_NUMBER_OF_CANISTERS,
         );
 
@@ -174,6 +186,7 @@ fn test_delivered_batch(provided_batch: Batch) {
             fixture.stream_builder,
             log,
             fixture.metrics,
+            fixture.canister_http_divergence_metrics,
         ));
 
         let _state_after = state_machine.execute_round(
@@ -182,6 +195,7 @@ fn test_delivered_batch(provided_batch: Batch) {
             provided_batch,
             ProvisionalWhitelist::Set(BTreeSet::new()),
             Default::default(),
+            Default::default(),
             MAX_NUMBER_OF_CANISTERS,
         );
     });
@@ -218,3 +232,131 @@ fn test_delivered_batch_interface() {
         param_batch_test(Height::from(27), i);
     }
 }
+
+#[test]
+fn timed_out_canister_http_requests_are_rejected_once() {
+    let mut fixture = test_fixture(&BatchBuilder::new().batch_number(Height::new(1)).build());
+    let manager = &mut fixture.initial_state.metadata.subnet_call_context_manager;
+    let mut push_http_request = |timeout| {
+        manager.push_http_request(CanisterHttpRequestContext {
+            request: RequestBuilder::new().sender(canister_test_id(1)).build(),
+            url: "https://example.com".to_string(),
+            body: None,
+            http_method: HttpMethodType::GET,
+            transform_method_name: None,
+            time: mock_time(),
+            timeout,
+            max_response_bytes: 0,
+            fee_per_response_byte: Cycles::zero(),
+        })
+    };
+    let timed_out = push_http_request(mock_time());
+    let diverged = push_http_request(mock_time());
+    let pending = push_http_request(mock_time() + std::time::Duration::from_secs(1));
+    let provided_batch = BatchBuilder::new()
+        .batch_number(Height::new(1))
+        .time(mock_time())
+        .canister_http_responses(vec![CanisterHttpResponse::Divergence(
+            CanisterHttpRequestDivergence::new(diverged, vec![]),
+        )])
+        .build();
+
+    with_test_replica_logger(|log| {
+        let state_machine = Box::new(StateMachineImpl::new(
+            fixture.scheduler,
+            fixture.demux,
+            fixture.stream_builder,
+            log,
+            fixture.metrics,
+            fixture.canister_http_divergence_metrics,
+        ));
+
+        let state = state_machine.execute_round(
+            fixture.initial_state,
+            NetworkTopology::default(),
+            provided_batch,
+            ProvisionalWhitelist::Set(BTreeSet::new()),
+            Default::default(),
+            Default::default(),
+            MAX_NUMBER_OF_CANISTERS,
+        );
+
+        let rejected: Vec<CallbackId> = state
+            .consensus_queue
+            .iter()
+            .map(|response| response.originator_reply_callback)
+            .collect();
+        assert_eq!(rejected, vec![diverged, timed_out]);
+        assert!(!rejected.contains(&pending));
+        for response in state.consensus_queue.iter() {
+            match &response.response_payload {
+                Payload::Reject(reject) => assert_eq!(reject.code, RejectCode::SysTransient),
+                payload => panic!("Unexpected payload {:?}", payload),
+            }
+        }
+    });
+}
+
+#[test]
+fn diverged_canister_http_requests_are_recorded_and_rejected() {
+    let mut fixture = test_fixture(&BatchBuilder::new().batch_number(Height::new(1)).build());
+    let callback_id = fixture
+        .initial_state
+        .metadata
+        .subnet_call_context_manager
+        .push_http_request(CanisterHttpRequestContext {
+            request: RequestBuilder::new().sender(canister_test_id(1)).build(),
+            url: "https://example.com".to_string(),
+            body: None,
+            http_method: HttpMethodType::GET,
+            transform_method_name: None,
+            time: mock_time(),
+            timeout: mock_time(),
+            max_response_bytes: 0,
+            fee_per_response_byte: Cycles::zero(),
+        });
+    let provided_batch = BatchBuilder::new()
+        .batch_number(Height::new(1))
+        .canister_http_responses(vec![CanisterHttpResponse::Divergence(
+            CanisterHttpRequestDivergence::new(callback_id, vec![]),
+        )])
+        .build();
+
+    with_test_replica_logger(|log| {
+        let state_machine = Box::new(StateMachineImpl::new(
+            fixture.scheduler,
+            fixture.demux,
+            fixture.stream_builder,
+            log,
+            fixture.metrics,
+            fixture.canister_http_divergence_metrics,
+        ));
+
+        let state = state_machine.execute_round(
+            fixture.initial_state,
+            NetworkTopology::default(),
+            provided_batch,
+            ProvisionalWhitelist::Set(BTreeSet::new()),
+            Default::default(),
+            Default::default(),
+            MAX_NUMBER_OF_CANISTERS,
+        );
+
+        let reports: Vec<_> = state
+            .metadata
+            .subnet_call_context_manager
+            .http_request_divergences(&canister_test_id(1))
+            .map(|report| report.request_id)
+            .collect();
+        assert_eq!(reports, vec![callback_id]);
+        assert_eq!(state.consensus_queue.len(), 1);
+        assert_eq!(
+            state.consensus_queue[0].originator_reply_callback,
+            callback_id
+        );
+        match &state.consensus_queue[0].response_payload {
+            Payload::Reject(reject) => assert_eq!(reject.code, RejectCode::SysTransient),
+            payload => panic!("Unexpected payload {:?}", payload),
+        }
+    });
+}
@@ -0,0 +1,6 @@
+use std::io::Result;
+fn main() -> Result<()> {
+    tonic_build::compile_protos("src/proto.proto")?;
+
+    Ok(())
+}
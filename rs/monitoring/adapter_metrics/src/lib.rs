@@ -0,0 +1,157 @@
+//! Relaying of the metrics of adapter processes through the replica.
+//!
+//! Adapters run as separate processes next to the replica and only talk to
+//! the replica over unix domain sockets. Instead of exposing one more
+//! scrapable port on the host for each adapter, an adapter serves its metrics
+//! over the gRPC service it already exposes to the replica, using an
+//! [`AdapterMetricsExporter`], and the replica relays them on its own
+//! metrics endpoint with an [`AdapterMetricsRelay`] registered in its
+//! [`MetricsRegistry`].
+//!
+//! The metric families of an adapter are namespaced by the name of the
+//! adapter, e.g. the family `requests_total` of the bitcoin adapter is
+//! exported by the replica as `bitcoin_adapter_requests_total`.
+use ic_metrics::MetricsRegistry;
+use prometheus::{
+    core::{Collector, Desc},
+    proto::MetricFamily,
+    IntGauge,
+};
+use proto::{
+    adapter_metrics_client::AdapterMetricsClient, adapter_metrics_server::AdapterMetrics,
+    ScrapeMetricsRequest, ScrapeMetricsResponse,
+};
+use protobuf::Message;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tonic::{transport::Channel, Request, Response, Status};
+
+/// This module contains the protobuf structs to relay the metrics of an
+/// adapter to the replica.
+pub mod proto {
+    tonic::include_proto!("adapter_metrics");
+}
+
+/// The interval at which the replica fetches the metrics of an adapter.
+pub const DEFAULT_SCRAPE_INTERVAL: Duration = Duration::from_secs(10);
+/// The time after which fetching the metrics of an adapter is abandoned.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the metrics gathered in a [`MetricsRegistry`] of an adapter.
+pub struct AdapterMetricsExporter {
+    metrics_registry: MetricsRegistry,
+}
+
+impl AdapterMetricsExporter {
+    pub fn new(metrics_registry: MetricsRegistry) -> Self {
+        Self { metrics_registry }
+    }
+}
+
+#[tonic::async_trait]
+impl AdapterMetrics for AdapterMetricsExporter {
+    async fn scrape_metrics(
+        &self,
+        _request: Request<ScrapeMetricsRequest>,
+    ) -> Result<Response<ScrapeMetricsResponse>, Status> {
+        let metric_families = self
+            .metrics_registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .map(|family| family.write_to_bytes())
+            .collect::<Result<_, _>>()
+            .map_err(|e| Status::internal(format!("Failed to encode metrics: {}", e)))?;
+        Ok(Response::new(ScrapeMetricsResponse { metric_families }))
+    }
+}
+
+/// A [`Collector`] of the metrics of an adapter, for the registry of the
+/// replica.
+///
+/// The metrics are fetched from the adapter every `scrape_interval` in a task
+/// spawned on the given runtime, so the metrics endpoint of the replica never
+/// waits for an adapter. While the adapter is unreachable, none of its metrics
+/// are exported, and the gauge `replica_<adapter>_adapter_metrics_up` is 0.
+#[derive(Clone)]
+pub struct AdapterMetricsRelay {
+    metric_families: Arc<RwLock<Vec<MetricFamily>>>,
+    up: IntGauge,
+}
+
+impl AdapterMetricsRelay {
+    /// Creates the relay of the metrics of the adapter named `adapter` that is
+    /// reachable over `channel`.
+    pub fn new(
+        adapter: &str,
+        channel: Channel,
+        rt_handle: &tokio::runtime::Handle,
+        scrape_interval: Duration,
+    ) -> Self {
+        let up = IntGauge::new(
+            format!("replica_{}_adapter_metrics_up", adapter),
+            format!(
+                "1 if the last scrape of the metrics of the {} adapter succeeded, 0 otherwise.",
+                adapter
+            ),
+        )
+        .expect("adapter name should be a valid metric name");
+        let relay = Self {
+            metric_families: Arc::new(RwLock::new(vec![])),
+            up,
+        };
+        let namespace = format!("{}_adapter", adapter);
+        let metric_families = Arc::clone(&relay.metric_families);
+        let up = relay.up.clone();
+        rt_handle.spawn(async move {
+            let mut client = AdapterMetricsClient::new(channel);
+            loop {
+                let scraped = tokio::time::timeout(
+                    SCRAPE_TIMEOUT,
+                    client.scrape_metrics(ScrapeMetricsRequest {}),
+                )
+                .await;
+                let families = match scraped {
+                    Ok(Ok(response)) => {
+                        up.set(1);
+                        decode_metric_families(&namespace, response.into_inner())
+                    }
+                    _ => {
+                        up.set(0);
+                        vec![]
+                    }
+                };
+                *metric_families.write().unwrap() = families;
+                tokio::time::sleep(scrape_interval).await;
+            }
+        });
+        relay
+    }
+}
+
+impl Collector for AdapterMetricsRelay {
+    fn desc(&self) -> Vec<&Desc> {
+        self.up.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut families = self.up.collect();
+        families.extend(self.metric_families.read().unwrap().iter().cloned());
+        families
+    }
+}
+
+/// Decodes the metric families of a scrape and prefixes their names with
+/// `namespace`. Families that fail to decode are dropped.
+fn decode_metric_families(namespace: &str, response: ScrapeMetricsResponse) -> Vec<MetricFamily> {
+    response
+        .metric_families
+        .iter()
+        .filter_map(|bytes| protobuf::parse_from_bytes::<MetricFamily>(bytes).ok())
+        .map(|mut family| {
+            let name = format!("{}_{}", namespace, family.get_name());
+            family.set_name(name);
+            family
+        })
+        .collect()
+}
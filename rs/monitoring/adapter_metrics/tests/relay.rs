@@ -0,0 +1,133 @@
+use ic_adapter_metrics::{
+    proto::adapter_metrics_server::AdapterMetricsServer, AdapterMetricsExporter,
+    AdapterMetricsRelay,
+};
+use ic_metrics::MetricsRegistry;
+use prometheus::proto::MetricFamily;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::net::{UnixListener, UnixStream};
+use tonic::transport::{Channel, Endpoint, Server, Uri};
+use tower::service_fn;
+
+const SCRAPE_INTERVAL: Duration = Duration::from_millis(10);
+
+fn serve_adapter_metrics(uds_path: &Path, metrics_registry: MetricsRegistry) {
+    let listener = UnixListener::bind(uds_path).expect("failed to bind socket");
+    let incoming = async_stream::stream! {
+        loop {
+            yield listener
+                .accept()
+                .await
+                .map(|(stream, _)| ic_async_utils::UnixStream(stream));
+        }
+    };
+    tokio::spawn(
+        Server::builder()
+            .add_service(AdapterMetricsServer::new(AdapterMetricsExporter::new(
+                metrics_registry,
+            )))
+            .serve_with_incoming(incoming),
+    );
+}
+
+fn channel(uds_path: PathBuf) -> Channel {
+    Endpoint::try_from("http://[::]:50051")
+        .unwrap()
+        .connect_with_connector_lazy(service_fn(move |_: Uri| {
+            UnixStream::connect(uds_path.clone())
+        }))
+        .unwrap()
+}
+
+fn family(registry: &MetricsRegistry, name: &str) -> Option<MetricFamily> {
+    registry
+        .prometheus_registry()
+        .gather()
+        .into_iter()
+        .find(|family| family.get_name() == name)
+}
+
+/// Waits until the family `name` is gathered from `registry` and satisfies
+/// `condition`.
+async fn await_family<F: Fn(&MetricFamily) -> bool>(
+    registry: &MetricsRegistry,
+    name: &str,
+    condition: F,
+) -> MetricFamily {
+    for _ in 0..500 {
+        if let Some(family) = family(registry, name).filter(|family| condition(family)) {
+            return family;
+        }
+        tokio::time::sleep(SCRAPE_INTERVAL).await;
+    }
+    panic!("Metric family {} was not relayed", name);
+}
+
+#[tokio::test]
+async fn should_relay_namespaced_adapter_metrics() {
+    let dir = tempfile::tempdir().unwrap();
+    let uds_path = dir.path().join("adapter.socket");
+    let adapter_registry = MetricsRegistry::new();
+    let requests = adapter_registry.int_counter("requests_total", "Number of requests.");
+    requests.inc_by(3);
+    serve_adapter_metrics(&uds_path, adapter_registry);
+
+    let replica_registry = MetricsRegistry::new();
+    replica_registry.register(AdapterMetricsRelay::new(
+        "test",
+        channel(uds_path),
+        &tokio::runtime::Handle::current(),
+        SCRAPE_INTERVAL,
+    ));
+
+    let relayed = await_family(&replica_registry, "test_adapter_requests_total", |_| true).await;
+    assert_eq!(relayed.get_metric()[0].get_counter().get_value(), 3.0);
+    let up = family(&replica_registry, "replica_test_adapter_metrics_up").unwrap();
+    assert_eq!(up.get_metric()[0].get_gauge().get_value(), 1.0);
+    assert!(family(&replica_registry, "requests_total").is_none());
+
+    // Subsequent scrapes pick up changes of the adapter metrics.
+    requests.inc();
+    await_family(&replica_registry, "test_adapter_requests_total", |family| {
+        family.get_metric()[0].get_counter().get_value() == 4.0
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn should_report_unreachable_adapter() {
+    let dir = tempfile::tempdir().unwrap();
+    let replica_registry = MetricsRegistry::new();
+    let relay = AdapterMetricsRelay::new(
+        "test",
+        channel(dir.path().join("missing.socket")),
+        &tokio::runtime::Handle::current(),
+        SCRAPE_INTERVAL,
+    );
+    replica_registry.register(relay);
+    // A second adapter can be relayed into the same registry.
+    replica_registry.register(AdapterMetricsRelay::new(
+        "other",
+        channel(dir.path().join("other.socket")),
+        &tokio::runtime::Handle::current(),
+        SCRAPE_INTERVAL,
+    ));
+
+    // Let the relay attempt a scrape.
+    tokio::time::sleep(10 * SCRAPE_INTERVAL).await;
+
+    let families = replica_registry.prometheus_registry().gather();
+    let names: Vec<_> = families.iter().map(|family| family.get_name()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "replica_other_adapter_metrics_up",
+            "replica_test_adapter_metrics_up"
+        ]
+    );
+    for family in families.iter() {
+        assert_eq!(family.get_metric()[0].get_gauge().get_value(), 0.0);
+    }
+}
@@ -61,6 +61,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                canister_http_config: None,
             };
 
             let key = make_subnet_record_key(subnet_id);
@@ -109,6 +110,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 max_instructions_per_install_code: None,
                 features: None,
                 ecdsa_config: None,
+                canister_http_config: None,
                 max_number_of_canisters: Some(200),
                 ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
                 ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -181,6 +183,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                     ssh_readonly_access: vec!["pub_key_0".to_string()],
                     ssh_backup_access: vec!["pub_key_1".to_string()],
                     ecdsa_config: None,
+                    canister_http_config: None,
                 }
             );
             Ok(())
@@ -537,6 +537,7 @@ pub fn prepare_add_node_payload() -> (AddNodePayload, NodePublicKeys, NodeId) {
         node_signing_pk,
         committee_signing_pk,
         ni_dkg_dealing_encryption_pk,
+        idkg_dealing_encryption_pk: None,
         transport_tls_cert,
         xnet_endpoint: "128.0.0.1:1234".to_string(),
         http_endpoint: "128.0.0.1:8123".to_string(),
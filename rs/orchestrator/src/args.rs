@@ -49,6 +49,12 @@ pub struct OrchestratorArgs {
     /// Print the replica's current node ID.
     #[structopt(long)]
     pub node_id: bool,
+
+    /// Print whether the node holds a valid secret key for the iDKG dealing
+    /// encryption key registered for it in the local registry, without
+    /// revealing the key, and exit with a non-zero code if it does not.
+    #[structopt(long)]
+    pub check_idkg_dealing_encryption_key: bool,
 }
 
 impl OrchestratorArgs {
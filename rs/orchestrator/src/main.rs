@@ -12,6 +12,13 @@ async fn main() {
         return;
     }
 
+    if args.check_idkg_dealing_encryption_key {
+        if !Orchestrator::check_idkg_dealing_encryption_key(args) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut orchestrator = Orchestrator::new(args)
         .await
         .expect("Failed to start orchestrator");
@@ -9,11 +9,23 @@ pub struct OrchestratorMetrics {
     /// Registry version last used to succesfully fetch datacenter information
     pub datacenter_registry_version: IntGauge,
     pub ssh_access_registry_version: IntGauge,
+    /// 1 if the node holds a valid secret key for the iDKG dealing encryption
+    /// key registered in the registry, 0 if it does not, and -1 before the
+    /// first check
+    pub idkg_dealing_encryption_key_status: IntGauge,
+    /// Registry version last used to check the iDKG dealing encryption key
+    pub idkg_dealing_encryption_key_registry_version: IntGauge,
+    /// Number of rotated iDKG dealing encryption keys that were registered
+    pub idkg_dealing_encryption_key_rotations: IntCounter,
+    /// 1 if the replica reports that it is healthy, 0 otherwise
+    pub replica_healthy: IntGauge,
+    /// Height of the latest certified state the replica reported
+    pub replica_certified_height: IntGauge,
 }
 
 impl OrchestratorMetrics {
     pub fn new(metrics_registry: &ic_metrics::MetricsRegistry) -> Self {
-        Self {
+        let metrics = Self {
             heart_beat_count: metrics_registry.int_counter(
                 "replica_heart_beat_count",
                 "Number of times a process heart beat has been observed for the Subnet Replica",
@@ -30,6 +42,28 @@ impl OrchestratorMetrics {
                 "shh_access_registry_version",
                 "Registry version last used to update the SSH public keys",
             ),
-        }
+            idkg_dealing_encryption_key_status: metrics_registry.int_gauge(
+                "orchestrator_idkg_dealing_encryption_key_status",
+                "1 if the node holds a valid secret key for its iDKG dealing encryption key in the registry, 0 if not, -1 if not yet checked",
+            ),
+            idkg_dealing_encryption_key_registry_version: metrics_registry.int_gauge(
+                "orchestrator_idkg_dealing_encryption_key_registry_version",
+                "Registry version last used to check the iDKG dealing encryption key",
+            ),
+            idkg_dealing_encryption_key_rotations: metrics_registry.int_counter(
+                "orchestrator_idkg_dealing_encryption_key_rotations_total",
+                "Number of rotated iDKG dealing encryption keys that were registered",
+            ),
+            replica_healthy: metrics_registry.int_gauge(
+                "orchestrator_replica_healthy",
+                "1 if the replica reports that it is healthy on its status endpoint, 0 if it reports another health status or cannot be reached",
+            ),
+            replica_certified_height: metrics_registry.int_gauge(
+                "orchestrator_replica_certified_height",
+                "Height of the latest certified state the replica reported on its status endpoint",
+            ),
+        };
+        metrics.idkg_dealing_encryption_key_status.set(-1);
+        metrics
     }
 }
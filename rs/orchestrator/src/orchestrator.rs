@@ -15,21 +15,28 @@ use ic_config::{
 };
 use ic_crypto::utils::get_node_keys_or_generate_if_missing;
 use ic_crypto_tls_interfaces::TlsHandshake;
-use ic_interfaces::{crypto::KeyManager, registry::RegistryClient};
+use ic_interfaces::{
+    crypto::{BasicSigner, KeyManager},
+    registry::RegistryClient,
+};
 use ic_logger::{error, info, new_replica_logger, warn, LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_metrics_exporter::MetricsRuntimeImpl;
 use ic_registry_replicator::RegistryReplicator;
-use ic_types::{ReplicaVersion, SubnetId};
+use ic_replica_status::fetch_status;
+use ic_types::{messages::MessageId, ReplicaVersion, SubnetId};
 use slog_async::AsyncGuard;
 use std::env;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::{convert::TryFrom, time::Duration};
 use tokio::{sync::RwLock, task::JoinHandle};
+use url::Url;
 
 const CHECK_INTERVAL_SECS: Duration = Duration::from_secs(10);
+/// The timeout of the requests to the status endpoint of the replica.
+const REPLICA_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct Orchestrator {
     pub logger: ReplicaLogger,
@@ -38,6 +45,10 @@ pub struct Orchestrator {
     upgrade: Option<Upgrade>,
     firewall: Option<Firewall>,
     ssh_access_manager: Option<SshAccessManager>,
+    registration: Option<NodeRegistration>,
+    metrics: Arc<OrchestratorMetrics>,
+    // The URL of the public API of the replica run by the orchestrator.
+    replica_url: Url,
     // A flag used to communicate to async tasks, that their job is done.
     exit_signal: Arc<RwLock<bool>>,
     // The subnet id of the node.
@@ -46,6 +57,20 @@ pub struct Orchestrator {
     task_handles: Vec<JoinHandle<()>>,
 }
 
+// Returns the URL of the public API of the replica listening on
+// `listen_addr`. A replica listening on all interfaces is reached through the
+// loopback interface.
+fn replica_url(listen_addr: SocketAddr) -> Url {
+    let mut addr = listen_addr;
+    if addr.ip().is_unspecified() {
+        match addr {
+            SocketAddr::V4(_) => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+            SocketAddr::V6(_) => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+        }
+    }
+    Url::parse(&format!("http://{}/", addr)).expect("failed to build the replica URL")
+}
+
 // Loads the replica version from the file specified as argument on
 // orchestrator's start.
 fn load_version_from_file(logger: &ReplicaLogger, path: &Path) -> Result<ReplicaVersion, ()> {
@@ -119,7 +144,9 @@ impl Orchestrator {
             logger.clone(),
             config.clone(),
             Arc::clone(&registry_client),
-            Arc::clone(&crypto) as Arc<dyn KeyManager>,
+            Arc::clone(&crypto) as Arc<dyn KeyManager + Send + Sync>,
+            Arc::clone(&crypto) as Arc<dyn BasicSigner<MessageId> + Send + Sync>,
+            node_id,
             registry_local_store.clone(),
         );
 
@@ -185,13 +212,16 @@ impl Orchestrator {
             upgrade,
             firewall,
             ssh_access_manager,
+            registration: Some(registration),
+            metrics,
+            replica_url: replica_url(config.http_handler.listen_addr),
             exit_signal: Default::default(),
             subnet_id: Default::default(),
             task_handles: Default::default(),
         })
     }
 
-    /// Starts two asynchronous tasks:
+    /// Starts four asynchronous tasks:
     ///
     /// 1. One that constantly monitors for a new CUP pointing to a newer
     /// replica version and executes the upgrade to this version if such a
@@ -203,6 +233,13 @@ impl Orchestrator {
     /// new data center is added, orchestrator will generate a new firewall
     /// configuration allowing access from the IP range specified in the DC
     /// record.
+    ///
+    /// 3. Third task regularly checks whether the node holds a valid secret
+    /// key for its iDKG dealing encryption key in the registry and reports
+    /// the result as a metric.
+    ///
+    /// 4. Fourth task regularly fetches the status of the replica and reports
+    /// its health and certified height as metrics.
     pub fn spawn_tasks(&mut self) {
         async fn upgrade_checks(
             maybe_subnet_id: Arc<RwLock<Option<SubnetId>>>,
@@ -243,6 +280,53 @@ impl Orchestrator {
             info!(log, "Shut down the ssh keys & firewall monitoring loop");
         }
 
+        async fn key_material_checks(
+            mut registration: NodeRegistration,
+            metrics: Arc<OrchestratorMetrics>,
+            exit_signal: Arc<RwLock<bool>>,
+            log: ReplicaLogger,
+        ) {
+            while !*exit_signal.read().await {
+                registration.check_idkg_dealing_encryption_key(&metrics);
+                registration
+                    .rotate_idkg_dealing_encryption_key_if_due(&metrics)
+                    .await;
+                tokio::time::sleep(CHECK_INTERVAL_SECS).await;
+            }
+            info!(log, "Shut down the key material monitoring loop");
+        }
+
+        async fn replica_health_checks(
+            replica_url: Url,
+            metrics: Arc<OrchestratorMetrics>,
+            exit_signal: Arc<RwLock<bool>>,
+            log: ReplicaLogger,
+        ) {
+            let mut was_healthy = false;
+            while !*exit_signal.read().await {
+                // The replica is not running while the node is unassigned, in
+                // which case it is reported as unhealthy.
+                let status = fetch_status(&replica_url, REPLICA_STATUS_TIMEOUT).await;
+                let is_healthy = matches!(&status, Ok(status) if status.is_healthy());
+                if let Ok(Some(height)) = status.as_ref().map(|status| status.certified_height) {
+                    metrics.replica_certified_height.set(height.get() as i64);
+                }
+                metrics.replica_healthy.set(is_healthy as i64);
+                if is_healthy != was_healthy {
+                    match &status {
+                        Ok(status) => info!(
+                            log,
+                            "Replica health status changed to {:?}", status.health_status
+                        ),
+                        Err(err) => info!(log, "Replica status is unavailable: {}", err),
+                    }
+                    was_healthy = is_healthy;
+                }
+                tokio::time::sleep(CHECK_INTERVAL_SECS).await;
+            }
+            info!(log, "Shut down the replica health monitoring loop");
+        }
+
         if let Some(upgrade) = self.upgrade.take() {
             info!(self.logger, "Spawning the upgrade loop");
             self.task_handles.push(tokio::spawn(upgrade_checks(
@@ -268,6 +352,24 @@ impl Orchestrator {
                     self.logger.clone(),
                 )));
         }
+
+        if let Some(registration) = self.registration.take() {
+            info!(self.logger, "Spawning the key material check loop");
+            self.task_handles.push(tokio::spawn(key_material_checks(
+                registration,
+                Arc::clone(&self.metrics),
+                Arc::clone(&self.exit_signal),
+                self.logger.clone(),
+            )));
+        }
+
+        info!(self.logger, "Spawning the replica health check loop");
+        self.task_handles.push(tokio::spawn(replica_health_checks(
+            self.replica_url.clone(),
+            Arc::clone(&self.metrics),
+            Arc::clone(&self.exit_signal),
+            self.logger.clone(),
+        )));
     }
 
     /// Print the replica's current node ID.
@@ -278,6 +380,36 @@ impl Orchestrator {
         println!("{}", node_id);
     }
 
+    /// Print whether the node holds a valid secret key for the iDKG dealing
+    /// encryption key registered for it in the latest version of the local
+    /// registry. Returns whether it does.
+    pub fn check_idkg_dealing_encryption_key(args: OrchestratorArgs) -> bool {
+        let config = args.get_ic_config();
+        let (_node_pks, node_id) = get_node_keys_or_generate_if_missing(&config.crypto.crypto_root);
+        let (logger, _async_log_guard) = Self::get_logger(&config);
+        let registry_client =
+            RegistryReplicator::new_from_config(logger.clone(), Some(node_id), &config)
+                .get_registry_client();
+        let crypto = setup_crypto(&config.crypto, Arc::clone(&registry_client), logger);
+        let latest_version = registry_client.get_latest_version();
+        match crypto.check_idkg_dealing_encryption_key_with_registry(latest_version) {
+            Ok(()) => {
+                println!(
+                    "iDKG dealing encryption key is set up at registry version {}",
+                    latest_version
+                );
+                true
+            }
+            Err(e) => {
+                println!(
+                    "iDKG dealing encryption key is not set up at registry version {}: {}",
+                    latest_version, e
+                );
+                false
+            }
+        }
+    }
+
     /// Shuts down the orchestrator: stops async tasks and the replica process
     pub async fn shutdown(self) {
         info!(self.logger, "Shutting down orchestrator...");
@@ -1,35 +1,51 @@
 #![allow(dead_code)]
 use crate::error::{OrchestratorError, OrchestratorResult};
-use candid::Encode;
-use ic_canister_client::{Agent, Sender};
+use crate::metrics::OrchestratorMetrics;
+use candid::{Decode, Encode};
+use ic_canister_client::{ed25519_public_key_to_der, Agent, Sender};
 use ic_config::{
     http_handler::Config as HttpConfig,
     message_routing::Config as MsgRoutingConfig,
     metrics::{Config as MetricsConfig, Exporter},
     Config,
 };
-use ic_interfaces::crypto::KeyManager;
+use ic_interfaces::crypto::{BasicSigner, KeyManager, DOMAIN_IC_REQUEST};
 use ic_interfaces::registry::{RegistryClient, ZERO_REGISTRY_VERSION};
 use ic_logger::{info, warn, ReplicaLogger};
 use ic_nns_constants::REGISTRY_CANISTER_ID;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
+use ic_registry_client::helper::crypto::CryptoRegistry;
 use ic_registry_common::local_store::LocalStore;
 use ic_sys::utility_command::UtilityCommand;
+use ic_types::crypto::KeyPurpose;
+use ic_types::messages::MessageId;
 use ic_types::transport::TransportConfig;
+use ic_types::{NodeId, RegistryVersion};
 use prost::Message;
 use rand::prelude::*;
 use registry_canister::mutations::do_add_node::AddNodePayload;
+use registry_canister::mutations::do_update_node_directly::UpdateNodeDirectlyPayload;
+use std::convert::TryFrom;
 use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use url::Url;
 
+/// The age after which the iDKG dealing encryption key of a node is rotated.
+const IDKG_DEALING_ENCRYPTION_KEY_ROTATION_PERIOD: Duration =
+    Duration::from_secs(14 * 24 * 60 * 60);
+
 /// Subcomponent used to register this node with the provided NNS.
 pub(crate) struct NodeRegistration {
     log: ReplicaLogger,
     node_config: Config,
     registry_client: Arc<dyn RegistryClient>,
-    key_manager: Arc<dyn KeyManager>,
+    key_manager: Arc<dyn KeyManager + Send + Sync>,
+    signer: Arc<dyn BasicSigner<MessageId> + Send + Sync>,
+    node_id: NodeId,
     local_store: Arc<dyn LocalStore>,
+    /// The rotated iDKG dealing encryption key that is not yet registered.
+    pending_idkg_dealing_encryption_pk: Option<PublicKeyProto>,
 }
 
 impl NodeRegistration {
@@ -37,7 +53,9 @@ impl NodeRegistration {
         log: ReplicaLogger,
         node_config: Config,
         registry_client: Arc<dyn RegistryClient>,
-        key_manager: Arc<dyn KeyManager>,
+        key_manager: Arc<dyn KeyManager + Send + Sync>,
+        signer: Arc<dyn BasicSigner<MessageId> + Send + Sync>,
+        node_id: NodeId,
         local_store: Arc<dyn LocalStore>,
     ) -> Self {
         Self {
@@ -45,7 +63,10 @@ impl NodeRegistration {
             node_config,
             registry_client,
             key_manager,
+            signer,
+            node_id,
             local_store,
+            pending_idkg_dealing_encryption_pk: None,
         }
     }
 
@@ -73,41 +94,9 @@ impl NodeRegistration {
             version = self.registry_client.get_latest_version();
         }
 
-        use ic_registry_client::helper::{node::NodeRegistry, subnet::SubnetRegistry};
-
-        let nns_subnet_id = self
-            .registry_client
-            .get_root_subnet_id(version)
-            .expect("Error when fetching nns subnet id.")
-            .expect("NNS subnet id not defined");
-        let node_ids = self
-            .registry_client
-            .get_node_ids_on_subnet(nns_subnet_id, version)
-            .expect("could not load node ids from nns")
-            .expect("no nodes on the nns subnet");
-
-        let mut nns_urls = node_ids
-            .iter()
-            .map(|nid| {
-                let r = self
-                    .registry_client
-                    .get_transport_info(*nid, version)
-                    .expect("Registration: Fetching NNS node record registry failed.")
-                    .expect("Registration: No NNS node record found in registry.");
-                let http = r.http.expect("no http record");
-                let ip = http.ip_addr;
-                let port = http.port;
-                Url::parse(&format!(
-                    "http://{}/",
-                    get_endpoint(&self.log, ip, port as u16)
-                        .expect("could not parse connection endpoint information")
-                ))
-                .expect("can't fail")
-            })
-            .collect::<Vec<_>>();
-
-        let mut rng = thread_rng();
-        nns_urls.shuffle(&mut rng);
+        let nns_urls = self
+            .nns_urls(version)
+            .unwrap_or_else(|e| panic!("Registration: {}", e));
         let mut nns_urls = nns_urls.iter().cycle();
 
         let sign_cmd = |msg: &[u8]| {
@@ -165,6 +154,43 @@ impl NodeRegistration {
         }
     }
 
+    /// Returns the URLs of the NNS nodes at `version`, in random order.
+    fn nns_urls(&self, version: RegistryVersion) -> Result<Vec<Url>, String> {
+        use ic_registry_client::helper::{node::NodeRegistry, subnet::SubnetRegistry};
+
+        let nns_subnet_id = self
+            .registry_client
+            .get_root_subnet_id(version)
+            .map_err(|e| format!("Error when fetching nns subnet id: {:?}", e))?
+            .ok_or("NNS subnet id not defined")?;
+        let node_ids = self
+            .registry_client
+            .get_node_ids_on_subnet(nns_subnet_id, version)
+            .map_err(|e| format!("could not load node ids from nns: {:?}", e))?
+            .ok_or("no nodes on the nns subnet")?;
+
+        let mut nns_urls = node_ids
+            .iter()
+            .map(|nid| -> Result<Url, String> {
+                let r = self
+                    .registry_client
+                    .get_transport_info(*nid, version)
+                    .map_err(|e| format!("Fetching NNS node record registry failed: {:?}", e))?
+                    .ok_or("No NNS node record found in registry.")?;
+                let http = r.http.ok_or("no http record")?;
+                let endpoint =
+                    get_endpoint(&self.log, http.ip_addr, http.port as u16).map_err(|e| {
+                        format!("could not parse connection endpoint information: {}", e)
+                    })?;
+                Ok(Url::parse(&format!("http://{}/", endpoint)).expect("can't fail"))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut rng = thread_rng();
+        nns_urls.shuffle(&mut rng);
+        Ok(nns_urls)
+    }
+
     fn assemble_add_node_message(&self) -> AddNodePayload {
         let node_pub_keys = self.key_manager.node_public_keys();
 
@@ -175,6 +201,9 @@ impl NodeRegistration {
             ni_dkg_dealing_encryption_pk: protobuf_to_vec(
                 node_pub_keys.dkg_dealing_encryption_pk.unwrap(),
             ),
+            // The I-DKG dealing encryption key is not yet part of the node's
+            // public keys, it is registered separately.
+            idkg_dealing_encryption_pk: None,
             transport_tls_cert: protobuf_to_vec(node_pub_keys.tls_certificate.unwrap()),
 
             xnet_endpoint: msg_routing_config_to_endpoint(
@@ -211,6 +240,151 @@ impl NodeRegistration {
         }
     }
 
+    /// Checks whether the node holds a valid secret key for the iDKG dealing
+    /// encryption key registered for it in the latest registry version, and
+    /// records the outcome in `metrics`. Changes of the outcome are logged.
+    pub(crate) fn check_idkg_dealing_encryption_key(&self, metrics: &OrchestratorMetrics) {
+        let latest_version = self.registry_client.get_latest_version();
+        let status = match self
+            .key_manager
+            .check_idkg_dealing_encryption_key_with_registry(latest_version)
+        {
+            Ok(()) => {
+                if metrics.idkg_dealing_encryption_key_status.get() != 1 {
+                    info!(
+                        self.log,
+                        "iDKG dealing encryption key is set up at version {}", latest_version
+                    );
+                }
+                1
+            }
+            Err(e) => {
+                if metrics.idkg_dealing_encryption_key_status.get() != 0 {
+                    warn!(
+                        self.log,
+                        "iDKG dealing encryption key is not set up at version {}: {:?}",
+                        latest_version,
+                        e
+                    );
+                }
+                0
+            }
+        };
+        metrics.idkg_dealing_encryption_key_status.set(status);
+        metrics
+            .idkg_dealing_encryption_key_registry_version
+            .set(latest_version.get() as i64);
+    }
+
+    /// Rotates the iDKG dealing encryption key of the node once the registered
+    /// one is older than [`IDKG_DEALING_ENCRYPTION_KEY_ROTATION_PERIOD`], or
+    /// has no timestamp.
+    ///
+    /// The new key is registered with an `update_node_directly` request that
+    /// is signed with the node signing key. The request is sent again on each
+    /// call until the new key shows up in the registry. The secret keys of
+    /// the previous registrations are retired by the replica, once no active
+    /// transcript references them anymore.
+    pub(crate) async fn rotate_idkg_dealing_encryption_key_if_due(
+        &mut self,
+        metrics: &OrchestratorMetrics,
+    ) {
+        let latest_version = self.registry_client.get_latest_version();
+        let registered = match self.registry_client.get_crypto_key_for_node(
+            self.node_id,
+            KeyPurpose::IDkgMEGaEncryption,
+            latest_version,
+        ) {
+            Ok(Some(registered)) => registered,
+            // Keys are only rotated once the node registered its first key.
+            _ => return,
+        };
+        let rotated = match self.pending_idkg_dealing_encryption_pk.take() {
+            Some(pending) if pending.key_value == registered.key_value => {
+                info!(
+                    self.log,
+                    "Rotated iDKG dealing encryption key is registered at version {}",
+                    latest_version
+                );
+                metrics.idkg_dealing_encryption_key_rotations.inc();
+                return;
+            }
+            Some(pending) => pending,
+            None if !is_rotation_due(&registered) => return,
+            None => match self.key_manager.rotate_idkg_dealing_encryption_key() {
+                Ok(rotated) => {
+                    info!(self.log, "Generated a new iDKG dealing encryption key");
+                    rotated
+                }
+                Err(e) => {
+                    warn!(
+                        self.log,
+                        "Failed to generate a new iDKG dealing encryption key: {:?}", e
+                    );
+                    return;
+                }
+            },
+        };
+        if let Err(e) = self
+            .register_rotated_idkg_key(&rotated, latest_version)
+            .await
+        {
+            warn!(
+                self.log,
+                "Error when registering the rotated iDKG dealing encryption key: {}", e
+            );
+        }
+        self.pending_idkg_dealing_encryption_pk = Some(rotated);
+    }
+
+    async fn register_rotated_idkg_key(
+        &self,
+        idkg_dealing_encryption_pk: &PublicKeyProto,
+        version: RegistryVersion,
+    ) -> Result<(), String> {
+        let nns_url = self
+            .nns_urls(version)?
+            .into_iter()
+            .next()
+            .ok_or("no NNS node found")?;
+        let node_signing_pk = self
+            .key_manager
+            .node_public_keys()
+            .node_signing_pk
+            .ok_or("no node signing key found")?;
+        let signer = Arc::clone(&self.signer);
+        let node_id = self.node_id;
+        let sign_cmd = move |msg: &[u8]| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            let message_id = msg
+                .strip_prefix(&DOMAIN_IC_REQUEST[..])
+                .and_then(|msg_id| MessageId::try_from(msg_id).ok())
+                .ok_or("message to sign is not a request id")?;
+            signer
+                .sign_basic(&message_id, node_id, version)
+                .map(|signature| signature.get().0)
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
+        };
+        let sender = Sender::ExternalHsm {
+            pub_key: ed25519_public_key_to_der(node_signing_pk.key_value),
+            sign: Arc::new(sign_cmd),
+        };
+        let payload = UpdateNodeDirectlyPayload {
+            idkg_dealing_encryption_pk: Some(protobuf_to_vec(idkg_dealing_encryption_pk.clone())),
+        };
+        let response = Agent::new(nns_url, sender)
+            .execute_update(
+                &REGISTRY_CANISTER_ID,
+                "update_node_directly",
+                Encode!(&payload).expect("Could not encode payload for update_node_directly-call."),
+                generate_nonce(),
+            )
+            .await?
+            .ok_or("no response to update_node_directly-call")?;
+        // The registry replies with `()`, or rejects the call, which is
+        // returned as an error above.
+        Decode!(&response, ()).map_err(|e| format!("Could not decode response: {:?}", e))
+    }
+
     /// Create file that signal the host vm to eject the keycard.
     fn touch_eject_file(&self) {
         if let Err(e) = std::fs::File::create(
@@ -315,6 +489,20 @@ fn generate_nonce() -> Vec<u8> {
         .to_vec()
 }
 
+/// Returns whether the `registered` iDKG dealing encryption key is due for a
+/// rotation.
+fn is_rotation_due(registered: &PublicKeyProto) -> bool {
+    let timestamp = match registered.timestamp {
+        Some(timestamp) => timestamp,
+        None => return true,
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    now.saturating_sub(timestamp) >= IDKG_DEALING_ENCRYPTION_KEY_ROTATION_PERIOD.as_millis() as u64
+}
+
 fn protobuf_to_vec<M: Message>(entry: M) -> Vec<u8> {
     let mut buf: Vec<u8> = Vec::new();
     entry.encode(&mut buf).expect("This must not fail");
@@ -28,7 +28,7 @@ use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
     replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
     routing_table::v1::RoutingTable as PbRoutingTable,
-    subnet::v1::SubnetListRecord,
+    subnet::v1::{EcdsaSigningSubnetList, SubnetListRecord},
     unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
 };
 use ic_protobuf::types::v1::{PrincipalId as PrincipalIdProto, SubnetId as SubnetIdProto};
@@ -38,9 +38,10 @@ use ic_registry_common::{
     proto_registry_data_provider::ProtoRegistryDataProvider,
 };
 use ic_registry_keys::{
-    make_blessed_replica_version_key, make_node_operator_record_key,
-    make_provisional_whitelist_record_key, make_replica_version_key, make_routing_table_record_key,
-    make_subnet_list_record_key, make_unassigned_nodes_config_record_key, ROOT_SUBNET_ID_KEY,
+    make_blessed_replica_version_key, make_ecdsa_signing_subnet_list_key,
+    make_node_operator_record_key, make_provisional_whitelist_record_key, make_replica_version_key,
+    make_routing_table_record_key, make_subnet_list_record_key,
+    make_unassigned_nodes_config_record_key, ROOT_SUBNET_ID_KEY,
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
@@ -202,6 +203,9 @@ pub struct IcConfig {
 
     provisional_whitelist: Option<ProvisionalWhitelist>,
 
+    /// The subnets that may sign with a threshold ECDSA key, per key id.
+    ecdsa_signing_subnets: BTreeMap<String, Vec<SubnetIndex>>,
+
     /// Mutations to apply to the initial Registry
     /// TODO (VER-624): Make ic-prep API orthogonal again
     pub initial_mutations: Vec<RegistryMutation>,
@@ -285,6 +289,13 @@ impl IcConfig {
         self.provisional_whitelist = Some(provisional_whitelist);
     }
 
+    /// Restricts signing with the threshold ECDSA key `key_id` to the subnets
+    /// with the given indices.
+    pub fn set_ecdsa_signing_subnets(&mut self, key_id: &str, subnet_indices: Vec<SubnetIndex>) {
+        self.ecdsa_signing_subnets
+            .insert(key_id.to_string(), subnet_indices);
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new<P: AsRef<Path>>(
         target_dir: P,
@@ -309,6 +320,7 @@ impl IcConfig {
             initial_release_package_sha256_hex: release_package_sha256_hex,
             initial_registry_node_operator_entries: Vec::new(),
             provisional_whitelist,
+            ecdsa_signing_subnets: BTreeMap::new(),
             initial_mutations: Vec::new(),
             initial_node_operator,
             initial_node_provider,
@@ -432,6 +444,29 @@ impl IcConfig {
             routing_table_record,
         );
 
+        for (key_id, subnet_indices) in &self.ecdsa_signing_subnets {
+            let signing_subnet_list = EcdsaSigningSubnetList {
+                subnets: subnet_indices
+                    .iter()
+                    .map(|subnet_index| SubnetIdProto {
+                        principal_id: Some(PrincipalIdProto {
+                            raw: initialized_topology[subnet_index]
+                                .subnet_id
+                                .get()
+                                .into_vec(),
+                        }),
+                    })
+                    .collect(),
+            };
+            write_registry_entry(
+                &data_provider,
+                self.target_dir.as_path(),
+                &make_ecdsa_signing_subnet_list_key(key_id),
+                version,
+                signing_subnet_list,
+            );
+        }
+
         let replica_version_record = ReplicaVersionRecord {
             release_package_url: self
                 .initial_release_package_url
@@ -10,7 +10,7 @@ use thiserror::Error;
 
 use crate::util::{write_proto_to_file_raw, write_registry_entry};
 use ic_crypto::utils::{
-    generate_idkg_dealing_encryption_keys, get_node_keys_or_generate_if_missing,
+    generate_idkg_dealing_encryption_keys_with_pop, get_node_keys_or_generate_if_missing,
 };
 use ic_protobuf::{
     crypto::v1::NodePublicKeys,
@@ -426,7 +426,8 @@ impl NodeSecretKeyStore {
         let (node_pks, node_id) = get_node_keys_or_generate_if_missing(&path);
         // CRP-1273: Remove the following call when the encryption keys are generated
         // together with the rest of the node keys.
-        let idkg_mega_encryption_pubkey = generate_idkg_dealing_encryption_keys(&path);
+        let idkg_mega_encryption_pubkey =
+            generate_idkg_dealing_encryption_keys_with_pop(&path, node_id);
 
         use prost::Message;
         let node_pks = node_pks.encode_to_vec();
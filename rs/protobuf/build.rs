@@ -229,6 +229,14 @@ fn build_registry_proto() {
         ".registry.subnet.v1.EcdsaConfig",
         "#[derive(candid::CandidType, Eq)]",
     );
+    config.type_attribute(
+        ".registry.subnet.v1.CanisterHttpConfig",
+        "#[derive(candid::CandidType, Eq)]",
+    );
+    config.type_attribute(
+        ".registry.subnet.v1.CanisterHttpPricing",
+        "#[derive(candid::CandidType, Eq)]",
+    );
     config.type_attribute(
         ".registry.replica_version",
         "#[derive(serde::Serialize, serde::Deserialize)]",
@@ -0,0 +1,134 @@
+//! Backwards compatibility tests of the messages exchanged between the replica
+//! and the adapters.
+//!
+//! The replica and the adapters are released independently, so a replica may
+//! talk to an adapter built from an older or a newer version of the protobuf
+//! definitions. Every test checks that a message with all fields set encodes to
+//! the bytes of a fixture checked in under `adapter_api_fixtures`, and that the
+//! fixture decodes to the same message.
+//!
+//! A failing test means that the wire format of the message changed, e.g. a
+//! field number or type. Such changes break the replica↔adapter boundary and
+//! must be done by adding new fields or messages instead. Adding a field is
+//! compatible: set it to its default value in the message of the test, so that
+//! the fixture remains unchanged.
+use ic_protobuf::{bitcoin::v1 as btc, canister_http::v1 as http};
+use prost::Message;
+use std::fmt::Debug;
+
+/// Asserts that `message` encodes to `fixture` and that `fixture` decodes to
+/// `message`.
+fn assert_wire_compatible<M: Message + Default + PartialEq + Debug>(message: M, fixture: &[u8]) {
+    assert_eq!(
+        message.encode_to_vec(),
+        fixture,
+        "the encoding of {:?} changed",
+        message
+    );
+    assert_eq!(
+        M::decode(fixture).expect("the fixture no longer decodes"),
+        message
+    );
+}
+
+fn block_header(version: i32, hash_byte: u8, time: u32, nonce: u32) -> btc::BlockHeader {
+    btc::BlockHeader {
+        version,
+        prev_blockhash: vec![hash_byte; 4],
+        merkle_root: vec![hash_byte + 0x11; 4],
+        time,
+        bits: 0x1d00ffff,
+        nonce,
+    }
+}
+
+fn http_header(name: &str, value: &[u8]) -> http::HttpHeader {
+    http::HttpHeader {
+        name: name.to_string(),
+        value: value.to_vec(),
+    }
+}
+
+#[test]
+fn get_successors_request_is_wire_compatible() {
+    assert_wire_compatible(
+        btc::GetSuccessorsRequest {
+            processed_block_hashes: vec![vec![1, 2, 3], vec![4, 5]],
+            anchor: vec![0xaa, 0xbb, 0xcc],
+        },
+        include_bytes!("adapter_api_fixtures/get_successors_request.pb"),
+    );
+}
+
+#[test]
+fn get_successors_response_is_wire_compatible() {
+    let transaction = btc::Transaction {
+        version: 2,
+        lock_time: 100,
+        input: vec![btc::TxIn {
+            previous_output: Some(btc::OutPoint {
+                txid: vec![0x33; 4],
+                vout: 1,
+            }),
+            script_sig: vec![0x51],
+            sequence: 0xfffffffe,
+            witness: vec![vec![0x44, 0x45], vec![0x46]],
+        }],
+        output: vec![btc::TxOut {
+            value: 5_000_000_000,
+            script_pubkey: vec![0x76, 0xa9, 0x14],
+        }],
+    };
+    assert_wire_compatible(
+        btc::GetSuccessorsResponse {
+            blocks: vec![btc::Block {
+                header: Some(block_header(1, 0x11, 1231006505, 2083236893)),
+                txdata: vec![transaction],
+            }],
+            next: vec![block_header(2, 0x55, 1231469665, 1639830024)],
+        },
+        include_bytes!("adapter_api_fixtures/get_successors_response.pb"),
+    );
+}
+
+#[test]
+fn send_transaction_request_is_wire_compatible() {
+    assert_wire_compatible(
+        btc::SendTransactionRequest {
+            raw_tx: vec![2, 0, 0, 0, 1],
+        },
+        include_bytes!("adapter_api_fixtures/send_transaction_request.pb"),
+    );
+}
+
+#[test]
+fn canister_http_request_is_wire_compatible() {
+    assert_wire_compatible(
+        http::CanisterHttpRequest {
+            url: "https://example.com/api".to_string(),
+            body: br#"{"key":"value"}"#.to_vec(),
+            headers: vec![
+                http_header("accept", b"application/json"),
+                http_header("user-agent", b"ic"),
+            ],
+            request_id: 7,
+            pseudo_random_delay_window_ms: 500,
+            max_response_bytes: 0,
+        },
+        include_bytes!("adapter_api_fixtures/canister_http_request.pb"),
+    );
+}
+
+#[test]
+fn canister_http_response_is_wire_compatible() {
+    assert_wire_compatible(
+        http::CanisterHttpResponse {
+            status: 200,
+            headers: vec![http_header("content-type", b"text/plain")],
+            content: b"hello world".to_vec(),
+            request_size: 61,
+            response_size: 33,
+        },
+        include_bytes!("adapter_api_fixtures/canister_http_response.pb"),
+    );
+}
@@ -0,0 +1,5 @@
+
+https://example.com/api{"key":"value"}
+acceptapplication/json
+
+user-agentic (
\ No newline at end of file
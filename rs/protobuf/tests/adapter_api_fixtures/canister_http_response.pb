@@ -0,0 +1,3 @@
+
+content-type
+text/plainhello world =(!
\ No newline at end of file
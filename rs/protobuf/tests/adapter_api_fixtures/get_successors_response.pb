@@ -0,0 +1,5 @@
+
+Q
+ """" (0خ-d
+
+3333Q"DE"F"v UUUUffff (0
\ No newline at end of file
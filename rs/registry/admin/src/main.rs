@@ -59,7 +59,10 @@ use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as ProvisionalWhitelistProto,
     replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
     routing_table::v1::RoutingTable,
-    subnet::v1::{EcdsaConfig, SubnetListRecord, SubnetRecord as SubnetRecordProto},
+    subnet::v1::{
+        CanisterHttpConfig, CanisterHttpPricing, EcdsaConfig, SubnetListRecord,
+        SubnetRecord as SubnetRecordProto,
+    },
     unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
 };
 use ic_protobuf::registry::{
@@ -1250,6 +1253,54 @@ struct ProposeToUpdateSubnetCmd {
     #[clap(long)]
     pub ecdsa_quadruples_to_create_in_advance: Option<u32>,
 
+    /// Maximum number of ECDSA signing requests that are matched to
+    /// pre-signatures in a single round. Only used together with
+    /// `ecdsa_quadruples_to_create_in_advance`.
+    #[clap(long)]
+    pub ecdsa_max_signing_requests_per_round: Option<u32>,
+
+    /// Maximum number of ECDSA pre-signatures that canisters may reserve in
+    /// total for their own signing requests. Must be less than
+    /// `ecdsa_quadruples_to_create_in_advance`, together with which it is
+    /// used.
+    #[clap(long)]
+    pub ecdsa_max_reserved_quadruples: Option<u32>,
+
+    /// Maximum size in bytes of a response to a canister http request. If
+    /// any of the `canister_http` options is set, the whole canister http
+    /// config of the subnet is replaced and the unset options fall back to
+    /// their defaults.
+    #[clap(long)]
+    pub canister_http_max_response_bytes: Option<u64>,
+
+    /// Maximum number of canister http requests in flight on the subnet.
+    #[clap(long)]
+    pub canister_http_max_concurrent_requests: Option<u32>,
+
+    /// The url schemes that canister http requests may use, e.g. `https`.
+    #[clap(long)]
+    pub canister_http_allowed_url_schemes: Option<Vec<String>>,
+
+    /// The base fee in cycles of a canister http request. If any of the
+    /// `canister_http_fee` options is set, canister http requests are
+    /// charged and the unset fees are zero.
+    #[clap(long)]
+    pub canister_http_base_fee: Option<u64>,
+
+    /// The fee in cycles per byte of the payload of a canister http request.
+    #[clap(long)]
+    pub canister_http_fee_per_request_byte: Option<u64>,
+
+    /// The fee in cycles per byte of the maximum response size of a canister
+    /// http request.
+    #[clap(long)]
+    pub canister_http_fee_per_response_byte: Option<u64>,
+
+    /// Maximum number of instructions the transform function of a canister
+    /// may execute on the response to a canister http request.
+    #[clap(long)]
+    pub canister_http_max_transform_instructions: Option<u64>,
+
     /// The features that are enabled and disabled on the subnet.
     #[clap(long)]
     pub features: Option<SubnetFeatures>,
@@ -1270,6 +1321,23 @@ struct ProposeToUpdateSubnetCmd {
     pub max_number_of_canisters: Option<u64>,
 }
 
+impl ProposeToUpdateSubnetCmd {
+    fn canister_http_pricing(&self) -> Option<CanisterHttpPricing> {
+        if self.canister_http_base_fee.is_some()
+            || self.canister_http_fee_per_request_byte.is_some()
+            || self.canister_http_fee_per_response_byte.is_some()
+        {
+            Some(CanisterHttpPricing {
+                base_fee: self.canister_http_base_fee.unwrap_or_default(),
+                fee_per_request_byte: self.canister_http_fee_per_request_byte.unwrap_or_default(),
+                fee_per_response_byte: self.canister_http_fee_per_response_byte.unwrap_or_default(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
 #[async_trait]
 impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
     fn title(&self) -> String {
@@ -1319,7 +1387,34 @@ impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
                 .map(|val| EcdsaConfig {
                     quadruples_to_create_in_advance: val,
                     key_ids: vec![],
+                    max_signing_requests_per_round: self
+                        .ecdsa_max_signing_requests_per_round
+                        .unwrap_or_default(),
+                    max_reserved_quadruples: self.ecdsa_max_reserved_quadruples.unwrap_or_default(),
                 }),
+            canister_http_config: if self.canister_http_max_response_bytes.is_some()
+                || self.canister_http_max_concurrent_requests.is_some()
+                || self.canister_http_allowed_url_schemes.is_some()
+                || self.canister_http_pricing().is_some()
+                || self.canister_http_max_transform_instructions.is_some()
+            {
+                Some(CanisterHttpConfig {
+                    max_response_bytes: self.canister_http_max_response_bytes.unwrap_or_default(),
+                    max_concurrent_requests: self
+                        .canister_http_max_concurrent_requests
+                        .unwrap_or_default(),
+                    allowed_url_schemes: self
+                        .canister_http_allowed_url_schemes
+                        .clone()
+                        .unwrap_or_default(),
+                    pricing: self.canister_http_pricing(),
+                    max_transform_instructions: self
+                        .canister_http_max_transform_instructions
+                        .unwrap_or_default(),
+                })
+            } else {
+                None
+            },
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters,
@@ -39,6 +39,7 @@ use registry_canister::{
         do_recover_subnet::RecoverSubnetPayload,
         do_remove_node_directly::RemoveNodeDirectlyPayload, do_remove_nodes::RemoveNodesPayload,
         do_remove_nodes_from_subnet::RemoveNodesFromSubnetPayload,
+        do_update_node_directly::UpdateNodeDirectlyPayload,
         do_update_node_operator_config::UpdateNodeOperatorConfigPayload,
         do_update_subnet::UpdateSubnetPayload,
         do_update_subnet_replica::UpdateSubnetReplicaVersionPayload,
@@ -692,6 +693,27 @@ fn remove_node_directly_(payload: RemoveNodeDirectlyPayload) {
     recertify_registry();
 }
 
+#[export_name = "canister_update update_node_directly"]
+fn update_node_directly() {
+    // This method can be called by any node, to update its own keys
+    println!(
+        "{}call: update_node_directly from: {}",
+        LOG_PREFIX,
+        dfn_core::api::caller()
+    );
+    over(candid_one, |payload: UpdateNodeDirectlyPayload| {
+        update_node_directly_(payload)
+    });
+}
+
+#[candid_method(update, rename = "update_node_directly")]
+fn update_node_directly_(payload: UpdateNodeDirectlyPayload) {
+    if let Err(msg) = registry_mut().do_update_node_directly(payload) {
+        panic!("{} Reject: {}", LOG_PREFIX, msg);
+    }
+    recertify_registry();
+}
+
 fn recertify_registry() {
     use ic_certified_map::{fork_hash, labeled_hash};
 
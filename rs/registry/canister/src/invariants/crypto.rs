@@ -343,6 +343,7 @@ mod tests {
                 algorithm: 0,
                 key_value: vec![],
                 proof_data: None,
+                timestamp: None,
             }),
             tls_certificate: npks_2.tls_certificate,
         };
@@ -11,7 +11,7 @@ use candid::{CandidType, Deserialize};
 use dfn_core::println;
 
 use ic_base_types::NodeId;
-use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_crypto_node_key_validation::{validate_idkg_dealing_encryption_key, ValidNodePublicKeys};
 use ic_crypto_utils_basic_sig::conversions as crypto_basicsig_conversions;
 use ic_protobuf::{
     crypto::v1::NodePublicKeys,
@@ -63,6 +63,7 @@ impl Registry {
 
         // 3. Validate keys and get the node id
         let (node_id, valid_pks) = valid_keys_from_payload(&payload)?;
+        let idkg_dealing_encryption_pk = valid_idkg_key_from_payload(&payload, node_id)?;
 
         println!("{}do_add_node: The node id is {:?}", LOG_PREFIX, node_id);
 
@@ -124,7 +125,7 @@ impl Registry {
             encode_or_panic(&node_operator_record),
         );
 
-        let mutations = vec![
+        let mut mutations = vec![
             add_node_entry,
             add_committee_signing_key,
             add_node_signing_key,
@@ -132,6 +133,14 @@ impl Registry {
             add_tls_certificate,
             update_node_operator_record,
         ];
+        if let Some(idkg_dealing_encryption_pk) = idkg_dealing_encryption_pk {
+            mutations.push(insert(
+                make_crypto_node_key(node_id, KeyPurpose::IDkgMEGaEncryption)
+                    .as_bytes()
+                    .to_vec(),
+                encode_or_panic(&idkg_dealing_encryption_pk),
+            ));
+        }
 
         // Check invariants before applying mutations
         self.maybe_apply_mutation_internal(mutations);
@@ -147,6 +156,9 @@ pub struct AddNodePayload {
     pub node_signing_pk: Vec<u8>,
     pub committee_signing_pk: Vec<u8>,
     pub ni_dkg_dealing_encryption_pk: Vec<u8>,
+    // Raw bytes of the protobuf, but this should be a PublicKey with a proof
+    // of possession bound to the node id
+    pub idkg_dealing_encryption_pk: Option<Vec<u8>>,
     // Raw bytes of the protobuf, but these should be X509PublicKeyCert
     pub transport_tls_cert: Vec<u8>,
 
@@ -260,11 +272,40 @@ fn valid_keys_from_payload(
     }
 }
 
+/// Validates the optional I-DKG dealing encryption key of the payload,
+/// including its proof of possession for `node_id`
+fn valid_idkg_key_from_payload(
+    payload: &AddNodePayload,
+    node_id: NodeId,
+) -> Result<Option<PublicKey>, String> {
+    let idkg_dealing_encryption_pk = match &payload.idkg_dealing_encryption_pk {
+        Some(pk) => pk,
+        None => return Ok(None),
+    };
+    let idkg_dealing_encryption_pk =
+        PublicKey::decode(&idkg_dealing_encryption_pk[..]).map_err(|e| {
+            format!(
+                "idkg_dealing_encryption_pk is not in the expected format: {:?}",
+                e
+            )
+        })?;
+    validate_idkg_dealing_encryption_key(&idkg_dealing_encryption_pk, node_id).map_err(|e| {
+        format!(
+            "Could not validate idkg_dealing_encryption_pk, due to {:?}",
+            e
+        )
+    })?;
+    Ok(Some(idkg_dealing_encryption_pk))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ic_base_types::NodeId;
-    use ic_crypto::utils::get_node_keys_or_generate_if_missing;
+    use ic_crypto::utils::{
+        generate_idkg_dealing_encryption_keys, generate_idkg_dealing_encryption_keys_with_pop,
+        get_node_keys_or_generate_if_missing,
+    };
     use ic_nns_common::registry::encode_or_panic;
     use ic_protobuf::crypto::v1::NodePublicKeys;
     use ic_test_utilities::crypto::temp_dir::temp_dir;
@@ -292,6 +333,7 @@ mod tests {
             node_signing_pk: vec![],
             committee_signing_pk: vec![],
             ni_dkg_dealing_encryption_pk: vec![],
+            idkg_dealing_encryption_pk: None,
             transport_tls_cert: vec![],
             xnet_endpoint: "127.0.0.1:1234".to_string(),
             http_endpoint: "127.0.0.1:8123".to_string(),
@@ -340,6 +382,48 @@ mod tests {
         assert!(valid_keys_from_payload(&payload).is_err());
     }
 
+    #[test]
+    fn missing_idkg_dealing_key_is_accepted() {
+        let payload = PAYLOAD.clone();
+        assert_eq!(
+            valid_idkg_key_from_payload(&payload, TEST_DATA.clone()._node_id),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn idkg_dealing_key_with_valid_pop_is_accepted() {
+        let temp_dir = temp_dir();
+        let node_id = TEST_DATA.clone()._node_id;
+        let idkg_pubkey = generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id);
+        let mut payload = PAYLOAD.clone();
+        payload.idkg_dealing_encryption_pk = Some(encode_or_panic(&idkg_pubkey));
+        assert_eq!(
+            valid_idkg_key_from_payload(&payload, node_id),
+            Ok(Some(idkg_pubkey))
+        );
+    }
+
+    #[test]
+    fn idkg_dealing_key_without_pop_is_detected() {
+        let temp_dir = temp_dir();
+        let idkg_pubkey = generate_idkg_dealing_encryption_keys(temp_dir.path());
+        let mut payload = PAYLOAD.clone();
+        payload.idkg_dealing_encryption_pk = Some(encode_or_panic(&idkg_pubkey));
+        assert!(valid_idkg_key_from_payload(&payload, TEST_DATA.clone()._node_id).is_err());
+    }
+
+    #[test]
+    fn idkg_dealing_key_with_pop_for_other_node_is_detected() {
+        let temp_dir = temp_dir();
+        let other_node_id = NodeId::from(ic_base_types::PrincipalId::new_node_test_id(42));
+        let idkg_pubkey =
+            generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), other_node_id);
+        let mut payload = PAYLOAD.clone();
+        payload.idkg_dealing_encryption_pk = Some(encode_or_panic(&idkg_pubkey));
+        assert!(valid_idkg_key_from_payload(&payload, TEST_DATA.clone()._node_id).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn empty_string_causes_panic() {
@@ -0,0 +1,207 @@
+use crate::{common::LOG_PREFIX, mutations::common::decode_registry_value, registry::Registry};
+
+use candid::{CandidType, Deserialize};
+#[cfg(target_arch = "wasm32")]
+use dfn_core::println;
+use prost::Message;
+
+use ic_base_types::NodeId;
+use ic_crypto_node_key_validation::validate_idkg_dealing_encryption_key;
+use ic_nns_common::registry::encode_or_panic;
+use ic_protobuf::registry::crypto::v1::PublicKey;
+use ic_registry_keys::{make_crypto_node_key, make_node_record_key};
+use ic_registry_transport::upsert;
+use ic_types::crypto::KeyPurpose;
+
+impl Registry {
+    /// Updates the key material of an existing node in the registry.
+    ///
+    /// This method is called directly by the node whose keys are updated,
+    /// e.g. to register a rotated I-DKG dealing encryption key.
+    pub fn do_update_node_directly(
+        &mut self,
+        payload: UpdateNodeDirectlyPayload,
+    ) -> Result<(), String> {
+        println!("{}do_update_node_directly: {:?}", LOG_PREFIX, payload);
+
+        // 1. The caller must be an existing node
+        let node_id = NodeId::from(dfn_core::api::caller());
+        let node_key = make_node_record_key(node_id);
+        if self
+            .get(node_key.as_bytes(), self.latest_version())
+            .filter(|registry_value| !registry_value.deletion_marker)
+            .is_none()
+        {
+            return Err(format!(
+                "{}do_update_node_directly: Node Id {:} not found in the registry, aborting node update.",
+                LOG_PREFIX, node_id
+            ));
+        }
+
+        // 2. Validate the new key against the registered one
+        let idkg_key = make_crypto_node_key(node_id, KeyPurpose::IDkgMEGaEncryption);
+        let registered_idkg_dealing_encryption_pk = self
+            .get(idkg_key.as_bytes(), self.latest_version())
+            .filter(|registry_value| !registry_value.deletion_marker)
+            .map(|registry_value| decode_registry_value::<PublicKey>(registry_value.value.clone()));
+        let idkg_dealing_encryption_pk = valid_rotated_idkg_key_from_payload(
+            &payload,
+            node_id,
+            registered_idkg_dealing_encryption_pk.as_ref(),
+        )?;
+
+        // 3. Register the new key
+        let mutations = vec![upsert(
+            idkg_key.as_bytes(),
+            encode_or_panic(&idkg_dealing_encryption_pk),
+        )];
+
+        // Check invariants before applying mutations
+        self.maybe_apply_mutation_internal(mutations);
+
+        Ok(())
+    }
+}
+
+/// The payload of an update request to update the key material of a node.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UpdateNodeDirectlyPayload {
+    // Raw bytes of the protobuf, but this should be a PublicKey with a proof
+    // of possession bound to the node id and the timestamp of its generation
+    pub idkg_dealing_encryption_pk: Option<Vec<u8>>,
+}
+
+/// Validates the I-DKG dealing encryption key of the payload, including its
+/// proof of possession for `node_id`. The key must have been generated after
+/// the `registered` key, if any.
+fn valid_rotated_idkg_key_from_payload(
+    payload: &UpdateNodeDirectlyPayload,
+    node_id: NodeId,
+    registered: Option<&PublicKey>,
+) -> Result<PublicKey, String> {
+    let idkg_dealing_encryption_pk = payload
+        .idkg_dealing_encryption_pk
+        .as_ref()
+        .ok_or_else(|| "idkg_dealing_encryption_pk is missing".to_string())?;
+    let idkg_dealing_encryption_pk =
+        PublicKey::decode(&idkg_dealing_encryption_pk[..]).map_err(|e| {
+            format!(
+                "idkg_dealing_encryption_pk is not in the expected format: {:?}",
+                e
+            )
+        })?;
+    validate_idkg_dealing_encryption_key(&idkg_dealing_encryption_pk, node_id).map_err(|e| {
+        format!(
+            "Could not validate idkg_dealing_encryption_pk, due to {:?}",
+            e
+        )
+    })?;
+    let timestamp = idkg_dealing_encryption_pk
+        .timestamp
+        .ok_or_else(|| "idkg_dealing_encryption_pk has no timestamp".to_string())?;
+    if let Some(registered_timestamp) = registered.and_then(|registered| registered.timestamp) {
+        if timestamp <= registered_timestamp {
+            return Err(format!(
+                "idkg_dealing_encryption_pk with timestamp {} is not newer than the registered one with timestamp {}",
+                timestamp, registered_timestamp
+            ));
+        }
+    }
+    Ok(idkg_dealing_encryption_pk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::PrincipalId;
+    use ic_crypto::utils::generate_idkg_dealing_encryption_keys_with_pop;
+    use ic_test_utilities::crypto::temp_dir::temp_dir;
+
+    fn node_id() -> NodeId {
+        NodeId::from(PrincipalId::new_node_test_id(1))
+    }
+
+    fn idkg_pubkey_with_timestamp(timestamp: Option<u64>) -> PublicKey {
+        let temp_dir = temp_dir();
+        let mut idkg_pubkey =
+            generate_idkg_dealing_encryption_keys_with_pop(temp_dir.path(), node_id());
+        idkg_pubkey.timestamp = timestamp;
+        idkg_pubkey
+    }
+
+    fn payload_with(idkg_pubkey: &PublicKey) -> UpdateNodeDirectlyPayload {
+        UpdateNodeDirectlyPayload {
+            idkg_dealing_encryption_pk: Some(encode_or_panic(idkg_pubkey)),
+        }
+    }
+
+    #[test]
+    fn newer_idkg_dealing_key_is_accepted() {
+        let registered = idkg_pubkey_with_timestamp(Some(1_000));
+        let rotated = idkg_pubkey_with_timestamp(Some(2_000));
+        assert_eq!(
+            valid_rotated_idkg_key_from_payload(
+                &payload_with(&rotated),
+                node_id(),
+                Some(&registered)
+            ),
+            Ok(rotated)
+        );
+    }
+
+    #[test]
+    fn idkg_dealing_key_replacing_key_without_timestamp_is_accepted() {
+        let registered = idkg_pubkey_with_timestamp(None);
+        let rotated = idkg_pubkey_with_timestamp(Some(2_000));
+        assert_eq!(
+            valid_rotated_idkg_key_from_payload(
+                &payload_with(&rotated),
+                node_id(),
+                Some(&registered)
+            ),
+            Ok(rotated.clone())
+        );
+        assert_eq!(
+            valid_rotated_idkg_key_from_payload(&payload_with(&rotated), node_id(), None),
+            Ok(rotated)
+        );
+    }
+
+    #[test]
+    fn older_idkg_dealing_key_is_detected() {
+        let registered = idkg_pubkey_with_timestamp(Some(2_000));
+        let rotated = idkg_pubkey_with_timestamp(Some(2_000));
+        assert!(valid_rotated_idkg_key_from_payload(
+            &payload_with(&rotated),
+            node_id(),
+            Some(&registered)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn idkg_dealing_key_without_timestamp_is_detected() {
+        let rotated = idkg_pubkey_with_timestamp(None);
+        assert!(
+            valid_rotated_idkg_key_from_payload(&payload_with(&rotated), node_id(), None).is_err()
+        );
+    }
+
+    #[test]
+    fn missing_idkg_dealing_key_is_detected() {
+        let payload = UpdateNodeDirectlyPayload {
+            idkg_dealing_encryption_pk: None,
+        };
+        assert!(valid_rotated_idkg_key_from_payload(&payload, node_id(), None).is_err());
+    }
+
+    #[test]
+    fn idkg_dealing_key_with_pop_for_other_node_is_detected() {
+        let rotated = idkg_pubkey_with_timestamp(Some(2_000));
+        let other_node_id = NodeId::from(PrincipalId::new_node_test_id(42));
+        assert!(
+            valid_rotated_idkg_key_from_payload(&payload_with(&rotated), other_node_id, None)
+                .is_err()
+        );
+    }
+}
@@ -5,12 +5,14 @@ use dfn_core::println;
 use serde::Serialize;
 
 use ic_base_types::SubnetId;
-use ic_protobuf::registry::subnet::v1::{EcdsaConfig, GossipAdvertConfig, SubnetRecord};
+use ic_protobuf::registry::subnet::v1::{
+    CanisterHttpConfig, EcdsaConfig, GossipAdvertConfig, SubnetRecord,
+};
 use ic_registry_keys::make_subnet_record_key;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_registry_transport::pb::v1::{registry_mutation, RegistryMutation};
-use ic_types::p2p::build_default_gossip_config;
+use ic_types::{canister_http::MAX_CANISTER_HTTP_RESPONSE_BYTES, p2p::build_default_gossip_config};
 
 /// Updates the subnet's configuration in the registry.
 ///
@@ -85,6 +87,8 @@ pub struct UpdateSubnetPayload {
 
     pub ecdsa_config: Option<EcdsaConfig>,
 
+    pub canister_http_config: Option<CanisterHttpConfig>,
+
     pub max_number_of_canisters: Option<u64>,
 
     pub ssh_readonly_access: Option<Vec<String>>,
@@ -125,6 +129,41 @@ fn is_any_gossip_field_set(payload: &UpdateSubnetPayload) -> bool {
         || payload.advert_best_effort_percentage.is_some()
 }
 
+/// The url schemes that a subnet may allow canister http requests to use.
+const SUPPORTED_CANISTER_HTTP_URL_SCHEMES: [&str; 2] = ["https", "http"];
+
+/// Panics if `config` sets limits that the canister http adapter can not
+/// enforce. Zero limits and an empty list of schemes select the defaults.
+fn validate_canister_http_config(config: &CanisterHttpConfig) {
+    assert!(
+        config.max_response_bytes <= MAX_CANISTER_HTTP_RESPONSE_BYTES,
+        "max_response_bytes of {} exceeds the maximum of {} bytes",
+        config.max_response_bytes,
+        MAX_CANISTER_HTTP_RESPONSE_BYTES
+    );
+    for scheme in &config.allowed_url_schemes {
+        assert!(
+            SUPPORTED_CANISTER_HTTP_URL_SCHEMES.contains(&scheme.as_str()),
+            "Unsupported url scheme {}, the supported schemes are {:?}",
+            scheme,
+            SUPPORTED_CANISTER_HTTP_URL_SCHEMES
+        );
+    }
+}
+
+/// Panics if `config` lets canisters reserve all the pre-signatures that the
+/// subnet creates in advance, which would starve the signing requests of all
+/// other canisters.
+fn validate_ecdsa_config(config: &EcdsaConfig) {
+    assert!(
+        config.max_reserved_quadruples == 0
+            || config.max_reserved_quadruples < config.quadruples_to_create_in_advance,
+        "max_reserved_quadruples of {} is not less than the {} quadruples created in advance",
+        config.max_reserved_quadruples,
+        config.quadruples_to_create_in_advance
+    );
+}
+
 // Merges the changes included in the `UpdateSubnetPayload` to the given
 // `SubnetRecord`. If any value in the provided payload is None, then it is
 // skipped, otherwise it overwrites the corresponding value in the
@@ -172,6 +211,7 @@ fn merge_subnet_record(
         max_instructions_per_install_code,
         features,
         ecdsa_config,
+        canister_http_config,
         max_number_of_canisters,
         ssh_readonly_access,
         ssh_backup_access,
@@ -229,9 +269,17 @@ fn merge_subnet_record(
             .iter()
             .all(|x| new_ecdsa_config.key_ids.contains(x)));
     }
+    if let Some(config) = ecdsa_config.as_ref() {
+        validate_ecdsa_config(config);
+    }
     maybe_set_option!(subnet_record, features);
     maybe_set_option!(subnet_record, ecdsa_config);
 
+    if let Some(config) = canister_http_config.as_ref() {
+        validate_canister_http_config(config);
+    }
+    maybe_set_option!(subnet_record, canister_http_config);
+
     maybe_set!(subnet_record, max_number_of_canisters);
 
     maybe_set!(subnet_record, ssh_readonly_access);
@@ -243,7 +291,9 @@ fn merge_subnet_record(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ic_protobuf::registry::subnet::v1::{GossipAdvertConfig, GossipConfig};
+    use ic_protobuf::registry::subnet::v1::{
+        CanisterHttpPricing, GossipAdvertConfig, GossipConfig,
+    };
     use ic_registry_subnet_type::SubnetType;
     use ic_types::p2p::{
         MAX_ARTIFACT_STREAMS_PER_PEER, MAX_CHUNK_WAIT_MS, MAX_DUPLICITY, PFN_EVALUATION_PERIOD_MS,
@@ -292,7 +342,10 @@ mod tests {
             ecdsa_config: Some(EcdsaConfig {
                 quadruples_to_create_in_advance: 10,
                 key_ids: vec!["key_id_1".to_string()],
+                max_signing_requests_per_round: 5,
+                max_reserved_quadruples: 0,
             }),
+            canister_http_config: None,
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -333,6 +386,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -374,6 +428,19 @@ mod tests {
             ecdsa_config: Some(EcdsaConfig {
                 quadruples_to_create_in_advance: 10,
                 key_ids: vec!["key_id_1".to_string()],
+                max_signing_requests_per_round: 5,
+                max_reserved_quadruples: 0,
+            }),
+            canister_http_config: Some(CanisterHttpConfig {
+                max_response_bytes: 1024,
+                max_concurrent_requests: 50,
+                allowed_url_schemes: vec!["https".to_string(), "http".to_string()],
+                pricing: Some(CanisterHttpPricing {
+                    base_fee: 1_000_000,
+                    fee_per_request_byte: 100,
+                    fee_per_response_byte: 10,
+                }),
+                max_transform_instructions: 1_000_000,
             }),
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
@@ -422,7 +489,20 @@ mod tests {
                 ),
                 ecdsa_config: Some(EcdsaConfig {
                     quadruples_to_create_in_advance: 10,
-                    key_ids: vec!["key_id_1".to_string()]
+                    key_ids: vec!["key_id_1".to_string()],
+                    max_signing_requests_per_round: 5,
+                    max_reserved_quadruples: 0,
+                }),
+                canister_http_config: Some(CanisterHttpConfig {
+                    max_response_bytes: 1024,
+                    max_concurrent_requests: 50,
+                    allowed_url_schemes: vec!["https".to_string(), "http".to_string()],
+                    pricing: Some(CanisterHttpPricing {
+                        base_fee: 1_000_000,
+                        fee_per_request_byte: 100,
+                        fee_per_response_byte: 10,
+                    }),
+                    max_transform_instructions: 1_000_000,
                 }),
                 max_number_of_canisters: 10,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
@@ -467,6 +547,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -501,6 +582,7 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: Some(50),
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -540,6 +622,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                canister_http_config: None,
             }
         );
     }
@@ -550,6 +633,8 @@ mod tests {
         let ecdsa_config = Some(EcdsaConfig {
             key_ids: vec!["key_id_1".to_string()],
             quadruples_to_create_in_advance: 0,
+            max_signing_requests_per_round: 0,
+            max_reserved_quadruples: 0,
         });
 
         let subnet_record = SubnetRecord {
@@ -619,6 +704,43 @@ mod tests {
         merge_subnet_record(subnet_record, payload);
     }
 
+    #[test]
+    #[should_panic(expected = "is not less than the 10 quadruples created in advance")]
+    fn panic_on_reserving_all_quadruples_created_in_advance() {
+        let mut payload = make_default_payload_for_tests();
+        payload.ecdsa_config = Some(EcdsaConfig {
+            quadruples_to_create_in_advance: 10,
+            max_reserved_quadruples: 10,
+            ..Default::default()
+        });
+
+        merge_subnet_record(SubnetRecord::default(), payload);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the maximum")]
+    fn panic_on_too_large_canister_http_max_response_bytes() {
+        let mut payload = make_default_payload_for_tests();
+        payload.canister_http_config = Some(CanisterHttpConfig {
+            max_response_bytes: MAX_CANISTER_HTTP_RESPONSE_BYTES + 1,
+            ..Default::default()
+        });
+
+        merge_subnet_record(SubnetRecord::default(), payload);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported url scheme")]
+    fn panic_on_unsupported_canister_http_url_scheme() {
+        let mut payload = make_default_payload_for_tests();
+        payload.canister_http_config = Some(CanisterHttpConfig {
+            allowed_url_schemes: vec!["https".to_string(), "ftp".to_string()],
+            ..Default::default()
+        });
+
+        merge_subnet_record(SubnetRecord::default(), payload);
+    }
+
     #[test]
     #[should_panic]
     // This test confirms that if `set_gossip_config_to_default` = false and the
@@ -647,6 +769,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -681,6 +804,7 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -713,6 +837,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -747,6 +872,7 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -788,6 +914,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                canister_http_config: None,
             }
         );
     }
@@ -828,6 +955,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -862,6 +990,7 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -903,6 +1032,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                canister_http_config: None,
             }
         );
     }
@@ -73,6 +73,7 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -141,6 +142,7 @@ fn test_a_canister_other_than_the_proposals_canister_cannot_update_a_subnets_con
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            canister_http_config: None,
         };
 
         // An attacker got a canister that is trying to pass for the proposals
@@ -195,6 +197,7 @@ fn test_a_canister_other_than_the_proposals_canister_cannot_update_a_subnets_con
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: Some(100),
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -264,6 +267,7 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
                             ssh_readonly_access: vec![],
                             ssh_backup_access: vec![],
                             ecdsa_config: None,
+                            canister_http_config: None,
                         }),
                     )],
                     preconditions: vec![],
@@ -309,6 +313,7 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            canister_http_config: None,
             max_number_of_canisters: Some(42),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -364,6 +369,7 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
                 ecdsa_config: None,
+                canister_http_config: None,
             }
         );
 
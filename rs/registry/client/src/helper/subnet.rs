@@ -1,21 +1,29 @@
 use ic_interfaces::registry::{
-    RegistryClient, RegistryClientResult, RegistryClientVersionedResult, RegistryVersionedRecord,
+    RegistryClient, RegistryClientError, RegistryClientResult, RegistryClientVersionedResult,
+    RegistryVersionedRecord,
 };
 use ic_protobuf::registry::{
     node::v1::NodeRecord,
     replica_version::v1::ReplicaVersionRecord,
     subnet::v1::{
-        CatchUpPackageContents, EcdsaConfig, GossipConfig, SubnetListRecord, SubnetRecord,
+        CatchUpPackageContents, EcdsaConfig, EcdsaSigningSubnetList, GossipConfig,
+        SubnetListRecord, SubnetRecord,
     },
 };
 use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
 use ic_registry_common::values::deserialize_registry_value;
 use ic_registry_keys::{
-    make_catch_up_package_contents_key, make_node_record_key, make_replica_version_key,
-    make_subnet_list_record_key, make_subnet_record_key, ROOT_SUBNET_ID_KEY,
+    make_catch_up_package_contents_key, make_ecdsa_signing_subnet_list_key, make_node_record_key,
+    make_replica_version_key, make_subnet_list_record_key, make_subnet_record_key,
+    maybe_parse_ecdsa_signing_subnet_list_key, ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX,
+    ROOT_SUBNET_ID_KEY,
 };
 use ic_registry_subnet_features::SubnetFeatures;
-use ic_types::{Height, NodeId, PrincipalId, RegistryVersion, ReplicaVersion, SubnetId};
+use ic_types::{
+    canister_http::CanisterHttpLimits, Height, NodeId, PrincipalId, RegistryVersion,
+    ReplicaVersion, SubnetId,
+};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::time::Duration;
 
@@ -85,6 +93,23 @@ pub trait SubnetRegistry {
         version: RegistryVersion,
     ) -> RegistryClientResult<EcdsaConfig>;
 
+    /// Returns the identifiers of the threshold ECDSA keys held by the subnet
+    /// as recorded in its ecdsa config. Returns an empty list if the subnet
+    /// has no ecdsa config.
+    fn get_ecdsa_key_ids(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<String>>;
+
+    /// Returns the limits on canister http requests of the subnet, with unset
+    /// limits replaced by their defaults
+    fn get_canister_http_limits(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<CanisterHttpLimits>;
+
     /// Returns notarization delay settings:
     /// - the unit delay for blockmaker;
     /// - the initial delay for notary, to give time to rank-0 block
@@ -258,6 +283,31 @@ impl<T: RegistryClient + ?Sized> SubnetRegistry for T {
         Ok(subnet.and_then(|subnet| subnet.ecdsa_config))
     }
 
+    fn get_ecdsa_key_ids(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<String>> {
+        let bytes = self.get_value(&make_subnet_record_key(subnet_id), version);
+        let subnet = deserialize_registry_value::<SubnetRecord>(bytes)?;
+        Ok(subnet.map(|subnet| {
+            subnet
+                .ecdsa_config
+                .map(|ecdsa_config| ecdsa_config.key_ids)
+                .unwrap_or_default()
+        }))
+    }
+
+    fn get_canister_http_limits(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<CanisterHttpLimits> {
+        let bytes = self.get_value(&make_subnet_record_key(subnet_id), version);
+        let subnet = deserialize_registry_value::<SubnetRecord>(bytes)?;
+        Ok(subnet.map(|subnet| subnet.canister_http_config.unwrap_or_default().into()))
+    }
+
     fn get_notarization_delay_settings(
         &self,
         subnet_id: SubnetId,
@@ -420,6 +470,29 @@ pub fn get_node_ids_from_subnet_record(subnet: &SubnetRecord) -> Vec<NodeId> {
 /// of the current topology of the IC.
 pub trait SubnetListRegistry {
     fn get_subnet_ids(&self, version: RegistryVersion) -> RegistryClientResult<Vec<SubnetId>>;
+
+    /// Returns the listed subnets that hold the threshold ECDSA key
+    /// `key_id`, in the order of the subnet list.
+    fn get_subnets_holding_ecdsa_key(
+        &self,
+        key_id: &str,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<SubnetId>>;
+
+    /// Returns the subnets that may sign with the threshold ECDSA key
+    /// `key_id`, or `None` if the registry has no signing subnet list for it.
+    fn get_ecdsa_signing_subnets(
+        &self,
+        key_id: &str,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<SubnetId>>;
+
+    /// Returns the signing subnet lists of all threshold ECDSA keys that have
+    /// one, by key id.
+    fn get_all_ecdsa_signing_subnets(
+        &self,
+        version: RegistryVersion,
+    ) -> Result<BTreeMap<String, Vec<SubnetId>>, RegistryClientError>;
 }
 
 impl<T: RegistryClient + ?Sized> SubnetListRegistry for T {
@@ -435,6 +508,61 @@ impl<T: RegistryClient + ?Sized> SubnetListRegistry for T {
             }),
         )
     }
+
+    fn get_subnets_holding_ecdsa_key(
+        &self,
+        key_id: &str,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<SubnetId>> {
+        let subnet_ids = match self.get_subnet_ids(version)? {
+            Some(subnet_ids) => subnet_ids,
+            None => return Ok(None),
+        };
+        let mut holding_subnets = vec![];
+        for subnet_id in subnet_ids {
+            if let Some(key_ids) = self.get_ecdsa_key_ids(subnet_id, version)? {
+                if key_ids.iter().any(|id| id == key_id) {
+                    holding_subnets.push(subnet_id);
+                }
+            }
+        }
+        Ok(Some(holding_subnets))
+    }
+
+    fn get_ecdsa_signing_subnets(
+        &self,
+        key_id: &str,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Vec<SubnetId>> {
+        let bytes = self.get_value(&make_ecdsa_signing_subnet_list_key(key_id), version);
+        Ok(
+            deserialize_registry_value::<EcdsaSigningSubnetList>(bytes)?.map(|list| {
+                list.subnets
+                    .into_iter()
+                    .filter_map(|subnet_id_proto| subnet_id_proto.principal_id)
+                    .map(|pr_id| {
+                        PrincipalId::try_from(pr_id.raw).expect("Could not parse principal id!")
+                    })
+                    .map(SubnetId::from)
+                    .collect()
+            }),
+        )
+    }
+
+    fn get_all_ecdsa_signing_subnets(
+        &self,
+        version: RegistryVersion,
+    ) -> Result<BTreeMap<String, Vec<SubnetId>>, RegistryClientError> {
+        let mut signing_subnets = BTreeMap::new();
+        for key in self.get_key_family(ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX, version)? {
+            if let Some(key_id) = maybe_parse_ecdsa_signing_subnet_list_key(&key) {
+                if let Some(subnets) = self.get_ecdsa_signing_subnets(&key_id, version)? {
+                    signing_subnets.insert(key_id, subnets);
+                }
+            }
+        }
+        Ok(signing_subnets)
+    }
 }
 
 /// Helper methods primarily used in `transport`/`p2p` where both, where
@@ -503,7 +631,7 @@ mod tests {
     use super::*;
     use crate::client::RegistryClientImpl;
     use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
-    use ic_types::PrincipalId;
+    use ic_types::{subnet_id_into_protobuf, PrincipalId};
     use std::sync::Arc;
 
     fn node_id(id: u64) -> NodeId {
@@ -582,4 +710,132 @@ mod tests {
             .unwrap();
         assert_eq!(result, Some(replica_version_record))
     }
+
+    fn subnet_record_with_ecdsa_key_ids(key_ids: &[&str]) -> SubnetRecord {
+        SubnetRecord {
+            ecdsa_config: Some(EcdsaConfig {
+                key_ids: key_ids.iter().map(|key_id| key_id.to_string()).collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn can_get_ecdsa_key_ids_and_subnets_holding_ecdsa_key() {
+        let version = RegistryVersion::from(2);
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let subnet_records = vec![
+            (
+                subnet_id(1),
+                subnet_record_with_ecdsa_key_ids(&["secp256k1"]),
+            ),
+            (subnet_id(2), SubnetRecord::default()),
+            (
+                subnet_id(3),
+                subnet_record_with_ecdsa_key_ids(&["other_key", "secp256k1"]),
+            ),
+        ];
+        for (subnet_id, subnet_record) in subnet_records.iter() {
+            data_provider
+                .add(
+                    &make_subnet_record_key(*subnet_id),
+                    version,
+                    Some(subnet_record.clone()),
+                )
+                .unwrap();
+        }
+        data_provider
+            .add(
+                &make_subnet_list_record_key(),
+                version,
+                Some(SubnetListRecord {
+                    subnets: subnet_records
+                        .iter()
+                        .map(|(subnet_id, _)| subnet_id.get().into_vec())
+                        .collect(),
+                }),
+            )
+            .unwrap();
+
+        let registry = Arc::new(RegistryClientImpl::new(data_provider, None));
+        registry.fetch_and_start_polling().unwrap();
+        let registry: Arc<dyn RegistryClient> = registry;
+
+        assert_eq!(
+            registry.get_ecdsa_key_ids(subnet_id(3), version).unwrap(),
+            Some(vec!["other_key".to_string(), "secp256k1".to_string()])
+        );
+        assert_eq!(
+            registry.get_ecdsa_key_ids(subnet_id(2), version).unwrap(),
+            Some(vec![])
+        );
+        assert_eq!(
+            registry.get_ecdsa_key_ids(subnet_id(4), version).unwrap(),
+            None
+        );
+        assert_eq!(
+            registry
+                .get_subnets_holding_ecdsa_key("secp256k1", version)
+                .unwrap(),
+            Some(vec![subnet_id(1), subnet_id(3)])
+        );
+        assert_eq!(
+            registry
+                .get_subnets_holding_ecdsa_key("unknown_key", version)
+                .unwrap(),
+            Some(vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn can_get_ecdsa_signing_subnets() {
+        let version = RegistryVersion::from(2);
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        data_provider
+            .add(
+                &make_ecdsa_signing_subnet_list_key("secp256k1"),
+                version,
+                Some(EcdsaSigningSubnetList {
+                    subnets: vec![subnet_id(1), subnet_id(3)]
+                        .into_iter()
+                        .map(subnet_id_into_protobuf)
+                        .collect(),
+                }),
+            )
+            .unwrap();
+        data_provider
+            .add(
+                &make_ecdsa_signing_subnet_list_key("other_key"),
+                version,
+                Some(EcdsaSigningSubnetList::default()),
+            )
+            .unwrap();
+
+        let registry = Arc::new(RegistryClientImpl::new(data_provider, None));
+        registry.fetch_and_start_polling().unwrap();
+        let registry: Arc<dyn RegistryClient> = registry;
+
+        assert_eq!(
+            registry
+                .get_ecdsa_signing_subnets("secp256k1", version)
+                .unwrap(),
+            Some(vec![subnet_id(1), subnet_id(3)])
+        );
+        assert_eq!(
+            registry
+                .get_ecdsa_signing_subnets("unknown_key", version)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            registry.get_all_ecdsa_signing_subnets(version).unwrap(),
+            vec![
+                ("other_key".to_string(), vec![]),
+                ("secp256k1".to_string(), vec![subnet_id(1), subnet_id(3)]),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
 }
@@ -26,6 +26,7 @@ pub const CRYPTO_RECORD_KEY_PREFIX: &str = "crypto_record_";
 pub const CRYPTO_TLS_CERT_KEY_PREFIX: &str = "crypto_tls_cert_";
 pub const CRYPTO_THRESHOLD_SIGNING_KEY_PREFIX: &str = "crypto_threshold_signing_public_key_";
 pub const DATA_CENTER_KEY_PREFIX: &str = "data_center_record_";
+pub const ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX: &str = "key_id_";
 
 /// Returns the only key whose payload is the ICP/XDR conversion rate.
 pub fn make_icp_xdr_conversion_rate_record_key() -> String {
@@ -132,6 +133,20 @@ pub fn maybe_parse_crypto_threshold_signing_pubkey_key(key: &str) -> Option<Subn
     }
 }
 
+/// Makes a key for the list of subnets that may sign with the threshold
+/// ECDSA key `key_id`.
+pub fn make_ecdsa_signing_subnet_list_key(key_id: &str) -> String {
+    format!("{}{}", ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX, key_id)
+}
+
+// If `key` starts with `ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX`, returns the
+// ECDSA key id that it is the signing subnet list of, otherwise returns None.
+pub fn maybe_parse_ecdsa_signing_subnet_list_key(key: &str) -> Option<String> {
+    key.strip_prefix(ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX)
+        .filter(|key_id| !key_id.is_empty())
+        .map(str::to_string)
+}
+
 /// Makes a key for a record for the catch up package contents.
 pub fn make_catch_up_package_contents_key(subnet_id: SubnetId) -> String {
     format!("catch_up_package_contents_{}", subnet_id)
@@ -248,4 +263,25 @@ mod tests {
         let parsed = maybe_parse_crypto_threshold_signing_pubkey_key(&wrong_key);
         assert!(parsed.is_none());
     }
+
+    #[test]
+    fn should_parse_ecdsa_signing_subnet_list_key() {
+        let key = make_ecdsa_signing_subnet_list_key("secp256k1");
+        assert_eq!(key, "key_id_secp256k1");
+        assert_eq!(
+            maybe_parse_ecdsa_signing_subnet_list_key(&key),
+            Some("secp256k1".to_string())
+        );
+    }
+
+    #[test]
+    fn should_fail_parsing_ecdsa_signing_subnet_list_key() {
+        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(42));
+        let wrong_key = make_subnet_record_key(subnet_id);
+        assert!(maybe_parse_ecdsa_signing_subnet_list_key(&wrong_key).is_none());
+        assert!(
+            maybe_parse_ecdsa_signing_subnet_list_key(ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX)
+                .is_none()
+        );
+    }
 }
@@ -519,6 +519,7 @@ impl Player {
             registry_version,
             time,
             consensus_responses: Vec::new(),
+            canister_http_responses: Vec::new(),
         };
         let context_time = extra_batch.time;
         let extra_msgs = extra(self, context_time);
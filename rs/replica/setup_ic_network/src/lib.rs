@@ -384,6 +384,9 @@ fn setup_artifact_manager(
         ),
     ));
 
+    let max_parallel_ecdsa_transcript_operations =
+        consensus_config.max_parallel_ecdsa_transcript_operations();
+    let ecdsa_key_transcript_grace = consensus_config.ecdsa_key_transcript_grace();
     {
         // Create the consensus client.
         let event_handler = event_handler.clone();
@@ -508,6 +511,8 @@ fn setup_artifact_manager(
                             metrics_registry.clone(),
                             replica_logger.clone(),
                             malicious_flags,
+                            max_parallel_ecdsa_transcript_operations,
+                            ecdsa_key_transcript_grace,
                         ),
                         ecdsa::EcdsaGossipImpl::new(Arc::clone(&consensus_block_cache)),
                     )
@@ -0,0 +1,134 @@
+//! Honoring the [`Options`] of the calls from the replica to an adapter.
+//!
+//! A call is attempted until it reaches the adapter, at most as often as its
+//! [`RetryPolicy`] allows, and never beyond the deadline set by its timeout.
+//! Only calls that failed to reach the adapter are attempted again, as the
+//! adapter may already have acted on any other call. Calls with
+//! [`Priority::Background`] wait for one of a bounded number of slots, so that
+//! they can not crowd out the calls that consensus waits for.
+//!
+//! [`RetryPolicy`]: ic_interfaces::adapter_client::RetryPolicy
+use ic_crypto_sha::Sha256;
+use ic_interfaces::adapter_client::{
+    Options, Priority, TraceContext, PURPOSE_METADATA_KEY, TRACEPARENT_METADATA_KEY,
+};
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{
+    sync::Semaphore,
+    time::{timeout_at, Instant},
+};
+use tonic::{metadata::MetadataValue, Code, Request, Status};
+
+/// Runs the calls to an adapter according to their [`Options`].
+#[derive(Clone, Debug)]
+pub struct AdapterCallScheduler {
+    background_slots: Arc<Semaphore>,
+}
+
+impl AdapterCallScheduler {
+    /// Creates a scheduler that lets at most `max_background_calls` calls
+    /// with [`Priority::Background`] be in flight at once.
+    pub fn new(max_background_calls: usize) -> Self {
+        Self {
+            background_slots: Arc::new(Semaphore::new(max_background_calls)),
+        }
+    }
+
+    /// Runs the call made by `attempt` as described in the [module
+    /// documentation](self). Each attempt is passed the time left until the
+    /// deadline of the call, if any, to propagate it to the adapter.
+    pub async fn call<T, F, Fut>(&self, opts: &Options, mut attempt: F) -> Result<T, Status>
+    where
+        F: FnMut(Option<Duration>) -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+        let _slot = match opts.priority {
+            Priority::ConsensusCritical => None,
+            Priority::Background => Some(
+                until(deadline, self.background_slots.acquire())
+                    .await?
+                    .expect("the semaphore is never closed"),
+            ),
+        };
+        let max_attempts = opts.retries.max_attempts.max(1);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let time_left =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let result = until(deadline, attempt(time_left)).await?;
+            match result {
+                Err(status) if is_connection_broken(&status) && attempts < max_attempts => {
+                    until(deadline, tokio::time::sleep(opts.retries.backoff)).await?
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns `opts` with the `purpose` and the trace context of the call that
+/// `id` identifies, unless the caller already set them.
+///
+/// The trace context is derived from `purpose` and `id`, e.g. the subnet and
+/// the callback of a canister http request, so that the spans of the adapter
+/// can be found from the ids that the replica logs without the replica
+/// exporting spans itself.
+pub fn with_call_context(mut opts: Options, purpose: &'static str, id: &[u8]) -> Options {
+    if opts.trace_context.is_none() {
+        let mut hasher = Sha256::new();
+        hasher.write(purpose.as_bytes());
+        hasher.write(id);
+        let digest = hasher.finish();
+        let mut trace_id = [0; 16];
+        trace_id.copy_from_slice(&digest[..16]);
+        let mut span_id = [0; 8];
+        span_id.copy_from_slice(&digest[16..24]);
+        opts.trace_context = Some(TraceContext {
+            trace_id: u128::from_be_bytes(trace_id),
+            span_id: u64::from_be_bytes(span_id),
+        });
+    }
+    opts.purpose.get_or_insert(purpose);
+    opts
+}
+
+/// Wraps `message` in a request to the adapter that times out after
+/// `timeout`, if any, and carries the trace context and the purpose of `opts`
+/// as metadata. A purpose that is not a valid metadata value is not sent.
+pub fn adapter_request<T>(opts: &Options, message: T, timeout: Option<Duration>) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(timeout) = timeout {
+        request.set_timeout(timeout);
+    }
+    let metadata = request.metadata_mut();
+    if let Some(trace_context) = &opts.trace_context {
+        if let Ok(value) = MetadataValue::from_str(&trace_context.to_traceparent()) {
+            metadata.insert(TRACEPARENT_METADATA_KEY, value);
+        }
+    }
+    if let Some(purpose) = opts.purpose {
+        if let Ok(value) = MetadataValue::from_str(purpose) {
+            metadata.insert(PURPOSE_METADATA_KEY, value);
+        }
+    }
+    request
+}
+
+/// The channel reports failures to reach the adapter as `Unavailable`, a code
+/// that the adapters themselves do not return.
+pub fn is_connection_broken(status: &Status) -> bool {
+    status.code() == Code::Unavailable
+}
+
+/// Awaits `fut`, failing with `DeadlineExceeded` if it does not complete
+/// before `deadline`.
+async fn until<T>(deadline: Option<Instant>, fut: impl Future<Output = T>) -> Result<T, Status> {
+    match deadline {
+        None => Ok(fut.await),
+        Some(deadline) => timeout_at(deadline, fut)
+            .await
+            .map_err(|_| Status::deadline_exceeded("The deadline of the adapter call passed")),
+    }
+}
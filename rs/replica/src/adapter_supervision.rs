@@ -0,0 +1,233 @@
+//! Supervision of the connection from the replica to an adapter process.
+//!
+//! Adapters run as separate processes managed by systemd, so the replica must
+//! cope with an adapter that crashed or is restarting. Instead of attempting
+//! (and logging) one failing call after another, the [`AdapterSupervisor`]
+//! backs off exponentially once the connection is broken, raises a critical
+//! error if the adapter stays unreachable for too long, and resumes as soon as
+//! a call succeeds again.
+use ic_logger::{error, info, warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use prometheus::{IntCounter, IntGauge};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Timing parameters of the supervision of an adapter connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterSupervisionConfig {
+    /// The time to wait before the first call after the connection broke.
+    pub initial_backoff: Duration,
+    /// The maximum time to wait between two calls while the connection is
+    /// broken.
+    pub max_backoff: Duration,
+    /// The time after which an adapter that is still unreachable is reported
+    /// as a critical error.
+    pub critical_after: Duration,
+}
+
+impl Default for AdapterSupervisionConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            critical_after: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// The state of the connection to an adapter, as observed by the replica.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdapterConnectionState {
+    /// The last call reached the adapter.
+    Connected,
+    /// The calls fail to reach the adapter since `since`. No further call is
+    /// attempted before `retry_at`.
+    Broken {
+        since: Instant,
+        failures: u64,
+        backoff: Duration,
+        retry_at: Instant,
+        reported_critical: bool,
+    },
+}
+
+struct AdapterSupervisionMetrics {
+    connected: IntGauge,
+    connection_broken: IntCounter,
+    skipped_calls: IntCounter,
+    unreachable_critical: IntCounter,
+}
+
+impl AdapterSupervisionMetrics {
+    fn new(adapter: &str, metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            connected: metrics_registry.int_gauge(
+                format!("replica_{}_adapter_connected", adapter),
+                format!(
+                    "1 if the last call reached the {} adapter, 0 otherwise.",
+                    adapter
+                ),
+            ),
+            connection_broken: metrics_registry.int_counter(
+                format!("replica_{}_adapter_connection_broken_total", adapter),
+                format!(
+                    "Total number of calls to the {} adapter that failed to reach it.",
+                    adapter
+                ),
+            ),
+            skipped_calls: metrics_registry.int_counter(
+                format!("replica_{}_adapter_skipped_calls_total", adapter),
+                format!(
+                    "Total number of calls to the {} adapter that were not attempted due to backoff.",
+                    adapter
+                ),
+            ),
+            unreachable_critical: metrics_registry
+                .error_counter(&critical_error_adapter_unreachable(adapter)),
+        }
+    }
+}
+
+/// The name of the critical error raised when the adapter `adapter` stays
+/// unreachable.
+pub fn critical_error_adapter_unreachable(adapter: &str) -> String {
+    format!("{}_adapter_unreachable", adapter)
+}
+
+/// Tracks the connection to an adapter and decides whether calls are
+/// attempted. See the module documentation.
+pub struct AdapterSupervisor {
+    adapter: &'static str,
+    config: AdapterSupervisionConfig,
+    state: Mutex<AdapterConnectionState>,
+    metrics: AdapterSupervisionMetrics,
+    log: ReplicaLogger,
+}
+
+impl AdapterSupervisor {
+    pub fn new(
+        adapter: &'static str,
+        config: AdapterSupervisionConfig,
+        metrics_registry: &MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> Self {
+        let metrics = AdapterSupervisionMetrics::new(adapter, metrics_registry);
+        metrics.connected.set(1);
+        Self {
+            adapter,
+            config,
+            state: Mutex::new(AdapterConnectionState::Connected),
+            metrics,
+            log,
+        }
+    }
+
+    /// Returns the current state of the connection.
+    pub fn state(&self) -> AdapterConnectionState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Runs `call` unless the connection is broken and the backoff has not
+    /// elapsed yet, in which case `on_skip` is returned right away. The
+    /// outcome of `call` is classified as a broken connection or not by
+    /// `is_connection_broken`.
+    pub fn supervise<T, E>(
+        &self,
+        call: impl FnOnce() -> Result<T, E>,
+        is_connection_broken: impl Fn(&E) -> bool,
+        on_skip: impl FnOnce() -> E,
+    ) -> Result<T, E> {
+        if !self.should_attempt(Instant::now()) {
+            self.metrics.skipped_calls.inc();
+            return Err(on_skip());
+        }
+        let result = call();
+        match &result {
+            Err(err) if is_connection_broken(err) => self.record_connection_broken(Instant::now()),
+            _ => self.record_success(Instant::now()),
+        }
+        result
+    }
+
+    /// Returns whether a call is to be attempted at time `now`.
+    pub fn should_attempt(&self, now: Instant) -> bool {
+        match &*self.state.lock().unwrap() {
+            AdapterConnectionState::Connected => true,
+            AdapterConnectionState::Broken { retry_at, .. } => now >= *retry_at,
+        }
+    }
+
+    /// Records that a call reached the adapter at time `now`.
+    pub fn record_success(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if let AdapterConnectionState::Broken {
+            since, failures, ..
+        } = &*state
+        {
+            info!(
+                self.log,
+                "The {} adapter is reachable again after {} failed calls over {:?}",
+                self.adapter,
+                failures,
+                now.saturating_duration_since(*since)
+            );
+        }
+        *state = AdapterConnectionState::Connected;
+        self.metrics.connected.set(1);
+    }
+
+    /// Records that a call failed to reach the adapter at time `now`.
+    pub fn record_connection_broken(&self, now: Instant) {
+        self.metrics.connection_broken.inc();
+        self.metrics.connected.set(0);
+        let mut state = self.state.lock().unwrap();
+        let next_state = match &*state {
+            AdapterConnectionState::Connected => {
+                warn!(
+                    self.log,
+                    "The connection to the {} adapter is broken, retrying in {:?}",
+                    self.adapter,
+                    self.config.initial_backoff
+                );
+                AdapterConnectionState::Broken {
+                    since: now,
+                    failures: 1,
+                    backoff: self.config.initial_backoff,
+                    retry_at: now + self.config.initial_backoff,
+                    reported_critical: false,
+                }
+            }
+            AdapterConnectionState::Broken {
+                since,
+                failures,
+                backoff,
+                reported_critical,
+                ..
+            } => {
+                let backoff = std::cmp::min(*backoff * 2, self.config.max_backoff);
+                let broken_for = now.saturating_duration_since(*since);
+                let report_critical =
+                    !reported_critical && broken_for >= self.config.critical_after;
+                if report_critical {
+                    error!(
+                        self.log,
+                        "{}: The {} adapter has been unreachable for {:?} ({} failed calls)",
+                        critical_error_adapter_unreachable(self.adapter),
+                        self.adapter,
+                        broken_for,
+                        failures + 1
+                    );
+                    self.metrics.unreachable_critical.inc();
+                }
+                AdapterConnectionState::Broken {
+                    since: *since,
+                    failures: failures + 1,
+                    backoff,
+                    retry_at: now + backoff,
+                    reported_critical: *reported_critical || report_critical,
+                }
+            }
+        };
+        *state = next_state;
+    }
+}
@@ -0,0 +1,179 @@
+//! Receiving the successors of the bitcoin adapter over a stream.
+//!
+//! The adapter streams the blocks of a `GetSuccessors` response as chunks of
+//! their protobuf encodings, so that batches of large blocks need not fit into
+//! a single gRPC message. The replica acknowledges each block once it received
+//! all its chunks, and the adapter sends the next block only after that. If
+//! the deadline of the call passes, the replica keeps the blocks it received
+//! completely and stops the stream, so that the adapter does not send more.
+//! The `next` headers are only sent at the end of the stream, so a response
+//! that is cut short at the deadline has none. This loses no headers: the
+//! next call starts from the last block that was received, and the adapter
+//! returns the remaining blocks and the `next` headers then.
+//! Adapters that do not implement the stream are called with the unary
+//! `GetSuccessors` instead.
+use crate::adapter_calls::adapter_request;
+use futures::channel::mpsc;
+use ic_btc_adapter::{
+    get_successors_stream_request, get_successors_stream_response, BtcAdapterClient,
+    GetSuccessorsStreamRequest, GetSuccessorsStreamResponse,
+};
+use ic_interfaces::bitcoin_adapter_client::Options;
+use ic_protobuf::bitcoin::v1::{Block, BlockHeader, GetSuccessorsRequest, GetSuccessorsResponse};
+use prost::Message;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+use tonic::{transport::Channel, Code, Status};
+
+/// The maximum size of the encoding of a block that is assembled, well above
+/// the size of the encoding of any valid bitcoin block.
+pub const MAX_ASSEMBLED_BLOCK_BYTES: usize = 16 * 1024 * 1024;
+
+/// What a message of the stream completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssemblyProgress {
+    /// A chunk of a block that is still incomplete.
+    Chunk,
+    /// The block with the given index, which the replica acknowledges.
+    Block(u32),
+    /// The stream, after all its blocks.
+    End,
+}
+
+/// Assembles the messages of a stream of successors into a
+/// [`GetSuccessorsResponse`].
+#[derive(Debug, Default)]
+pub struct SuccessorsAssembler {
+    blocks: Vec<Block>,
+    partial_block: Vec<u8>,
+    next: Option<Vec<BlockHeader>>,
+}
+
+impl SuccessorsAssembler {
+    /// Adds the next message of the stream. Fails with `Internal` if the
+    /// message does not continue the stream.
+    pub fn push(
+        &mut self,
+        message: GetSuccessorsStreamResponse,
+    ) -> Result<AssemblyProgress, Status> {
+        if self.next.is_some() {
+            return Err(Status::internal("Received a message after the end"));
+        }
+        match message.response {
+            Some(get_successors_stream_response::Response::BlockChunk(chunk)) => {
+                let index = self.blocks.len() as u32;
+                if chunk.block_index != index {
+                    return Err(Status::internal(format!(
+                        "Received a chunk of block {} instead of block {}",
+                        chunk.block_index, index
+                    )));
+                }
+                if self.partial_block.len() + chunk.data.len() > MAX_ASSEMBLED_BLOCK_BYTES {
+                    return Err(Status::internal(format!(
+                        "Block {} exceeds {} bytes",
+                        index, MAX_ASSEMBLED_BLOCK_BYTES
+                    )));
+                }
+                self.partial_block.extend_from_slice(&chunk.data);
+                if !chunk.last {
+                    return Ok(AssemblyProgress::Chunk);
+                }
+                let block = Block::decode(self.partial_block.as_slice()).map_err(|err| {
+                    Status::internal(format!("Failed to decode block {}: {}", index, err))
+                })?;
+                self.partial_block.clear();
+                self.blocks.push(block);
+                Ok(AssemblyProgress::Block(index))
+            }
+            Some(get_successors_stream_response::Response::End(end)) => {
+                if !self.partial_block.is_empty() || end.block_count as usize != self.blocks.len() {
+                    return Err(Status::internal(format!(
+                        "The stream ended after {} of {} blocks",
+                        self.blocks.len(),
+                        end.block_count
+                    )));
+                }
+                self.next = Some(end.next);
+                Ok(AssemblyProgress::End)
+            }
+            None => Err(Status::internal("Received an empty message")),
+        }
+    }
+
+    /// The number of blocks that were received completely.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the blocks that were received completely, with the next
+    /// headers if the stream ended and without any otherwise.
+    pub fn into_response(self) -> GetSuccessorsResponse {
+        GetSuccessorsResponse {
+            blocks: self.blocks,
+            next: self.next.unwrap_or_default(),
+        }
+    }
+}
+
+/// Gets the successors of `request` from the adapter over a stream, as
+/// described in the [module documentation](self). The call to the adapter
+/// times out after `timeout`, if any. Once `deadline` passes, the blocks that
+/// were received completely are returned, or `DeadlineExceeded` if there are
+/// none.
+pub async fn get_successors_streamed(
+    mut client: BtcAdapterClient<Channel>,
+    opts: &Options,
+    request: GetSuccessorsRequest,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+) -> Result<GetSuccessorsResponse, Status> {
+    let (acks, outbound) = mpsc::unbounded();
+    acks.unbounded_send(GetSuccessorsStreamRequest {
+        request: Some(get_successors_stream_request::Request::Start(
+            request.clone(),
+        )),
+    })
+    .expect("the receiver is alive");
+    let mut inbound = match client
+        .get_successors_stream(adapter_request(opts, outbound, timeout))
+        .await
+    {
+        Ok(response) => response.into_inner(),
+        Err(status) if status.code() == Code::Unimplemented => {
+            return client
+                .get_successors(adapter_request(opts, request, timeout))
+                .await
+                .map(|response| response.into_inner());
+        }
+        Err(status) => return Err(status),
+    };
+
+    let mut assembler = SuccessorsAssembler::default();
+    loop {
+        let message = match deadline {
+            None => inbound.message().await?,
+            Some(deadline) => match timeout_at(deadline, inbound.message()).await {
+                Ok(message) => message?,
+                Err(_) if assembler.block_count() > 0 => return Ok(assembler.into_response()),
+                Err(_) => {
+                    return Err(Status::deadline_exceeded(
+                        "No block was received before the deadline of the call",
+                    ))
+                }
+            },
+        };
+        let message = message
+            .ok_or_else(|| Status::internal("The stream of successors ended before its end"))?;
+        match assembler.push(message)? {
+            AssemblyProgress::Chunk => {}
+            AssemblyProgress::Block(index) => {
+                // The adapter stops sending if the stream is gone, which is
+                // noticed on the next message.
+                let _ = acks.unbounded_send(GetSuccessorsStreamRequest {
+                    request: Some(get_successors_stream_request::Request::AckBlockIndex(index)),
+                });
+            }
+            AssemblyProgress::End => return Ok(assembler.into_response()),
+        }
+    }
+}
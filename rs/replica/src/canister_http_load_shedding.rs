@@ -0,0 +1,155 @@
+//! Shedding of canister http requests while the adapter is slow.
+//!
+//! The client of the canister http adapter records how long the adapter takes
+//! to reply to each request. While the 95th percentile of the recent latencies
+//! exceeds a threshold, new requests whose replies are prone to diverge
+//! between the replicas of the subnet are shed: the client hands them back as
+//! if it were full, which delays them until the caller sends them again.
+//! Such requests are unlikely to reach consensus when the replicas fetch them
+//! at very different times, so shedding them first keeps outcalls from piling
+//! up and slowing down the block rate. Latencies expire after a while, so the
+//! client stops shedding even if only divergence-prone requests are made.
+use ic_config::adapters::CanisterHttpLoadSheddingConfig;
+use ic_metrics::MetricsRegistry;
+use ic_types::canister_http::CanisterHttpRequest;
+use prometheus::{Gauge, IntCounter, IntGauge};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The configuration of a [`LatencyLoadShedder`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadSheddingConfig {
+    /// The 95th percentile of the adapter latency above which
+    /// divergence-prone requests are shed.
+    pub latency_threshold: Duration,
+    /// The maximum number of recent latencies the percentile is computed
+    /// over.
+    pub max_samples: usize,
+    /// The age after which a latency no longer counts towards the
+    /// percentile.
+    pub max_sample_age: Duration,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self::from(&CanisterHttpLoadSheddingConfig::default())
+    }
+}
+
+impl From<&CanisterHttpLoadSheddingConfig> for LoadSheddingConfig {
+    fn from(config: &CanisterHttpLoadSheddingConfig) -> Self {
+        Self {
+            latency_threshold: Duration::from_millis(config.latency_threshold_millis),
+            max_samples: config.max_samples,
+            max_sample_age: Duration::from_millis(config.max_sample_age_millis),
+        }
+    }
+}
+
+/// Returns whether the reply to `request` is prone to diverge between
+/// replicas, i.e. whether the request has no transform to normalize it.
+pub fn is_divergence_prone(request: &CanisterHttpRequest) -> bool {
+    request.content.transform_method_name.is_none()
+}
+
+#[derive(Clone)]
+struct LoadSheddingMetrics {
+    latency_p95: Gauge,
+    shedding: IntGauge,
+    shed_requests: IntCounter,
+}
+
+impl LoadSheddingMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            latency_p95: metrics_registry.gauge(
+                "replica_canister_http_client_adapter_latency_p95_seconds",
+                "95th percentile of the recent latencies of the canister http adapter.",
+            ),
+            shedding: metrics_registry.int_gauge(
+                "replica_canister_http_client_shedding",
+                "Whether divergence-prone canister http requests are shed (1) or not (0).",
+            ),
+            shed_requests: metrics_registry.int_counter(
+                "replica_canister_http_client_shed_total",
+                "Total number of canister http requests shed because the adapter was slow.",
+            ),
+        }
+    }
+}
+
+/// Decides whether to shed canister http requests from the recent latencies
+/// of the adapter, as described in the [module documentation](self).
+///
+/// Clones share their latencies.
+#[derive(Clone)]
+pub struct LatencyLoadShedder {
+    config: LoadSheddingConfig,
+    latencies: Arc<Mutex<VecDeque<(Instant, Duration)>>>,
+    metrics: LoadSheddingMetrics,
+}
+
+impl LatencyLoadShedder {
+    pub fn new(config: LoadSheddingConfig, metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            latencies: Arc::new(Mutex::new(VecDeque::with_capacity(config.max_samples))),
+            config,
+            metrics: LoadSheddingMetrics::new(metrics_registry),
+        }
+    }
+
+    /// Records that the adapter took `latency` to reply to a request.
+    pub fn record_latency(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        latencies.push_back((Instant::now(), latency));
+        while latencies.len() > self.config.max_samples {
+            latencies.pop_front();
+        }
+        self.update(&mut latencies);
+    }
+
+    /// Returns whether the p95 latency of the adapter exceeds the threshold.
+    pub fn is_shedding(&self) -> bool {
+        self.update(&mut self.latencies.lock().unwrap())
+    }
+
+    /// Returns whether `request` is to be shed, counting it if so.
+    pub fn should_shed(&self, request: &CanisterHttpRequest) -> bool {
+        let shed = is_divergence_prone(request) && self.is_shedding();
+        if shed {
+            self.metrics.shed_requests.inc();
+        }
+        shed
+    }
+
+    /// Drops the expired latencies and updates the metrics, returning whether
+    /// requests are shed.
+    fn update(&self, latencies: &mut VecDeque<(Instant, Duration)>) -> bool {
+        let now = Instant::now();
+        while let Some((recorded, _)) = latencies.front() {
+            if now.saturating_duration_since(*recorded) <= self.config.max_sample_age {
+                break;
+            }
+            latencies.pop_front();
+        }
+        let p95 = p95(latencies.iter().map(|(_, latency)| *latency).collect());
+        let shedding = p95 > self.config.latency_threshold;
+        self.metrics.latency_p95.set(p95.as_secs_f64());
+        self.metrics.shedding.set(shedding as i64);
+        shedding
+    }
+}
+
+/// Returns the 95th percentile of `latencies` by the nearest-rank method, or
+/// zero if there are none.
+fn p95(mut latencies: Vec<Duration>) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    latencies.sort_unstable();
+    let rank = (latencies.len() * 95 + 99) / 100;
+    latencies[rank - 1]
+}
@@ -1,4 +1,9 @@
+pub mod adapter_calls;
+pub mod adapter_supervision;
 pub mod args;
+pub mod bitcoin_successors;
+pub mod canister_http_load_shedding;
 pub mod setup;
 pub mod setup_bitcoin_client;
+pub mod setup_canister_http_client;
 pub mod setup_p2p;
@@ -289,6 +289,7 @@ async fn run() -> io::Result<()> {
         consensus_pool_cache,
         ingress_message_filter,
         _xnet_endpoint,
+        _canister_http_adapter_client,
     ) = ic_replica::setup_p2p::construct_ic_stack(
         logger.clone(),
         config.clone(),
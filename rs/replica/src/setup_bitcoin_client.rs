@@ -1,43 +1,95 @@
+use crate::adapter_calls::{self, AdapterCallScheduler};
+use crate::adapter_supervision::{AdapterSupervisionConfig, AdapterSupervisor};
+use crate::bitcoin_successors::get_successors_streamed;
+use ic_adapter_metrics::{AdapterMetricsRelay, DEFAULT_SCRAPE_INTERVAL};
 use ic_btc_adapter::BtcAdapterClient;
+use ic_crypto_sha::Sha256;
 use ic_interfaces::bitcoin_adapter_client::{BitcoinAdapterClient, Options, RpcError, RpcResult};
 use ic_logger::{error, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
 use ic_protobuf::bitcoin::v1::{
     GetSuccessorsRequest, GetSuccessorsResponse, SendTransactionRequest, SendTransactionResponse,
 };
 use std::{convert::TryFrom, path::PathBuf, sync::Arc};
-use tokio::net::UnixStream;
+use tokio::{net::UnixStream, time::Instant};
 use tonic::transport::{Channel, Endpoint, Uri};
 use tower::service_fn;
 
 struct BitcoinAdapterClientImpl {
     rt_handle: tokio::runtime::Handle,
     client: BtcAdapterClient<Channel>,
+    supervisor: AdapterSupervisor,
+    scheduler: AdapterCallScheduler,
 }
 
+/// The number of background calls that may be in flight to the bitcoin
+/// adapter at once.
+const MAX_BACKGROUND_CALLS: usize = 2;
+
+/// The purpose of the calls that get the successors of an anchor block.
+const GET_SUCCESSORS_PURPOSE: &str = "get_successors";
+
+/// The purpose of the calls that send a transaction.
+const SEND_TRANSACTION_PURPOSE: &str = "send_transaction";
+
 impl BitcoinAdapterClientImpl {
-    fn new(rt_handle: tokio::runtime::Handle, channel: Channel) -> Self {
+    fn new(
+        rt_handle: tokio::runtime::Handle,
+        channel: Channel,
+        supervisor: AdapterSupervisor,
+    ) -> Self {
         let client = BtcAdapterClient::new(channel);
-        Self { rt_handle, client }
+        Self {
+            rt_handle,
+            client,
+            supervisor,
+            scheduler: AdapterCallScheduler::new(MAX_BACKGROUND_CALLS),
+        }
+    }
+}
+
+fn rpc_error_from_status(status: tonic::Status) -> RpcError {
+    if adapter_calls::is_connection_broken(&status) {
+        RpcError::ConnectionBroken
+    } else {
+        RpcError::ServerError(status)
     }
 }
 
+fn is_connection_broken(err: &RpcError) -> bool {
+    matches!(err, RpcError::ConnectionBroken)
+}
+
 impl BitcoinAdapterClient for BitcoinAdapterClientImpl {
     fn get_successors(
         &self,
         request: GetSuccessorsRequest,
         opts: Options,
     ) -> RpcResult<GetSuccessorsResponse> {
-        let mut client = self.client.clone();
-        self.rt_handle.block_on(async move {
-            let mut tonic_request = tonic::Request::new(request);
-            if let Some(timeout) = opts.timeout {
-                tonic_request.set_timeout(timeout);
-            }
-            match client.get_successors(tonic_request).await {
-                Ok(tonic_response) => Ok(tonic_response.into_inner()),
-                Err(tonic_status) => Err(RpcError::ServerError(tonic_status)),
-            }
-        })
+        let opts = adapter_calls::with_call_context(opts, GET_SUCCESSORS_PURPOSE, &request.anchor);
+        self.supervisor.supervise(
+            || {
+                self.rt_handle.block_on(async {
+                    // The blocks received until this deadline are kept, so it
+                    // must not pass after the deadline of the scheduler.
+                    let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+                    self.scheduler
+                        .call(&opts, |timeout| {
+                            get_successors_streamed(
+                                self.client.clone(),
+                                &opts,
+                                request.clone(),
+                                timeout,
+                                deadline,
+                            )
+                        })
+                        .await
+                        .map_err(rpc_error_from_status)
+                })
+            },
+            is_connection_broken,
+            || RpcError::ConnectionBroken,
+        )
     }
 
     fn send_transaction(
@@ -45,17 +97,33 @@ impl BitcoinAdapterClient for BitcoinAdapterClientImpl {
         request: SendTransactionRequest,
         opts: Options,
     ) -> RpcResult<SendTransactionResponse> {
-        let mut client = self.client.clone();
-        self.rt_handle.block_on(async move {
-            let mut tonic_request = tonic::Request::new(request);
-            if let Some(timeout) = opts.timeout {
-                tonic_request.set_timeout(timeout);
-            }
-            match client.send_transaction(tonic_request).await {
-                Ok(tonic_response) => Ok(tonic_response.into_inner()),
-                Err(tonic_status) => Err(RpcError::ServerError(tonic_status)),
-            }
-        })
+        let opts = adapter_calls::with_call_context(
+            opts,
+            SEND_TRANSACTION_PURPOSE,
+            &Sha256::hash(&request.raw_tx),
+        );
+        self.supervisor.supervise(
+            || {
+                self.rt_handle.block_on(async {
+                    self.scheduler
+                        .call(&opts, |timeout| {
+                            let mut client = self.client.clone();
+                            let tonic_request =
+                                adapter_calls::adapter_request(&opts, request.clone(), timeout);
+                            async move {
+                                client
+                                    .send_transaction(tonic_request)
+                                    .await
+                                    .map(|tonic_response| tonic_response.into_inner())
+                            }
+                        })
+                        .await
+                        .map_err(rpc_error_from_status)
+                })
+            },
+            is_connection_broken,
+            || RpcError::ConnectionBroken,
+        )
     }
 }
 
@@ -79,8 +147,13 @@ impl BitcoinAdapterClient for BrokenConnectionBitcoinClient {
     }
 }
 
+/// Sets up the client of the bitcoin adapter listening at `uds_path`. While the
+/// adapter is unreachable, calls are backed off as described in
+/// [`crate::adapter_supervision`]. The metrics of the adapter are relayed into
+/// `metrics_registry`.
 pub fn setup_bitcoin_client(
     log: ReplicaLogger,
+    metrics_registry: &MetricsRegistry,
     rt_handle: tokio::runtime::Handle,
     uds_path: Option<PathBuf>,
 ) -> Arc<dyn BitcoinAdapterClient> {
@@ -96,7 +169,23 @@ pub fn setup_bitcoin_client(
                         // Connect to a Uds socket
                         UnixStream::connect(uds_path.clone())
                     })) {
-                        Ok(channel) => Arc::new(BitcoinAdapterClientImpl::new(rt_handle, channel)),
+                        Ok(channel) => {
+                            metrics_registry.register(AdapterMetricsRelay::new(
+                                "bitcoin",
+                                channel.clone(),
+                                &rt_handle,
+                                DEFAULT_SCRAPE_INTERVAL,
+                            ));
+                            let supervisor = AdapterSupervisor::new(
+                                "bitcoin",
+                                AdapterSupervisionConfig::default(),
+                                metrics_registry,
+                                log,
+                            );
+                            Arc::new(BitcoinAdapterClientImpl::new(
+                                rt_handle, channel, supervisor,
+                            ))
+                        }
                         Err(_) => {
                             error!(log, "Could not connect endpoint.");
                             Arc::new(BrokenConnectionBitcoinClient())
@@ -0,0 +1,326 @@
+//! The client of the canister http adapter.
+//!
+//! Requests are sent from the execution and consensus threads without
+//! blocking, and their replies are picked up later. The number of requests
+//! whose replies have not been picked up yet is bounded: once the bound is
+//! reached, [`NonBlockingChannel::send`] fails with
+//! [`RpcBridgeSendError::Full`], handing the request back to the caller, so a
+//! flood of canister http requests is pushed back to the caller instead of
+//! piling up in the memory of the client. While the adapter is slow,
+//! divergence-prone requests are pushed back in the same way, see
+//! [`canister_http_load_shedding`](crate::canister_http_load_shedding).
+use crate::adapter_calls::{self, AdapterCallScheduler};
+use crate::canister_http_load_shedding::{LatencyLoadShedder, LoadSheddingConfig};
+use ic_adapter_metrics::{AdapterMetricsRelay, DEFAULT_SCRAPE_INTERVAL};
+use ic_canister_http_service::http_adapter_client::HttpAdapterClient;
+use ic_interfaces::{
+    adapter_client::{Options, OptionsBuilder, RetryPolicy},
+    canister_http::{CanisterHttpAdapterClient, NonBlockingChannel},
+    execution_environment::QueryHandler,
+    rpc_bridge::{RpcBridgeReceiveError, RpcBridgeSendError},
+    state_manager::StateReader,
+};
+use ic_logger::{error, warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_replicated_state::ReplicatedState;
+use ic_types::canister_http::{
+    CanisterHttpReply, CanisterHttpRequest, DEFAULT_MAX_CONCURRENT_CANISTER_HTTP_REQUESTS,
+};
+use ic_types::SubnetId;
+use prometheus::{IntCounter, IntGauge};
+use std::{
+    convert::TryFrom,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::UnixStream,
+    sync::mpsc::{
+        channel,
+        error::{TryRecvError, TrySendError},
+        Receiver, Sender,
+    },
+};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+/// The number of requests whose replies may be pending in the client if not
+/// configured otherwise. It matches the default number of requests that
+/// execution lets be in flight on a subnet.
+pub const DEFAULT_CANISTER_HTTP_REQUESTS_IN_FLIGHT: usize =
+    DEFAULT_MAX_CONCURRENT_CANISTER_HTTP_REQUESTS as usize;
+
+/// The options of the calls to the canister http adapter. Each request is
+/// sent only once, since outgoing requests need not be idempotent, and
+/// without a deadline, since the adapter bounds the time of each outgoing
+/// request itself.
+pub fn canister_http_adapter_options() -> Options {
+    OptionsBuilder::new(Options::background())
+        .timeout(None)
+        .retries(RetryPolicy::no_retries())
+        .build()
+}
+
+/// The purpose of the calls that send a canister http request.
+const CANISTER_HTTP_PURPOSE: &str = "canister_http_request";
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Sends a request to the adapter and resolves to its reply.
+pub type SendToAdapter = Box<
+    dyn FnMut(CanisterHttpRequest) -> BoxFuture<Result<CanisterHttpReply, tonic::Status>> + Send,
+>;
+
+/// Wraps `send_to_adapter` so that the transform function of the requesting
+/// canister runs on each reply, against the latest state, before consensus
+/// picks the reply up. A reply whose transform fails, e.g. because it
+/// exceeds the instruction budget of the subnet, is dropped like a failed
+/// call.
+pub fn transform_replies(
+    mut send_to_adapter: SendToAdapter,
+    query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+) -> SendToAdapter {
+    Box::new(move |request: CanisterHttpRequest| {
+        let context = request.content.clone();
+        let reply_fut = send_to_adapter(request);
+        let query_handler = Arc::clone(&query_handler);
+        let state_reader = Arc::clone(&state_reader);
+        Box::pin(async move {
+            let reply = reply_fut.await?;
+            // The transform executes canister code, which must not block the
+            // threads of the runtime.
+            tokio::task::spawn_blocking(move || {
+                let state = state_reader.get_latest_state().take();
+                query_handler.transform_canister_http_reply(&context, reply, state)
+            })
+            .await
+            .map_err(|err| tonic::Status::internal(err.to_string()))?
+            .map_err(|err| tonic::Status::aborted(err.to_string()))
+        })
+    })
+}
+
+struct CanisterHttpClientMetrics {
+    in_flight: IntGauge,
+    rejected_full: IntCounter,
+    failed_calls: IntCounter,
+}
+
+impl CanisterHttpClientMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            in_flight: metrics_registry.int_gauge(
+                "replica_canister_http_client_requests_in_flight",
+                "Number of canister http requests whose replies were not picked up yet.",
+            ),
+            rejected_full: metrics_registry.int_counter(
+                "replica_canister_http_client_rejected_full_total",
+                "Total number of canister http requests rejected because too many were in flight.",
+            ),
+            failed_calls: metrics_registry.int_counter(
+                "replica_canister_http_client_failed_calls_total",
+                "Total number of canister http requests for which the adapter returned no reply.",
+            ),
+        }
+    }
+}
+
+/// A [`NonBlockingChannel`] to the canister http adapter that holds at most
+/// `inflight_requests` requests whose replies were not received yet, and that
+/// sheds divergence-prone requests while the adapter is slow. At least one
+/// request is let in flight, even if `inflight_requests` is 0.
+///
+/// A request for which the adapter does not return a reply is dropped after
+/// logging the error. It releases its slot, and the request context times out
+/// in execution.
+pub struct BoundedCanisterHttpClient {
+    rt_handle: tokio::runtime::Handle,
+    send_to_adapter: SendToAdapter,
+    tx: Sender<CanisterHttpReply>,
+    rx: Receiver<CanisterHttpReply>,
+    metrics: CanisterHttpClientMetrics,
+    load_shedder: LatencyLoadShedder,
+    log: ReplicaLogger,
+}
+
+impl BoundedCanisterHttpClient {
+    pub fn new(
+        rt_handle: tokio::runtime::Handle,
+        inflight_requests: usize,
+        send_to_adapter: SendToAdapter,
+        load_shedding: LoadSheddingConfig,
+        metrics_registry: &MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> Self {
+        let (tx, rx) = channel(inflight_requests.max(1));
+        Self {
+            rt_handle,
+            send_to_adapter,
+            tx,
+            rx,
+            metrics: CanisterHttpClientMetrics::new(metrics_registry),
+            load_shedder: LatencyLoadShedder::new(load_shedding, metrics_registry),
+            log,
+        }
+    }
+}
+
+impl NonBlockingChannel<CanisterHttpRequest> for BoundedCanisterHttpClient {
+    type Response = CanisterHttpReply;
+
+    fn send(
+        &mut self,
+        request: CanisterHttpRequest,
+    ) -> Result<(), RpcBridgeSendError<CanisterHttpRequest>> {
+        if self.load_shedder.should_shed(&request) {
+            return Err(RpcBridgeSendError::Full(request));
+        }
+        // Accept the request iff there is capacity for its reply.
+        let permit = match self.tx.clone().try_reserve_owned() {
+            Ok(permit) => permit,
+            Err(TrySendError::Full(_)) => {
+                self.metrics.rejected_full.inc();
+                return Err(RpcBridgeSendError::Full(request));
+            }
+            Err(TrySendError::Closed(_)) => return Err(RpcBridgeSendError::Closed(request)),
+        };
+        let id = request.id;
+        let reply_fut = (self.send_to_adapter)(request);
+        let in_flight = self.metrics.in_flight.clone();
+        let failed_calls = self.metrics.failed_calls.clone();
+        let load_shedder = self.load_shedder.clone();
+        let log = self.log.clone();
+        in_flight.inc();
+        let started = Instant::now();
+        self.rt_handle.spawn(async move {
+            let reply = reply_fut.await;
+            load_shedder.record_latency(started.elapsed());
+            match reply {
+                Ok(reply) => permit.send(reply),
+                Err(status) => {
+                    in_flight.dec();
+                    failed_calls.inc();
+                    warn!(
+                        log,
+                        "Canister http request {} failed in the adapter: {}", id, status
+                    );
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn try_receive(&mut self) -> Result<CanisterHttpReply, RpcBridgeReceiveError> {
+        match self.rx.try_recv() {
+            Ok(reply) => {
+                self.metrics.in_flight.dec();
+                Ok(reply)
+            }
+            Err(TryRecvError::Empty) => Err(RpcBridgeReceiveError::Empty),
+            Err(TryRecvError::Disconnected) => Err(RpcBridgeReceiveError::Disconnected),
+        }
+    }
+}
+
+struct BrokenConnectionCanisterHttpClient();
+
+impl NonBlockingChannel<CanisterHttpRequest> for BrokenConnectionCanisterHttpClient {
+    type Response = CanisterHttpReply;
+
+    fn send(
+        &mut self,
+        request: CanisterHttpRequest,
+    ) -> Result<(), RpcBridgeSendError<CanisterHttpRequest>> {
+        Err(RpcBridgeSendError::Closed(request))
+    }
+
+    fn try_receive(&mut self) -> Result<CanisterHttpReply, RpcBridgeReceiveError> {
+        Err(RpcBridgeReceiveError::Disconnected)
+    }
+}
+
+/// Sets up the client of the canister http adapter listening at `uds_path`,
+/// holding at most `inflight_requests` requests whose replies were not
+/// received yet. The requests are sent to the adapter with the options
+/// `opts`, with the trace context of each request of `subnet_id`, and their
+/// replies are transformed with `query_handler`. Divergence-prone requests
+/// are shed as configured by `load_shedding` while the adapter is slow. The
+/// metrics of the adapter are relayed into `metrics_registry`.
+#[allow(clippy::too_many_arguments)]
+pub fn setup_canister_http_client(
+    log: ReplicaLogger,
+    metrics_registry: &MetricsRegistry,
+    rt_handle: tokio::runtime::Handle,
+    subnet_id: SubnetId,
+    uds_path: Option<PathBuf>,
+    inflight_requests: usize,
+    load_shedding: LoadSheddingConfig,
+    pseudo_random_delay_window: Duration,
+    opts: Options,
+    query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+) -> Box<dyn CanisterHttpAdapterClient> {
+    let uds_path = match uds_path {
+        None => return Box::new(BrokenConnectionCanisterHttpClient()),
+        Some(uds_path) => uds_path,
+    };
+    // We will ignore this uri because uds do not use it.
+    let endpoint = match Endpoint::try_from("http://[::]:50051") {
+        Ok(endpoint) => endpoint,
+        Err(_) => {
+            error!(log, "Could not create an endpoint.");
+            return Box::new(BrokenConnectionCanisterHttpClient());
+        }
+    };
+    let channel: Channel = match endpoint.connect_with_connector_lazy(service_fn(move |_: Uri| {
+        // Connect to a Uds socket
+        UnixStream::connect(uds_path.clone())
+    })) {
+        Ok(channel) => channel,
+        Err(_) => {
+            error!(log, "Could not connect endpoint.");
+            return Box::new(BrokenConnectionCanisterHttpClient());
+        }
+    };
+    metrics_registry.register(AdapterMetricsRelay::new(
+        "canister_http",
+        channel.clone(),
+        &rt_handle,
+        DEFAULT_SCRAPE_INTERVAL,
+    ));
+    let client = HttpAdapterClient::new(channel);
+    // The number of requests in flight is already bounded by the client.
+    let scheduler = AdapterCallScheduler::new(inflight_requests);
+    let send_to_adapter: SendToAdapter = Box::new(move |request: CanisterHttpRequest| {
+        let client = client.clone();
+        let scheduler = scheduler.clone();
+        let opts = opts.clone();
+        Box::pin(async move {
+            let id = request.id;
+            let pb_request = request.to_adapter_request(pseudo_random_delay_window);
+            let call_id = [subnet_id.get_ref().as_slice(), &id.get().to_be_bytes()].concat();
+            let opts = &adapter_calls::with_call_context(opts, CANISTER_HTTP_PURPOSE, &call_id);
+            scheduler
+                .call(opts, move |timeout| {
+                    let mut client = client.clone();
+                    let tonic_request =
+                        adapter_calls::adapter_request(opts, pb_request.clone(), timeout);
+                    async move { client.send_http_request(tonic_request).await }
+                })
+                .await
+                .map(|response| CanisterHttpReply::from_adapter_response(id, response.into_inner()))
+        })
+    });
+    Box::new(BoundedCanisterHttpClient::new(
+        rt_handle,
+        inflight_requests,
+        transform_replies(send_to_adapter, query_handler, state_reader),
+        load_shedding,
+        metrics_registry,
+        log,
+    ))
+}
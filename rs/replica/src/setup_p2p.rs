@@ -1,9 +1,15 @@
+use crate::canister_http_load_shedding::LoadSheddingConfig;
+use crate::setup_canister_http_client::{
+    canister_http_adapter_options, setup_canister_http_client,
+    DEFAULT_CANISTER_HTTP_REQUESTS_IN_FLIGHT,
+};
 use ic_config::{artifact_pool::ArtifactPoolConfig, subnet_config::SubnetConfig, Config};
 use ic_consensus::certification::VerifierImpl;
 use ic_crypto::CryptoComponent;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::setup_execution;
 use ic_interfaces::{
+    canister_http::CanisterHttpAdapterClient,
     certified_stream_store::CertifiedStreamStore,
     consensus_pool::ConsensusPoolCache,
     execution_environment::{IngressFilterService, QueryExecutionService, QueryHandler},
@@ -21,7 +27,7 @@ use ic_replica_setup_ic_network::{
 use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_types::{consensus::catchup::CUPWithOriginalProtobuf, NodeId, SubnetId};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn construct_ic_stack(
@@ -48,6 +54,7 @@ pub fn construct_ic_stack(
     Arc<dyn ConsensusPoolCache>,
     IngressFilterService,
     XNetEndpoint,
+    Box<dyn CanisterHttpAdapterClient>,
 )> {
     let artifact_pool_config = ArtifactPoolConfig::from(config.artifact_pool);
 
@@ -162,6 +169,24 @@ pub fn construct_ic_stack(
         Arc::clone(&state_manager) as Arc<_>,
     );
 
+    let canister_http_adapter_client = setup_canister_http_client(
+        replica_logger.clone(),
+        &metrics_registry,
+        tokio::runtime::Handle::current(),
+        subnet_id,
+        config.adapters_config.canister_http_uds_path.clone(),
+        DEFAULT_CANISTER_HTTP_REQUESTS_IN_FLIGHT,
+        LoadSheddingConfig::from(&config.adapters_config.canister_http_load_shedding),
+        Duration::from_millis(
+            config
+                .adapters_config
+                .canister_http_pseudo_random_delay_window_millis,
+        ),
+        canister_http_adapter_options(),
+        Arc::clone(&sync_query_handler),
+        Arc::clone(&state_manager) as Arc<_>,
+    );
+
     let certified_stream_store: Arc<dyn CertifiedStreamStore> =
         Arc::clone(&state_manager) as Arc<_>;
 
@@ -263,5 +288,6 @@ pub fn construct_ic_stack(
         artifact_pools.consensus_pool_cache,
         ingress_filter,
         xnet_endpoint,
+        canister_http_adapter_client,
     ))
 }
@@ -0,0 +1,228 @@
+use ic_interfaces::adapter_client::{
+    Options, Priority, RetryPolicy, TraceContext, PURPOSE_METADATA_KEY, TRACEPARENT_METADATA_KEY,
+};
+use ic_replica::adapter_calls::{adapter_request, with_call_context, AdapterCallScheduler};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tonic::{Code, Status};
+
+fn options(timeout: Option<Duration>, max_attempts: u32, priority: Priority) -> Options {
+    Options::builder()
+        .timeout(timeout)
+        .retries(RetryPolicy::attempts(
+            max_attempts,
+            Duration::from_millis(1),
+        ))
+        .priority(priority)
+        .build()
+}
+
+/// Returns a call that fails with `status` the first `failures` times it is
+/// attempted, and the number of its attempts.
+fn failing_call(
+    failures: u32,
+    status: Status,
+) -> (
+    impl FnMut(Option<Duration>) -> std::future::Ready<Result<u32, Status>>,
+    Arc<AtomicU32>,
+) {
+    let attempts = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&attempts);
+    let call = move |_timeout| {
+        let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt <= failures {
+            std::future::ready(Err(status.clone()))
+        } else {
+            std::future::ready(Ok(attempt))
+        }
+    };
+    (call, attempts)
+}
+
+#[tokio::test]
+async fn should_retry_calls_that_failed_to_reach_the_adapter() {
+    let scheduler = AdapterCallScheduler::new(1);
+    let opts = options(None, 3, Priority::ConsensusCritical);
+
+    let (call, _) = failing_call(2, Status::unavailable("adapter is down"));
+    assert_eq!(scheduler.call(&opts, call).await.unwrap(), 3);
+
+    let (call, attempts) = failing_call(3, Status::unavailable("adapter is down"));
+    assert_eq!(
+        scheduler.call(&opts, call).await.unwrap_err().code(),
+        Code::Unavailable
+    );
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn should_not_retry_calls_that_reached_the_adapter() {
+    let scheduler = AdapterCallScheduler::new(1);
+    let (call, attempts) = failing_call(1, Status::internal("adapter failed"));
+
+    let result = scheduler
+        .call(&options(None, 3, Priority::ConsensusCritical), call)
+        .await;
+
+    assert_eq!(result.unwrap_err().code(), Code::Internal);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_attempt_once_without_retries() {
+    let scheduler = AdapterCallScheduler::new(1);
+    let (call, attempts) = failing_call(1, Status::unavailable("adapter is down"));
+
+    let result = scheduler.call(&Options::consensus_critical(), call).await;
+
+    assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_fail_calls_past_their_deadline() {
+    let scheduler = AdapterCallScheduler::new(1);
+    let opts = options(
+        Some(Duration::from_millis(10)),
+        1,
+        Priority::ConsensusCritical,
+    );
+
+    let result = scheduler
+        .call(&opts, |timeout| {
+            assert!(timeout.unwrap() <= Duration::from_millis(10));
+            std::future::pending::<Result<(), Status>>()
+        })
+        .await;
+
+    assert_eq!(result.unwrap_err().code(), Code::DeadlineExceeded);
+}
+
+#[tokio::test]
+async fn should_bound_background_calls_only() {
+    let scheduler = AdapterCallScheduler::new(1);
+    let background = options(Some(Duration::from_millis(10)), 1, Priority::Background);
+    let (release, released) = tokio::sync::oneshot::channel::<()>();
+    let mut released = Some(released);
+    let in_flight = {
+        let scheduler = scheduler.clone();
+        let background = background.clone();
+        tokio::spawn(async move {
+            scheduler
+                .call(
+                    &Options {
+                        timeout: None,
+                        ..background
+                    },
+                    move |_timeout| {
+                        let released = released.take().unwrap();
+                        async move {
+                            released.await.unwrap();
+                            Ok::<_, Status>(())
+                        }
+                    },
+                )
+                .await
+        })
+    };
+    tokio::task::yield_now().await;
+
+    // The only background slot is taken, so further background calls wait
+    // until their deadline while consensus critical calls go ahead.
+    let (call, _) = failing_call(0, Status::internal("unused"));
+    assert_eq!(
+        scheduler.call(&background, call).await.unwrap_err().code(),
+        Code::DeadlineExceeded
+    );
+    let (call, _) = failing_call(0, Status::internal("unused"));
+    assert_eq!(
+        scheduler
+            .call(
+                &options(
+                    Some(Duration::from_millis(10)),
+                    1,
+                    Priority::ConsensusCritical
+                ),
+                call
+            )
+            .await
+            .unwrap(),
+        1
+    );
+
+    release.send(()).unwrap();
+    in_flight.await.unwrap().unwrap();
+    let (call, _) = failing_call(0, Status::internal("unused"));
+    assert_eq!(scheduler.call(&background, call).await.unwrap(), 1);
+}
+
+#[test]
+fn should_send_trace_context_and_purpose_as_metadata() {
+    let opts = Options::builder()
+        .trace_context(TraceContext {
+            trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+            span_id: 0x00f067aa0ba902b7,
+        })
+        .purpose("get_successors")
+        .build();
+
+    let request = adapter_request(&opts, (), Some(Duration::from_millis(10)));
+
+    let metadata = request.metadata();
+    assert_eq!(
+        metadata.get(TRACEPARENT_METADATA_KEY).unwrap(),
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+    );
+    assert_eq!(
+        metadata.get(PURPOSE_METADATA_KEY).unwrap(),
+        "get_successors"
+    );
+    assert_eq!(metadata.get("grpc-timeout").unwrap(), "10000000n");
+}
+
+#[test]
+fn should_send_no_metadata_by_default() {
+    let request = adapter_request(&Options::default(), (), None);
+
+    assert!(request.metadata().is_empty());
+}
+
+#[test]
+fn should_derive_trace_context_of_call_and_set_purpose() {
+    let opts = with_call_context(Options::background(), "get_successors", b"anchor");
+
+    assert_eq!(opts.purpose, Some("get_successors"));
+    assert!(opts.trace_context.is_some());
+    assert_eq!(
+        opts.trace_context,
+        with_call_context(Options::default(), "get_successors", b"anchor").trace_context
+    );
+    assert_ne!(
+        opts.trace_context,
+        with_call_context(Options::default(), "get_successors", b"other anchor").trace_context
+    );
+    assert_ne!(
+        opts.trace_context,
+        with_call_context(Options::default(), "send_transaction", b"anchor").trace_context
+    );
+}
+
+#[test]
+fn should_keep_trace_context_and_purpose_of_caller() {
+    let trace_context = TraceContext {
+        trace_id: 1,
+        span_id: 2,
+    };
+    let opts = Options::builder()
+        .trace_context(trace_context)
+        .purpose("caller")
+        .build();
+
+    let opts = with_call_context(opts, "get_successors", b"anchor");
+
+    assert_eq!(opts.trace_context, Some(trace_context));
+    assert_eq!(opts.purpose, Some("caller"));
+}
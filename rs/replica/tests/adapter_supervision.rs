@@ -0,0 +1,118 @@
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_replica::adapter_supervision::{
+    AdapterConnectionState, AdapterSupervisionConfig, AdapterSupervisor,
+};
+use std::time::{Duration, Instant};
+
+fn config() -> AdapterSupervisionConfig {
+    AdapterSupervisionConfig {
+        initial_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(4),
+        critical_after: Duration::from_secs(10),
+    }
+}
+
+fn supervisor(metrics_registry: &MetricsRegistry) -> AdapterSupervisor {
+    AdapterSupervisor::new("test", config(), metrics_registry, no_op_logger())
+}
+
+fn critical_errors(metrics_registry: &MetricsRegistry) -> u64 {
+    metrics_registry
+        .prometheus_registry()
+        .gather()
+        .iter()
+        .filter(|family| family.get_name() == "critical_errors")
+        .flat_map(|family| family.get_metric().iter())
+        .map(|metric| metric.get_counter().get_value() as u64)
+        .sum()
+}
+
+#[test]
+fn should_back_off_exponentially_while_broken() {
+    let metrics_registry = MetricsRegistry::new();
+    let supervisor = supervisor(&metrics_registry);
+    let start = Instant::now();
+
+    assert!(supervisor.should_attempt(start));
+    supervisor.record_connection_broken(start);
+    assert!(!supervisor.should_attempt(start));
+    assert!(supervisor.should_attempt(start + Duration::from_secs(1)));
+
+    let mut now = start + Duration::from_secs(1);
+    for expected_backoff in [2, 4, 4].iter() {
+        supervisor.record_connection_broken(now);
+        let backoff = Duration::from_secs(*expected_backoff);
+        assert!(!supervisor.should_attempt(now + backoff - Duration::from_millis(1)));
+        assert!(supervisor.should_attempt(now + backoff));
+        now += backoff;
+    }
+
+    match supervisor.state() {
+        AdapterConnectionState::Broken {
+            since, failures, ..
+        } => {
+            assert_eq!(since, start);
+            assert_eq!(failures, 4);
+        }
+        state => panic!("Unexpected state {:?}", state),
+    }
+}
+
+#[test]
+fn should_resume_when_adapter_returns() {
+    let metrics_registry = MetricsRegistry::new();
+    let supervisor = supervisor(&metrics_registry);
+    let start = Instant::now();
+
+    supervisor.record_connection_broken(start);
+    supervisor.record_success(start + Duration::from_secs(1));
+
+    assert_eq!(supervisor.state(), AdapterConnectionState::Connected);
+    assert!(supervisor.should_attempt(start + Duration::from_secs(1)));
+
+    // A new failure starts over with the initial backoff.
+    supervisor.record_connection_broken(start + Duration::from_secs(2));
+    assert!(supervisor.should_attempt(start + Duration::from_secs(3)));
+}
+
+#[test]
+fn should_report_critical_error_once_per_outage() {
+    let metrics_registry = MetricsRegistry::new();
+    let supervisor = supervisor(&metrics_registry);
+    let start = Instant::now();
+
+    supervisor.record_connection_broken(start);
+    supervisor.record_connection_broken(start + Duration::from_secs(9));
+    assert_eq!(critical_errors(&metrics_registry), 0);
+
+    supervisor.record_connection_broken(start + Duration::from_secs(10));
+    assert_eq!(critical_errors(&metrics_registry), 1);
+
+    supervisor.record_connection_broken(start + Duration::from_secs(20));
+    assert_eq!(critical_errors(&metrics_registry), 1);
+
+    // After a recovery, another long outage is reported again.
+    let later = start + Duration::from_secs(30);
+    supervisor.record_success(later);
+    supervisor.record_connection_broken(later);
+    supervisor.record_connection_broken(later + Duration::from_secs(10));
+    assert_eq!(critical_errors(&metrics_registry), 2);
+}
+
+#[test]
+fn should_skip_calls_during_backoff() {
+    let metrics_registry = MetricsRegistry::new();
+    let supervisor = supervisor(&metrics_registry);
+
+    let result: Result<(), &str> =
+        supervisor.supervise(|| Err("broken"), |err| *err == "broken", || "skipped");
+    assert_eq!(result, Err("broken"));
+
+    let result: Result<(), &str> = supervisor.supervise(
+        || panic!("The call must not be attempted during backoff"),
+        |err| *err == "broken",
+        || "skipped",
+    );
+    assert_eq!(result, Err("skipped"));
+}
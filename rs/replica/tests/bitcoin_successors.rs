@@ -0,0 +1,360 @@
+use futures::{channel::mpsc, SinkExt};
+use ic_btc_adapter::{
+    get_successors_stream_request, get_successors_stream_response::Response, BlockChunk,
+    BtcAdapter, BtcAdapterClient, BtcAdapterServer, GetSuccessorsStreamEnd,
+    GetSuccessorsStreamRequest, GetSuccessorsStreamResponse,
+};
+use ic_interfaces::bitcoin_adapter_client::Options;
+use ic_protobuf::bitcoin::v1::{
+    Block, BlockHeader, GetSuccessorsRequest, GetSuccessorsResponse, SendTransactionRequest,
+    SendTransactionResponse, Transaction,
+};
+use ic_replica::bitcoin_successors::{
+    get_successors_streamed, AssemblyProgress, SuccessorsAssembler,
+};
+use prost::Message;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::{net::UnixStream, time::Instant};
+use tonic::transport::{Channel, Endpoint, Server, Uri};
+use tonic::{Code, Request, Response as GrpcResponse, Status, Streaming};
+use tower::service_fn;
+
+fn block(version: i32) -> Block {
+    Block {
+        header: Some(BlockHeader {
+            version,
+            prev_blockhash: vec![1; 32],
+            merkle_root: vec![2; 32],
+            time: 3,
+            bits: 4,
+            nonce: 5,
+        }),
+        txdata: vec![Transaction {
+            version,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        }],
+    }
+}
+
+/// Splits the encoding of `block` into `count` chunks.
+fn chunks(block_index: u32, block: &Block, count: usize) -> Vec<GetSuccessorsStreamResponse> {
+    let encoding = block.encode_to_vec();
+    let size = (encoding.len() + count - 1) / count;
+    let chunks: Vec<_> = encoding.chunks(size).collect();
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, data)| GetSuccessorsStreamResponse {
+            response: Some(Response::BlockChunk(BlockChunk {
+                block_index,
+                data: data.to_vec(),
+                last: i + 1 == chunks.len(),
+            })),
+        })
+        .collect()
+}
+
+fn end(block_count: u32, next: Vec<BlockHeader>) -> GetSuccessorsStreamResponse {
+    GetSuccessorsStreamResponse {
+        response: Some(Response::End(GetSuccessorsStreamEnd { block_count, next })),
+    }
+}
+
+#[test]
+fn should_assemble_blocks_from_chunks() {
+    let blocks = vec![block(1), block(2)];
+    let next = vec![block(3).header.unwrap()];
+    let mut assembler = SuccessorsAssembler::default();
+
+    let mut progress = vec![];
+    for (index, block) in blocks.iter().enumerate() {
+        for chunk in chunks(index as u32, block, 3) {
+            progress.push(assembler.push(chunk).unwrap());
+        }
+    }
+    progress.push(assembler.push(end(2, next.clone())).unwrap());
+
+    assert_eq!(
+        progress,
+        vec![
+            AssemblyProgress::Chunk,
+            AssemblyProgress::Chunk,
+            AssemblyProgress::Block(0),
+            AssemblyProgress::Chunk,
+            AssemblyProgress::Chunk,
+            AssemblyProgress::Block(1),
+            AssemblyProgress::End,
+        ]
+    );
+    assert_eq!(
+        assembler.into_response(),
+        GetSuccessorsResponse { blocks, next }
+    );
+}
+
+#[test]
+fn should_keep_only_complete_blocks_of_an_unfinished_stream() {
+    let mut assembler = SuccessorsAssembler::default();
+    for chunk in chunks(0, &block(1), 2) {
+        assembler.push(chunk).unwrap();
+    }
+    let second = chunks(1, &block(2), 2);
+    assembler.push(second[0].clone()).unwrap();
+
+    assert_eq!(assembler.block_count(), 1);
+    assert_eq!(
+        assembler.into_response(),
+        GetSuccessorsResponse {
+            blocks: vec![block(1)],
+            next: vec![],
+        }
+    );
+}
+
+#[test]
+fn should_reject_messages_that_do_not_continue_the_stream() {
+    let mut assembler = SuccessorsAssembler::default();
+    let status = assembler
+        .push(chunks(1, &block(1), 1)[0].clone())
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+
+    let mut assembler = SuccessorsAssembler::default();
+    assembler.push(chunks(0, &block(1), 2)[0].clone()).unwrap();
+    let status = assembler.push(end(1, vec![])).unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+
+    let mut assembler = SuccessorsAssembler::default();
+    assembler.push(end(0, vec![])).unwrap();
+    let status = assembler
+        .push(chunks(0, &block(1), 1)[0].clone())
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+
+    let mut assembler = SuccessorsAssembler::default();
+    let status = assembler
+        .push(GetSuccessorsStreamResponse { response: None })
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+}
+
+/// A fake adapter that serves the successors `blocks` and `next`.
+#[derive(Clone, Default)]
+struct FakeAdapter {
+    blocks: Vec<Block>,
+    next: Vec<BlockHeader>,
+    /// Whether the adapter implements `GetSuccessorsStream`.
+    streaming: bool,
+    /// The number of blocks after which the stream stalls, if any.
+    stall_after: Option<usize>,
+    /// The indices of the blocks that the replica acknowledged.
+    acks: Arc<Mutex<Vec<u32>>>,
+}
+
+impl FakeAdapter {
+    fn response(&self) -> GetSuccessorsResponse {
+        GetSuccessorsResponse {
+            blocks: self.blocks.clone(),
+            next: self.next.clone(),
+        }
+    }
+
+    async fn stream(
+        self,
+        mut inbound: Streaming<GetSuccessorsStreamRequest>,
+        mut outbound: mpsc::Sender<Result<GetSuccessorsStreamResponse, Status>>,
+    ) {
+        for (index, block) in self.blocks.iter().enumerate() {
+            if self.stall_after == Some(index) {
+                futures::future::pending::<()>().await;
+            }
+            for chunk in chunks(index as u32, block, 2) {
+                if outbound.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+            match inbound.message().await {
+                Ok(Some(GetSuccessorsStreamRequest {
+                    request: Some(get_successors_stream_request::Request::AckBlockIndex(acked)),
+                })) => self.acks.lock().unwrap().push(acked),
+                _ => return,
+            }
+        }
+        let _ = outbound
+            .send(Ok(end(self.blocks.len() as u32, self.next.clone())))
+            .await;
+    }
+}
+
+#[tonic::async_trait]
+impl BtcAdapter for FakeAdapter {
+    type GetSuccessorsStreamStream = mpsc::Receiver<Result<GetSuccessorsStreamResponse, Status>>;
+
+    async fn get_successors(
+        &self,
+        _request: Request<GetSuccessorsRequest>,
+    ) -> Result<GrpcResponse<GetSuccessorsResponse>, Status> {
+        Ok(GrpcResponse::new(self.response()))
+    }
+
+    async fn get_successors_stream(
+        &self,
+        request: Request<Streaming<GetSuccessorsStreamRequest>>,
+    ) -> Result<GrpcResponse<Self::GetSuccessorsStreamStream>, Status> {
+        if !self.streaming {
+            return Err(Status::unimplemented("GetSuccessorsStream"));
+        }
+        let mut inbound = request.into_inner();
+        match inbound.message().await? {
+            Some(GetSuccessorsStreamRequest {
+                request: Some(get_successors_stream_request::Request::Start(_)),
+            }) => {}
+            _ => return Err(Status::invalid_argument("The call was not started")),
+        }
+        let (outbound, receiver) = mpsc::channel(4);
+        tokio::spawn(self.clone().stream(inbound, outbound));
+        Ok(GrpcResponse::new(receiver))
+    }
+
+    async fn send_transaction(
+        &self,
+        _request: Request<SendTransactionRequest>,
+    ) -> Result<GrpcResponse<SendTransactionResponse>, Status> {
+        Ok(GrpcResponse::new(SendTransactionResponse {}))
+    }
+}
+
+/// Serves `adapter` on a socket in `dir` and returns a client of it.
+fn serve(dir: &tempfile::TempDir, adapter: FakeAdapter) -> BtcAdapterClient<Channel> {
+    let uds_path: PathBuf = dir.path().join("adapter.socket");
+    let incoming = ic_async_utils::incoming_from_path(&uds_path).unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(BtcAdapterServer::new(adapter))
+            .serve_with_incoming(incoming),
+    );
+    let channel = Endpoint::try_from("http://[::]:50051")
+        .unwrap()
+        .connect_with_connector_lazy(service_fn(move |_: Uri| {
+            UnixStream::connect(uds_path.clone())
+        }))
+        .unwrap();
+    BtcAdapterClient::new(channel)
+}
+
+fn successors_request() -> GetSuccessorsRequest {
+    GetSuccessorsRequest {
+        anchor: vec![0; 32],
+        processed_block_hashes: vec![],
+    }
+}
+
+#[tokio::test]
+async fn should_stream_and_acknowledge_all_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let adapter = FakeAdapter {
+        blocks: vec![block(1), block(2), block(3)],
+        next: vec![block(4).header.unwrap()],
+        streaming: true,
+        ..FakeAdapter::default()
+    };
+    let client = serve(&dir, adapter.clone());
+
+    let response = get_successors_streamed(
+        client,
+        &Options::default(),
+        successors_request(),
+        None,
+        Some(Instant::now() + Duration::from_secs(10)),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response, adapter.response());
+    assert_eq!(*adapter.acks.lock().unwrap(), vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn should_return_complete_blocks_at_the_deadline() {
+    let dir = tempfile::tempdir().unwrap();
+    let adapter = FakeAdapter {
+        blocks: vec![block(1), block(2), block(3)],
+        next: vec![block(4).header.unwrap()],
+        streaming: true,
+        stall_after: Some(2),
+        ..FakeAdapter::default()
+    };
+    let client = serve(&dir, adapter.clone());
+
+    let response = get_successors_streamed(
+        client,
+        &Options::default(),
+        successors_request(),
+        None,
+        Some(Instant::now() + Duration::from_secs(1)),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        response,
+        GetSuccessorsResponse {
+            blocks: vec![block(1), block(2)],
+            next: vec![],
+        }
+    );
+}
+
+#[tokio::test]
+async fn should_fail_if_no_block_is_received_before_the_deadline() {
+    let dir = tempfile::tempdir().unwrap();
+    let adapter = FakeAdapter {
+        blocks: vec![block(1)],
+        streaming: true,
+        stall_after: Some(0),
+        ..FakeAdapter::default()
+    };
+    let client = serve(&dir, adapter);
+
+    let status = get_successors_streamed(
+        client,
+        &Options::default(),
+        successors_request(),
+        None,
+        Some(Instant::now() + Duration::from_secs(1)),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+}
+
+#[tokio::test]
+async fn should_fall_back_to_unary_call_if_stream_is_unimplemented() {
+    let dir = tempfile::tempdir().unwrap();
+    let adapter = FakeAdapter {
+        blocks: vec![block(1), block(2)],
+        next: vec![block(3).header.unwrap()],
+        streaming: false,
+        ..FakeAdapter::default()
+    };
+    let client = serve(&dir, adapter.clone());
+
+    let response = get_successors_streamed(
+        client,
+        &Options::default(),
+        successors_request(),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response, adapter.response());
+    assert!(adapter.acks.lock().unwrap().is_empty());
+}
@@ -0,0 +1,315 @@
+use ic_base_types::HttpMethodType;
+use ic_interfaces::{
+    adapter_client::Options,
+    canister_http::{CanisterHttpAdapterClient, NonBlockingChannel},
+    execution_environment::QueryHandler,
+    rpc_bridge::{RpcBridgeReceiveError, RpcBridgeSendError},
+};
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_replica::canister_http_load_shedding::{LatencyLoadShedder, LoadSheddingConfig};
+use ic_replica::setup_canister_http_client::{
+    setup_canister_http_client, transform_replies, BoundedCanisterHttpClient, SendToAdapter,
+};
+use ic_replicated_state::ReplicatedState;
+use ic_test_utilities::{state_manager::FakeStateManager, types::ids::subnet_test_id};
+use ic_types::{
+    canister_http::{
+        CanisterHttpReply, CanisterHttpRequest, CanisterHttpRequestContext,
+        CanisterHttpTransformError, DEFAULT_CANISTER_HTTP_PSEUDO_RANDOM_DELAY_WINDOW,
+    },
+    ingress::WasmResult,
+    messages::{CallbackId, Request, UserQuery},
+    time::UNIX_EPOCH,
+    user_error::UserError,
+    CanisterId, Cycles, NumInstructions,
+};
+use std::{sync::Arc, time::Duration};
+
+fn request(id: u64) -> CanisterHttpRequest {
+    CanisterHttpRequest {
+        id: CallbackId::from(id),
+        content: CanisterHttpRequestContext {
+            request: Request {
+                receiver: CanisterId::ic_00(),
+                sender: CanisterId::from_u64(1),
+                sender_reply_callback: CallbackId::from(id),
+                payment: Cycles::zero(),
+                method_name: "http_request".to_string(),
+                method_payload: vec![],
+            },
+            url: "https://example.com".to_string(),
+            body: None,
+            http_method: HttpMethodType::GET,
+            transform_method_name: None,
+            time: UNIX_EPOCH,
+            timeout: UNIX_EPOCH,
+            max_response_bytes: 0,
+            fee_per_response_byte: Cycles::zero(),
+        },
+    }
+}
+
+fn reply(request: &CanisterHttpRequest) -> CanisterHttpReply {
+    CanisterHttpReply {
+        id: request.id,
+        status: 200,
+        headers: vec![],
+        body: vec![],
+    }
+}
+
+fn client(inflight_requests: usize, send_to_adapter: SendToAdapter) -> BoundedCanisterHttpClient {
+    client_with_load_shedding(
+        inflight_requests,
+        send_to_adapter,
+        LoadSheddingConfig::default(),
+    )
+}
+
+fn client_with_load_shedding(
+    inflight_requests: usize,
+    send_to_adapter: SendToAdapter,
+    load_shedding: LoadSheddingConfig,
+) -> BoundedCanisterHttpClient {
+    BoundedCanisterHttpClient::new(
+        tokio::runtime::Handle::current(),
+        inflight_requests,
+        send_to_adapter,
+        load_shedding,
+        &MetricsRegistry::new(),
+        no_op_logger(),
+    )
+}
+
+/// Replaces the body of replies to requests with the transform `transform`
+/// and fails the transform `loop` as if it ran out of instructions.
+struct FakeTransform;
+
+impl QueryHandler for FakeTransform {
+    type State = ReplicatedState;
+
+    fn query(
+        &self,
+        _query: UserQuery,
+        _state: Arc<ReplicatedState>,
+        _data_certificate: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        unimplemented!()
+    }
+
+    fn transform_canister_http_reply(
+        &self,
+        context: &CanisterHttpRequestContext,
+        mut reply: CanisterHttpReply,
+        _state: Arc<ReplicatedState>,
+    ) -> Result<CanisterHttpReply, CanisterHttpTransformError> {
+        match context.transform_method_name.as_deref() {
+            None => Ok(reply),
+            Some("transform") => {
+                reply.body = b"transformed".to_vec();
+                Ok(reply)
+            }
+            Some(_) => Err(CanisterHttpTransformError::InstructionLimitExceeded {
+                limit: NumInstructions::from(1),
+            }),
+        }
+    }
+}
+
+fn request_with_transform(id: u64) -> CanisterHttpRequest {
+    let mut request = request(id);
+    request.content.transform_method_name = Some("transform".to_string());
+    request
+}
+
+#[tokio::test]
+async fn should_reject_requests_while_full() {
+    let mut client = client(2, Box::new(|_request| Box::pin(std::future::pending())));
+
+    assert_eq!(client.send(request(1)), Ok(()));
+    assert_eq!(client.send(request(2)), Ok(()));
+    assert_eq!(
+        client.send(request(3)),
+        Err(RpcBridgeSendError::Full(request(3)))
+    );
+}
+
+#[tokio::test]
+async fn should_let_one_request_in_flight_if_none_are_configured() {
+    let mut client = client(0, Box::new(|_request| Box::pin(std::future::pending())));
+
+    assert_eq!(client.send(request(1)), Ok(()));
+    assert_eq!(
+        client.send(request(2)),
+        Err(RpcBridgeSendError::Full(request(2)))
+    );
+}
+
+#[tokio::test]
+async fn should_accept_requests_again_once_replies_are_received() {
+    let mut client = client(
+        1,
+        Box::new(|request| Box::pin(async move { Ok(reply(&request)) })),
+    );
+
+    assert_eq!(client.send(request(1)), Ok(()));
+    // We must yield here in order to allow for the task that sends the request
+    // to be executed.
+    tokio::task::yield_now().await;
+    assert_eq!(
+        client.send(request(2)),
+        Err(RpcBridgeSendError::Full(request(2)))
+    );
+    assert_eq!(client.try_receive(), Ok(reply(&request(1))));
+    assert_eq!(client.send(request(2)), Ok(()));
+    tokio::task::yield_now().await;
+    assert_eq!(client.try_receive(), Ok(reply(&request(2))));
+    assert_eq!(client.try_receive(), Err(RpcBridgeReceiveError::Empty));
+}
+
+#[tokio::test]
+async fn should_release_capacity_of_failed_requests() {
+    let mut client = client(
+        1,
+        Box::new(|_request| {
+            Box::pin(async move { Err(tonic::Status::unavailable("adapter is down")) })
+        }),
+    );
+
+    assert_eq!(client.send(request(1)), Ok(()));
+    tokio::task::yield_now().await;
+    assert_eq!(client.try_receive(), Err(RpcBridgeReceiveError::Empty));
+    assert_eq!(client.send(request(2)), Ok(()));
+}
+
+#[tokio::test]
+async fn should_close_client_without_adapter() {
+    let mut client = setup_canister_http_client(
+        no_op_logger(),
+        &MetricsRegistry::new(),
+        tokio::runtime::Handle::current(),
+        subnet_test_id(1),
+        None,
+        1,
+        LoadSheddingConfig::default(),
+        DEFAULT_CANISTER_HTTP_PSEUDO_RANDOM_DELAY_WINDOW,
+        Options::background(),
+        Arc::new(FakeTransform),
+        Arc::new(FakeStateManager::new()),
+    );
+
+    assert_eq!(
+        client.send(request(1)),
+        Err(RpcBridgeSendError::Closed(request(1)))
+    );
+    assert_eq!(
+        client.try_receive(),
+        Err(RpcBridgeReceiveError::Disconnected)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn should_poll_replies_from_another_thread() {
+    let mut client: Box<dyn CanisterHttpAdapterClient> = Box::new(client(
+        1,
+        Box::new(|request| Box::pin(async move { Ok(reply(&request)) })),
+    ));
+
+    let received = std::thread::spawn(move || {
+        assert_eq!(client.send(request(1)), Ok(()));
+        loop {
+            match client.try_receive() {
+                Err(RpcBridgeReceiveError::Empty) => std::thread::sleep(Duration::from_millis(1)),
+                received => return received,
+            }
+        }
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(received, Ok(reply(&request(1))));
+}
+
+#[test]
+fn should_shed_once_p95_latency_exceeds_threshold() {
+    let shedder = LatencyLoadShedder::new(
+        LoadSheddingConfig {
+            latency_threshold: Duration::from_secs(1),
+            max_samples: 20,
+            max_sample_age: Duration::from_secs(60),
+        },
+        &MetricsRegistry::new(),
+    );
+    assert!(!shedder.is_shedding());
+
+    for _ in 0..19 {
+        shedder.record_latency(Duration::from_millis(100));
+    }
+    shedder.record_latency(Duration::from_secs(2));
+    assert!(!shedder.is_shedding());
+
+    // The oldest fast latency is dropped, so 2 of the 20 latencies are slow.
+    shedder.record_latency(Duration::from_secs(2));
+    assert!(shedder.is_shedding());
+    assert!(shedder.should_shed(&request(1)));
+    assert!(!shedder.should_shed(&request_with_transform(2)));
+}
+
+#[tokio::test]
+async fn should_shed_divergence_prone_requests_while_adapter_is_slow() {
+    let mut client = client_with_load_shedding(
+        10,
+        Box::new(|request| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(reply(&request))
+            })
+        }),
+        LoadSheddingConfig {
+            latency_threshold: Duration::from_millis(10),
+            max_samples: 10,
+            max_sample_age: Duration::from_millis(500),
+        },
+    );
+
+    assert_eq!(client.send(request(1)), Ok(()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(client.try_receive(), Ok(reply(&request(1))));
+
+    assert_eq!(
+        client.send(request(2)),
+        Err(RpcBridgeSendError::Full(request(2)))
+    );
+    assert_eq!(client.send(request_with_transform(3)), Ok(()));
+
+    // Once the slow latencies expired, requests are accepted again.
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(client.try_receive(), Ok(reply(&request_with_transform(3))));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(client.send(request(2)), Ok(()));
+}
+
+#[tokio::test]
+async fn should_transform_replies_before_they_are_received() {
+    let mut client = client(
+        2,
+        transform_replies(
+            Box::new(|request| Box::pin(async move { Ok(reply(&request)) })),
+            Arc::new(FakeTransform),
+            Arc::new(FakeStateManager::new()),
+        ),
+    );
+    let mut request_with_failing_transform = request(2);
+    request_with_failing_transform.content.transform_method_name = Some("loop".to_string());
+
+    assert_eq!(client.send(request_with_transform(1)), Ok(()));
+    assert_eq!(client.send(request_with_failing_transform), Ok(()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut transformed = reply(&request(1));
+    transformed.body = b"transformed".to_vec();
+    assert_eq!(client.try_receive(), Ok(transformed));
+    // The reply whose transform failed is dropped.
+    assert_eq!(client.try_receive(), Err(RpcBridgeReceiveError::Empty));
+}
@@ -0,0 +1,279 @@
+//! Fetches the status of a replica from its `/api/v2/status` endpoint and
+//! polls it until the replica is healthy, asynchronously or blocking.
+//!
+//! Used by the orchestrator to check the health of the replica it runs, and
+//! by the system tests to wait for the nodes of a testnet.
+use ic_types::{
+    messages::{Blob, HttpStatusResponse, ReplicaHealthStatus},
+    Height, ReplicaVersion,
+};
+use std::{convert::TryFrom, error::Error, fmt, time::Duration};
+use url::Url;
+
+/// The path of the status endpoint relative to the URL of a replica.
+pub const STATUS_PATH: &str = "api/v2/status";
+
+/// The status of a replica, as reported by its status endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplicaStatus {
+    pub ic_api_version: String,
+    pub root_key: Option<Blob>,
+    /// The version of the replica, if it reports a valid one. A version that
+    /// is not a valid [ReplicaVersion] is dropped, so that the rest of the
+    /// status, e.g. its health, can still be used.
+    pub impl_version: Option<ReplicaVersion>,
+    pub impl_hash: Option<String>,
+    /// The health of the replica, if it reports it.
+    pub health_status: Option<ReplicaHealthStatus>,
+    /// The height of the latest certified state of the replica, if it
+    /// reports it. Replicas that predate the field do not report it. See
+    /// [HttpStatusResponse::certified_height].
+    pub certified_height: Option<Height>,
+}
+
+impl ReplicaStatus {
+    /// Returns whether the replica reports that it is healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.health_status == Some(ReplicaHealthStatus::Healthy)
+    }
+}
+
+impl From<HttpStatusResponse> for ReplicaStatus {
+    fn from(response: HttpStatusResponse) -> Self {
+        let impl_version = response
+            .impl_version
+            .and_then(|version| ReplicaVersion::try_from(version).ok());
+        Self {
+            ic_api_version: response.ic_api_version,
+            root_key: response.root_key,
+            impl_version,
+            impl_hash: response.impl_hash,
+            health_status: response.replica_health_status,
+            certified_height: response.certified_height,
+        }
+    }
+}
+
+/// An error that occurred while fetching the status of a replica.
+#[derive(Debug)]
+pub enum StatusError {
+    /// The URL of the status endpoint could not be built.
+    InvalidUrl(String),
+    /// The status endpoint could not be reached.
+    Http(reqwest::Error),
+    /// The status endpoint responded with an HTTP error.
+    UnexpectedStatusCode(u16),
+    /// The response is not a CBOR-encoded status.
+    Decode(String),
+    /// The replica did not become healthy in time. Contains the last status
+    /// that was fetched, if any.
+    NotHealthy(Option<ReplicaStatus>),
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(err) => write!(f, "invalid status URL: {}", err),
+            Self::Http(err) => write!(f, "failed to fetch status: {}", err),
+            Self::UnexpectedStatusCode(code) => {
+                write!(f, "status endpoint responded with HTTP status {}", code)
+            }
+            Self::Decode(err) => write!(f, "failed to decode status: {}", err),
+            Self::NotHealthy(Some(status)) => write!(
+                f,
+                "replica is not healthy, its health status is {:?}",
+                status.health_status
+            ),
+            Self::NotHealthy(None) => write!(f, "replica status could not be fetched"),
+        }
+    }
+}
+
+impl Error for StatusError {}
+
+impl From<reqwest::Error> for StatusError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+/// Returns the URL of the status endpoint of the replica at `replica_url`.
+pub fn status_url(replica_url: &Url) -> Result<Url, StatusError> {
+    replica_url
+        .join(STATUS_PATH)
+        .map_err(|err| StatusError::InvalidUrl(err.to_string()))
+}
+
+/// Decodes the CBOR-encoded body of a response of the status endpoint.
+pub fn decode_status(body: &[u8]) -> Result<ReplicaStatus, StatusError> {
+    let response: HttpStatusResponse =
+        serde_cbor::from_slice(body).map_err(|err| StatusError::Decode(err.to_string()))?;
+    Ok(ReplicaStatus::from(response))
+}
+
+/// Fetches the status of the replica at `replica_url`, giving up after
+/// `timeout`.
+pub async fn fetch_status(
+    replica_url: &Url,
+    timeout: Duration,
+) -> Result<ReplicaStatus, StatusError> {
+    let response = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()?
+        .get(status_url(replica_url)?)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(StatusError::UnexpectedStatusCode(
+            response.status().as_u16(),
+        ));
+    }
+    decode_status(&response.bytes().await?)
+}
+
+/// Polls the status of the replica at `replica_url` every `interval` until it
+/// is healthy, and returns its status. Fails with
+/// [StatusError::NotHealthy] if the replica is not healthy after `timeout`.
+pub async fn wait_until_healthy(
+    replica_url: &Url,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<ReplicaStatus, StatusError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let status = fetch_status(replica_url, interval).await.ok();
+        match status {
+            Some(status) if status.is_healthy() => return Ok(status),
+            status if tokio::time::Instant::now() + interval > deadline => {
+                return Err(StatusError::NotHealthy(status))
+            }
+            _ => tokio::time::sleep(interval).await,
+        }
+    }
+}
+
+/// The blocking counterparts of the functions of this crate. They must not be
+/// called from within an asynchronous runtime.
+pub mod blocking {
+    use super::{decode_status, status_url, ReplicaStatus, StatusError};
+    use std::time::{Duration, Instant};
+    use url::Url;
+
+    /// Fetches the status of the replica at `replica_url`, giving up after
+    /// `timeout`.
+    pub fn fetch_status(
+        replica_url: &Url,
+        timeout: Duration,
+    ) -> Result<ReplicaStatus, StatusError> {
+        let response = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()?
+            .get(status_url(replica_url)?)
+            .send()?;
+        if !response.status().is_success() {
+            return Err(StatusError::UnexpectedStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        decode_status(&response.bytes()?)
+    }
+
+    /// Polls the status of the replica at `replica_url` every `interval` until
+    /// it is healthy, and returns its status. Fails with
+    /// [StatusError::NotHealthy] if the replica is not healthy after
+    /// `timeout`.
+    pub fn wait_until_healthy(
+        replica_url: &Url,
+        timeout: Duration,
+        interval: Duration,
+    ) -> Result<ReplicaStatus, StatusError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = fetch_status(replica_url, interval).ok();
+            match status {
+                Some(status) if status.is_healthy() => return Ok(status),
+                status if Instant::now() + interval > deadline => {
+                    return Err(StatusError::NotHealthy(status))
+                }
+                _ => std::thread::sleep(interval),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> HttpStatusResponse {
+        HttpStatusResponse {
+            ic_api_version: "0.18.0".to_string(),
+            root_key: None,
+            impl_version: Some("0.8.0".to_string()),
+            impl_hash: None,
+            replica_health_status: Some(ReplicaHealthStatus::Healthy),
+            certified_height: Some(Height::from(7)),
+        }
+    }
+
+    #[test]
+    fn test_decode_status() {
+        let body = serde_cbor::to_vec(&response()).unwrap();
+
+        let status = decode_status(&body).unwrap();
+
+        assert_eq!(
+            status.impl_version,
+            Some(ReplicaVersion::try_from("0.8.0").unwrap())
+        );
+        assert_eq!(status.certified_height, Some(Height::from(7)));
+        assert!(status.is_healthy());
+    }
+
+    #[test]
+    fn test_decode_status_of_replica_without_health_status() {
+        let body = serde_cbor::to_vec(&HttpStatusResponse {
+            replica_health_status: None,
+            certified_height: None,
+            ..response()
+        })
+        .unwrap();
+
+        let status = decode_status(&body).unwrap();
+
+        assert_eq!(status.health_status, None);
+        assert!(!status.is_healthy());
+    }
+
+    #[test]
+    fn test_decode_status_with_invalid_impl_version() {
+        let body = serde_cbor::to_vec(&HttpStatusResponse {
+            impl_version: Some("not a version!".to_string()),
+            ..response()
+        })
+        .unwrap();
+
+        let status = decode_status(&body).unwrap();
+
+        assert_eq!(status.impl_version, None);
+        assert_eq!(status.certified_height, Some(Height::from(7)));
+        assert!(status.is_healthy());
+    }
+
+    #[test]
+    fn test_decode_invalid_status() {
+        assert!(matches!(
+            decode_status(b"not cbor"),
+            Err(StatusError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_status_url() {
+        let url = Url::parse("http://[::1]:8080/").unwrap();
+        assert_eq!(
+            status_url(&url).unwrap().as_str(),
+            "http://[::1]:8080/api/v2/status"
+        );
+    }
+}
@@ -337,7 +337,7 @@ where
         let init_subnet = init_ic.initialized_topology.values().next().unwrap();
         let init_node = init_subnet.initialized_nodes.values().next().unwrap();
         let crypto_root = init_node.crypto_path();
-        config.crypto = CryptoConfig { crypto_root };
+        config.crypto = CryptoConfig::new(crypto_root);
 
         // load the registry file written by ic-prep
         let data_provider =
@@ -380,7 +380,7 @@ where
             queue_size: 0,
         }];
         let temp_node = node_id;
-        let (_, state_manager, query_handler, _, mut p2p, p2p_event_handler, _, _, _) =
+        let (_, state_manager, query_handler, _, mut p2p, p2p_event_handler, _, _, _, _) =
             ic_replica::setup_p2p::construct_ic_stack(
                 logger,
                 config.clone(),
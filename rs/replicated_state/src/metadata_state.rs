@@ -17,6 +17,7 @@ use ic_registry_routing_table::RoutingTable;
 use ic_registry_subnet_features::{BitcoinFeature, SubnetFeatures};
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
+    canister_http::CanisterHttpLimits,
     crypto::CryptoHash,
     ingress::{IngressStatus, MAX_INGRESS_TTL},
     messages::{MessageId, RequestOrResponse},
@@ -69,6 +70,10 @@ pub struct SystemMetadata {
 
     pub own_subnet_features: SubnetFeatures,
 
+    /// The limits on canister http requests of this subnet, as set in the
+    /// registry.
+    pub own_subnet_canister_http_limits: CanisterHttpLimits,
+
     /// Asynchronously handled subnet messages.
     pub subnet_call_context_manager: SubnetCallContextManager,
 
@@ -123,6 +128,9 @@ pub struct NetworkTopology {
     pub subnets: BTreeMap<SubnetId, SubnetTopology>,
     pub routing_table: Arc<RoutingTable>,
     pub nns_subnet_id: SubnetId,
+    /// The subnets that may sign with a threshold ECDSA key, for the keys
+    /// that have a signing subnet list in the registry.
+    pub ecdsa_signing_subnets: BTreeMap<String, Vec<SubnetId>>,
 }
 
 impl Default for NetworkTopology {
@@ -131,6 +139,7 @@ impl Default for NetworkTopology {
             subnets: Default::default(),
             routing_table: Default::default(),
             nns_subnet_id: SubnetId::new(PrincipalId::new_anonymous()),
+            ecdsa_signing_subnets: Default::default(),
         }
     }
 }
@@ -156,6 +165,15 @@ impl NetworkTopology {
             .map(|(subnet_id, _)| *subnet_id)
             .collect()
     }
+
+    /// Returns whether `subnet_id` may sign with the threshold ECDSA key
+    /// `key_id`. Keys without a signing subnet list may be used by any
+    /// subnet.
+    pub fn may_sign_with_ecdsa_key(&self, subnet_id: SubnetId, key_id: &str) -> bool {
+        self.ecdsa_signing_subnets
+            .get(key_id)
+            .map_or(true, |subnets| subnets.contains(&subnet_id))
+    }
 }
 
 impl From<&NetworkTopology> for pb_metadata::NetworkTopology {
@@ -171,6 +189,17 @@ impl From<&NetworkTopology> for pb_metadata::NetworkTopology {
                 .collect(),
             routing_table: Some(item.routing_table.as_ref().into()),
             nns_subnet_id: Some(subnet_id_into_protobuf(item.nns_subnet_id)),
+            ecdsa_signing_subnets: item
+                .ecdsa_signing_subnets
+                .iter()
+                .map(|(key_id, subnets)| pb_metadata::EcdsaSigningSubnetsEntry {
+                    key_id: key_id.clone(),
+                    subnets: subnets
+                        .iter()
+                        .map(|subnet_id| subnet_id_into_protobuf(*subnet_id))
+                        .collect(),
+                })
+                .collect(),
         }
     }
 }
@@ -195,6 +224,15 @@ impl TryFrom<pb_metadata::NetworkTopology> for NetworkTopology {
                 Ok(subnet_id) => subnet_id_try_from_protobuf(subnet_id)?,
                 Err(_) => SubnetId::new(PrincipalId::new_anonymous()),
             };
+        let mut ecdsa_signing_subnets = BTreeMap::new();
+        for entry in item.ecdsa_signing_subnets {
+            let subnets = entry
+                .subnets
+                .into_iter()
+                .map(subnet_id_try_from_protobuf)
+                .collect::<Result<_, _>>()?;
+            ecdsa_signing_subnets.insert(entry.key_id, subnets);
+        }
 
         Ok(Self {
             subnets,
@@ -204,6 +242,7 @@ impl TryFrom<pb_metadata::NetworkTopology> for NetworkTopology {
             )
             .map(Arc::new)?,
             nns_subnet_id,
+            ecdsa_signing_subnets,
         })
     }
 }
@@ -216,6 +255,9 @@ pub struct SubnetTopology {
     pub nodes: BTreeMap<NodeId, NodeTopology>,
     pub subnet_type: SubnetType,
     pub subnet_features: SubnetFeatures,
+    /// The number of pre-signatures that canisters may reserve in total, per
+    /// ECDSA key id, on this subnet.
+    pub max_reserved_ecdsa_quadruples: u32,
 }
 
 impl From<&SubnetTopology> for pb_metadata::SubnetTopology {
@@ -232,6 +274,7 @@ impl From<&SubnetTopology> for pb_metadata::SubnetTopology {
                 .collect(),
             subnet_type: i32::from(item.subnet_type),
             subnet_features: Some(pb_subnet::SubnetFeatures::from(item.subnet_features)),
+            max_reserved_ecdsa_quadruples: item.max_reserved_ecdsa_quadruples,
         }
     }
 }
@@ -261,6 +304,7 @@ impl TryFrom<pb_metadata::SubnetTopology> for SubnetTopology {
                 .subnet_features
                 .map(SubnetFeatures::from)
                 .unwrap_or_default(),
+            max_reserved_ecdsa_quadruples: item.max_reserved_ecdsa_quadruples,
         })
     }
 }
@@ -317,6 +361,7 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
             certification_version: item.certification_version,
             heap_delta_estimate: item.heap_delta_estimate.get(),
             own_subnet_features: Some(item.own_subnet_features.into()),
+            own_subnet_canister_http_limits: Some((&item.own_subnet_canister_http_limits).into()),
             time_of_last_allocation_charge_nanos: Some(TimeOfLastAllocationCharge {
                 time_of_last_allocation_charge_nanos: item
                     .time_of_last_allocation_charge
@@ -349,6 +394,10 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             // properly set this value.
             own_subnet_type: SubnetType::default(),
             own_subnet_features: item.own_subnet_features.unwrap_or_default().into(),
+            own_subnet_canister_http_limits: item
+                .own_subnet_canister_http_limits
+                .unwrap_or_default()
+                .into(),
             generated_id_counter: item.generated_id_counter,
             prev_state_hash: item.prev_state_hash.map(|b| CryptoHash(b).into()),
             batch_time: Time::from_nanos_since_unix_epoch(item.batch_time_nanos),
@@ -395,6 +444,7 @@ impl SystemMetadata {
             network_topology: Default::default(),
             subnet_call_context_manager: Default::default(),
             own_subnet_features: SubnetFeatures::default(),
+            own_subnet_canister_http_limits: CanisterHttpLimits::default(),
             // StateManager populates proper values of these fields before
             // committing each state.
             prev_state_hash: Default::default(),
@@ -4,16 +4,44 @@ use ic_protobuf::{
     state::system_metadata::v1 as pb_metadata,
 };
 use ic_types::{
-    canister_http::CanisterHttpRequestContext,
+    canister_http::{
+        CanisterHttpDivergenceReport, CanisterHttpRequestContext, CanisterHttpRequestDivergence,
+    },
     crypto::threshold_sig::ni_dkg::{id::ni_dkg_target_id, NiDkgTargetId},
     messages::{CallbackId, Request},
-    node_id_into_protobuf, node_id_try_from_protobuf, NodeId, RegistryVersion, Time,
+    node_id_into_protobuf, node_id_try_from_protobuf, CanisterId, NodeId, RegistryVersion, Time,
 };
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     convert::{From, TryFrom},
+    time::Duration,
 };
 
+/// The number of divergence reports kept per canister. Once a canister has
+/// that many, recording another one drops its oldest.
+pub const MAX_CANISTER_HTTP_DIVERGENCE_REPORTS: usize = 10;
+
+/// The time after which a reservation of ECDSA pre-signatures expires unless
+/// it is renewed by a signing request of the canister with the key. The
+/// pre-signatures that canisters reserved but do not use are thus released to
+/// all canisters.
+pub const ECDSA_QUADRUPLE_RESERVATION_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The pre-signatures of an ECDSA key that a canister reserved for its own
+/// signing requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EcdsaQuadrupleReservation {
+    pub quadruples: u32,
+    /// The batch time from which on the reservation has expired.
+    pub expiry: Time,
+}
+
+impl EcdsaQuadrupleReservation {
+    fn is_active(&self, now: Time) -> bool {
+        now < self.expiry
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct SubnetCallContextManager {
     next_callback_id: u64,
@@ -21,6 +49,28 @@ pub struct SubnetCallContextManager {
     pub sign_with_ecdsa_contexts: BTreeMap<CallbackId, SignWithEcdsaContext>,
     pub sign_with_mock_ecdsa_contexts: BTreeMap<CallbackId, SignWithEcdsaContext>,
     pub canister_http_request_contexts: BTreeMap<CallbackId, CanisterHttpRequestContext>,
+    /// The most recent reports of canister http requests whose responses did
+    /// not reach consensus, per calling canister and oldest first.
+    pub canister_http_divergence_reports:
+        BTreeMap<CanisterId, VecDeque<CanisterHttpDivergenceReport>>,
+    /// The `sign_with_ecdsa_batch` calls that still have message hashes being
+    /// signed. Each of these message hashes has a context of its own in
+    /// `sign_with_ecdsa_contexts`. Batch contexts are only added and removed
+    /// through `push_sign_with_ecdsa_batch_request` and
+    /// `complete_sign_with_ecdsa_batch_item`, which keep
+    /// `sign_with_ecdsa_batch_items` up to date.
+    pub sign_with_ecdsa_batch_contexts: BTreeMap<CallbackId, SignWithEcdsaBatchContext>,
+    /// The callback id of the `sign_with_ecdsa_batch` call in
+    /// `sign_with_ecdsa_batch_contexts` that each pending message hash belongs
+    /// to, by the callback id the message hash is signed under. Not persisted,
+    /// but rebuilt from `sign_with_ecdsa_batch_contexts`.
+    sign_with_ecdsa_batch_items: BTreeMap<CallbackId, CallbackId>,
+    /// The pre-signatures that canisters reserved for their own signing
+    /// requests, per ECDSA key id and canister. Consensus does not match the
+    /// signing requests of other canisters to the pre-signatures of the
+    /// reservations that have not expired.
+    pub ecdsa_quadruple_reservations:
+        BTreeMap<String, BTreeMap<CanisterId, EcdsaQuadrupleReservation>>,
 }
 
 impl SubnetCallContextManager {
@@ -31,23 +81,267 @@ impl SubnetCallContextManager {
         self.setup_initial_dkg_contexts.insert(callback_id, context);
     }
 
-    pub fn push_sign_with_ecdsa_request(&mut self, context: SignWithEcdsaContext, is_mock: bool) {
+    /// Records the signing request `context`. A signing request that is not
+    /// mocked renews the reservation of pre-signatures of its canister for
+    /// its key, if any.
+    pub fn push_sign_with_ecdsa_request(
+        &mut self,
+        context: SignWithEcdsaContext,
+        is_mock: bool,
+    ) -> CallbackId {
         let callback_id = CallbackId::new(self.next_callback_id);
         self.next_callback_id += 1;
         match is_mock {
             true => self
                 .sign_with_mock_ecdsa_contexts
                 .insert(callback_id, context),
-            false => self.sign_with_ecdsa_contexts.insert(callback_id, context),
+            false => {
+                self.renew_ecdsa_quadruple_reservation(
+                    &context.key_id,
+                    &context.request.sender,
+                    context.batch_time,
+                );
+                self.sign_with_ecdsa_contexts.insert(callback_id, context)
+            }
         };
+        callback_id
+    }
+
+    /// Records the `sign_with_ecdsa_batch` call `context`, whose pending
+    /// message hashes must have been pushed with
+    /// `push_sign_with_ecdsa_request` already.
+    pub fn push_sign_with_ecdsa_batch_request(
+        &mut self,
+        context: SignWithEcdsaBatchContext,
+    ) -> CallbackId {
+        let callback_id = CallbackId::new(self.next_callback_id);
+        self.next_callback_id += 1;
+
+        self.index_sign_with_ecdsa_batch_items(callback_id, &context);
+        self.sign_with_ecdsa_batch_contexts
+            .insert(callback_id, context);
+        callback_id
+    }
+
+    /// Records that the pending message hashes of `context` belong to the
+    /// `sign_with_ecdsa_batch` call with callback id `batch_id`.
+    fn index_sign_with_ecdsa_batch_items(
+        &mut self,
+        batch_id: CallbackId,
+        context: &SignWithEcdsaBatchContext,
+    ) {
+        for item in &context.items {
+            if let SignWithEcdsaBatchItem::Pending(callback_id) = item {
+                self.sign_with_ecdsa_batch_items
+                    .insert(*callback_id, batch_id);
+            }
+        }
+    }
+
+    /// Returns whether `callback_id` is the callback id of a message hash of a
+    /// `sign_with_ecdsa_batch` call that is being signed.
+    pub fn is_sign_with_ecdsa_batch_item(&self, callback_id: CallbackId) -> bool {
+        self.sign_with_ecdsa_batch_items.contains_key(&callback_id)
     }
 
-    pub fn push_http_request(&mut self, context: CanisterHttpRequestContext) {
+    /// Records `outcome` for the message hash of a `sign_with_ecdsa_batch`
+    /// call that was signed under `callback_id`. Once no message hash of the
+    /// call is pending anymore, the call is removed and returned so that it
+    /// can be replied to.
+    pub fn complete_sign_with_ecdsa_batch_item(
+        &mut self,
+        callback_id: CallbackId,
+        outcome: SignWithEcdsaBatchItem,
+    ) -> Option<SignWithEcdsaBatchContext> {
+        let batch_id = self.sign_with_ecdsa_batch_items.remove(&callback_id)?;
+        let context = self.sign_with_ecdsa_batch_contexts.get_mut(&batch_id)?;
+        let index = context.pending_item(callback_id)?;
+        context.items[index] = outcome;
+        if context.is_complete() {
+            return self.sign_with_ecdsa_batch_contexts.remove(&batch_id);
+        }
+        None
+    }
+
+    /// Records the in-flight canister http request `context` and returns the
+    /// callback id under which its response is expected.
+    pub fn push_http_request(&mut self, context: CanisterHttpRequestContext) -> CallbackId {
         let callback_id = CallbackId::new(self.next_callback_id);
         self.next_callback_id += 1;
 
         self.canister_http_request_contexts
             .insert(callback_id, context);
+        callback_id
+    }
+
+    /// Returns the callback ids of the canister http requests that have timed
+    /// out at batch time `now`, in increasing order. The requests are kept
+    /// until they are rejected, so that the unused response bytes are
+    /// refunded with the reject.
+    pub fn timed_out_http_requests(&self, now: Time) -> Vec<CallbackId> {
+        self.canister_http_request_contexts
+            .iter()
+            .filter(|(_, context)| context.has_timed_out(now))
+            .map(|(callback_id, _)| *callback_id)
+            .collect()
+    }
+
+    /// Records the report of the `divergence` of a canister http request
+    /// whose responses did not reach consensus at batch time `now` and
+    /// returns it. The request is kept until it is rejected, so that the
+    /// unused response bytes are refunded with the reject.
+    pub fn record_diverged_http_request(
+        &mut self,
+        divergence: &CanisterHttpRequestDivergence,
+        now: Time,
+    ) -> Option<CanisterHttpDivergenceReport> {
+        let context = self.canister_http_request_contexts.get(&divergence.id())?;
+        let report = divergence.report(context, now);
+        self.record_http_request_divergence(report.clone());
+        Some(report)
+    }
+
+    fn record_http_request_divergence(&mut self, report: CanisterHttpDivergenceReport) {
+        let reports = self
+            .canister_http_divergence_reports
+            .entry(report.canister_id)
+            .or_default();
+        reports.push_back(report);
+        while reports.len() > MAX_CANISTER_HTTP_DIVERGENCE_REPORTS {
+            reports.pop_front();
+        }
+    }
+
+    /// Returns the most recent divergence reports of the canister http
+    /// requests of `canister_id`, oldest first.
+    pub fn http_request_divergences(
+        &self,
+        canister_id: &CanisterId,
+    ) -> impl Iterator<Item = &CanisterHttpDivergenceReport> {
+        self.canister_http_divergence_reports
+            .get(canister_id)
+            .into_iter()
+            .flatten()
+    }
+
+    /// Removes the divergence reports of the canister http requests of
+    /// `canister_id`, e.g. because the canister was deleted.
+    pub fn remove_http_request_divergences(&mut self, canister_id: &CanisterId) {
+        self.canister_http_divergence_reports.remove(canister_id);
+    }
+
+    /// Returns the number of pre-signatures of the ECDSA key `key_id` that
+    /// `canister_id` reserved, if the reservation has not expired at batch
+    /// time `now`.
+    pub fn ecdsa_quadruple_reservation(
+        &self,
+        key_id: &str,
+        canister_id: &CanisterId,
+        now: Time,
+    ) -> u32 {
+        self.ecdsa_quadruple_reservations
+            .get(key_id)
+            .and_then(|reservations| reservations.get(canister_id))
+            .filter(|reservation| reservation.is_active(now))
+            .map_or(0, |reservation| reservation.quadruples)
+    }
+
+    /// Returns the number of pre-signatures of the ECDSA key `key_id` that
+    /// all canisters reserved together in the reservations that have not
+    /// expired at batch time `now`.
+    pub fn reserved_ecdsa_quadruples(&self, key_id: &str, now: Time) -> u32 {
+        self.ecdsa_quadruple_reservations
+            .get(key_id)
+            .map_or(0, |reservations| {
+                reservations
+                    .values()
+                    .filter(|reservation| reservation.is_active(now))
+                    .map(|reservation| reservation.quadruples)
+                    .sum()
+            })
+    }
+
+    /// Returns the number of pre-signatures reserved per ECDSA key id and
+    /// canister in the reservations that have not expired at batch time
+    /// `now`.
+    pub fn active_ecdsa_quadruple_reservations(
+        &self,
+        now: Time,
+    ) -> BTreeMap<String, BTreeMap<CanisterId, u32>> {
+        self.ecdsa_quadruple_reservations
+            .iter()
+            .map(|(key_id, reservations)| {
+                let active = reservations
+                    .iter()
+                    .filter(|(_, reservation)| reservation.is_active(now))
+                    .map(|(canister_id, reservation)| (*canister_id, reservation.quadruples))
+                    .collect::<BTreeMap<_, _>>();
+                (key_id.clone(), active)
+            })
+            .filter(|(_, active)| !active.is_empty())
+            .collect()
+    }
+
+    /// Sets the number of pre-signatures of the ECDSA key `key_id` that
+    /// `canister_id` reserved at batch time `now` to `quadruples`, replacing
+    /// its previous reservation. Changing a reservation keeps its expiry, a
+    /// new reservation expires [`ECDSA_QUADRUPLE_RESERVATION_LIFETIME`] after
+    /// `now` unless it is renewed. A reservation of zero is removed, and so
+    /// are all reservations that have expired.
+    pub fn set_ecdsa_quadruple_reservation(
+        &mut self,
+        key_id: &str,
+        canister_id: CanisterId,
+        quadruples: u32,
+        now: Time,
+    ) {
+        for reservations in self.ecdsa_quadruple_reservations.values_mut() {
+            reservations.retain(|_, reservation| reservation.is_active(now));
+        }
+        if quadruples > 0 {
+            self.ecdsa_quadruple_reservations
+                .entry(key_id.to_string())
+                .or_default()
+                .entry(canister_id)
+                .or_insert(EcdsaQuadrupleReservation {
+                    quadruples,
+                    expiry: now + ECDSA_QUADRUPLE_RESERVATION_LIFETIME,
+                })
+                .quadruples = quadruples;
+        } else if let Some(reservations) = self.ecdsa_quadruple_reservations.get_mut(key_id) {
+            reservations.remove(&canister_id);
+        }
+        self.ecdsa_quadruple_reservations
+            .retain(|_, reservations| !reservations.is_empty());
+    }
+
+    /// Extends the reservation of pre-signatures of the ECDSA key `key_id`
+    /// by `canister_id` to expire [`ECDSA_QUADRUPLE_RESERVATION_LIFETIME`]
+    /// after `now`, unless it has expired already.
+    fn renew_ecdsa_quadruple_reservation(
+        &mut self,
+        key_id: &str,
+        canister_id: &CanisterId,
+        now: Time,
+    ) {
+        if let Some(reservation) = self
+            .ecdsa_quadruple_reservations
+            .get_mut(key_id)
+            .and_then(|reservations| reservations.get_mut(canister_id))
+            .filter(|reservation| reservation.is_active(now))
+        {
+            reservation.expiry = now + ECDSA_QUADRUPLE_RESERVATION_LIFETIME;
+        }
+    }
+
+    /// Removes the pre-signatures that `canister_id` reserved for any ECDSA
+    /// key, e.g. because the canister was deleted.
+    pub fn remove_ecdsa_quadruple_reservations(&mut self, canister_id: &CanisterId) {
+        for reservations in self.ecdsa_quadruple_reservations.values_mut() {
+            reservations.remove(canister_id);
+        }
+        self.ecdsa_quadruple_reservations
+            .retain(|_, reservations| !reservations.is_empty());
     }
 
     pub fn retrieve_request(
@@ -151,6 +445,36 @@ impl From<&SubnetCallContextManager> for pb_metadata::SubnetCallContextManager {
                     },
                 )
                 .collect(),
+            canister_http_divergence_reports: item
+                .canister_http_divergence_reports
+                .values()
+                .flatten()
+                .map(From::from)
+                .collect(),
+            sign_with_ecdsa_batch_contexts: item
+                .sign_with_ecdsa_batch_contexts
+                .iter()
+                .map(
+                    |(callback_id, context)| pb_metadata::SignWithEcdsaBatchContextTree {
+                        callback_id: callback_id.get(),
+                        context: Some(context.into()),
+                    },
+                )
+                .collect(),
+            ecdsa_quadruple_reservations: item
+                .ecdsa_quadruple_reservations
+                .iter()
+                .flat_map(|(key_id, reservations)| {
+                    reservations.iter().map(move |(canister_id, reservation)| {
+                        pb_metadata::EcdsaQuadrupleReservation {
+                            key_id: key_id.clone(),
+                            canister_id: Some((*canister_id).into()),
+                            quadruples: reservation.quadruples,
+                            expiry: reservation.expiry.as_nanos_since_unix_epoch(),
+                        }
+                    })
+                })
+                .collect(),
         }
     }
 }
@@ -187,13 +511,52 @@ impl TryFrom<pb_metadata::SubnetCallContextManager> for SubnetCallContextManager
             canister_http_request_contexts.insert(CallbackId::new(entry.callback_id), context);
         }
 
-        Ok(Self {
+        let mut sign_with_ecdsa_batch_contexts =
+            BTreeMap::<CallbackId, SignWithEcdsaBatchContext>::new();
+        for entry in item.sign_with_ecdsa_batch_contexts {
+            let context: SignWithEcdsaBatchContext =
+                try_from_option_field(entry.context, "SystemMetadata::SignWithEcdsaBatchContext")?;
+            sign_with_ecdsa_batch_contexts.insert(CallbackId::new(entry.callback_id), context);
+        }
+
+        let mut manager = Self {
             next_callback_id: item.next_callback_id,
             setup_initial_dkg_contexts,
             sign_with_ecdsa_contexts,
             sign_with_mock_ecdsa_contexts,
             canister_http_request_contexts,
-        })
+            canister_http_divergence_reports: BTreeMap::new(),
+            sign_with_ecdsa_batch_contexts: BTreeMap::new(),
+            sign_with_ecdsa_batch_items: BTreeMap::new(),
+            ecdsa_quadruple_reservations: BTreeMap::new(),
+        };
+        for (batch_id, context) in sign_with_ecdsa_batch_contexts {
+            manager.index_sign_with_ecdsa_batch_items(batch_id, &context);
+            manager
+                .sign_with_ecdsa_batch_contexts
+                .insert(batch_id, context);
+        }
+        for report in item.canister_http_divergence_reports {
+            manager.record_http_request_divergence(CanisterHttpDivergenceReport::try_from(report)?);
+        }
+        for reservation in item.ecdsa_quadruple_reservations {
+            let canister_id: CanisterId = try_from_option_field(
+                reservation.canister_id,
+                "EcdsaQuadrupleReservation::canister_id",
+            )?;
+            manager
+                .ecdsa_quadruple_reservations
+                .entry(reservation.key_id)
+                .or_default()
+                .insert(
+                    canister_id,
+                    EcdsaQuadrupleReservation {
+                        quadruples: reservation.quadruples,
+                        expiry: Time::from_nanos_since_unix_epoch(reservation.expiry),
+                    },
+                );
+        }
+        Ok(manager)
     }
 }
 
@@ -246,6 +609,8 @@ pub struct SignWithEcdsaContext {
     pub derivation_path: Vec<Vec<u8>>,
     pub pseudo_random_id: [u8; 32],
     pub batch_time: Time,
+    /// The id of the ECDSA key to sign with.
+    pub key_id: String,
 }
 
 impl From<&SignWithEcdsaContext> for pb_metadata::SignWithEcdsaContext {
@@ -256,6 +621,7 @@ impl From<&SignWithEcdsaContext> for pb_metadata::SignWithEcdsaContext {
             derivation_path_vec: context.derivation_path.clone(),
             pseudo_random_id: context.pseudo_random_id.to_vec(),
             batch_time: context.batch_time.as_nanos_since_unix_epoch(),
+            key_id: context.key_id.clone(),
         }
     }
 }
@@ -280,6 +646,98 @@ impl TryFrom<pb_metadata::SignWithEcdsaContext> for SignWithEcdsaContext {
                 id
             },
             batch_time: Time::from_nanos_since_unix_epoch(context.batch_time),
+            key_id: context.key_id,
+        })
+    }
+}
+
+/// The outcome of signing one message hash of a `sign_with_ecdsa_batch` call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignWithEcdsaBatchItem {
+    /// The message hash is being signed under the `SignWithEcdsaContext` with
+    /// this callback id.
+    Pending(CallbackId),
+    Signed(Vec<u8>),
+    Rejected(String),
+}
+
+impl From<&SignWithEcdsaBatchItem> for pb_metadata::SignWithEcdsaBatchItem {
+    fn from(item: &SignWithEcdsaBatchItem) -> Self {
+        use pb_metadata::sign_with_ecdsa_batch_item::Outcome;
+        let outcome = match item {
+            SignWithEcdsaBatchItem::Pending(callback_id) => Outcome::Pending(callback_id.get()),
+            SignWithEcdsaBatchItem::Signed(signature) => Outcome::Signature(signature.clone()),
+            SignWithEcdsaBatchItem::Rejected(message) => Outcome::Rejected(message.clone()),
+        };
+        pb_metadata::SignWithEcdsaBatchItem {
+            outcome: Some(outcome),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::SignWithEcdsaBatchItem> for SignWithEcdsaBatchItem {
+    type Error = ProxyDecodeError;
+    fn try_from(item: pb_metadata::SignWithEcdsaBatchItem) -> Result<Self, Self::Error> {
+        use pb_metadata::sign_with_ecdsa_batch_item::Outcome;
+        match item.outcome {
+            Some(Outcome::Pending(callback_id)) => Ok(SignWithEcdsaBatchItem::Pending(
+                CallbackId::new(callback_id),
+            )),
+            Some(Outcome::Signature(signature)) => Ok(SignWithEcdsaBatchItem::Signed(signature)),
+            Some(Outcome::Rejected(message)) => Ok(SignWithEcdsaBatchItem::Rejected(message)),
+            None => Err(ProxyDecodeError::MissingField(
+                "SignWithEcdsaBatchItem::outcome",
+            )),
+        }
+    }
+}
+
+/// A `sign_with_ecdsa_batch` call, which is replied to once all of its
+/// message hashes are signed or rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignWithEcdsaBatchContext {
+    pub request: Request,
+    /// The outcomes of the message hashes, in the order of the call.
+    pub items: Vec<SignWithEcdsaBatchItem>,
+}
+
+impl SignWithEcdsaBatchContext {
+    /// Returns the index of the message hash that is being signed under
+    /// `callback_id`, if it belongs to this call.
+    fn pending_item(&self, callback_id: CallbackId) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| *item == SignWithEcdsaBatchItem::Pending(callback_id))
+    }
+
+    /// Returns whether none of the message hashes is being signed anymore.
+    pub fn is_complete(&self) -> bool {
+        !self
+            .items
+            .iter()
+            .any(|item| matches!(item, SignWithEcdsaBatchItem::Pending(_)))
+    }
+}
+
+impl From<&SignWithEcdsaBatchContext> for pb_metadata::SignWithEcdsaBatchContext {
+    fn from(context: &SignWithEcdsaBatchContext) -> Self {
+        pb_metadata::SignWithEcdsaBatchContext {
+            request: Some((&context.request).into()),
+            items: context.items.iter().map(From::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::SignWithEcdsaBatchContext> for SignWithEcdsaBatchContext {
+    type Error = ProxyDecodeError;
+    fn try_from(context: pb_metadata::SignWithEcdsaBatchContext) -> Result<Self, Self::Error> {
+        Ok(SignWithEcdsaBatchContext {
+            request: try_from_option_field(context.request, "SignWithEcdsaBatchContext::request")?,
+            items: context
+                .items
+                .into_iter()
+                .map(SignWithEcdsaBatchItem::try_from)
+                .collect::<Result<_, _>>()?,
         })
     }
 }
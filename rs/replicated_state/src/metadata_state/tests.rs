@@ -1,5 +1,9 @@
 use super::*;
-use crate::metadata_state::subnet_call_context_manager::SubnetCallContextManager;
+use crate::metadata_state::subnet_call_context_manager::{
+    SignWithEcdsaBatchContext, SignWithEcdsaBatchItem, SignWithEcdsaContext,
+    SubnetCallContextManager, ECDSA_QUADRUPLE_RESERVATION_LIFETIME,
+    MAX_CANISTER_HTTP_DIVERGENCE_REPORTS,
+};
 use ic_base_types::HttpMethodType;
 use ic_test_utilities::{
     mock_time,
@@ -13,9 +17,12 @@ use ic_test_utilities::{
     },
 };
 use ic_types::{
-    canister_http::CanisterHttpRequestContext,
+    canister_http::{
+        CanisterHttpRequestContext, CanisterHttpRequestDivergence, CANISTER_HTTP_TIMEOUT_INTERVAL,
+    },
     ingress::{WasmResult, MAX_INGRESS_TTL},
     messages::{CallbackId, Payload},
+    Cycles,
 };
 use maplit::btreemap;
 use std::str::FromStr;
@@ -216,6 +223,9 @@ fn subnet_call_contexts_deserialization() {
         http_method: HttpMethodType::GET,
         transform_method_name: transform_method_name.clone(),
         time: mock_time(),
+        timeout: mock_time() + Duration::from_secs(10),
+        max_response_bytes: 1_000,
+        fee_per_response_byte: Cycles::new(10),
     };
     system_call_context_manager.push_http_request(canister_http_request);
 
@@ -243,6 +253,264 @@ fn subnet_call_contexts_deserialization() {
         deserialized_http_request_context.transform_method_name,
         transform_method_name
     );
+    assert_eq!(
+        deserialized_http_request_context.timeout,
+        mock_time() + Duration::from_secs(10)
+    );
+    assert_eq!(deserialized_http_request_context.max_response_bytes, 1_000);
+    assert_eq!(
+        deserialized_http_request_context.fee_per_response_byte,
+        Cycles::new(10)
+    );
+}
+
+fn canister_http_request_context(time: Time, timeout: Time) -> CanisterHttpRequestContext {
+    CanisterHttpRequestContext {
+        request: RequestBuilder::default()
+            .sender(canister_test_id(1))
+            .receiver(canister_test_id(2))
+            .build(),
+        url: "https://example.com".to_string(),
+        body: None,
+        http_method: HttpMethodType::GET,
+        transform_method_name: None,
+        time,
+        timeout,
+        max_response_bytes: 0,
+        fee_per_response_byte: Cycles::zero(),
+    }
+}
+
+#[test]
+fn canister_http_request_context_without_timeout_gets_default_timeout() {
+    let context = canister_http_request_context(mock_time(), mock_time());
+    let mut proto =
+        ic_protobuf::state::system_metadata::v1::CanisterHttpRequestContext::from(&context);
+    proto.timeout = 0;
+
+    let deserialized = CanisterHttpRequestContext::try_from(proto).unwrap();
+
+    assert_eq!(
+        deserialized.timeout,
+        mock_time() + CANISTER_HTTP_TIMEOUT_INTERVAL
+    );
+}
+
+#[test]
+fn timed_out_canister_http_requests_are_returned_in_callback_id_order() {
+    let mut manager = SubnetCallContextManager::default();
+    let now = mock_time() + Duration::from_secs(100);
+    let first = manager.push_http_request(canister_http_request_context(
+        mock_time(),
+        mock_time() + Duration::from_secs(50),
+    ));
+    let pending = manager.push_http_request(canister_http_request_context(
+        mock_time(),
+        mock_time() + Duration::from_secs(150),
+    ));
+    let second = manager.push_http_request(canister_http_request_context(mock_time(), now));
+
+    assert_eq!(manager.timed_out_http_requests(now), vec![first, second]);
+    assert_eq!(
+        manager
+            .canister_http_request_contexts
+            .keys()
+            .collect::<Vec<_>>(),
+        vec![&first, &pending, &second]
+    );
+}
+
+#[test]
+fn diverged_canister_http_requests_are_reported_to_the_calling_canister() {
+    let mut manager = SubnetCallContextManager::default();
+    let callback_ids: Vec<CallbackId> = (0..MAX_CANISTER_HTTP_DIVERGENCE_REPORTS + 1)
+        .map(|_| manager.push_http_request(canister_http_request_context(mock_time(), mock_time())))
+        .collect();
+
+    for (i, callback_id) in callback_ids.iter().enumerate() {
+        let now = mock_time() + Duration::from_secs(i as u64);
+        let divergence = CanisterHttpRequestDivergence::new(*callback_id, vec![]);
+        let report = manager
+            .record_diverged_http_request(&divergence, now)
+            .unwrap();
+        assert_eq!(report.request_id, *callback_id);
+        assert_eq!(report.canister_id, canister_test_id(1));
+        assert_eq!(report.time, now);
+    }
+    // The requests are kept until they are rejected.
+    assert_eq!(
+        manager.canister_http_request_contexts.len(),
+        callback_ids.len()
+    );
+    assert_eq!(
+        manager.record_diverged_http_request(
+            &CanisterHttpRequestDivergence::new(CallbackId::from(u64::MAX), vec![]),
+            mock_time()
+        ),
+        None
+    );
+
+    // Only the most recent reports are kept, and they survive a round trip
+    // through the protobuf representation.
+    let proto: ic_protobuf::state::system_metadata::v1::SubnetCallContextManager =
+        (&manager).into();
+    let deserialized: SubnetCallContextManager = proto.try_into().unwrap();
+    let reported: Vec<CallbackId> = deserialized
+        .http_request_divergences(&canister_test_id(1))
+        .map(|report| report.request_id)
+        .collect();
+    assert_eq!(reported, callback_ids[1..].to_vec());
+    assert_eq!(
+        deserialized
+            .http_request_divergences(&canister_test_id(2))
+            .count(),
+        0
+    );
+
+    manager.remove_http_request_divergences(&canister_test_id(1));
+    assert_eq!(
+        manager
+            .http_request_divergences(&canister_test_id(1))
+            .count(),
+        0
+    );
+}
+
+fn sign_with_ecdsa_context(message_hash: Vec<u8>) -> SignWithEcdsaContext {
+    SignWithEcdsaContext {
+        request: RequestBuilder::default()
+            .sender(canister_test_id(1))
+            .receiver(canister_test_id(2))
+            .build(),
+        message_hash,
+        derivation_path: vec![],
+        pseudo_random_id: [0; 32],
+        batch_time: mock_time(),
+        key_id: "secp256k1".to_string(),
+    }
+}
+
+#[test]
+fn sign_with_ecdsa_batch_is_returned_once_all_items_are_completed() {
+    let mut manager = SubnetCallContextManager::default();
+    let first = manager.push_sign_with_ecdsa_request(sign_with_ecdsa_context(vec![1; 32]), false);
+    let second = manager.push_sign_with_ecdsa_request(sign_with_ecdsa_context(vec![2; 32]), false);
+    let request = RequestBuilder::default()
+        .sender(canister_test_id(1))
+        .receiver(canister_test_id(2))
+        .build();
+    manager.push_sign_with_ecdsa_batch_request(SignWithEcdsaBatchContext {
+        request: request.clone(),
+        items: vec![
+            SignWithEcdsaBatchItem::Pending(first),
+            SignWithEcdsaBatchItem::Rejected("invalid".to_string()),
+            SignWithEcdsaBatchItem::Pending(second),
+        ],
+    });
+    assert!(manager.is_sign_with_ecdsa_batch_item(first));
+    assert!(manager.is_sign_with_ecdsa_batch_item(second));
+
+    assert_eq!(
+        manager.complete_sign_with_ecdsa_batch_item(
+            second,
+            SignWithEcdsaBatchItem::Rejected("failed".to_string())
+        ),
+        None
+    );
+    assert!(!manager.is_sign_with_ecdsa_batch_item(second));
+
+    // The pending batch survives a round trip through the protobuf
+    // representation.
+    let proto: ic_protobuf::state::system_metadata::v1::SubnetCallContextManager =
+        (&manager).into();
+    let mut manager: SubnetCallContextManager = proto.try_into().unwrap();
+    assert!(manager.is_sign_with_ecdsa_batch_item(first));
+    assert!(!manager.is_sign_with_ecdsa_batch_item(second));
+
+    let context = manager
+        .complete_sign_with_ecdsa_batch_item(first, SignWithEcdsaBatchItem::Signed(vec![7; 64]))
+        .unwrap();
+    assert_eq!(context.request, request);
+    assert_eq!(
+        context.items,
+        vec![
+            SignWithEcdsaBatchItem::Signed(vec![7; 64]),
+            SignWithEcdsaBatchItem::Rejected("invalid".to_string()),
+            SignWithEcdsaBatchItem::Rejected("failed".to_string()),
+        ]
+    );
+    assert!(manager.sign_with_ecdsa_batch_contexts.is_empty());
+    assert!(!manager.is_sign_with_ecdsa_batch_item(first));
+}
+
+#[test]
+fn ecdsa_quadruple_reservations_are_removed_with_their_canister() {
+    let now = mock_time();
+    let mut manager = SubnetCallContextManager::default();
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(1), 2, now);
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(2), 3, now);
+    manager.set_ecdsa_quadruple_reservation("other_key", canister_test_id(1), 1, now);
+    assert_eq!(manager.reserved_ecdsa_quadruples("secp256k1", now), 5);
+
+    // The reservations survive a round trip through the protobuf
+    // representation.
+    let proto: ic_protobuf::state::system_metadata::v1::SubnetCallContextManager =
+        (&manager).into();
+    let mut manager: SubnetCallContextManager = proto.try_into().unwrap();
+    assert_eq!(
+        manager.ecdsa_quadruple_reservation("secp256k1", &canister_test_id(2), now),
+        3
+    );
+
+    // Reserving no quadruples releases the reservation.
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(2), 0, now);
+    assert_eq!(manager.reserved_ecdsa_quadruples("secp256k1", now), 2);
+
+    manager.remove_ecdsa_quadruple_reservations(&canister_test_id(1));
+    assert!(manager.ecdsa_quadruple_reservations.is_empty());
+}
+
+#[test]
+fn ecdsa_quadruple_reservations_expire_unless_used() {
+    let now = mock_time();
+    let mut manager = SubnetCallContextManager::default();
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(1), 2, now);
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(3), 1, now);
+
+    // A signing request of the first canister with the key renews its
+    // reservation, while that of the other canister expires.
+    let later = now + ECDSA_QUADRUPLE_RESERVATION_LIFETIME / 2;
+    manager.push_sign_with_ecdsa_request(
+        SignWithEcdsaContext {
+            batch_time: later,
+            ..sign_with_ecdsa_context(vec![1; 32])
+        },
+        false,
+    );
+    let expired = now + ECDSA_QUADRUPLE_RESERVATION_LIFETIME;
+    assert_eq!(
+        manager.ecdsa_quadruple_reservation("secp256k1", &canister_test_id(1), expired),
+        2
+    );
+    assert_eq!(
+        manager.ecdsa_quadruple_reservation("secp256k1", &canister_test_id(3), expired),
+        0
+    );
+    assert_eq!(
+        manager.active_ecdsa_quadruple_reservations(expired),
+        btreemap! {
+            "secp256k1".to_string() => btreemap! { canister_test_id(1) => 2 },
+        }
+    );
+
+    // Changing a reservation does not renew it, and removes the expired ones.
+    manager.set_ecdsa_quadruple_reservation("secp256k1", canister_test_id(1), 1, expired);
+    assert_eq!(manager.ecdsa_quadruple_reservations["secp256k1"].len(), 1);
+    let renewed_expiry = later + ECDSA_QUADRUPLE_RESERVATION_LIFETIME;
+    assert_eq!(
+        manager.ecdsa_quadruple_reservation("secp256k1", &canister_test_id(1), renewed_expiry),
+        0
+    );
 }
 
 #[test]
@@ -251,6 +519,7 @@ fn empty_network_topology() {
         subnets: BTreeMap::new(),
         routing_table: Arc::new(RoutingTable::default()),
         nns_subnet_id: subnet_test_id(42),
+        ecdsa_signing_subnets: BTreeMap::new(),
     };
 
     assert_eq!(network_topology.bitcoin_testnet_subnets(), vec![]);
@@ -266,7 +535,8 @@ fn network_topology_bitcoin_testnet_subnets() {
                 public_key: vec![],
                 nodes: BTreeMap::new(),
                 subnet_type: SubnetType::Application,
-                subnet_features: SubnetFeatures::from_str("bitcoin_testnet").unwrap()
+                subnet_features: SubnetFeatures::from_str("bitcoin_testnet").unwrap(),
+                max_reserved_ecdsa_quadruples: 0,
             },
 
             // A subnet with the bitcoin testnet feature paused.
@@ -274,7 +544,8 @@ fn network_topology_bitcoin_testnet_subnets() {
                 public_key: vec![],
                 nodes: BTreeMap::new(),
                 subnet_type: SubnetType::Application,
-                subnet_features: SubnetFeatures::from_str("bitcoin_testnet_paused").unwrap()
+                subnet_features: SubnetFeatures::from_str("bitcoin_testnet_paused").unwrap(),
+                max_reserved_ecdsa_quadruples: 0,
             },
 
             // A subnet without the bitcoin feature enabled.
@@ -282,11 +553,13 @@ fn network_topology_bitcoin_testnet_subnets() {
                 public_key: vec![],
                 nodes: BTreeMap::new(),
                 subnet_type: SubnetType::Application,
-                subnet_features: SubnetFeatures::default()
+                subnet_features: SubnetFeatures::default(),
+                max_reserved_ecdsa_quadruples: 0,
             }
         ],
         routing_table: Arc::new(RoutingTable::default()),
         nns_subnet_id: subnet_test_id(42),
+        ecdsa_signing_subnets: BTreeMap::new(),
     };
 
     assert_eq!(
@@ -304,7 +577,8 @@ fn network_topology_ecdsa_subnets() {
                 public_key: vec![],
                 nodes: BTreeMap::new(),
                 subnet_type: SubnetType::Application,
-                subnet_features: SubnetFeatures::from_str("bitcoin_testnet_paused").unwrap()
+                subnet_features: SubnetFeatures::from_str("bitcoin_testnet_paused").unwrap(),
+                max_reserved_ecdsa_quadruples: 0,
             },
 
             // A subnet with ECDSA enabled.
@@ -312,12 +586,33 @@ fn network_topology_ecdsa_subnets() {
                 public_key: vec![],
                 nodes: BTreeMap::new(),
                 subnet_type: SubnetType::Application,
-                subnet_features: SubnetFeatures::from_str("ecdsa_signatures").unwrap()
+                subnet_features: SubnetFeatures::from_str("ecdsa_signatures").unwrap(),
+                max_reserved_ecdsa_quadruples: 0,
             }
         ],
         routing_table: Arc::new(RoutingTable::default()),
         nns_subnet_id: subnet_test_id(42),
+        ecdsa_signing_subnets: BTreeMap::new(),
     };
 
     assert_eq!(network_topology.ecdsa_subnets(), vec![subnet_test_id(1)]);
 }
+
+#[test]
+fn network_topology_ecdsa_signing_subnets() {
+    let network_topology = NetworkTopology {
+        ecdsa_signing_subnets: btreemap! {
+            "secp256k1".to_string() => vec![subnet_test_id(1)],
+        },
+        ..Default::default()
+    };
+
+    assert!(network_topology.may_sign_with_ecdsa_key(subnet_test_id(1), "secp256k1"));
+    assert!(!network_topology.may_sign_with_ecdsa_key(subnet_test_id(2), "secp256k1"));
+    // Keys without a signing subnet list may be used by any subnet.
+    assert!(network_topology.may_sign_with_ecdsa_key(subnet_test_id(2), "other_key"));
+
+    let pb_network_topology = pb_metadata::NetworkTopology::from(&network_topology);
+    let deserialized = NetworkTopology::try_from(pb_network_topology).unwrap();
+    assert_eq!(deserialized, network_topology);
+}
@@ -0,0 +1,100 @@
+//! This canister serves assets over `http_request` and certifies them the way
+//! the certified assets canister does: the SHA-256 hash of the body of each
+//! asset is stored under `http_assets/<path>` in a hash tree whose root hash
+//! is the certified data of the canister, and every response carries the
+//! certificate and a witness of its asset in the `IC-Certificate` header.
+//!
+//! The asset `/tampered` is served with a different body than the certified
+//! one, so that HTTP gateways must reject its responses.
+use dfn_core::api::{data_certificate, set_certified_data};
+use dfn_http::types::{HttpRequest, HttpResponse};
+use ic_certified_map::{labeled, labeled_hash, AsHashTree, Hash, RbTree};
+use serde::Serialize;
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+
+const LABEL_ASSETS: &[u8] = b"http_assets";
+
+/// The paths of the assets and the bodies that are certified for them.
+const CERTIFIED_ASSETS: &[(&str, &[u8])] = &[
+    ("/", b"Hello from a certified asset!"),
+    ("/tampered", b"Hello from a certified asset!"),
+];
+
+/// The body that is served for `/tampered` instead of the certified one.
+const TAMPERED_BODY: &[u8] = b"Hello from a tampered asset!";
+
+fn assets_tree() -> RbTree<&'static str, Hash> {
+    let mut tree = RbTree::new();
+    for (path, body) in CERTIFIED_ASSETS {
+        tree.insert(*path, Sha256::digest(body).into());
+    }
+    tree
+}
+
+fn certify_assets() {
+    set_certified_data(&labeled_hash(LABEL_ASSETS, &assets_tree().root_hash()));
+}
+
+#[export_name = "canister_init"]
+fn canister_init() {
+    certify_assets();
+}
+
+#[export_name = "canister_post_upgrade"]
+fn canister_post_upgrade() {
+    certify_assets();
+}
+
+/// Returns the value of the `IC-Certificate` header of the asset `path`.
+fn certificate_header(path: &str) -> String {
+    let tree = assets_tree();
+    let witness = labeled(LABEL_ASSETS, tree.witness(path.as_bytes()));
+    let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+    serializer.self_describe().unwrap();
+    witness.serialize(&mut serializer).unwrap();
+    let certificate = data_certificate().expect("No data certificate available");
+    format!(
+        "certificate=:{}:, tree=:{}:",
+        base64::encode(&certificate),
+        base64::encode(&serializer.into_inner())
+    )
+}
+
+#[export_name = "canister_query http_request"]
+fn http_request() {
+    dfn_core::over(
+        dfn_candid::candid,
+        |(req,): (HttpRequest,)| -> HttpResponse {
+            // The query string, e.g. the `canisterId` parameter of the HTTP
+            // gateway, is not part of the path of the asset.
+            let path = match req.url.find('?') {
+                None => &req.url[..],
+                Some(index) => &req.url[..index],
+            };
+            let body = match CERTIFIED_ASSETS.iter().find(|(p, _)| *p == path) {
+                Some(_) if path == "/tampered" => TAMPERED_BODY,
+                Some((_, body)) => *body,
+                None => {
+                    return HttpResponse {
+                        status_code: 404,
+                        headers: vec![],
+                        body: ByteBuf::from("not found"),
+                        streaming_strategy: None,
+                    }
+                }
+            };
+            HttpResponse {
+                status_code: 200,
+                headers: vec![
+                    ("Content-Type".to_string(), "text/plain".to_string()),
+                    ("IC-Certificate".to_string(), certificate_header(path)),
+                ],
+                body: ByteBuf::from(body),
+                streaming_strategy: None,
+            }
+        },
+    )
+}
+
+fn main() {}
@@ -0,0 +1,263 @@
+//! Typed builders for the threshold ECDSA API of the management canister.
+//!
+//! The builders take care of the candid encoding of the arguments, of the
+//! cycles to attach and of the decoding of the replies, independently of how
+//! the call is sent. From a canister, the call is made to the management
+//! canister directly; in system tests, it is usually forwarded by the
+//! universal canister:
+//!
+//! ```text
+//! let call = SignWithEcdsa::new(message_hash).with_derivation_path(path);
+//! let reply = uni_can
+//!     .forward_with_cycles_to(
+//!         &Principal::management_canister(),
+//!         &call.method_name(),
+//!         call.payload(),
+//!         call.cycles(),
+//!     )
+//!     .await?;
+//! let signature = call.decode_reply(&reply)?.signature;
+//! ```
+use candid::Encode;
+use ic_base_types::CanisterId;
+use ic_ic00_types::{
+    GetECDSAPublicKeyArgs, GetECDSAPublicKeyResponse, Method, Payload, SignWithECDSAArgs,
+    SignWithECDSAReply, IC_00,
+};
+use std::fmt;
+
+/// The id of the ECDSA key that calls refer to unless specified otherwise.
+pub const DEFAULT_KEY_ID: &str = "secp256k1";
+
+/// The error returned if the reply to a call can not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeReplyError {
+    pub method_name: String,
+    pub reason: String,
+}
+
+impl fmt::Display for DecodeReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to decode the reply to {}: {}",
+            self.method_name, self.reason
+        )
+    }
+}
+
+impl std::error::Error for DecodeReplyError {}
+
+/// A call to the threshold ECDSA API of the management canister.
+pub trait EcdsaApiCall {
+    /// The decoded reply to the call.
+    type Reply;
+
+    /// The management canister method that is called.
+    fn method(&self) -> Method;
+
+    /// The candid encoded argument of the call.
+    fn payload(&self) -> Vec<u8>;
+
+    /// The cycles attached to the call.
+    fn cycles(&self) -> u64;
+
+    /// Decodes the candid encoded reply to the call.
+    fn decode_reply(&self, reply: &[u8]) -> Result<Self::Reply, DecodeReplyError>;
+
+    /// The canister that is called, i.e., the management canister.
+    fn callee(&self) -> CanisterId {
+        IC_00
+    }
+
+    /// The name of the management canister method that is called.
+    fn method_name(&self) -> String {
+        self.method().to_string()
+    }
+}
+
+fn decode<'a, T: Payload<'a>>(method: Method, reply: &'a [u8]) -> Result<T, DecodeReplyError> {
+    T::decode(reply).map_err(|err| DecodeReplyError {
+        method_name: method.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// A `sign_with_ecdsa` call, requesting a signature of `message_hash` with the
+/// key derived from the key `key_id` of the calling canister along
+/// `derivation_path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignWithEcdsa {
+    pub message_hash: Vec<u8>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub key_id: String,
+    pub cycles: u64,
+}
+
+impl SignWithEcdsa {
+    /// Creates a call to sign the 32 bytes `message_hash` with the default key
+    /// and an empty derivation path, without cycles attached.
+    pub fn new(message_hash: [u8; 32]) -> Self {
+        Self {
+            message_hash: message_hash.to_vec(),
+            derivation_path: vec![],
+            key_id: DEFAULT_KEY_ID.to_string(),
+            cycles: 0,
+        }
+    }
+
+    pub fn with_derivation_path(mut self, derivation_path: Vec<Vec<u8>>) -> Self {
+        self.derivation_path = derivation_path;
+        self
+    }
+
+    pub fn with_key_id<S: Into<String>>(mut self, key_id: S) -> Self {
+        self.key_id = key_id.into();
+        self
+    }
+
+    pub fn with_cycles(mut self, cycles: u64) -> Self {
+        self.cycles = cycles;
+        self
+    }
+}
+
+impl EcdsaApiCall for SignWithEcdsa {
+    type Reply = SignWithECDSAReply;
+
+    fn method(&self) -> Method {
+        Method::SignWithECDSA
+    }
+
+    fn payload(&self) -> Vec<u8> {
+        Encode!(&SignWithECDSAArgs {
+            message_hash: self.message_hash.clone(),
+            derivation_path: self.derivation_path.clone(),
+            key_id: self.key_id.clone(),
+        })
+        .unwrap()
+    }
+
+    fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    fn decode_reply(&self, reply: &[u8]) -> Result<SignWithECDSAReply, DecodeReplyError> {
+        decode(self.method(), reply)
+    }
+}
+
+/// A `get_ecdsa_public_key` call, requesting the public key derived from the
+/// key `key_id` of `canister_id` (the calling canister if not set) along
+/// `derivation_path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetEcdsaPublicKey {
+    pub canister_id: Option<CanisterId>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub key_id: String,
+}
+
+impl GetEcdsaPublicKey {
+    /// Creates a call to get the public key of the calling canister for the
+    /// default key and an empty derivation path.
+    pub fn new() -> Self {
+        Self {
+            canister_id: None,
+            derivation_path: vec![],
+            key_id: DEFAULT_KEY_ID.to_string(),
+        }
+    }
+
+    pub fn with_canister_id(mut self, canister_id: CanisterId) -> Self {
+        self.canister_id = Some(canister_id);
+        self
+    }
+
+    pub fn with_derivation_path(mut self, derivation_path: Vec<Vec<u8>>) -> Self {
+        self.derivation_path = derivation_path;
+        self
+    }
+
+    pub fn with_key_id<S: Into<String>>(mut self, key_id: S) -> Self {
+        self.key_id = key_id.into();
+        self
+    }
+}
+
+impl Default for GetEcdsaPublicKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EcdsaApiCall for GetEcdsaPublicKey {
+    type Reply = GetECDSAPublicKeyResponse;
+
+    fn method(&self) -> Method {
+        Method::GetECDSAPublicKey
+    }
+
+    fn payload(&self) -> Vec<u8> {
+        Encode!(&GetECDSAPublicKeyArgs {
+            canister_id: self.canister_id,
+            derivation_path: self.derivation_path.clone(),
+            key_id: self.key_id.clone(),
+        })
+        .unwrap()
+    }
+
+    /// Getting a public key is free.
+    fn cycles(&self) -> u64 {
+        0
+    }
+
+    fn decode_reply(&self, reply: &[u8]) -> Result<GetECDSAPublicKeyResponse, DecodeReplyError> {
+        decode(self.method(), reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_with_ecdsa_payload_decodes_to_args() {
+        let call = SignWithEcdsa::new([1; 32])
+            .with_derivation_path(vec![vec![2, 3]])
+            .with_key_id("some_key")
+            .with_cycles(42);
+
+        let args = SignWithECDSAArgs::decode(&call.payload()).unwrap();
+
+        assert_eq!(call.method_name(), "sign_with_ecdsa");
+        assert_eq!(call.cycles(), 42);
+        assert_eq!(args.message_hash, vec![1; 32]);
+        assert_eq!(args.derivation_path, vec![vec![2, 3]]);
+        assert_eq!(args.key_id, "some_key");
+    }
+
+    #[test]
+    fn get_ecdsa_public_key_decodes_reply() {
+        let call = GetEcdsaPublicKey::new();
+        let reply = GetECDSAPublicKeyResponse {
+            public_key: vec![4; 33],
+            chain_code: vec![5; 32],
+        }
+        .encode();
+
+        let response = call.decode_reply(&reply).unwrap();
+
+        assert_eq!(call.method_name(), "get_ecdsa_public_key");
+        assert_eq!(response.public_key, vec![4; 33]);
+        assert_eq!(response.chain_code, vec![5; 32]);
+    }
+
+    #[test]
+    fn malformed_reply_is_an_error() {
+        let call = SignWithEcdsa::new([0; 32]);
+
+        let err = call.decode_reply(b"not candid").unwrap_err();
+
+        assert_eq!(err.method_name, "sign_with_ecdsa");
+    }
+}
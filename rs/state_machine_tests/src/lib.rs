@@ -283,6 +283,7 @@ impl StateMachine {
             registry_version: self.registry_client.get_latest_version(),
             time: self.time.get(),
             consensus_responses: vec![],
+            canister_http_responses: vec![],
         };
         self.message_routing
             .deliver_batch(batch)
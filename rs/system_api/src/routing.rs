@@ -38,6 +38,8 @@ pub(super) fn resolve_destination(
         | Ok(Ic00Method::GetMockECDSAPublicKey)
         | Ok(Ic00Method::SignWithMockECDSA)
         | Ok(Ic00Method::SignWithECDSA)
+        | Ok(Ic00Method::SignWithECDSABatch)
+        | Ok(Ic00Method::ReserveECDSAPreSignatures)
         | Ok(Ic00Method::HttpRequest) => Ok(own_subnet),
         // This message needs to be routed to the NNS subnet.  We assume that
         // this message can only be sent by canisters on the NNS subnet hence
@@ -70,6 +72,7 @@ pub(super) fn resolve_destination(
             })
         }
         Ok(Ic00Method::CanisterStatus)
+        | Ok(Ic00Method::HttpRequestDivergences)
         | Ok(Ic00Method::StartCanister)
         | Ok(Ic00Method::StopCanister)
         | Ok(Ic00Method::DeleteCanister)
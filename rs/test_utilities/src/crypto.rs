@@ -17,6 +17,7 @@ use ic_interfaces::crypto::{
 use ic_interfaces::crypto::{MultiSigVerifier, MultiSigner, Signable};
 use ic_interfaces::registry::RegistryClient;
 use ic_protobuf::crypto::v1::NodePublicKeys;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_registry_client::client::RegistryClientImpl;
 use ic_registry_client::fake::FakeRegistryClient;
 use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
@@ -483,6 +484,17 @@ impl KeyManager for CryptoReturningOk {
         Ok(())
     }
 
+    fn check_idkg_dealing_encryption_key_with_registry(
+        &self,
+        _registry_version: RegistryVersion,
+    ) -> CryptoResult<()> {
+        Ok(())
+    }
+
+    fn rotate_idkg_dealing_encryption_key(&self) -> CryptoResult<PublicKeyProto> {
+        Ok(PublicKeyProto::default())
+    }
+
     fn node_public_keys(&self) -> NodePublicKeys {
         unimplemented!()
     }
@@ -594,6 +606,12 @@ impl IDkgProtocol for CryptoReturningOk {
     }
 
     fn retain_active_transcripts(&self, _active_transcripts: &[IDkgTranscript]) {}
+
+    fn supported_algorithms(&self) -> BTreeSet<AlgorithmId> {
+        vec![AlgorithmId::ThresholdEcdsaSecp256k1]
+            .into_iter()
+            .collect()
+    }
 }
 
 impl ThresholdEcdsaSigner for CryptoReturningOk {
@@ -622,7 +640,10 @@ impl ThresholdEcdsaSigVerifier for CryptoReturningOk {
         _inputs: &ThresholdEcdsaSigInputs,
         _shares: &BTreeMap<NodeId, ThresholdEcdsaSigShare>,
     ) -> Result<ThresholdEcdsaCombinedSignature, ThresholdEcdsaCombineSigSharesError> {
-        Ok(ThresholdEcdsaCombinedSignature { signature: vec![] })
+        Ok(ThresholdEcdsaCombinedSignature {
+            signature: vec![],
+            recovery_id: None,
+        })
     }
 
     fn verify_combined_sig(
@@ -1,6 +1,7 @@
 use crate::util::mock_time;
 use ic_types::{
     batch::{Batch, BatchPayload},
+    canister_http::CanisterHttpResponse,
     Height, Randomness, RegistryVersion, Time,
 };
 
@@ -21,6 +22,7 @@ impl Default for BatchBuilder {
                 registry_version: RegistryVersion::from(1),
                 time: mock_time(),
                 consensus_responses: vec![],
+                canister_http_responses: vec![],
             },
         }
     }
@@ -61,6 +63,15 @@ impl BatchBuilder {
         self
     }
 
+    /// Set the canister_http_responses field to canister_http_responses.
+    pub fn canister_http_responses(
+        mut self,
+        canister_http_responses: Vec<CanisterHttpResponse>,
+    ) -> Self {
+        self.batch.canister_http_responses = canister_http_responses;
+        self
+    }
+
     /// Return the built Batch.
     pub fn build(&self) -> Batch {
         self.batch.clone()
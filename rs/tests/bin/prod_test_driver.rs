@@ -3,6 +3,7 @@ use ic_fondue::prod_tests::cli::CliArgs;
 use ic_fondue::prod_tests::driver_setup::create_driver_context_from_cli;
 use ic_fondue::prod_tests::evaluation::evaluate;
 use ic_fondue::prod_tests::pot_dsl::*;
+use ic_fondue::prod_tests::reporting::write_reports;
 use ic_tests::create_subnet::{self, create_subnet_test};
 use ic_tests::nns_fault_tolerance_test;
 use ic_tests::nns_follow_test::{self, test as follow_test};
@@ -12,10 +13,11 @@ use ic_tests::node_graceful_leaving_test::{self, test as node_graceful_leaving_t
 use ic_tests::node_removal_from_registry_test::{self, test as node_removal_from_registry_test};
 use ic_tests::node_restart_test::{self, test as node_restart_test};
 use ic_tests::orchestrator::{
-    cup_fetching_across_upgrades,
+    cup_fetching_across_upgrades, mixed_version_subnet_test,
     node_reassignment_test::{self, test as node_reassignment_test},
-    ssh_access_to_nodes, unassigned_node_upgrade_test, upgrade_reject,
+    ssh_access_to_nodes, subnet_halt_and_resume, unassigned_node_upgrade_test, upgrade_reject,
 };
+use ic_tests::registry_invariants::{check_registry_invariants, REGISTRY_INVARIANTS_CHECK};
 use ic_tests::rejoin_test::{self, test as rejoin_test};
 use ic_tests::rosetta_test;
 use ic_tests::security::nns_voting_fuzzing_poc_test;
@@ -23,14 +25,15 @@ use ic_tests::spec_compliance;
 use ic_tests::token_balance_test::{self, test as token_balance_test};
 use ic_tests::{
     basic_health_test::{self, basic_health_test},
+    boundary_node_http_gateway_test, canister_http_fault_tolerance_test, clock_skew_test,
     execution, message_routing,
 };
 use ic_tests::{
     cycles_minting_test, feature_flags,
     networking::firewall::{self, change_to_firewall_rules_takes_effect},
     nns_canister_upgrade_test, nns_uninstall_canister_by_proposal_test,
-    registry_authentication_test, tecdsa_signature_test, transaction_ledger_correctness_test,
-    wasm_generator_test,
+    registry_authentication_test, tecdsa_key_rotation_test, tecdsa_replica_restart_test,
+    tecdsa_signature_test, transaction_ledger_correctness_test, wasm_generator_test,
 };
 use regex::Regex;
 use std::collections::HashMap;
@@ -61,12 +64,16 @@ fn main() -> anyhow::Result<()> {
         &validated_args.skip_pattern,
     );
 
-    let context = create_driver_context_from_cli(validated_args, get_hostname());
+    let context = create_driver_context_from_cli(validated_args, get_hostname())
+        .with_post_pot_check(REGISTRY_INVARIANTS_CHECK, check_registry_invariants);
     let result = evaluate(&context, suite);
 
     if let Some(mut w) = writer {
         serde_json::to_writer_pretty(&mut w, &result)?;
     }
+    if let Some(dir) = &context.logs_base_dir {
+        write_reports(dir, &result)?;
+    }
 
     if result.result == TestResult::Failed {
         anyhow::bail!(format!("Test suite {} failed", result.name))
@@ -343,6 +350,14 @@ fn get_test_suites() -> HashMap<String, Suite> {
                     upgrade_reject::config,
                     par(vec![t("upgrade_reject_test", upgrade_reject::test)]),
                 ),
+                pot(
+                    "subnet_halt_and_resume_pot",
+                    subnet_halt_and_resume::config,
+                    par(vec![t(
+                        "subnet_halt_and_resume_test",
+                        subnet_halt_and_resume::test,
+                    )]),
+                ),
                 pot(
                     "basic_pot_with_all_features_enabled",
                     feature_flags::basic_config_with_all_features_enabled,
@@ -359,11 +374,54 @@ fn get_test_suites() -> HashMap<String, Suite> {
                 pot(
                     "tecdsa_signature_test_pot",
                     tecdsa_signature_test::enable_ecdsa_signatures_feature,
+                    par(vec![
+                        t(
+                            "test_threshold_ecdsa_signature",
+                            tecdsa_signature_test::test_threshold_ecdsa_signature,
+                        ),
+                        t(
+                            "test_threshold_ecdsa_signature_on_external_chains",
+                            tecdsa_signature_test::test_threshold_ecdsa_signature_on_external_chains,
+                        ),
+                    ]),
+                ),
+                pot(
+                    "tecdsa_signing_subnet_list_test_pot",
+                    tecdsa_signature_test::config_with_signing_subnet_list,
+                    par(vec![t(
+                        "test_threshold_ecdsa_signing_subnet_list",
+                        tecdsa_signature_test::test_threshold_ecdsa_signing_subnet_list,
+                    )]),
+                ),
+                pot(
+                    "tecdsa_replica_restart_test_pot",
+                    tecdsa_signature_test::enable_ecdsa_signatures_feature,
+                    par(vec![t(
+                        "test_threshold_ecdsa_signature_survives_replica_restart",
+                        tecdsa_replica_restart_test::test_threshold_ecdsa_signature_survives_replica_restart,
+                    )]),
+                ),
+                pot(
+                    "tecdsa_key_rotation_test_pot",
+                    tecdsa_signature_test::enable_ecdsa_signatures_feature,
                     par(vec![t(
-                        "test_threshold_ecdsa_signature",
-                        tecdsa_signature_test::test_threshold_ecdsa_signature,
+                        "test_threshold_ecdsa_key_rotation",
+                        tecdsa_key_rotation_test::test_threshold_ecdsa_key_rotation,
                     )]),
                 ),
+                pot(
+                    "canister_http_fault_tolerance_pot",
+                    canister_http_fault_tolerance_test::config,
+                    par(vec![t(
+                        "canister_http_fault_tolerance_test",
+                        canister_http_fault_tolerance_test::test,
+                    )]),
+                ),
+                pot(
+                    "clock_skew_pot",
+                    clock_skew_test::config,
+                    par(vec![t("clock_skew_test", clock_skew_test::test)]),
+                ),
             ],
         ),
     );
@@ -387,14 +445,42 @@ fn get_test_suites() -> HashMap<String, Suite> {
         "upgrade_compatibility".to_string(),
         suite(
             "upgrade_compatibility",
-            vec![pot_with_time_limit(
-                "cup_fetching_across_upgrades",
-                cup_fetching_across_upgrades::config,
-                par(vec![t(
+            vec![
+                pot_with_time_limit(
                     "cup_fetching_across_upgrades",
-                    cup_fetching_across_upgrades::test,
+                    cup_fetching_across_upgrades::config,
+                    par(vec![t(
+                        "cup_fetching_across_upgrades",
+                        cup_fetching_across_upgrades::test,
+                    )]),
+                    Duration::from_secs(1800),
+                ),
+                pot_with_time_limit(
+                    "mixed_version_subnet_pot",
+                    mixed_version_subnet_test::config,
+                    par(vec![t(
+                        "mixed_version_subnet_test",
+                        mixed_version_subnet_test::test,
+                    )]),
+                    Duration::from_secs(1800),
+                ),
+            ],
+        ),
+    );
+
+    // The tests in this suite require the boundary node image to be built
+    // prior to running the tests which is why we separate it out.
+    m.insert(
+        "boundary_nodes".to_string(),
+        suite(
+            "boundary_nodes",
+            vec![pot(
+                "boundary_node_http_gateway_pot",
+                boundary_node_http_gateway_test::config,
+                par(vec![t(
+                    "boundary_node_http_gateway_test",
+                    boundary_node_http_gateway_test::test,
                 )]),
-                Duration::from_secs(1800),
             )],
         ),
     );
@@ -91,6 +91,7 @@ def create_env_variables(is_local_run: bool, artifact_dir: str, ci_project_dir:
     env["PATH"] = f"{ci_project_dir}/ic-os/guestos/scripts:" + env["PATH"]
     if not is_local_run:
         env["XNET_TEST_CANISTER_WASM_PATH"] = f"{artifact_dir}/xnet-test-canister.wasm"
+        env["CERTIFIED_ASSETS_TEST_CANISTER_WASM_PATH"] = f"{artifact_dir}/certified-assets-test-canister.wasm"
     slack_notify = f"{ci_project_dir}/gitlab-ci/src/notify_slack"
     if env.get("PYTHONPATH") is None:
         env.setdefault("PYTHONPATH", slack_notify)
@@ -1 +1,4 @@
+pub mod boundary_node;
+pub mod node_clock;
 pub mod system_test_context;
+pub mod universal_vm;
@@ -0,0 +1,256 @@
+//! # Boundary Nodes
+//!
+//! A boundary node is a VM booted from a boundary-guestos image that serves
+//! the public API and the HTTP gateway of the Internet Computer under test
+//! over HTTPS. Like a universal VM, it is allocated in the Farm group of the
+//! IC nodes and hence deleted together with them at the end of the pot.
+//!
+//! The boundary node is configured with a bootstrap config image that is
+//! built with the `build-bootstrap-config-image.sh` script of the
+//! boundary-guestos. It points the boundary node to the NNS of the Internet
+//! Computer under test and authorizes the SSH keys given to the test driver.
+//! Once the control plane of the boundary node has fetched the routing table
+//! from the NNS, the boundary node forwards requests to the IC nodes.
+//!
+//! ```text
+//! let boundary_node = ctx.spawn_boundary_node(
+//!     "boundary-node-1",
+//!     BoundaryNodeImage::new(image_url, image_sha256),
+//!     BoundaryNodeConfig::default(),
+//! )?;
+//! boundary_node.await_status_is_healthy()?;
+//! let response = boundary_node
+//!     .http_client()?
+//!     .get(boundary_node.get_public_url().join("api/v2/status")?)
+//!     .send()?;
+//! ```
+//!
+//! The boundary node presents a self-signed certificate, which the client
+//! returned by `http_client()` accepts.
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::api::system_test_context::{
+    retry, HasIpAddr, HasPublicApiUrl, HasVmConsole, IcNodeContainer, SystemTestContext,
+};
+use anyhow::{bail, Result};
+use ic_fondue::ic_instance::{AmountOfMemoryKiB, NrOfVCPUs};
+use ic_fondue::prod_tests::farm::{CreateVmRequest, Farm, PrimaryImage};
+use ic_registry_subnet_type::SubnetType;
+use reqwest::{blocking::Client, redirect::Policy, StatusCode};
+use slog::info;
+use url::Url;
+
+const DEFAULT_VCPUS_PER_BOUNDARY_NODE: NrOfVCPUs = NrOfVCPUs::new(4);
+const DEFAULT_MEMORY_KIB_PER_BOUNDARY_NODE: AmountOfMemoryKiB = AmountOfMemoryKiB::new(8388608); // 8GiB
+const BOOTSTRAP_SCRIPT: &str = "ic-os/boundary-guestos/scripts/build-bootstrap-config-image.sh";
+const CONFIG_IMAGE_FNAME: &str = "bootstrap-config.img";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Booting the boundary node and fetching the routing table take longer than
+/// booting an IC node.
+const READY_TIMEOUT: Duration = Duration::from_secs(300);
+const READY_BACKOFF: Duration = Duration::from_secs(10);
+
+/// The disk image a boundary node boots from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundaryNodeImage {
+    pub url: Url,
+    pub sha256: String,
+}
+
+impl BoundaryNodeImage {
+    pub fn new(url: Url, sha256: String) -> Self {
+        Self { url, sha256 }
+    }
+}
+
+/// The resources and the configuration of a boundary node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundaryNodeConfig {
+    pub vcpus: NrOfVCPUs,
+    pub memory_kibibytes: AmountOfMemoryKiB,
+    /// The script that builds the bootstrap config image. Defaults to the
+    /// script of the boundary-guestos in this repository.
+    pub bootstrap_script: PathBuf,
+}
+
+impl Default for BoundaryNodeConfig {
+    fn default() -> Self {
+        Self {
+            vcpus: DEFAULT_VCPUS_PER_BOUNDARY_NODE,
+            memory_kibibytes: DEFAULT_MEMORY_KIB_PER_BOUNDARY_NODE,
+            bootstrap_script: Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../..")
+                .join(BOOTSTRAP_SCRIPT),
+        }
+    }
+}
+
+/// A handle to a running boundary node.
+#[derive(Clone)]
+pub struct BoundaryNodeSnapshot {
+    name: String,
+    group_name: String,
+    ip_addr: IpAddr,
+    farm: Farm,
+    ctx: SystemTestContext,
+}
+
+impl SystemTestContext {
+    /// Creates and starts the boundary node `name` in the Farm group of the
+    /// Internet Computer under test, configured with the NNS of the Internet
+    /// Computer. This function returns once the VM is started, which does not
+    /// imply that the boundary node serves requests yet; see
+    /// [BoundaryNodeSnapshot::await_status_is_healthy].
+    pub fn spawn_boundary_node(
+        &self,
+        name: &str,
+        image: BoundaryNodeImage,
+        config: BoundaryNodeConfig,
+    ) -> Result<BoundaryNodeSnapshot> {
+        let (farm, group_name) = self.farm_group()?;
+        let create_vm_request = CreateVmRequest::new(
+            name.to_string(),
+            config.vcpus,
+            config.memory_kibibytes,
+            PrimaryImage::new(image.url, image.sha256),
+        );
+        let ip_addr = farm.create_vm(&group_name, create_vm_request)?;
+        info!(self.log, "Boundary node({}) IP-Addr: {}", name, ip_addr);
+
+        let config_dir = tempfile::tempdir()?;
+        let config_image = self.create_boundary_node_config_image(
+            name,
+            &config.bootstrap_script,
+            config_dir.path(),
+        )?;
+        let image_id = farm.upload_image(
+            &group_name,
+            &config_image,
+            format!("{}-{}", name, CONFIG_IMAGE_FNAME),
+        )?;
+        farm.attach_disk_image(&group_name, name, "usb-storage", image_id)?;
+        farm.start_vm(&group_name, name)?;
+        Ok(BoundaryNodeSnapshot {
+            name: name.to_string(),
+            group_name,
+            ip_addr,
+            farm,
+            ctx: self.clone(),
+        })
+    }
+
+    /// Builds the bootstrap config image of the boundary node `name` in `dir`
+    /// and returns its path.
+    fn create_boundary_node_config_image(
+        &self,
+        name: &str,
+        bootstrap_script: &Path,
+        dir: &Path,
+    ) -> Result<PathBuf> {
+        let nns_urls: Vec<_> = self
+            .topology_snapshot()
+            .subnets()
+            .filter(|s| s.subnet_type() == SubnetType::System)
+            .flat_map(|s| s.nodes())
+            .map(|n| n.get_public_url().to_string())
+            .collect();
+        if nns_urls.is_empty() {
+            bail!("The Internet Computer under test has no NNS subnet");
+        }
+
+        let ssh_keys_dir = dir.join("ssh_authorized_keys");
+        std::fs::create_dir_all(&ssh_keys_dir)?;
+        for account in self.ssh_key_pairs() {
+            std::fs::write(ssh_keys_dir.join(&account.name), &account.public_key)?;
+        }
+
+        let img_path = dir.join(CONFIG_IMAGE_FNAME);
+        let output = Command::new(bootstrap_script)
+            .arg(&img_path)
+            .arg("--hostname")
+            .arg(name)
+            .arg("--nns_url")
+            .arg(nns_urls.join(" "))
+            .arg("--nns_public_key")
+            .arg(self.root_public_key_path())
+            .arg("--accounts_ssh_authorized_keys")
+            .arg(&ssh_keys_dir)
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "Could not build the bootstrap config image of boundary node {}: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(img_path)
+    }
+}
+
+impl BoundaryNodeSnapshot {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The url of the HTTPS endpoint through which the boundary node serves
+    /// the public API and the HTTP gateway.
+    pub fn get_public_url(&self) -> Url {
+        let host_str = match self.ip_addr {
+            IpAddr::V6(ip) => format!("[{}]", ip),
+            IpAddr::V4(ip) => ip.to_string(),
+        };
+        Url::from_str(&format!("https://{}/", host_str)).expect("Could not parse Url")
+    }
+
+    /// Returns a client for requests to the boundary node. The client accepts
+    /// the self-signed certificate of the boundary node and does not follow
+    /// redirects, so that they can be asserted on.
+    pub fn http_client(&self) -> Result<Client> {
+        Ok(Client::builder()
+            .danger_accept_invalid_certs(true)
+            .redirect(Policy::none())
+            .timeout(REQUEST_TIMEOUT)
+            .build()?)
+    }
+
+    /// Waits until the boundary node forwards status requests to the Internet
+    /// Computer under test, i.e. until its control plane fetched the routing
+    /// table.
+    pub fn await_status_is_healthy(&self) -> Result<()> {
+        let client = self.http_client()?;
+        let url = self.get_public_url().join("api/v2/status")?;
+        retry(self.ctx.log.clone(), READY_TIMEOUT, READY_BACKOFF, || {
+            let status = client.get(url.clone()).send()?.status();
+            if status != StatusCode::OK {
+                bail!("Boundary node {} replied with {}", self.name, status);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl HasIpAddr for BoundaryNodeSnapshot {
+    fn get_ip_addr(&self) -> IpAddr {
+        self.ip_addr
+    }
+}
+
+impl HasVmConsole for BoundaryNodeSnapshot {
+    fn console_output(&self) -> Result<String> {
+        Ok(self
+            .farm
+            .get_vm_console_output(&self.group_name, &self.name)?)
+    }
+
+    fn capture_console_output(&self) -> Result<PathBuf> {
+        let output = self.console_output()?;
+        self.ctx.write_console_log(&self.name, output)
+    }
+}
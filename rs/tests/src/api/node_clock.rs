@@ -0,0 +1,155 @@
+//! # Node clocks
+//!
+//! Replicas compare the time of their clock with the expiry of ingress
+//! messages, with the time of block proposals and with the time of the state
+//! they certify. To test how they handle clocks that are off, the clock of an
+//! IC node can be skewed relative to the clock of the test driver over SSH.
+//! Skewing the clock stops the time synchronization (chrony) on the node, so
+//! that the skew persists until the clock is reset.
+//!
+//! ```text
+//! let node = ctx.topology_snapshot().subnets().next().unwrap().nodes().next().unwrap();
+//! node.skew_clock(ClockSkew::Ahead(Duration::from_secs(600)))?;
+//! // ... assert on the behavior of the replica ...
+//! node.reset_clock()?;
+//! ```
+//!
+//! The skews are measured relative to the clock of the test driver, with a
+//! precision that is limited by the latency of SSH commands, i.e. they are
+//! only accurate to about a second.
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::system_test_context::IcNodeSnapshot;
+use anyhow::{bail, Result};
+
+/// The maximum difference between the skew of a clock and the requested skew
+/// after setting it.
+const CLOCK_SKEW_TOLERANCE: Duration = Duration::from_secs(5);
+const TIME_SYNC_SERVICE: &str = "chrony";
+
+/// The skew of the clock of a node relative to the clock of the test driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSkew {
+    Ahead(Duration),
+    Behind(Duration),
+}
+
+impl ClockSkew {
+    /// The absolute difference between the clocks.
+    pub fn magnitude(&self) -> Duration {
+        match self {
+            ClockSkew::Ahead(d) | ClockSkew::Behind(d) => *d,
+        }
+    }
+
+    /// Returns whether this skew differs from `other` by at most `tolerance`.
+    pub fn is_within(&self, other: ClockSkew, tolerance: Duration) -> bool {
+        let difference = (self.as_signed_nanos() - other.as_signed_nanos()).unsigned_abs();
+        difference <= tolerance.as_nanos()
+    }
+
+    fn as_signed_nanos(&self) -> i128 {
+        match self {
+            ClockSkew::Ahead(d) => d.as_nanos() as i128,
+            ClockSkew::Behind(d) => -(d.as_nanos() as i128),
+        }
+    }
+
+    fn from_signed_nanos(nanos: i128) -> Self {
+        let magnitude = Duration::from_nanos(nanos.unsigned_abs() as u64);
+        if nanos < 0 {
+            ClockSkew::Behind(magnitude)
+        } else {
+            ClockSkew::Ahead(magnitude)
+        }
+    }
+}
+
+impl fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockSkew::Ahead(d) => write!(f, "{:?} ahead", d),
+            ClockSkew::Behind(d) => write!(f, "{:?} behind", d),
+        }
+    }
+}
+
+/// Any node whose clock can be read and set implements this trait.
+pub trait HasClock {
+    /// Returns the skew of the clock of the node relative to the clock of the
+    /// test driver.
+    fn clock_skew(&self) -> Result<ClockSkew>;
+
+    /// Stops the time synchronization on the node and sets its clock to the
+    /// time of the clock of the test driver shifted by `skew`. Fails if the
+    /// time synchronization cannot be stopped, so that it does not slew the
+    /// clock back, or if the resulting skew is off by more than a few seconds.
+    fn skew_clock(&self, skew: ClockSkew) -> Result<()>;
+
+    /// Sets the clock of the node to the time of the clock of the test driver
+    /// and restarts the time synchronization on the node.
+    fn reset_clock(&self) -> Result<()>;
+}
+
+impl HasClock for IcNodeSnapshot {
+    fn clock_skew(&self) -> Result<ClockSkew> {
+        let before = unix_nanos(SystemTime::now());
+        let output = self.execute_admin_command("date +%s.%N")?;
+        let after = unix_nanos(SystemTime::now());
+        // The time on the node is compared with the time at which the
+        // command was probably executed, halfway through the SSH roundtrip.
+        let node_time = parse_unix_nanos(&output)?;
+        Ok(ClockSkew::from_signed_nanos(
+            node_time - (before + (after - before) / 2),
+        ))
+    }
+
+    fn skew_clock(&self, skew: ClockSkew) -> Result<()> {
+        self.execute_admin_command(&format!("sudo systemctl stop {}", TIME_SYNC_SERVICE))?;
+        set_clock(self, skew)?;
+        let actual = self.clock_skew()?;
+        if !actual.is_within(skew, CLOCK_SKEW_TOLERANCE) {
+            bail!(
+                "The clock of the node is {} after setting it to {}",
+                actual,
+                skew
+            );
+        }
+        Ok(())
+    }
+
+    fn reset_clock(&self) -> Result<()> {
+        set_clock(self, ClockSkew::Ahead(Duration::ZERO))?;
+        self.execute_admin_command(&format!("sudo systemctl start {}", TIME_SYNC_SERVICE))?;
+        Ok(())
+    }
+}
+
+/// Sets the clock of `node` to the time of the clock of the test driver
+/// shifted by `skew`.
+fn set_clock(node: &IcNodeSnapshot, skew: ClockSkew) -> Result<()> {
+    let time = unix_nanos(SystemTime::now()) + skew.as_signed_nanos();
+    node.execute_admin_command(&format!(
+        "sudo date --set @{}.{:09}",
+        time / 1_000_000_000,
+        time % 1_000_000_000
+    ))?;
+    Ok(())
+}
+
+fn unix_nanos(time: SystemTime) -> i128 {
+    time.duration_since(UNIX_EPOCH)
+        .expect("The clock of the test driver is before the Unix epoch")
+        .as_nanos() as i128
+}
+
+/// Parses the output of `date +%s.%N`.
+fn parse_unix_nanos(output: &str) -> Result<i128> {
+    let output = output.trim();
+    let (secs, nanos) = match output.split_once('.') {
+        Some((secs, nanos)) if nanos.len() == 9 => (secs, nanos),
+        _ => bail!("Could not parse the time {:?}", output),
+    };
+    Ok(secs.parse::<i128>()? * 1_000_000_000 + nanos.parse::<i128>()?)
+}
@@ -83,8 +83,64 @@
 //! let agent = node.build_default_agent();
 //! ```
 //!
-//! Upcoming: Implementation of VM operations as a separate trait implemented by
-//! NodeSnapshot.
+//! ### Waiting for the nodes to pick up a registry version
+//!
+//! A proposal being executed only means that the registry canister holds the
+//! new registry version. The nodes pick it up later, each at its own pace. To
+//! wait until all nodes of a subnet have fetched a registry version, e.g.
+//! before asserting on the effect of a proposal, use
+//! `await_nodes_at_registry_version()`:
+//!
+//! ```text
+//! subnet.await_nodes_at_registry_version(version, Duration::from_secs(60)).unwrap();
+//! ```
+//!
+//! ### Asserting on changes of the topology
+//!
+//! To wait until the topology reflects a proposal, e.g. until a subnet was
+//! created, use `assert_topology_eventually()`. Unlike `topology_snapshot()`,
+//! it syncs the local store with the NNS until the predicate holds:
+//!
+//! ```text
+//! let after = ctx.assert_topology_eventually(
+//!     |topology| topology.subnets().count() == 2,
+//!     Duration::from_secs(60),
+//! );
+//! ```
+//!
+//! To assert on what changed between two snapshots, compare them with
+//! `diff()`:
+//!
+//! ```text
+//! let diff = after.diff(&before);
+//! diff.assert_subnets_added(1);
+//! diff.assert_nodes_removed(subnet_id, &[node_id]);
+//! ```
+//!
+//! ### Selecting nodes by their registry labels
+//!
+//! The registry records the operator of each node, and the data center and the
+//! rewardable node types of each operator. [IcNodeSnapshot] exposes them and
+//! every [IcNodeContainer] can be filtered by them, e.g. to stop all nodes in
+//! a data center:
+//!
+//! ```text
+//! let nodes_in_zh1: Vec<_> = ctx.topology_snapshot().nodes_in_dc("zh1").collect();
+//! ```
+//!
+//! ### Capturing the console output of a node
+//!
+//! If a node fails before its public API or SSH is available, e.g. after a
+//! failed upgrade, the output of the serial console of its VM is often the
+//! only lead. The trait [HasVmConsole] is implemented for [IcNodeSnapshot] and
+//! stores the console output in the system test context:
+//!
+//! ```text
+//! let console_log = node.capture_console_output().unwrap();
+//! ```
+//!
+//! Upcoming: Implementation of further VM operations as a separate trait
+//! implemented by NodeSnapshot.
 //!
 //! ## Design Principles
 //!
@@ -118,7 +174,9 @@
 //! better to let the user select a node.
 
 use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
     convert::TryFrom,
+    fmt,
     future::Future,
     net::IpAddr,
     path::PathBuf,
@@ -127,18 +185,25 @@ use std::{
     time::{Duration, Instant},
 };
 
+use crate::orchestrator::utils::ssh_access::{admin_auth_mean_of, execute_remote_command_checked};
+use crate::prometheus_alerts::MetricsTarget;
 use crate::util::create_agent;
 use anyhow::{bail, Result};
 use ic_agent::Agent;
-use ic_fondue::ic_manager::IcHandle;
+use ic_fondue::ic_manager::{IcHandle, RuntimeDescriptor};
+use ic_fondue::prod_tests::{cli::AuthorizedSshAccount, farm::Farm};
+use ic_fondue::triage::{self, TriageHints};
 use ic_interfaces::registry::{RegistryClient, RegistryClientResult};
-use ic_protobuf::registry::{node::v1 as pb_node, subnet::v1 as pb_subnet};
+use ic_prep_lib::prep_state_directory::IcPrepStateDir;
+use ic_protobuf::registry::{
+    node::v1 as pb_node, node_operator::v1 as pb_node_operator, subnet::v1 as pb_subnet,
+};
 use ic_registry_client::{helper::node::NodeRegistry, local_registry::LocalRegistry};
+use ic_registry_common::values::deserialize_registry_value;
+use ic_registry_keys::make_node_operator_record_key;
 use ic_registry_subnet_type::SubnetType;
-use ic_types::{
-    messages::{HttpStatusResponse, ReplicaHealthStatus},
-    NodeId, RegistryVersion, SubnetId,
-};
+use ic_replica_status::{blocking::fetch_status, ReplicaStatus};
+use ic_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
 use rand_chacha::ChaCha8Rng;
 use slog::{info, warn};
 use tokio::runtime::{Handle as RtHandle, Runtime as Rt};
@@ -148,12 +213,25 @@ const REGISTRY_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
 const READY_RESPONSE_TIMEOUT: Duration = Duration::from_secs(6);
 const RETRY_TIMEOUT: Duration = Duration::from_secs(90);
 const RETRY_BACKOFF: Duration = Duration::from_secs(5);
+/// The metric in which the registry client of a replica reports the latest
+/// registry version it fetched.
+const REGISTRY_VERSION_METRIC: &str = "ic_registry_client_registry_version";
+
+/// The name under which the failures of
+/// [HasPublicApiUrl::await_status_is_healthy] are triaged, see
+/// [ic_fondue::triage::attach_hints].
+pub const AWAIT_STATUS_IS_HEALTHY: &str = "await_status_is_healthy";
+/// The name under which the failures of
+/// [IcNodeContainer::await_all_nodes_healthy] are triaged.
+pub const AWAIT_ALL_NODES_HEALTHY: &str = "await_all_nodes_healthy";
 
 /// Note: The SystemTestContext itself can be cloned/copied.
 #[derive(Clone)]
 pub struct SystemTestContext {
-    _path: PathBuf,
+    path: PathBuf,
     local_registry: Arc<LocalRegistry>,
+    runtime_descriptors: Arc<HashMap<NodeId, RuntimeDescriptor>>,
+    ssh_key_pairs: Arc<Vec<AuthorizedSshAccount>>,
     _rng: ChaCha8Rng,
     pub log: slog::Logger,
     handle: RtHandle,
@@ -166,6 +244,21 @@ impl SystemTestContext {
     ///
     /// * This function panics if the `ic_prep_working_dir` is `None`.
     pub fn from_ic_handle(ic_handle: IcHandle, fondue_context: &ic_fondue::pot::Context) -> Self {
+        let runtime_descriptors = Arc::new(
+            ic_handle
+                .public_api_endpoints
+                .iter()
+                .chain(ic_handle.malicious_public_api_endpoints.iter())
+                .map(|e| (e.node_id, e.runtime_descriptor.clone()))
+                .collect(),
+        );
+        let ssh_key_pairs = Arc::new(
+            ic_handle
+                .public_api_endpoints
+                .first()
+                .map(|e| e.ssh_key_pairs.clone())
+                .unwrap_or_default(),
+        );
         let ic_prep_dir = ic_handle
             .ic_prep_working_dir
             .expect("ic_prep_working_dir is not set!");
@@ -181,8 +274,10 @@ impl SystemTestContext {
         let handle = rt.handle().clone();
         let rt = Arc::new(Some(rt));
         Self {
-            _path: path,
+            path,
             local_registry,
+            runtime_descriptors,
+            ssh_key_pairs,
             _rng: rng,
             log,
             handle,
@@ -199,6 +294,85 @@ impl SystemTestContext {
             ctx: self.clone(),
         }
     }
+
+    /// Syncs the local store with the NNS until the topology at its newest
+    /// registry version satisfies `predicate`, and returns a snapshot of that
+    /// topology.
+    ///
+    /// # Panics
+    ///
+    /// * This function panics if the topology does not satisfy `predicate`
+    ///   within `timeout`.
+    pub fn assert_topology_eventually<P>(&self, predicate: P, timeout: Duration) -> TopologySnapshot
+    where
+        P: Fn(&TopologySnapshot) -> bool,
+    {
+        retry(self.log.clone(), timeout, RETRY_BACKOFF, || {
+            self.local_registry.sync_with_nns()?;
+            let topology = self.topology_snapshot();
+            if predicate(&topology) {
+                Ok(topology)
+            } else {
+                bail!(
+                    "The topology at registry version {} does not satisfy the predicate",
+                    topology.registry_version
+                )
+            }
+        })
+        .unwrap_or_else(|e| panic!("{:?}", e))
+    }
+
+    /// Syncs the local store with the NNS once.
+    pub fn sync_registry_with_nns(&self) -> Result<()> {
+        Ok(self.local_registry.sync_with_nns()?)
+    }
+
+    /// The path of the PEM file with the root public key of the Internet
+    /// Computer under test, e.g. to configure auxiliary VMs with it.
+    pub(crate) fn root_public_key_path(&self) -> PathBuf {
+        IcPrepStateDir::new(&self.path).root_public_key_path()
+    }
+
+    /// The registry backed by the local store of the system test context.
+    pub(crate) fn local_registry(&self) -> &LocalRegistry {
+        &self.local_registry
+    }
+
+    /// Returns the Farm instance and the name of the Farm group that host the
+    /// VMs of the Internet Computer under test.
+    pub(crate) fn farm_group(&self) -> Result<(Farm, String)> {
+        self.runtime_descriptors
+            .values()
+            .find_map(|d| match d {
+                RuntimeDescriptor::Vm(info) => Some((
+                    Farm::new(info.url.clone(), self.log.clone()),
+                    info.group_name.clone(),
+                )),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!("The Internet Computer under test is not hosted by Farm")
+            })
+    }
+
+    /// The accounts whose SSH keys are authorized on the VMs under test.
+    pub(crate) fn ssh_key_pairs(&self) -> &[AuthorizedSshAccount] {
+        &self.ssh_key_pairs
+    }
+
+    /// Stores the console output of a VM in the file
+    /// `console_logs/<file_stem>.log` and returns the path of the file.
+    pub(crate) fn write_console_log(&self, file_stem: &str, output: String) -> Result<PathBuf> {
+        let dir = self.path.join("console_logs");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.log", file_stem));
+        std::fs::write(&path, output)?;
+        info!(
+            self.log,
+            "Captured the console output of {} in {:?}", file_stem, path
+        );
+        Ok(path)
+    }
 }
 
 /// An immutable snapshot of the Internet Computer topology valid at a
@@ -212,7 +386,7 @@ pub struct TopologySnapshot {
 impl TopologySnapshot {
     pub fn subnets(&self) -> Box<dyn Iterator<Item = SubnetSnapshot>> {
         use ic_registry_client::helper::subnet::SubnetListRegistry;
-        let registry_version = self.ctx.local_registry.get_latest_version();
+        let registry_version = self.registry_version;
         Box::new(
             self.ctx
                 .local_registry
@@ -228,6 +402,236 @@ impl TopologySnapshot {
                 .into_iter(),
         )
     }
+
+    /// Returns the subnets that hold the threshold ECDSA key `key_id`.
+    pub fn subnets_holding_ecdsa_key(&self, key_id: &str) -> Vec<SubnetSnapshot> {
+        use ic_registry_client::helper::subnet::SubnetListRegistry;
+        let registry_version = self.registry_version;
+        self.ctx
+            .local_registry
+            .get_subnets_holding_ecdsa_key(key_id, registry_version)
+            .unwrap_result()
+            .into_iter()
+            .map(|subnet_id| SubnetSnapshot {
+                subnet_id,
+                registry_version,
+                ctx: self.ctx.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the changes of the topology from `earlier` to this snapshot.
+    pub fn diff(&self, earlier: &TopologySnapshot) -> TopologyDiff {
+        TopologyDiff::new(
+            earlier.registry_version,
+            &earlier.subnet_layouts(),
+            self.registry_version,
+            &self.subnet_layouts(),
+        )
+    }
+
+    fn subnet_layouts(&self) -> BTreeMap<SubnetId, SubnetLayout> {
+        self.subnets()
+            .map(|subnet| {
+                let layout = SubnetLayout {
+                    nodes: subnet.nodes().map(|node| node.node_id).collect(),
+                    features: subnet.raw_subnet_record().features,
+                };
+                (subnet.subnet_id, layout)
+            })
+            .collect()
+    }
+}
+
+/// The parts of a subnet that [TopologyDiff] compares.
+#[derive(Clone, Debug, PartialEq)]
+struct SubnetLayout {
+    nodes: BTreeSet<NodeId>,
+    features: Option<pb_subnet::SubnetFeatures>,
+}
+
+/// The changes of a subnet that is part of both compared snapshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubnetDiff {
+    pub subnet_id: SubnetId,
+    pub added_nodes: BTreeSet<NodeId>,
+    pub removed_nodes: BTreeSet<NodeId>,
+    /// The features before and after, if they changed.
+    pub features: Option<(
+        Option<pb_subnet::SubnetFeatures>,
+        Option<pb_subnet::SubnetFeatures>,
+    )>,
+}
+
+/// The structural changes of the topology between two [TopologySnapshot]s:
+/// the subnets that were created or removed, and the node membership and
+/// features of the subnets that changed. The assertions panic with the whole
+/// diff, so that a failing test shows everything that changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopologyDiff {
+    pub from_version: RegistryVersion,
+    pub to_version: RegistryVersion,
+    pub added_subnets: BTreeSet<SubnetId>,
+    pub removed_subnets: BTreeSet<SubnetId>,
+    /// The subnets that are part of both snapshots and changed.
+    pub changed_subnets: BTreeMap<SubnetId, SubnetDiff>,
+}
+
+impl TopologyDiff {
+    fn new(
+        from_version: RegistryVersion,
+        from: &BTreeMap<SubnetId, SubnetLayout>,
+        to_version: RegistryVersion,
+        to: &BTreeMap<SubnetId, SubnetLayout>,
+    ) -> Self {
+        let changed_subnets = to
+            .iter()
+            .filter_map(|(subnet_id, after)| {
+                let before = from.get(subnet_id)?;
+                let diff = SubnetDiff {
+                    subnet_id: *subnet_id,
+                    added_nodes: after.nodes.difference(&before.nodes).copied().collect(),
+                    removed_nodes: before.nodes.difference(&after.nodes).copied().collect(),
+                    features: (before.features != after.features)
+                        .then(|| (before.features.clone(), after.features.clone())),
+                };
+                let changed = !diff.added_nodes.is_empty()
+                    || !diff.removed_nodes.is_empty()
+                    || diff.features.is_some();
+                changed.then(|| (*subnet_id, diff))
+            })
+            .collect();
+        Self {
+            from_version,
+            to_version,
+            added_subnets: to
+                .keys()
+                .filter(|subnet_id| !from.contains_key(subnet_id))
+                .copied()
+                .collect(),
+            removed_subnets: from
+                .keys()
+                .filter(|subnet_id| !to.contains_key(subnet_id))
+                .copied()
+                .collect(),
+            changed_subnets,
+        }
+    }
+
+    /// Returns true if the topology did not change structurally.
+    pub fn is_empty(&self) -> bool {
+        self.added_subnets.is_empty()
+            && self.removed_subnets.is_empty()
+            && self.changed_subnets.is_empty()
+    }
+
+    pub fn assert_no_changes(&self) {
+        assert!(self.is_empty(), "The topology changed: {}", self);
+    }
+
+    /// Asserts that exactly `count` subnets were created and none removed.
+    pub fn assert_subnets_added(&self, count: usize) {
+        assert!(
+            self.added_subnets.len() == count && self.removed_subnets.is_empty(),
+            "Expected {} added subnets: {}",
+            count,
+            self
+        );
+    }
+
+    /// Asserts that exactly `count` subnets were removed and none created.
+    pub fn assert_subnets_removed(&self, count: usize) {
+        assert!(
+            self.removed_subnets.len() == count && self.added_subnets.is_empty(),
+            "Expected {} removed subnets: {}",
+            count,
+            self
+        );
+    }
+
+    /// Asserts that exactly `nodes` joined `subnet_id`.
+    pub fn assert_nodes_added(&self, subnet_id: SubnetId, nodes: &[NodeId]) {
+        let added = self
+            .changed_subnets
+            .get(&subnet_id)
+            .map(|diff| diff.added_nodes.clone())
+            .unwrap_or_default();
+        assert_eq!(
+            added,
+            nodes.iter().copied().collect(),
+            "Unexpected nodes added to subnet {}: {}",
+            subnet_id,
+            self
+        );
+    }
+
+    /// Asserts that exactly `nodes` left `subnet_id`.
+    pub fn assert_nodes_removed(&self, subnet_id: SubnetId, nodes: &[NodeId]) {
+        let removed = self
+            .changed_subnets
+            .get(&subnet_id)
+            .map(|diff| diff.removed_nodes.clone())
+            .unwrap_or_default();
+        assert_eq!(
+            removed,
+            nodes.iter().copied().collect(),
+            "Unexpected nodes removed from subnet {}: {}",
+            subnet_id,
+            self
+        );
+    }
+
+    /// Asserts that the features of `subnet_id` changed to `features`.
+    pub fn assert_features_changed(
+        &self,
+        subnet_id: SubnetId,
+        features: pb_subnet::SubnetFeatures,
+    ) {
+        let after = self
+            .changed_subnets
+            .get(&subnet_id)
+            .and_then(|diff| diff.features.as_ref())
+            .map(|(_, after)| after.clone());
+        assert_eq!(
+            after,
+            Some(Some(features)),
+            "Unexpected features of subnet {}: {}",
+            subnet_id,
+            self
+        );
+    }
+}
+
+impl fmt::Display for TopologyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "registry version {} -> {}",
+            self.from_version, self.to_version
+        )?;
+        if self.is_empty() {
+            return write!(f, ": no changes");
+        }
+        for subnet_id in &self.added_subnets {
+            write!(f, "\n  + subnet {}", subnet_id)?;
+        }
+        for subnet_id in &self.removed_subnets {
+            write!(f, "\n  - subnet {}", subnet_id)?;
+        }
+        for diff in self.changed_subnets.values() {
+            write!(f, "\n  ~ subnet {}", diff.subnet_id)?;
+            for node_id in &diff.added_nodes {
+                write!(f, "\n      + node {}", node_id)?;
+            }
+            for node_id in &diff.removed_nodes {
+                write!(f, "\n      - node {}", node_id)?;
+            }
+            if let Some((before, after)) = &diff.features {
+                write!(f, "\n      features {:?} -> {:?}", before, after)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -244,6 +648,46 @@ impl SubnetSnapshot {
             .expect("Could not transform from protobuf subnet type")
     }
 
+    /// Returns the identifiers of the threshold ECDSA keys held by the subnet.
+    pub fn ecdsa_key_ids(&self) -> Vec<String> {
+        use ic_registry_client::helper::subnet::SubnetRegistry;
+
+        self.ctx
+            .local_registry
+            .get_ecdsa_key_ids(self.subnet_id, self.registry_version)
+            .unwrap_result()
+    }
+
+    /// Waits until every node of the subnet reports that it fetched at least
+    /// the registry version `version`, or fails after `timeout`.
+    pub fn await_nodes_at_registry_version(
+        &self,
+        version: RegistryVersion,
+        timeout: Duration,
+    ) -> Result<()> {
+        let nodes: Vec<_> = self.nodes().collect();
+        retry(self.ctx.log.clone(), timeout, RETRY_BACKOFF, || {
+            let mut lagging_nodes = vec![];
+            for node in nodes.iter() {
+                match node.registry_version_on_node() {
+                    Ok(node_version) if node_version >= version => (),
+                    Ok(node_version) => lagging_nodes
+                        .push(format!("{} (at version {})", node.node_id, node_version)),
+                    Err(e) => lagging_nodes.push(format!("{} ({})", node.node_id, e)),
+                }
+            }
+            if lagging_nodes.is_empty() {
+                Ok(())
+            } else {
+                bail!(
+                    "Nodes not at registry version {} yet: {}",
+                    version,
+                    lagging_nodes.join(", ")
+                )
+            }
+        })
+    }
+
     pub fn raw_subnet_record(&self) -> pb_subnet::SubnetRecord {
         use ic_registry_client::helper::subnet::SubnetRegistry;
 
@@ -269,6 +713,106 @@ impl IcNodeSnapshot {
             .unwrap_result()
     }
 
+    fn raw_node_operator_record(&self) -> Option<pb_node_operator::NodeOperatorRecord> {
+        let node_operator_id = self.node_operator_id()?;
+        let bytes = self.ctx.local_registry.get_value(
+            &make_node_operator_record_key(node_operator_id),
+            self.registry_version,
+        );
+        deserialize_registry_value::<pb_node_operator::NodeOperatorRecord>(bytes)
+            .expect("registry error!")
+    }
+
+    /// Returns the id of the operator of the node, if its node record names
+    /// one.
+    pub fn node_operator_id(&self) -> Option<PrincipalId> {
+        let node_operator_id = self.raw_node_record().node_operator_id;
+        if node_operator_id.is_empty() {
+            return None;
+        }
+        Some(
+            PrincipalId::try_from(node_operator_id)
+                .expect("Could not transform from protobuf node operator id"),
+        )
+    }
+
+    /// Returns the id of the data center that the node is located in, as
+    /// recorded for its operator.
+    pub fn dc_id(&self) -> Option<String> {
+        self.raw_node_operator_record()
+            .map(|record| record.dc_id)
+            .filter(|dc_id| !dc_id.is_empty())
+    }
+
+    /// Returns the type under which the node is rewarded, e.g. `default`.
+    ///
+    /// The registry only records how many nodes of each type an operator is
+    /// rewarded for, so the type of the node is only known if all rewardable
+    /// nodes of its operator have the same type.
+    pub fn node_reward_type(&self) -> Option<String> {
+        self.raw_node_operator_record()
+            .and_then(|record| single_node_reward_type(&record.rewardable_nodes))
+    }
+
+    /// Returns the metrics endpoint of the replica on the node, named by the
+    /// node id.
+    pub fn metrics_target(&self) -> Result<MetricsTarget> {
+        let node_record = self.raw_node_record();
+        let endpoint = match node_record
+            .prometheus_metrics
+            .first()
+            .or_else(|| node_record.prometheus_metrics_http.as_ref())
+        {
+            Some(endpoint) => endpoint,
+            None => bail!("Node {} has no metrics endpoint", self.node_id),
+        };
+        Ok(MetricsTarget {
+            instance: self.node_id.to_string(),
+            url: IcNodeSnapshot::http_endpoint_to_url(endpoint),
+        })
+    }
+
+    /// Returns the latest registry version that the replica on the node
+    /// fetched, as reported in its metrics.
+    pub fn registry_version_on_node(&self) -> Result<RegistryVersion> {
+        let metrics = reqwest::blocking::Client::builder()
+            .timeout(READY_RESPONSE_TIMEOUT)
+            .build()
+            .expect("cannot build a reqwest client")
+            .get(self.metrics_target()?.url)
+            .send()?
+            .text()?;
+        metrics
+            .lines()
+            .find_map(|line| {
+                line.strip_prefix(REGISTRY_VERSION_METRIC)?
+                    .strip_prefix(' ')
+            })
+            .map(|value| -> Result<RegistryVersion> {
+                Ok(RegistryVersion::from(value.trim().parse::<f64>()? as u64))
+            })
+            .unwrap_or_else(|| bail!("Metric {} is not reported", REGISTRY_VERSION_METRIC))
+    }
+
+    /// Executes `command` on the node over SSH as the `admin` user and returns
+    /// its standard output. Fails if the command exits with a non-zero status.
+    pub(crate) fn execute_admin_command(&self, command: &str) -> Result<String> {
+        let auth_mean = match admin_auth_mean_of(self.ctx.ssh_key_pairs()) {
+            Some(auth_mean) => auth_mean,
+            None => bail!("No SSH key pair for the admin account"),
+        };
+        execute_remote_command_checked(&self.get_ip_addr(), "admin", &auth_mean, command).map_err(
+            |e| {
+                anyhow::anyhow!(
+                    "Could not execute `{}` on node {}: {}",
+                    command,
+                    self.node_id,
+                    e
+                )
+            },
+        )
+    }
+
     fn http_endpoint_to_url(http: &pb_node::ConnectionEndpoint) -> Url {
         let host_str = match IpAddr::from_str(&http.ip_addr.clone()) {
             Ok(v) if v.is_ipv6() => format!("[{}]", v),
@@ -298,7 +842,7 @@ pub trait HasPublicApiUrl {
 
     fn build_default_agent(&self) -> Agent;
 
-    fn status(&self) -> Result<HttpStatusResponse>;
+    fn status(&self) -> Result<ReplicaStatus>;
 }
 
 impl HasPublicApiUrl for IcNodeSnapshot {
@@ -328,9 +872,7 @@ impl HasPublicApiUrl for IcNodeSnapshot {
 
     fn status_is_healthy(&self) -> Result<bool> {
         match self.status() {
-            Ok(s) if s.replica_health_status.is_some() => {
-                Ok(Some(ReplicaHealthStatus::Healthy) == s.replica_health_status)
-            }
+            Ok(s) if s.health_status.is_some() => Ok(s.is_healthy()),
             Ok(_) => {
                 warn!(self.ctx.log, "Health status not set in status response!");
                 Ok(false)
@@ -347,31 +889,52 @@ impl HasPublicApiUrl for IcNodeSnapshot {
             self.status_is_healthy()
                 .and_then(|s| if !s { bail!("Not ready!") } else { Ok(()) })
         })
+        .map_err(|e| {
+            triage::helper_failed(
+                AWAIT_STATUS_IS_HEALTHY,
+                TriageHints::new().subsystem("replica"),
+                &format!("{} is not healthy: {}", self.get_public_url(), e),
+            );
+            e
+        })
     }
 
-    fn status(&self) -> Result<HttpStatusResponse> {
-        let response = reqwest::blocking::Client::builder()
-            .timeout(READY_RESPONSE_TIMEOUT)
-            .build()
-            .expect("cannot build a reqwest client")
-            .get(
-                self.get_public_url()
-                    .join("api/v2/status")
-                    .expect("failed to join URLs"),
-            )
-            .send()?;
-
-        let cbor_response = serde_cbor::from_slice(
-            &response
-                .bytes()
-                .expect("failed to convert a response to bytes")
-                .to_vec(),
-        )
-        .expect("response is not encoded as cbor");
-        Ok(
-            serde_cbor::value::from_value::<HttpStatusResponse>(cbor_response)
-                .expect("failed to deserialize a response to HttpStatusResponse"),
-        )
+    fn status(&self) -> Result<ReplicaStatus> {
+        Ok(fetch_status(
+            &self.get_public_url(),
+            READY_RESPONSE_TIMEOUT,
+        )?)
+    }
+}
+
+/// Any node that runs in a VM implements this trait to give access to the
+/// serial console of the VM. Unlike the public API or SSH, the console is
+/// available from the moment the VM is started.
+pub trait HasVmConsole {
+    /// Returns the output written to the serial console of the VM so far.
+    fn console_output(&self) -> Result<String>;
+
+    /// Stores the output written to the serial console of the VM so far in
+    /// the file `console_logs/<node id or VM name>.log` of the system test
+    /// context and returns the path of the file.
+    fn capture_console_output(&self) -> Result<PathBuf>;
+}
+
+impl HasVmConsole for IcNodeSnapshot {
+    fn console_output(&self) -> Result<String> {
+        match self.ctx.runtime_descriptors.get(&self.node_id) {
+            Some(RuntimeDescriptor::Vm(info)) => {
+                let farm = Farm::new(info.url.clone(), self.ctx.log.clone());
+                Ok(farm.get_vm_console_output(&info.group_name, &info.vm_name)?)
+            }
+            _ => bail!("Node {} does not run in a Farm VM", self.node_id),
+        }
+    }
+
+    fn capture_console_output(&self) -> Result<PathBuf> {
+        let output = self.console_output()?;
+        self.ctx
+            .write_console_log(&self.node_id.to_string(), output)
     }
 }
 
@@ -379,6 +942,13 @@ pub trait HasIpAddr {
     fn get_ip_addr(&self) -> IpAddr;
 }
 
+impl HasIpAddr for IcNodeSnapshot {
+    fn get_ip_addr(&self) -> IpAddr {
+        let node_record = self.raw_node_record();
+        IpAddr::from_str(&node_record.http.unwrap().ip_addr).expect("Could not parse IP address")
+    }
+}
+
 pub trait HasRegistryVersion {
     fn get_registry_version(&self) -> RegistryVersion;
 }
@@ -408,7 +978,79 @@ pub trait IcNodeContainer {
     /// unassigned nodes if called on [TopologySnapshot], for example.
     fn nodes(&self) -> Box<dyn Iterator<Item = IcNodeSnapshot>>;
 
-    fn await_all_nodes_healthy(&self) -> Result<()>;
+    /// Returns the nodes that are located in the data center `dc_id`.
+    fn nodes_in_dc(&self, dc_id: &str) -> Box<dyn Iterator<Item = IcNodeSnapshot>> {
+        let dc_id = dc_id.to_string();
+        Box::new(
+            self.nodes()
+                .filter(move |node| node.dc_id().as_deref() == Some(dc_id.as_str())),
+        )
+    }
+
+    /// Returns the nodes that are operated by `node_operator_id`.
+    fn nodes_of_operator(
+        &self,
+        node_operator_id: PrincipalId,
+    ) -> Box<dyn Iterator<Item = IcNodeSnapshot>> {
+        Box::new(
+            self.nodes()
+                .filter(move |node| node.node_operator_id() == Some(node_operator_id)),
+        )
+    }
+
+    /// Returns the nodes that are rewarded as `node_reward_type`, see
+    /// [IcNodeSnapshot::node_reward_type].
+    fn nodes_with_reward_type(
+        &self,
+        node_reward_type: &str,
+    ) -> Box<dyn Iterator<Item = IcNodeSnapshot>> {
+        let node_reward_type = node_reward_type.to_string();
+        Box::new(self.nodes().filter(move |node| {
+            node.node_reward_type().as_deref() == Some(node_reward_type.as_str())
+        }))
+    }
+
+    fn await_all_nodes_healthy(&self) -> Result<()> {
+        let mut jhs = vec![];
+        for node in self.nodes() {
+            jhs.push(std::thread::spawn(move || node.await_status_is_healthy()));
+        }
+        #[allow(clippy::needless_collect)]
+        let res: Vec<_> = jhs.into_iter().map(|j| j.join().unwrap()).collect();
+        res.into_iter().try_for_each(|x| x).map_err(|e| {
+            // The nodes are awaited on separate threads, so the failure is
+            // recorded on the thread of the test here.
+            triage::helper_failed(
+                AWAIT_ALL_NODES_HEALTHY,
+                TriageHints::new().subsystem("replica"),
+                &e,
+            );
+            e
+        })
+    }
+}
+
+impl IcNodeContainer for TopologySnapshot {
+    fn nodes(&self) -> Box<dyn Iterator<Item = IcNodeSnapshot>> {
+        let registry_version = self.registry_version;
+        let node_ids = self
+            .ctx
+            .local_registry
+            .get_node_ids(registry_version)
+            .expect("registry error!");
+
+        Box::new(
+            node_ids
+                .into_iter()
+                .map(|node_id| IcNodeSnapshot {
+                    node_id,
+                    registry_version,
+                    ctx: self.ctx.clone(),
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
+    }
 }
 
 impl IcNodeContainer for SubnetSnapshot {
@@ -434,15 +1076,18 @@ impl IcNodeContainer for SubnetSnapshot {
                 .into_iter(),
         )
     }
+}
 
-    fn await_all_nodes_healthy(&self) -> Result<()> {
-        let mut jhs = vec![];
-        for node in self.nodes() {
-            jhs.push(std::thread::spawn(move || node.await_status_is_healthy()));
-        }
-        #[allow(clippy::needless_collect)]
-        let res: Vec<_> = jhs.into_iter().map(|j| j.join().unwrap()).collect();
-        res.into_iter().try_for_each(|x| x)
+/// Returns the only node type in `rewardable_nodes` with a positive count, if
+/// there is exactly one.
+fn single_node_reward_type(rewardable_nodes: &BTreeMap<String, u32>) -> Option<String> {
+    let mut node_types = rewardable_nodes
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(node_type, _)| node_type);
+    match (node_types.next(), node_types.next()) {
+        (Some(node_type), None) => Some(node_type.clone()),
+        _ => None,
     }
 }
 
@@ -457,7 +1102,12 @@ trait RegistryResultHelper<T> {
     fn unwrap_result(self) -> T;
 }
 
-fn retry<F, R>(log: slog::Logger, timeout: Duration, backoff: Duration, f: F) -> Result<R>
+pub(crate) fn retry<F, R>(
+    log: slog::Logger,
+    timeout: Duration,
+    backoff: Duration,
+    f: F,
+) -> Result<R>
 where
     F: Fn() -> Result<R>,
 {
@@ -491,3 +1141,110 @@ impl std::fmt::Display for TimeoutError {
         write!(f, "TimeoutError: {:?}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types::PrincipalId;
+
+    fn node_test_id(id: u64) -> NodeId {
+        NodeId::from(PrincipalId::new_node_test_id(id))
+    }
+
+    fn subnet_test_id(id: u64) -> SubnetId {
+        SubnetId::from(PrincipalId::new_subnet_test_id(id))
+    }
+
+    fn layout(nodes: &[u64], ecdsa_signatures: bool) -> SubnetLayout {
+        SubnetLayout {
+            nodes: nodes.iter().map(|id| node_test_id(*id)).collect(),
+            features: Some(pb_subnet::SubnetFeatures {
+                ecdsa_signatures,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn should_diff_subnets_nodes_and_features() {
+        let from = btreemap(vec![
+            (1, layout(&[1, 2], false)),
+            (2, layout(&[3], false)),
+            (3, layout(&[4], false)),
+        ]);
+        let to = btreemap(vec![
+            (1, layout(&[1, 2], false)),
+            (2, layout(&[3, 5], true)),
+            (4, layout(&[4], false)),
+        ]);
+
+        let diff = TopologyDiff::new(
+            RegistryVersion::from(1),
+            &from,
+            RegistryVersion::from(2),
+            &to,
+        );
+
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added_subnets,
+            vec![subnet_test_id(4)].into_iter().collect()
+        );
+        assert_eq!(
+            diff.removed_subnets,
+            vec![subnet_test_id(3)].into_iter().collect()
+        );
+        assert_eq!(
+            diff.changed_subnets.keys().collect::<Vec<_>>(),
+            vec![&subnet_test_id(2)]
+        );
+        diff.assert_nodes_added(subnet_test_id(2), &[node_test_id(5)]);
+        diff.assert_nodes_removed(subnet_test_id(2), &[]);
+        diff.assert_nodes_added(subnet_test_id(1), &[]);
+        diff.assert_features_changed(
+            subnet_test_id(2),
+            pb_subnet::SubnetFeatures {
+                ecdsa_signatures: true,
+                ..Default::default()
+            },
+        );
+        TopologyDiff::new(
+            RegistryVersion::from(1),
+            &from,
+            RegistryVersion::from(2),
+            &from,
+        )
+        .assert_no_changes();
+    }
+
+    #[test]
+    fn should_only_determine_node_reward_type_if_unambiguous() {
+        let rewardable_nodes = |entries: &[(&str, u32)]| -> BTreeMap<String, u32> {
+            entries
+                .iter()
+                .map(|(node_type, count)| (node_type.to_string(), *count))
+                .collect()
+        };
+
+        assert_eq!(single_node_reward_type(&rewardable_nodes(&[])), None);
+        assert_eq!(
+            single_node_reward_type(&rewardable_nodes(&[("default", 4)])),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            single_node_reward_type(&rewardable_nodes(&[("default", 4), ("type1", 0)])),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            single_node_reward_type(&rewardable_nodes(&[("default", 4), ("type1", 1)])),
+            None
+        );
+    }
+
+    fn btreemap(layouts: Vec<(u64, SubnetLayout)>) -> BTreeMap<SubnetId, SubnetLayout> {
+        layouts
+            .into_iter()
+            .map(|(id, layout)| (subnet_test_id(id), layout))
+            .collect()
+    }
+}
@@ -0,0 +1,158 @@
+//! # Universal VMs
+//!
+//! Some tests need companion services next to the Internet Computer under
+//! test, e.g. a mock HTTPS API as the target of canister http requests, a
+//! bitcoind, or an NTP server. A universal VM is an auxiliary VM booted from
+//! an arbitrary image that is allocated in the same Farm group as the IC
+//! nodes. Hence, it shares the lifecycle of the nodes: it is deleted together
+//! with the group at the end of the pot. Like for IC nodes, the SSH keys of the
+//! authorized accounts are the ones given to the test driver and the console
+//! output is captured in the system test context.
+//!
+//! ```text
+//! let vm = ctx.spawn_universal_vm(
+//!     "httpbin",
+//!     UniversalVmImage::new(image_url, image_sha256),
+//!     UniversalVmConfig::default(),
+//! )?;
+//! let ip_addr = vm.get_ip_addr();
+//! ```
+use std::{net::IpAddr, path::PathBuf};
+
+use crate::api::system_test_context::{HasIpAddr, HasVmConsole, SystemTestContext};
+use anyhow::{bail, Result};
+use ic_fondue::ic_instance::{AmountOfMemoryKiB, NrOfVCPUs};
+use ic_fondue::prod_tests::farm::{CreateVmRequest, Farm, PrimaryImage};
+use slog::info;
+use ssh2::Session;
+use std::net::TcpStream;
+use url::Url;
+
+const DEFAULT_VCPUS_PER_UNIVERSAL_VM: NrOfVCPUs = NrOfVCPUs::new(2);
+const DEFAULT_MEMORY_KIB_PER_UNIVERSAL_VM: AmountOfMemoryKiB = AmountOfMemoryKiB::new(8388608); // 8GiB
+
+/// The disk image a universal VM boots from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniversalVmImage {
+    pub url: Url,
+    pub sha256: String,
+}
+
+impl UniversalVmImage {
+    pub fn new(url: Url, sha256: String) -> Self {
+        Self { url, sha256 }
+    }
+}
+
+/// The resources and the configuration of a universal VM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniversalVmConfig {
+    pub vcpus: NrOfVCPUs,
+    pub memory_kibibytes: AmountOfMemoryKiB,
+    /// If set, the disk image at this path is attached to the VM as a USB
+    /// storage device, e.g. to configure the services running on the VM.
+    pub config_image: Option<PathBuf>,
+}
+
+impl Default for UniversalVmConfig {
+    fn default() -> Self {
+        Self {
+            vcpus: DEFAULT_VCPUS_PER_UNIVERSAL_VM,
+            memory_kibibytes: DEFAULT_MEMORY_KIB_PER_UNIVERSAL_VM,
+            config_image: None,
+        }
+    }
+}
+
+/// A handle to a running universal VM.
+#[derive(Clone)]
+pub struct UniversalVm {
+    name: String,
+    group_name: String,
+    ip_addr: IpAddr,
+    farm: Farm,
+    ctx: SystemTestContext,
+}
+
+impl SystemTestContext {
+    /// Creates and starts the universal VM `name` in the Farm group of the
+    /// Internet Computer under test. This function returns once the VM is
+    /// started, which does not imply that the services on the VM are ready.
+    pub fn spawn_universal_vm(
+        &self,
+        name: &str,
+        image: UniversalVmImage,
+        config: UniversalVmConfig,
+    ) -> Result<UniversalVm> {
+        let (farm, group_name) = self.farm_group()?;
+        let create_vm_request = CreateVmRequest::new(
+            name.to_string(),
+            config.vcpus,
+            config.memory_kibibytes,
+            PrimaryImage::new(image.url, image.sha256),
+        );
+        let ip_addr = farm.create_vm(&group_name, create_vm_request)?;
+        info!(self.log, "Universal VM({}) IP-Addr: {}", name, ip_addr);
+        if let Some(config_image) = config.config_image {
+            let filename = config_image
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_else(|| format!("{}-config.img", name));
+            let image_id = farm.upload_image(&group_name, &config_image, filename)?;
+            farm.attach_disk_image(&group_name, name, "usb-storage", image_id)?;
+        }
+        farm.start_vm(&group_name, name)?;
+        Ok(UniversalVm {
+            name: name.to_string(),
+            group_name,
+            ip_addr,
+            farm,
+            ctx: self.clone(),
+        })
+    }
+}
+
+impl UniversalVm {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Opens an SSH session to the VM as `username`, authenticating with the
+    /// key of the authorized account of the same name.
+    pub fn ssh_session(&self, username: &str) -> Result<Session> {
+        let account = match self.ctx.ssh_key_pairs().iter().find(|a| a.name == username) {
+            Some(account) => account,
+            None => bail!("No SSH key pair for account {}", username),
+        };
+        let tcp = TcpStream::connect((self.ip_addr, 22))?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        session.userauth_pubkey_memory(
+            username,
+            None,
+            &String::from_utf8_lossy(&account.private_key),
+            None,
+        )?;
+        Ok(session)
+    }
+}
+
+impl HasIpAddr for UniversalVm {
+    fn get_ip_addr(&self) -> IpAddr {
+        self.ip_addr
+    }
+}
+
+impl HasVmConsole for UniversalVm {
+    fn console_output(&self) -> Result<String> {
+        Ok(self
+            .farm
+            .get_vm_console_output(&self.group_name, &self.name)?)
+    }
+
+    fn capture_console_output(&self) -> Result<PathBuf> {
+        let output = self.console_output()?;
+        self.ctx.write_console_log(&self.name, output)
+    }
+}
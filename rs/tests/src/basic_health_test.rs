@@ -15,8 +15,10 @@ Runbook::
 . Verify that the canisters can be updated and the modifications queried
 . Perform cross-net messaging from each UC to the other
 . Verify that the canisters' state differs in-between
-. Verify that the canisters finally arrive at an equal state
+. Verify that the canisters finally arrive at an equal state, with queries
+  whose certificates are verified
 . Verify that all the nodes self-report as healthy.
+. Verify that none of the production alerts would fire.
 
 Success:: All mutations to the subnets and installed canisters on them occur
 in the expected way. Intermediate and final canister states can be observed.
@@ -31,6 +33,7 @@ Coverage::
 
 end::catalog[] */
 
+use crate::prometheus_alerts::assert_no_alerts_fire;
 use crate::{api::system_test_context::*, util::*}; // to use the universal canister
 use ic_fondue::{
     ic_instance::{InternetComputer, Subnet}, // which is declared through these types
@@ -61,6 +64,7 @@ const MSG: &[u8] = b"this beautiful prose should be persisted for future generat
 /// [ic_fondue::pot::Context] which contains a number of auxiliary tools such as
 /// a logger, and a PRNG.
 pub fn basic_health_test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let root_key = root_public_key(&handle);
     // The system test context can be created from the IcHandle and the fondue-Context.
     let ctx = SystemTestContext::from_ic_handle(handle, ctx);
     // Assemble a list that contains one node per subnet.
@@ -140,14 +144,25 @@ pub fn basic_health_test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
 
     info!(ctx.log, "Assert that message has been stored ...");
     // Finally we query each of the canisters to ensure that the canister
-    // memories have been updated as expected.
+    // memories have been updated as expected. The certificates of these
+    // queries are verified as well.
     for (node, ucan_id) in nodes.iter().zip(ucan_ids) {
         node.with_default_agent(move |agent| async move {
-            let ucan = UniversalCanister::from_canister_id(&agent, ucan_id);
+            let ucan = UniversalCanister::from_canister_id(&agent, ucan_id)
+                .with_certified_queries(root_key);
             assert_eq!(
                 ucan.try_read_stable(0, XNET_MSG.len() as u32).await,
                 XNET_MSG.to_vec()
             );
         })
     }
+
+    info!(ctx.log, "Checking the alerting rules ...");
+    let metrics_targets: Vec<_> = ctx
+        .topology_snapshot()
+        .subnets()
+        .flat_map(|s| s.nodes())
+        .map(|n| n.metrics_target().unwrap())
+        .collect();
+    assert_no_alerts_fire(&metrics_targets, &[], &ctx.log);
 }
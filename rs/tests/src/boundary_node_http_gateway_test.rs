@@ -0,0 +1,257 @@
+/* tag::catalog[]
+Title:: HTTP gateway of a boundary node
+
+Goal::
+Ensure that a boundary node in front of an Internet Computer proxies the
+public API, serves certified canister responses through the HTTP gateway and
+rejects tampered ones, rejects unknown routes and rate limits clients, replying
+with the expected status codes and headers.
+
+Description::
+We deploy an NNS subnet of one node and provision a boundary node pointing to
+it with the boundary node API of the system test context. The boundary node
+boots from the image at `$BOUNDARY_NODE_IMAGE_URL` with the SHA256 hash
+`$BOUNDARY_NODE_IMAGE_SHA256`. The expected status codes and headers are the
+ones configured by nginx on the boundary-guestos. The certified assets test
+canister certifies its assets like the certified assets canister, but serves
+`/tampered` with a different body than the certified one, which the HTTP
+gateway must fail to verify and reply to with an error page.
+
+Runbook::
+. Deploy an NNS subnet of one node and install the certified assets test
+  canister.
+. Provision a boundary node and wait until it proxies status requests.
+. Make requests to the public API, to unknown routes, to the HTTP port and to
+  the HTTP gateway and assert on their status codes and headers.
+. Make a burst of concurrent requests to the HTTP gateway.
+
+Success::
+. All responses carry the expected status code and headers.
+. The certified asset is served with its body, the tampered one is rejected.
+. Some of the burst of requests are rejected by the rate limit.
+
+end::catalog[] */
+
+use crate::api::boundary_node::{BoundaryNodeConfig, BoundaryNodeImage};
+use crate::api::system_test_context::*;
+use crate::util::*;
+use canister_test::Project;
+use ic_fondue::ic_instance::{InternetComputer, Subnet};
+use ic_fondue::ic_manager::IcHandle;
+use ic_registry_subnet_type::SubnetType;
+use reqwest::blocking::{Client, Response};
+use reqwest::{Method, StatusCode};
+use slog::info;
+use std::env;
+use std::str::FromStr;
+use url::Url;
+
+const BOUNDARY_NODE_NAME: &str = "boundary-node-1";
+
+/// The body that the certified assets test canister certifies and serves for
+/// its index.
+const CERTIFIED_BODY: &str = "Hello from a certified asset!";
+
+/// The headers that nginx adds to all responses of locations without headers
+/// of their own.
+const SECURITY_HEADERS: &[(&str, &str)] = &[
+    (
+        "strict-transport-security",
+        "max-age=31536000; includeSubDomains; preload",
+    ),
+    ("x-frame-options", "DENY"),
+    ("x-content-type-options", "nosniff"),
+    ("x-xss-protection", "1; mode=block"),
+];
+
+/// The CORS headers of the responses of the public API.
+const API_CORS_HEADERS: &[(&str, &str)] = &[
+    ("access-control-allow-methods", "GET, POST, HEAD, OPTIONS"),
+    ("access-control-allow-origin", "*"),
+    ("access-control-allow-credentials", "true"),
+    ("access-control-max-age", "600"),
+];
+
+/// The CORS headers of the responses of the HTTP gateway.
+const GATEWAY_CORS_HEADERS: &[(&str, &str)] = &[
+    ("access-control-allow-origin", "*"),
+    ("access-control-allow-methods", "GET, POST, HEAD, OPTIONS"),
+    (
+        "access-control-expose-headers",
+        "Content-Length,Content-Range",
+    ),
+];
+
+/// The HTTP gateway is limited to 100 requests per second with a burst of
+/// 200, so that some of these requests must be rejected.
+const BURST_THREADS: usize = 100;
+const BURST_REQUESTS_PER_THREAD: usize = 10;
+
+pub fn config() -> InternetComputer {
+    InternetComputer::new().add_subnet(Subnet::new(SubnetType::System).add_nodes(1))
+}
+
+pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let image = boundary_node_image_from_env();
+    let ctx = SystemTestContext::from_ic_handle(handle, ctx);
+    let node = ctx
+        .topology_snapshot()
+        .subnets()
+        .flat_map(|s| s.nodes())
+        .next()
+        .unwrap();
+    node.await_status_is_healthy().unwrap();
+    let wasm = Project::cargo_bin_maybe_use_path_relative_to_rs(
+        "rust_canisters/certified_assets_test",
+        "certified-assets-test-canister",
+        &[],
+    );
+    let canister_id = node.with_default_agent(move |agent| async move {
+        create_and_install(&agent, &wasm.bytes()).await
+    });
+    info!(
+        ctx.log,
+        "Installed the certified assets test canister {}", canister_id
+    );
+
+    let boundary_node = ctx
+        .spawn_boundary_node(BOUNDARY_NODE_NAME, image, BoundaryNodeConfig::default())
+        .unwrap();
+    info!(
+        ctx.log,
+        "Waiting for the boundary node to become healthy ..."
+    );
+    if let Err(err) = boundary_node.await_status_is_healthy() {
+        let _ = boundary_node.capture_console_output();
+        panic!("Boundary node did not become healthy: {}", err);
+    }
+    let client = boundary_node.http_client().unwrap();
+    let base_url = boundary_node.get_public_url();
+
+    info!(ctx.log, "Requesting the status through the public API");
+    let response = send(
+        &client,
+        Method::GET,
+        base_url.join("api/v2/status").unwrap(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_headers(&response, SECURITY_HEADERS);
+    assert_has_header(&response, "x-ic-node-id");
+    assert_has_header(&response, "x-ic-subnet-id");
+
+    info!(ctx.log, "Requesting the CORS preflight of the public API");
+    let url = base_url
+        .join(&format!("api/v2/canister/{}/read_state", canister_id))
+        .unwrap();
+    let response = send(&client, Method::OPTIONS, url);
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_headers(&response, API_CORS_HEADERS);
+
+    info!(ctx.log, "Requesting unknown routes");
+    for path in &["api/v2/canister/aaaaa-aa/read_state", "api/v1/status", "_/"] {
+        let response = send(&client, Method::GET, base_url.join(path).unwrap());
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "GET /{}", path);
+        assert_headers(&response, SECURITY_HEADERS);
+    }
+
+    info!(ctx.log, "Requesting the HTTP port");
+    let mut http_url = base_url.clone();
+    http_url.set_scheme("http").unwrap();
+    let response = send(&client, Method::GET, http_url);
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    let location = header_value(&response, "location");
+    assert!(
+        location.starts_with("https://"),
+        "Redirected to {}",
+        location
+    );
+
+    info!(
+        ctx.log,
+        "Requesting a certified asset through the HTTP gateway"
+    );
+    let gateway_url = Url::from_str(&format!("{}?canisterId={}", base_url, canister_id)).unwrap();
+    let response = send(&client, Method::GET, gateway_url.clone());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_headers(&response, GATEWAY_CORS_HEADERS);
+    assert_has_header(&response, "x-cache-status");
+    assert_eq!(response.text().unwrap(), CERTIFIED_BODY);
+
+    info!(
+        ctx.log,
+        "Requesting a tampered asset through the HTTP gateway"
+    );
+    let tampered_url =
+        Url::from_str(&format!("{}tampered?canisterId={}", base_url, canister_id)).unwrap();
+    let response = send(&client, Method::GET, tampered_url);
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_headers(&response, GATEWAY_CORS_HEADERS);
+    assert_ne!(
+        response.text().unwrap(),
+        "Hello from a tampered asset!",
+        "The HTTP gateway served a response that does not match its certificate"
+    );
+
+    info!(
+        ctx.log,
+        "Making {} concurrent requests to the HTTP gateway",
+        BURST_THREADS * BURST_REQUESTS_PER_THREAD
+    );
+    let threads: Vec<_> = (0..BURST_THREADS)
+        .map(|_| {
+            let client = client.clone();
+            let url = gateway_url.clone();
+            std::thread::spawn(move || {
+                (0..BURST_REQUESTS_PER_THREAD)
+                    .filter_map(|_| client.get(url.clone()).send().ok())
+                    .filter(|r| r.status() == StatusCode::SERVICE_UNAVAILABLE)
+                    .count()
+            })
+        })
+        .collect();
+    let rejected: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    info!(ctx.log, "{} requests were rate limited", rejected);
+    assert!(rejected > 0, "No request was rate limited");
+}
+
+fn boundary_node_image_from_env() -> BoundaryNodeImage {
+    let url = match env::var("BOUNDARY_NODE_IMAGE_URL") {
+        Ok(url) => Url::from_str(&url).expect("Could not parse $BOUNDARY_NODE_IMAGE_URL"),
+        Err(_) => panic!("Environment variable $BOUNDARY_NODE_IMAGE_URL is not set!"),
+    };
+    let sha256 = match env::var("BOUNDARY_NODE_IMAGE_SHA256") {
+        Ok(sha256) => sha256,
+        Err(_) => panic!("Environment variable $BOUNDARY_NODE_IMAGE_SHA256 is not set!"),
+    };
+    BoundaryNodeImage::new(url, sha256)
+}
+
+fn send(client: &Client, method: Method, url: Url) -> Response {
+    client
+        .request(method.clone(), url.clone())
+        .send()
+        .unwrap_or_else(|err| panic!("{} {} failed: {}", method, url, err))
+}
+
+fn header_value(response: &Response, name: &str) -> String {
+    match response.headers().get(name) {
+        Some(value) => value.to_str().unwrap().to_string(),
+        None => panic!("Response of {} has no header {}", response.url(), name),
+    }
+}
+
+fn assert_has_header(response: &Response, name: &str) {
+    header_value(response, name);
+}
+
+fn assert_headers(response: &Response, expected: &[(&str, &str)]) {
+    for (name, value) in expected {
+        assert_eq!(
+            header_value(response, name),
+            *value,
+            "Header {} of the response of {}",
+            name,
+            response.url()
+        );
+    }
+}
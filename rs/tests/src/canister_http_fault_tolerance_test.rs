@@ -0,0 +1,211 @@
+/* tag::catalog[]
+Title:: Canister http requests with failing adapters
+
+Goal::
+Ensure that canister http requests still reach consensus while the canister
+http adapter is down on some nodes of the subnet, as long as enough replicas
+fetch the response, and that a replica serves canister http requests again
+once its adapter is restarted.
+
+Description::
+We deploy a subnet of four nodes with canister http requests enabled and make
+the universal canister send canister http requests to the URL in
+`$CANISTER_HTTP_TARGET_URL`. The target must reply identically to all
+replicas, since the universal canister does not transform the responses. The
+adapters are stopped and started through SSH. As the adapter is socket
+activated and restarted by systemd, both its socket and its service are
+stopped.
+
+Runbook::
+. Deploy a subnet of four nodes with canister http requests enabled.
+. Install a universal canister and make a canister http request, which costs
+  the canister no cycles on a system subnet.
+. Make several concurrent canister http requests through a node while
+  stopping the adapter on another node.
+. Make several concurrent canister http requests while the adapter is down.
+. Restart the adapter and wait until it is active.
+. Stop the adapter on another node.
+. Make several concurrent canister http requests through the node whose
+  adapter was restarted.
+. Restart all stopped adapters.
+
+Success::
+. All canister http requests are replied with the response of the target,
+  both while an adapter is down and after it was restarted.
+
+end::catalog[] */
+
+use crate::orchestrator::utils::ssh_access::{admin_auth_mean, execute_remote_command};
+use crate::util::*;
+use futures::future::join_all;
+use ic_base_types::HttpMethodType;
+use ic_fondue::ic_instance::{InternetComputer, Subnet};
+use ic_fondue::ic_manager::{IcControl, IcEndpoint, IcHandle};
+use ic_ic00_types::CanisterHttpRequestArgs;
+use ic_protobuf::registry::subnet::v1::SubnetFeatures;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::Cycles;
+use slog::{info, Logger};
+use std::env;
+use std::time::{Duration, Instant};
+
+const SUBNET_SIZE: usize = 4;
+/// The number of nodes whose adapter is down at the same time. The other
+/// nodes must be at least two thirds of the subnet for the requests to reach
+/// consensus.
+const FAULTY_NODES: usize = (SUBNET_SIZE - 1) / 3;
+/// The number of canister http requests that are made at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+/// The systemd units of the adapter, in the order in which they are stopped.
+const ADAPTER_UNITS: &str = "ic-canister-http-adapter.socket ic-canister-http-adapter.service";
+const ADAPTER_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn config() -> InternetComputer {
+    InternetComputer::new().add_subnet(
+        Subnet::new(SubnetType::System)
+            .add_nodes(SUBNET_SIZE)
+            .with_features(SubnetFeatures {
+                http_requests: true,
+                ..SubnetFeatures::default()
+            }),
+    )
+}
+
+pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let target_url = match env::var("CANISTER_HTTP_TARGET_URL") {
+        Ok(url) => url,
+        Err(_) => panic!("Environment variable $CANISTER_HTTP_TARGET_URL is not set!"),
+    };
+    info!(ctx.logger, "CANISTER_HTTP_TARGET_URL: {}", target_url);
+
+    let mut rng = ctx.rng.clone();
+    let endpoints: Vec<_> = handle.as_permutation(&mut rng).collect();
+    for endpoint in endpoints.iter() {
+        block_on(endpoint.assert_ready(ctx));
+    }
+    let (first_faulty_nodes, others) = endpoints.split_at(FAULTY_NODES);
+    let (second_faulty_nodes, healthy_nodes) = others.split_at(FAULTY_NODES);
+
+    let canister_id = block_on(async {
+        let agent = assert_create_agent(healthy_nodes[0].url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        // Canister http requests are free on system subnets, like all other
+        // processing.
+        assert_cycles_cost_within(
+            &uni_can.canister_id(),
+            &agent,
+            CyclesCostEnvelope::new(Cycles::zero(), Cycles::zero()),
+            || assert_http_requests_succeed(&uni_can, &target_url, 1, &ctx.logger),
+        )
+        .await;
+        uni_can.canister_id()
+    });
+
+    info!(
+        ctx.logger,
+        "Stopping {} adapters during canister http requests", FAULTY_NODES
+    );
+    block_on(async {
+        let agent = assert_create_agent(healthy_nodes[0].url.as_str()).await;
+        let uni_can = UniversalCanister::from_canister_id(&agent, canister_id);
+        let faulty_nodes: Vec<IcEndpoint> = first_faulty_nodes
+            .iter()
+            .map(|node| (*node).clone())
+            .collect();
+        let logger = ctx.logger.clone();
+        let stopping = tokio::task::spawn_blocking(move || {
+            stop_adapters(&faulty_nodes.iter().collect::<Vec<_>>(), &logger)
+        });
+        assert_http_requests_succeed(&uni_can, &target_url, CONCURRENT_REQUESTS, &ctx.logger).await;
+        stopping.await.expect("failed to stop the adapters");
+
+        // The first requests may have completed before the adapters stopped.
+        assert_http_requests_succeed(&uni_can, &target_url, CONCURRENT_REQUESTS, &ctx.logger).await;
+    });
+
+    start_adapters(first_faulty_nodes, &ctx.logger);
+    stop_adapters(second_faulty_nodes, &ctx.logger);
+    // The requests only reach consensus if the replicas whose adapter was
+    // restarted fetch their responses again.
+    info!(
+        ctx.logger,
+        "Making canister http requests through a node whose adapter was restarted"
+    );
+    block_on(async {
+        let agent = assert_create_agent(first_faulty_nodes[0].url.as_str()).await;
+        let uni_can = UniversalCanister::from_canister_id(&agent, canister_id);
+        assert_http_requests_succeed(&uni_can, &target_url, CONCURRENT_REQUESTS, &ctx.logger).await;
+    });
+    start_adapters(second_faulty_nodes, &ctx.logger);
+}
+
+/// Makes `count` concurrent canister http requests to `url` and asserts that
+/// they are all replied with a successful response.
+async fn assert_http_requests_succeed(
+    uni_can: &UniversalCanister<'_>,
+    url: &str,
+    count: usize,
+    logger: &Logger,
+) {
+    let requests = (0..count).map(|_| {
+        uni_can.http_request(
+            CanisterHttpRequestArgs {
+                url: url.to_string(),
+                body: None,
+                http_method: HttpMethodType::GET,
+                transform_method_name: None,
+                max_response_bytes: None,
+            },
+            0,
+        )
+    });
+    for (i, result) in join_all(requests).await.into_iter().enumerate() {
+        let response =
+            result.unwrap_or_else(|err| panic!("Canister http request {} failed: {:?}", i, err));
+        assert!(
+            (200..300).contains(&response.status),
+            "Canister http request {} was replied with status {}",
+            i,
+            response.status
+        );
+    }
+    info!(logger, "{} canister http requests succeeded", count);
+}
+
+fn stop_adapters(nodes: &[&IcEndpoint], logger: &Logger) {
+    for node in nodes {
+        info!(logger, "Stopping the adapter of node {}", node.node_id);
+        run_systemctl(node, &format!("stop {}", ADAPTER_UNITS));
+        assert!(!adapter_is_active(node));
+    }
+}
+
+fn start_adapters(nodes: &[&IcEndpoint], logger: &Logger) {
+    for node in nodes {
+        info!(logger, "Starting the adapter of node {}", node.node_id);
+        run_systemctl(node, &format!("start {}", ADAPTER_UNITS));
+        let deadline = Instant::now() + ADAPTER_START_TIMEOUT;
+        while !adapter_is_active(node) {
+            if Instant::now() > deadline {
+                panic!("The adapter of node {} did not start", node.node_id);
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+fn adapter_is_active(node: &IcEndpoint) -> bool {
+    run_systemctl(node, "is-active ic-canister-http-adapter.service").trim() == "active"
+}
+
+/// Runs `systemctl` with `args` on `node` and returns its output.
+fn run_systemctl(node: &IcEndpoint, args: &str) -> String {
+    let ip = node.ip_address().expect("node without IP address");
+    let command = format!("sudo systemctl {}", args);
+    execute_remote_command(&ip, "admin", &admin_auth_mean(node), &command).unwrap_or_else(|e| {
+        panic!(
+            "failed to run `{}` on node {}: {}",
+            command, node.node_id, e
+        )
+    })
+}
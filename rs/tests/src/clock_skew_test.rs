@@ -0,0 +1,171 @@
+/* tag::catalog[]
+Title:: Replicas with skewed clocks
+
+Goal::
+Ensure that a replica whose clock is off rejects ingress messages whose expiry
+is out of range for its clock, and that neither the progress of consensus nor
+the certified time of the subnet depend on the clock of a single replica.
+
+Description::
+We deploy a subnet of four nodes and skew the clock of one of them relative to
+the clock of the test driver, first ahead and then behind by more than the
+maximum time to live of ingress messages. The agents of the test driver set
+the expiry of ingress messages according to the clock of the test driver, so
+the replica with the skewed clock must reject them as expired or as expiring
+too far in the future. The block proposals of the replica with the skewed
+clock carry a skewed time, which must neither stall the subnet nor drag its
+certified time along.
+
+Runbook::
+. Deploy a subnet of four nodes and install a universal canister.
+. Skew the clock of one node ahead by more than the maximum ingress TTL.
+. Submit an update call through the skewed node and assert it is rejected.
+. Make an update call through another node and assert it is executed.
+. Read the certified time through another node and assert it follows the
+  clock of the test driver.
+. Repeat the previous steps with the clock of the node skewed behind.
+. Reset the clock of the node and assert that it accepts update calls again
+  and that it certifies the same time as the other nodes.
+
+Success::
+. The node with the skewed clock rejects update calls with 400 Bad Request.
+. The subnet executes update calls and certifies a time close to the time of
+  the test driver while the clock of the node is skewed.
+
+end::catalog[] */
+
+use crate::api::node_clock::{ClockSkew, HasClock};
+use crate::api::system_test_context::*;
+use crate::util::*;
+use ic_agent::export::Principal;
+use ic_fondue::ic_instance::{InternetComputer, Subnet};
+use ic_fondue::ic_manager::IcHandle;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::CanisterId;
+use ic_universal_canister::wasm;
+use slog::info;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SUBNET_SIZE: usize = 4;
+/// Larger than the maximum time to live of ingress messages plus the drift
+/// that replicas permit, so that any expiry set by the test driver is out of
+/// range for the skewed clock.
+const SKEW: Duration = Duration::from_secs(15 * 60);
+/// The maximum difference between the certified time and the time of the
+/// test driver. The certified time lags behind by the time it takes to
+/// finalize and certify a block.
+const CERTIFIED_TIME_TOLERANCE: Duration = Duration::from_secs(30);
+const CLOCK_RESET_TOLERANCE: Duration = Duration::from_secs(2);
+
+pub fn config() -> InternetComputer {
+    InternetComputer::new().add_subnet(Subnet::new(SubnetType::System).add_nodes(SUBNET_SIZE))
+}
+
+pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let ctx = SystemTestContext::from_ic_handle(handle, ctx);
+    let nodes: Vec<_> = ctx
+        .topology_snapshot()
+        .subnets()
+        .flat_map(|s| s.nodes())
+        .collect();
+    nodes
+        .iter()
+        .try_for_each(|n| n.await_status_is_healthy())
+        .unwrap();
+    let (skewed_node, other_node) = (&nodes[0], &nodes[1]);
+    let canister_id = other_node.with_default_agent(|agent| async move {
+        UniversalCanister::new(&agent).await.canister_id()
+    });
+
+    for skew in &[ClockSkew::Ahead(SKEW), ClockSkew::Behind(SKEW)] {
+        info!(
+            ctx.log,
+            "Skewing the clock of node {} {}",
+            skewed_node.get_ip_addr(),
+            skew
+        );
+        skewed_node.skew_clock(*skew).unwrap();
+
+        info!(ctx.log, "Submitting an update call through the skewed node");
+        skewed_node.with_default_agent(move |agent| async move {
+            assert_http_submit_fails(
+                agent
+                    .update(&canister_id, "update")
+                    .with_arg(wasm().reply().build())
+                    .call()
+                    .await,
+                reqwest::StatusCode::BAD_REQUEST,
+            );
+        });
+
+        info!(ctx.log, "Making an update call through another node");
+        assert_update_succeeds(other_node, canister_id);
+        assert_certified_time_follows_test_driver(other_node, canister_id);
+    }
+
+    info!(ctx.log, "Resetting the clock of the skewed node");
+    skewed_node.reset_clock().unwrap();
+    let skew = skewed_node.clock_skew().unwrap();
+    assert!(
+        skew.magnitude() <= CLOCK_RESET_TOLERANCE,
+        "The clock of the node is {} after resetting it",
+        skew
+    );
+    assert_update_succeeds(skewed_node, canister_id);
+    assert_certified_time_follows_test_driver(skewed_node, canister_id);
+}
+
+fn assert_update_succeeds(node: &IcNodeSnapshot, canister_id: Principal) {
+    node.with_default_agent(move |agent| async move {
+        let reply = UniversalCanister::from_canister_id(&agent, canister_id)
+            .update(wasm().reply_data(b"ok"))
+            .await
+            .expect("The update call failed");
+        assert_eq!(reply, b"ok".to_vec());
+    });
+}
+
+/// Asserts that the time that `node` certifies for the subnet of
+/// `canister_id` is close to the time of the clock of the test driver.
+fn assert_certified_time_follows_test_driver(node: &IcNodeSnapshot, canister_id: Principal) {
+    let url = node.get_public_url();
+    let certified_time = block_on(async move {
+        let reader = StateTreeReader::new(url.as_str())
+            .await
+            .unwrap_or_else(|err| panic!("Could not create a state reader: {}", err));
+        let canister_id = CanisterId::try_from(canister_id.as_slice()).unwrap();
+        let time = reader
+            .read_leaf(&canister_id, state_tree_paths::time())
+            .await
+            .unwrap_or_else(|err| panic!("Could not read the state of {}: {}", url, err))
+            .unwrap_or_else(|| panic!("{} certified no time", url));
+        decode_leb128_u64(&time)
+    });
+    let driver_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    let difference = Duration::from_nanos(if certified_time > driver_time {
+        certified_time - driver_time
+    } else {
+        driver_time - certified_time
+    });
+    assert!(
+        difference <= CERTIFIED_TIME_TOLERANCE,
+        "The certified time differs from the time of the test driver by {:?}",
+        difference
+    );
+}
+
+/// Decodes the unsigned LEB128 encoding of the time in the state tree.
+fn decode_leb128_u64(bytes: &[u8]) -> u64 {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+    panic!("Invalid LEB128 encoding {:?}", bytes)
+}
@@ -4,7 +4,7 @@ use crate::nns::{
 };
 use crate::util::{
     assert_all_ready, get_random_application_node_endpoint, get_random_nns_node_endpoint,
-    runtime_from_url,
+    root_public_key, runtime_from_url, verify_certified_tree,
 };
 
 use canister_test::{Canister, Project, Wasm};
@@ -16,10 +16,7 @@ use cycles_minting_canister::{
 use dfn_candid::{candid_one, CandidOne};
 use dfn_protobuf::{ProtoBuf, ToProto};
 use ic_canister_client::{Agent, HttpClient, Sender};
-use ic_certified_vars::verify_certificate;
 use ic_config::subnet_config::CyclesAccountManagerConfig;
-use ic_crypto::threshold_sig_public_key_from_der;
-use ic_crypto_tree_hash::MixedHashTree;
 use ic_fondue::{ic_instance::InternetComputer, ic_manager::IcHandle};
 use ic_nns_common::types::{NeuronId, UpdateIcpXdrConversionRatePayload};
 use ic_nns_constants::{
@@ -154,24 +151,13 @@ pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
             xdr_permyriad_per_icp
         );
 
-        let pk_bytes = handle
-            .ic_prep_working_dir
-            .as_ref()
-            .unwrap()
-            .root_public_key()
-            .expect("failed to read threshold sig PK bytes");
-        let pk = threshold_sig_public_key_from_der(&pk_bytes[..])
-            .expect("failed to decode threshold sig PK");
-
-        let mixed_hash_tree: MixedHashTree =
-            serde_cbor::from_slice(&conversion_rate_response.hash_tree).unwrap();
         // Verify the authenticity of the root hash stored by the canister in the
         // certified_data field
-        verify_certificate(
+        verify_certified_tree(
             &conversion_rate_response.certificate[..],
+            &conversion_rate_response.hash_tree[..],
             &CYCLES_MINTING_CANISTER_ID,
-            &pk,
-            mixed_hash_tree.digest().as_bytes(),
+            &root_public_key(&handle),
         )
         .unwrap();
 
@@ -25,20 +25,8 @@ pub fn ecdsa_signatures_disabled_by_default(handle: IcHandle, ctx: &ic_fondue::p
         endpoint.assert_ready(ctx).await;
         let agent = assert_create_agent(endpoint.url.as_str()).await;
 
-        let request = SignWithECDSAArgs {
-            message_hash: [0u8; 32].to_vec(),
-            derivation_path: Vec::new(),
-            key_id: "secp256k1".to_string(),
-        };
-
         let uni_can = UniversalCanister::new(&agent).await;
-        let res = uni_can
-            .forward_to(
-                &Principal::management_canister(),
-                "sign_with_ecdsa",
-                Encode!(&request).unwrap(),
-            )
-            .await;
+        let res = uni_can.sign_with_ecdsa("secp256k1", [0u8; 32], 0).await;
 
         assert_reject(res, RejectCode::CanisterReject);
     });
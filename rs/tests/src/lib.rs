@@ -1,6 +1,9 @@
 pub mod api;
 pub mod basic_health_test;
+pub mod boundary_node_http_gateway_test;
+pub mod canister_http_fault_tolerance_test;
 pub mod cli;
+pub mod clock_skew_test;
 pub mod consensus;
 pub mod cow_safety_test;
 pub mod create_subnet;
@@ -21,7 +24,9 @@ pub mod node_graceful_leaving_test;
 pub mod node_removal_from_registry_test;
 pub mod node_restart_test;
 pub mod orchestrator;
+pub mod prometheus_alerts;
 pub mod registry_authentication_test;
+pub mod registry_invariants;
 pub mod rejoin_test;
 pub mod replica_determinism_test;
 pub mod request_auth_malicious_replica_test;
@@ -30,6 +35,8 @@ pub mod rosetta_test;
 pub mod security;
 pub mod spec_compliance;
 pub mod tecdsa_complaint_test;
+pub mod tecdsa_key_rotation_test;
+pub mod tecdsa_replica_restart_test;
 pub mod tecdsa_signature_test;
 pub mod token_balance_test;
 pub mod transaction_ledger_correctness_test;
@@ -1,6 +1,8 @@
 pub mod cup_fetching_across_upgrades;
+pub mod mixed_version_subnet_test;
 pub mod node_reassignment_test;
 pub mod ssh_access_to_nodes;
+pub mod subnet_halt_and_resume;
 pub mod unassigned_node_upgrade_test;
 pub mod upgrade_reject;
 pub mod utils;
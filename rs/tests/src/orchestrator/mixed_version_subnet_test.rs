@@ -0,0 +1,140 @@
+/* tag::catalog[]
+
+Title:: Subnet with nodes of mixed replica versions
+
+Goal::
+Ensure that a subnet keeps making progress while a part of its nodes still
+runs the previous replica version, that the held back nodes stall at the CUP of
+the upgrade meanwhile, and that they catch up with the nodes running the new
+version afterwards.
+
+Description::
+We deploy an IC with a subnet running replicas of the current branch version.
+We upgrade the subnet to the master branch version, while holding back a
+quarter of the nodes on the current branch version. Once the upgraded nodes run
+the master branch version, we check that the held back nodes stall at the CUP of
+the upgrade while the subnet makes progress. Finally we release the held back
+nodes and check that they upgrade and serve the state of the subnet.
+
+Runbook::
+. Deploy an IC with a subnet.
+. Hold back a quarter of the nodes of the subnet.
+. Bless the master branch version and upgrade the subnet to it.
+. Wait for the other nodes to run the master version.
+. Wait for the certified height of the held back nodes to stop increasing.
+. Store and read a message on an upgraded node.
+. Check that the held back nodes did not certify a higher height.
+. Release the held back nodes.
+. Check that they run the master version and read the message.
+
+Success::
+. The subnet makes progress with mixed versions while the held back nodes are
+  stalled, and all nodes eventually run the master version.
+
+end::catalog[] */
+
+use crate::nns::NnsExt;
+use crate::orchestrator::node_reassignment_test::{can_read_msg, store_message};
+use crate::orchestrator::utils::mixed_version::upgrade_fraction_of_subnet;
+use crate::orchestrator::utils::upgrade::{get_assigned_replica_version, UpdateImageType};
+use crate::util::{
+    block_on, get_other_subnet_nodes, get_random_application_node_endpoint,
+    get_random_nns_node_endpoint,
+};
+use ic_fondue::pot::Context;
+use ic_fondue::{
+    ic_instance::{InternetComputer, Subnet},
+    ic_manager::IcHandle,
+};
+use ic_registry_subnet_type::SubnetType;
+use ic_types::Height;
+use slog::{info, warn};
+use std::env;
+
+const DKG_INTERVAL: u64 = 14;
+const SUBNET_SIZE: usize = 4;
+/// The fraction of nodes upgraded before the others. The held back nodes must
+/// be less than a third of the subnet for it to make progress.
+const UPGRADED_FRACTION: f64 = 0.75;
+
+pub fn config() -> InternetComputer {
+    InternetComputer::new()
+        .add_fast_single_node_subnet(SubnetType::System)
+        .add_subnet(
+            Subnet::new(SubnetType::Application)
+                .with_dkg_interval_length(Height::from(DKG_INTERVAL))
+                .add_nodes(SUBNET_SIZE),
+        )
+}
+
+pub fn test(handle: IcHandle, ctx: &Context) {
+    let mut rng = ctx.rng.clone();
+
+    let master_version = match env::var("MASTER_GIT_REVISION") {
+        Ok(ver) => ver,
+        Err(_) => panic!("Environment variable $MASTER_GIT_REVISION is not set!"),
+    };
+    info!(ctx.logger, "MASTER_GIT_REVISION: {}", master_version);
+
+    ctx.install_nns_canisters(&handle, true);
+
+    let nns_node = get_random_nns_node_endpoint(&handle, &mut rng);
+    block_on(nns_node.assert_ready(ctx));
+    let app_node = get_random_application_node_endpoint(&handle, &mut rng);
+    block_on(app_node.assert_ready(ctx));
+
+    let original_version = get_assigned_replica_version(app_node).unwrap();
+    info!(ctx.logger, "Original version: {}", original_version);
+    if original_version == master_version {
+        warn!(
+            ctx.logger,
+            "Mixing a version with itself is useless: original_version == master_version!"
+        );
+        return;
+    }
+
+    let mut subnet_nodes = get_other_subnet_nodes(&handle, app_node);
+    subnet_nodes.push(app_node);
+    let mixed_subnet = upgrade_fraction_of_subnet(
+        nns_node,
+        &subnet_nodes,
+        UPGRADED_FRACTION,
+        &master_version,
+        UpdateImageType::Image,
+        &ctx.logger,
+    );
+
+    let stalled_heights = mixed_subnet.wait_for_held_back_nodes_to_stall(&ctx.logger);
+    info!(
+        ctx.logger,
+        "Held back nodes stalled at certified heights {:?}", stalled_heights
+    );
+
+    let upgraded_node = mixed_subnet.target_version_nodes[0];
+    let msg = "message stored on a subnet of mixed versions";
+    info!(ctx.logger, "Store message '{}'", msg);
+    let app_can_id = block_on(store_message(&upgraded_node.url, msg));
+    assert!(block_on(can_read_msg(
+        &ctx.logger,
+        &upgraded_node.url,
+        app_can_id,
+        msg
+    )));
+
+    mixed_subnet.assert_held_back_nodes_stalled_at(&stalled_heights);
+
+    let held_back_nodes = mixed_subnet.initial_version_nodes.clone();
+    mixed_subnet.release_held_back_nodes(&ctx.logger);
+    for node in held_back_nodes {
+        info!(
+            ctx.logger,
+            "Read message '{}' on node {}", msg, node.node_id
+        );
+        assert!(block_on(can_read_msg(
+            &ctx.logger,
+            &node.url,
+            app_can_id,
+            msg
+        )));
+    }
+}
@@ -0,0 +1,127 @@
+/* tag::catalog[]
+Title:: Subnet halt and resume by proposal
+
+Goal:: Ensure that an application subnet can be halted and resumed with
+proposals to update its subnet record, as done by operators when recovering
+a subnet, and that it resumes from the state at which it halted.
+
+Runbook::
+. Set up an NNS subnet and an application subnet with four nodes
+. Install a universal canister on the application subnet and write to its
+  stable memory
+. Halt the application subnet with a proposal
+. Verify that update calls are no longer executed
+. Verify that all nodes of the halted subnet certify the same state, read
+  with verified `read_state` requests, and that it does not change
+. Verify that all nodes serve the data written before the halt
+. Resume the application subnet with a proposal
+. Verify that update calls are executed again, and that all nodes serve the
+  data written before the halt and after the resumption
+
+Success:: The halted subnet executes no update calls and all of its nodes
+agree on its certified state. Once resumed, the subnet makes progress and
+no data is lost or diverges between nodes.
+
+Coverage::
+. The `is_halted` field of the subnet record halts and resumes consensus
+. Halting a subnet does not lead to divergence of the state of its nodes
+
+end::catalog[] */
+
+use crate::{
+    nns::NnsExt,
+    orchestrator::utils::subnet_halt::*,
+    util::{
+        assert_create_agent, block_on, get_other_subnet_nodes,
+        get_random_application_node_endpoint, get_random_nns_node_endpoint, to_principal_id,
+        UniversalCanister,
+    },
+};
+
+use ic_fondue::{
+    ic_instance::{InternetComputer, Subnet},
+    ic_manager::{IcEndpoint, IcHandle},
+};
+use ic_registry_subnet_type::SubnetType;
+use ic_types::CanisterId;
+use slog::info;
+use std::time::{Duration, Instant};
+
+const BEFORE_HALT: (u32, &[u8]) = (0, b"written before the halt");
+const DURING_HALT: (u32, &[u8]) = (64, b"written during the halt");
+const AFTER_RESUME: (u32, &[u8]) = (128, b"written after resumption");
+
+/// The time the nodes of the subnet get to serve the latest state after an
+/// update call completed on one of them.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn config() -> InternetComputer {
+    InternetComputer::new()
+        .add_fast_single_node_subnet(SubnetType::System)
+        .add_subnet(Subnet::fast(SubnetType::Application, 4))
+}
+
+pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let mut rng = ctx.rng.clone();
+
+    let nns_endpoint = get_random_nns_node_endpoint(&handle, &mut rng);
+    block_on(nns_endpoint.assert_ready(ctx));
+    info!(ctx.logger, "Installing NNS canisters on the root subnet...");
+    ctx.install_nns_canisters(&handle, true);
+
+    let app_endpoint = get_random_application_node_endpoint(&handle, &mut rng);
+    block_on(app_endpoint.assert_ready(ctx));
+    let app_subnet_id = app_endpoint.subnet_id().unwrap();
+    let mut app_endpoints = get_other_subnet_nodes(&handle, app_endpoint);
+    app_endpoints.push(app_endpoint);
+
+    block_on(async {
+        let agent = assert_create_agent(app_endpoint.url.as_str()).await;
+        let canister = UniversalCanister::new(&agent).await;
+        let canister_id = CanisterId::new(to_principal_id(&canister.canister_id())).unwrap();
+        canister.store_to_stable(BEFORE_HALT.0, BEFORE_HALT.1).await;
+
+        info!(ctx.logger, "Halting subnet {}...", app_subnet_id);
+        set_subnet_halted(nns_endpoint, app_subnet_id, true).await;
+        wait_until_ingress_is_not_executed(&canister, DURING_HALT.0, DURING_HALT.1).await;
+
+        info!(ctx.logger, "Checking the state of the halted subnet...");
+        assert_halted_nodes_agree_on_certified_state(&app_endpoints, &canister_id).await;
+        assert_all_nodes_read(&app_endpoints, &canister, BEFORE_HALT).await;
+
+        info!(ctx.logger, "Resuming subnet {}...", app_subnet_id);
+        set_subnet_halted(nns_endpoint, app_subnet_id, false).await;
+        wait_until_ingress_is_executed(&canister, AFTER_RESUME.0, AFTER_RESUME.1).await;
+
+        info!(ctx.logger, "Checking the state of the resumed subnet...");
+        assert_all_nodes_read(&app_endpoints, &canister, BEFORE_HALT).await;
+        assert_all_nodes_read(&app_endpoints, &canister, AFTER_RESUME).await;
+    });
+}
+
+/// Asserts that each of `endpoints` serves `message` at `offset` of the stable
+/// memory of `canister`, giving the nodes [READ_TIMEOUT] to catch up.
+async fn assert_all_nodes_read(
+    endpoints: &[&IcEndpoint],
+    canister: &UniversalCanister<'_>,
+    (offset, message): (u32, &[u8]),
+) {
+    for endpoint in endpoints {
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let canister = UniversalCanister::from_canister_id(&agent, canister.canister_id());
+        let start = Instant::now();
+        loop {
+            let read = canister.read_stable(offset, message.len() as u32).await;
+            if matches!(&read, Ok(bytes) if bytes.as_slice() == message) {
+                break;
+            }
+            if start.elapsed() > READ_TIMEOUT {
+                panic!(
+                    "{} read {:?} instead of {:?} at offset {}",
+                    endpoint.url, read, message, offset
+                );
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+}
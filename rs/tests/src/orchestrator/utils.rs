@@ -1,2 +1,4 @@
+pub mod mixed_version;
 pub mod ssh_access;
+pub mod subnet_halt;
 pub mod upgrade;
@@ -0,0 +1,209 @@
+//! Utilities to bring a subnet into a state where its nodes run different
+//! replica versions.
+//!
+//! The orchestrators of a subnet upgrade their nodes to the replica version of
+//! the subnet record, so a subnet is mixed only while an upgrade is rolled
+//! out. The utilities below drive such a rollout node by node: the subnet is
+//! upgraded to a target version, while a part of its nodes is held back on the
+//! initial version by blocking the download of the release package on them.
+//! The held back nodes stall at the CUP of the upgrade, since they can not
+//! run the version that the subnet continues with. Releasing the held back
+//! nodes completes the rollout.
+use crate::orchestrator::utils::ssh_access::{admin_auth_mean, execute_remote_command};
+use crate::orchestrator::utils::upgrade::{
+    assert_assigned_replica_version, bless_replica_version, get_update_image_url,
+    update_subnet_replica_version, UpdateImageType,
+};
+use crate::util::block_on;
+use ic_fondue::ic_manager::{IcControl, IcEndpoint};
+use ic_types::{Height, ReplicaVersion};
+use slog::{info, Logger};
+use std::convert::TryFrom;
+use std::time::Duration;
+use url::Url;
+
+/// The comment of the firewall rules holding back the upgrade of a node.
+const HOLD_BACK_RULE_COMMENT: &str = "hold-back-upgrade";
+/// The time during which the certified height of a stalled node must not
+/// change. A node that makes progress certifies several heights in this time.
+const STALL_INTERVAL: Duration = Duration::from_secs(30);
+/// The number of times the certified height of a node is checked to have
+/// stopped changing before giving up.
+const STALL_ATTEMPTS: usize = 20;
+
+/// A subnet that was partially upgraded with [`upgrade_fraction_of_subnet`].
+pub(crate) struct MixedVersionSubnet<'a> {
+    /// The nodes held back on the initial replica version.
+    pub initial_version_nodes: Vec<&'a IcEndpoint>,
+    /// The nodes running the target replica version.
+    pub target_version_nodes: Vec<&'a IcEndpoint>,
+    /// The replica version the subnet is upgraded to.
+    pub target_version: ReplicaVersion,
+    release_package_host: String,
+}
+
+impl<'a> MixedVersionSubnet<'a> {
+    /// Waits until the held back nodes stall at the CUP of the upgrade and
+    /// returns the certified heights at which they stalled, in the order of
+    /// [`initial_version_nodes`](Self::initial_version_nodes).
+    pub(crate) fn wait_for_held_back_nodes_to_stall(&self, logger: &Logger) -> Vec<Height> {
+        self.initial_version_nodes
+            .iter()
+            .map(|node| {
+                for attempt in 1..=STALL_ATTEMPTS {
+                    let height = certified_height(node);
+                    std::thread::sleep(STALL_INTERVAL);
+                    let later_height = certified_height(node);
+                    info!(
+                        logger,
+                        "Try: {}. node {} certified heights {} and {}",
+                        attempt,
+                        node.node_id,
+                        height,
+                        later_height
+                    );
+                    if height == later_height {
+                        return height;
+                    }
+                }
+                panic!("held back node {} did not stall", node.node_id)
+            })
+            .collect()
+    }
+
+    /// Asserts that the held back nodes are still stalled at the certified
+    /// `heights` returned by
+    /// [`wait_for_held_back_nodes_to_stall`](Self::wait_for_held_back_nodes_to_stall).
+    pub(crate) fn assert_held_back_nodes_stalled_at(&self, heights: &[Height]) {
+        for (node, height) in self.initial_version_nodes.iter().zip(heights) {
+            assert_eq!(
+                certified_height(node),
+                *height,
+                "held back node {} made progress",
+                node.node_id
+            );
+        }
+    }
+
+    /// Lets the held back nodes upgrade to the target version and waits until
+    /// they run it.
+    pub(crate) fn release_held_back_nodes(self, logger: &Logger) {
+        for node in self.initial_version_nodes.iter() {
+            info!(logger, "Releasing node {}", node.node_id);
+            set_upgrades_held_back(node, &self.release_package_host, false);
+        }
+        for node in self.initial_version_nodes.iter() {
+            assert_assigned_replica_version(node, self.target_version.as_ref(), logger);
+        }
+    }
+}
+
+/// Splits `nodes` into the nodes to keep on their version and the
+/// `fraction` of the nodes to upgrade, rounded to the closest number of nodes.
+///
+/// # Panics
+///
+/// This function panics if `fraction` is not in `[0, 1]`.
+pub(crate) fn split_nodes<'a>(
+    nodes: &[&'a IcEndpoint],
+    fraction: f64,
+) -> (Vec<&'a IcEndpoint>, Vec<&'a IcEndpoint>) {
+    assert!(
+        (0.0..=1.0).contains(&fraction),
+        "the fraction of nodes to upgrade must be in [0, 1], got {}",
+        fraction
+    );
+    let upgraded = (fraction * nodes.len() as f64).round() as usize;
+    let (kept, upgraded) = nodes.split_at(nodes.len() - upgraded);
+    (kept.to_vec(), upgraded.to_vec())
+}
+
+/// Upgrades the `fraction` of `nodes` to `target_version`, while the other
+/// nodes keep running their current replica version. All `nodes` must belong
+/// to the same subnet and must be all of its nodes.
+///
+/// The subnet record is updated to `target_version`, so the held back nodes
+/// stall at the CUP of the upgrade, see
+/// [`MixedVersionSubnet::wait_for_held_back_nodes_to_stall`]. The subnet hence
+/// only makes progress as long as the upgraded nodes can finalize blocks
+/// without the held back ones.
+pub(crate) fn upgrade_fraction_of_subnet<'a>(
+    nns_node: &IcEndpoint,
+    nodes: &[&'a IcEndpoint],
+    fraction: f64,
+    target_version: &str,
+    image_type: UpdateImageType,
+    logger: &Logger,
+) -> MixedVersionSubnet<'a> {
+    let subnet_id = nodes
+        .first()
+        .and_then(|node| node.subnet_id())
+        .expect("the nodes to upgrade must belong to a subnet");
+    let (initial_version_nodes, target_version_nodes) = split_nodes(nodes, fraction);
+    let release_package_host = Url::parse(&get_update_image_url(image_type, target_version))
+        .expect("invalid update image URL")
+        .host_str()
+        .expect("update image URL without host")
+        .to_string();
+
+    // The download must be blocked before the upgrade is proposed, as the
+    // orchestrators download the release package as soon as the upgrade is
+    // scheduled.
+    for node in initial_version_nodes.iter() {
+        info!(logger, "Holding back node {}", node.node_id);
+        set_upgrades_held_back(node, &release_package_host, true);
+    }
+
+    block_on(bless_replica_version(
+        nns_node,
+        target_version,
+        image_type,
+        logger,
+    ));
+    let target_version = match image_type {
+        UpdateImageType::ImageTest => {
+            ReplicaVersion::try_from(format!("{}-test", target_version)).unwrap()
+        }
+        _ => ReplicaVersion::try_from(target_version).unwrap(),
+    };
+    block_on(update_subnet_replica_version(
+        nns_node,
+        &target_version,
+        subnet_id,
+    ));
+    for node in target_version_nodes.iter() {
+        assert_assigned_replica_version(node, target_version.as_ref(), logger);
+    }
+
+    MixedVersionSubnet {
+        initial_version_nodes,
+        target_version_nodes,
+        target_version,
+        release_package_host,
+    }
+}
+
+/// Returns the height of the latest certified state of `node`.
+fn certified_height(node: &IcEndpoint) -> Height {
+    block_on(node.status())
+        .ok()
+        .and_then(|status| status.certified_height)
+        .unwrap_or_else(|| panic!("node {} reports no certified height", node.node_id))
+}
+
+/// Adds or removes the firewall rule on `node` that rejects connections to
+/// `host`, from which release packages are downloaded.
+fn set_upgrades_held_back(node: &IcEndpoint, host: &str, held_back: bool) {
+    let action = if held_back { "-I" } else { "-D" };
+    let command = format!(
+        "sudo ip6tables {} OUTPUT -d {} -j REJECT -m comment --comment {}",
+        action, host, HOLD_BACK_RULE_COMMENT
+    );
+    let ip = node.ip_address().expect("node without IP address");
+    execute_remote_command(&ip, "admin", &admin_auth_mean(node), &command).unwrap_or_else(|e| {
+        panic!(
+            "failed to update the firewall rules of node {}: {}",
+            node.node_id, e
+        )
+    });
+}
@@ -8,6 +8,7 @@ use crate::{
 };
 
 use ic_fondue::ic_manager::IcEndpoint;
+use ic_fondue::prod_tests::cli::AuthorizedSshAccount;
 use ic_nns_governance::pb::v1::NnsFunction;
 use ic_types::{time::current_time, SubnetId};
 use openssh_keys::PublicKey;
@@ -104,6 +105,69 @@ pub(crate) fn read_remote_file(
     Ok(String::from_utf8_lossy(&buffer).to_string())
 }
 
+/// Executes `command` on the node at `ip` and returns its standard output.
+pub(crate) fn execute_remote_command(
+    ip: &IpAddr,
+    username: &str,
+    mean: &AuthMean,
+    command: &str,
+) -> Result<String, String> {
+    run_remote_command(ip, username, mean, command).map(|(output, _)| output)
+}
+
+/// Like [`execute_remote_command`], but fails if the command exits with a
+/// non-zero status.
+pub(crate) fn execute_remote_command_checked(
+    ip: &IpAddr,
+    username: &str,
+    mean: &AuthMean,
+    command: &str,
+) -> Result<String, String> {
+    match run_remote_command(ip, username, mean, command)? {
+        (output, 0) => Ok(output),
+        (output, status) => Err(format!("exited with status {}: {}", status, output)),
+    }
+}
+
+/// Executes `command` and returns its standard output and exit status.
+fn run_remote_command(
+    ip: &IpAddr,
+    username: &str,
+    mean: &AuthMean,
+    command: &str,
+) -> Result<(String, i32), String> {
+    let mut sess = SshSession::new();
+    sess.login(ip, username, mean)?;
+    let mut channel = sess
+        .session
+        .channel_session()
+        .map_err(|err| err.to_string())?;
+    channel.exec(command).map_err(|err| err.to_string())?;
+    let mut output = String::new();
+    channel
+        .read_to_string(&mut output)
+        .map_err(|err| err.to_string())?;
+    channel.wait_close().map_err(|err| err.to_string())?;
+    let status = channel.exit_status().map_err(|err| err.to_string())?;
+    Ok((output, status))
+}
+
+/// Returns the means to authenticate as admin on the node of `endpoint`.
+pub(crate) fn admin_auth_mean(endpoint: &IcEndpoint) -> AuthMean {
+    admin_auth_mean_of(&endpoint.ssh_key_pairs).expect("No SSH key pair for the admin account")
+}
+
+/// Returns the means to authenticate as admin with the key pair of the
+/// `admin` account among `ssh_key_pairs`, if there is one.
+pub(crate) fn admin_auth_mean_of(ssh_key_pairs: &[AuthorizedSshAccount]) -> Option<AuthMean> {
+    let account = ssh_key_pairs
+        .iter()
+        .find(|account| account.name == "admin")?;
+    Some(AuthMean::PrivateKey(
+        String::from_utf8_lossy(&account.private_key).to_string(),
+    ))
+}
+
 pub(crate) fn assert_authentication_works(ip: &IpAddr, username: &str, mean: &AuthMean) {
     SshSession::new().login(ip, username, mean).unwrap();
 }
@@ -172,6 +236,7 @@ pub(crate) fn get_updatesubnetpayload_with_keys(
         max_instructions_per_install_code: None,
         features: None,
         ecdsa_config: None,
+        canister_http_config: None,
         max_number_of_canisters: None,
         ssh_readonly_access: readonly_keys,
         ssh_backup_access: backup_keys,
@@ -0,0 +1,180 @@
+/// Subnet Halting Utilities
+use crate::{
+    orchestrator::utils::ssh_access::update_subnet_record,
+    util::{create_delay, state_tree_paths, StateTreeReader, UniversalCanister},
+};
+
+use ic_agent::AgentError;
+use ic_fondue::ic_manager::IcEndpoint;
+use ic_types::{CanisterId, SubnetId};
+use registry_canister::mutations::do_update_subnet::UpdateSubnetPayload;
+use std::time::{Duration, Instant};
+
+/// The time a halted subnet gets to stop executing ingress messages, or a
+/// resumed subnet to start again, after the proposal is executed.
+const HALT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The time an update call gets to complete before it is considered stuck.
+const UPDATE_TIMEOUT_SECS: u64 = 30;
+
+/// The payload of a proposal that only sets `is_halted` of `subnet_id`.
+pub(crate) fn get_halt_subnet_payload(subnet_id: SubnetId, is_halted: bool) -> UpdateSubnetPayload {
+    UpdateSubnetPayload {
+        subnet_id,
+        max_ingress_bytes_per_message: None,
+        max_ingress_messages_per_block: None,
+        max_block_payload_size: None,
+        unit_delay_millis: None,
+        initial_notary_delay_millis: None,
+        dkg_interval_length: None,
+        dkg_dealings_per_block: None,
+        max_artifact_streams_per_peer: None,
+        max_chunk_wait_ms: None,
+        max_duplicity: None,
+        max_chunk_size: None,
+        receive_check_cache_size: None,
+        pfn_evaluation_period_ms: None,
+        registry_poll_period_ms: None,
+        retransmission_request_ms: None,
+        advert_best_effort_percentage: None,
+        set_gossip_config_to_default: false,
+        start_as_nns: None,
+        subnet_type: None,
+        is_halted: Some(is_halted),
+        max_instructions_per_message: None,
+        max_instructions_per_round: None,
+        max_instructions_per_install_code: None,
+        features: None,
+        ecdsa_config: None,
+        canister_http_config: None,
+        max_number_of_canisters: None,
+        ssh_readonly_access: None,
+        ssh_backup_access: None,
+    }
+}
+
+/// Halts or resumes `subnet_id` with a proposal submitted to the NNS at
+/// `nns_endpoint`.
+pub(crate) async fn set_subnet_halted(
+    nns_endpoint: &IcEndpoint,
+    subnet_id: SubnetId,
+    is_halted: bool,
+) {
+    update_subnet_record(nns_endpoint, get_halt_subnet_payload(subnet_id, is_halted)).await;
+}
+
+/// Writes `message` to the stable memory of `canister` at `offset` with an
+/// update call that gives up after [UPDATE_TIMEOUT_SECS].
+async fn try_store_with_timeout(
+    canister: &UniversalCanister<'_>,
+    offset: u32,
+    message: &[u8],
+) -> Result<(), AgentError> {
+    canister
+        .try_store_to_stable(offset, message, create_delay(500, UPDATE_TIMEOUT_SECS))
+        .await
+}
+
+/// Waits until update calls to `canister` no longer complete, i.e., until the
+/// subnet of the canister picked up the registry version that halts it.
+/// Panics if the subnet still executes update calls after [HALT_TIMEOUT].
+///
+/// The update calls write `message` at `offset` of the stable memory. The
+/// last of them is still in the ingress pool, and may be executed once the
+/// subnet resumes.
+pub(crate) async fn wait_until_ingress_is_not_executed(
+    canister: &UniversalCanister<'_>,
+    offset: u32,
+    message: &[u8],
+) {
+    let start = Instant::now();
+    while start.elapsed() < HALT_TIMEOUT {
+        if try_store_with_timeout(canister, offset, message)
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+    panic!(
+        "The subnet still executed update calls {} secs after it was halted",
+        HALT_TIMEOUT.as_secs()
+    );
+}
+
+/// Waits until update calls to `canister` complete again, i.e., until the
+/// subnet of the canister picked up the registry version that resumes it.
+/// Panics if the subnet does not execute update calls after [HALT_TIMEOUT].
+/// The update calls write `message` at `offset` of the stable memory.
+pub(crate) async fn wait_until_ingress_is_executed(
+    canister: &UniversalCanister<'_>,
+    offset: u32,
+    message: &[u8],
+) {
+    let start = Instant::now();
+    while start.elapsed() < HALT_TIMEOUT {
+        if try_store_with_timeout(canister, offset, message)
+            .await
+            .is_ok()
+        {
+            return;
+        }
+    }
+    panic!(
+        "The subnet did not execute update calls {} secs after it was resumed",
+        HALT_TIMEOUT.as_secs()
+    );
+}
+
+/// The certified time of the state of each of `endpoints`, read through the
+/// subnet of `canister_id` with verified `read_state` requests.
+pub(crate) async fn read_certified_times(
+    endpoints: &[&IcEndpoint],
+    canister_id: &CanisterId,
+) -> Vec<Vec<u8>> {
+    let mut times = Vec::with_capacity(endpoints.len());
+    for endpoint in endpoints {
+        let reader = StateTreeReader::new(endpoint.url.as_str())
+            .await
+            .unwrap_or_else(|err| panic!("Could not create a state reader: {}", err));
+        let time = reader
+            .read_leaf(canister_id, state_tree_paths::time())
+            .await
+            .unwrap_or_else(|err| panic!("Could not read the state of {}: {}", endpoint.url, err))
+            .unwrap_or_else(|| panic!("{} certified no time", endpoint.url));
+        times.push(time);
+    }
+    times
+}
+
+/// Asserts that all of `endpoints`, which belong to a halted subnet, stopped
+/// at the same certified state, i.e., that the halt did not leave any of the
+/// nodes behind or let any of them diverge. Nodes may still be catching up
+/// with the certification of the last height when the subnet halts, so they
+/// get [HALT_TIMEOUT] to agree.
+pub(crate) async fn assert_halted_nodes_agree_on_certified_state(
+    endpoints: &[&IcEndpoint],
+    canister_id: &CanisterId,
+) {
+    let start = Instant::now();
+    loop {
+        let times = read_certified_times(endpoints, canister_id).await;
+        if times.windows(2).all(|pair| pair[0] == pair[1]) {
+            // A halted subnet certifies no further heights.
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            assert_eq!(
+                read_certified_times(endpoints, canister_id).await,
+                times,
+                "The certified state of the halted subnet changed"
+            );
+            return;
+        }
+        if start.elapsed() > HALT_TIMEOUT {
+            panic!(
+                "The nodes of the halted subnet certified different states: {:?}",
+                times
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
@@ -15,7 +15,7 @@ use ic_nns_test_utils::ids::TEST_NEURON_1_ID;
 use ic_protobuf::registry::replica_version::v1::BlessedReplicaVersions;
 use ic_registry_common::registry::RegistryCanister;
 use ic_registry_keys::make_blessed_replica_version_key;
-use ic_types::{messages::ReplicaHealthStatus, ReplicaVersion, SubnetId};
+use ic_types::{ReplicaVersion, SubnetId};
 use prost::Message;
 use slog::{info, Logger};
 use std::convert::TryFrom;
@@ -121,13 +121,13 @@ pub(crate) fn assert_assigned_replica_version(
 /// Gets the replica version from the endpoint if it is healthy.
 pub(crate) fn get_assigned_replica_version(endpoint: &IcEndpoint) -> Result<String, String> {
     let version = match block_on(async { endpoint.status().await }) {
-        Ok(status) if Some(ReplicaHealthStatus::Healthy) == status.replica_health_status => status,
+        Ok(status) if status.is_healthy() => status,
         Ok(_) => return Err("Replica is not healty".to_string()),
         Err(err) => return Err(err.to_string()),
     }
     .impl_version;
     match version {
-        Some(ver) => Ok(ver),
+        Some(ver) => Ok(ver.to_string()),
         None => Err("No version found in status".to_string()),
     }
 }
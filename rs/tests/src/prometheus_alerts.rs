@@ -0,0 +1,547 @@
+//! Checks that the production alerting rules would not have fired at the end
+//! of a scenario, catching regressions of the metrics the alerts rely on,
+//! e.g. a crypto key that is reported missing or an adapter that is down.
+//!
+//! The rule files in `$PROMETHEUS_ALERT_RULES` are unit tested with
+//! `promtool test rules` against a single scrape of the metrics of the test
+//! nodes, so that the rules are evaluated by Prometheus itself. Each scraped
+//! sample is fed to promtool as a series that keeps its value for longer than
+//! the longest `for` clause of the rules. An alert whose condition holds on
+//! the scrape hence fires regardless of its `for` clause, while alerts on how
+//! a metric changes, e.g. with `rate`, see a constant series.
+//!
+//! Each sample gets an `instance` label with the name of the scraped target,
+//! and an `up` sample is added per target, with value 0 if the scrape failed.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use slog::{info, warn, Logger};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use url::Url;
+
+/// The environment variable with the path of a rule file, or of a directory
+/// of rule files.
+pub const ALERT_RULES_ENV_VAR: &str = "PROMETHEUS_ALERT_RULES";
+
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The interval of the series and of the rule evaluations in the rule tests.
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The labels of a sample, by name.
+pub type Labels = BTreeMap<String, String>;
+
+/// A sample of a metric.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Labels,
+    pub value: f64,
+}
+
+impl Sample {
+    /// Returns the series of the sample in the notation of promtool, e.g.
+    /// `up{instance="node-1"}`.
+    fn series(&self) -> String {
+        if self.labels.is_empty() {
+            return self.name.clone();
+        }
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|(name, value)| {
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                format!("{}=\"{}\"", name, value)
+            })
+            .collect();
+        format!("{}{{{}}}", self.name, labels.join(","))
+    }
+}
+
+/// A target whose metrics are scraped.
+#[derive(Clone, Debug)]
+pub struct MetricsTarget {
+    /// The value of the `instance` label of the samples of the target.
+    pub instance: String,
+    pub url: Url,
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    groups: Vec<RuleGroup>,
+}
+
+#[derive(Deserialize)]
+struct RuleGroup {
+    #[serde(default)]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+struct RawRule {
+    /// Not set for recording rules.
+    alert: Option<String>,
+    #[serde(rename = "for")]
+    for_duration: Option<String>,
+}
+
+/// An alerting rule of a rule file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertRule {
+    pub name: String,
+    /// For how long the condition of the rule must hold until it fires.
+    pub for_duration: Duration,
+}
+
+/// The alerting rules of a set of rule files.
+#[derive(Clone, Debug, Default)]
+pub struct AlertRules {
+    files: Vec<PathBuf>,
+    rules: Vec<AlertRule>,
+}
+
+/// The rule test file that is passed to `promtool test rules`.
+#[derive(Serialize)]
+struct RuleTestFile {
+    rule_files: Vec<PathBuf>,
+    evaluation_interval: String,
+    tests: Vec<RuleTest>,
+}
+
+#[derive(Serialize)]
+struct RuleTest {
+    interval: String,
+    input_series: Vec<InputSeries>,
+    alert_rule_test: Vec<AlertRuleTest>,
+}
+
+#[derive(Serialize)]
+struct InputSeries {
+    series: String,
+    values: String,
+}
+
+#[derive(Serialize)]
+struct AlertRuleTest {
+    eval_time: String,
+    alertname: String,
+    /// Always empty, as none of the tested alerts may fire.
+    exp_alerts: Vec<()>,
+}
+
+impl AlertRules {
+    /// Loads the rules of `$PROMETHEUS_ALERT_RULES`, if it is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(ALERT_RULES_ENV_VAR) {
+            Ok(path) => Self::from_path(Path::new(&path)).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Loads the rules of the rule file at `path`, or of the `.yml` and
+    /// `.yaml` files in the directory at `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut files: Vec<PathBuf> = if path.is_dir() {
+            std::fs::read_dir(path)
+                .with_context(|| format!("Failed to list rule files in {:?}", path))?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("yml") | Some("yaml")
+                    )
+                })
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        files.sort();
+        let mut rules = Self::default();
+        for file in files {
+            let contents = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read rule file {:?}", file))?;
+            rules.rules.append(
+                &mut Self::parse(&contents)
+                    .with_context(|| format!("Failed to parse rule file {:?}", file))?,
+            );
+            rules.files.push(file.canonicalize()?);
+        }
+        Ok(rules)
+    }
+
+    /// Parses the alerting rules of the rule file whose contents are
+    /// `rule_file`.
+    fn parse(rule_file: &str) -> Result<Vec<AlertRule>> {
+        let rule_file: RuleFile = serde_yaml::from_str(rule_file)?;
+        let mut rules = vec![];
+        for rule in rule_file.groups.into_iter().flat_map(|group| group.rules) {
+            let name = match rule.alert {
+                Some(name) => name,
+                None => continue,
+            };
+            let for_duration = match rule.for_duration {
+                Some(duration) => parse_duration(&duration)
+                    .with_context(|| format!("Invalid `for` of alert {}", name))?,
+                None => Duration::ZERO,
+            };
+            rules.push(AlertRule { name, for_duration });
+        }
+        Ok(rules)
+    }
+
+    /// The alerting rules.
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    /// Returns the rule test file that checks that none of the rules other
+    /// than `expected_alerts` fires on `samples`.
+    fn rule_test_file(&self, samples: &[Sample], expected_alerts: &[&str]) -> RuleTestFile {
+        // The condition of an alert holds from the first evaluation on, so
+        // the alert fires at the latest once its `for` duration elapsed.
+        let longest_for = self
+            .rules
+            .iter()
+            .map(|rule| rule.for_duration)
+            .max()
+            .unwrap_or_default();
+        let intervals = longest_for.as_secs() / EVALUATION_INTERVAL.as_secs() + 1;
+        let input_series = samples
+            .iter()
+            .map(|sample| InputSeries {
+                series: sample.series(),
+                values: format!("{}+0x{}", format_value(sample.value), intervals),
+            })
+            .collect();
+        let alert_rule_test = self
+            .rules
+            .iter()
+            .filter(|rule| !expected_alerts.contains(&rule.name.as_str()))
+            .map(|rule| AlertRuleTest {
+                eval_time: format!("{}s", intervals * EVALUATION_INTERVAL.as_secs()),
+                alertname: rule.name.clone(),
+                exp_alerts: vec![],
+            })
+            .collect();
+        let interval = format!("{}s", EVALUATION_INTERVAL.as_secs());
+        RuleTestFile {
+            rule_files: self.files.clone(),
+            evaluation_interval: interval.clone(),
+            tests: vec![RuleTest {
+                interval,
+                input_series,
+                alert_rule_test,
+            }],
+        }
+    }
+
+    /// Runs `promtool test rules` to check that none of the rules other than
+    /// `expected_alerts` fires on `samples`. Returns the output of promtool,
+    /// which lists the alerts that fire, as error if any does.
+    pub fn check_no_alerts_fire(&self, samples: &[Sample], expected_alerts: &[&str]) -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let test_file = dir.path().join("alert_rules_test.yml");
+        std::fs::write(
+            &test_file,
+            serde_yaml::to_string(&self.rule_test_file(samples, expected_alerts))?,
+        )?;
+        let output = Command::new("promtool")
+            .arg("test")
+            .arg("rules")
+            .arg(&test_file)
+            .output()
+            .context("Failed to run promtool")?;
+        if !output.status.success() {
+            bail!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Scrapes the metrics of `targets`.
+pub fn scrape(targets: &[MetricsTarget], logger: &Logger) -> Vec<Sample> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(SCRAPE_TIMEOUT)
+        .build()
+        .expect("cannot build a reqwest client");
+    let mut samples = vec![];
+    for target in targets {
+        let scraped = client
+            .get(target.url.clone())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(anyhow::Error::from)
+            .and_then(|text| parse_exposition(&text));
+        let up = match scraped {
+            Ok(mut scraped) => {
+                for sample in scraped.iter_mut() {
+                    sample
+                        .labels
+                        .insert("instance".to_string(), target.instance.clone());
+                }
+                samples.append(&mut scraped);
+                1.0
+            }
+            Err(e) => {
+                warn!(logger, "Failed to scrape {}: {:?}", target.url, e);
+                0.0
+            }
+        };
+        samples.push(Sample {
+            name: "up".to_string(),
+            labels: vec![("instance".to_string(), target.instance.clone())]
+                .into_iter()
+                .collect(),
+            value: up,
+        });
+    }
+    samples
+}
+
+/// Scrapes the metrics of `targets` and panics if any of the rules of
+/// `$PROMETHEUS_ALERT_RULES` other than `expected_alerts` fires. Does nothing
+/// if `$PROMETHEUS_ALERT_RULES` is not set.
+pub fn assert_no_alerts_fire(targets: &[MetricsTarget], expected_alerts: &[&str], logger: &Logger) {
+    let rules = match AlertRules::from_env() {
+        Ok(Some(rules)) => rules,
+        Ok(None) => {
+            info!(
+                logger,
+                "${} is not set, not checking the alerting rules", ALERT_RULES_ENV_VAR
+            );
+            return;
+        }
+        Err(e) => panic!("Failed to load the alerting rules: {:?}", e),
+    };
+    let samples = scrape(targets, logger);
+    if let Err(e) = rules.check_no_alerts_fire(&samples, expected_alerts) {
+        panic!("Alerts would have fired:\n{:?}", e);
+    }
+    let checked = rules
+        .rules()
+        .iter()
+        .filter(|rule| !expected_alerts.contains(&rule.name.as_str()))
+        .count();
+    info!(
+        logger,
+        "None of the {} checked alerts fires on {} targets",
+        checked,
+        targets.len()
+    );
+}
+
+/// Parses metrics in the Prometheus text exposition format.
+pub fn parse_exposition(text: &str) -> Result<Vec<Sample>> {
+    let mut samples = vec![];
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let sample = parse_sample(line).with_context(|| format!("Invalid sample {:?}", line))?;
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+fn parse_sample(line: &str) -> Result<Sample> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .unwrap_or_else(|| line.len());
+    let name = &line[..name_end];
+    if name.is_empty() {
+        bail!("no metric name");
+    }
+    let mut rest = &line[name_end..];
+    let mut labels = Labels::new();
+    if let Some(after_brace) = rest.strip_prefix('{') {
+        rest = after_brace;
+        loop {
+            rest = rest.trim_start().trim_start_matches(',').trim_start();
+            if let Some(after_brace) = rest.strip_prefix('}') {
+                rest = after_brace;
+                break;
+            }
+            let (label, after_eq) = match rest.split_once('=') {
+                Some((label, after_eq)) => (label.trim(), after_eq.trim_start()),
+                None => bail!("expected a label name followed by '='"),
+            };
+            let (value, after_value) = parse_label_value(after_eq)?;
+            labels.insert(label.to_string(), value);
+            rest = after_value;
+        }
+    }
+    let value = rest.split_whitespace().next().unwrap_or_default();
+    Ok(Sample {
+        name: name.to_string(),
+        labels,
+        value: parse_value(value)?,
+    })
+}
+
+/// Parses the quoted label value at the start of `s`, returning the value and
+/// the rest of `s`.
+fn parse_label_value(s: &str) -> Result<(String, &str)> {
+    let mut chars = match s.strip_prefix('"') {
+        Some(quoted) => quoted.char_indices(),
+        None => bail!("expected a quoted label value"),
+    };
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[i + 2..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, c)) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    bail!("unterminated label value")
+}
+
+fn parse_value(value: &str) -> Result<f64> {
+    match value {
+        "+Inf" | "Inf" => Ok(f64::INFINITY),
+        "-Inf" => Ok(f64::NEG_INFINITY),
+        "NaN" => Ok(f64::NAN),
+        _ => value
+            .parse()
+            .with_context(|| format!("invalid value {:?}", value)),
+    }
+}
+
+/// Formats `value` the way promtool parses values of series.
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parses a Prometheus duration, e.g. `5m` or `1h30m`.
+fn parse_duration(duration: &str) -> Result<Duration> {
+    let mut total = Duration::ZERO;
+    let mut rest = duration;
+    if rest.is_empty() {
+        bail!("empty duration");
+    }
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or_else(|| rest.len());
+        let amount: u64 = rest[..digits_end]
+            .parse()
+            .with_context(|| format!("invalid duration {:?}", duration))?;
+        rest = &rest[digits_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or_else(|| rest.len());
+        let millis_per_unit = match &rest[..unit_end] {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60 * 1_000,
+            "h" => 60 * 60 * 1_000,
+            "d" => 24 * 60 * 60 * 1_000,
+            "w" => 7 * 24 * 60 * 60 * 1_000,
+            "y" => 365 * 24 * 60 * 60 * 1_000,
+            unit => bail!("invalid unit {:?} in duration {:?}", unit, duration),
+        };
+        total += Duration::from_millis(amount * millis_per_unit);
+        rest = &rest[unit_end..];
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+groups:
+  - name: replica
+    rules:
+      - record: job:up:sum
+        expr: sum(up)
+      - alert: IC_Replica_Down
+        expr: up == 0
+        for: 1h30m
+      - alert: IC_Crypto_KeyMissing
+        expr: max by (instance) (crypto_key_missing{key_type=~"idkg_.*"}) > 0
+"#;
+
+    #[test]
+    fn should_parse_exposition_format() {
+        let samples =
+            parse_exposition("# TYPE m counter\nm{a=\"x\\\"y\",b=\"\"} 1.5 1600000000\nn +Inf\n")
+                .unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].name, "m");
+        assert_eq!(samples[0].labels["a"], "x\"y");
+        assert_eq!(samples[0].labels["b"], "");
+        assert_eq!(samples[0].value, 1.5);
+        assert_eq!(samples[0].series(), "m{a=\"x\\\"y\",b=\"\"}");
+        assert_eq!(samples[1].value, f64::INFINITY);
+        assert_eq!(samples[1].series(), "n");
+    }
+
+    #[test]
+    fn should_parse_durations() {
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn should_skip_recording_rules() {
+        assert_eq!(
+            AlertRules::parse(RULES).unwrap(),
+            vec![
+                AlertRule {
+                    name: "IC_Replica_Down".to_string(),
+                    for_duration: Duration::from_secs(5400),
+                },
+                AlertRule {
+                    name: "IC_Crypto_KeyMissing".to_string(),
+                    for_duration: Duration::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_hold_series_until_the_longest_for_elapsed() {
+        let rules = AlertRules {
+            files: vec![PathBuf::from("/rules.yml")],
+            rules: AlertRules::parse(RULES).unwrap(),
+        };
+        let samples = parse_exposition("up{instance=\"node-1\"} 0\n").unwrap();
+
+        let test_file = rules.rule_test_file(&samples, &["IC_Crypto_KeyMissing"]);
+        let test = &test_file.tests[0];
+        assert_eq!(test.input_series[0].series, "up{instance=\"node-1\"}");
+        assert_eq!(test.input_series[0].values, "0+0x91");
+        assert_eq!(test.alert_rule_test.len(), 1);
+        assert_eq!(test.alert_rule_test[0].alertname, "IC_Replica_Down");
+        assert_eq!(test.alert_rule_test[0].eval_time, "5460s");
+    }
+}
@@ -0,0 +1,372 @@
+//! Checks that the registry is in a valid state at the end of a scenario, so
+//! that proposals executed by a test cannot silently leave it inconsistent.
+//!
+//! The registry is read from the local store of the Internet Computer under
+//! test, after syncing it with the NNS if the NNS is installed. Each
+//! [RegistryInvariant] inspects a [RegistryView] of the newest registry
+//! version and reports its violations. The invariants of
+//! [default_invariants()] are checked after every pot of the prod test
+//! driver, see [check_registry_invariants()]. Further invariants are added by
+//! implementing [RegistryInvariant] and listing them there.
+use crate::api::system_test_context::SystemTestContext;
+use anyhow::{bail, Result};
+use ic_fondue::{ic_manager::IcHandle, pot::Context};
+use ic_interfaces::registry::RegistryClient;
+use ic_protobuf::registry::{
+    node::v1::{ConnectionEndpoint, NodeRecord},
+    subnet::v1::SubnetRecord,
+};
+use ic_registry_client::helper::{
+    node::NodeRegistry,
+    subnet::{get_node_ids_from_subnet_record, SubnetListRegistry, SubnetRegistry},
+};
+use ic_types::{NodeId, RegistryVersion, SubnetId};
+use slog::{info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The name under which the check is reported as a test of each pot.
+pub const REGISTRY_INVARIANTS_CHECK: &str = "registry_invariants";
+
+/// The subnets and nodes of the registry at a fixed version.
+#[derive(Clone, Debug, Default)]
+pub struct RegistryView {
+    pub version: RegistryVersion,
+    pub subnets: BTreeMap<SubnetId, SubnetRecord>,
+    pub nodes: BTreeMap<NodeId, NodeRecord>,
+}
+
+impl RegistryView {
+    /// Reads the subnets of the subnet list and all node records of
+    /// `registry` at `version`.
+    pub fn read(registry: &dyn RegistryClient, version: RegistryVersion) -> Result<Self> {
+        let mut subnets = BTreeMap::new();
+        for subnet_id in registry.get_subnet_ids(version)?.unwrap_or_default() {
+            match registry.get_subnet_record(subnet_id, version)? {
+                Some(record) => subnets.insert(subnet_id, record),
+                None => bail!(
+                    "Subnet {} is in the subnet list but has no subnet record",
+                    subnet_id
+                ),
+            };
+        }
+        let mut nodes = BTreeMap::new();
+        for node_id in registry.get_node_ids(version)? {
+            if let Some(record) = registry.get_transport_info(node_id, version)? {
+                nodes.insert(node_id, record);
+            }
+        }
+        Ok(Self {
+            version,
+            subnets,
+            nodes,
+        })
+    }
+}
+
+/// A property that the registry must satisfy at the end of every scenario.
+pub trait RegistryInvariant: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns a description of each violation of the invariant in `view`.
+    fn violations(&self, view: &RegistryView) -> Vec<String>;
+}
+
+/// Every member of a subnet has a node record, and no node is a member of
+/// more than one subnet.
+pub struct NoOrphanNodes;
+
+impl RegistryInvariant for NoOrphanNodes {
+    fn name(&self) -> &'static str {
+        "no_orphan_nodes"
+    }
+
+    fn violations(&self, view: &RegistryView) -> Vec<String> {
+        let mut violations = vec![];
+        let mut subnet_of_node = BTreeMap::new();
+        for (subnet_id, record) in view.subnets.iter() {
+            for node_id in get_node_ids_from_subnet_record(record) {
+                if !view.nodes.contains_key(&node_id) {
+                    violations.push(format!(
+                        "Node {} of subnet {} has no node record",
+                        node_id, subnet_id
+                    ));
+                }
+                if let Some(other) = subnet_of_node.insert(node_id, subnet_id) {
+                    violations.push(format!(
+                        "Node {} is a member of subnets {} and {}",
+                        node_id, other, subnet_id
+                    ));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// No two nodes share the address of an http, xnet or p2p endpoint.
+pub struct UniqueEndpoints;
+
+impl UniqueEndpoints {
+    fn endpoints(record: &NodeRecord) -> impl Iterator<Item = &ConnectionEndpoint> {
+        record
+            .http
+            .iter()
+            .chain(record.xnet.iter())
+            .chain(record.public_api.iter())
+            .chain(record.xnet_api.iter())
+            .chain(
+                record
+                    .p2p_flow_endpoints
+                    .iter()
+                    .filter_map(|flow| flow.endpoint.as_ref()),
+            )
+    }
+}
+
+impl RegistryInvariant for UniqueEndpoints {
+    fn name(&self) -> &'static str {
+        "unique_endpoints"
+    }
+
+    fn violations(&self, view: &RegistryView) -> Vec<String> {
+        let mut violations = vec![];
+        let mut node_of_address = BTreeMap::new();
+        for (node_id, record) in view.nodes.iter() {
+            let addresses: BTreeSet<_> = Self::endpoints(record)
+                .map(|endpoint| (endpoint.ip_addr.clone(), endpoint.port))
+                .collect();
+            for (ip_addr, port) in addresses {
+                let address = format!("{}:{}", ip_addr, port);
+                if let Some(other) = node_of_address.insert(address.clone(), node_id) {
+                    violations.push(format!(
+                        "Nodes {} and {} both have an endpoint at {}",
+                        other, node_id, address
+                    ));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// The ECDSA key ids of a subnet are non-empty and unique, and a subnet only
+/// holds ECDSA keys if it has the `ecdsa_signatures` feature.
+pub struct ConsistentEcdsaConfigs;
+
+impl RegistryInvariant for ConsistentEcdsaConfigs {
+    fn name(&self) -> &'static str {
+        "consistent_ecdsa_configs"
+    }
+
+    fn violations(&self, view: &RegistryView) -> Vec<String> {
+        let mut violations = vec![];
+        for (subnet_id, record) in view.subnets.iter() {
+            let key_ids = match &record.ecdsa_config {
+                Some(config) => &config.key_ids,
+                None => continue,
+            };
+            let mut seen = BTreeSet::new();
+            for key_id in key_ids {
+                if key_id.is_empty() {
+                    violations.push(format!("Subnet {} holds an empty ECDSA key id", subnet_id));
+                } else if !seen.insert(key_id) {
+                    violations.push(format!(
+                        "Subnet {} holds the ECDSA key {} more than once",
+                        subnet_id, key_id
+                    ));
+                }
+            }
+            let ecdsa_enabled = record
+                .features
+                .as_ref()
+                .map_or(false, |features| features.ecdsa_signatures);
+            if !key_ids.is_empty() && !ecdsa_enabled {
+                violations.push(format!(
+                    "Subnet {} holds ECDSA keys without the ecdsa_signatures feature",
+                    subnet_id
+                ));
+            }
+        }
+        violations
+    }
+}
+
+/// The invariants checked after every pot.
+pub fn default_invariants() -> Vec<Box<dyn RegistryInvariant>> {
+    vec![
+        Box::new(NoOrphanNodes),
+        Box::new(UniqueEndpoints),
+        Box::new(ConsistentEcdsaConfigs),
+    ]
+}
+
+/// Fails with all violations of `invariants` in `view`, if any.
+pub fn check_invariants(
+    view: &RegistryView,
+    invariants: &[Box<dyn RegistryInvariant>],
+) -> Result<()> {
+    let violations: Vec<_> = invariants
+        .iter()
+        .flat_map(|invariant| {
+            invariant
+                .violations(view)
+                .into_iter()
+                .map(move |violation| format!("{}: {}", invariant.name(), violation))
+        })
+        .collect();
+    if !violations.is_empty() {
+        bail!(
+            "The registry at version {} violates invariants:\n{}",
+            view.version,
+            violations.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Checks the [default_invariants()] against the newest version of the
+/// registry of the Internet Computer under test. Meant to be run after all
+/// tests of a pot, e.g. as a post-pot check of the prod test driver.
+///
+/// # Panics
+///
+/// * This function panics if the registry violates an invariant.
+pub fn check_registry_invariants(ic_handle: IcHandle, ctx: &Context) {
+    let test_ctx = SystemTestContext::from_ic_handle(ic_handle, ctx);
+    // Without the NNS, the local store is the registry of the scenario.
+    if let Err(e) = test_ctx.sync_registry_with_nns() {
+        warn!(
+            ctx.logger,
+            "Could not sync the local store with the NNS, checking it as is: {}", e
+        );
+    }
+    let version = test_ctx.local_registry().get_latest_version();
+    let view = RegistryView::read(test_ctx.local_registry(), version)
+        .unwrap_or_else(|e| panic!("Could not read the registry: {:?}", e));
+    check_invariants(&view, &default_invariants()).unwrap_or_else(|e| panic!("{}", e));
+    info!(
+        ctx.logger,
+        "The registry at version {} satisfies all invariants", version
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_protobuf::registry::{
+        node::v1::FlowEndpoint,
+        subnet::v1::{EcdsaConfig, SubnetFeatures},
+    };
+    use ic_types::PrincipalId;
+
+    fn node_test_id(id: u64) -> NodeId {
+        NodeId::from(PrincipalId::new_node_test_id(id))
+    }
+
+    fn subnet_test_id(id: u64) -> SubnetId {
+        SubnetId::from(PrincipalId::new_subnet_test_id(id))
+    }
+
+    fn endpoint(ip_addr: &str, port: u32) -> ConnectionEndpoint {
+        ConnectionEndpoint {
+            ip_addr: ip_addr.to_string(),
+            port,
+            ..Default::default()
+        }
+    }
+
+    fn node_record(ip_addr: &str) -> NodeRecord {
+        NodeRecord {
+            http: Some(endpoint(ip_addr, 8080)),
+            xnet: Some(endpoint(ip_addr, 2497)),
+            p2p_flow_endpoints: vec![FlowEndpoint {
+                flow_tag: 0,
+                endpoint: Some(endpoint(ip_addr, 4100)),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn subnet_record(nodes: &[u64]) -> SubnetRecord {
+        SubnetRecord {
+            membership: nodes
+                .iter()
+                .map(|id| node_test_id(*id).get().into_vec())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn view(subnets: Vec<(u64, SubnetRecord)>, nodes: &[(u64, &str)]) -> RegistryView {
+        RegistryView {
+            version: RegistryVersion::from(1),
+            subnets: subnets
+                .into_iter()
+                .map(|(id, record)| (subnet_test_id(id), record))
+                .collect(),
+            nodes: nodes
+                .iter()
+                .map(|(id, ip_addr)| (node_test_id(*id), node_record(ip_addr)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_accept_a_valid_registry() {
+        let view = view(
+            vec![(1, subnet_record(&[1, 2])), (2, subnet_record(&[3]))],
+            &[(1, "::1"), (2, "::2"), (3, "::3"), (4, "::4")],
+        );
+
+        assert!(check_invariants(&view, &default_invariants()).is_ok());
+    }
+
+    #[test]
+    fn should_report_orphan_and_shared_nodes() {
+        let view = view(
+            vec![(1, subnet_record(&[1, 2])), (2, subnet_record(&[2]))],
+            &[(2, "::2")],
+        );
+
+        let violations = NoOrphanNodes.violations(&view);
+
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("has no node record"));
+        assert!(violations[1].contains("is a member of subnets"));
+    }
+
+    #[test]
+    fn should_report_shared_endpoints() {
+        let view = view(vec![], &[(1, "::1"), (2, "::1"), (3, "::3")]);
+
+        // The http, xnet and p2p endpoints of nodes 1 and 2 collide.
+        assert_eq!(UniqueEndpoints.violations(&view).len(), 3);
+    }
+
+    #[test]
+    fn should_report_inconsistent_ecdsa_configs() {
+        let mut record = subnet_record(&[1]);
+        record.ecdsa_config = Some(EcdsaConfig {
+            key_ids: vec!["secp256k1".to_string(), "secp256k1".to_string()],
+            ..Default::default()
+        });
+        let view = view(vec![(1, record.clone())], &[(1, "::1")]);
+
+        let violations = ConsistentEcdsaConfigs.violations(&view);
+
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("more than once"));
+        assert!(violations[1].contains("without the ecdsa_signatures feature"));
+
+        record.features = Some(SubnetFeatures {
+            ecdsa_signatures: true,
+            ..Default::default()
+        });
+        record.ecdsa_config.as_mut().unwrap().key_ids.pop();
+        let view = RegistryView {
+            subnets: vec![(subnet_test_id(1), record)].into_iter().collect(),
+            ..view
+        };
+        assert!(ConsistentEcdsaConfigs.violations(&view).is_empty());
+    }
+}
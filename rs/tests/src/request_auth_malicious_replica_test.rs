@@ -43,8 +43,12 @@ use ic_types::{CanisterId, Time};
 use ic_universal_canister::wasm;
 use slog::info;
 use std::convert::TryFrom;
+use std::time::Duration;
 use url::Url;
 
+/// The time within which a valid request must be executed by honest nodes.
+const INGRESS_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub fn config() -> InternetComputer {
     InternetComputer::new().add_subnet(
         Subnet::new(SubnetType::System)
@@ -154,18 +158,33 @@ pub fn test(mut handle: IcHandle, ctx: &ic_fondue::pot::Context) {
             }
 
             // To verify that our implementation is correct, let's try sending a valid
-            // request to an honest node. The canister's memory should then be
-            // updated.
-            test_request_with_delegation(
+            // request to an honest node and wait until all honest nodes executed it.
+            // The canister's memory should then be updated.
+            let ingress = SignedIngress::new(&delegated_request_envelope(
                 &random_ed25519_identity(),
-                &honest_nodes[0].url,
                 canister.canister_id(),
                 expiry_time().as_nanos() as u64,
                 Some(vec![canister.canister_id()]),
-            )
-            .await;
-
-            std::thread::sleep(std::time::Duration::from_secs(30));
+            ));
+            let outcome = submit_and_await_ingress(&honest_nodes[0], &ingress, INGRESS_TIMEOUT)
+                .await
+                .expect("The valid request was not executed");
+            assert!(
+                matches!(outcome, IngressOutcome::Replied(_)),
+                "The valid request was not replied: {:?}",
+                outcome
+            );
+            for node in &honest_nodes[1..] {
+                let node_outcome = await_ingress_outcome(
+                    node,
+                    ingress.canister_id,
+                    &ingress.request_id,
+                    INGRESS_TIMEOUT,
+                )
+                .await
+                .expect("The valid request was not executed");
+                assert_eq!(node_outcome, outcome);
+            }
 
             // Query all the honest nodes and verify that the memory has been updated.
             for node in honest_nodes {
@@ -462,6 +481,35 @@ async fn test_request_with_delegation<T: Identity + 'static>(
     delegation_expiry: u64,
     delegation_targets: Option<Vec<Principal>>,
 ) {
+    let envelope =
+        delegated_request_envelope(identity, canister_id, delegation_expiry, delegation_targets);
+    let body = serde_cbor::ser::to_vec(&envelope).unwrap();
+    let client = reqwest::Client::new();
+    let res = client
+        .post(&format!(
+            "{}api/v2/canister/{}/call",
+            node_url,
+            canister_id.to_text()
+        ))
+        .header("Content-Type", "application/cbor")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    // Even though the request is invalid, the malicious node accepts it.
+    assert_eq!(res.status(), 202);
+}
+
+/// Returns an update call to set the global data to [4, 5, 6], which is
+/// signed by a random identity that `identity` delegates to until
+/// `delegation_expiry` for `delegation_targets`.
+fn delegated_request_envelope<T: Identity + 'static>(
+    identity: &T,
+    canister_id: Principal,
+    delegation_expiry: u64,
+    delegation_targets: Option<Vec<Principal>>,
+) -> HttpRequestEnvelope<HttpCallContent> {
     let identity2 = random_ed25519_identity();
 
     // An update call to set the global data to [4, 5, 6].
@@ -502,30 +550,12 @@ async fn test_request_with_delegation<T: Identity + 'static>(
 
     let public_key_identity = { identity.sign(&[]).unwrap().public_key.unwrap() };
 
-    // Add the correct public key but the wrong public key
-    let envelope = HttpRequestEnvelope {
-        content: content.clone(),
+    HttpRequestEnvelope {
+        content,
         sender_delegation: Some(vec![signed_delegation]),
         sender_sig: Some(Blob(signature.signature.unwrap())),
         sender_pubkey: Some(Blob(public_key_identity)),
-    };
-    let body = serde_cbor::ser::to_vec(&envelope).unwrap();
-    let client = reqwest::Client::new();
-    let res = client
-        .post(&format!(
-            "{}api/v2/canister/{}/call",
-            node_url,
-            canister_id.to_text()
-        ))
-        .header("Content-Type", "application/cbor")
-        .body(body)
-        .send()
-        .await
-        .unwrap();
-
-    // Even though the request can be invalid, the node should accepts it, since we
-    // only send invalid requests to malicious nodes, which accept all requests.
-    assert_eq!(res.status(), 202);
+    }
 }
 
 fn sign_delegation(delegation: Delegation, identity: &impl Identity) -> SignedDelegation {
@@ -0,0 +1,157 @@
+/* tag::catalog[]
+Title:: Threshold ECDSA key rotation test
+
+Goal:: Verify that a node rotates its I-DKG dealing encryption key without
+interrupting threshold ECDSA signing, and that the secret key of the rotated
+key is eventually removed from the node.
+
+Runbook::
+. start a subnet with ecdsa feature enabled and install the NNS canisters.
+. wait until the orchestrator of a node registers a rotated I-DKG dealing
+  encryption key, which is due immediately as the keys registered by ic-prep
+  have no timestamp.
+. have the canister sign messages and verify the signatures, until the secret
+  key of the rotated key is removed from the secret key store of the node,
+  verifying after every signature that the secret key of the new key is still
+  in the secret key store of the node
+
+Success:: The node registers a new key, all signatures requested during the
+transition verify, and only the secret key of the new key remains on the node.
+
+end::catalog[] */
+
+use crate::nns::NnsExt;
+use crate::orchestrator::utils::ssh_access::{admin_auth_mean, execute_remote_command, AuthMean};
+use crate::tecdsa_signature_test::{get_public_key, get_signature, verify_signature};
+use crate::util::*;
+use ic_crypto_internal_csp::keygen::mega_key_id;
+use ic_crypto_internal_threshold_sig_ecdsa::{EccCurveType, MEGaPublicKey};
+use ic_fondue::ic_manager::{IcControl, IcEndpoint, IcHandle};
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
+use ic_registry_common::registry::RegistryCanister;
+use ic_registry_keys::make_crypto_node_key;
+use ic_types::crypto::{KeyId, KeyPurpose};
+use prost::Message;
+use slog::info;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// The time within which the node must register a rotated key.
+const ROTATION_TIMEOUT: Duration = Duration::from_secs(300);
+/// The time within which the secret key of the rotated key must be removed
+/// from the node, once the new key is registered.
+const REMOVAL_TIMEOUT: Duration = Duration::from_secs(600);
+/// The location of the secret key store on the nodes.
+const SKS_PATH: &str = "/var/lib/ic/crypto/sks_data.pb";
+
+/// Tests whether the I-DKG dealing encryption key of a node is rotated while
+/// `sign_with_ecdsa` calls keep being completed, and whether the secret key
+/// of the rotated key is removed from the node afterwards.
+pub fn test_threshold_ecdsa_key_rotation(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let rt = tokio::runtime::Runtime::new().expect("Could not create tokio runtime.");
+    let mut rng = ctx.rng.clone();
+
+    ctx.install_nns_canisters(&handle, true);
+    let endpoint = get_random_nns_node_endpoint(&handle, &mut rng);
+    rt.block_on(endpoint.assert_ready(ctx));
+    let node_ip = endpoint.ip_address().unwrap();
+    let mean = admin_auth_mean(endpoint);
+    let registry = RegistryCanister::new(vec![endpoint.url.clone()]);
+
+    let (old_key, new_key) = rt.block_on(await_rotated_idkg_key(&registry, endpoint));
+    let old_key_id = sks_key_id_hex(&old_key);
+    let new_key_id = sks_key_id_hex(&new_key);
+    info!(
+        ctx.logger,
+        "Node {} rotated its iDKG dealing encryption key {} to {}",
+        endpoint.node_id,
+        old_key_id,
+        new_key_id
+    );
+
+    rt.block_on(async {
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        let public_key = get_public_key(&uni_can, ctx).await;
+        let deadline = Instant::now() + REMOVAL_TIMEOUT;
+        let mut message_hash = [0u8; 32];
+        loop {
+            // Dealings encrypted for either key must be decrypted while the
+            // transcripts of the subnet move over to the new key.
+            let signature = get_signature(&message_hash, &uni_can, ctx).await;
+            verify_signature(&message_hash, &public_key, &signature);
+            message_hash[0] = message_hash[0].wrapping_add(1);
+
+            // The orchestrator stores the new key while the replica keeps
+            // writing the secret key store, so the new key must survive the
+            // writes of the replica.
+            assert!(
+                is_in_sks(&node_ip, &mean, &new_key_id),
+                "The secret key {} of the new key was lost",
+                new_key_id
+            );
+            if !is_in_sks(&node_ip, &mean, &old_key_id) {
+                break;
+            }
+            if Instant::now() > deadline {
+                panic!(
+                    "The secret key {} was not removed within {:?}",
+                    old_key_id, REMOVAL_TIMEOUT
+                );
+            }
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    });
+    info!(ctx.logger, "Secret key {} was removed", old_key_id);
+}
+
+/// Waits until the node of `endpoint` registers an I-DKG dealing encryption
+/// key with a timestamp, and returns the key registered before it and the new
+/// key.
+async fn await_rotated_idkg_key(
+    registry: &RegistryCanister,
+    endpoint: &IcEndpoint,
+) -> (PublicKeyProto, PublicKeyProto) {
+    let key = make_crypto_node_key(endpoint.node_id, KeyPurpose::IDkgMEGaEncryption);
+    let deadline = Instant::now() + ROTATION_TIMEOUT;
+    loop {
+        let (value, version) = registry
+            .get_value(key.as_bytes().to_vec(), None)
+            .await
+            .expect("failed to get the iDKG dealing encryption key");
+        let new_key = PublicKeyProto::decode(&value[..]).expect("malformed public key");
+        if new_key.timestamp.is_some() {
+            let (value, _) = registry
+                .get_value(key.as_bytes().to_vec(), Some(version - 1))
+                .await
+                .expect("failed to get the rotated iDKG dealing encryption key");
+            let old_key = PublicKeyProto::decode(&value[..]).expect("malformed public key");
+            assert_ne!(old_key.key_value, new_key.key_value);
+            return (old_key, new_key);
+        }
+        if Instant::now() > deadline {
+            panic!(
+                "Node {} did not rotate its key within {:?}",
+                endpoint.node_id, ROTATION_TIMEOUT
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    }
+}
+
+/// Returns the hex encoded id of the secret key of `public_key`, as stored in
+/// the secret key store.
+fn sks_key_id_hex(public_key: &PublicKeyProto) -> String {
+    let public_key = MEGaPublicKey::deserialize(EccCurveType::K256, &public_key.key_value)
+        .expect("malformed MEGa public key");
+    hex::encode(KeyId::from(mega_key_id(&public_key)).0)
+}
+
+/// Checks over SSH whether the secret key store of the node at `ip` contains
+/// the key with the hex encoded id `key_id`.
+fn is_in_sks(ip: &IpAddr, mean: &AuthMean, key_id: &str) -> bool {
+    let command = format!("sudo grep -c {} {} || true", key_id, SKS_PATH);
+    let output = execute_remote_command(ip, "admin", mean, &command)
+        .expect("failed to inspect the secret key store");
+    output.trim() != "0"
+}
@@ -0,0 +1,141 @@
+/* tag::catalog[]
+Title:: Threshold ECDSA replica restart test
+
+Goal:: Verify that pending threshold ECDSA signature requests survive a
+restart of all replicas of the subnet, i.e., that the signing contexts are
+persisted with the replicated state and are still completed or rejected after
+the restart.
+
+Runbook::
+. start a subnet with ecdsa feature enabled.
+. get public key of a canister
+. submit several `sign_with_ecdsa` calls without waiting for their replies
+. restart all nodes of the subnet at once and wait till they become ready
+  again
+. poll the status of every call on every node until it is replied or rejected
+. verify that all nodes report the same outcome for every call and that every
+  returned signature is correct with respect to the public key
+. have the canister sign another message and verify the signature
+
+Success:: All calls submitted before the restart are either replied with a
+valid signature or rejected, consistently on all nodes, and the subnet keeps
+signing after the restart.
+
+end::catalog[] */
+
+use crate::tecdsa_signature_test::{get_public_key, get_signature, verify_signature};
+use crate::util::*;
+use futures::future::join_all;
+use ic_ecdsa_api::{EcdsaApiCall, SignWithEcdsa};
+use ic_fondue::ic_manager::{IcControl, IcHandle};
+use secp256k1::Signature;
+use slog::info;
+use std::time::Duration;
+
+/// The number of signature requests that are pending during the restart.
+const PENDING_REQUESTS: u8 = 4;
+/// The time within which every pending request must be completed after the
+/// nodes are ready again.
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Tests whether `sign_with_ecdsa` calls that are pending when all replicas of
+/// the subnet are restarted are deterministically completed after the restart.
+pub fn test_threshold_ecdsa_signature_survives_replica_restart(
+    handle: IcHandle,
+    ctx: &ic_fondue::pot::Context,
+) {
+    let rt = tokio::runtime::Runtime::new().expect("Could not create tokio runtime.");
+    let mut rng = ctx.rng.clone();
+    let endpoints: Vec<_> = handle.as_permutation(&mut rng).collect();
+
+    let (canister_id, public_key, requests) = rt.block_on(async {
+        let endpoint = endpoints[0];
+        endpoint.assert_ready(ctx).await;
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        let public_key = get_public_key(&uni_can, ctx).await;
+        let mut requests = vec![];
+        for i in 0..PENDING_REQUESTS {
+            let call = SignWithEcdsa::new([i; 32]);
+            let request_id = uni_can
+                .submit_ecdsa_api_call(&call)
+                .await
+                .expect("Failed to submit sign_with_ecdsa");
+            requests.push((call, request_id));
+        }
+        info!(
+            ctx.logger,
+            "Submitted {} sign_with_ecdsa calls",
+            requests.len()
+        );
+        (uni_can.canister_id(), public_key, requests)
+    });
+
+    info!(ctx.logger, "Restarting all nodes...");
+    rt.block_on(join_all(endpoints.iter().map(|ep| async move {
+        // Restarting a node makes a blocking request to the farm.
+        let node = (*ep).clone();
+        let logger = ctx.logger.clone();
+        tokio::task::spawn_blocking(move || node.restart_node(logger))
+            .await
+            .expect("Failed to restart node");
+        // Confirm the restart by asserting the node is unreachable.
+        assert_endpoints_reachability(&[*ep], EndpointsStatus::AllUnreachable).await;
+        ep.assert_ready(ctx).await;
+    })));
+    info!(ctx.logger, "All nodes are ready again");
+
+    rt.block_on(async {
+        for (call, request_id) in requests.iter() {
+            let mut outcomes = vec![];
+            for ep in endpoints.iter() {
+                let outcome = await_ingress_outcome(ep, canister_id, request_id, OUTCOME_TIMEOUT)
+                    .await
+                    .unwrap_or_else(|err| {
+                        panic!(
+                            "Call {:?} was not completed on {}: {}",
+                            request_id, ep.url, err
+                        )
+                    });
+                outcomes.push(outcome);
+            }
+            assert!(
+                outcomes.windows(2).all(|w| w[0] == w[1]),
+                "Nodes report different outcomes for call {:?}: {:?}",
+                request_id,
+                outcomes
+            );
+            match &outcomes[0] {
+                IngressOutcome::Replied(reply) => {
+                    let reply = call
+                        .decode_reply(reply)
+                        .unwrap_or_else(|err| panic!("{}", err));
+                    let signature = Signature::from_compact(&reply.signature)
+                        .expect("Response is not a valid signature");
+                    verify_signature(&call.message_hash, &public_key, &signature);
+                    info!(ctx.logger, "Call {:?} was replied", request_id);
+                }
+                IngressOutcome::Rejected {
+                    reject_code,
+                    reject_message,
+                } => info!(
+                    ctx.logger,
+                    "Call {:?} was rejected with code {}: {}",
+                    request_id,
+                    reject_code,
+                    reject_message
+                ),
+                IngressOutcome::Done => info!(
+                    ctx.logger,
+                    "Call {:?} was completed, but its outcome was already pruned", request_id
+                ),
+            }
+        }
+
+        let agent = assert_create_agent(endpoints[0].url.as_str()).await;
+        let uni_can = UniversalCanister::from_canister_id(&agent, canister_id);
+        let message_hash = [0xefu8; 32];
+        let signature = get_signature(&message_hash, &uni_can, ctx).await;
+        verify_signature(&message_hash, &public_key, &signature);
+    });
+}
@@ -8,30 +8,31 @@ Runbook::
 . start a subnet with ecdsa feature enabled.
 . get public key of a canister
 . have the canister sign a message and get the signature
-. verify if the signature is correct with respect to the public key
+. verify if the signature is correct with respect to the public key, and that
+  signing cost the canister no cycles, as the subnet is a system subnet
+. verify the signature with the encoding rules of Bitcoin (strict DER, low S)
+  and Ethereum (recoverable signature with v value, low S)
+. restrict signing with the key to one of two ECDSA subnets and verify that
+  only that subnet signs, while the other one rejects the request
 
-Success:: An agent can complete the signing process and result signature verifies.
+Success:: An agent can complete the signing process and result signature verifies,
+also when encoded for and verified as on Bitcoin and Ethereum.
 
 end::catalog[] */
 
+use crate::types::*;
 use crate::util::*;
-use candid::Encode;
-use candid::Principal;
+use ic_ecdsa_api::DEFAULT_KEY_ID;
 use ic_fondue::{
     ic_instance::{InternetComputer, Subnet},
     ic_manager::IcHandle,
 };
-use ic_ic00_types::{
-    GetECDSAPublicKeyArgs, GetECDSAPublicKeyResponse, Payload, SignWithECDSAArgs,
-    SignWithECDSAReply,
-};
 use ic_protobuf::registry::subnet::v1::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
-use ic_types::Height;
+use ic_types::{Cycles, Height};
 use secp256k1::{Message, PublicKey, Secp256k1, Signature};
 use slog::{debug, info};
-
-const KEY_ID: &str = "secp256k1";
+use std::convert::TryInto;
 
 pub fn enable_ecdsa_signatures_feature() -> InternetComputer {
     InternetComputer::new().add_subnet(
@@ -49,27 +50,10 @@ pub(crate) async fn get_public_key(
     uni_can: &UniversalCanister<'_>,
     ctx: &ic_fondue::pot::Context,
 ) -> PublicKey {
-    let public_key_request = GetECDSAPublicKeyArgs {
-        canister_id: None,
-        derivation_path: vec![],
-        key_id: KEY_ID.to_string(),
-    };
-
     let mut count = 0;
     let public_key = loop {
-        let res = uni_can
-            .forward_to(
-                &Principal::management_canister(),
-                "get_ecdsa_public_key",
-                Encode!(&public_key_request).unwrap(),
-            )
-            .await;
-        match res {
-            Ok(bytes) => {
-                let key = GetECDSAPublicKeyResponse::decode(&bytes)
-                    .expect("failed to decode ECDSAPublicKeyResponse");
-                break key.public_key;
-            }
+        match uni_can.get_ecdsa_public_key(DEFAULT_KEY_ID).await {
+            Ok(key) => break key.public_key,
             Err(err) => {
                 count += 1;
                 if count < 10 {
@@ -92,27 +76,17 @@ pub(crate) async fn get_signature(
     uni_can: &UniversalCanister<'_>,
     ctx: &ic_fondue::pot::Context,
 ) -> Signature {
-    let signature_request = SignWithECDSAArgs {
-        message_hash: message_hash.to_vec(),
-        derivation_path: Vec::new(),
-        key_id: KEY_ID.to_string(),
-    };
+    let message_hash = message_hash
+        .try_into()
+        .expect("message hash must be 32 bytes");
 
     // Ask for a signature.
     let res = uni_can
-        .forward_to(
-            &Principal::management_canister(),
-            "sign_with_ecdsa",
-            Encode!(&signature_request).unwrap(),
-        )
+        .sign_with_ecdsa(DEFAULT_KEY_ID, message_hash, 0)
         .await;
 
     let signature = match res {
-        Ok(reply) => {
-            SignWithECDSAReply::decode(&reply)
-                .expect("failed to decode SignWithECDSAReply")
-                .signature
-        }
+        Ok(reply) => reply.signature,
         Err(err) => {
             panic!("sign_with_ecdsa returns error {:?}", err);
         }
@@ -142,6 +116,78 @@ pub fn test_threshold_ecdsa_signature(handle: IcHandle, ctx: &ic_fondue::pot::Co
         let uni_can = UniversalCanister::new(&agent).await;
         let message_hash = [0xabu8; 32];
         let public_key = get_public_key(&uni_can, ctx).await;
+        // Signing is free on system subnets, like all other processing.
+        let signature = assert_cycles_cost_within(
+            &uni_can.canister_id(),
+            &agent,
+            CyclesCostEnvelope::new(Cycles::zero(), Cycles::zero()),
+            || get_signature(&message_hash, &uni_can, ctx),
+        )
+        .await;
+        verify_signature(&message_hash, &public_key, &signature);
+    });
+}
+
+/// Tests whether a signature returned by `sign_with_ecdsa` is accepted by
+/// Bitcoin and Ethereum, i.e., whether it verifies with respect to the result
+/// from `get_ecdsa_public_key` when encoded as on these chains.
+pub fn test_threshold_ecdsa_signature_on_external_chains(
+    handle: IcHandle,
+    ctx: &ic_fondue::pot::Context,
+) {
+    let rt = tokio::runtime::Runtime::new().expect("Could not create tokio runtime.");
+    let mut rng = ctx.rng.clone();
+
+    rt.block_on(async move {
+        let endpoint = get_random_node_endpoint(&handle, &mut rng);
+        endpoint.assert_ready(ctx).await;
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        let message_hash = [0xcdu8; 32];
+        let public_key = get_public_key(&uni_can, ctx).await;
+        let signature = get_signature(&message_hash, &uni_can, ctx).await;
+        verify_bitcoin_signature(&message_hash, &public_key, &signature)
+            .expect("Signature is not valid on Bitcoin");
+        verify_ethereum_signature(&message_hash, &public_key, &signature)
+            .expect("Signature is not valid on Ethereum");
+        let recovery_id = ecdsa_recovery_id(&message_hash, &public_key, &signature)
+            .expect("Failed to derive the recovery id");
+        info!(
+            ctx.logger,
+            "Signature is valid on Bitcoin and Ethereum with recovery id {:?}, address 0x{}",
+            recovery_id,
+            hex::encode(ethereum_address(&public_key))
+        );
+    });
+}
+
+/// Tests whether `sign_with_ecdsa` is only served by the subnets in the
+/// signing subnet list of the key, and rejected on all other subnets.
+pub fn test_threshold_ecdsa_signing_subnet_list(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
+    let rt = tokio::runtime::Runtime::new().expect("Could not create tokio runtime.");
+    let mut rng = ctx.rng.clone();
+
+    rt.block_on(async move {
+        let message_hash = [0xefu8; 32];
+
+        let endpoint = get_random_application_node_endpoint(&handle, &mut rng);
+        endpoint.assert_ready(ctx).await;
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        let res = uni_can
+            .sign_with_ecdsa(DEFAULT_KEY_ID, message_hash, 0)
+            .await;
+        assert_reject(res, RejectCode::CanisterReject);
+        info!(
+            ctx.logger,
+            "sign_with_ecdsa is rejected on the subnet that may not sign"
+        );
+
+        let endpoint = get_random_nns_node_endpoint(&handle, &mut rng);
+        endpoint.assert_ready(ctx).await;
+        let agent = assert_create_agent(endpoint.url.as_str()).await;
+        let uni_can = UniversalCanister::new(&agent).await;
+        let public_key = get_public_key(&uni_can, ctx).await;
         let signature = get_signature(&message_hash, &uni_can, ctx).await;
         verify_signature(&message_hash, &public_key, &signature);
     });
@@ -2,11 +2,19 @@ use crate::types::*;
 use candid::{Decode, Encode};
 use canister_test::{Canister, RemoteTestRuntime, Runtime, Wasm};
 use ic_agent::{
-    agent::http_transport::ReqwestHttpReplicaV2Transport, Agent, AgentError, Identity, RequestId,
+    agent::{http_transport::ReqwestHttpReplicaV2Transport, Replied, RequestStatusResponse},
+    Agent, AgentError, Identity, RequestId,
 };
 use ic_canister_client::{Agent as DeprecatedAgent, Sender};
+use ic_certified_vars::{verify_certificate, verify_read_state_certificate};
+use ic_crypto::threshold_sig_public_key_from_der;
+use ic_crypto_tree_hash::{lookup_path, LabeledTree, MixedHashTree, Path};
+use ic_ecdsa_api::{EcdsaApiCall, GetEcdsaPublicKey, SignWithEcdsa};
 use ic_fondue::ic_manager::{IcEndpoint, IcHandle};
-use ic_ic00_types::{CanisterStatusResult, EmptyBlob};
+use ic_ic00_types::{
+    CanisterHttpRequestArgs, CanisterHttpResponsePayload, CanisterStatusResult, EmptyBlob,
+    GetECDSAPublicKeyResponse, Method, Payload, SignWithECDSAReply,
+};
 use ic_nns_constants::{GOVERNANCE_CANISTER_ID, ROOT_CANISTER_ID};
 use slog::info;
 
@@ -15,7 +23,11 @@ use ic_agent::export::Principal;
 use ic_nns_test_utils::governance::upgrade_nns_canister_by_proposal;
 use ic_registry_subnet_type::SubnetType;
 use ic_rosetta_api::convert::to_arg;
-use ic_types::{ingress::MAX_INGRESS_TTL, CanisterId, Cycles, PrincipalId};
+use ic_types::messages::{HttpCallContent, HttpRequestEnvelope};
+use ic_types::{
+    crypto::threshold_sig::ThresholdSigPublicKey, ingress::MAX_INGRESS_TTL, CanisterId, Cycles,
+    PrincipalId,
+};
 use ic_universal_canister::wasm as universal_canister_argument_builder;
 use ic_universal_canister::{call_args, UNIVERSAL_CANISTER_WASM};
 use ic_utils::call::AsyncCall;
@@ -63,6 +75,7 @@ pub fn runtime_from_url(url: Url) -> Runtime {
 pub struct UniversalCanister<'a> {
     agent: &'a Agent,
     canister_id: Principal,
+    query_root_key: Option<ThresholdSigPublicKey>,
 }
 
 impl<'a> UniversalCanister<'a> {
@@ -99,7 +112,11 @@ impl<'a> UniversalCanister<'a> {
             .await
             .map_err(|err| format!("Couldn't install universal canister: {}", err))?;
 
-        Ok(Self { agent, canister_id })
+        Ok(Self {
+            agent,
+            canister_id,
+            query_root_key: None,
+        })
     }
 
     pub async fn new_with_cycles<C: Into<u64>>(
@@ -125,7 +142,11 @@ impl<'a> UniversalCanister<'a> {
             .await
             .unwrap_or_else(|err| panic!("Couldn't install universal canister: {}", err));
 
-        Self { agent, canister_id }
+        Self {
+            agent,
+            canister_id,
+            query_root_key: None,
+        }
     }
 
     pub async fn new_with_64bit_stable_memory(
@@ -152,13 +173,34 @@ impl<'a> UniversalCanister<'a> {
             .await
             .map_err(|err| format!("Couldn't install universal canister: {}", err))?;
 
-        Ok(Self { agent, canister_id })
+        Ok(Self {
+            agent,
+            canister_id,
+            query_root_key: None,
+        })
     }
 
     /// Initializes a universal canister wrapper from a canister id. Does /NOT/
     /// perform any installation operation on the runtime.
     pub fn from_canister_id(agent: &'a Agent, canister_id: Principal) -> UniversalCanister<'a> {
-        Self { agent, canister_id }
+        Self {
+            agent,
+            canister_id,
+            query_root_key: None,
+        }
+    }
+
+    /// Makes [UniversalCanister::query] require certified responses.
+    ///
+    /// The queries are still sent to the query endpoint, but the canister
+    /// prepends the certificate of its certified data, which the replica
+    /// provides to non-replicated queries, to each reply. The certificate is
+    /// verified against `root_key`, e.g. the one of [root_public_key], and
+    /// removed from the reply. The replies themselves are not signed by the
+    /// replicas, so only the certificate can be verified.
+    pub fn with_certified_queries(mut self, root_key: ThresholdSigPublicKey) -> Self {
+        self.query_root_key = Some(root_key);
+        self
     }
 
     /// Upgrades an NNS canister to universal abilities (by proposal),
@@ -224,17 +266,14 @@ impl<'a> UniversalCanister<'a> {
 
     /// Tries to read `len` bytes of the stable memory, starting from `offset`.
     pub async fn read_stable(&self, offset: u32, len: u32) -> Result<Vec<u8>, AgentError> {
-        self.agent
-            .query(&self.canister_id, "query")
-            .with_arg(
-                universal_canister_argument_builder()
-                    .stable_read(offset, len)
-                    .reply_data_append()
-                    .reply()
-                    .build(),
-            )
-            .call()
-            .await
+        self.query(
+            universal_canister_argument_builder()
+                .stable_read(offset, len)
+                .reply_data_append()
+                .reply()
+                .build(),
+        )
+        .await
     }
 
     /// Tries to read `len` bytes of the stable memory, starting from `offset`.
@@ -255,23 +294,30 @@ impl<'a> UniversalCanister<'a> {
         payload: Vec<u8>,
         cycles: u64,
     ) -> Result<Vec<u8>, AgentError> {
-        let universal_canister_payload = universal_canister_argument_builder()
-            .call_with_cycles(
-                // The universal canister API expects a `PrincipalId`.
-                PrincipalId::try_from(receiver.as_slice()).unwrap(),
-                method,
-                call_args().other_side(payload),
-                cycles,
-            )
-            .build();
-
         self.agent
             .update(&self.canister_id, "update")
-            .with_arg(universal_canister_payload)
+            .with_arg(forward_payload(receiver, method, payload, cycles))
             .call_and_wait(delay())
             .await
     }
 
+    /// Like [UniversalCanister::forward_with_cycles_to], but returns as soon
+    /// as the ingress message is submitted, without waiting for the result.
+    /// The returned request id can be used to poll the status of the message.
+    pub async fn submit_forward_with_cycles_to(
+        &self,
+        receiver: &Principal,
+        method: &str,
+        payload: Vec<u8>,
+        cycles: u64,
+    ) -> Result<RequestId, AgentError> {
+        self.agent
+            .update(&self.canister_id, "update")
+            .with_arg(forward_payload(receiver, method, payload, cycles))
+            .call()
+            .await
+    }
+
     /// Forwards a message to the `receiver` that calls
     /// `receiver.method(payload)` and returns the result.
     pub async fn forward_to(
@@ -284,16 +330,141 @@ impl<'a> UniversalCanister<'a> {
             .await
     }
 
+    /// Makes `call` to the threshold ECDSA API of the management canister,
+    /// attaching the cycles of the call, and decodes the reply.
+    pub async fn call_ecdsa_api<C: EcdsaApiCall>(&self, call: &C) -> Result<C::Reply, AgentError> {
+        let reply = self
+            .forward_with_cycles_to(
+                &Principal::management_canister(),
+                &call.method_name(),
+                call.payload(),
+                call.cycles(),
+            )
+            .await?;
+        Ok(call
+            .decode_reply(&reply)
+            .unwrap_or_else(|err| panic!("{}", err)))
+    }
+
+    /// Submits `call` to the threshold ECDSA API of the management canister,
+    /// attaching the cycles of the call, without waiting for the reply.
+    pub async fn submit_ecdsa_api_call<C: EcdsaApiCall>(
+        &self,
+        call: &C,
+    ) -> Result<RequestId, AgentError> {
+        self.submit_forward_with_cycles_to(
+            &Principal::management_canister(),
+            &call.method_name(),
+            call.payload(),
+            call.cycles(),
+        )
+        .await
+    }
+
+    /// Asks the management canister to sign `message_hash` with the ECDSA key
+    /// `key_id` of this canister, attaching `cycles` to the call.
+    pub async fn sign_with_ecdsa(
+        &self,
+        key_id: &str,
+        message_hash: [u8; 32],
+        cycles: u64,
+    ) -> Result<SignWithECDSAReply, AgentError> {
+        self.call_ecdsa_api(
+            &SignWithEcdsa::new(message_hash)
+                .with_key_id(key_id)
+                .with_cycles(cycles),
+        )
+        .await
+    }
+
+    /// Asks the management canister for the public key of the ECDSA key
+    /// `key_id` of this canister.
+    pub async fn get_ecdsa_public_key(
+        &self,
+        key_id: &str,
+    ) -> Result<GetECDSAPublicKeyResponse, AgentError> {
+        self.call_ecdsa_api(&GetEcdsaPublicKey::new().with_key_id(key_id))
+            .await
+    }
+
+    /// Makes the canister http request `request` through the management
+    /// canister, attaching `cycles` to the call.
+    pub async fn http_request(
+        &self,
+        request: CanisterHttpRequestArgs,
+        cycles: u64,
+    ) -> Result<CanisterHttpResponsePayload, AgentError> {
+        let reply = self
+            .forward_with_cycles_to(
+                &Principal::management_canister(),
+                &Method::HttpRequest.to_string(),
+                request.encode(),
+                cycles,
+            )
+            .await?;
+        Ok(CanisterHttpResponsePayload::decode(&reply)
+            .unwrap_or_else(|err| panic!("failed to decode the reply to http_request: {}", err)))
+    }
+
     pub fn canister_id(&self) -> Principal {
         self.canister_id
     }
 
     pub async fn query<P: Into<Vec<u8>>>(&self, payload: P) -> Result<Vec<u8>, AgentError> {
-        self.agent
+        let root_key = match &self.query_root_key {
+            Some(root_key) => root_key,
+            None => {
+                return self
+                    .agent
+                    .query(&self.canister_id, "query")
+                    .with_arg(payload.into())
+                    .call()
+                    .await
+            }
+        };
+        let mut program = universal_canister_argument_builder()
+            .data_certificate()
+            .reply_data_append()
+            .build();
+        program.extend(payload.into());
+        let reply = self
+            .agent
             .query(&self.canister_id, "query")
-            .with_arg(payload.into())
+            .with_arg(program)
             .call()
-            .await
+            .await?;
+        Ok(self.verify_query_certificate(&reply, root_key))
+    }
+
+    /// Verifies the certificate at the start of the `reply` to a certified
+    /// query and returns the rest of the reply.
+    ///
+    /// # Panics
+    ///
+    /// * If the certificate is malformed, not signed on behalf of `root_key`
+    ///   or does not certify the certified data of the canister.
+    fn verify_query_certificate(&self, reply: &[u8], root_key: &ThresholdSigPublicKey) -> Vec<u8> {
+        // The certificate is a single CBOR value, so it ends where the first
+        // value of the reply ends.
+        let mut values =
+            serde_cbor::Deserializer::from_slice(reply).into_iter::<serde_cbor::Value>();
+        values
+            .next()
+            .expect("the reply to a certified query has no certificate")
+            .unwrap_or_else(|err| panic!("malformed certificate of a certified query: {}", err));
+        let (certificate, reply) = reply.split_at(values.byte_offset());
+
+        let canister_id = CanisterId::new(to_principal_id(&self.canister_id)).unwrap();
+        let tree = verify_read_state_certificate(certificate, &canister_id, root_key)
+            .unwrap_or_else(|err| panic!("invalid certificate of a certified query: {}", err));
+        let path = state_tree_paths::canister_certified_data(&canister_id);
+        let labels: Vec<&[u8]> = path.iter().map(|label| label.as_bytes()).collect();
+        assert!(
+            certified_leaf(&tree, &labels).is_some(),
+            "the certificate of a certified query does not certify the data of canister {}",
+            canister_id
+        );
+        reply.to_vec()
     }
 
     pub async fn update<P: Into<Vec<u8>>>(&self, payload: P) -> Result<Vec<u8>, AgentError> {
@@ -344,6 +515,165 @@ pub async fn agent_with_identity(
     Ok(a)
 }
 
+/// Paths of the state tree that can be read with a [StateTreeReader].
+pub mod state_tree_paths {
+    use ic_crypto_tree_hash::{Label, Path};
+    use ic_types::{messages::MessageId, CanisterId, SubnetId};
+
+    /// The path of the time of the state.
+    pub fn time() -> Path {
+        Path::new(vec![Label::from("time")])
+    }
+
+    /// The path of the certified data of `canister_id`.
+    pub fn canister_certified_data(canister_id: &CanisterId) -> Path {
+        canister_path(canister_id, "certified_data")
+    }
+
+    /// The path of the hash of the module installed on `canister_id`.
+    pub fn canister_module_hash(canister_id: &CanisterId) -> Path {
+        canister_path(canister_id, "module_hash")
+    }
+
+    /// The path of the CBOR-encoded controllers of `canister_id`.
+    pub fn canister_controllers(canister_id: &CanisterId) -> Path {
+        canister_path(canister_id, "controllers")
+    }
+
+    /// The path of the status of the request `request_id`, including its reply
+    /// or reject. Only the sender of the request may read it.
+    pub fn request_status(request_id: &MessageId) -> Path {
+        Path::new(vec![
+            Label::from("request_status"),
+            Label::from(request_id.as_bytes().to_vec()),
+        ])
+    }
+
+    /// The path of the DER-encoded public key of `subnet_id`.
+    pub fn subnet_public_key(subnet_id: &SubnetId) -> Path {
+        Path::new(vec![
+            Label::from("subnet"),
+            Label::from(subnet_id.get_ref().to_vec()),
+            Label::from("public_key"),
+        ])
+    }
+
+    fn canister_path(canister_id: &CanisterId, field: &str) -> Path {
+        Path::new(vec![
+            Label::from("canister"),
+            Label::from(canister_id.get_ref().to_vec()),
+            Label::from(field),
+        ])
+    }
+}
+
+/// Reads subtrees of the state tree of a node with `read_state` requests,
+/// and verifies the certificates of the responses against the root key of
+/// the IC, so that tests fail if the node certifies its state incorrectly.
+pub struct StateTreeReader {
+    agent: DeprecatedAgent,
+    root_key: ThresholdSigPublicKey,
+}
+
+impl StateTreeReader {
+    /// Creates a reader of the state tree of the node at `url`. The root key
+    /// is fetched from the status endpoint of the node, as testnets do not use
+    /// the root key of the mainnet.
+    pub async fn new(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        let agent = DeprecatedAgent::new(
+            url,
+            Sender::from_keypair(&ic_test_identity::TEST_IDENTITY_KEYPAIR),
+        );
+        let root_key = agent
+            .root_key()
+            .await?
+            .ok_or_else(|| "The node did not report a root key".to_string())?;
+        let root_key = threshold_sig_public_key_from_der(&root_key.0)
+            .map_err(|e| format!("Invalid root key: {}", e))?;
+        Ok(Self { agent, root_key })
+    }
+
+    /// Reads the given `paths` through the subnet of `canister_id`, and
+    /// returns the verified subtree that contains them.
+    pub async fn read(
+        &self,
+        canister_id: &CanisterId,
+        paths: &[Path],
+    ) -> Result<LabeledTree<Vec<u8>>, String> {
+        let certificate = self.agent.read_state(canister_id, paths).await?;
+        verify_read_state_certificate(&certificate.0, canister_id, &self.root_key)
+            .map_err(|e| format!("Invalid certificate: {}", e))
+    }
+
+    /// Reads the leaf at `path` through the subnet of `canister_id`. Returns
+    /// `None` if the path is absent from the state tree.
+    pub async fn read_leaf(
+        &self,
+        canister_id: &CanisterId,
+        path: Path,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let labels: Vec<&[u8]> = path.iter().map(|label| label.as_bytes()).collect();
+        let tree = self.read(canister_id, &[path.clone()]).await?;
+        match lookup_path(&tree, &labels) {
+            Some(LabeledTree::Leaf(value)) => Ok(Some(value.clone())),
+            Some(LabeledTree::SubTree(_)) => Err(format!("{} is not a leaf", path)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns the root public key of the Internet Computer of `handle`, against
+/// which the certificates of all its subnets verify.
+///
+/// # Panics
+///
+/// * If the handle has no ic-prep working directory or the key can not be read.
+pub fn root_public_key(handle: &IcHandle) -> ThresholdSigPublicKey {
+    let pk_bytes = handle
+        .ic_prep_working_dir
+        .as_ref()
+        .expect("ic_prep_working_dir is not set!")
+        .root_public_key()
+        .expect("failed to read threshold sig PK bytes");
+    threshold_sig_public_key_from_der(&pk_bytes[..]).expect("failed to decode threshold sig PK")
+}
+
+/// Verifies a certified response of `canister_id` as an end user would: the
+/// CBOR-encoded `hash_tree` must hash to the certified data of the canister
+/// in `certificate`, which must be signed by the subnet of the canister on
+/// behalf of `root_key`. Returns the values that the tree certifies; its
+/// pruned parts are omitted.
+///
+/// This is how e.g. the responses of the asset canister or of Internet
+/// Identity, which carry the result of `ic0.data_certificate`, are checked.
+pub fn verify_certified_tree(
+    certificate: &[u8],
+    hash_tree: &[u8],
+    canister_id: &CanisterId,
+    root_key: &ThresholdSigPublicKey,
+) -> Result<LabeledTree<Vec<u8>>, String> {
+    let hash_tree: MixedHashTree =
+        serde_cbor::from_slice(hash_tree).map_err(|e| format!("Invalid hash tree: {}", e))?;
+    verify_certificate(
+        certificate,
+        canister_id,
+        root_key,
+        hash_tree.digest().as_bytes(),
+    )
+    .map_err(|e| format!("Invalid certificate: {}", e))?;
+    LabeledTree::try_from(hash_tree).map_err(|e| format!("Malformed hash tree: {:?}", e))
+}
+
+/// Returns the leaf at `path` of a tree returned by [verify_certified_tree]
+/// or [StateTreeReader::read], or `None` if the path is absent.
+pub fn certified_leaf<'a>(tree: &'a LabeledTree<Vec<u8>>, path: &[&[u8]]) -> Option<&'a [u8]> {
+    match lookup_path(tree, path) {
+        Some(LabeledTree::Leaf(value)) => Some(value),
+        _ => None,
+    }
+}
+
 // Creates an identity to be used with `Agent`.
 pub fn random_ed25519_identity() -> impl Identity {
     let rng = ring::rand::SystemRandom::new();
@@ -523,6 +853,21 @@ pub(crate) fn assert_reject<T: std::fmt::Debug>(res: Result<T, AgentError>, code
     }
 }
 
+/// The payload instructing the universal canister to call
+/// `receiver.method(payload)` with `cycles` attached and to reply with the
+/// result.
+fn forward_payload(receiver: &Principal, method: &str, payload: Vec<u8>, cycles: u64) -> Vec<u8> {
+    universal_canister_argument_builder()
+        .call_with_cycles(
+            // The universal canister API expects a `PrincipalId`.
+            PrincipalId::try_from(receiver.as_slice()).unwrap(),
+            method,
+            call_args().other_side(payload),
+            cycles,
+        )
+        .build()
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum EndpointsStatus {
     AllReachable,
@@ -576,6 +921,115 @@ pub(crate) fn assert_http_submit_fails(
     }
 }
 
+/// An ingress message whose envelope is signed and serialized, ready to be
+/// submitted to the `call` endpoint of a node.
+#[derive(Clone, Debug)]
+pub struct SignedIngress {
+    pub canister_id: Principal,
+    pub request_id: RequestId,
+    pub envelope: Vec<u8>,
+}
+
+impl SignedIngress {
+    pub fn new(envelope: &HttpRequestEnvelope<HttpCallContent>) -> Self {
+        let HttpCallContent::Call { update } = &envelope.content;
+        Self {
+            canister_id: Principal::from_slice(&update.canister_id.0),
+            request_id: RequestId::new(&envelope.content.representation_independent_hash()),
+            envelope: serde_cbor::ser::to_vec(envelope).expect("failed to serialize envelope"),
+        }
+    }
+}
+
+/// The terminal status of an ingress message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngressOutcome {
+    Replied(Vec<u8>),
+    Rejected {
+        reject_code: u64,
+        reject_message: String,
+    },
+    /// The status of the ingress message was already pruned, so the reply
+    /// or reject is no longer known.
+    Done,
+}
+
+/// Submits `ingress` to `node` and polls the status of the request until it
+/// reaches a terminal status or `timeout` elapses. The status is read from
+/// `read_state` responses whose certificates are verified by the agent
+/// against the root key of the IC, so a malicious node can not forge the
+/// outcome.
+pub async fn submit_and_await_ingress(
+    node: &IcEndpoint,
+    ingress: &SignedIngress,
+    timeout: Duration,
+) -> anyhow::Result<IngressOutcome> {
+    let started_at = Instant::now();
+    let res = reqwest::Client::new()
+        .post(
+            node.url
+                .join(&format!("api/v2/canister/{}/call", ingress.canister_id))?,
+        )
+        .header("Content-Type", "application/cbor")
+        .body(ingress.envelope.clone())
+        .send()
+        .await?;
+    if res.status() != reqwest::StatusCode::ACCEPTED {
+        anyhow::bail!(
+            "Submitting ingress {:?} failed with status {}: {}",
+            ingress.request_id,
+            res.status(),
+            res.text().await.unwrap_or_default()
+        );
+    }
+    let timeout = timeout
+        .checked_sub(started_at.elapsed())
+        .unwrap_or_default();
+    await_ingress_outcome(node, ingress.canister_id, &ingress.request_id, timeout).await
+}
+
+/// Polls the status of the ingress message `request_id` to `canister_id` on
+/// `node` until it reaches a terminal status or `timeout` elapses. As for
+/// [submit_and_await_ingress], the status is read from certified `read_state`
+/// responses.
+pub async fn await_ingress_outcome(
+    node: &IcEndpoint,
+    canister_id: Principal,
+    request_id: &RequestId,
+    timeout: Duration,
+) -> anyhow::Result<IngressOutcome> {
+    let started_at = Instant::now();
+    let agent = create_agent(node.url.as_str()).await?;
+    loop {
+        match agent.request_status_raw(request_id, canister_id).await? {
+            RequestStatusResponse::Replied {
+                reply: Replied::CallReplied(reply),
+            } => return Ok(IngressOutcome::Replied(reply)),
+            RequestStatusResponse::Rejected {
+                reject_code,
+                reject_message,
+            } => {
+                return Ok(IngressOutcome::Rejected {
+                    reject_code,
+                    reject_message,
+                })
+            }
+            RequestStatusResponse::Done => return Ok(IngressOutcome::Done),
+            RequestStatusResponse::Unknown
+            | RequestStatusResponse::Received
+            | RequestStatusResponse::Processing => (),
+        }
+        if started_at.elapsed() > timeout {
+            anyhow::bail!(
+                "Ingress {:?} did not complete within {:?}",
+                request_id,
+                timeout
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
 pub(crate) async fn create_and_install(agent: &Agent, canister_wasm: &[u8]) -> Principal {
     // Initialize the canister with a healthy amount of cycles.
     create_and_install_with_cycles(agent, canister_wasm, CYCLES_LIMIT_PER_CANISTER).await
@@ -627,6 +1081,98 @@ pub(crate) async fn get_balance(canister_id: &Principal, agent: &Agent) -> u64 {
     u64::try_from(canister_status.cycles.0).unwrap()
 }
 
+/// The range of cycles that an operation is expected to cost, including both
+/// bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclesCostEnvelope {
+    pub min: Cycles,
+    pub max: Cycles,
+}
+
+impl CyclesCostEnvelope {
+    pub fn new(min: Cycles, max: Cycles) -> Self {
+        assert!(min <= max, "invalid cost envelope [{}, {}]", min, max);
+        Self { min, max }
+    }
+
+    /// An envelope of `expected` cycles, give or take `tolerance`.
+    pub fn around(expected: Cycles, tolerance: Cycles) -> Self {
+        Self::new(expected - tolerance, expected + tolerance)
+    }
+
+    pub fn contains(&self, cost: Cycles) -> bool {
+        self.min <= cost && cost <= self.max
+    }
+}
+
+/// The cycle balance of a canister at some point in time, as reported by the
+/// `canister_status` method of the management canister.
+///
+/// Note that the balance of a canister also decreases due to charges that
+/// are not caused by the operation under test, e.g. for storage and for the
+/// `canister_status` calls themselves. Cost envelopes must be wide enough to
+/// tolerate them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclesSnapshot {
+    pub canister_id: Principal,
+    pub balance: Cycles,
+}
+
+impl CyclesSnapshot {
+    /// Takes a snapshot of the balance of `canister_id`, which must be
+    /// controlled by the identity of `agent`.
+    pub async fn take(canister_id: &Principal, agent: &Agent) -> Self {
+        Self {
+            canister_id: *canister_id,
+            balance: Cycles::from(get_balance(canister_id, agent).await),
+        }
+    }
+
+    /// Returns the cycles spent by the canister since the snapshot was taken.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the balance increased in the meantime, since the cycles that
+    /// were spent can then not be told apart from the cycles that were added.
+    pub async fn cycles_spent_since(&self, agent: &Agent) -> Cycles {
+        let now = Self::take(&self.canister_id, agent).await;
+        assert!(
+            now.balance <= self.balance,
+            "the balance of canister {} increased from {} to {}",
+            self.canister_id,
+            self.balance,
+            now.balance
+        );
+        self.balance - now.balance
+    }
+}
+
+/// Runs `operation` and asserts that the cycles it cost `canister_id` are
+/// within `envelope`. Returns the output of `operation`.
+pub async fn assert_cycles_cost_within<F, Fut, T>(
+    canister_id: &Principal,
+    agent: &Agent,
+    envelope: CyclesCostEnvelope,
+    operation: F,
+) -> T
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let before = CyclesSnapshot::take(canister_id, agent).await;
+    let output = operation().await;
+    let cost = before.cycles_spent_since(agent).await;
+    assert!(
+        envelope.contains(cost),
+        "the operation cost canister {} {} cycles, expected between {} and {}",
+        canister_id,
+        cost,
+        envelope.min,
+        envelope.max
+    );
+    output
+}
+
 pub(crate) async fn set_controller(
     controllee: &Principal,
     controller: &Principal,
@@ -794,3 +1340,331 @@ pub(crate) fn escape_for_wat(id: &Principal) -> String {
         res
     })
 }
+
+/// The sighash type that is appended to the DER encoding of a signature in a
+/// Bitcoin transaction input, SIGHASH_ALL.
+pub const BITCOIN_SIGHASH_ALL: u8 = 0x01;
+
+/// Checks that `sig` is encoded as required by BIP-66, i.e., a strict DER
+/// encoding of the signature followed by the one-byte sighash type.
+///
+/// This is a port of `IsValidSignatureEncoding` of Bitcoin Core.
+pub fn is_valid_bitcoin_signature_encoding(sig: &[u8]) -> bool {
+    // Minimum and maximum size constraints.
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    // A signature is of type 0x30 (compound) and its length covers the
+    // entire signature, apart from the sighash type.
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+    // The length of the S element must still be inside the signature, and
+    // the lengths of the elements must add up to the length of the signature.
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+    // R and S are non-empty, non-negative integers without excessive padding.
+    if sig[2] != 0x02
+        || len_r == 0
+        || sig[4] & 0x80 != 0
+        || (len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0)
+    {
+        return false;
+    }
+    if sig[len_r + 4] != 0x02
+        || len_s == 0
+        || sig[len_r + 6] & 0x80 != 0
+        || (len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0)
+    {
+        return false;
+    }
+    true
+}
+
+/// Returns whether the S value of `signature` is in the lower half of the
+/// curve order, as required by Bitcoin (BIP-146) and Ethereum (EIP-2).
+pub fn is_low_s(signature: &secp256k1::Signature) -> bool {
+    let mut normalized = *signature;
+    normalized.normalize_s();
+    normalized == *signature
+}
+
+/// Encodes `signature` as it appears in a Bitcoin transaction input, i.e., as
+/// DER followed by the SIGHASH_ALL sighash type.
+pub fn bitcoin_signature_encoding(signature: &secp256k1::Signature) -> Vec<u8> {
+    let mut encoding = signature.serialize_der().to_vec();
+    encoding.push(BITCOIN_SIGHASH_ALL);
+    encoding
+}
+
+/// Verifies `signature` with the rules that Bitcoin applies to signatures of
+/// transaction inputs: the BIP-66 encoding must be valid, S must be low, and
+/// the signature parsed from the encoding must be valid for `message_hash`
+/// and `public_key`.
+pub fn verify_bitcoin_signature(
+    message_hash: &[u8],
+    public_key: &secp256k1::PublicKey,
+    signature: &secp256k1::Signature,
+) -> Result<(), String> {
+    let encoding = bitcoin_signature_encoding(signature);
+    if !is_valid_bitcoin_signature_encoding(&encoding) {
+        return Err(format!(
+            "Invalid BIP-66 encoding {}",
+            hex::encode(&encoding)
+        ));
+    }
+    if !is_low_s(signature) {
+        return Err("Signature does not have a low S value".to_string());
+    }
+    let parsed = secp256k1::Signature::from_der(&encoding[..encoding.len() - 1])
+        .map_err(|e| format!("Failed to parse DER signature: {}", e))?;
+    let message = secp256k1::Message::from_slice(message_hash)
+        .map_err(|e| format!("Invalid message hash: {}", e))?;
+    secp256k1::Secp256k1::verification_only()
+        .verify(&message, &parsed, public_key)
+        .map_err(|e| format!("Signature verification failed: {}", e))
+}
+
+/// Derives the recovery id of `signature`, i.e., the index of `public_key`
+/// among the public keys that can be recovered from `signature` and
+/// `message_hash`.
+pub fn ecdsa_recovery_id(
+    message_hash: &[u8],
+    public_key: &secp256k1::PublicKey,
+    signature: &secp256k1::Signature,
+) -> Result<secp256k1::recovery::RecoveryId, String> {
+    let secp = secp256k1::Secp256k1::verification_only();
+    let message = secp256k1::Message::from_slice(message_hash)
+        .map_err(|e| format!("Invalid message hash: {}", e))?;
+    let compact = signature.serialize_compact();
+    for id in 0..4 {
+        let recovery_id =
+            secp256k1::recovery::RecoveryId::from_i32(id).expect("recovery ids are 0 to 3");
+        let recoverable =
+            secp256k1::recovery::RecoverableSignature::from_compact(&compact, recovery_id)
+                .map_err(|e| format!("Invalid signature: {}", e))?;
+        if secp.recover(&message, &recoverable).as_ref() == Ok(public_key) {
+            return Ok(recovery_id);
+        }
+    }
+    Err("The public key can not be recovered from the signature".to_string())
+}
+
+/// Computes the `v` value of an Ethereum signature with the given recovery
+/// id, as defined in EIP-155 if a `chain_id` is given and as in the legacy
+/// format otherwise.
+pub fn ethereum_v(recovery_id: secp256k1::recovery::RecoveryId, chain_id: Option<u64>) -> u64 {
+    let recovery_id = recovery_id.to_i32() as u64;
+    match chain_id {
+        Some(chain_id) => chain_id * 2 + 35 + recovery_id,
+        None => 27 + recovery_id,
+    }
+}
+
+/// Computes the Ethereum address of `public_key`, i.e., the last 20 bytes of
+/// the Keccak-256 hash of the uncompressed key without its prefix.
+pub fn ethereum_address(public_key: &secp256k1::PublicKey) -> [u8; 20] {
+    use sha3::{Digest, Keccak256};
+    let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Encodes `signature` as a recoverable Ethereum signature `r || s || v` in
+/// the legacy format, i.e., with `v` being 27 or 28.
+pub fn ethereum_signature_encoding(
+    message_hash: &[u8],
+    public_key: &secp256k1::PublicKey,
+    signature: &secp256k1::Signature,
+) -> Result<[u8; 65], String> {
+    let recovery_id = ecdsa_recovery_id(message_hash, public_key, signature)?;
+    let mut encoding = [0u8; 65];
+    encoding[..64].copy_from_slice(&signature.serialize_compact());
+    encoding[64] = ethereum_v(recovery_id, None) as u8;
+    Ok(encoding)
+}
+
+/// Verifies `signature` the way Ethereum does: S must be low, and the
+/// address recovered from the encoding `r || s || v` and `message_hash` must
+/// be the address of `public_key`.
+pub fn verify_ethereum_signature(
+    message_hash: &[u8],
+    public_key: &secp256k1::PublicKey,
+    signature: &secp256k1::Signature,
+) -> Result<(), String> {
+    if !is_low_s(signature) {
+        return Err("Signature does not have a low S value".to_string());
+    }
+    let encoding = ethereum_signature_encoding(message_hash, public_key, signature)?;
+    let recovery_id = secp256k1::recovery::RecoveryId::from_i32(encoding[64] as i32 - 27)
+        .map_err(|e| format!("Invalid v value {}: {}", encoding[64], e))?;
+    let recoverable =
+        secp256k1::recovery::RecoverableSignature::from_compact(&encoding[..64], recovery_id)
+            .map_err(|e| format!("Invalid signature: {}", e))?;
+    let message = secp256k1::Message::from_slice(message_hash)
+        .map_err(|e| format!("Invalid message hash: {}", e))?;
+    let recovered = secp256k1::Secp256k1::verification_only()
+        .recover(&message, &recoverable)
+        .map_err(|e| format!("Public key recovery failed: {}", e))?;
+    if ethereum_address(&recovered) != ethereum_address(public_key) {
+        return Err(format!(
+            "Recovered address 0x{} instead of 0x{}",
+            hex::encode(ethereum_address(&recovered)),
+            hex::encode(ethereum_address(public_key))
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The order of the secp256k1 curve.
+    const CURVE_ORDER: [u8; 32] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ];
+
+    fn key_pair(secret: u8) -> (secp256k1::SecretKey, secp256k1::PublicKey) {
+        let secret_key = secp256k1::SecretKey::from_slice(&[secret; 32]).unwrap();
+        let public_key =
+            secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &secret_key);
+        (secret_key, public_key)
+    }
+
+    fn sign(message_hash: &[u8], secret_key: &secp256k1::SecretKey) -> secp256k1::Signature {
+        let message = secp256k1::Message::from_slice(message_hash).unwrap();
+        secp256k1::Secp256k1::new().sign(&message, secret_key)
+    }
+
+    /// Returns the signature with S replaced by `n - S`, which is equally
+    /// valid for ECDSA but not accepted by Bitcoin and Ethereum.
+    fn with_high_s(signature: &secp256k1::Signature) -> secp256k1::Signature {
+        let mut compact = signature.serialize_compact();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = CURVE_ORDER[i] as i16 - compact[32 + i] as i16 - borrow;
+            borrow = if diff < 0 { 1 } else { 0 };
+            compact[32 + i] = (diff + 256 * borrow) as u8;
+        }
+        secp256k1::Signature::from_compact(&compact).unwrap()
+    }
+
+    #[test]
+    fn should_accept_signatures_valid_on_bitcoin() {
+        let (secret_key, public_key) = key_pair(0x42);
+        let message_hash = [0xcd; 32];
+        let signature = sign(&message_hash, &secret_key);
+
+        assert!(is_low_s(&signature));
+        assert!(is_valid_bitcoin_signature_encoding(
+            &bitcoin_signature_encoding(&signature)
+        ));
+        assert_eq!(
+            verify_bitcoin_signature(&message_hash, &public_key, &signature),
+            Ok(())
+        );
+        assert!(verify_bitcoin_signature(&[0xce; 32], &public_key, &signature).is_err());
+    }
+
+    #[test]
+    fn should_reject_invalid_bitcoin_signature_encodings() {
+        let (secret_key, _) = key_pair(0x42);
+        let encoding = bitcoin_signature_encoding(&sign(&[0xcd; 32], &secret_key));
+
+        // Without the sighash type, the length of the signature is off.
+        assert!(!is_valid_bitcoin_signature_encoding(
+            &encoding[..encoding.len() - 1]
+        ));
+        let mut not_compound = encoding.clone();
+        not_compound[0] = 0x31;
+        assert!(!is_valid_bitcoin_signature_encoding(&not_compound));
+        // R padded with a superfluous zero byte.
+        let len_r = encoding[3] as usize;
+        let mut padded = vec![0x30, encoding[1] + 1, 0x02, len_r as u8 + 1, 0x00];
+        padded.extend_from_slice(&encoding[4..]);
+        assert!(!is_valid_bitcoin_signature_encoding(&padded));
+        assert!(!is_valid_bitcoin_signature_encoding(&[0x30; 8]));
+    }
+
+    #[test]
+    fn should_reject_high_s_signatures() {
+        let (secret_key, public_key) = key_pair(0x42);
+        let message_hash = [0xcd; 32];
+        let high_s = with_high_s(&sign(&message_hash, &secret_key));
+
+        assert!(!is_low_s(&high_s));
+        assert!(verify_bitcoin_signature(&message_hash, &public_key, &high_s).is_err());
+        assert!(verify_ethereum_signature(&message_hash, &public_key, &high_s).is_err());
+    }
+
+    #[test]
+    fn should_derive_recovery_id_of_signature() {
+        let (secret_key, public_key) = key_pair(0x42);
+        let message_hash = [0xcd; 32];
+        let message = secp256k1::Message::from_slice(&message_hash).unwrap();
+        let (expected_recovery_id, compact) = secp256k1::Secp256k1::new()
+            .sign_recoverable(&message, &secret_key)
+            .serialize_compact();
+        let signature = secp256k1::Signature::from_compact(&compact).unwrap();
+
+        assert_eq!(
+            ecdsa_recovery_id(&message_hash, &public_key, &signature),
+            Ok(expected_recovery_id)
+        );
+        let (_, other_public_key) = key_pair(0x43);
+        assert!(ecdsa_recovery_id(&message_hash, &other_public_key, &signature).is_err());
+    }
+
+    #[test]
+    fn should_accept_signatures_valid_on_ethereum() {
+        let (secret_key, public_key) = key_pair(0x42);
+        let message_hash = [0xcd; 32];
+        let signature = sign(&message_hash, &secret_key);
+
+        let encoding = ethereum_signature_encoding(&message_hash, &public_key, &signature).unwrap();
+        assert_eq!(encoding[..64], signature.serialize_compact()[..]);
+        assert!(encoding[64] == 27 || encoding[64] == 28);
+        assert_eq!(
+            verify_ethereum_signature(&message_hash, &public_key, &signature),
+            Ok(())
+        );
+        let (_, other_public_key) = key_pair(0x43);
+        assert!(verify_ethereum_signature(&message_hash, &other_public_key, &signature).is_err());
+    }
+
+    #[test]
+    fn should_compute_ethereum_v() {
+        let recovery_id = |id| secp256k1::recovery::RecoveryId::from_i32(id).unwrap();
+
+        assert_eq!(ethereum_v(recovery_id(0), None), 27);
+        assert_eq!(ethereum_v(recovery_id(1), None), 28);
+        // EIP-155 values of the Ethereum mainnet, chain id 1.
+        assert_eq!(ethereum_v(recovery_id(0), Some(1)), 37);
+        assert_eq!(ethereum_v(recovery_id(1), Some(1)), 38);
+    }
+
+    #[test]
+    fn should_compute_ethereum_address() {
+        // The well-known address of the secret key 1.
+        let mut secret = [0u8; 32];
+        secret[31] = 1;
+        let secret_key = secp256k1::SecretKey::from_slice(&secret).unwrap();
+        let public_key =
+            secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &secret_key);
+
+        assert_eq!(
+            hex::encode(ethereum_address(&public_key)),
+            "7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+    }
+}
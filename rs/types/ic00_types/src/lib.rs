@@ -25,12 +25,15 @@ pub enum Method {
     DeleteCanister,
     DepositCycles,
     HttpRequest,
+    HttpRequestDivergences,
     GetECDSAPublicKey,
     InstallCode,
     RawRand,
+    ReserveECDSAPreSignatures,
     SetController,
     SetupInitialDKG,
     SignWithECDSA,
+    SignWithECDSABatch,
     StartCanister,
     StopCanister,
     UninstallCode,
@@ -473,6 +476,7 @@ impl Payload<'_> for SetControllerArgs {}
 //     method : variant { get };
 //     body : opt blob;
 //     transform : opt variant { function: func (http_response) -> (http_response) query };
+//     max_response_bytes : opt nat64;
 //   })`
 #[derive(CandidType, Deserialize, Debug)]
 pub struct CanisterHttpRequestArgs {
@@ -480,10 +484,74 @@ pub struct CanisterHttpRequestArgs {
     pub body: Option<Vec<u8>>,
     pub http_method: HttpMethodType,
     pub transform_method_name: Option<String>,
+    /// The largest response, in bytes, that the caller expects and pays for.
+    /// Defaults to the limit of the subnet.
+    pub max_response_bytes: Option<u64>,
 }
 
 impl Payload<'_> for CanisterHttpRequestArgs {}
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     name : text;
+///     value : text;
+/// })`
+#[derive(CandidType, Clone, Deserialize, Debug, PartialEq, Eq)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Struct used for encoding/decoding the reply of `http_request`
+/// `(record {
+///     status : nat64;
+///     headers : vec http_header;
+///     body : blob;
+/// })`
+#[derive(CandidType, Clone, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterHttpResponsePayload {
+    pub status: u64,
+    pub headers: Vec<HttpHeader>,
+    pub body: Vec<u8>,
+}
+
+impl Payload<'_> for CanisterHttpResponsePayload {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     request_id : nat64;
+///     url : text;
+///     distinct_responses : nat32;
+///     response_shares : nat32;
+///     transform_method_name : opt text;
+///     time : nat64;
+/// })`
+#[derive(CandidType, Clone, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterHttpDivergence {
+    pub request_id: u64,
+    pub url: String,
+    /// The number of different responses the replicas signed.
+    pub distinct_responses: u32,
+    /// The number of replicas that signed a response.
+    pub response_shares: u32,
+    pub transform_method_name: Option<String>,
+    /// The time, in nanoseconds since the Unix epoch, at which consensus
+    /// gave up on the request.
+    pub time: u64,
+}
+
+/// Struct used for encoding/decoding the reply of `http_request_divergences`
+/// `(record {
+///     divergences : vec http_request_divergence;
+/// })`
+#[derive(CandidType, Clone, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterHttpDivergencesResult {
+    /// The most recent divergences of the canister, oldest first.
+    pub divergences: Vec<CanisterHttpDivergence>,
+}
+
+impl Payload<'_> for CanisterHttpDivergencesResult {}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     node_ids : vec principal;
@@ -661,6 +729,77 @@ pub struct SignWithECDSAReply {
 
 impl Payload<'_> for SignWithECDSAReply {}
 
+/// The maximum number of message hashes that a single call of the
+/// sign_with_ecdsa_batch API may sign.
+pub const MAX_SIGN_WITH_ECDSA_BATCH_SIZE: usize = 64;
+
+/// Represents the argument of the sign_with_ecdsa_batch API, which signs up to
+/// `MAX_SIGN_WITH_ECDSA_BATCH_SIZE` message hashes under the same derivation
+/// path.
+/// ```text
+/// (record {
+///   message_hashes : vec blob;
+///   derivation_path : vec blob;
+///   key_id : text;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithECDSABatchArgs {
+    pub message_hashes: Vec<Vec<u8>>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub key_id: String,
+}
+
+impl Payload<'_> for SignWithECDSABatchArgs {}
+
+/// The outcome of signing one message hash of a sign_with_ecdsa_batch call.
+/// ```text
+/// variant {
+///   signature : blob;
+///   error : text;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SignWithECDSABatchResult {
+    #[serde(rename = "signature")]
+    Signature(Vec<u8>),
+    #[serde(rename = "error")]
+    Error(String),
+}
+
+/// Struct used to return the outcomes of a sign_with_ecdsa_batch call, in the
+/// order of its message hashes.
+/// ```text
+/// (record {
+///   results : vec variant { signature : blob; error : text };
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithECDSABatchReply {
+    pub results: Vec<SignWithECDSABatchResult>,
+}
+
+impl Payload<'_> for SignWithECDSABatchReply {}
+
+/// Represents the argument of the reserve_ecdsa_pre_signatures API, which sets
+/// the number of pre-signatures of the ECDSA key `key_id` that are reserved
+/// for the signing requests of the calling canister. Reserving no
+/// pre-signatures releases the reservation of the canister. A reservation
+/// expires unless the canister makes signing requests with the key.
+/// ```text
+/// (record {
+///   key_id : text;
+///   pre_signatures : nat32;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct ReserveECDSAPreSignaturesArgs {
+    pub key_id: String,
+    pub pre_signatures: u32,
+}
+
+impl Payload<'_> for ReserveECDSAPreSignaturesArgs {}
+
 /// Represents the argument of the get_ecdsa_public_key API.
 /// ```text
 /// (record {
@@ -2,6 +2,7 @@
 //! Consensus and Message Routing.
 use super::{
     artifact::IngressMessageId,
+    canister_http::CanisterHttpResponse,
     messages::{MessageId, Response, SignedIngress, EXPECTED_MESSAGE_ID_LENGTH},
     xnet::CertifiedStreamSlice,
     CountBytes, Height, Randomness, RegistryVersion, SubnetId, Time,
@@ -34,6 +35,8 @@ pub struct Batch {
     pub time: Time,
     /// Responses to subnet calls that require consensus' involvement.
     pub consensus_responses: Vec<Response>,
+    /// The outcomes of canister http requests that consensus agreed on.
+    pub canister_http_responses: Vec<CanisterHttpResponse>,
 }
 
 /// The context built by Consensus for deterministic processing. Captures all
@@ -3,26 +3,66 @@ use crate::{
     crypto::Signed,
     messages::{CallbackId, Request},
     signature::*,
-    Time,
+    CanisterId, CountBytes, Cycles, NumInstructions, Time,
 };
 use ic_base_types::HttpMethodType;
 use ic_protobuf::{
+    canister_http::v1 as pb_canister_http,
     proxy::{try_from_option_field, ProxyDecodeError},
+    registry::subnet::v1 as pb_subnet,
     state::system_metadata::v1 as pb_metadata,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::time::Duration;
 
 pub type CanisterHttpRequestId = CallbackId;
 
+/// The default window over which the adapters of the replicas of a subnet
+/// spread sending the same canister http request.
+pub const DEFAULT_CANISTER_HTTP_PSEUDO_RANDOM_DELAY_WINDOW: Duration = Duration::from_millis(500);
+
+/// The time after which a canister http request that did not get a response
+/// times out.
+pub const CANISTER_HTTP_TIMEOUT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// An in-flight canister http request, as kept in the replicated state from
+/// the moment execution accepts the request until its response, or its
+/// timeout, is delivered to the calling canister.
+///
+/// Since the context is part of the replicated state, a request that is in
+/// flight at a checkpoint is still in flight, with the same callback id and
+/// timeout, after a restart of the replica from that checkpoint.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CanisterHttpRequestContext {
     pub request: Request,
     pub url: String,
     pub body: Option<Vec<u8>>,
     pub http_method: HttpMethodType,
+    /// The method of the calling canister that transforms the response
+    /// before consensus is reached on it, if any.
     pub transform_method_name: Option<String>,
+    /// The batch time at which the request was accepted.
     pub time: Time,
+    /// The batch time from which on the request has timed out.
+    pub timeout: Time,
+    /// The size, in bytes, of the largest response that the fee of the
+    /// request paid for.
+    pub max_response_bytes: u64,
+    /// The fee per response byte that was charged for the request. The bytes
+    /// of `max_response_bytes` that the response does not use are refunded
+    /// at this price.
+    pub fee_per_response_byte: Cycles,
+}
+
+impl CanisterHttpRequestContext {
+    /// Returns whether the request has timed out at batch time `now`. Since
+    /// batch times are agreed by consensus, all replicas of the subnet time
+    /// out the same requests in the same round.
+    pub fn has_timed_out(&self, now: Time) -> bool {
+        now >= self.timeout
+    }
 }
 
 impl From<&CanisterHttpRequestContext> for pb_metadata::CanisterHttpRequestContext {
@@ -37,6 +77,9 @@ impl From<&CanisterHttpRequestContext> for pb_metadata::CanisterHttpRequestConte
                 .map(|method_name| method_name.into()),
             http_method: pb_metadata::HttpMethodType::from(&context.http_method) as i32,
             time: context.time.as_nanos_since_unix_epoch(),
+            timeout: context.timeout.as_nanos_since_unix_epoch(),
+            max_response_bytes: context.max_response_bytes,
+            fee_per_response_byte: u64::from(context.fee_per_response_byte),
         }
     }
 }
@@ -46,6 +89,13 @@ impl TryFrom<pb_metadata::CanisterHttpRequestContext> for CanisterHttpRequestCon
     fn try_from(context: pb_metadata::CanisterHttpRequestContext) -> Result<Self, Self::Error> {
         let request: Request =
             try_from_option_field(context.request, "CanisterHttpRequestContext::request")?;
+        let time = Time::from_nanos_since_unix_epoch(context.time);
+        // Contexts persisted before timeouts were recorded time out after the
+        // default interval.
+        let timeout = match context.timeout {
+            0 => time + CANISTER_HTTP_TIMEOUT_INTERVAL,
+            timeout => Time::from_nanos_since_unix_epoch(timeout),
+        };
         Ok(CanisterHttpRequestContext {
             request,
             url: context.url,
@@ -54,17 +104,283 @@ impl TryFrom<pb_metadata::CanisterHttpRequestContext> for CanisterHttpRequestCon
                 pb_metadata::HttpMethodType::from_i32(context.http_method).unwrap_or_default(),
             ),
             transform_method_name: context.transform_method_name.map(From::from),
-            time: Time::from_nanos_since_unix_epoch(context.time),
+            time,
+            timeout,
+            // Contexts persisted before response fees were recorded get no
+            // refund, and may get responses up to the upper bound.
+            max_response_bytes: match context.max_response_bytes {
+                0 => MAX_CANISTER_HTTP_RESPONSE_BYTES,
+                max_response_bytes => max_response_bytes,
+            },
+            fee_per_response_byte: Cycles::from(context.fee_per_response_byte),
         })
     }
 }
 
+/// A canister http request, as handed from execution to the canister http
+/// adapter.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CanisterHttpRequest {
-    id: CanisterHttpRequestId,
-    content: CanisterHttpRequestContext,
+    pub id: CanisterHttpRequestId,
+    pub content: CanisterHttpRequestContext,
+}
+
+impl CanisterHttpRequest {
+    /// Builds the request to the adapter, which spreads sending it over
+    /// `pseudo_random_delay_window`.
+    pub fn to_adapter_request(
+        &self,
+        pseudo_random_delay_window: Duration,
+    ) -> pb_canister_http::CanisterHttpRequest {
+        pb_canister_http::CanisterHttpRequest {
+            url: self.content.url.clone(),
+            body: self.content.body.clone().unwrap_or_default(),
+            headers: vec![],
+            request_id: self.id.get(),
+            pseudo_random_delay_window_ms: pseudo_random_delay_window.as_millis() as u64,
+            max_response_bytes: self.content.max_response_bytes,
+        }
+    }
+}
+
+impl CountBytes for CanisterHttpRequest {
+    fn count_bytes(&self) -> usize {
+        self.content.url.len() + self.content.body.as_ref().map_or(0, |body| body.len())
+    }
+}
+
+/// A header of a canister http response.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpHeader {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+impl From<pb_canister_http::HttpHeader> for CanisterHttpHeader {
+    fn from(header: pb_canister_http::HttpHeader) -> Self {
+        CanisterHttpHeader {
+            name: header.name,
+            value: header.value,
+        }
+    }
+}
+
+impl From<&CanisterHttpHeader> for pb_canister_http::HttpHeader {
+    fn from(header: &CanisterHttpHeader) -> Self {
+        pb_canister_http::HttpHeader {
+            name: header.name.clone(),
+            value: header.value.clone(),
+        }
+    }
+}
+
+/// The reply of the canister http adapter to a [`CanisterHttpRequest`],
+/// before consensus on it has been reached.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpReply {
+    pub id: CanisterHttpRequestId,
+    pub status: u32,
+    pub headers: Vec<CanisterHttpHeader>,
+    pub body: Vec<u8>,
+}
+
+impl CanisterHttpReply {
+    /// Builds the reply to the request with the given `id` from the response
+    /// returned by the adapter.
+    pub fn from_adapter_response(
+        id: CanisterHttpRequestId,
+        response: pb_canister_http::CanisterHttpResponse,
+    ) -> Self {
+        CanisterHttpReply {
+            id,
+            status: response.status,
+            headers: response.headers.into_iter().map(From::from).collect(),
+            body: response.content,
+        }
+    }
+}
+
+impl CountBytes for CanisterHttpReply {
+    fn count_bytes(&self) -> usize {
+        self.headers
+            .iter()
+            .map(|header| header.name.len() + header.value.len())
+            .sum::<usize>()
+            + self.body.len()
+    }
+}
+
+/// The upper bound on the size of a response to a canister http request.
+/// Subnets may configure a lower limit in the registry.
+pub const MAX_CANISTER_HTTP_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// The number of canister http requests that may be in flight on a subnet if
+/// the registry does not configure a limit.
+pub const DEFAULT_MAX_CONCURRENT_CANISTER_HTTP_REQUESTS: u32 = 500;
+
+/// The url scheme canisters may use if the registry does not configure any.
+pub const DEFAULT_CANISTER_HTTP_URL_SCHEME: &str = "https";
+
+/// The number of instructions the transform function of a canister may
+/// execute on a response if the registry does not configure a limit.
+pub const DEFAULT_MAX_CANISTER_HTTP_TRANSFORM_INSTRUCTIONS: NumInstructions =
+    NumInstructions::new(200_000_000);
+
+/// The cycles charged for canister http requests on a subnet, as configured
+/// by the `pricing` of the `canister_http_config` of its subnet record.
+/// Unless the registry configures a pricing, the cycles account manager
+/// charges the default pricing of the subnet type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpPricing {
+    pub base_fee: Cycles,
+    pub fee_per_request_byte: Cycles,
+    pub fee_per_response_byte: Cycles,
+}
+
+impl Default for CanisterHttpPricing {
+    fn default() -> Self {
+        Self {
+            base_fee: Cycles::zero(),
+            fee_per_request_byte: Cycles::zero(),
+            fee_per_response_byte: Cycles::zero(),
+        }
+    }
+}
+
+impl From<pb_subnet::CanisterHttpPricing> for CanisterHttpPricing {
+    fn from(pricing: pb_subnet::CanisterHttpPricing) -> Self {
+        Self {
+            base_fee: Cycles::from(pricing.base_fee),
+            fee_per_request_byte: Cycles::from(pricing.fee_per_request_byte),
+            fee_per_response_byte: Cycles::from(pricing.fee_per_response_byte),
+        }
+    }
+}
+
+impl From<&CanisterHttpPricing> for pb_subnet::CanisterHttpPricing {
+    fn from(pricing: &CanisterHttpPricing) -> Self {
+        Self {
+            base_fee: u64::from(pricing.base_fee),
+            fee_per_request_byte: u64::from(pricing.fee_per_request_byte),
+            fee_per_response_byte: u64::from(pricing.fee_per_response_byte),
+        }
+    }
+}
+
+/// The limits on canister http requests of a subnet, as configured by the
+/// `canister_http_config` of its subnet record.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpLimits {
+    pub max_response_bytes: u64,
+    pub max_concurrent_requests: u32,
+    pub allowed_url_schemes: Vec<String>,
+    /// The pricing configured in the registry, if any.
+    pub pricing: Option<CanisterHttpPricing>,
+    /// The budget for running the transform function of a canister on the
+    /// response to one of its requests.
+    pub max_transform_instructions: NumInstructions,
 }
 
+impl Default for CanisterHttpLimits {
+    fn default() -> Self {
+        Self {
+            max_response_bytes: MAX_CANISTER_HTTP_RESPONSE_BYTES,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_CANISTER_HTTP_REQUESTS,
+            allowed_url_schemes: vec![DEFAULT_CANISTER_HTTP_URL_SCHEME.to_string()],
+            pricing: None,
+            max_transform_instructions: DEFAULT_MAX_CANISTER_HTTP_TRANSFORM_INSTRUCTIONS,
+        }
+    }
+}
+
+impl CanisterHttpLimits {
+    /// Returns true if the scheme of `url` is one of the allowed schemes.
+    pub fn allows_url(&self, url: &str) -> bool {
+        match url.split_once(':') {
+            Some((scheme, _)) => self
+                .allowed_url_schemes
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(scheme)),
+            None => false,
+        }
+    }
+}
+
+/// Unset fields of the registry config, i.e. zero limits and an empty list of
+/// schemes, fall back to the defaults.
+impl From<pb_subnet::CanisterHttpConfig> for CanisterHttpLimits {
+    fn from(config: pb_subnet::CanisterHttpConfig) -> Self {
+        let default = Self::default();
+        Self {
+            max_response_bytes: match config.max_response_bytes {
+                0 => default.max_response_bytes,
+                bytes => bytes.min(MAX_CANISTER_HTTP_RESPONSE_BYTES),
+            },
+            max_concurrent_requests: match config.max_concurrent_requests {
+                0 => default.max_concurrent_requests,
+                requests => requests,
+            },
+            allowed_url_schemes: if config.allowed_url_schemes.is_empty() {
+                default.allowed_url_schemes
+            } else {
+                config.allowed_url_schemes
+            },
+            pricing: config.pricing.map(CanisterHttpPricing::from),
+            max_transform_instructions: match config.max_transform_instructions {
+                0 => default.max_transform_instructions,
+                instructions => NumInstructions::from(instructions),
+            },
+        }
+    }
+}
+
+impl From<&CanisterHttpLimits> for pb_subnet::CanisterHttpConfig {
+    fn from(limits: &CanisterHttpLimits) -> Self {
+        Self {
+            max_response_bytes: limits.max_response_bytes,
+            max_concurrent_requests: limits.max_concurrent_requests,
+            allowed_url_schemes: limits.allowed_url_schemes.clone(),
+            pricing: limits.pricing.as_ref().map(Into::into),
+            max_transform_instructions: limits.max_transform_instructions.get(),
+        }
+    }
+}
+
+/// The reasons why the transform function of a canister did not produce a
+/// transformed [`CanisterHttpReply`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CanisterHttpTransformError {
+    /// The transform function exceeded the instruction budget of the subnet.
+    InstructionLimitExceeded { limit: NumInstructions },
+    /// The transform function trapped, rejected or could not be called.
+    Rejected(String),
+    /// The transform function replied with something that does not decode
+    /// to an http response.
+    InvalidReply(String),
+}
+
+impl std::fmt::Display for CanisterHttpTransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InstructionLimitExceeded { limit } => write!(
+                f,
+                "Transform function exceeded the limit of {} instructions",
+                limit
+            ),
+            Self::Rejected(reason) => write!(f, "Transform function failed: {}", reason),
+            Self::InvalidReply(reason) => {
+                write!(
+                    f,
+                    "Transform function returned an invalid reply: {}",
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanisterHttpTransformError {}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CanisterHttpResponseContent {
     id: CanisterHttpRequestId,
@@ -91,11 +407,113 @@ pub type CanisterHttpResponseShare = Signed<
     MultiSignatureShare<CryptoHashOf<CanisterHttpResponseContent>>,
 >;
 
+/// The response shares of the replicas to a canister http request on which
+/// consensus could not be reached, because too few replicas signed the same
+/// response.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CanisterHttpRequestDivergence {
+    id: CanisterHttpRequestId,
     response_shares: Vec<CanisterHttpResponseShare>,
 }
 
+impl CanisterHttpRequestDivergence {
+    pub fn new(id: CanisterHttpRequestId, response_shares: Vec<CanisterHttpResponseShare>) -> Self {
+        Self {
+            id,
+            response_shares,
+        }
+    }
+
+    /// Returns the id of the request whose responses diverged.
+    pub fn id(&self) -> CanisterHttpRequestId {
+        self.id
+    }
+
+    /// Returns the number of different responses the replicas signed.
+    pub fn distinct_responses(&self) -> usize {
+        self.response_shares
+            .iter()
+            .map(|share| &share.content)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Returns the report of the divergence of the request with the given
+    /// `context`, recorded at batch time `time`.
+    pub fn report(
+        &self,
+        context: &CanisterHttpRequestContext,
+        time: Time,
+    ) -> CanisterHttpDivergenceReport {
+        CanisterHttpDivergenceReport {
+            request_id: self.id,
+            canister_id: context.request.sender,
+            url: context.url.clone(),
+            distinct_responses: self.distinct_responses() as u32,
+            response_shares: self.response_shares.len() as u32,
+            transform_method_name: context.transform_method_name.clone(),
+            time,
+        }
+    }
+}
+
+/// Why the responses to a canister http request did not reach consensus, as
+/// kept in the replicated state for the calling canister and its controllers
+/// to query through `http_request_divergences`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterHttpDivergenceReport {
+    pub request_id: CanisterHttpRequestId,
+    /// The canister that made the request.
+    pub canister_id: CanisterId,
+    pub url: String,
+    /// The number of different responses the replicas signed. More than one
+    /// usually means that the responses differ between replicas, e.g. in a
+    /// timestamp, and need to be normalized by a transform.
+    pub distinct_responses: u32,
+    /// The number of replicas that signed a response.
+    pub response_shares: u32,
+    /// The transform applied to the responses before they were signed, if
+    /// any.
+    pub transform_method_name: Option<String>,
+    /// The batch time at which the divergence was recorded.
+    pub time: Time,
+}
+
+impl From<&CanisterHttpDivergenceReport> for pb_metadata::CanisterHttpDivergenceReport {
+    fn from(report: &CanisterHttpDivergenceReport) -> Self {
+        pb_metadata::CanisterHttpDivergenceReport {
+            request_id: report.request_id.get(),
+            canister_id: Some(report.canister_id.into()),
+            url: report.url.clone(),
+            distinct_responses: report.distinct_responses,
+            response_shares: report.response_shares,
+            transform_method_name: report
+                .transform_method_name
+                .as_ref()
+                .map(|method_name| method_name.into()),
+            time: report.time.as_nanos_since_unix_epoch(),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::CanisterHttpDivergenceReport> for CanisterHttpDivergenceReport {
+    type Error = ProxyDecodeError;
+    fn try_from(report: pb_metadata::CanisterHttpDivergenceReport) -> Result<Self, Self::Error> {
+        Ok(CanisterHttpDivergenceReport {
+            request_id: CanisterHttpRequestId::new(report.request_id),
+            canister_id: try_from_option_field(
+                report.canister_id,
+                "CanisterHttpDivergenceReport::canister_id",
+            )?,
+            url: report.url,
+            distinct_responses: report.distinct_responses,
+            response_shares: report.response_shares,
+            transform_method_name: report.transform_method_name.map(From::from),
+            time: Time::from_nanos_since_unix_epoch(report.time),
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CanisterHttpResponse {
     WithConsensus(CanisterHttpResponseWithConsensus),
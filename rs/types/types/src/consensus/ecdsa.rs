@@ -24,10 +24,13 @@ use crate::{Height, NodeId};
 /// For completed signature requests, we differentiate between those
 /// that have already been reported and those that have not. This is
 /// to prevent signatures from being reported more than once.
+/// Requests that cannot be signed are completed with an unreported
+/// rejection instead, which is reported to execution the same way.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CompletedSignature {
     ReportedToExecution,
     Unreported(ThresholdEcdsaCombinedSignature),
+    UnreportedReject(String),
 }
 
 /// The payload information necessary for ECDSA threshold signatures, that is
@@ -417,6 +417,10 @@ pub trait EcdsaBlockReader {
     /// Returns the set of all the active references.
     fn active_transcripts(&self) -> Vec<TranscriptRef>;
 
+    /// Returns the key transcript of the current interval, as published in
+    /// its summary block, if any.
+    fn current_key_transcript(&self) -> Option<&UnmaskedTranscript>;
+
     /// Looks up the transcript for the given transcript ref.
     fn transcript(
         &self,
@@ -295,6 +295,9 @@ pub enum CryptoError {
     },
     /// Root subnet public key not found at given registry version.
     RootSubnetPublicKeyNotFound { registry_version: RegistryVersion },
+    /// An internal error that may not occur again when retried, e.g. a
+    /// failed call of the remote CSP vault.
+    TransientInternalError { internal_error: String },
 }
 
 impl From<ThresholdSigPublicKeyBytesConversionError> for CryptoError {
@@ -364,6 +367,10 @@ impl CryptoError {
     pub fn is_invalid_argument(&self) -> bool {
         matches!(self, CryptoError::InvalidArgument { .. })
     }
+
+    pub fn is_transient_internal_error(&self) -> bool {
+        matches!(self, CryptoError::TransientInternalError { .. })
+    }
 }
 
 impl From<RegistryClientError> for CryptoError {
@@ -526,7 +533,10 @@ impl fmt::Debug for CryptoError {
                 f,
                 "Cannot find root subnet public key at registry version {:?}",
                 registry_version
-            )
+            ),
+            CryptoError::TransientInternalError { internal_error } => {
+                write!(f, "Transient internal error: {}", internal_error)
+            }
         }
     }
 }
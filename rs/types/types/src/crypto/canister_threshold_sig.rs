@@ -35,9 +35,14 @@ pub struct MasterEcdsaPublicKey {
 /// A combined threshold ECDSA signature.
 ///
 /// The signature itself is stored as raw bytes.
+///
+/// The recovery id of the signature, if known, allows recovering the public
+/// key from the signature and the message. It is for instance needed to
+/// compute the `v` value of an Ethereum signature.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ThresholdEcdsaCombinedSignature {
     pub signature: Vec<u8>,
+    pub recovery_id: Option<u8>,
 }
 
 /// Quadruple of signature-specific IDKG transcripts required to generate a
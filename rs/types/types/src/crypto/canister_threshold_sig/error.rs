@@ -5,6 +5,7 @@ use crate::registry::RegistryClientError;
 use crate::{NodeId, RegistryVersion};
 use ic_protobuf::registry::crypto::v1::AlgorithmId as AlgorithmIdProto;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 macro_rules! impl_display_using_debug {
     ($t:ty) => {
@@ -46,6 +47,24 @@ pub enum IDkgParamsValidationError {
 }
 impl_display_using_debug!(IDkgParamsValidationError);
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InitialIDkgDealingsValidationError {
+    InvalidTranscriptOperation,
+    DealersAndReceiversNotDisjoint,
+    MismatchingTranscriptId { dealer_id: NodeId },
+    DealerNotAllowed { dealer_id: NodeId },
+    MultipleDealingsFromSameDealer { dealer_id: NodeId },
+    UnsatisfiedCollectionThreshold { threshold: u32, dealings_count: u32 },
+    MismatchingReceivers,
+}
+impl_display_using_debug!(InitialIDkgDealingsValidationError);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InitialIDkgDealingsEncodingError {
+    SerializationError(String),
+}
+impl_display_using_debug!(InitialIDkgDealingsEncodingError);
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ThresholdEcdsaGetPublicKeyError {
     InvalidArgument(String),
@@ -79,6 +98,10 @@ pub enum IDkgCreateTranscriptError {
     InvalidMultisignature {
         crypto_error: CryptoError,
     },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(IDkgCreateTranscriptError);
 
@@ -91,6 +114,10 @@ pub enum IDkgVerifyTranscriptError {
     },
     SerializationError(String),
     InvalidTranscript,
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(IDkgVerifyTranscriptError);
 
@@ -158,16 +185,66 @@ pub enum IDkgCreateDealingError {
     AlgorithmMismatchWithSKS {
         algorithm_id: AlgorithmId,
     },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(IDkgCreateDealingError);
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub enum IDkgVerifyDealingPublicError {}
+pub enum IDkgVerifyDealingPublicError {
+    TranscriptIdMismatch,
+    InvalidDealer {
+        node_id: NodeId,
+    },
+    InvalidArgument {
+        internal_error: String,
+    },
+    InvalidDealing {
+        reason: String,
+    },
+    InternalError {
+        internal_error: String,
+    },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
+}
 impl_display_using_debug!(IDkgVerifyDealingPublicError);
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IDkgVerifyDealingPrivateError {
     NotAReceiver,
+    InvalidArgument {
+        internal_error: String,
+    },
+    InvalidDealing {
+        reason: String,
+    },
+    PrivateKeyNotFound {
+        key_id: KeyId,
+    },
+    PublicKeyNotInRegistry {
+        node_id: NodeId,
+        registry_version: RegistryVersion,
+    },
+    MalformedPublicKey {
+        node_id: NodeId,
+        key_bytes: Vec<u8>,
+    },
+    UnsupportedAlgorithm {
+        algorithm_id: Option<AlgorithmIdProto>,
+    },
+    RegistryError(RegistryClientError),
+    InternalError {
+        internal_error: String,
+    },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(IDkgVerifyDealingPrivateError);
 
@@ -216,10 +293,20 @@ impl_display_using_debug!(ThresholdEcdsaVerifySigShareError);
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ThresholdEcdsaSignShareError {
-    InternalError { internal_error: String },
+    InternalError {
+        internal_error: String,
+    },
     NotAReceiver,
-    SerializationError { internal_error: String },
-    SecretSharesNotFound { commitment_string: String },
+    SerializationError {
+        internal_error: String,
+    },
+    SecretSharesNotFound {
+        commitment_string: String,
+    },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(ThresholdEcdsaSignShareError);
 
@@ -229,9 +316,22 @@ impl_display_using_debug!(ThresholdEcdsaVerifyCombinedSignatureError);
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ThresholdEcdsaCombineSigSharesError {
-    InternalError { internal_error: String },
-    UnsatisfiedReconstructionThreshold { threshold: u32, share_count: usize },
-    SerializationError { internal_error: String },
-    SignerNotAllowed { node_id: NodeId },
+    InternalError {
+        internal_error: String,
+    },
+    UnsatisfiedReconstructionThreshold {
+        threshold: u32,
+        share_count: usize,
+    },
+    SerializationError {
+        internal_error: String,
+    },
+    SignerNotAllowed {
+        node_id: NodeId,
+    },
+    UnsupportedAlgorithmId {
+        algorithm_id: AlgorithmId,
+        supported: BTreeSet<AlgorithmId>,
+    },
 }
 impl_display_using_debug!(ThresholdEcdsaCombineSigSharesError);
@@ -1,7 +1,9 @@
 //! Defines interactive distributed key generation (IDkg) types.
 use crate::consensus::ecdsa::EcdsaDealing;
 use crate::consensus::get_faults_tolerated;
-use crate::crypto::canister_threshold_sig::error::IDkgParamsValidationError;
+use crate::crypto::canister_threshold_sig::error::{
+    IDkgParamsValidationError, InitialIDkgDealingsValidationError,
+};
 use crate::crypto::{AlgorithmId, CombinedMultiSigOf};
 use crate::{NodeId, NumberOfNodes, RegistryVersion};
 use ic_base_types::SubnetId;
@@ -11,7 +13,9 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 
+pub mod context_data;
 pub mod conversions;
+pub use context_data::*;
 pub use conversions::*;
 
 #[cfg(test)]
@@ -380,11 +384,8 @@ impl IDkgTranscriptParams {
 
     /// Contextual data needed for the creation of a dealing.
     pub fn context_data(&self) -> Vec<u8> {
-        context_data(
-            &self.transcript_id,
-            self.registry_version,
-            self.algorithm_id,
-        )
+        IDkgContextData::new(self.transcript_id, self.registry_version, self.algorithm_id)
+            .to_bytes()
     }
 
     fn ensure_collection_threshold_satisfied(&self) -> Result<(), IDkgParamsValidationError> {
@@ -532,11 +533,8 @@ impl IDkgTranscript {
 
     /// Contextual data needed for the creation of a dealing.
     pub fn context_data(&self) -> Vec<u8> {
-        context_data(
-            &self.transcript_id,
-            self.registry_version,
-            self.algorithm_id,
-        )
+        IDkgContextData::new(self.transcript_id, self.registry_version, self.algorithm_id)
+            .to_bytes()
     }
 
     /// Returns the dealer ID for the given node index, or `None` if there is no such dealer.
@@ -704,31 +702,106 @@ pub struct IDkgOpening {
     pub internal_opening_raw: Vec<u8>,
 }
 
-fn number_of_nodes_from_usize(number: usize) -> Result<NumberOfNodes, ()> {
-    let count = NodeIndex::try_from(number).map_err(|_| ())?;
-    Ok(NumberOfNodes::from(count))
+/// Initial dealings for resharing a threshold ECDSA key from the subnet
+/// holding it to the nodes of another subnet.
+///
+/// The dealings are computed by the dealers of `params`, i.e., the receivers
+/// of the transcript of the key on the source subnet, for the receivers of
+/// `params`, i.e., the nodes of the target subnet. They are included in the
+/// catch up package contents of the target subnet, which can then bootstrap
+/// the key without interacting with the source subnet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitialIDkgDealings {
+    params: IDkgTranscriptParams,
+    dealings: Vec<IDkgDealing>,
 }
 
-/// Contextual data needed for the creation of a dealing.
-///
-/// Returns a byte vector consisting of:
-/// - IDkgTranscriptId::SubnetId, as a byte-string (prefixed with its
-///   64-bit-big-endian-integer length)
-/// - IDkgTranscriptId::id, as a big-endian 64-bit integer
-/// - RegistryVersion, as a big-endian 64-bit integer
-/// - AlgorithmId, as an 8-bit integer value
-fn context_data(
-    transcript_id: &IDkgTranscriptId,
-    registry_version: RegistryVersion,
-    algorithm_id: AlgorithmId,
-) -> Vec<u8> {
-    let mut ret = Vec::with_capacity(8 + transcript_id.subnet().get().as_slice().len() + 8 + 8 + 1);
+impl InitialIDkgDealings {
+    /// Checks the following invariants:
+    /// * `params.operation_type` is ReshareOfMasked(_) or ReshareOfUnmasked(_)
+    ///   (error: `InvalidTranscriptOperation`)
+    /// * params.dealers and params.receivers are disjoint (error:
+    ///   `DealersAndReceiversNotDisjoint`)
+    /// * every dealing is for `params.transcript_id` (error:
+    ///   `MismatchingTranscriptId`)
+    /// * every dealing is from a dealer in params.dealers (error:
+    ///   `DealerNotAllowed`)
+    /// * there is at most one dealing per dealer (error:
+    ///   `MultipleDealingsFromSameDealer`)
+    /// * |dealings| >= params.collection_threshold (error:
+    ///   `UnsatisfiedCollectionThreshold`)
+    pub fn new(
+        params: IDkgTranscriptParams,
+        dealings: Vec<IDkgDealing>,
+    ) -> Result<Self, InitialIDkgDealingsValidationError> {
+        match params.operation_type() {
+            IDkgTranscriptOperation::ReshareOfMasked(_)
+            | IDkgTranscriptOperation::ReshareOfUnmasked(_) => (),
+            IDkgTranscriptOperation::Random
+            | IDkgTranscriptOperation::UnmaskedTimesMasked(_, _) => {
+                return Err(InitialIDkgDealingsValidationError::InvalidTranscriptOperation)
+            }
+        }
+        if !params.dealers().get().is_disjoint(params.receivers().get()) {
+            return Err(InitialIDkgDealingsValidationError::DealersAndReceiversNotDisjoint);
+        }
 
-    ret.extend_from_slice(&(transcript_id.subnet().get().as_slice().len() as u64).to_be_bytes());
-    ret.extend_from_slice(transcript_id.subnet().get().as_slice());
-    ret.extend_from_slice(&(transcript_id.id() as u64).to_be_bytes());
-    ret.extend_from_slice(&(registry_version.get() as u64).to_be_bytes());
-    ret.push(algorithm_id as u8);
+        let mut dealers_with_dealing = BTreeSet::new();
+        for dealing in dealings.iter() {
+            let dealer_id = dealing.dealer_id;
+            if dealing.transcript_id != params.transcript_id() {
+                return Err(
+                    InitialIDkgDealingsValidationError::MismatchingTranscriptId { dealer_id },
+                );
+            }
+            if !params.dealers().get().contains(&dealer_id) {
+                return Err(InitialIDkgDealingsValidationError::DealerNotAllowed { dealer_id });
+            }
+            if !dealers_with_dealing.insert(dealer_id) {
+                return Err(
+                    InitialIDkgDealingsValidationError::MultipleDealingsFromSameDealer {
+                        dealer_id,
+                    },
+                );
+            }
+        }
 
-    ret
+        let threshold = params.collection_threshold().get();
+        if dealings.len() < threshold as usize {
+            return Err(
+                InitialIDkgDealingsValidationError::UnsatisfiedCollectionThreshold {
+                    threshold,
+                    dealings_count: dealings.len() as u32,
+                },
+            );
+        }
+
+        Ok(Self { params, dealings })
+    }
+
+    pub fn params(&self) -> &IDkgTranscriptParams {
+        &self.params
+    }
+
+    pub fn dealings(&self) -> &[IDkgDealing] {
+        &self.dealings
+    }
+
+    /// Checks that the dealings are for exactly the given receivers on the
+    /// target subnet, e.g., its nodes at the registry version of its catch up
+    /// package (error: `MismatchingReceivers`).
+    pub fn verify_against_receivers(
+        &self,
+        receivers: &BTreeSet<NodeId>,
+    ) -> Result<(), InitialIDkgDealingsValidationError> {
+        if self.params.receivers().get() != receivers {
+            return Err(InitialIDkgDealingsValidationError::MismatchingReceivers);
+        }
+        Ok(())
+    }
+}
+
+fn number_of_nodes_from_usize(number: usize) -> Result<NumberOfNodes, ()> {
+    let count = NodeIndex::try_from(number).map_err(|_| ())?;
+    Ok(NumberOfNodes::from(count))
 }
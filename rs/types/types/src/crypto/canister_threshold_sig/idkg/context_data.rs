@@ -0,0 +1,109 @@
+//! The contextual data that binds IDkg dealings, complaints, and openings to
+//! the transcript they belong to.
+use super::IDkgTranscriptId;
+use crate::crypto::AlgorithmId;
+use crate::RegistryVersion;
+
+/// The domain separator of the contextual data, so that it can not be
+/// mistaken for the bytes signed or hashed in another context.
+const DOMAIN_IDKG_CONTEXT_DATA: &str = "ic-idkg-context-data";
+
+/// The version of the encoding of [`IDkgContextData`].
+///
+/// Starting with `V1`, the version is part of the encoded bytes: changing
+/// the encoding requires a new version, so that bytes encoded with different
+/// versions never coincide.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IDkgContextDataVersion {
+    /// The encoding without domain separator and version that dealings were
+    /// created with before the encoding was versioned.
+    Legacy = 0,
+    V1 = 1,
+}
+
+impl IDkgContextDataVersion {
+    /// The version used to create and verify dealings. Replicas that create
+    /// and verify dealings of the same transcript must agree on it, so it
+    /// stays `Legacy` until all replicas are able to verify `V1`.
+    pub const CURRENT: Self = Self::Legacy;
+}
+
+/// Builder of the contextual data needed for the creation and verification of
+/// dealings, complaints, and openings of an IDkg transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IDkgContextData {
+    version: IDkgContextDataVersion,
+    transcript_id: IDkgTranscriptId,
+    registry_version: RegistryVersion,
+    algorithm_id: AlgorithmId,
+}
+
+impl IDkgContextData {
+    /// Creates the contextual data of the transcript `transcript_id`, using the
+    /// current version of the encoding.
+    pub fn new(
+        transcript_id: IDkgTranscriptId,
+        registry_version: RegistryVersion,
+        algorithm_id: AlgorithmId,
+    ) -> Self {
+        Self {
+            version: IDkgContextDataVersion::CURRENT,
+            transcript_id,
+            registry_version,
+            algorithm_id,
+        }
+    }
+
+    /// Uses the given version of the encoding instead of the current one.
+    pub fn with_version(mut self, version: IDkgContextDataVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Encodes the contextual data.
+    ///
+    /// With version `V1`, returns a byte vector consisting of:
+    /// - the domain separator, as a byte-string (prefixed with its 8-bit
+    ///   length)
+    /// - the version, as an 8-bit integer value
+    /// - the fields of the `Legacy` encoding.
+    ///
+    /// With version `Legacy`, returns a byte vector consisting of:
+    /// - IDkgTranscriptId::SubnetId, as a byte-string (prefixed with its
+    ///   64-bit-big-endian-integer length)
+    /// - IDkgTranscriptId::id, as a big-endian 64-bit integer
+    /// - RegistryVersion, as a big-endian 64-bit integer
+    /// - AlgorithmId, as an 8-bit integer value
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.version {
+            IDkgContextDataVersion::Legacy => self.to_bytes_legacy(),
+            IDkgContextDataVersion::V1 => self.to_bytes_v1(),
+        }
+    }
+
+    fn to_bytes_v1(&self) -> Vec<u8> {
+        let legacy = self.to_bytes_legacy();
+        let mut ret = Vec::with_capacity(1 + DOMAIN_IDKG_CONTEXT_DATA.len() + 1 + legacy.len());
+
+        ret.push(DOMAIN_IDKG_CONTEXT_DATA.len() as u8);
+        ret.extend_from_slice(DOMAIN_IDKG_CONTEXT_DATA.as_bytes());
+        ret.push(self.version as u8);
+        ret.extend_from_slice(&legacy);
+
+        ret
+    }
+
+    fn to_bytes_legacy(&self) -> Vec<u8> {
+        let subnet = self.transcript_id.subnet().get();
+        let subnet_bytes = subnet.as_slice();
+        let mut ret = Vec::with_capacity(8 + subnet_bytes.len() + 8 + 8 + 1);
+
+        ret.extend_from_slice(&(subnet_bytes.len() as u64).to_be_bytes());
+        ret.extend_from_slice(subnet_bytes);
+        ret.extend_from_slice(&(self.transcript_id.id() as u64).to_be_bytes());
+        ret.extend_from_slice(&self.registry_version.get().to_be_bytes());
+        ret.push(self.algorithm_id as u8);
+
+        ret
+    }
+}
@@ -2,8 +2,17 @@
 
 use super::*;
 use crate::consensus::ecdsa::EcdsaDealing;
+use crate::crypto::canister_threshold_sig::error::InitialIDkgDealingsEncodingError;
 use crate::crypto::Signed;
 use crate::signature::MultiSignature;
+use crate::{node_id_into_protobuf, subnet_id_into_protobuf, subnet_id_try_from_protobuf};
+use ic_base_types::PrincipalId;
+use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
+use ic_protobuf::registry::subnet::v1 as pb;
+
+/// The version of the encoding of the transcript parameters of
+/// `InitialIDkgDealings`.
+const INITIAL_IDKG_DEALINGS_VERSION: u32 = 0;
 
 impl From<Signed<EcdsaDealing, MultiSignature<EcdsaDealing>>> for IDkgMultiSignedDealing {
     fn from(signed: Signed<EcdsaDealing, MultiSignature<EcdsaDealing>>) -> Self {
@@ -40,3 +49,116 @@ impl From<&IDkgTranscriptOperation> for IDkgTranscriptType {
         }
     }
 }
+
+impl From<IDkgTranscriptId> for pb::IDkgTranscriptId {
+    fn from(transcript_id: IDkgTranscriptId) -> Self {
+        Self {
+            id: transcript_id.id() as u64,
+            subnet_id: Some(subnet_id_into_protobuf(*transcript_id.subnet())),
+        }
+    }
+}
+
+impl TryFrom<pb::IDkgTranscriptId> for IDkgTranscriptId {
+    type Error = ProxyDecodeError;
+
+    fn try_from(transcript_id: pb::IDkgTranscriptId) -> Result<Self, Self::Error> {
+        let subnet_id = subnet_id_try_from_protobuf(transcript_id.subnet_id.ok_or(
+            ProxyDecodeError::MissingField("IDkgTranscriptId::subnet_id"),
+        )?)?;
+        let id =
+            usize::try_from(transcript_id.id).map_err(|err| ProxyDecodeError::ValueOutOfRange {
+                typ: "IDkgTranscriptId::id",
+                err: err.to_string(),
+            })?;
+        Ok(Self::new(subnet_id, id))
+    }
+}
+
+impl From<IDkgDealing> for pb::IDkgDealing {
+    fn from(dealing: IDkgDealing) -> Self {
+        Self {
+            transcript_id: Some(dealing.transcript_id.into()),
+            dealer_id: Some(node_id_into_protobuf(dealing.dealer_id)),
+            internal_dealing_raw: dealing.internal_dealing_raw,
+        }
+    }
+}
+
+impl TryFrom<pb::IDkgDealing> for IDkgDealing {
+    type Error = ProxyDecodeError;
+
+    fn try_from(dealing: pb::IDkgDealing) -> Result<Self, Self::Error> {
+        let dealer_principal = dealing
+            .dealer_id
+            .and_then(|dealer_id| dealer_id.principal_id)
+            .ok_or(ProxyDecodeError::MissingField("IDkgDealing::dealer_id"))?;
+        Ok(Self {
+            transcript_id: try_from_option_field(
+                dealing.transcript_id,
+                "IDkgDealing::transcript_id",
+            )?,
+            dealer_id: NodeId::from(PrincipalId::try_from(dealer_principal)?),
+            internal_dealing_raw: dealing.internal_dealing_raw,
+        })
+    }
+}
+
+impl TryFrom<InitialIDkgDealings> for pb::InitialIDkgDealings {
+    type Error = InitialIDkgDealingsEncodingError;
+
+    fn try_from(initial_dealings: InitialIDkgDealings) -> Result<Self, Self::Error> {
+        let params = serde_cbor::to_vec(&initial_dealings.params).map_err(|err| {
+            InitialIDkgDealingsEncodingError::SerializationError(format!(
+                "failed to serialize the transcript params: {}",
+                err
+            ))
+        })?;
+        Ok(Self {
+            version: INITIAL_IDKG_DEALINGS_VERSION,
+            params,
+            dealings: initial_dealings
+                .dealings
+                .into_iter()
+                .map(pb::IDkgDealing::from)
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<pb::InitialIDkgDealings> for InitialIDkgDealings {
+    type Error = ProxyDecodeError;
+
+    /// Decodes the dealings and checks the invariants of
+    /// `IDkgTranscriptParams::new` and `InitialIDkgDealings::new`, since the
+    /// dealings typically come from the registry rather than from the
+    /// subnet holding the key.
+    fn try_from(initial_dealings: pb::InitialIDkgDealings) -> Result<Self, Self::Error> {
+        if initial_dealings.version != INITIAL_IDKG_DEALINGS_VERSION {
+            return Err(ProxyDecodeError::ValueOutOfRange {
+                typ: "InitialIDkgDealings::version",
+                err: format!(
+                    "unsupported version {}, expected {}",
+                    initial_dealings.version, INITIAL_IDKG_DEALINGS_VERSION
+                ),
+            });
+        }
+        let params: IDkgTranscriptParams = serde_cbor::from_slice(&initial_dealings.params)
+            .map_err(|err| ProxyDecodeError::CborDecodeError(Box::new(err)))?;
+        let params = IDkgTranscriptParams::new(
+            params.transcript_id,
+            params.dealers.get().clone(),
+            params.receivers.get().clone(),
+            params.registry_version,
+            params.algorithm_id,
+            params.operation_type,
+        )
+        .map_err(|err| ProxyDecodeError::Other(format!("{:?}", err)))?;
+        let dealings = initial_dealings
+            .dealings
+            .into_iter()
+            .map(IDkgDealing::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(params, dealings).map_err(|err| ProxyDecodeError::Other(format!("{:?}", err)))
+    }
+}
@@ -1,6 +1,7 @@
 mod context_data;
 mod dealers;
 mod index;
+mod initial_dealings;
 mod params;
 mod receivers;
 mod verify_transcript_params;
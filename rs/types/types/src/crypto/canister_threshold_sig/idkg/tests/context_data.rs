@@ -16,7 +16,62 @@ fn should_create_context_data_as_expected() {
     expected.push(15);
 
     assert_eq!(
-        context_data(&transcript_id, registry_version, algorithm_id),
+        IDkgContextData::new(transcript_id, registry_version, algorithm_id).to_bytes(),
         expected
     );
 }
+
+#[test]
+fn should_create_v1_context_data_as_expected() {
+    let transcript_id =
+        IDkgTranscriptId::new(SubnetId::from(PrincipalId::new_subnet_test_id(2)), 1);
+    let context_data = IDkgContextData::new(
+        transcript_id,
+        RegistryVersion::from(1),
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+    );
+
+    let mut expected = vec![20];
+    expected.extend_from_slice(b"ic-idkg-context-data");
+    expected.push(1);
+    expected.extend_from_slice(&context_data.to_bytes());
+
+    assert_eq!(
+        context_data
+            .with_version(IDkgContextDataVersion::V1)
+            .to_bytes(),
+        expected
+    );
+}
+
+#[test]
+fn should_use_current_version_by_default() {
+    let transcript_id =
+        IDkgTranscriptId::new(SubnetId::from(PrincipalId::new_subnet_test_id(2)), 1);
+    let context_data = IDkgContextData::new(
+        transcript_id,
+        RegistryVersion::from(1),
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+    );
+
+    assert_eq!(
+        context_data.clone().to_bytes(),
+        context_data
+            .with_version(IDkgContextDataVersion::CURRENT)
+            .to_bytes()
+    );
+}
+
+#[test]
+fn should_create_different_context_data_for_transcripts_of_different_subnets() {
+    let context_data = |subnet: u64| {
+        IDkgContextData::new(
+            IDkgTranscriptId::new(SubnetId::from(PrincipalId::new_subnet_test_id(subnet)), 1),
+            RegistryVersion::from(1),
+            AlgorithmId::ThresholdEcdsaSecp256k1,
+        )
+        .to_bytes()
+    };
+
+    assert_ne!(context_data(1), context_data(2));
+}
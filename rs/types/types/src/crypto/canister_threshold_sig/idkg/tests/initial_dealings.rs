@@ -0,0 +1,246 @@
+use crate::crypto::canister_threshold_sig::error::InitialIDkgDealingsValidationError;
+use crate::crypto::canister_threshold_sig::idkg::{
+    IDkgDealing, IDkgReceivers, IDkgTranscript, IDkgTranscriptId, IDkgTranscriptOperation,
+    IDkgTranscriptParams, IDkgTranscriptType, IDkgUnmaskedTranscriptOrigin, InitialIDkgDealings,
+};
+use crate::crypto::AlgorithmId;
+use crate::{NodeId, PrincipalId, RegistryVersion, SubnetId};
+use ic_protobuf::proxy::ProtoProxy;
+use ic_protobuf::registry::subnet::v1 as pb;
+use maplit::btreeset;
+use prost::Message;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+#[test]
+fn should_create_initial_dealings() {
+    let params = reshare_of_unmasked_params();
+
+    let initial_dealings =
+        InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2])).unwrap();
+
+    assert_eq!(initial_dealings.params(), &params);
+    assert_eq!(initial_dealings.dealings(), &dealings(&params, &[1, 2])[..]);
+}
+
+#[test]
+fn should_not_create_initial_dealings_for_random_transcript() {
+    let params = IDkgTranscriptParams::new(
+        transcript_id(),
+        source_nodes(),
+        target_nodes(),
+        RegistryVersion::from(1),
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        IDkgTranscriptOperation::Random,
+    )
+    .unwrap();
+
+    let result = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2]));
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::InvalidTranscriptOperation
+    );
+}
+
+#[test]
+fn should_not_create_initial_dealings_with_overlapping_dealers_and_receivers() {
+    let params = IDkgTranscriptParams::new(
+        transcript_id(),
+        source_nodes(),
+        btreeset! {node_id(4), node_id(11), node_id(12), node_id(13)},
+        RegistryVersion::from(1),
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        IDkgTranscriptOperation::ReshareOfUnmasked(key_transcript()),
+    )
+    .unwrap();
+
+    let result = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2]));
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::DealersAndReceiversNotDisjoint
+    );
+}
+
+#[test]
+fn should_not_create_initial_dealings_with_dealing_for_other_transcript() {
+    let params = reshare_of_unmasked_params();
+    let mut dealings = dealings(&params, &[1, 2]);
+    dealings[1].transcript_id = dealings[1].transcript_id.increment();
+
+    let result = InitialIDkgDealings::new(params, dealings);
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::MismatchingTranscriptId {
+            dealer_id: node_id(2)
+        }
+    );
+}
+
+#[test]
+fn should_not_create_initial_dealings_with_dealing_from_non_dealer() {
+    let params = reshare_of_unmasked_params();
+
+    let result = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 11]));
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::DealerNotAllowed {
+            dealer_id: node_id(11)
+        }
+    );
+}
+
+#[test]
+fn should_not_create_initial_dealings_with_multiple_dealings_from_same_dealer() {
+    let params = reshare_of_unmasked_params();
+
+    let result = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2, 1]));
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::MultipleDealingsFromSameDealer {
+            dealer_id: node_id(1)
+        }
+    );
+}
+
+#[test]
+fn should_not_create_initial_dealings_with_too_few_dealings() {
+    let params = reshare_of_unmasked_params();
+
+    let result = InitialIDkgDealings::new(params.clone(), dealings(&params, &[3]));
+
+    assert_eq!(
+        result.unwrap_err(),
+        InitialIDkgDealingsValidationError::UnsatisfiedCollectionThreshold {
+            threshold: 2,
+            dealings_count: 1
+        }
+    );
+}
+
+#[test]
+fn should_verify_initial_dealings_against_receivers() {
+    let params = reshare_of_unmasked_params();
+    let initial_dealings =
+        InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2])).unwrap();
+
+    assert_eq!(
+        initial_dealings.verify_against_receivers(&target_nodes()),
+        Ok(())
+    );
+    assert_eq!(
+        initial_dealings.verify_against_receivers(&btreeset! {node_id(11), node_id(12)}),
+        Err(InitialIDkgDealingsValidationError::MismatchingReceivers)
+    );
+}
+
+#[test]
+fn should_round_trip_initial_dealings_through_proto() {
+    let params = reshare_of_unmasked_params();
+    let initial_dealings =
+        InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2, 4])).unwrap();
+
+    let proto = pb::InitialIDkgDealings::try_from(initial_dealings.clone()).unwrap();
+    let mut bytes = vec![];
+    proto.encode(&mut bytes).unwrap();
+    let decoded =
+        InitialIDkgDealings::try_from(pb::InitialIDkgDealings::decode(&bytes[..]).unwrap())
+            .unwrap();
+
+    assert_eq!(decoded, initial_dealings);
+}
+
+#[test]
+fn should_round_trip_dealing_through_proto() {
+    let params = reshare_of_unmasked_params();
+    let dealing = dealings(&params, &[3]).remove(0);
+
+    let bytes = pb::IDkgDealing::proxy_encode(dealing.clone()).unwrap();
+    let decoded: IDkgDealing = pb::IDkgDealing::proxy_decode(&bytes).unwrap();
+
+    assert_eq!(decoded, dealing);
+}
+
+#[test]
+fn should_not_decode_initial_dealings_with_unknown_version() {
+    let params = reshare_of_unmasked_params();
+    let initial_dealings = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2]));
+    let mut proto = pb::InitialIDkgDealings::try_from(initial_dealings.unwrap()).unwrap();
+    proto.version += 1;
+
+    assert!(InitialIDkgDealings::try_from(proto).is_err());
+}
+
+#[test]
+fn should_not_decode_invalid_initial_dealings() {
+    let params = reshare_of_unmasked_params();
+    let initial_dealings = InitialIDkgDealings::new(params.clone(), dealings(&params, &[1, 2]));
+    let mut proto = pb::InitialIDkgDealings::try_from(initial_dealings.unwrap()).unwrap();
+    proto.dealings.truncate(1);
+
+    assert!(InitialIDkgDealings::try_from(proto).is_err());
+}
+
+/// Parameters for resharing the key of the source subnet, held by nodes 1 to
+/// 4, to the nodes 11 to 14 of the target subnet.
+fn reshare_of_unmasked_params() -> IDkgTranscriptParams {
+    IDkgTranscriptParams::new(
+        transcript_id(),
+        source_nodes(),
+        target_nodes(),
+        RegistryVersion::from(1),
+        AlgorithmId::ThresholdEcdsaSecp256k1,
+        IDkgTranscriptOperation::ReshareOfUnmasked(key_transcript()),
+    )
+    .unwrap()
+}
+
+fn key_transcript() -> IDkgTranscript {
+    let key_transcript_id = IDkgTranscriptId::new(subnet_id(1), 3);
+    IDkgTranscript {
+        transcript_id: key_transcript_id,
+        receivers: IDkgReceivers::new(source_nodes()).unwrap(),
+        registry_version: RegistryVersion::from(1),
+        verified_dealings: BTreeMap::new(),
+        transcript_type: IDkgTranscriptType::Unmasked(IDkgUnmaskedTranscriptOrigin::ReshareMasked(
+            IDkgTranscriptId::new(subnet_id(1), 2),
+        )),
+        algorithm_id: AlgorithmId::ThresholdEcdsaSecp256k1,
+        internal_transcript_raw: vec![1, 2, 3],
+    }
+}
+
+fn dealings(params: &IDkgTranscriptParams, dealers: &[u64]) -> Vec<IDkgDealing> {
+    dealers
+        .iter()
+        .map(|dealer| IDkgDealing {
+            transcript_id: params.transcript_id(),
+            dealer_id: node_id(*dealer),
+            internal_dealing_raw: vec![*dealer as u8; 4],
+        })
+        .collect()
+}
+
+fn source_nodes() -> BTreeSet<NodeId> {
+    btreeset! {node_id(1), node_id(2), node_id(3), node_id(4)}
+}
+
+fn target_nodes() -> BTreeSet<NodeId> {
+    btreeset! {node_id(11), node_id(12), node_id(13), node_id(14)}
+}
+
+fn transcript_id() -> IDkgTranscriptId {
+    IDkgTranscriptId::new(subnet_id(1), 7)
+}
+
+fn node_id(id: u64) -> NodeId {
+    NodeId::from(PrincipalId::new_node_test_id(id))
+}
+
+fn subnet_id(id: u64) -> SubnetId {
+    SubnetId::from(PrincipalId::new_subnet_test_id(id))
+}
@@ -1,7 +1,9 @@
 //! Data types used for encoding/decoding the Candid payloads of ic:00.
 pub use ic_ic00_types::{
-    CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs, CanisterStatusResult,
-    CanisterStatusResultV2, CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method, Payload,
-    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
-    SetupInitialDKGArgs, SetupInitialDKGResponse, UpdateSettingsArgs, IC_00,
+    CanisterHttpDivergencesResult, CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs,
+    CanisterStatusResult, CanisterStatusResultV2, CreateCanisterArgs, EmptyBlob, InstallCodeArgs,
+    Method, Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    ReserveECDSAPreSignaturesArgs, SetControllerArgs, SetupInitialDKGArgs, SetupInitialDKGResponse,
+    SignWithECDSAArgs, SignWithECDSABatchArgs, SignWithECDSABatchReply, SignWithECDSABatchResult,
+    SignWithECDSAReply, UpdateSettingsArgs, IC_00, MAX_SIGN_WITH_ECDSA_BATCH_SIZE,
 };
@@ -9,7 +9,7 @@ use crate::{
         message_id::hash_of_map, MessageId, ReadState, SignedIngressContent, UserQuery,
         UserSignature,
     },
-    Time, UserId,
+    Height, Time, UserId,
 };
 use ic_base_types::{CanisterId, CanisterIdError, PrincipalId};
 use ic_crypto_tree_hash::{MixedHashTree, Path};
@@ -634,6 +634,16 @@ pub struct HttpStatusResponse {
     pub impl_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub replica_health_status: Option<ReplicaHealthStatus>,
+    /// The height of the latest state of the replica that is certified, i.e.
+    /// whose certification has been delivered by consensus. It lags behind
+    /// the latest executed height while the state is being certified, and
+    /// stops increasing while the replica is stuck or catching up.
+    ///
+    /// This field is not part of the public interface specification. It is
+    /// only reported by replicas that support it, so clients must treat it
+    /// as optional.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certified_height: Option<Height>,
 }
 
 #[cfg(test)]
@@ -773,6 +783,7 @@ mod test {
                 impl_version: Some("0.0".to_string()),
                 impl_hash: None,
                 replica_health_status: Some(ReplicaHealthStatus::Starting),
+                certified_height: None,
             },
             Value::Map(btreemap! {
                 text("ic_api_version") => text("foobar"),
@@ -791,6 +802,7 @@ mod test {
                 impl_version: Some("0.0".to_string()),
                 impl_hash: None,
                 replica_health_status: Some(ReplicaHealthStatus::Healthy),
+                certified_height: None,
             },
             Value::Map(btreemap! {
                 text("ic_api_version") => text("foobar"),
@@ -810,6 +822,7 @@ mod test {
                 impl_version: Some("0.0".to_string()),
                 impl_hash: None,
                 replica_health_status: None,
+                certified_height: None,
             },
             Value::Map(btreemap! {
                 text("ic_api_version") => text("foobar"),
@@ -819,6 +832,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn encoding_status_with_certified_height() {
+        assert_cbor_ser_equal(
+            &HttpStatusResponse {
+                ic_api_version: "foobar".to_string(),
+                root_key: None,
+                impl_version: Some("0.0".to_string()),
+                impl_hash: None,
+                replica_health_status: Some(ReplicaHealthStatus::Healthy),
+                certified_height: Some(Height::from(42)),
+            },
+            Value::Map(btreemap! {
+                text("ic_api_version") => text("foobar"),
+                text("impl_version") => text("0.0"),
+                text("replica_health_status") => text("healthy"),
+                text("certified_height") => int(42),
+            }),
+        );
+    }
+
     #[test]
     fn encoding_delegation() {
         assert_cbor_ser_equal(
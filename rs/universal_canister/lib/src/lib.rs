@@ -47,6 +47,7 @@ enum Ops {
     CallDataAppend = 33,
     CallCyclesAdd = 34,
     CallPerform = 35,
+    DataCertificate = 38,
     SetHeartbeat = 40,
     AcceptMessage = 41,
     SetInspectMessage = 42,
@@ -293,6 +294,13 @@ impl PayloadBuilder {
         self
     }
 
+    /// Pushes the certificate of the certified data of the canister, which
+    /// is only available in non-replicated queries.
+    pub fn data_certificate(mut self) -> Self {
+        self.0.push(Ops::DataCertificate as u8);
+        self
+    }
+
     pub fn reject_message(mut self) -> Self {
         self.0.push(Ops::RejectMessage as u8);
         self